@@ -13,6 +13,10 @@ use core::{
 };
 use num_traits::Signed;
 
+mod polygon;
+
+pub use polygon::{Circle, ConvexPolygon};
+
 #[doc(hidden)]
 /// Used internally by the [num!] macro which should be used instead.
 pub use agb_macros::num as num_inner;
@@ -24,6 +28,8 @@ pub use agb_macros::num as num_inner;
 /// let n: Num<i32, 8> = num!(0.75);
 /// assert_eq!(n, Num::new(3) / 4, "0.75 == 3/4");
 /// ```
+///
+/// Integer literals are also accepted, so `num!(5)` is the same as `num!(5.)`.
 #[macro_export]
 macro_rules! num {
     ($value:literal) => {{
@@ -31,6 +37,19 @@ macro_rules! num {
     }};
 }
 
+/// The same as [num!] but usable in const contexts. This only works for [`Num<i32, N>`].
+/// ```
+/// # use agb_fixnum::*;
+/// const THREE_QUARTERS: Num<i32, 8> = const_num!(0.75);
+/// assert_eq!(THREE_QUARTERS, num!(0.75));
+/// ```
+#[macro_export]
+macro_rules! const_num {
+    ($value:literal) => {{
+        $crate::Num::const_from_parts($crate::num_inner!($value))
+    }};
+}
+
 /// A trait for everything required to use as the internal representation of the
 /// fixed point number.
 pub trait Number: Copy + PartialOrd + Ord + num_traits::Num {}
@@ -120,6 +139,11 @@ fixed_width_unsigned_integer_impl!(u16, u32);
 fixed_width_unsigned_integer_impl!(i32, optimised_64_bit);
 fixed_width_unsigned_integer_impl!(u32, optimised_64_bit);
 
+// The 64 bit types have no hardware support on the ARM7TDMI, so everything here gets turned
+// into calls to compiler intrinsics. They exist for when range matters more than speed.
+fixed_width_unsigned_integer_impl!(i64, i128);
+fixed_width_unsigned_integer_impl!(u64, u128);
+
 /// A fixed point number represented using `I` with `N` bits of fractional precision
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -360,7 +384,7 @@ impl<I: FixedWidthUnsignedInteger, const N: usize> Num<I, N> {
     }
 
     /// The internal representation of the fixed point number
-    pub fn to_raw(self) -> I {
+    pub const fn to_raw(self) -> I {
         self.0
     }
 
@@ -427,6 +451,40 @@ impl<I: FixedWidthUnsignedInteger, const N: usize> Num<I, N> {
         self.0 >> N
     }
 
+    /// Performs rounding towards positive infinity
+    /// ```rust
+    /// # use agb_fixnum::*;
+    /// let n: Num<i32, 8> = num!(5.67);
+    /// assert_eq!(n.ceil(), 6);
+    /// let n: Num<i32, 8> = num!(-5.67);
+    /// assert_eq!(n.ceil(), -5);
+    /// let n: Num<i32, 8> = num!(5.);
+    /// assert_eq!(n.ceil(), 5);
+    /// ```
+    pub fn ceil(self) -> I {
+        (self.0 + (I::one() << N) - I::one()) >> N
+    }
+
+    /// Rounds to the nearest integer, with halfway cases rounded towards positive infinity
+    /// ```rust
+    /// # use agb_fixnum::*;
+    /// let n: Num<i32, 8> = num!(5.67);
+    /// assert_eq!(n.round(), 6);
+    /// let n: Num<i32, 8> = num!(-5.67);
+    /// assert_eq!(n.round(), -6);
+    /// let n: Num<i32, 8> = num!(2.5);
+    /// assert_eq!(n.round(), 3);
+    /// let n: Num<i32, 8> = num!(-2.5);
+    /// assert_eq!(n.round(), -2);
+    /// ```
+    pub fn round(self) -> I {
+        if N == 0 {
+            return self.0;
+        }
+
+        (self.0 + (I::one() << (N - 1))) >> N
+    }
+
     /// Returns the fractional component of a number as it's integer representation
     /// ```
     /// # use agb_fixnum::*;
@@ -490,6 +548,189 @@ impl<const N: usize> Num<i32, N> {
     }
 }
 
+/// Const versions of the common operations on [`FixedNum`].
+///
+/// Trait methods can't currently be called in const contexts, so these exist to allow
+/// building lookup tables at compile time. They give identical results to their non-const
+/// counterparts, so a table generated with [`const_sin`](Num::const_sin) will match calling
+/// [`sin`](Num::sin) at runtime.
+///
+/// ```
+/// # use agb_fixnum::*;
+/// const fn generate_circle_table() -> [Vector2D<Num<i32, 8>>; 64] {
+///     let mut table = [Vector2D::new(Num::from_raw(0), Num::from_raw(0)); 64];
+///
+///     let mut i = 0;
+///     while i < 64 {
+///         table[i] = Vector2D::const_new_from_angle(Num::const_new(i as i32).const_div_int(64));
+///         i += 1;
+///     }
+///
+///     table
+/// }
+///
+/// static AIM_TABLE: [Vector2D<Num<i32, 8>>; 64] = generate_circle_table();
+///
+/// assert_eq!(AIM_TABLE[16], Vector2D::new_from_angle(num!(0.25)));
+/// ```
+impl<const N: usize> Num<i32, N> {
+    /// Creates an integer represented by a fixed point number, see [Num::new].
+    #[must_use]
+    pub const fn const_new(integral: i32) -> Self {
+        Self(integral << N)
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    /// Called by the [const_num!] macro in order to create a fixed point number
+    pub const fn const_from_parts(num: (i32, i32)) -> Self {
+        Self((num.0 << N) + (num.1 >> (30 - N)))
+    }
+
+    /// Adds two numbers in a const context.
+    #[must_use]
+    pub const fn const_add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+
+    /// Subtracts two numbers in a const context.
+    #[must_use]
+    pub const fn const_sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+
+    /// Negates a number in a const context.
+    #[must_use]
+    pub const fn const_neg(self) -> Self {
+        Self(-self.0)
+    }
+
+    /// Multiplies two numbers in a const context.
+    #[must_use]
+    pub const fn const_mul(self, other: Self) -> Self {
+        Self(((self.0 as i64 * other.0 as i64) >> N) as i32)
+    }
+
+    /// Divides two numbers in a const context.
+    #[must_use]
+    pub const fn const_div(self, other: Self) -> Self {
+        Self((self.0 << N) / other.0)
+    }
+
+    /// Divides by an integer in a const context.
+    #[must_use]
+    pub const fn const_div_int(self, other: i32) -> Self {
+        Self(self.0 / other)
+    }
+
+    /// Rounds towards negative infinity in a const context, see [Num::floor].
+    #[must_use]
+    pub const fn const_floor(self) -> i32 {
+        self.0 >> N
+    }
+
+    /// Calculates the absolute value in a const context.
+    #[must_use]
+    pub const fn const_abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// Calculates the cosine in a const context, see [Num::cos].
+    /// ```
+    /// # use agb_fixnum::*;
+    /// const COS: Num<i32, 8> = const_num!(0.5).const_cos();
+    /// assert_eq!(COS, num!(-1.));
+    /// ```
+    #[must_use]
+    pub const fn const_cos(self) -> Self {
+        let quarter: Self = const_num!(0.25);
+
+        let mut x = self;
+        x = x.const_sub(quarter.const_add(Self::const_new(x.const_add(quarter).const_floor())));
+        x = x.const_mul(
+            x.const_abs()
+                .const_sub(const_num!(0.5))
+                .const_mul(const_num!(16.)),
+        );
+        x.const_add(
+            x.const_mul(x.const_abs().const_sub(const_num!(1.)))
+                .const_mul(const_num!(0.225)),
+        )
+    }
+
+    /// Calculates the sine in a const context, see [Num::sin].
+    #[must_use]
+    pub const fn const_sin(self) -> Self {
+        self.const_sub(Self::const_new(1).const_div_int(4))
+            .const_cos()
+    }
+}
+
+impl<const N: usize> Vector2D<Num<i32, N>> {
+    /// Creates a unit vector from an angle in a const context, see [Vector2D::new_from_angle].
+    #[must_use]
+    pub const fn const_new_from_angle(angle: Num<i32, N>) -> Self {
+        Vector2D {
+            x: angle.const_cos(),
+            y: angle.const_sin(),
+        }
+    }
+}
+
+macro_rules! widening_impl {
+    ($T: ty, $Wide: ty) => {
+        impl<const N: usize> Num<$T, N> {
+            #[must_use]
+            /// Multiplies two numbers returning the result in a 64 bit fixed point number, so
+            /// this can never overflow.
+            ///
+            /// On the ARM7TDMI this is a single long multiply instruction if compiled as ARM
+            /// code, and a call to a short compiler intrinsic in thumb code. Either way it costs
+            /// a few more cycles than a regular multiply, but much less than a 64 bit division.
+            /// ```
+            /// # use agb_fixnum::*;
+            #[doc = concat!("let a: Num<", stringify!($T), ", 8> = Num::new(40_000);")]
+            #[doc = concat!("let b: Num<", stringify!($T), ", 8> = Num::new(40_000);")]
+            #[doc = concat!("assert_eq!(a.mul_widening(b), Num::<", stringify!($Wide), ", 8>::new(1_600_000_000));")]
+            /// ```
+            pub fn mul_widening(self, other: Self) -> Num<$Wide, N> {
+                Num((<$Wide>::from(self.0) * <$Wide>::from(other.0)) >> N)
+            }
+
+            #[must_use]
+            /// Calculates `self * b / c` using a 64 bit intermediate, so the multiplication
+            /// cannot overflow and no precision is lost before the division.
+            ///
+            /// This is useful for scaling a value by a ratio, such as moving along a slope or
+            /// converting between coordinate systems.
+            ///
+            /// This is considerably slower than the 32 bit operations since the ARM7TDMI has
+            /// no division instruction at all and 64 bit division is done in software. Expect
+            /// it to take a few hundred cycles, so avoid calling it many times per frame.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `c` is zero, or if the result doesn't fit in the original type.
+            /// ```
+            /// # use agb_fixnum::*;
+            #[doc = concat!("let a: Num<", stringify!($T), ", 8> = Num::new(50_000);")]
+            #[doc = concat!("let b: Num<", stringify!($T), ", 8> = Num::new(30_000);")]
+            #[doc = concat!("let c: Num<", stringify!($T), ", 8> = Num::new(60_000);")]
+            /// assert_eq!(a.mul_div(b, c), Num::new(25_000));
+            /// ```
+            pub fn mul_div(self, b: Self, c: Self) -> Self {
+                let result = <$Wide>::from(self.0) * <$Wide>::from(b.0) / <$Wide>::from(c.0);
+                Num(result
+                    .try_into()
+                    .expect("result of mul_div does not fit in the target type"))
+            }
+        }
+    };
+}
+
+widening_impl!(i32, i64);
+widening_impl!(u32, u64);
+
 impl<I: FixedWidthSignedInteger, const N: usize> Num<I, N> {
     #[must_use]
     /// Returns the absolute value of a fixed point number
@@ -788,6 +1029,36 @@ impl<I: FixedWidthUnsignedInteger, const N: usize> Vector2D<Num<I, N>> {
         }
     }
 
+    #[must_use]
+    /// Rounds the x and y coordinate towards positive infinity, see [Num::ceil]
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let v1: Vector2D<Num<i32, 8>> = Vector2D::new(num!(1.56), num!(-2.2));
+    /// let v2: Vector2D<i32> = (2, -2).into();
+    /// assert_eq!(v1.ceil(), v2);
+    /// ```
+    pub fn ceil(self) -> Vector2D<I> {
+        Vector2D {
+            x: self.x.ceil(),
+            y: self.y.ceil(),
+        }
+    }
+
+    #[must_use]
+    /// Rounds the x and y coordinate to the nearest integer, see [Num::round]
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let v1: Vector2D<Num<i32, 8>> = Vector2D::new(num!(1.56), num!(-2.2));
+    /// let v2: Vector2D<i32> = (2, -2).into();
+    /// assert_eq!(v1.round(), v2);
+    /// ```
+    pub fn round(self) -> Vector2D<I> {
+        Vector2D {
+            x: self.x.round(),
+            y: self.y.round(),
+        }
+    }
+
     #[must_use]
     /// Attempts to change the base returning None if the numbers cannot be represented
     pub fn try_change_base<J: FixedWidthUnsignedInteger + TryFrom<I>, const M: usize>(
@@ -869,6 +1140,25 @@ impl<T: Number> Vector2D<T> {
     pub fn change_base<U: Number + From<T>>(self) -> Vector2D<U> {
         (self.x, self.y).into()
     }
+
+    /// Attempts to convert the representation of the vector to another type, returning
+    /// [None] if either coordinate doesn't fit
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let v1: Vector2D<i32> = Vector2D::new(1, 2);
+    /// let v2: Option<Vector2D<u16>> = v1.try_cast();
+    /// assert_eq!(v2, Some(Vector2D::new(1, 2)));
+    ///
+    /// let v1: Vector2D<i32> = Vector2D::new(-1, 2);
+    /// let v2: Option<Vector2D<u16>> = v1.try_cast();
+    /// assert_eq!(v2, None);
+    /// ```
+    pub fn try_cast<U: Number + TryFrom<T>>(self) -> Option<Vector2D<U>> {
+        Some(Vector2D::new(
+            self.x.try_into().ok()?,
+            self.y.try_into().ok()?,
+        ))
+    }
 }
 
 impl<I: FixedWidthSignedInteger, const N: usize> Vector2D<Num<I, N>> {
@@ -1018,6 +1308,79 @@ impl<T: Number> Rect<T> {
     }
 }
 
+impl<T: Number> Rect<T> {
+    /// Converts the representation of the rectangle to another type
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let r1: Rect<i16> = Rect::new(Vector2D::new(1, 2), Vector2D::new(3, 4));
+    /// let r2: Rect<i32> = r1.change_base();
+    /// ```
+    pub fn change_base<U: Number + From<T>>(self) -> Rect<U> {
+        Rect::new(self.position.change_base(), self.size.change_base())
+    }
+
+    /// Attempts to convert the representation of the rectangle to another type, returning
+    /// [None] if any component doesn't fit
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let r1: Rect<i32> = Rect::new(Vector2D::new(1, 2), Vector2D::new(3, 4));
+    /// let r2: Option<Rect<u16>> = r1.try_cast();
+    /// assert_eq!(r2, Some(Rect::new(Vector2D::new(1, 2), Vector2D::new(3, 4))));
+    ///
+    /// let r1: Rect<i32> = Rect::new(Vector2D::new(-1, 2), Vector2D::new(3, 4));
+    /// let r2: Option<Rect<u16>> = r1.try_cast();
+    /// assert_eq!(r2, None);
+    /// ```
+    pub fn try_cast<U: Number + TryFrom<T>>(self) -> Option<Rect<U>> {
+        Some(Rect::new(self.position.try_cast()?, self.size.try_cast()?))
+    }
+}
+
+impl<I: FixedWidthUnsignedInteger, const N: usize> Rect<Num<I, N>> {
+    #[must_use]
+    /// Truncates the position and size, see [Num::trunc]
+    pub fn trunc(self) -> Rect<I> {
+        Rect::new(self.position.trunc(), self.size.trunc())
+    }
+
+    #[must_use]
+    /// Floors the position and size, see [Num::floor]
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let r: Rect<Num<i32, 8>> = Rect::new(
+    ///     Vector2D::new(num!(-0.5), num!(1.5)),
+    ///     Vector2D::new(num!(2.5), num!(3.)),
+    /// );
+    /// assert_eq!(r.floor(), Rect::new(Vector2D::new(-1, 1), Vector2D::new(2, 3)));
+    /// ```
+    pub fn floor(self) -> Rect<I> {
+        Rect::new(self.position.floor(), self.size.floor())
+    }
+
+    #[must_use]
+    /// Rounds the position and size towards positive infinity, see [Num::ceil]
+    pub fn ceil(self) -> Rect<I> {
+        Rect::new(self.position.ceil(), self.size.ceil())
+    }
+
+    #[must_use]
+    /// Rounds the position and size to the nearest integer, see [Num::round]
+    pub fn round(self) -> Rect<I> {
+        Rect::new(self.position.round(), self.size.round())
+    }
+
+    #[must_use]
+    /// Attempts to change the base returning None if the numbers cannot be represented
+    pub fn try_change_base<J: FixedWidthUnsignedInteger + TryFrom<I>, const M: usize>(
+        self,
+    ) -> Option<Rect<Num<J, M>>> {
+        Some(Rect::new(
+            self.position.try_change_base()?,
+            self.size.try_change_base()?,
+        ))
+    }
+}
+
 impl<T: FixedWidthUnsignedInteger> Rect<T> {
     /// Iterate over the points in a rectangle in row major order.
     /// ```
@@ -1285,6 +1648,9 @@ mod tests {
             test_positive::<i32, B>();
             test_positive::<u32, B>();
             test_negative::<i32, B>();
+            test_positive::<i64, B>();
+            test_positive::<u64, B>();
+            test_negative::<i64, B>();
 
             if B < 16 {
                 test_positive::<u16, B>();
@@ -1324,6 +1690,74 @@ mod tests {
         )
     }
 
+    #[test]
+    fn const_operations_match_runtime_ones() {
+        fn check<const N: usize>() {
+            for i in -600..600 {
+                let a: Num<i32, N> = Num::from_raw(i * 7);
+                let b: Num<i32, N> = Num::from_raw(i * 3 + 1);
+
+                assert_eq!(a.const_add(b), a + b);
+                assert_eq!(a.const_sub(b), a - b);
+                assert_eq!(a.const_mul(b), a * b);
+                assert_eq!(a.const_div(b), a / b);
+                assert_eq!(a.const_floor(), a.floor());
+                assert_eq!(a.const_cos(), a.cos());
+                assert_eq!(a.const_sin(), a.sin());
+            }
+        }
+
+        check::<8>();
+        check::<12>();
+        check::<16>();
+    }
+
+    #[test]
+    fn num_accepts_integer_literals() {
+        let a: Num<i32, 8> = num!(5);
+        let b: Num<i32, 8> = num!(-3);
+
+        assert_eq!(a, Num::new(5));
+        assert_eq!(b, Num::new(-3));
+        assert_eq!(const_num!(5), a);
+    }
+
+    #[test]
+    fn i64_holds_values_beyond_i32() {
+        let a: Num<i64, 12> = Num::new(1 << 20);
+        let b: Num<i64, 12> = num!(0.5);
+
+        assert_eq!(a * 4096, Num::new(1 << 32));
+        assert_eq!(a * b, Num::new(1 << 19));
+        assert_eq!((a * 4096).floor(), 1 << 32);
+    }
+
+    #[test]
+    fn mul_widening_matches_i64_multiplication() {
+        for (a, b) in [(100_000, 100_000), (-70_000, 90_000), (-1, -1)] {
+            let x: Num<i32, 8> = Num::new(a);
+            let y: Num<i32, 8> = Num::new(b);
+
+            assert_eq!(x.mul_widening(y), Num::<i64, 8>::new(a as i64 * b as i64));
+        }
+
+        let x: Num<i32, 8> = num!(-1.5);
+        assert_eq!(x.mul_widening(x), num!(2.25));
+    }
+
+    #[test]
+    fn mul_div_does_not_overflow_in_the_intermediate() {
+        let a: Num<i32, 12> = Num::new(100_000);
+        let b: Num<i32, 12> = Num::new(3);
+        let c: Num<i32, 12> = Num::new(4);
+
+        assert_eq!(a.mul_div(b, c), Num::new(75_000));
+        assert_eq!((-a).mul_div(b, c), Num::new(-75_000));
+
+        let d: Num<u32, 8> = Num::new(1_000_000);
+        assert_eq!(d.mul_div(Num::new(7), Num::new(7)), d);
+    }
+
     #[test]
     fn test_numbers() {
         // test addition
@@ -1484,6 +1918,68 @@ mod tests {
         assert_eq!(v1 + v1, (v2 + v2).into());
     }
 
+    #[test]
+    fn rounding_negative_numbers() {
+        let cases: [(Num<i32, 8>, i32, i32, i32, i32); 7] = [
+            (num!(-0.5), -1, 0, 0, 0),
+            (num!(-1.5), -2, -1, -1, -1),
+            (num!(-1.25), -2, -1, -1, -1),
+            (num!(-1.75), -2, -1, -2, -1),
+            (num!(-2.), -2, -2, -2, -2),
+            (num!(0.5), 0, 1, 1, 0),
+            (num!(1.25), 1, 2, 1, 1),
+        ];
+
+        for (n, floor, ceil, round, trunc) in cases {
+            assert_eq!(n.floor(), floor, "floor of {n}");
+            assert_eq!(n.ceil(), ceil, "ceil of {n}");
+            assert_eq!(n.round(), round, "round of {n}");
+            assert_eq!(n.trunc(), trunc, "trunc of {n}");
+        }
+    }
+
+    #[test]
+    fn rounding_vectors_and_rects() {
+        let v: Vector2D<Num<i32, 8>> = Vector2D::new(num!(-0.5), num!(0.5));
+
+        assert_eq!(v.floor(), Vector2D::new(-1, 0));
+        assert_eq!(v.ceil(), Vector2D::new(0, 1));
+        assert_eq!(v.round(), Vector2D::new(0, 1));
+        assert_eq!(v.trunc(), Vector2D::new(0, 0));
+
+        let r = Rect::new(v, Vector2D::new(num!(1.5), num!(-1.5)));
+
+        assert_eq!(
+            r.floor(),
+            Rect::new(Vector2D::new(-1, 0), Vector2D::new(1, -2))
+        );
+        assert_eq!(
+            r.ceil(),
+            Rect::new(Vector2D::new(0, 1), Vector2D::new(2, -1))
+        );
+        assert_eq!(
+            r.round(),
+            Rect::new(Vector2D::new(0, 1), Vector2D::new(2, -1))
+        );
+    }
+
+    #[test]
+    fn casting_vectors_and_rects() {
+        let v: Vector2D<i32> = Vector2D::new(300, 2);
+
+        assert_eq!(v.try_cast::<u16>(), Some(Vector2D::new(300, 2)));
+        assert_eq!(v.try_cast::<u8>(), None);
+        assert_eq!((-v).try_cast::<u16>(), None);
+        assert_eq!((-v).try_cast::<i16>(), Some(Vector2D::new(-300, -2)));
+
+        let r: Rect<Num<i32, 8>> = Rect::new((1, 2).into(), (3, 4).into());
+        let small: Option<Rect<Num<i16, 4>>> = r.try_change_base();
+        assert_eq!(small, Some(Rect::new((1, 2).into(), (3, 4).into())));
+
+        let big: Rect<Num<i32, 8>> = Rect::new((1000, 2).into(), (3, 4).into());
+        assert_eq!(big.try_change_base::<u8, 4>(), None);
+    }
+
     #[test]
     fn test_rect_iter() {
         let rect: Rect<i32> = Rect::new((5_i32, 5_i32).into(), (3_i32, 3_i32).into());
@@ -0,0 +1,450 @@
+//! Convex polygons and circles for collision detection where axis aligned [`Rect`]s aren't enough.
+
+use crate::{Num, Number, Rect, Vector2D};
+
+/// A convex polygon with at most `V` vertices, stored inline so no allocation is needed.
+///
+/// The vertices can be given in either winding order, but they must describe a convex shape.
+/// No check is done for this and the results of the overlap tests for a concave polygon are
+/// meaningless.
+///
+/// Overlap tests use the separating axis theorem. The dot products involved are calculated
+/// with 64 bit intermediates, so they won't overflow for any coordinates you'd reasonably use
+/// for a game on the GBA.
+///
+/// As with [`Rect::touches`], shapes which only touch along an edge or at a corner are not
+/// considered to be overlapping.
+///
+/// # Degenerate polygons
+///
+/// A polygon whose vertices are all collinear has no area. These are treated as the line
+/// segment (or single point) that they describe, which means:
+///
+/// * A segment or point overlaps a polygon with area if any part of it is strictly inside
+///   that polygon.
+/// * Two segments overlap only if they cross, and collinear segments never overlap.
+/// * A point never overlaps another point or a segment.
+///
+/// Repeated vertices are ignored.
+/// ```
+/// # use agb_fixnum::*;
+/// let sword: ConvexPolygon<Num<i32, 8>, 4> = ConvexPolygon::new(&[
+///     (num!(0.), num!(4.)).into(),
+///     (num!(4.), num!(0.)).into(),
+///     (num!(20.), num!(16.)).into(),
+///     (num!(16.), num!(20.)).into(),
+/// ])
+/// .unwrap();
+///
+/// let enemy = Rect::new((num!(14.), num!(14.)).into(), (num!(8.), num!(8.)).into());
+/// let wall = Rect::new((num!(16.), num!(0.)).into(), (num!(8.), num!(8.)).into());
+///
+/// assert!(sword.overlaps_rect(enemy));
+/// assert!(!sword.overlaps_rect(wall));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConvexPolygon<T: Number, const V: usize> {
+    vertices: [Vector2D<T>; V],
+    len: usize,
+}
+
+impl<T: Number, const V: usize> ConvexPolygon<T, V> {
+    #[must_use]
+    /// Creates a polygon from the given vertices, returning [None] if there are no vertices or
+    /// there are more than `V` of them.
+    pub fn new(vertices: &[Vector2D<T>]) -> Option<Self> {
+        if vertices.is_empty() || vertices.len() > V {
+            return None;
+        }
+
+        let zero = Vector2D::new(T::zero(), T::zero());
+        let mut polygon = Self {
+            vertices: [zero; V],
+            len: vertices.len(),
+        };
+        polygon.vertices[..vertices.len()].copy_from_slice(vertices);
+
+        Some(polygon)
+    }
+
+    #[must_use]
+    /// The vertices of the polygon
+    pub fn vertices(&self) -> &[Vector2D<T>] {
+        &self.vertices[..self.len]
+    }
+
+    #[must_use]
+    /// Moves every vertex by `offset`
+    pub fn translate(mut self, offset: Vector2D<T>) -> Self {
+        for vertex in &mut self.vertices[..self.len] {
+            *vertex += offset;
+        }
+
+        self
+    }
+
+    fn bounding_box(&self) -> (Vector2D<T>, Vector2D<T>) {
+        let mut min = self.vertices[0];
+        let mut max = self.vertices[0];
+
+        for vertex in &self.vertices[1..self.len] {
+            min.x = min.x.min(vertex.x);
+            min.y = min.y.min(vertex.y);
+            max.x = max.x.max(vertex.x);
+            max.y = max.y.max(vertex.y);
+        }
+
+        (min, max)
+    }
+
+    fn edges(&self) -> impl Iterator<Item = Vector2D<T>> + '_ {
+        let zero = Vector2D::new(T::zero(), T::zero());
+
+        (0..self.len)
+            .map(|i| self.vertices[(i + 1) % self.len] - self.vertices[i])
+            .filter(move |&edge| edge != zero)
+    }
+}
+
+impl<T: Number> ConvexPolygon<T, 4> {
+    #[must_use]
+    /// Creates a polygon with the same corners as `rect`
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let polygon = ConvexPolygon::from_rect(Rect::new(Vector2D::new(1, 2), Vector2D::new(3, 4)));
+    /// assert_eq!(
+    ///     polygon.vertices(),
+    ///     &[(1, 2).into(), (4, 2).into(), (4, 6).into(), (1, 6).into()]
+    /// );
+    /// ```
+    pub fn from_rect(rect: Rect<T>) -> Self {
+        let Rect { position, size } = rect;
+
+        Self {
+            vertices: [
+                position,
+                Vector2D::new(position.x + size.x, position.y),
+                position + size,
+                Vector2D::new(position.x, position.y + size.y),
+            ],
+            len: 4,
+        }
+    }
+}
+
+fn dot_wide<const N: usize>(a: Vector2D<Num<i32, N>>, b: Vector2D<Num<i32, N>>) -> i64 {
+    i64::from(a.x.to_raw()) * i64::from(b.x.to_raw())
+        + i64::from(a.y.to_raw()) * i64::from(b.y.to_raw())
+}
+
+fn cross_wide<const N: usize>(a: Vector2D<Num<i32, N>>, b: Vector2D<Num<i32, N>>) -> i64 {
+    i64::from(a.x.to_raw()) * i64::from(b.y.to_raw())
+        - i64::from(a.y.to_raw()) * i64::from(b.x.to_raw())
+}
+
+fn perpendicular<const N: usize>(v: Vector2D<Num<i32, N>>) -> Vector2D<Num<i32, N>> {
+    Vector2D::new(-v.y, v.x)
+}
+
+fn bounding_boxes_touch<T: Number>(
+    a: (Vector2D<T>, Vector2D<T>),
+    b: (Vector2D<T>, Vector2D<T>),
+) -> bool {
+    a.0.x < b.1.x && b.0.x < a.1.x && a.0.y < b.1.y && b.0.y < a.1.y
+}
+
+impl<const N: usize, const V: usize> ConvexPolygon<Num<i32, N>, V> {
+    #[must_use]
+    /// Whether the polygon has zero area because all its vertices are collinear
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let line: ConvexPolygon<Num<i32, 8>, 3> =
+    ///     ConvexPolygon::new(&[(0, 0).into(), (1, 1).into(), (3, 3).into()]).unwrap();
+    /// assert!(line.is_degenerate());
+    /// ```
+    pub fn is_degenerate(&self) -> bool {
+        let origin = self.vertices[0];
+
+        (1..self.len.saturating_sub(1))
+            .all(|i| cross_wide(self.vertices[i] - origin, self.vertices[i + 1] - origin) == 0)
+    }
+
+    fn project(&self, axis: Vector2D<Num<i32, N>>) -> (i64, i64) {
+        let mut min = i64::MAX;
+        let mut max = i64::MIN;
+
+        for &vertex in self.vertices() {
+            let projection = dot_wide(vertex, axis);
+            min = min.min(projection);
+            max = max.max(projection);
+        }
+
+        (min, max)
+    }
+
+    fn separated_along<const W: usize>(
+        &self,
+        other: &ConvexPolygon<Num<i32, N>, W>,
+        axis: Vector2D<Num<i32, N>>,
+    ) -> bool {
+        let (min, max) = self.project(axis);
+        let (other_min, other_max) = other.project(axis);
+
+        max <= other_min || other_max <= min
+    }
+
+    fn has_separating_axis<const W: usize>(&self, other: &ConvexPolygon<Num<i32, N>, W>) -> bool {
+        let degenerate = self.is_degenerate();
+
+        self.edges().any(|edge| {
+            self.separated_along(other, perpendicular(edge))
+                || (degenerate && self.separated_along(other, edge))
+        })
+    }
+
+    #[must_use]
+    /// Returns true if the two polygons overlap.
+    ///
+    /// This first checks the bounding boxes of the polygons, so tests between polygons which are
+    /// far apart are cheap.
+    pub fn overlaps<const W: usize>(&self, other: &ConvexPolygon<Num<i32, N>, W>) -> bool {
+        if !bounding_boxes_touch(self.bounding_box(), other.bounding_box()) {
+            // a zero area shape can still cross a polygon while its bounding box has no width
+            // or height, so only bail out early if both have some area
+            if !self.is_degenerate() && !other.is_degenerate() {
+                return false;
+            }
+        }
+
+        if self.edges().next().is_none() && other.edges().next().is_none() {
+            return false;
+        }
+
+        !self.has_separating_axis(other) && !other.has_separating_axis(self)
+    }
+
+    #[must_use]
+    /// Returns true if the polygon overlaps the rectangle
+    pub fn overlaps_rect(&self, rect: Rect<Num<i32, N>>) -> bool {
+        self.overlaps(&ConvexPolygon::from_rect(rect))
+    }
+
+    #[must_use]
+    /// Returns true if the polygon overlaps the circle.
+    ///
+    /// This needs 128 bit arithmetic to compare distances without a square root, so is
+    /// noticeably slower than testing against another polygon.
+    pub fn overlaps_circle(&self, circle: &Circle<Num<i32, N>>) -> bool {
+        let closest_vertex = self
+            .vertices()
+            .iter()
+            .copied()
+            .min_by_key(|&vertex| {
+                let offset = circle.centre - vertex;
+                dot_wide(offset, offset)
+            })
+            .expect("polygons always have at least one vertex");
+
+        let radius = i128::from(circle.radius.to_raw());
+        let radius_squared = radius * radius;
+
+        let separated_along = |axis: Vector2D<Num<i32, N>>| {
+            let (min, max) = self.project(axis);
+            let centre = dot_wide(circle.centre, axis);
+
+            let distance = i128::from(if centre > max {
+                centre - max
+            } else if centre < min {
+                min - centre
+            } else {
+                return false;
+            });
+
+            distance * distance >= radius_squared * i128::from(dot_wide(axis, axis))
+        };
+
+        let zero = Vector2D::new(Num::new(0), Num::new(0));
+        let to_centre = circle.centre - closest_vertex;
+
+        !((to_centre != zero && separated_along(to_centre))
+            || self
+                .edges()
+                .any(|edge| separated_along(perpendicular(edge))))
+    }
+}
+
+/// A circle given by its centre and radius
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Circle<T: Number> {
+    /// The centre of the circle
+    pub centre: Vector2D<T>,
+    /// The radius of the circle
+    pub radius: T,
+}
+
+impl<T: Number> Circle<T> {
+    #[must_use]
+    /// Creates a circle from its centre and radius
+    pub fn new(centre: Vector2D<T>, radius: T) -> Self {
+        Self { centre, radius }
+    }
+}
+
+impl<const N: usize> Circle<Num<i32, N>> {
+    #[must_use]
+    /// Returns true if the two circles overlap
+    /// ```
+    /// # use agb_fixnum::*;
+    /// let a: Circle<Num<i32, 8>> = Circle::new((0, 0).into(), num!(2.));
+    /// let b = Circle::new((3, 0).into(), num!(1.5));
+    /// let c = Circle::new((3, 0).into(), num!(1.));
+    ///
+    /// assert!(a.overlaps(&b));
+    /// assert!(!a.overlaps(&c));
+    /// ```
+    pub fn overlaps(&self, other: &Circle<Num<i32, N>>) -> bool {
+        let offset = self.centre - other.centre;
+        let radii = i64::from((self.radius + other.radius).to_raw());
+
+        dot_wide(offset, offset) < radii * radii
+    }
+
+    #[must_use]
+    /// Returns true if the circle overlaps the rectangle
+    pub fn overlaps_rect(&self, rect: Rect<Num<i32, N>>) -> bool {
+        ConvexPolygon::from_rect(rect).overlaps_circle(self)
+    }
+
+    #[must_use]
+    /// Returns true if the circle overlaps the polygon. See [`ConvexPolygon::overlaps_circle`].
+    pub fn overlaps_polygon<const V: usize>(
+        &self,
+        polygon: &ConvexPolygon<Num<i32, N>, V>,
+    ) -> bool {
+        polygon.overlaps_circle(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num;
+
+    type Number = Num<i32, 8>;
+
+    fn polygon<const V: usize>(vertices: &[(i32, i32)]) -> ConvexPolygon<Number, V> {
+        let mut points = [Vector2D::new(Number::new(0), Number::new(0)); V];
+        for (point, &(x, y)) in points.iter_mut().zip(vertices) {
+            *point = (x, y).into();
+        }
+
+        ConvexPolygon::new(&points[..vertices.len()]).unwrap()
+    }
+
+    fn rect(position: (i32, i32), size: (i32, i32)) -> Rect<Number> {
+        Rect::new(position.into(), size.into())
+    }
+
+    #[test]
+    fn new_rejects_empty_and_too_many_vertices() {
+        assert!(ConvexPolygon::<Number, 3>::new(&[]).is_none());
+        assert!(ConvexPolygon::<Number, 2>::new(&[(0, 0).into(); 3]).is_none());
+        assert_eq!(
+            ConvexPolygon::<Number, 4>::new(&[(0, 0).into(); 3])
+                .unwrap()
+                .vertices()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn rotated_squares() {
+        let diamond = polygon::<4>(&[(0, -2), (2, 0), (0, 2), (-2, 0)]);
+
+        // the bounding boxes overlap but the corner of the square misses the diamond
+        let square = polygon::<4>(&[(1, 1), (3, 1), (3, 3), (1, 3)]);
+        assert!(!diamond.overlaps(&square));
+        assert!(!square.overlaps(&diamond));
+
+        let square = square.translate((num!(-0.5), num!(-0.5)).into());
+        assert!(diamond.overlaps(&square));
+        assert!(square.overlaps(&diamond));
+    }
+
+    #[test]
+    fn winding_order_does_not_matter() {
+        let clockwise = polygon::<3>(&[(0, 0), (4, 0), (0, 4)]);
+        let anticlockwise = polygon::<3>(&[(0, 0), (0, 4), (4, 0)]);
+        let rect = rect((1, 1), (1, 1));
+
+        assert!(clockwise.overlaps_rect(rect));
+        assert!(anticlockwise.overlaps_rect(rect));
+    }
+
+    #[test]
+    fn touching_edges_do_not_overlap() {
+        let a = ConvexPolygon::from_rect(rect((0, 0), (2, 2)));
+        let b = ConvexPolygon::from_rect(rect((2, 0), (2, 2)));
+
+        assert!(!a.overlaps(&b));
+        assert!(a.overlaps(&a));
+    }
+
+    #[test]
+    fn large_coordinates_do_not_overflow() {
+        let a = polygon::<3>(&[(-30_000, -30_000), (30_000, -30_000), (0, 30_000)]);
+        let b = polygon::<3>(&[(29_000, 29_000), (30_000, 29_000), (30_000, 30_000)]);
+
+        assert!(!a.overlaps(&b));
+        assert!(a.overlaps(&b.translate((-29_000, -29_000).into())));
+    }
+
+    #[test]
+    fn degenerate_polygons() {
+        let square = ConvexPolygon::from_rect(rect((0, 0), (4, 4)));
+
+        let crossing = polygon::<2>(&[(-1, 2), (5, 2)]);
+        let along_edge = polygon::<2>(&[(-1, 0), (5, 0)]);
+        let outside = polygon::<2>(&[(5, -1), (5, 5)]);
+        let inside_point = polygon::<1>(&[(2, 2)]);
+        let collinear = polygon::<3>(&[(1, 1), (2, 2), (3, 3)]);
+
+        assert!(crossing.is_degenerate());
+        assert!(collinear.is_degenerate());
+        assert!(!square.is_degenerate());
+
+        assert!(crossing.overlaps(&square));
+        assert!(square.overlaps(&crossing));
+        assert!(!along_edge.overlaps(&square));
+        assert!(!outside.overlaps(&square));
+        assert!(inside_point.overlaps(&square));
+        assert!(collinear.overlaps(&square));
+
+        let vertical = polygon::<2>(&[(2, -1), (2, 5)]);
+        assert!(crossing.overlaps(&vertical));
+        assert!(!crossing.overlaps(&polygon::<2>(&[(0, 2), (10, 2)])));
+        assert!(!crossing.overlaps(&polygon::<2>(&[(6, -1), (6, 5)])));
+
+        assert!(!inside_point.overlaps(&inside_point));
+        assert!(!inside_point.overlaps(&polygon::<2>(&[(0, 2), (4, 2)])));
+    }
+
+    #[test]
+    fn polygon_against_circle() {
+        let square = ConvexPolygon::from_rect(rect((0, 0), (4, 4)));
+
+        // near the corner, within the bounding box of the circle but not the circle itself
+        let circle = Circle::new((5, 5).into(), num!(1.4));
+        assert!(!square.overlaps_circle(&circle));
+        assert!(square.overlaps_circle(&Circle::new((5, 5).into(), num!(1.5))));
+
+        assert!(square.overlaps_circle(&Circle::new((2, 2).into(), num!(0.5))));
+        assert!(square.overlaps_circle(&Circle::new((6, 2).into(), num!(2.5))));
+        assert!(!square.overlaps_circle(&Circle::new((6, 2).into(), num!(2.))));
+
+        let circle = Circle::new((6, 2).into(), num!(2.5));
+        assert!(circle.overlaps_polygon(&square));
+        assert!(circle.overlaps_rect(rect((0, 0), (4, 4))));
+    }
+}
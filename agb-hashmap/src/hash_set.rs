@@ -145,6 +145,23 @@ impl<K, ALLOCATOR: ClonableAllocator> HashSet<K, ALLOCATOR> {
     {
         self.map.retain(|k, _| f(k));
     }
+
+    /// Removes all the values from the set, returning them as an iterator. The set keeps its
+    /// capacity.
+    ///
+    /// If the iterator is dropped before it is fully consumed, the remaining values are
+    /// dropped too.
+    pub fn drain(&mut self) -> Drain<'_, K, ALLOCATOR> {
+        Drain {
+            map_drain: self.map.drain(),
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more values, so that inserting them won't
+    /// resize the set.
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
 }
 
 impl<K> Default for HashSet<K> {
@@ -323,7 +340,7 @@ where
             (other, self)
         };
 
-        larger.iter().chain(smaller.difference(self))
+        larger.iter().chain(smaller.difference(larger))
     }
 }
 
@@ -389,6 +406,27 @@ impl<'a, K, ALLOCATOR: ClonableAllocator> Iterator for Iter<'a, K, ALLOCATOR> {
 
 impl<K, ALLOCATOR: ClonableAllocator> ExactSizeIterator for Iter<'_, K, ALLOCATOR> {}
 
+/// A draining iterator over the values of a [`HashSet`].
+///
+/// This struct is created using the [`drain()`](HashSet::drain) method on [`HashSet`].
+pub struct Drain<'a, K, ALLOCATOR: ClonableAllocator> {
+    map_drain: super::Drain<'a, K, (), ALLOCATOR>,
+}
+
+impl<K, ALLOCATOR: ClonableAllocator> Iterator for Drain<'_, K, ALLOCATOR> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.map_drain.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.map_drain.size_hint()
+    }
+}
+
+impl<K, ALLOCATOR: ClonableAllocator> ExactSizeIterator for Drain<'_, K, ALLOCATOR> {}
+
 impl<K> FromIterator<K> for HashSet<K>
 where
     K: Eq + Hash,
@@ -400,14 +438,12 @@ where
     }
 }
 
-impl<K> Extend<K> for HashSet<K>
+impl<K, ALLOCATOR: ClonableAllocator> Extend<K> for HashSet<K, ALLOCATOR>
 where
     K: Eq + Hash,
 {
     fn extend<T: IntoIterator<Item = K>>(&mut self, iter: T) {
-        for k in iter {
-            self.insert(k);
-        }
+        self.map.extend(iter.into_iter().map(|k| (k, ())));
     }
 }
 
@@ -439,3 +475,122 @@ where
         HashSet::from_iter(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn can_insert_and_remove_values() {
+        let mut set = HashSet::new();
+
+        assert!(set.insert(3));
+        assert!(set.insert(5));
+        assert!(!set.insert(3));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.contains(&3));
+        assert!(!set.contains(&4));
+
+        assert!(set.remove(&3));
+        assert!(!set.remove(&3));
+        assert!(!set.contains(&3));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn can_store_lots_of_values() {
+        let mut set = HashSet::new();
+
+        for i in 0..1000 {
+            set.insert(i * 7);
+        }
+
+        assert_eq!(set.len(), 1000);
+        for i in 0..7000 {
+            assert_eq!(set.contains(&i), i % 7 == 0);
+        }
+    }
+
+    #[test]
+    fn iterates_over_every_value() {
+        let set: HashSet<_> = (0..100).collect();
+
+        let mut values: Vec<_> = set.iter().copied().collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+
+        let mut owned: Vec<_> = set.into_iter().collect();
+        owned.sort_unstable();
+        assert_eq!(owned, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut set: HashSet<_> = (0..100).collect();
+
+        set.retain(|v| v % 2 == 0);
+
+        assert_eq!(set.len(), 50);
+        assert!(set.contains(&2));
+        assert!(!set.contains(&3));
+        assert_eq!(set.iter().count(), 50);
+    }
+
+    #[test]
+    fn extend_skips_values_already_present() {
+        let mut set: HashSet<_> = [1, 2, 3].into();
+        set.extend([3, 4, 5, 5]);
+
+        assert_eq!(set, [1, 2, 3, 4, 5].into());
+    }
+
+    #[test]
+    fn drain_empties_the_set_and_keeps_capacity() {
+        let mut set: HashSet<_> = (0..100).collect();
+        let capacity = set.capacity();
+
+        let mut drained: Vec<_> = set.drain().collect();
+        drained.sort_unstable();
+
+        assert_eq!(drained, (0..100).collect::<Vec<_>>());
+        assert!(set.is_empty());
+        assert_eq!(set.capacity(), capacity);
+    }
+
+    #[test]
+    fn set_operations() {
+        let a: HashSet<_> = [1, 2, 3, 4].into();
+        let b: HashSet<_> = [3, 4, 5].into();
+
+        let sorted = |iter: &mut dyn Iterator<Item = &i32>| {
+            let mut values: Vec<_> = iter.copied().collect();
+            values.sort_unstable();
+            values
+        };
+
+        assert_eq!(sorted(&mut a.union(&b)), [1, 2, 3, 4, 5]);
+        assert_eq!(sorted(&mut a.intersection(&b)), [3, 4]);
+        assert_eq!(sorted(&mut a.difference(&b)), [1, 2]);
+        assert_eq!(sorted(&mut b.difference(&a)), [5]);
+        assert_eq!(sorted(&mut a.symmetric_difference(&b)), [1, 2, 5]);
+    }
+
+    #[test]
+    fn union_keeps_values_from_the_smaller_set() {
+        let small: HashSet<_> = [1, 2].into();
+        let large: HashSet<_> = [2, 3, 4, 5].into();
+
+        let expected: HashSet<_> = [1, 2, 3, 4, 5].into();
+
+        let union: HashSet<_> = small.union(&large).copied().collect();
+        assert_eq!(union, expected);
+        assert_eq!(small.union(&large).count(), 5);
+
+        let union: HashSet<_> = large.union(&small).copied().collect();
+        assert_eq!(union, expected);
+        assert_eq!(large.union(&small).count(), 5);
+    }
+}
@@ -0,0 +1,161 @@
+use core::hash::{BuildHasherDefault, Hasher};
+
+/// A fast [`Hasher`] for small integer keys, such as entity ids or tile coordinates packed into
+/// a `u32`.
+///
+/// Each integer written to the hasher costs a single 32-bit multiply, which the ARM7TDMI can do
+/// in a few cycles, unlike the 64-bit arithmetic many general purpose hashers use. The map
+/// spreads the bits of the result further before picking a bucket, so keys which are close
+/// together still end up in different buckets.
+///
+/// Keys which aren't integers still work, their bytes are hashed 4 at a time, but one of the
+/// more general hashers will usually do a better job with them.
+///
+/// # Denial of service resistance
+///
+/// This hasher isn't randomly seeded, so someone who can choose the keys can pick lots which
+/// collide and make the map slow. Hash maps which store untrusted input on a server need to
+/// worry about that, but a game running on a console doesn't, so it is a good trade for speed
+/// here. The default hasher used by [`HashMap`](crate::HashMap) isn't resistant to this either.
+///
+/// ```
+/// use agb_hashmap::{BuildIntegerHasher, HashMap};
+///
+/// let mut positions: HashMap<u32, (i32, i32), _, _> =
+///     HashMap::with_hasher(BuildIntegerHasher::default());
+/// positions.insert(3, (10, 20));
+///
+/// assert_eq!(positions.get(&3), Some(&(10, 20)));
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct IntegerHasher {
+    hash: u32,
+}
+
+/// Creates [`IntegerHasher`]s, for use with [`HashMap::with_hasher`](crate::HashMap::with_hasher).
+pub type BuildIntegerHasher = BuildHasherDefault<IntegerHasher>;
+
+// 2^32 divided by the golden ratio, as used for Fibonacci hashing
+const MULTIPLIER: u32 = 0x9e37_79b9;
+
+impl IntegerHasher {
+    fn add(&mut self, value: u32) {
+        self.hash = (self.hash.rotate_left(5) ^ value).wrapping_mul(MULTIPLIER);
+    }
+}
+
+// truncating is fine when hashing, and usize is 32 bits on the GBA
+#[allow(clippy::cast_possible_truncation)]
+impl Hasher for IntegerHasher {
+    fn finish(&self) -> u64 {
+        u64::from(self.hash)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(4);
+        for chunk in &mut chunks {
+            self.add(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        }
+
+        let mut remainder = [0; 4];
+        remainder[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+        // include the length so that trailing zero bytes change the hash
+        self.add(u32::from_le_bytes(remainder) ^ (bytes.len() as u32) << 24);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i.into());
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i.into());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i as u32);
+        self.add((i >> 32) as u32);
+    }
+
+    // usize and isize are 32 bits on the GBA, but can be 64 bits when testing on other platforms
+    fn write_usize(&mut self, i: usize) {
+        if usize::BITS == 32 {
+            self.add(i as u32);
+        } else {
+            self.write_u64(i as u64);
+        }
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(i as u8);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_usize(i as usize);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::hash::BuildHasher;
+
+    use crate::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn can_store_and_retrieve_integer_keys() {
+        let mut map = HashMap::with_hasher(BuildIntegerHasher::default());
+
+        for i in 0..1000u32 {
+            map.insert(i * 32, i);
+        }
+
+        assert_eq!(map.len(), 1000);
+        for i in 0..1000u32 {
+            assert_eq!(map.get(&(i * 32)), Some(&i));
+            assert_eq!(map.get(&(i * 32 + 1)), None);
+        }
+    }
+
+    #[test]
+    fn can_store_other_keys() {
+        let mut map = HashMap::with_capacity_and_hasher(10, BuildIntegerHasher::default());
+
+        map.insert("hello", 1);
+        map.insert("hello\0", 2);
+        map.insert("a longer string", 3);
+
+        assert_eq!(map.get("hello"), Some(&1));
+        assert_eq!(map.get("hello\0"), Some(&2));
+        assert_eq!(map.get("a longer string"), Some(&3));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn nearby_keys_hash_differently() {
+        let builder = BuildIntegerHasher::default();
+
+        assert_ne!(builder.hash_one(1u32), builder.hash_one(2u32));
+        assert_ne!(
+            builder.hash_one((1u16, 2u16)),
+            builder.hash_one((2u16, 1u16))
+        );
+        assert_eq!(builder.hash_one(-5i32), builder.hash_one(-5i32 as u32));
+    }
+}
@@ -55,9 +55,11 @@ use core::{
     ops::Index,
 };
 
+use alloc::vec::Vec;
 use rustc_hash::FxHasher;
 
 mod hash_set;
+mod integer_hasher;
 mod node;
 mod node_storage;
 
@@ -65,6 +67,12 @@ use node::Node;
 use node_storage::NodeStorage;
 
 pub use hash_set::HashSet;
+pub use integer_hasher::{BuildIntegerHasher, IntegerHasher};
+
+/// The [`BuildHasher`] used by [`HashMap`] unless another is given with
+/// [`HashMap::with_hasher`]. It is fast for most keys, but see [`IntegerHasher`] for an even
+/// faster one for integer keys.
+pub type DefaultHashBuilder = BuildHasherDefault<FxHasher>;
 
 // # Robin Hood Hash Tables
 //
@@ -135,6 +143,20 @@ pub use hash_set::HashSet;
 /// [`Eq`]: https://doc.rust-lang.org/core/cmp/trait.Eq.html
 /// [`Hash`]: https://doc.rust-lang.org/core/hash/trait.Hash.html
 ///
+/// # Iteration order
+///
+/// The order the elements are visited in by [`iter`](HashMap::iter) and the other iterators
+/// isn't specified, but it is deterministic. Unlike the standard library's `HashMap`, the
+/// default hasher isn't randomly seeded, so two maps which have the same sequence of
+/// insertions, removals and other changes made to them, starting from the same capacity and
+/// hasher, will always iterate in the same order. This holds across runs of the same build of
+/// a game, so it is fine for replays.
+///
+/// It doesn't hold between maps with different histories which happen to contain the same
+/// elements, or across different targets or versions of this crate. When the order needs to
+/// be canonical, for example to serialise the map into save data or to checksum it, use
+/// [`iter_sorted_by_key`](HashMap::iter_sorted_by_key).
+///
 /// # Example
 /// ```
 /// use agb_hashmap::HashMap;
@@ -167,10 +189,10 @@ pub use hash_set::HashSet;
 /// }
 /// ```
 #[derive(Clone)]
-pub struct HashMap<K, V, ALLOCATOR: Allocator = Global> {
+pub struct HashMap<K, V, ALLOCATOR: Allocator = Global, S = DefaultHashBuilder> {
     nodes: NodeStorage<K, V, ALLOCATOR>,
 
-    hasher: BuildHasherDefault<FxHasher>,
+    hasher: S,
 }
 
 /// Trait for allocators that are clonable, blanket implementation for all types that implement Allocator and Clone
@@ -198,15 +220,27 @@ impl<K, V> HashMap<K, V> {
     }
 }
 
+impl<K, V, S> HashMap<K, V, Global, S> {
+    /// Creates an empty `HashMap` which will use `hasher` to hash its keys
+    #[must_use]
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_hasher_in(hasher, Global)
+    }
+
+    /// Creates an empty `HashMap` which can hold at least `capacity` elements before resizing,
+    /// and which will use `hasher` to hash its keys
+    #[must_use]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self::with_capacity_and_hasher_in(capacity, hasher, Global)
+    }
+}
+
 impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR> {
     #[must_use]
     /// Creates an empty `HashMap` with specified internal size using the
     /// specified allocator. The size must be a power of 2
     pub fn with_size_in(size: usize, alloc: ALLOCATOR) -> Self {
-        Self {
-            nodes: NodeStorage::with_size_in(size, alloc),
-            hasher: BuildHasherDefault::default(),
-        }
+        Self::with_size_and_hasher_in(size, DefaultHashBuilder::default(), alloc)
     }
 
     #[must_use]
@@ -215,11 +249,6 @@ impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR> {
         Self::with_size_in(16, alloc)
     }
 
-    /// Returns a reference to the underlying allocator
-    pub fn allocator(&self) -> &ALLOCATOR {
-        self.nodes.allocator()
-    }
-
     /// Creates an empty `HashMap` which can hold at least `capacity` elements before resizing. The actual
     /// internal size may be larger as it must be a power of 2
     ///
@@ -228,10 +257,40 @@ impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR> {
     /// Panics if capacity is larger than 2^32 * .85
     #[must_use]
     pub fn with_capacity_in(capacity: usize, alloc: ALLOCATOR) -> Self {
+        Self::with_capacity_and_hasher_in(capacity, DefaultHashBuilder::default(), alloc)
+    }
+}
+
+impl<K, V, ALLOCATOR: ClonableAllocator, S> HashMap<K, V, ALLOCATOR, S> {
+    #[must_use]
+    /// Creates an empty `HashMap` with specified internal size using the specified allocator,
+    /// which will use `hasher` to hash its keys. The size must be a power of 2
+    pub fn with_size_and_hasher_in(size: usize, hasher: S, alloc: ALLOCATOR) -> Self {
+        Self {
+            nodes: NodeStorage::with_size_in(size, alloc),
+            hasher,
+        }
+    }
+
+    #[must_use]
+    /// Creates a `HashMap` with a specified allocator which will use `hasher` to hash its keys
+    pub fn with_hasher_in(hasher: S, alloc: ALLOCATOR) -> Self {
+        Self::with_size_and_hasher_in(16, hasher, alloc)
+    }
+
+    /// Creates an empty `HashMap` which can hold at least `capacity` elements before resizing,
+    /// using the specified allocator and `hasher` to hash its keys. The actual internal size may
+    /// be larger as it must be a power of 2
+    ///
+    /// # Panics
+    ///
+    /// Panics if capacity is larger than 2^32 * .85
+    #[must_use]
+    pub fn with_capacity_and_hasher_in(capacity: usize, hasher: S, alloc: ALLOCATOR) -> Self {
         for i in 0..32 {
             let attempted_size = 1usize << i;
             if number_before_resize(attempted_size) > capacity {
-                return Self::with_size_in(attempted_size, alloc);
+                return Self::with_size_and_hasher_in(attempted_size, hasher, alloc);
             }
         }
 
@@ -241,6 +300,16 @@ impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR> {
         );
     }
 
+    /// Returns a reference to the underlying allocator
+    pub fn allocator(&self) -> &ALLOCATOR {
+        self.nodes.allocator()
+    }
+
+    /// Returns a reference to the map's [`BuildHasher`]
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
     /// Returns the number of elements in the map
     #[must_use]
     pub fn len(&self) -> usize {
@@ -282,12 +351,72 @@ impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR> {
         }
     }
 
+    /// An iterator visiting all key-value pairs in order of their keys.
+    ///
+    /// This collects references to the elements into a scratch buffer and sorts it, so it
+    /// allocates and takes `O(n log n)` time. Use it when the order has to depend only on what
+    /// is in the map, such as when serialising the map or computing a checksum of it.
+    ///
+    /// ```
+    /// use agb_hashmap::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert(3, "c");
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    ///
+    /// let values: Vec<_> = map.iter_sorted_by_key().map(|(_, v)| *v).collect();
+    /// assert_eq!(values, ["a", "b", "c"]);
+    /// ```
+    pub fn iter_sorted_by_key(&self) -> impl Iterator<Item = (&'_ K, &'_ V)>
+    where
+        K: Ord,
+    {
+        let mut sorted: Vec<_> = self.iter().collect();
+        sorted.sort_unstable_by_key(|&(k, _)| k);
+        sorted.into_iter()
+    }
+
     /// An iterator visiting all key-value pairs in an arbitrary order, with mutable references to the values
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&'_ K, &'_ mut V)> {
         self.nodes.iter_mut().filter_map(Node::key_value_mut)
     }
 
-    /// Retains only the elements specified by the predicate `f`.
+    /// Removes all elements from the map, returning them as an iterator. The map keeps its
+    /// capacity, so it can be refilled without allocating.
+    ///
+    /// If the iterator is dropped before it is fully consumed, the remaining elements are
+    /// dropped too.
+    pub fn drain(&mut self) -> Drain<'_, K, V, ALLOCATOR, S> {
+        Drain {
+            map: self,
+            at: 0,
+            num_found: 0,
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements, so that inserting them
+    /// won't resize the map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new size of the map would overflow a `usize`
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self
+            .len()
+            .checked_add(additional)
+            .expect("capacity overflow");
+
+        let mut new_size = self.nodes.backing_vec_size();
+        while number_before_resize(new_size) < required {
+            new_size = new_size.checked_mul(2).expect("capacity overflow");
+        }
+
+        self.resize(new_size);
+    }
+
+    /// Retains only the elements specified by the predicate `f`. Elements are removed in place,
+    /// without allocating.
     pub fn retain<F>(&mut self, f: F)
     where
         F: FnMut(&K, &mut V) -> bool,
@@ -320,9 +449,10 @@ impl<K, V> Default for HashMap<K, V> {
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> HashMap<K, V, ALLOCATOR, S>
 where
     K: Eq + Hash,
+    S: BuildHasher,
 {
     /// Inserts a key-value pair into the map.
     ///
@@ -334,31 +464,40 @@ where
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         let hash = self.hash(&key);
 
-        if let Some(location) = self.nodes.location(&key, hash) {
-            Some(
+        match self.nodes.probe(&key, hash) {
+            Ok(location) => Some(
                 // SAFETY: location is valid due to the above
                 unsafe {
                     self.nodes
                         .replace_at_location_unchecked(location, key, value)
                 },
-            )
-        } else {
-            if self.nodes.capacity() <= self.len() {
-                self.resize(self.nodes.backing_vec_size() * 2);
-            }
-
-            self.nodes.insert_new(key, value, hash);
+            ),
+            Err(distance_to_initial_bucket) => {
+                // SAFETY: the key isn't in the map and the distance came from probing for it
+                unsafe { self.insert_new_and_get(key, value, hash, distance_to_initial_bucket) };
 
-            None
+                None
+            }
         }
     }
 
-    unsafe fn insert_new_and_get(&mut self, key: K, value: V, hash: HashType) -> &'_ mut V {
-        if self.nodes.capacity() <= self.len() {
+    /// Inserts a key which isn't in the map. `distance_to_initial_bucket` is where probing for
+    /// the key stopped, which saves searching the same slots again unless the map has to grow.
+    unsafe fn insert_new_and_get(
+        &mut self,
+        key: K,
+        value: V,
+        hash: HashType,
+        distance_to_initial_bucket: i32,
+    ) -> &'_ mut V {
+        let location = if self.nodes.capacity() <= self.len() {
+            // everything moves when resizing, so the probe needs to start again
             self.resize(self.nodes.backing_vec_size() * 2);
-        }
-
-        let location = self.nodes.insert_new(key, value, hash);
+            self.nodes.insert_new(key, value, hash)
+        } else {
+            self.nodes
+                .insert_new_from(key, value, hash, distance_to_initial_bucket)
+        };
 
         // SAFETY: location is always valid
         unsafe {
@@ -476,9 +615,10 @@ where
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> HashMap<K, V, ALLOCATOR, S>
 where
     K: Hash,
+    S: BuildHasher,
 {
     fn hash<Q>(&self, key: &Q) -> HashType
     where
@@ -498,13 +638,13 @@ where
 ///
 /// This struct is created using the `into_iter()` method on [`HashMap`]. See its
 /// documentation for more.
-pub struct Iter<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator> {
-    map: &'a HashMap<K, V, ALLOCATOR>,
+pub struct Iter<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator, S = DefaultHashBuilder> {
+    map: &'a HashMap<K, V, ALLOCATOR, S>,
     at: usize,
     num_found: usize,
 }
 
-impl<'a, K, V, ALLOCATOR: ClonableAllocator> Iterator for Iter<'a, K, V, ALLOCATOR> {
+impl<'a, K, V, ALLOCATOR: ClonableAllocator, S> Iterator for Iter<'a, K, V, ALLOCATOR, S> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -531,11 +671,11 @@ impl<'a, K, V, ALLOCATOR: ClonableAllocator> Iterator for Iter<'a, K, V, ALLOCAT
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> ExactSizeIterator for Iter<'_, K, V, ALLOCATOR> {}
+impl<K, V, ALLOCATOR: ClonableAllocator, S> ExactSizeIterator for Iter<'_, K, V, ALLOCATOR, S> {}
 
-impl<'a, K, V, ALLOCATOR: ClonableAllocator> IntoIterator for &'a HashMap<K, V, ALLOCATOR> {
+impl<'a, K, V, ALLOCATOR: ClonableAllocator, S> IntoIterator for &'a HashMap<K, V, ALLOCATOR, S> {
     type Item = (&'a K, &'a V);
-    type IntoIter = Iter<'a, K, V, ALLOCATOR>;
+    type IntoIter = Iter<'a, K, V, ALLOCATOR, S>;
 
     fn into_iter(self) -> Self::IntoIter {
         Iter {
@@ -546,17 +686,63 @@ impl<'a, K, V, ALLOCATOR: ClonableAllocator> IntoIterator for &'a HashMap<K, V,
     }
 }
 
+/// A draining iterator over the entries of a [`HashMap`]
+///
+/// This struct is created using the [`drain()`](HashMap::drain) method on [`HashMap`]. See its
+/// documentation for more.
+pub struct Drain<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator = Global, S = DefaultHashBuilder> {
+    map: &'a mut HashMap<K, V, ALLOCATOR, S>,
+    at: usize,
+    num_found: usize,
+}
+
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Iterator for Drain<'_, K, V, ALLOCATOR, S> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.at >= self.map.nodes.backing_vec_size() {
+                return None;
+            }
+
+            let maybe_kv = self.map.nodes.node_at_mut(self.at).take_key_value();
+            self.at += 1;
+
+            if let Some((k, v, _)) = maybe_kv {
+                self.num_found += 1;
+                return Some((k, v));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (
+            self.map.len() - self.num_found,
+            Some(self.map.len() - self.num_found),
+        )
+    }
+}
+
+impl<K, V, ALLOCATOR: ClonableAllocator, S> ExactSizeIterator for Drain<'_, K, V, ALLOCATOR, S> {}
+
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Drop for Drain<'_, K, V, ALLOCATOR, S> {
+    fn drop(&mut self) {
+        // drops anything which wasn't taken and puts the map back into a consistent state
+        self.map.clear();
+    }
+}
+
 /// An iterator over entries of a [`HashMap`]
 ///
 /// This struct is created using the `into_iter()` method on [`HashMap`] as part of its implementation
 /// of the `IntoIterator` trait.
-pub struct IterOwned<K, V, ALLOCATOR: Allocator = Global> {
-    map: HashMap<K, V, ALLOCATOR>,
+pub struct IterOwned<K, V, ALLOCATOR: Allocator = Global, S = DefaultHashBuilder> {
+    map: HashMap<K, V, ALLOCATOR, S>,
     at: usize,
     num_found: usize,
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> Iterator for IterOwned<K, V, ALLOCATOR> {
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Iterator for IterOwned<K, V, ALLOCATOR, S> {
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -583,15 +769,15 @@ impl<K, V, ALLOCATOR: ClonableAllocator> Iterator for IterOwned<K, V, ALLOCATOR>
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> ExactSizeIterator for IterOwned<K, V, ALLOCATOR> {}
+impl<K, V, ALLOCATOR: ClonableAllocator, S> ExactSizeIterator for IterOwned<K, V, ALLOCATOR, S> {}
 
 /// An iterator over entries of a [`HashMap`]
 ///
 /// This struct is created using the `into_iter()` method on [`HashMap`] as part of its implementation
 /// of the `IntoIterator` trait.
-impl<K, V, ALLOCATOR: ClonableAllocator> IntoIterator for HashMap<K, V, ALLOCATOR> {
+impl<K, V, ALLOCATOR: ClonableAllocator, S> IntoIterator for HashMap<K, V, ALLOCATOR, S> {
     type Item = (K, V);
-    type IntoIter = IterOwned<K, V, ALLOCATOR>;
+    type IntoIter = IterOwned<K, V, ALLOCATOR, S>;
 
     fn into_iter(self) -> Self::IntoIter {
         IterOwned {
@@ -604,24 +790,24 @@ impl<K, V, ALLOCATOR: ClonableAllocator> IntoIterator for HashMap<K, V, ALLOCATO
 
 mod entries {
     use crate::allocate::Allocator;
-    use core::hash::Hash;
+    use core::hash::{BuildHasher, Hash};
 
-    use super::{ClonableAllocator, HashMap, HashType};
+    use super::{ClonableAllocator, DefaultHashBuilder, HashMap, HashType};
 
     /// A view into an occupied entry in a `HashMap`. This is part of the [`crate::Entry`] enum.
-    pub struct OccupiedEntry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator> {
+    pub struct OccupiedEntry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator, S = DefaultHashBuilder> {
         key: K,
-        map: &'a mut HashMap<K, V, ALLOCATOR>,
+        map: &'a mut HashMap<K, V, ALLOCATOR, S>,
         location: usize,
     }
 
-    impl<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator> OccupiedEntry<'a, K, V, ALLOCATOR> {
+    impl<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator, S> OccupiedEntry<'a, K, V, ALLOCATOR, S> {
         /// # Safety
         ///
         /// You must call this with a valid location (one where the entry is defined)
         pub(crate) unsafe fn new(
             key: K,
-            map: &'a mut HashMap<K, V, ALLOCATOR>,
+            map: &'a mut HashMap<K, V, ALLOCATOR, S>,
             location: usize,
         ) -> Self {
             Self { key, map, location }
@@ -699,19 +885,26 @@ mod entries {
     }
 
     /// A view into a vacant entry in a `HashMap`. It is part of the [`crate::Entry`] enum.
-    pub struct VacantEntry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator> {
+    pub struct VacantEntry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator, S = DefaultHashBuilder> {
         key: K,
-        map: &'a mut HashMap<K, V, ALLOCATOR>,
+        map: &'a mut HashMap<K, V, ALLOCATOR, S>,
         hash: HashType,
+        distance_to_initial_bucket: i32,
     }
 
-    impl<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator> VacantEntry<'a, K, V, ALLOCATOR> {
+    impl<'a, K: 'a, V: 'a, ALLOCATOR: ClonableAllocator, S> VacantEntry<'a, K, V, ALLOCATOR, S> {
         pub(crate) unsafe fn new(
             key: K,
             hash: HashType,
-            map: &'a mut HashMap<K, V, ALLOCATOR>,
+            distance_to_initial_bucket: i32,
+            map: &'a mut HashMap<K, V, ALLOCATOR, S>,
         ) -> Self {
-            Self { key, map, hash }
+            Self {
+                key,
+                map,
+                hash,
+                distance_to_initial_bucket,
+            }
         }
 
         /// Gets a reference to the key that would be used when inserting a value through `VacantEntry`
@@ -728,9 +921,18 @@ mod entries {
         pub fn insert(self, value: V) -> &'a mut V
         where
             K: Hash + Eq,
+            S: BuildHasher,
         {
-            // SAFETY: by construction, this doesn't already exist in the hashmap and we were given the hash and key
-            unsafe { self.map.insert_new_and_get(self.key, value, self.hash) }
+            // SAFETY: by construction, this doesn't already exist in the hashmap and we were given the hash, key
+            // and where probing for it stopped
+            unsafe {
+                self.map.insert_new_and_get(
+                    self.key,
+                    value,
+                    self.hash,
+                    self.distance_to_initial_bucket,
+                )
+            }
         }
     }
 }
@@ -742,16 +944,17 @@ pub use entries::{OccupiedEntry, VacantEntry};
 /// This is constructed using the [`entry`] method on [`HashMap`]
 ///
 /// [`entry`]: HashMap::entry()
-pub enum Entry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator = Global> {
+pub enum Entry<'a, K: 'a, V: 'a, ALLOCATOR: Allocator = Global, S = DefaultHashBuilder> {
     /// An occupied entry
-    Occupied(OccupiedEntry<'a, K, V, ALLOCATOR>),
+    Occupied(OccupiedEntry<'a, K, V, ALLOCATOR, S>),
     /// A vacant entry
-    Vacant(VacantEntry<'a, K, V, ALLOCATOR>),
+    Vacant(VacantEntry<'a, K, V, ALLOCATOR, S>),
 }
 
-impl<'a, K, V, ALLOCATOR: ClonableAllocator> Entry<'a, K, V, ALLOCATOR>
+impl<'a, K, V, ALLOCATOR: ClonableAllocator, S> Entry<'a, K, V, ALLOCATOR, S>
 where
     K: Hash + Eq,
+    S: BuildHasher,
 {
     /// Ensures a value is in the entry by inserting the given value, and returns a mutable
     /// reference to the value in the entry.
@@ -830,25 +1033,23 @@ where
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> HashMap<K, V, ALLOCATOR, S>
 where
     K: Hash + Eq,
+    S: BuildHasher,
 {
     /// Gets the given key's corresponding entry in the map for in-place manipulation.
-    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, ALLOCATOR> {
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, ALLOCATOR, S> {
         let hash = self.hash(&key);
-        let location = self.nodes.location(&key, hash);
-
-        if let Some(location) = location {
-            Entry::Occupied(
-                // SAFETY: location is valid by the call to location above
+        match self.nodes.probe(&key, hash) {
+            Ok(location) => Entry::Occupied(
+                // SAFETY: location is valid by the call to probe above
                 unsafe { OccupiedEntry::new(key, self, location) },
-            )
-        } else {
-            Entry::Vacant(
-                // SAFETY: item doesn't exist yet and the hash is correct here
-                unsafe { VacantEntry::new(key, hash, self) },
-            )
+            ),
+            Err(distance_to_initial_bucket) => Entry::Vacant(
+                // SAFETY: item doesn't exist yet and the hash and distance are correct here
+                unsafe { VacantEntry::new(key, hash, distance_to_initial_bucket, self) },
+            ),
         }
     }
 }
@@ -864,20 +1065,33 @@ where
     }
 }
 
-impl<K, V> Extend<(K, V)> for HashMap<K, V>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Extend<(K, V)> for HashMap<K, V, ALLOCATOR, S>
 where
     K: Eq + Hash,
+    S: BuildHasher,
 {
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+
+        // Some of the keys may already be in the map, so if it isn't empty only reserve for half
+        // of them. That way we resize at most once more than if we knew exactly.
+        let (lower_bound, _) = iter.size_hint();
+        if self.is_empty() {
+            self.reserve(lower_bound);
+        } else {
+            self.reserve(lower_bound.div_ceil(2));
+        }
+
         for (k, v) in iter {
             self.insert(k, v);
         }
     }
 }
 
-impl<K, V, Q, ALLOCATOR: ClonableAllocator> Index<&Q> for HashMap<K, V, ALLOCATOR>
+impl<K, V, Q, ALLOCATOR: ClonableAllocator, S> Index<&Q> for HashMap<K, V, ALLOCATOR, S>
 where
     K: Eq + Hash + Borrow<Q>,
+    S: BuildHasher,
     Q: Eq + Hash + ?Sized,
 {
     type Output = V;
@@ -887,12 +1101,13 @@ where
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> PartialEq for HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> PartialEq for HashMap<K, V, ALLOCATOR, S>
 where
     K: Eq + Hash,
+    S: BuildHasher,
     V: PartialEq,
 {
-    fn eq(&self, other: &HashMap<K, V, ALLOCATOR>) -> bool {
+    fn eq(&self, other: &HashMap<K, V, ALLOCATOR, S>) -> bool {
         if self.len() != other.len() {
             return false;
         }
@@ -902,14 +1117,15 @@ where
     }
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> Eq for HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Eq for HashMap<K, V, ALLOCATOR, S>
 where
     K: Eq + Hash,
+    S: BuildHasher,
     V: PartialEq,
 {
 }
 
-impl<K, V, ALLOCATOR: ClonableAllocator> Debug for HashMap<K, V, ALLOCATOR>
+impl<K, V, ALLOCATOR: ClonableAllocator, S> Debug for HashMap<K, V, ALLOCATOR, S>
 where
     K: Debug,
     V: Debug,
@@ -1295,6 +1511,134 @@ mod test {
         assert_eq!(map.iter().count(), 50); // force full iteration
     }
 
+    #[test]
+    fn retain_keeps_remaining_elements_findable() {
+        let mut map = HashMap::new();
+
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+
+        map.retain(|k, v| {
+            *v += 1;
+            k % 3 == 0
+        });
+
+        assert_eq!(map.len(), 334);
+        for i in 0..1000 {
+            if i % 3 == 0 {
+                assert_eq!(map.get(&i), Some(&(i + 1)));
+            } else {
+                assert_eq!(map.get(&i), None);
+            }
+        }
+    }
+
+    #[test]
+    fn drain_empties_the_map_and_keeps_capacity() {
+        let mut map = HashMap::new();
+
+        for i in 0..100 {
+            map.insert(i, i * 2);
+        }
+
+        let capacity = map.capacity();
+
+        let mut drained: Vec<_> = map.drain().collect();
+        drained.sort_unstable();
+
+        assert_eq!(drained, (0..100).map(|i| (i, i * 2)).collect::<Vec<_>>());
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.get(&5), None);
+
+        map.insert(5, 5);
+        assert_eq!(map[&5], 5);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn partially_consumed_drain_drops_the_rest() {
+        let drop_registry = DropRegistry::new();
+        let mut map = HashMap::new();
+
+        let ids: Vec<_> = (0..10)
+            .map(|i| {
+                let droppable = drop_registry.new_droppable();
+                let id = droppable.id;
+                map.insert(i, droppable);
+                id
+            })
+            .collect();
+
+        let mut drain = map.drain();
+        assert_eq!(drain.size_hint(), (10, Some(10)));
+        drain.next();
+        drain.next();
+        assert_eq!(drain.size_hint(), (8, Some(8)));
+        drop(drain);
+
+        assert!(map.is_empty());
+        for id in ids {
+            drop_registry.assert_dropped_once(id);
+        }
+    }
+
+    #[test]
+    fn extend_reserves_from_the_size_hint() {
+        let mut map = HashMap::new();
+        map.extend((0..1000).map(|i| (i, i)));
+
+        assert_eq!(map.len(), 1000);
+        assert_eq!(
+            map.capacity(),
+            HashMap::<i32, i32>::with_capacity(1000).capacity()
+        );
+
+        map.extend((500..1500).map(|i| (i, -i)));
+        assert_eq!(map.len(), 1500);
+        assert_eq!(map[&400], 400);
+        assert_eq!(map[&600], -600);
+
+        let mut reserved: HashMap<i32, i32> = HashMap::new();
+        reserved.reserve(100);
+        assert!(reserved.capacity() >= 100);
+    }
+
+    #[test]
+    fn iteration_order_only_depends_on_history() {
+        fn build() -> HashMap<u32, u32> {
+            let mut map = HashMap::new();
+            for i in 0..200u32 {
+                map.insert(i.wrapping_mul(2_654_435_761), i);
+            }
+            map.retain(|_, v| *v % 3 != 0);
+            for i in 0..50 {
+                map.remove(&(i * 7u32).wrapping_mul(2_654_435_761));
+            }
+            map
+        }
+
+        let first: Vec<_> = build().into_iter().collect();
+        let second: Vec<_> = build().into_iter().collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn iter_sorted_by_key_ignores_insertion_order() {
+        let mut forwards = HashMap::new();
+        let mut backwards = HashMap::new();
+        for i in 0..100 {
+            forwards.insert(i, i * 2);
+            backwards.insert(99 - i, (99 - i) * 2);
+        }
+
+        let sorted: Vec<_> = forwards.iter_sorted_by_key().collect();
+        assert_eq!(sorted, backwards.iter_sorted_by_key().collect::<Vec<_>>());
+        assert!(sorted.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(sorted.len(), 100);
+    }
+
     #[test]
     fn test_size_hint_iter() {
         let mut map = HashMap::new();
@@ -1420,6 +1764,102 @@ mod test {
             assert_eq!(a[key], value);
         }
 
+        #[test]
+        fn test_entry_take_doesnt_corrupt() {
+            use rand::SeedableRng;
+
+            use super::RngNextI32;
+
+            // Test for rust-lang/rust#19292
+            fn check(m: &HashMap<i32, ()>) {
+                for k in m.keys() {
+                    assert!(m.contains_key(k), "{k} is in keys() but not in the map?");
+                }
+            }
+
+            let mut m = HashMap::new();
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(19292);
+
+            // Populate the map with some items.
+            for _ in 0..50 {
+                let x = rng.next_i32().rem_euclid(20) - 10;
+                m.insert(x, ());
+            }
+
+            for _ in 0..1000 {
+                let x = rng.next_i32().rem_euclid(20) - 10;
+                match m.entry(x) {
+                    Vacant(_) => {}
+                    Occupied(e) => {
+                        e.remove();
+                    }
+                }
+
+                check(&m);
+            }
+        }
+
+        #[test]
+        fn test_entry_and_modify_vacant_doesnt_insert() {
+            let mut map: HashMap<i32, i32> = HashMap::new();
+
+            let _ = map.entry(1).and_modify(|v| *v += 1);
+            assert!(map.is_empty());
+            assert_eq!(map.get(&1), None);
+
+            map.entry(1).and_modify(|v| *v += 1).or_insert(10);
+            assert_eq!(map[&1], 10);
+            map.entry(1).and_modify(|v| *v += 1).or_insert(10);
+            assert_eq!(map[&1], 11);
+            assert_eq!(map.len(), 1);
+        }
+
+        #[test]
+        fn test_entry_or_insert_with_and_or_default() {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+
+            for word in "the quick brown fox jumps over the lazy dog the end".split(' ') {
+                *counts.entry(word).or_default() += 1;
+            }
+
+            assert_eq!(counts.len(), 9);
+            assert_eq!(counts["the"], 3);
+            assert_eq!(counts["fox"], 1);
+
+            let mut called = false;
+            assert_eq!(
+                *counts.entry("the").or_insert_with(|| {
+                    called = true;
+                    0
+                }),
+                3
+            );
+            assert!(!called);
+
+            assert_eq!(*counts.entry("cat").or_insert_with(|| 7), 7);
+            assert_eq!(*counts.entry("cat").or_insert_with_key(|k| k.len()), 7);
+            assert_eq!(*counts.entry("hat").or_insert_with_key(|k| k.len()), 3);
+        }
+
+        #[test]
+        fn test_entry_insert_through_resizes() {
+            let mut map = HashMap::new();
+
+            for i in 0..1000 {
+                match map.entry(i) {
+                    Occupied(_) => unreachable!(),
+                    Vacant(e) => {
+                        assert_eq!(*e.insert(i * 2), i * 2);
+                    }
+                }
+            }
+
+            assert_eq!(map.len(), 1000);
+            for i in 0..1000 {
+                assert_eq!(map[&i], i * 2);
+            }
+        }
+
         #[test]
         fn test_index() {
             let mut map = HashMap::new();
@@ -110,6 +110,11 @@ impl<K, V> Node<K, V> {
         (old_key.assume_init(), old_value.assume_init())
     }
 
+    pub(crate) fn set_distance(&mut self, distance: i32) {
+        debug_assert!(self.has_value(), "Cannot set the distance of an empty node");
+        self.distance_to_initial_bucket = distance;
+    }
+
     pub(crate) fn increment_distance(&mut self) {
         self.distance_to_initial_bucket += 1;
     }
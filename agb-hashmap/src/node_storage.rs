@@ -49,6 +49,19 @@ impl<K, V, ALLOCATOR: ClonableAllocator> NodeStorage<K, V, ALLOCATOR> {
     }
 
     pub(crate) fn insert_new(&mut self, key: K, value: V, hash: HashType) -> usize {
+        self.insert_new_from(key, value, hash, 0)
+    }
+
+    /// Inserts a key which isn't in the map, starting the search for somewhere to put it
+    /// `distance_to_initial_bucket` slots from its initial bucket. Use the distance returned by
+    /// [`probe`](Self::probe) to avoid searching the slots it has already looked at again.
+    pub(crate) fn insert_new_from(
+        &mut self,
+        key: K,
+        value: V,
+        hash: HashType,
+        distance_to_initial_bucket: i32,
+    ) -> usize {
         debug_assert!(
             self.capacity() > self.len(),
             "Do not have space to insert into len {} with {}",
@@ -57,6 +70,10 @@ impl<K, V, ALLOCATOR: ClonableAllocator> NodeStorage<K, V, ALLOCATOR> {
         );
 
         let mut new_node = Node::new_with(key, value, hash);
+        new_node.set_distance(distance_to_initial_bucket);
+        self.max_distance_to_initial_bucket =
+            distance_to_initial_bucket.max(self.max_distance_to_initial_bucket);
+
         let mut inserted_location = usize::MAX;
 
         loop {
@@ -136,6 +153,18 @@ impl<K, V, ALLOCATOR: ClonableAllocator> NodeStorage<K, V, ALLOCATOR> {
     }
 
     pub(crate) fn location<Q>(&self, key: &Q, hash: HashType) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.probe(key, hash).ok()
+    }
+
+    /// Finds the location of `key`, or if it isn't in the map, the distance from its initial
+    /// bucket at which it would be inserted. Passing that distance to
+    /// [`insert_new_from`](Self::insert_new_from) inserts the key without probing again, as long
+    /// as the storage hasn't changed in between.
+    pub(crate) fn probe<Q>(&self, key: &Q, hash: HashType) -> Result<usize, i32>
     where
         K: Borrow<Q>,
         Q: Eq + ?Sized,
@@ -148,17 +177,19 @@ impl<K, V, ALLOCATOR: ClonableAllocator> NodeStorage<K, V, ALLOCATOR> {
             // if we've seen a node which is further from home than what we'd expect to find, then
             // our node cannot exist because it would've been inserted here.
             if node.distance() < distance_to_initial_bucket {
-                return None;
+                return Err(distance_to_initial_bucket);
             }
 
-            let node_key_ref = node.key_ref()?;
+            let Some(node_key_ref) = node.key_ref() else {
+                return Err(distance_to_initial_bucket);
+            };
 
             if node_key_ref.borrow() == key {
-                return Some(location);
+                return Ok(location);
             }
         }
 
-        None
+        Err(self.max_distance_to_initial_bucket + 1)
     }
 
     pub(crate) fn resized_to(&mut self, new_size: usize) -> Self {
@@ -10,8 +10,8 @@ use crate::{ClonableAllocator, HashMap, HashSet};
 mod hashmap {
     use super::*;
 
-    impl<K: Serialize, V: Serialize, ALLOCATOR: ClonableAllocator> Serialize
-        for HashMap<K, V, ALLOCATOR>
+    impl<K: Serialize, V: Serialize, ALLOCATOR: ClonableAllocator, H> Serialize
+        for HashMap<K, V, ALLOCATOR, H>
     {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
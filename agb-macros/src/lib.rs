@@ -3,7 +3,10 @@ use proc_macro::TokenStream;
 
 use proc_macro2::Span;
 use quote::{quote, ToTokens};
-use syn::{FnArg, Ident, ItemFn, Pat, ReturnType, Token, Type, Visibility};
+use syn::{
+    parse::Parser, punctuated::Punctuated, FnArg, Ident, ItemFn, Meta, Pat, ReturnType, Token,
+    Type, Visibility,
+};
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -128,10 +131,147 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     .into()
 }
 
+#[proc_macro_attribute]
+pub fn bench(args: TokenStream, input: TokenStream) -> TokenStream {
+    let f: ItemFn = match syn::parse(input.clone()) {
+        Ok(it) => it,
+        Err(_) => return input,
+    };
+
+    if !(f.sig.constness.is_none()
+        && f.sig.asyncness.is_none()
+        && f.sig.unsafety.is_none()
+        && f.sig.abi.is_none()
+        && f.sig.generics.params.is_empty()
+        && (1..=2).contains(&f.sig.inputs.len())
+        && f.sig
+            .inputs
+            .iter()
+            .all(|arg| matches!(arg, FnArg::Typed(_)))
+        && matches!(f.sig.output, ReturnType::Default))
+    {
+        return token_stream_with_string_error(
+            input,
+            "#[agb::bench] must have signature fn ([&mut agb::Gba,] &mut agb::test_runner::Bencher)",
+        );
+    }
+
+    if args.to_string() != "" {
+        return token_stream_with_string_error(input, "Must pass no args to #[agb::bench] macro");
+    }
+
+    let name = &f.sig.ident;
+    let bench_name = Ident::new(&format!("_agb_bench_{name}"), Span::call_site());
+
+    let call = if f.sig.inputs.len() == 1 {
+        quote!(|_gba, bencher| #name(bencher))
+    } else {
+        quote!(#name)
+    };
+
+    quote!(
+        #f
+
+        #[test_case]
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        const #bench_name: agb::test_runner::Bench =
+            agb::test_runner::Bench::new(concat!(module_path!(), "::", stringify!(#name)), #call);
+    )
+    .into()
+}
+
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut f: ItemFn = match syn::parse(input.clone()) {
+        Ok(it) => it,
+        Err(_) => return input,
+    };
+
+    if !(f.sig.constness.is_none()
+        && f.sig.asyncness.is_none()
+        && f.sig.unsafety.is_none()
+        && f.sig.abi.is_none()
+        && f.sig.generics.params.is_empty()
+        && f.sig.inputs.len() == 1
+        && matches!(f.sig.inputs[0], FnArg::Typed(_))
+        && matches!(f.sig.output, ReturnType::Default))
+    {
+        return token_stream_with_string_error(
+            input,
+            "#[agb::test] must have signature fn (&mut agb::Gba)",
+        );
+    }
+
+    let args =
+        match Punctuated::<Meta, Token![,]>::parse_terminated.parse(args) {
+            Ok(args) => args,
+            Err(_) => return token_stream_with_string_error(
+                input,
+                "The arguments to #[agb::test] are ignore, no_alloc and timeout_frames = <frames>",
+            ),
+        };
+
+    let mut timeout_frames = quote!(agb::test_runner::DEFAULT_TIMEOUT_FRAMES);
+    let mut ignore = false;
+    let mut no_alloc = false;
+
+    for arg in args {
+        match arg {
+            Meta::Path(path) if path.is_ident("ignore") => ignore = true,
+            Meta::Path(path) if path.is_ident("no_alloc") => no_alloc = true,
+            Meta::NameValue(arg) if arg.path.is_ident("timeout_frames") => {
+                timeout_frames = arg.value.into_token_stream();
+            }
+            _ => return token_stream_with_string_error(
+                input,
+                "The arguments to #[agb::test] are ignore, no_alloc and timeout_frames = <frames>",
+            ),
+        }
+    }
+
+    // #[ignore] only means something to the built in test harness, so it is taken off here
+    let attribute_count = f.attrs.len();
+    f.attrs.retain(|attr| !attr.path().is_ident("ignore"));
+    ignore |= f.attrs.len() != attribute_count;
+
+    let ignore = if ignore { quote!(.ignore()) } else { quote!() };
+    let no_alloc = if no_alloc {
+        quote!(.no_alloc())
+    } else {
+        quote!()
+    };
+
+    let name = &f.sig.ident;
+    let test_name = Ident::new(&format!("_agb_test_{name}"), Span::call_site());
+
+    quote!(
+        #f
+
+        #[test_case]
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        const #test_name: agb::test_runner::Test = agb::test_runner::Test::new(
+            concat!(module_path!(), "::", stringify!(#name)),
+            #name,
+            #timeout_frames,
+        )#ignore #no_alloc;
+    )
+    .into()
+}
+
 #[proc_macro]
 pub fn num(input: TokenStream) -> TokenStream {
-    let f = syn::parse_macro_input!(input as syn::LitFloat);
-    let v: f64 = f.base10_parse().expect("The number should be parsable");
+    let lit = syn::parse_macro_input!(input as syn::Lit);
+    let v: f64 = match lit {
+        syn::Lit::Float(f) => f.base10_parse().expect("The number should be parsable"),
+        syn::Lit::Int(i) => i.base10_parse().expect("The number should be parsable"),
+        _ => {
+            return syn::Error::new_spanned(lit, "expected an integer or floating point literal")
+                .into_compile_error()
+                .into()
+        }
+    };
 
     let integer = v.trunc();
     let fractional = v.fract() * (1_u64 << 30) as f64;
@@ -0,0 +1,18 @@
+//! Helpers for build scripts which make their own background tiles for the Game Boy Advance,
+//! rather than using `agb::include_background_gfx!`.
+//!
+//! Add this crate as a build dependency, and write what these functions produce to `OUT_DIR`
+//! for the game to `include!` or `include_bytes!`. It is `no_std` so that `agb` can share the
+//! parts which are also needed at runtime.
+#![no_std]
+#![deny(clippy::all)]
+#![deny(clippy::must_use_candidate)]
+#![deny(missing_docs)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(clippy::missing_panics_doc)]
+
+extern crate alloc;
+
+pub mod rle;
+pub mod tile_brush;
+pub mod tileset_packer;
@@ -0,0 +1,134 @@
+//! Compressing data at build time into the format the GBA BIOS can decompress.
+
+use alloc::vec::Vec;
+
+/// The largest number of bytes a single literal block can hold.
+const MAX_LITERAL_LENGTH: usize = 128;
+/// The shortest run worth encoding as a run rather than as part of a literal block.
+const MIN_RUN_LENGTH: usize = 3;
+/// The longest run a single run block can hold.
+const MAX_RUN_LENGTH: usize = 130;
+
+/// Compresses 4bpp tiles, given as one word per row of 8 pixels, into the BIOS run length
+/// encoding format, ready to be decompressed by `agb::display::tileset_compress_rle::RleTileset`.
+///
+/// This is meant to be called from a build script, with the result written to a file for the
+/// game to `include_bytes!`.
+#[must_use]
+pub fn rle_compress_tiles(tiles: &[[u32; 8]]) -> Vec<u8> {
+    let bytes: Vec<u8> = tiles
+        .iter()
+        .flatten()
+        .flat_map(|row| row.to_le_bytes())
+        .collect();
+
+    rle_compress(&bytes)
+}
+
+/// Compresses `data` into the BIOS run length encoding format.
+///
+/// The result starts with a 4 byte header of `0x30` and the decompressed length in the top 24
+/// bits. Then comes a series of blocks, each starting with a flag byte. If bit 7 of the flag is
+/// set, the next byte is repeated `(flag & 0x7f) + 3` times, otherwise the next
+/// `(flag & 0x7f) + 1` bytes are copied as they are. The result is padded with zeros to a
+/// multiple of 4 bytes.
+///
+/// # Panics
+///
+/// Panics if `data` is 16MiB or longer, since the length wouldn't fit in the header.
+#[must_use]
+pub fn rle_compress(data: &[u8]) -> Vec<u8> {
+    assert!(data.len() < 1 << 24, "data is too long to compress");
+
+    let header = 0x30 | ((data.len() as u32) << 8);
+    let mut output = header.to_le_bytes().to_vec();
+
+    let mut literal_start = 0;
+    let mut i = 0;
+
+    while i < data.len() {
+        let run_length = data[i..]
+            .iter()
+            .take(MAX_RUN_LENGTH)
+            .take_while(|&&byte| byte == data[i])
+            .count();
+
+        if run_length >= MIN_RUN_LENGTH {
+            push_literals(&mut output, &data[literal_start..i]);
+
+            output.push(0x80 | (run_length - MIN_RUN_LENGTH) as u8);
+            output.push(data[i]);
+
+            i += run_length;
+            literal_start = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    push_literals(&mut output, &data[literal_start..]);
+
+    while !output.len().is_multiple_of(4) {
+        output.push(0);
+    }
+
+    output
+}
+
+fn push_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    for chunk in literals.chunks(MAX_LITERAL_LENGTH) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let header = u32::from_le_bytes(data[..4].try_into().unwrap());
+        assert_eq!(header & 0xff, 0x30);
+        let length = (header >> 8) as usize;
+
+        let mut output = vec![];
+        let mut i = 4;
+        while output.len() < length {
+            let flag = data[i];
+            if flag & 0x80 != 0 {
+                let count = usize::from(flag & 0x7f) + 3;
+                output.extend(core::iter::repeat_n(data[i + 1], count));
+                i += 2;
+            } else {
+                let count = usize::from(flag) + 1;
+                output.extend_from_slice(&data[i + 1..i + 1 + count]);
+                i += 1 + count;
+            }
+        }
+
+        output
+    }
+
+    #[test]
+    fn round_trips_runs_and_literals() {
+        let mut data = vec![];
+        data.extend(0..200u8);
+        data.extend([7; 300]);
+        data.extend([1, 2, 2, 3, 3, 3]);
+
+        let compressed = rle_compress(&data);
+        assert!(compressed.len().is_multiple_of(4));
+        assert_eq!(decompress(&compressed), data);
+    }
+
+    #[test]
+    fn compresses_blank_tiles() {
+        let tiles = [[0; 8]; 4];
+
+        let compressed = rle_compress_tiles(&tiles);
+        // a header and a single run of the 128 zero bytes, padded to 8 bytes
+        assert_eq!(compressed, [0x30, 128, 0, 0, 0x80 | 125, 0, 0, 0]);
+        assert_eq!(decompress(&compressed), vec![0; 128]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Converting indexed image data into the 4bpp tile format used by the Game Boy Advance.
+//!
+//! Most of the time you'll want `agb::include_background_gfx!` instead, but if you are generating
+//! graphics yourself (for example from a level editor or a procedural generator) then
+//! [`TileBrush`] will slice the raw palette indices into 8x8 tiles for you in a build script, and
+//! write them out as rust source for the game to `include!`.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+/// Encodes indexed image data into 4bpp tiles.
+#[non_exhaustive]
+pub struct TileBrush;
+
+impl TileBrush {
+    /// Slices an image into 8x8 tiles and encodes each one as 4bpp.
+    ///
+    /// `data` should contain one palette index per pixel stored row by row, so it must be exactly
+    /// `width * height` bytes long. Both `width` and `height` must be multiples of 8 and every
+    /// index must fit in a 16 colour palette.
+    ///
+    /// The tiles are returned left to right, then top to bottom. Each `u32` is a single row of
+    /// the tile with the leftmost pixel in the lowest nibble, which is the layout expected by the
+    /// hardware and by `agb::display::tiled::DynamicTile`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimensions aren't multiples of 8, `data` is the wrong length, or an index is
+    /// 16 or more.
+    #[must_use]
+    pub fn from_indexed_raw(data: &[u8], width: usize, height: usize) -> Vec<[u32; 8]> {
+        assert!(
+            width.is_multiple_of(8) && height.is_multiple_of(8),
+            "image dimensions must be a multiple of 8, got {width}x{height}"
+        );
+        assert_eq!(
+            data.len(),
+            width * height,
+            "expected {} bytes of image data for a {width}x{height} image",
+            width * height
+        );
+
+        let tiles_wide = width / 8;
+        let tiles_high = height / 8;
+
+        let mut tiles = Vec::with_capacity(tiles_wide * tiles_high);
+
+        for tile_y in 0..tiles_high {
+            for tile_x in 0..tiles_wide {
+                let mut tile = [0; 8];
+
+                for (y, row) in tile.iter_mut().enumerate() {
+                    let start = (tile_y * 8 + y) * width + tile_x * 8;
+
+                    for (x, &index) in data[start..start + 8].iter().enumerate() {
+                        assert!(
+                            index < 16,
+                            "palette index {index} is too large for a 4bpp tile"
+                        );
+                        *row |= u32::from(index) << (x * 4);
+                    }
+                }
+
+                tiles.push(tile);
+            }
+        }
+
+        tiles
+    }
+
+    /// Generates rust source declaring a `pub static` called `const_name` containing `tiles`.
+    ///
+    /// The output is intended to be written to a file in `OUT_DIR` and then `include!`ed.
+    #[must_use]
+    pub fn emit_rust_source(tiles: &[[u32; 8]], const_name: &str) -> String {
+        let mut source = String::new();
+
+        // writing to a string can't fail
+        let _ = writeln!(
+            source,
+            "pub static {const_name}: [[u32; 8]; {}] = [",
+            tiles.len()
+        );
+
+        for tile in tiles {
+            source.push_str("    [");
+            for (i, row) in tile.iter().enumerate() {
+                if i != 0 {
+                    source.push_str(", ");
+                }
+                let _ = write!(source, "{row:#010x}");
+            }
+            source.push_str("],\n");
+        }
+
+        source.push_str("];\n");
+
+        source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_tiles_in_reading_order() {
+        let mut data = [0u8; 16 * 8];
+        for y in 0..8 {
+            data[y * 16] = 1;
+            data[y * 16 + 15] = 2;
+        }
+
+        let tiles = TileBrush::from_indexed_raw(&data, 16, 8);
+
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[0], [0x0000_0001; 8]);
+        assert_eq!(tiles[1], [0x2000_0000; 8]);
+    }
+
+    #[test]
+    #[should_panic = "too large for a 4bpp tile"]
+    fn rejects_indices_outside_the_palette() {
+        let _ = TileBrush::from_indexed_raw(&[16; 64], 8, 8);
+    }
+
+    #[test]
+    fn emits_static_array() {
+        let source = TileBrush::emit_rust_source(&[[0x1234_5678; 8]], "TILES");
+
+        assert!(source.starts_with("pub static TILES: [[u32; 8]; 1] = [\n"));
+        assert!(source.contains("[0x12345678, 0x12345678,"));
+        assert!(source.ends_with("];\n"));
+    }
+}
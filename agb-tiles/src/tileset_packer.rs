@@ -0,0 +1,164 @@
+//! Removing duplicate tiles from a tile set at build time.
+//!
+//! Tile sets often contain the same tile more than once, or tiles which are mirror images of
+//! each other. Since screen entries can flip tiles horizontally and vertically, only one copy of
+//! each needs to be in VRAM. [`pack_tileset`] finds the unique tiles and gives a table for
+//! changing a tile map to use them, which [`remap_tilemap`] applies.
+//!
+//! Backgrounds loaded with `agb::include_background_gfx!` can do this with the `deduplicate`
+//! option. These functions are for build scripts which make their own tiles, such as from a
+//! level editor's format. The remap table can also be written out with the tiles, and applied
+//! to maps the game only builds at runtime with [`remap_tilemap`], which `agb` re-exports as
+//! `agb::display::tileset_packer::remap_tilemap`.
+//!
+//! Tiles are 4 bits per pixel, stored as 8 rows where the lowest 4 bits of each row are its
+//! leftmost pixel, the same as they are in VRAM.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// The bits of a screen entry which give the index of its tile.
+pub const TILE_INDEX_MASK: u16 = 0x3ff;
+/// The bit of a screen entry which flips its tile horizontally.
+pub const HFLIP: u16 = 1 << 10;
+/// The bit of a screen entry which flips its tile vertically.
+pub const VFLIP: u16 = 1 << 11;
+
+/// The number of tiles a screen entry can refer to.
+const MAX_TILES: usize = 1024;
+
+fn hflipped(tile: &[u32; 8]) -> [u32; 8] {
+    tile.map(|row| {
+        // reverse the order of the 8 pixels in the row
+        let row = row.swap_bytes();
+        ((row & 0x0f0f_0f0f) << 4) | ((row >> 4) & 0x0f0f_0f0f)
+    })
+}
+
+fn vflipped(tile: &[u32; 8]) -> [u32; 8] {
+    let mut flipped = *tile;
+    flipped.reverse();
+    flipped
+}
+
+/// Finds the unique tiles in `tiles`, counting tiles which are flipped copies of each other as
+/// the same tile.
+///
+/// Returns the unique tiles, and a table with an entry for each tile in `tiles`. That entry is
+/// the screen entry, the index of the unique tile and whether it needs flipping, which draws the
+/// original tile. Use [`remap_tilemap`] to apply the table to a tile map.
+///
+/// # Panics
+///
+/// Panics if there are more than 1024 unique tiles, since screen entries can't refer to any
+/// more than that.
+#[must_use]
+pub fn pack_tileset(tiles: &[[u32; 8]]) -> (Vec<[u32; 8]>, Vec<u16>) {
+    let mut unique_tiles = Vec::new();
+    let mut remap = Vec::with_capacity(tiles.len());
+    let mut existing_tiles = BTreeMap::new();
+
+    for tile in tiles {
+        let h = hflipped(tile);
+        let v = vflipped(tile);
+        let hv = vflipped(&h);
+
+        // store the smallest of the variations, so that every flipped copy picks the same one.
+        // The flags flip the stored tile back into the original
+        let (canonical, flips) = [(*tile, 0), (h, HFLIP), (v, VFLIP), (hv, HFLIP | VFLIP)]
+            .into_iter()
+            .min_by_key(|&(variation, _)| variation)
+            .expect("there are 4 variations");
+
+        let index = *existing_tiles.entry(canonical).or_insert_with(|| {
+            unique_tiles.push(canonical);
+            unique_tiles.len() - 1
+        });
+
+        assert!(
+            index < MAX_TILES,
+            "there are more than {MAX_TILES} unique tiles"
+        );
+
+        remap.push(index as u16 | flips);
+    }
+
+    (unique_tiles, remap)
+}
+
+/// Changes each screen entry in `map` to use the tile given by `remap`, a table returned by
+/// [`pack_tileset`].
+///
+/// The palette of each entry is kept, and any flipping it already had is combined with the
+/// flipping from the table.
+///
+/// # Panics
+///
+/// Panics if an entry refers to a tile which isn't in `remap`.
+pub fn remap_tilemap(map: &mut [u16], remap: &[u16]) {
+    for entry in map {
+        let new = remap[usize::from(*entry & TILE_INDEX_MASK)];
+        *entry = (*entry & !TILE_INDEX_MASK) ^ new;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a diagonal line with a dot, so every flip is different
+    const TILE: [u32; 8] = [
+        0x0000_0001,
+        0x0000_0010,
+        0x0000_0100,
+        0x0000_1000,
+        0x0001_0000,
+        0x0010_0000,
+        0x0100_0000,
+        0x1000_0002,
+    ];
+
+    #[test]
+    fn flips_tiles() {
+        assert_eq!(hflipped(&TILE)[0], 0x1000_0000);
+        assert_eq!(hflipped(&TILE)[7], 0x2000_0001);
+        assert_eq!(hflipped(&hflipped(&TILE)), TILE);
+        assert_eq!(vflipped(&TILE)[0], 0x1000_0002);
+    }
+
+    #[test]
+    fn merges_flipped_copies() {
+        let other = [0x1111_1111; 8];
+
+        let tiles = [TILE, hflipped(&TILE), other, vflipped(&TILE), TILE, other];
+        let (unique, remap) = pack_tileset(&tiles);
+
+        assert_eq!(unique.len(), 2);
+        assert_eq!(remap.len(), tiles.len());
+        assert_eq!(remap[0], remap[4]);
+        assert_eq!(remap[2], remap[5]);
+
+        // flipping each stored tile by its entry's flags gives the original back
+        for (original, &entry) in tiles.iter().zip(&remap) {
+            let mut drawn = unique[usize::from(entry & TILE_INDEX_MASK)];
+            if entry & HFLIP != 0 {
+                drawn = hflipped(&drawn);
+            }
+            if entry & VFLIP != 0 {
+                drawn = vflipped(&drawn);
+            }
+
+            assert_eq!(&drawn, original);
+        }
+    }
+
+    #[test]
+    fn remaps_keeping_palette_and_flips() {
+        let (_, remap) = pack_tileset(&[TILE, hflipped(&TILE)]);
+
+        // palette 3, hflipped tile 1
+        let mut map = [0x3000 | HFLIP | 1, 0];
+        remap_tilemap(&mut map, &remap);
+        assert_eq!(map[0], 0x3000 | (remap[1] ^ HFLIP));
+        assert_eq!(map[1], remap[0]);
+    }
+}
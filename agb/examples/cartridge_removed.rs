@@ -0,0 +1,33 @@
+//! Shows a red screen instead of crashing when the cartridge is pulled out.
+#![no_std]
+#![no_main]
+
+// Everything this calls has to be in iwram, since rom is gone by the time it runs.
+#[link_section = ".iwram"]
+extern "C" fn cartridge_removed() -> ! {
+    unsafe {
+        // forced blank off, no backgrounds, so only the backdrop colour is shown
+        (0x0400_0000 as *mut u16).write_volatile(0);
+        (0x0500_0000 as *mut u16).write_volatile(0x001f);
+    }
+
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
+
+#[agb::entry]
+fn main(_gba: agb::Gba) -> ! {
+    unsafe { agb::interrupt::set_cartridge_removed_handler(Some(cartridge_removed)) };
+
+    let vblank = agb::interrupt::VBlank::get();
+    let mut frame = 0;
+
+    loop {
+        vblank.wait_for_vblank();
+
+        if frame % 60 == 0 {
+            agb::println!("Still running, pull the cartridge out to see the red screen");
+        }
+        frame += 1;
+    }
+}
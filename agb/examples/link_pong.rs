@@ -0,0 +1,137 @@
+//! Pong for two GBAs connected with a link cable, running the same ROM.
+//!
+//! The parent controls the left paddle and the child the right one. Every frame each GBA sends
+//! where its paddle is and both run the same game with the two paddles, so they stay in step.
+#![no_std]
+#![no_main]
+
+use agb::{
+    display::{bitmap3::Bitmap3, HEIGHT, WIDTH},
+    input::ButtonController,
+    net::multiplayer::{BaudRate, MultiPlayer, Role},
+};
+
+const PADDLE_WIDTH: i32 = 4;
+const PADDLE_HEIGHT: i32 = 24;
+const BALL_SIZE: i32 = 4;
+
+const WHITE: u16 = 0x7fff;
+const BLACK: u16 = 0;
+
+fn fill_rect(bitmap: &mut Bitmap3, x: i32, y: i32, width: i32, height: i32, colour: u16) {
+    for y in y.max(0)..(y + height).min(HEIGHT) {
+        for x in x.max(0)..(x + width).min(WIDTH) {
+            bitmap.draw_point(x, y, colour);
+        }
+    }
+}
+
+struct Game {
+    paddles: [i32; 2],
+    ball: (i32, i32),
+    velocity: (i32, i32),
+}
+
+impl Game {
+    fn new() -> Self {
+        Self {
+            paddles: [(HEIGHT - PADDLE_HEIGHT) / 2; 2],
+            ball: (WIDTH / 2, HEIGHT / 2),
+            velocity: (2, 1),
+        }
+    }
+
+    fn paddle_x(player: usize) -> i32 {
+        if player == 0 {
+            8
+        } else {
+            WIDTH - 8 - PADDLE_WIDTH
+        }
+    }
+
+    fn step(&mut self) {
+        let (mut x, mut y) = (self.ball.0 + self.velocity.0, self.ball.1 + self.velocity.1);
+
+        if y < 0 || y + BALL_SIZE > HEIGHT {
+            self.velocity.1 = -self.velocity.1;
+            y = self.ball.1;
+        }
+
+        for (player, &paddle) in self.paddles.iter().enumerate() {
+            let paddle_x = Self::paddle_x(player);
+            let hits = x < paddle_x + PADDLE_WIDTH
+                && x + BALL_SIZE > paddle_x
+                && y < paddle + PADDLE_HEIGHT
+                && y + BALL_SIZE > paddle;
+
+            if hits {
+                self.velocity.0 = -self.velocity.0;
+                x = self.ball.0;
+            }
+        }
+
+        if x < 0 || x + BALL_SIZE > WIDTH {
+            // someone missed, so serve again from the middle
+            x = WIDTH / 2;
+            y = HEIGHT / 2;
+            self.velocity.0 = -self.velocity.0;
+        }
+
+        self.ball = (x, y);
+    }
+
+    fn draw(&self, bitmap: &mut Bitmap3, colour: u16) {
+        for (player, &paddle) in self.paddles.iter().enumerate() {
+            let x = Self::paddle_x(player);
+            fill_rect(bitmap, x, paddle, PADDLE_WIDTH, PADDLE_HEIGHT, colour);
+        }
+
+        fill_rect(
+            bitmap,
+            self.ball.0,
+            self.ball.1,
+            BALL_SIZE,
+            BALL_SIZE,
+            colour,
+        );
+    }
+}
+
+#[agb::entry]
+fn main(mut gba: agb::Gba) -> ! {
+    let mut bitmap = gba.display.video.bitmap3();
+    let vblank = agb::interrupt::VBlank::get();
+    let mut input = ButtonController::new();
+
+    let mut link = MultiPlayer::new(BaudRate::B115200).expect("nothing else uses the serial port");
+    let mut game = Game::new();
+    let mut my_paddle = game.paddles[0];
+
+    loop {
+        vblank.wait_for_vblank();
+        input.update();
+
+        my_paddle = (my_paddle + input.y_tri() as i32 * 2).clamp(0, HEIGHT - PADDLE_HEIGHT);
+
+        match link.transfer(my_paddle as u16) {
+            Ok([Some(left), Some(right), ..]) => {
+                game.draw(&mut bitmap, BLACK);
+
+                game.paddles = [i32::from(left), i32::from(right)];
+                game.step();
+
+                game.draw(&mut bitmap, WHITE);
+            }
+            Ok(_) => {
+                // waiting for the other player
+            }
+            Err(error) => {
+                let role = match link.role() {
+                    Role::Parent => "parent",
+                    Role::Child => "child",
+                };
+                agb::println!("link error as the {}: {:?}", role, error);
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+use agb::{
+    display::{
+        example_logo,
+        tiled::{RegularBackgroundSize, TileFormat},
+    },
+    interrupt::{VBlank, VCount},
+};
+use portable_atomic::{AtomicU16, Ordering};
+
+const BG0_X_SCROLL: *mut u16 = 0x0400_0010 as *mut u16;
+const SPLIT_LINE: u8 = 80;
+
+static TOP_SCROLL: AtomicU16 = AtomicU16::new(0);
+static BOTTOM_SCROLL: AtomicU16 = AtomicU16::new(0);
+
+#[agb::entry]
+fn main(mut gba: agb::Gba) -> ! {
+    let (gfx, mut vram) = gba.display.video.tiled0();
+
+    let mut background = gfx.background(
+        agb::display::Priority::P0,
+        RegularBackgroundSize::Background32x32,
+        TileFormat::FourBpp,
+    );
+
+    example_logo::display_logo(&mut background, &mut vram);
+
+    // A single interrupt handles both halves of the screen by moving its own match line.
+    // Safety: doesn't allocate
+    let _split = unsafe {
+        VCount::add_handler(SPLIT_LINE, |_| {
+            let (scroll, next_line) = if VCount::line() == SPLIT_LINE {
+                (BOTTOM_SCROLL.load(Ordering::Relaxed), 0)
+            } else {
+                (TOP_SCROLL.load(Ordering::Relaxed), SPLIT_LINE)
+            };
+
+            BG0_X_SCROLL.write_volatile(scroll);
+            VCount::set_line(next_line);
+        })
+    };
+
+    let vblank = VBlank::get();
+    let mut frame: u16 = 0;
+
+    loop {
+        vblank.wait_for_vblank();
+        frame = frame.wrapping_add(1);
+
+        // scroll the top half one way and the bottom half the other
+        TOP_SCROLL.store(frame % 256, Ordering::Relaxed);
+        BOTTOM_SCROLL.store(0u16.wrapping_sub(frame) % 256, Ordering::Relaxed);
+    }
+}
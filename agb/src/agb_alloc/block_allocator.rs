@@ -10,6 +10,7 @@ use core::ptr::NonNull;
 
 use super::bump_allocator::{BumpAllocatorInner, StartEnd};
 use super::SendNonNull;
+use crate::mem::HeapStats;
 
 struct Block {
     size: usize,
@@ -43,9 +44,41 @@ struct BlockAllocatorState {
     first_free_block: Option<SendNonNull<Block>>,
 }
 
+/// Keeps track of how much is allocated, for [`HeapStats`]
+struct Usage {
+    allocated_bytes: usize,
+    peak_allocated_bytes: usize,
+    allocations: usize,
+    /// Every successful allocation or reallocation, including ones which have been freed
+    total_allocations: usize,
+    failed_allocation: Option<Layout>,
+}
+
+impl Usage {
+    fn record(&mut self, result: Option<NonNull<u8>>, layout: Layout) -> Option<NonNull<u8>> {
+        if result.is_some() {
+            self.allocated_bytes += layout.size();
+            self.peak_allocated_bytes = self.peak_allocated_bytes.max(self.allocated_bytes);
+            self.allocations += 1;
+            self.total_allocations += 1;
+            self.failed_allocation = None;
+        } else {
+            self.failed_allocation = Some(layout);
+        }
+
+        result
+    }
+
+    fn record_free(&mut self, layout: Layout) {
+        self.allocated_bytes -= layout.size();
+        self.allocations -= 1;
+    }
+}
+
 struct BlockAllocatorInner {
     inner_allocator: BumpAllocatorInner,
     state: BlockAllocatorState,
+    usage: Usage,
 }
 
 pub struct BlockAllocator {
@@ -72,11 +105,17 @@ impl BlockAllocator {
     }
 
     pub unsafe fn alloc(&self, layout: Layout) -> Option<NonNull<u8>> {
-        self.with_inner(|inner| inner.alloc(layout))
+        self.with_inner(|inner| {
+            let result = inner.alloc(layout);
+            inner.usage.record(result, layout)
+        })
     }
 
     pub unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        self.with_inner(|inner| inner.dealloc(ptr, layout));
+        self.with_inner(|inner| {
+            inner.dealloc(ptr, layout);
+            inner.usage.record_free(layout);
+        });
     }
 
     pub unsafe fn grow(
@@ -85,7 +124,50 @@ impl BlockAllocator {
         layout: Layout,
         new_layout: Layout,
     ) -> Option<NonNull<u8>> {
-        self.with_inner(|inner| inner.grow(ptr, layout, new_layout))
+        self.with_inner(|inner| {
+            let result = inner.grow(ptr, layout, new_layout);
+            if result.is_some() {
+                inner.usage.record_free(layout);
+            }
+            inner.usage.record(result, new_layout)
+        })
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        unsafe {
+            self.with_inner(|inner| HeapStats {
+                allocated_bytes: inner.usage.allocated_bytes,
+                peak_allocated_bytes: inner.usage.peak_allocated_bytes,
+                allocations: inner.usage.allocations,
+                free_bytes: inner.free_bytes(),
+                largest_free_block: inner.largest_free_block(),
+            })
+        }
+    }
+
+    /// The layout of the last allocation, if it failed and nothing has been allocated since
+    pub fn failed_allocation(&self) -> Option<Layout> {
+        unsafe { self.with_inner(|inner| inner.usage.failed_allocation) }
+    }
+
+    /// The number of successful allocations and reallocations since the game started
+    #[cfg(any(test, feature = "testing"))]
+    pub fn total_allocations(&self) -> usize {
+        unsafe { self.with_inner(|inner| inner.usage.total_allocations) }
+    }
+
+    /// Starts measuring the peak usage again from what is allocated now
+    #[cfg(any(test, feature = "testing"))]
+    pub fn reset_peak(&self) {
+        unsafe {
+            self.with_inner(|inner| inner.usage.peak_allocated_bytes = inner.usage.allocated_bytes);
+        }
+    }
+
+    /// The address after the furthest the heap has reached, which nothing above is allocated from
+    #[cfg(any(test, feature = "testing"))]
+    pub fn used_end(&self) -> usize {
+        unsafe { self.with_inner(|inner| inner.inner_allocator.tip_or_start()) }
     }
 }
 
@@ -96,7 +178,45 @@ impl BlockAllocatorInner {
             state: BlockAllocatorState {
                 first_free_block: None,
             },
+            usage: Usage {
+                allocated_bytes: 0,
+                peak_allocated_bytes: 0,
+                allocations: 0,
+                total_allocations: 0,
+                failed_allocation: None,
+            },
+        }
+    }
+
+    /// The total size of every free block and the space left for the bump allocator
+    fn free_bytes(&self) -> usize {
+        let mut free = self.inner_allocator.remaining();
+
+        let mut block = self.state.first_free_block;
+        while let Some(current) = block {
+            // Safety: blocks in the free list are valid until they are allocated
+            let current = unsafe { current.as_ref() };
+            free += current.size;
+            block = current.next;
+        }
+
+        free
+    }
+
+    /// The size of the largest single allocation which could currently succeed, ignoring the
+    /// space needed for alignment and the block header
+    fn largest_free_block(&self) -> usize {
+        let mut largest = self.inner_allocator.remaining();
+
+        let mut block = self.state.first_free_block;
+        while let Some(current) = block {
+            // Safety: blocks in the free list are valid until they are allocated
+            let current = unsafe { current.as_ref() };
+            largest = largest.max(current.size);
+            block = current.next;
         }
+
+        largest
     }
 
     /// Requests a brand new block from the inner bump allocator
@@ -25,6 +25,17 @@ impl BumpAllocatorInner {
         self.current_ptr.map(|x| x.0)
     }
 
+    /// The address of the tip, or the start of the region if nothing has been allocated
+    pub fn tip_or_start(&self) -> usize {
+        self.current_ptr
+            .map_or_else(self.start_end.start, |c| c.as_ptr() as usize)
+    }
+
+    /// The number of bytes between the tip and the end of the region
+    pub fn remaining(&self) -> usize {
+        (self.start_end.end)().saturating_sub(self.tip_or_start())
+    }
+
     pub fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
         let current_ptr = &mut self.current_ptr;
 
@@ -32,6 +32,7 @@ impl<T> DerefMut for SendNonNull<T> {
 }
 
 const EWRAM_END: usize = 0x0204_0000;
+const IWRAM_START: usize = 0x0300_0000;
 const IWRAM_END: usize = 0x0300_8000;
 
 #[global_allocator]
@@ -42,6 +43,38 @@ static GLOBAL_ALLOC: BlockAllocator = unsafe {
     })
 };
 
+pub(crate) fn ewram_heap_stats() -> crate::mem::HeapStats {
+    GLOBAL_ALLOC.stats()
+}
+
+pub(crate) fn iwram_heap_stats() -> crate::mem::HeapStats {
+    __IWRAM_ALLOC.stats()
+}
+
+/// The number of allocations made in either heap since the game started
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn total_allocations() -> usize {
+    GLOBAL_ALLOC.total_allocations() + __IWRAM_ALLOC.total_allocations()
+}
+
+/// Starts measuring the peak usage of both heaps again from what is allocated now
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn reset_peak_heap_usage() {
+    GLOBAL_ALLOC.reset_peak();
+    __IWRAM_ALLOC.reset_peak();
+}
+
+/// The address after the furthest the iwram heap has reached, where the free space for the
+/// stack starts
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn iwram_heap_end() -> usize {
+    __IWRAM_ALLOC.used_end()
+}
+
+pub(crate) fn failed_ewram_allocation() -> Option<core::alloc::Layout> {
+    GLOBAL_ALLOC.failed_allocation()
+}
+
 macro_rules! impl_zst_allocator {
     ($name_of_struct: ty, $name_of_static: ident) => {
         unsafe impl core::alloc::Allocator for $name_of_struct {
@@ -113,6 +146,60 @@ pub struct InternalAllocator;
 
 impl_zst_allocator!(InternalAllocator, __IWRAM_ALLOC);
 
+/// An allocator which puts things in Internal Working Ram if there is space, and in External
+/// Working Ram otherwise.
+///
+/// Use this for data which would benefit from being in the faster internal working ram but
+/// which will still work if it ends up in external working ram. If the data has to be in
+/// internal working ram, use [`InternalAllocator`] and handle the error from methods like
+/// [`Box::try_new_in`](alloc::boxed::Box::try_new_in) instead.
+///
+/// ```rust,no_run
+/// #![feature(allocator_api)]
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::PreferInternalAllocator;
+/// # extern crate alloc;
+/// # use alloc::vec::Vec;
+/// # fn foo(gba: &mut agb::Gba) {
+/// let mut collision_grid = Vec::with_capacity_in(32 * 32, PreferInternalAllocator);
+/// collision_grid.resize(32 * 32, 0u8);
+///
+/// if !PreferInternalAllocator::is_internal(collision_grid.as_ptr()) {
+///     agb::println!("collision grid didn't fit in iwram");
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PreferInternalAllocator;
+
+impl PreferInternalAllocator {
+    /// Whether memory allocated by this allocator ended up in internal working ram.
+    #[must_use]
+    pub fn is_internal<T>(ptr: *const T) -> bool {
+        (IWRAM_START..IWRAM_END).contains(&(ptr as usize))
+    }
+}
+
+unsafe impl core::alloc::Allocator for PreferInternalAllocator {
+    fn allocate(
+        &self,
+        layout: core::alloc::Layout,
+    ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        __IWRAM_ALLOC
+            .allocate(layout)
+            .or_else(|_| GLOBAL_ALLOC.allocate(layout))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: core::alloc::Layout) {
+        if Self::is_internal(ptr.as_ptr()) {
+            __IWRAM_ALLOC.deallocate(ptr, layout);
+        } else {
+            GLOBAL_ALLOC.deallocate(ptr, layout);
+        }
+    }
+}
+
 static __IWRAM_ALLOC: BlockAllocator = unsafe {
     BlockAllocator::new(StartEnd {
         start: iwram_data_end,
@@ -120,7 +207,7 @@ static __IWRAM_ALLOC: BlockAllocator = unsafe {
     })
 };
 
-fn iwram_data_end() -> usize {
+pub(crate) fn iwram_data_end() -> usize {
     extern "C" {
         static __iwram_end: u8;
     }
@@ -130,7 +217,7 @@ fn iwram_data_end() -> usize {
     core::ptr::addr_of!(__iwram_end) as usize
 }
 
-fn data_end() -> usize {
+pub(crate) fn data_end() -> usize {
     extern "C" {
         static __ewram_data_end: u8;
     }
@@ -243,6 +330,19 @@ mod test {
         );
     }
 
+    #[test_case]
+    fn prefer_internal_falls_back_to_ewram(_gba: &mut crate::Gba) {
+        let small = Box::new_in(1, PreferInternalAllocator);
+        assert!(PreferInternalAllocator::is_internal(&*small));
+
+        // more than the whole of iwram
+        let large = Vec::<u8, _>::with_capacity_in(40 * 1024, PreferInternalAllocator);
+        assert!(!PreferInternalAllocator::is_internal(large.as_ptr()));
+        assert!((EWRAM_START..EWRAM_END).contains(&(large.as_ptr() as usize)));
+
+        assert!(Vec::<u8, _>::try_with_capacity_in(40 * 1024, InternalAllocator).is_err());
+    }
+
     #[test_case]
     fn benchmark_allocation(_gba: &mut crate::Gba) {
         let mut stored: Vec<Vec<u8>> = Vec::new();
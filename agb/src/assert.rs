@@ -0,0 +1,111 @@
+//! Assertions which log a backtrace before panicking, see [the backtrace
+//! module](crate::backtrace#assertions). Without the `backtrace` feature they panic like the
+//! ones in `core`.
+
+use core::fmt;
+
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn assert_failed(message: fmt::Arguments) -> ! {
+    #[cfg(feature = "backtrace")]
+    {
+        use crate::backtrace::{capture, display_addresses};
+
+        let mut addresses = [0; 16];
+        let len = capture(&mut addresses);
+        crate::log_error!("backtrace: {}", display_addresses(&addresses[..len]));
+    }
+
+    panic!("{message}");
+}
+
+/// Asserts that a condition is true, like [`assert!`], but with the `backtrace` feature also logs
+/// the addresses from `agb::backtrace::capture` to mgba if it fails. See the `backtrace` module.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(health: i32) {
+/// agb::agb_assert!(health >= 0, "health went negative: {health}");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! agb_assert {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            $crate::__assert_failed(format_args!(concat!(
+                "assertion failed: ",
+                stringify!($cond)
+            )));
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::__assert_failed(format_args!($($arg)+));
+        }
+    };
+}
+
+/// Asserts that two values are equal, like [`assert_eq!`], but with the `backtrace` feature
+/// also logs the addresses from `agb::backtrace::capture` to mgba if they aren't. See
+/// [`agb_assert!`](crate::agb_assert).
+#[macro_export]
+macro_rules! agb_assert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::__assert_failed(format_args!(
+                        "assertion `left == right` failed\n  left: {:?}\n right: {:?}",
+                        left, right
+                    ));
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::__assert_failed(format_args!(
+                        "assertion `left == right` failed: {}\n  left: {:?}\n right: {:?}",
+                        format_args!($($arg)+), left, right
+                    ));
+                }
+            }
+        }
+    };
+}
+
+/// Asserts that two values aren't equal, like [`assert_ne!`], but with the `backtrace` feature
+/// also logs the addresses from `agb::backtrace::capture` to mgba if they are. See
+/// [`agb_assert!`](crate::agb_assert).
+#[macro_export]
+macro_rules! agb_assert_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::__assert_failed(format_args!(
+                        "assertion `left != right` failed\n  left: {:?}\n right: {:?}",
+                        left, right
+                    ));
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::__assert_failed(format_args!(
+                        "assertion `left != right` failed: {}\n  left: {:?}\n right: {:?}",
+                        format_args!($($arg)+), left, right
+                    ));
+                }
+            }
+        }
+    };
+}
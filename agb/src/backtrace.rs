@@ -1,6 +1,60 @@
-use core::{arch::asm, ops::Index};
+//! Finding out how the game got to where it is, for diagnosing bugs.
+//!
+//! When the game panics with the `backtrace` feature enabled, the crash screen shows the
+//! addresses of the functions which were running, found by following the chain of frame
+//! pointers. [`capture`] gets similar addresses at any other time, for example from a watchdog
+//! which notices the game has got stuck, and writes them to a buffer so they can be logged to
+//! mgba or kept for later. They can be turned back into function names and line numbers with
+//! `addr2line` or `agb-debug`.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! let mut addresses = [0; 16];
+//! let len = agb::backtrace::capture(&mut addresses);
+//!
+//! agb::log_error!(
+//!     "stuck at {}",
+//!     agb::backtrace::display_addresses(&addresses[..len])
+//! );
+//! # }
+//! ```
+//!
+//! # Limitations
+//!
+//! agb games are built without unwind tables, so [`capture`] can't know where each stack frame
+//! starts. Instead it looks at every word on the stack and keeps the ones which look like return
+//! addresses: an odd address, as used for THUMB code, in ROM or in the code copied to IWRAM or
+//! EWRAM, straight after a `bl` instruction. This means that:
+//!
+//! * values left on the stack by functions which have already returned also look like return
+//!   addresses, so there can be extra entries which aren't part of the current call chain;
+//! * functions which were inlined, or which called onwards without saving the link register
+//!   (leaf functions and tail calls), don't appear;
+//! * calls from ARM code are missed, since the return addresses are even;
+//! * the addresses are where each call returns to, so they point just past the call rather than
+//!   at it.
+//!
+//! So treat the result as a list of places which were probably involved rather than an exact
+//! call stack. The innermost entries are the most reliable.
+//!
+//! # Assertions
+//!
+//! [`agb_assert!`](crate::agb_assert), [`agb_assert_eq!`](crate::agb_assert_eq) and
+//! [`agb_assert_ne!`](crate::agb_assert_ne) work like the ones in `core`, but when they fail
+//! they log the result of [`capture`] to mgba before panicking, so the crash screen comes up
+//! as usual and there is a second, frame pointer independent, trace in the log. The panic
+//! location is that of the assertion, or of the caller of a `#[track_caller]` function which
+//! contains it.
 
-use alloc::vec::Vec;
+use core::{arch::asm, fmt, ops::Index};
+
+use crate::collections::ArrayVec;
+
+/// The most frames which are recorded. This bounds the walk up the stack, so a corrupted frame
+/// pointer can't send it round in circles, and is more than fits in the QR code anyway.
+const MAX_FRAMES: usize = 32;
 
 // only works for code compiled as THUMB
 #[repr(C)]
@@ -9,10 +63,35 @@ struct Context {
     registers: [u32; 11],
 }
 
-pub struct Frames {
-    frames: Vec<u32>,
+/// Marks the end of the encoded frames, and which version of the encoding they use.
+pub(crate) const FORMAT_MARKER: &str = "v1";
+
+pub(crate) struct Frames {
+    frames: ArrayVec<u32, MAX_FRAMES>,
 }
 
+impl Frames {
+    /// The return address of each frame, innermost first
+    pub(crate) fn addresses(&self) -> &[u32] {
+        &self.frames
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_addresses(addresses: &[u32]) -> Self {
+        Self {
+            frames: addresses.iter().copied().collect(),
+        }
+    }
+
+    /// The encoded frames without the [`FORMAT_MARKER`] after them, for when it needs to be
+    /// added separately.
+    pub(crate) fn encoded_frames(&self) -> EncodedFrames<'_> {
+        EncodedFrames(self)
+    }
+}
+
+pub(crate) struct EncodedFrames<'a>(&'a Frames);
+
 #[allow(unused)]
 enum Register {
     R0,
@@ -67,7 +146,7 @@ pub(crate) fn unwind_exception() -> Frames {
 
     let mut frame_pointer = context[Register::FP];
 
-    let mut frames = Vec::new();
+    let mut frames = ArrayVec::new();
 
     loop {
         let sp = unsafe { *(frame_pointer as *const u32) };
@@ -80,7 +159,9 @@ pub(crate) fn unwind_exception() -> Frames {
         // need to subtract 2 here since the link register points to the _next_ instruction
         // to execute, not the one that is being branched from which is the one we care about
         // in the stack trace.
-        frames.push(lr - 2);
+        if frames.try_push(lr - 2).is_err() {
+            break;
+        }
 
         frame_pointer = sp;
     }
@@ -88,9 +169,9 @@ pub(crate) fn unwind_exception() -> Frames {
     Frames { frames }
 }
 
-impl core::fmt::Display for Frames {
+impl core::fmt::Display for EncodedFrames<'_> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        for frame in &self.frames {
+        for frame in &self.0.frames {
             if frame & 0xFFFF_0000 == 0x0800_0000 {
                 let frame = *frame as u16; // intentionally truncate
                 let frame_encoded = gwilym_encoding::encode_16(frame);
@@ -105,10 +186,105 @@ impl core::fmt::Display for Frames {
             }
         }
 
-        write!(f, "v1")
+        Ok(())
     }
 }
 
+impl core::fmt::Display for Frames {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{FORMAT_MARKER}", self.encoded_frames())
+    }
+}
+
+/// The top of the stack used by the game, set up by the BIOS.
+const USER_STACK_TOP: usize = 0x0300_7f00;
+/// The top of the stack used while handling interrupts, just above the game's stack.
+const IRQ_STACK_TOP: usize = 0x0300_7fa0;
+
+const ROM: core::ops::Range<u32> = 0x0800_0000..0x0a00_0000;
+const IWRAM_START: u32 = 0x0300_0000;
+const EWRAM_START: u32 = 0x0200_0000;
+
+/// Whether `addr` is inside code which could have been running.
+fn is_code(addr: u32) -> bool {
+    ROM.contains(&addr)
+        || (IWRAM_START..crate::agb_alloc::iwram_data_end() as u32).contains(&addr)
+        || (EWRAM_START..crate::agb_alloc::data_end() as u32).contains(&addr)
+}
+
+/// Whether `value` looks like the return address of a call from THUMB code.
+fn is_return_address(value: u32) -> bool {
+    // return addresses into THUMB code have the bottom bit set
+    if value & 1 == 0 {
+        return false;
+    }
+
+    let call = (value & !1).wrapping_sub(2);
+    if !is_code(call) {
+        return false;
+    }
+
+    // the second half of a `bl` instruction is 0b11111 followed by the low bits of the offset
+    let instruction = unsafe { (call as *const u16).read_volatile() };
+    instruction & 0xf800 == 0xf800
+}
+
+/// Scans the stack for return addresses and writes them to `buffer`, innermost first, returning
+/// how many were found. The first is the address this was called from. Stops once `buffer` is
+/// full.
+///
+/// This works whether or not the game was built with frame pointers, but can give addresses
+/// which aren't part of the current call chain. See the [module level
+/// documentation](self#limitations).
+#[inline(never)]
+pub fn capture(buffer: &mut [u32]) -> usize {
+    let sp: usize;
+    unsafe { asm!("mov {}, sp", out(reg) sp) };
+
+    let top = if sp < USER_STACK_TOP {
+        USER_STACK_TOP
+    } else {
+        IRQ_STACK_TOP
+    };
+
+    let mut found = 0;
+    for slot in (sp..top).step_by(4) {
+        if found == buffer.len() {
+            break;
+        }
+
+        let value = unsafe { (slot as *const u32).read_volatile() };
+        if is_return_address(value) {
+            buffer[found] = value & !1;
+            found += 1;
+        }
+    }
+
+    found
+}
+
+/// Formats `addresses` in hex, separated by spaces, for printing with [`println!`](crate::println)
+/// or the logging macros.
+#[must_use]
+pub fn display_addresses(addresses: &[u32]) -> impl fmt::Display + '_ {
+    struct Addresses<'a>(&'a [u32]);
+
+    impl fmt::Display for Addresses<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for (i, address) in self.0.iter().enumerate() {
+                if i != 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{address:08x}")?;
+            }
+
+            Ok(())
+        }
+    }
+
+    Addresses(addresses)
+}
+
 mod gwilym_encoding {
     static ALPHABET: &[u8] = b"0123456789=ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
 
@@ -135,3 +311,37 @@ mod gwilym_encoding {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[inline(never)]
+    fn nested(depth: u32, buffer: &mut [u32]) -> usize {
+        if depth == 0 {
+            capture(buffer)
+        } else {
+            // stop this being turned into a loop or a tail call
+            core::hint::black_box(nested(depth - 1, buffer))
+        }
+    }
+
+    #[test_case]
+    fn captures_return_addresses_in_rom(_gba: &mut crate::Gba) {
+        let mut addresses = [0; 32];
+        let len = nested(3, &mut addresses);
+
+        assert!(len >= 4, "only found {len} return addresses");
+        assert!(addresses[..len]
+            .iter()
+            .all(|&address| is_code(address) && address & 1 == 0));
+
+        // a short buffer is filled with the innermost addresses
+        let mut short = [0; 2];
+        assert_eq!(nested(3, &mut short), 2);
+
+        agb_assert_eq!(1 + 1, 2);
+        agb_assert_ne!(1, 2, "numbers should differ");
+        agb_assert!(len > 0);
+    }
+}
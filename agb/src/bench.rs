@@ -0,0 +1,153 @@
+//! Benchmarks which run alongside the tests, declared with [`#[agb::bench]`](crate::bench).
+
+use alloc::vec::Vec;
+use core::hint::black_box;
+
+use crate::{
+    mgba,
+    test_runner::Testable,
+    timer::{Divider, MonotonicClock, Timer, Timers},
+    Gba,
+};
+
+/// How many times the routine is timed unless the benchmark asks for something else.
+const DEFAULT_ITERATIONS: usize = 31;
+/// How many times the routine is run before timing starts, so that caches, lazily initialised
+/// statics and allocators are in their usual state.
+const WARM_UP_ITERATIONS: usize = 3;
+
+/// A benchmark for the test runner, made by [`#[agb::bench]`](crate::bench).
+pub struct Bench {
+    name: &'static str,
+    f: fn(&mut Gba, &mut Bencher),
+}
+
+impl Bench {
+    /// A benchmark called `name` which runs `f`. Usually made by
+    /// [`#[agb::bench]`](crate::bench) rather than by hand.
+    #[must_use]
+    pub const fn new(name: &'static str, f: fn(&mut Gba, &mut Bencher)) -> Self {
+        Self { name, f }
+    }
+}
+
+impl Testable for Bench {
+    fn run(&self, gba: &mut Gba) {
+        let mut bencher = Bencher {
+            iterations: DEFAULT_ITERATIONS,
+            median_cycles: None,
+        };
+
+        (self.f)(gba, &mut bencher);
+
+        let median_cycles = bencher
+            .median_cycles
+            .unwrap_or_else(|| panic!("benchmark {} never called Bencher::iter", self.name));
+
+        // read by the test runner, which compares it against the baseline
+        let mut mgba = mgba::Mgba::new().unwrap();
+        mgba.print(
+            format_args!(
+                "bench:{} median={median_cycles} iterations={}",
+                self.name, bencher.iterations
+            ),
+            mgba::DebugLevel::Info,
+        )
+        .unwrap();
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+/// Times the code being benchmarked, passed to each [`#[agb::bench]`](crate::bench) function.
+pub struct Bencher {
+    iterations: usize,
+    median_cycles: Option<u64>,
+}
+
+impl Bencher {
+    /// Sets how many times the routine is timed. The default is 31, which is plenty for code
+    /// which takes the same time every run. Use an odd number so the median is one of the runs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `iterations` is 0.
+    pub fn set_iterations(&mut self, iterations: usize) {
+        assert!(iterations > 0, "a benchmark needs at least one iteration");
+        self.iterations = iterations;
+    }
+
+    /// Times `routine`, reporting the median number of cycles it took.
+    ///
+    /// The routine is run a few times first to warm up, then timed each time it is run. The
+    /// time it takes to read the timers is measured the same way and taken off the result.
+    /// Whatever `routine` returns is passed through [`black_box`] so the work isn't optimised
+    /// away, and is dropped after the timing stops.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there aren't two adjacent timers free to time the routine with.
+    pub fn iter<T>(&mut self, mut routine: impl FnMut() -> T) {
+        self.iter_with_setup(|| (), |()| routine());
+    }
+
+    /// Like [`iter`](Bencher::iter), but calls `setup` before each run of `routine` and only
+    /// times `routine`. Use this when each run needs fresh input, or needs to wait for
+    /// something such as vblank first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there aren't two adjacent timers free to time the routine with.
+    pub fn iter_with_setup<S, T>(
+        &mut self,
+        mut setup: impl FnMut() -> S,
+        mut routine: impl FnMut(S) -> T,
+    ) {
+        for _ in 0..WARM_UP_ITERATIONS {
+            black_box(routine(black_box(setup())));
+        }
+
+        let clock = claim_clock();
+
+        let overhead = median((0..self.iterations).map(|_| {
+            let stopwatch = clock.stopwatch();
+            black_box(());
+            stopwatch.elapsed_ticks()
+        }));
+
+        let ticks = median((0..self.iterations).map(|_| {
+            let input = black_box(setup());
+
+            let stopwatch = clock.stopwatch();
+            let output = black_box(routine(input));
+            let ticks = stopwatch.elapsed_ticks();
+
+            drop(output);
+            ticks
+        }));
+
+        self.median_cycles = Some(clock.ticks_to_cycles(ticks.saturating_sub(overhead)));
+    }
+}
+
+/// A clock counting every cycle, on the first pair of adjacent timers which are free.
+fn claim_clock() -> MonotonicClock {
+    let available: Vec<u16> = Timers::available().collect();
+    let low = (0..3)
+        .find(|low| available.contains(low) && available.contains(&(low + 1)))
+        .expect("benchmarks need two adjacent timers to be free");
+
+    MonotonicClock::new(
+        Timer::claim_or_panic(low, "benchmark"),
+        Timer::claim_or_panic(low + 1, "benchmark"),
+        Divider::Divider1,
+    )
+}
+
+fn median(samples: impl Iterator<Item = u64>) -> u64 {
+    let mut samples: Vec<_> = samples.collect();
+    samples.sort_unstable();
+    samples[samples.len() / 2]
+}
@@ -4,7 +4,7 @@ pub struct Bitarray<const N: usize> {
 }
 
 impl<const N: usize> Bitarray<N> {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Bitarray { a: [0; N] }
     }
 
@@ -0,0 +1,218 @@
+use core::{
+    fmt::{self, Debug, Display},
+    ops::Deref,
+};
+
+use super::CapacityError;
+
+/// A string which can hold up to `N` bytes of UTF-8, stored inline without allocating.
+///
+/// It implements [`core::fmt::Write`], so it can be the target of [`write!`]. A write which
+/// doesn't fit returns an error and leaves the string as it was before that piece.
+#[derive(Clone, Copy)]
+pub struct ArrayString<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayString<N> {
+    /// Creates an empty string.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    /// The length of the string in bytes.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the string is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of bytes the string can hold, which is always `N`.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of bytes which can be added before the string is full.
+    #[must_use]
+    pub const fn remaining_capacity(&self) -> usize {
+        N - self.len
+    }
+
+    /// Adds `s` to the end of the string, or gives it back in the error if there isn't room for
+    /// all of it.
+    pub fn try_push_str<'a>(&mut self, s: &'a str) -> Result<(), CapacityError<&'a str>> {
+        if s.len() > self.remaining_capacity() {
+            return Err(CapacityError::new(s));
+        }
+
+        self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+
+    /// Adds `s` to the end of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't room for all of `s`, see
+    /// [`try_push_str`](ArrayString::try_push_str) for a version which doesn't.
+    pub fn push_str(&mut self, s: &str) {
+        assert!(self.try_push_str(s).is_ok(), "ArrayString is full");
+    }
+
+    /// Adds `c` to the end of the string, or gives it back in the error if there isn't room.
+    pub fn try_push(&mut self, c: char) -> Result<(), CapacityError<char>> {
+        self.try_push_str(c.encode_utf8(&mut [0; 4]))
+            .map_err(|_| CapacityError::new(c))
+    }
+
+    /// Adds `c` to the end of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't room for `c`.
+    pub fn push(&mut self, c: char) {
+        assert!(self.try_push(c).is_ok(), "ArrayString is full");
+    }
+
+    /// Removes the last character and returns it, or [`None`] if the string is empty.
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.chars().next_back()?;
+        self.len -= c.len_utf8();
+        Some(c)
+    }
+
+    /// Shortens the string to `len` bytes. Does nothing if the string is already that short.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` isn't on a character boundary.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            assert!(
+                self.is_char_boundary(len),
+                "truncating to {len} would split a character"
+            );
+            self.len = len;
+        }
+    }
+
+    /// Removes everything from the string.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// The contents of the string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        // Safety: only whole strs and chars are ever added, and truncation keeps to character
+        // boundaries, so the bytes are always valid UTF-8
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const N: usize> TryFrom<&'a str> for ArrayString<N> {
+    type Error = CapacityError<&'a str>;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        let mut string = Self::new();
+        string.try_push_str(s)?;
+        Ok(string)
+    }
+}
+
+impl<const N: usize> Deref for ArrayString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for ArrayString<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for ArrayString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl<const N: usize> Display for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> Debug for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize, const M: usize> PartialEq<ArrayString<M>> for ArrayString<N> {
+    fn eq(&self, other: &ArrayString<M>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> PartialEq<str> for ArrayString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for ArrayString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> Eq for ArrayString<N> {}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write;
+
+    use super::*;
+
+    #[test_case]
+    fn writes_until_full(_gba: &mut crate::Gba) {
+        let mut s = ArrayString::<8>::new();
+
+        write!(s, "{}-{}", 12, 34).unwrap();
+        assert_eq!(s, "12-34");
+        assert!(write!(s, "{}", 5678).is_err());
+        assert_eq!(s, "12-34");
+
+        assert_eq!(s.try_push_str("abcd").unwrap_err().into_inner(), "abcd");
+        s.push('é');
+        assert_eq!(s.len(), 7);
+        assert!(s.try_push('é').is_err());
+
+        assert_eq!(s.pop(), Some('é'));
+        s.truncate(2);
+        assert_eq!(s.as_str(), "12");
+        assert!(ArrayString::<2>::try_from("abc").is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use core::{
+    fmt::{self, Debug},
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+use super::CapacityError;
+
+/// A vector which can hold up to `N` elements, stored inline without allocating.
+///
+/// It dereferences to a slice, so everything you can do with a slice, such as sorting or
+/// iterating, works on it too.
+pub struct ArrayVec<T, const N: usize> {
+    data: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Creates an empty vector.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            data: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    /// The number of elements in the vector.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector contains no elements.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the vector contains `N` elements, so nothing more can be added.
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// The number of elements the vector can hold, which is always `N`.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of elements which can be added before the vector is full.
+    #[must_use]
+    pub const fn remaining_capacity(&self) -> usize {
+        N - self.len
+    }
+
+    /// Adds `value` to the end of the vector, or gives it back in the error if the vector is
+    /// full.
+    pub fn try_push(&mut self, value: T) -> Result<(), CapacityError<T>> {
+        if self.is_full() {
+            return Err(CapacityError::new(value));
+        }
+
+        self.data[self.len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Adds `value` to the end of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector is full, see [`try_push`](ArrayVec::try_push) for a version
+    /// which doesn't.
+    pub fn push(&mut self, value: T) {
+        assert!(self.try_push(value).is_ok(), "ArrayVec is full");
+    }
+
+    /// Inserts `value` at `index`, moving everything after it along by one. Gives the value
+    /// back in the error if the vector is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is more than the length of the vector.
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), CapacityError<T>> {
+        assert!(
+            index <= self.len,
+            "insertion index {index} is out of bounds for length {}",
+            self.len
+        );
+
+        if self.is_full() {
+            return Err(CapacityError::new(value));
+        }
+
+        // Safety: there is space for one more element, and everything being moved is initialised
+        unsafe {
+            let position = self.as_mut_ptr().add(index);
+            ptr::copy(position, position.add(1), self.len - index);
+            position.write(value);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Inserts `value` at `index`, moving everything after it along by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector is full or if `index` is more than the length of the vector.
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(self.try_insert(index, value).is_ok(), "ArrayVec is full");
+    }
+
+    /// Removes the last element and returns it, or [`None`] if the vector is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.len -= 1;
+        // Safety: the element was initialised, and is no longer counted by len
+        Some(unsafe { self.data[self.len].assume_init_read() })
+    }
+
+    /// Removes the element at `index` and returns it, moving everything after it back by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len,
+            "removal index {index} is out of bounds for length {}",
+            self.len
+        );
+
+        // Safety: the element at index is initialised, and the ones after it are moved over it
+        unsafe {
+            let position = self.as_mut_ptr().add(index);
+            let value = position.read();
+            ptr::copy(position.add(1), position, self.len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the last element. This
+    /// doesn't keep the order, but is faster than [`remove`](ArrayVec::remove).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let last = self.len - 1;
+        self.swap(index, last);
+        self.pop()
+            .expect("the vector can't be empty after the swap")
+    }
+
+    /// Shortens the vector to `len` elements, dropping the rest. Does nothing if the vector is
+    /// already that short.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        let old_len = self.len;
+        // set the length first so that a panicking drop can't cause a double drop
+        self.len = len;
+
+        // Safety: the elements between len and old_len are initialised and no longer counted
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.as_mut_ptr().add(len),
+                old_len - len,
+            ));
+        }
+    }
+
+    /// Removes every element.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Keeps only the elements for which `f` returns `true`, in their original order.
+    pub fn retain(&mut self, mut f: impl FnMut(&T) -> bool) {
+        let mut index = 0;
+        while index < self.len {
+            if f(&self[index]) {
+                index += 1;
+            } else {
+                self.remove(index);
+            }
+        }
+    }
+
+    /// The elements of the vector as a slice.
+    #[must_use]
+    pub fn as_slice(&self) -> &[T] {
+        // Safety: the first len elements are initialised
+        unsafe { core::slice::from_raw_parts(self.data.as_ptr().cast(), self.len) }
+    }
+
+    /// The elements of the vector as a mutable slice.
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // Safety: the first len elements are initialised
+        unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr().cast()
+    }
+}
+
+impl<T: Clone, const N: usize> ArrayVec<T, N> {
+    /// Adds a clone of every element of `values` to the end of the vector. Adds nothing if
+    /// there isn't room for all of them.
+    pub fn try_extend_from_slice(&mut self, values: &[T]) -> Result<(), CapacityError> {
+        if values.len() > self.remaining_capacity() {
+            return Err(CapacityError::new(()));
+        }
+
+        for value in values {
+            self.push(value.clone());
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for ArrayVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize, const M: usize> PartialEq<ArrayVec<T, M>> for ArrayVec<T, N> {
+    fn eq(&self, other: &ArrayVec<T, M>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq<[T]> for ArrayVec<T, N> {
+    fn eq(&self, other: &[T]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+/// Collects the elements of an iterator.
+///
+/// # Panics
+///
+/// Panics if the iterator yields more than `N` elements.
+impl<T, const N: usize> FromIterator<T> for ArrayVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+/// Adds the elements of an iterator to the end of the vector.
+///
+/// # Panics
+///
+/// Panics if the vector fills up before the iterator is finished.
+impl<T, const N: usize> Extend<T> for ArrayVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> IntoIterator for ArrayVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            index: 0,
+            vec: self,
+        }
+    }
+}
+
+/// An iterator which moves the elements out of an [`ArrayVec`], created by its
+/// [`into_iter`](IntoIterator::into_iter) method.
+pub struct IntoIter<T, const N: usize> {
+    // the elements before index have already been moved out
+    index: usize,
+    vec: ArrayVec<T, N>,
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.index == self.vec.len {
+            return None;
+        }
+
+        self.index += 1;
+        // Safety: the element is initialised, and index now stops it being read or dropped again
+        Some(unsafe { self.vec.data[self.index - 1].assume_init_read() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        let (index, len) = (self.index, self.vec.len);
+        // the vector mustn't drop the elements which have been moved out
+        self.vec.len = 0;
+
+        // Safety: the elements between index and len haven't been moved out yet
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.vec.as_mut_ptr().add(index),
+                len - index,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test_case]
+    fn push_fails_when_full(_gba: &mut crate::Gba) {
+        let mut vec = ArrayVec::<u8, 3>::new();
+
+        vec.push(1);
+        vec.insert(0, 0);
+        assert_eq!(vec.try_push(3), Ok(()));
+        assert!(vec.is_full());
+        assert_eq!(vec.try_push(4).unwrap_err().into_inner(), 4);
+        assert_eq!(vec.try_insert(1, 5).unwrap_err().into_inner(), 5);
+
+        assert_eq!(vec.as_slice(), &[0, 1, 3]);
+        assert_eq!(vec.remove(1), 1);
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.pop(), Some(0));
+        assert_eq!(vec.pop(), None);
+
+        assert!(vec.try_extend_from_slice(&[1, 2, 3, 4]).is_err());
+        assert!(vec.is_empty());
+        assert!(vec.try_extend_from_slice(&[1, 2, 3]).is_ok());
+        vec.as_mut_slice().sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(vec.swap_remove(0), 3);
+        assert_eq!(vec.as_slice(), &[1, 2]);
+    }
+
+    #[test_case]
+    fn drops_every_element_once(_gba: &mut crate::Gba) {
+        let drops = Cell::new(0);
+
+        let mut vec = ArrayVec::<_, 8>::new();
+        for _ in 0..6 {
+            vec.push(DropCounter(&drops));
+        }
+
+        vec.truncate(4);
+        assert_eq!(drops.get(), 2);
+
+        let mut iter = vec.into_iter();
+        drop(iter.next());
+        assert_eq!(drops.get(), 3);
+
+        drop(iter);
+        assert_eq!(drops.get(), 6);
+    }
+}
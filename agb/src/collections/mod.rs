@@ -0,0 +1,64 @@
+//! Collections for games which want to avoid allocating.
+//!
+//! Allocating in the middle of a frame can fragment the heap and takes an unpredictable amount
+//! of time. [`ArrayVec`] and [`ArrayString`] store their contents inline, so they can live on
+//! the stack or in a `static`, and adding to a full one fails rather than allocating.
+//! [`SlotMap`] does allocate, but reuses the space of removed values so a game which keeps
+//! adding and removing entities soon stops needing to.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use core::fmt::Write;
+//! use agb::collections::{ArrayString, ArrayVec};
+//!
+//! let mut scores = ArrayVec::<u32, 4>::new();
+//! scores.push(100);
+//! scores.push(250);
+//! assert!(scores.try_push(10).is_ok());
+//!
+//! let mut label = ArrayString::<16>::new();
+//! write!(label, "Best: {}", scores.iter().max().unwrap()).unwrap();
+//! # }
+//! ```
+
+mod array_string;
+mod array_vec;
+mod slot_map;
+
+use core::fmt::{self, Debug, Display};
+
+pub use array_string::ArrayString;
+pub use array_vec::{ArrayVec, IntoIter};
+pub use slot_map::{SlotMap, SlotMapKey};
+
+/// Returned when adding to a collection which doesn't have enough space left. Holds on to
+/// whatever couldn't be added so that it isn't lost.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError<T = ()> {
+    element: T,
+}
+
+impl<T> CapacityError<T> {
+    pub(crate) const fn new(element: T) -> Self {
+        Self { element }
+    }
+
+    /// The value which couldn't be added.
+    pub fn into_inner(self) -> T {
+        self.element
+    }
+}
+
+impl<T> Debug for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CapacityError")
+    }
+}
+
+impl<T> Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("insufficient capacity")
+    }
+}
@@ -0,0 +1,345 @@
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    ops::{Index, IndexMut},
+};
+
+/// Refers to a value in a [`SlotMap`].
+///
+/// Keys stay valid until the value is removed, however many other values are inserted or
+/// removed. Once the value is removed the key is stale, and looking it up gives [`None`] even
+/// if another value has been put in the same slot since.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotMapKey {
+    index: u32,
+    generation: u32,
+}
+
+enum Slot<T> {
+    Occupied {
+        generation: u32,
+        value: T,
+    },
+    Free {
+        generation: u32,
+        next_free: Option<u32>,
+    },
+}
+
+impl<T> Slot<T> {
+    fn generation(&self) -> u32 {
+        match self {
+            Slot::Occupied { generation, .. } | Slot::Free { generation, .. } => *generation,
+        }
+    }
+}
+
+/// A collection which gives each value a [`SlotMapKey`] when it is inserted, for things like
+/// the entities in a game.
+///
+/// Inserting, removing and looking up values are all constant time. Values are stored in a
+/// single vector, and the slots of removed values are reused, so after the first few frames a
+/// game which adds and removes entities stops allocating. Iteration is in the order of the
+/// slots, which is insertion order until values start being removed.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::collections::SlotMap;
+///
+/// struct Enemy {
+///     health: i32,
+/// }
+///
+/// let mut enemies = SlotMap::new();
+/// let boss = enemies.insert(Enemy { health: 100 });
+/// let minion = enemies.insert(Enemy { health: 5 });
+///
+/// enemies[minion].health -= 10;
+/// enemies.retain(|_, enemy| enemy.health > 0);
+///
+/// assert!(enemies.get(minion).is_none());
+/// assert_eq!(enemies[boss].health, 100);
+/// # }
+/// ```
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    first_free: Option<u32>,
+    len: usize,
+}
+
+impl<T> SlotMap<T> {
+    /// Creates an empty slot map. This doesn't allocate until the first value is inserted.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            first_free: None,
+            len: 0,
+        }
+    }
+
+    /// Creates an empty slot map with room for `capacity` values before it needs to allocate.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            first_free: None,
+            len: 0,
+        }
+    }
+
+    /// The number of values in the slot map.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the slot map contains no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of values the slot map can hold without allocating.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    /// Inserts `value`, returning the key which refers to it.
+    pub fn insert(&mut self, value: T) -> SlotMapKey {
+        self.len += 1;
+
+        if let Some(index) = self.first_free {
+            let slot = &mut self.slots[index as usize];
+            let Slot::Free {
+                generation,
+                next_free,
+            } = *slot
+            else {
+                unreachable!("the free list only contains free slots");
+            };
+
+            self.first_free = next_free;
+            *slot = Slot::Occupied { generation, value };
+
+            return SlotMapKey { index, generation };
+        }
+
+        let index = u32::try_from(self.slots.len()).expect("too many slots in slot map");
+        self.slots.push(Slot::Occupied {
+            generation: 0,
+            value,
+        });
+
+        SlotMapKey {
+            index,
+            generation: 0,
+        }
+    }
+
+    fn slot(&self, key: SlotMapKey) -> Option<&Slot<T>> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation() == key.generation)
+    }
+
+    /// Removes the value `key` refers to and returns it, or [`None`] if the key is stale.
+    pub fn remove(&mut self, key: SlotMapKey) -> Option<T> {
+        if !matches!(self.slot(key), Some(Slot::Occupied { .. })) {
+            return None;
+        }
+
+        let old = core::mem::replace(
+            &mut self.slots[key.index as usize],
+            Slot::Free {
+                generation: key.generation.wrapping_add(1),
+                next_free: self.first_free,
+            },
+        );
+        self.first_free = Some(key.index);
+        self.len -= 1;
+
+        match old {
+            Slot::Occupied { value, .. } => Some(value),
+            Slot::Free { .. } => unreachable!("the slot was checked to be occupied"),
+        }
+    }
+
+    /// The value `key` refers to, or [`None`] if the key is stale.
+    #[must_use]
+    pub fn get(&self, key: SlotMapKey) -> Option<&T> {
+        match self.slot(key) {
+            Some(Slot::Occupied { value, .. }) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value `key` refers to, or [`None`] if the key is stale.
+    #[must_use]
+    pub fn get_mut(&mut self, key: SlotMapKey) -> Option<&mut T> {
+        match self.slots.get_mut(key.index as usize) {
+            Some(Slot::Occupied { generation, value }) if *generation == key.generation => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `key` refers to a value in the slot map.
+    #[must_use]
+    pub fn contains_key(&self, key: SlotMapKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes every value for which `f` returns `false`.
+    pub fn retain(&mut self, mut f: impl FnMut(SlotMapKey, &mut T) -> bool) {
+        for index in 0..self.slots.len() {
+            let Slot::Occupied { generation, value } = &mut self.slots[index] else {
+                continue;
+            };
+
+            let key = SlotMapKey {
+                index: index as u32,
+                generation: *generation,
+            };
+
+            if !f(key, value) {
+                self.remove(key);
+            }
+        }
+    }
+
+    /// Removes every value. Every key given out so far becomes stale.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Iterates over the keys and values in the slot map.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotMapKey, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    SlotMapKey {
+                        index: index as u32,
+                        generation: *generation,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    /// Iterates over the keys and values in the slot map, allowing the values to be changed.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlotMapKey, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    SlotMapKey {
+                        index: index as u32,
+                        generation: *generation,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    /// Iterates over the values in the slot map.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Iterates over the values in the slot map, allowing them to be changed.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for SlotMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Index<SlotMapKey> for SlotMap<T> {
+    type Output = T;
+
+    fn index(&self, key: SlotMapKey) -> &T {
+        self.get(key).expect("key is stale")
+    }
+}
+
+impl<T> IndexMut<SlotMapKey> for SlotMap<T> {
+    fn index_mut(&mut self, key: SlotMapKey) -> &mut T {
+        self.get_mut(key).expect("key is stale")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test_case]
+    fn stale_keys_find_nothing(_gba: &mut crate::Gba) {
+        let mut map = SlotMap::new();
+
+        let a = map.insert('a');
+        let b = map.insert('b');
+        assert_eq!(map.remove(a), Some('a'));
+        assert_eq!(map.remove(a), None);
+
+        // reuses the slot a was in, but a is still stale
+        let c = map.insert('c');
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.get(c), Some(&'c'));
+        assert_eq!(map[b], 'b');
+        assert_eq!(map.len(), 2);
+
+        *map.get_mut(c).unwrap() = 'd';
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), vec!['d', 'b']);
+        assert!(map.get_mut(a).is_none());
+    }
+
+    #[test_case]
+    fn retain_removes_and_reuses_slots(_gba: &mut crate::Gba) {
+        let mut map = SlotMap::with_capacity(10);
+        let keys: Vec<_> = (0..10).map(|i| map.insert(i)).collect();
+
+        map.retain(|_, value| *value % 3 == 0);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.capacity(), 10);
+        assert!(map.contains_key(keys[3]) && !map.contains_key(keys[4]));
+        assert_eq!(
+            map.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            vec![keys[0], keys[3], keys[6], keys[9]]
+        );
+
+        for value in map.values_mut() {
+            *value += 1;
+        }
+        assert_eq!(map[keys[9]], 10);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(keys.iter().all(|&key| map.get(key).is_none()));
+        for i in 0..10 {
+            map.insert(i);
+        }
+        assert_eq!(map.capacity(), 10);
+    }
+}
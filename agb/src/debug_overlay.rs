@@ -0,0 +1,517 @@
+//! Showing the values of variables on screen while the game runs, for debugging on hardware.
+//!
+//! Give a [`DebugOverlay`] the values to watch each frame with
+//! [`debug_watch!`](crate::debug_watch), then call [`update`](DebugOverlay::update) once per
+//! frame. Pressing the button chord, [`DEFAULT_CHORD`] unless another is given, shows the values
+//! in the top left corner of the screen in a tiny built in font, and pressing it again hides
+//! them.
+//!
+//! The overlay is a single 64x64 object, with room for [`MAX_WATCHES`] lines of
+//! [`CHARACTERS_PER_LINE`] characters. Its object, tiles and palette are only allocated when it
+//! is shown and are freed when it is hidden, so it costs nothing until it is used. If there isn't
+//! room for them, the values are logged to mgba once a second instead.
+//!
+//! The overlay is only shown when the `debug-overlay` feature of agb is enabled. Without it,
+//! [`debug_watch!`](crate::debug_watch) and [`update`](DebugOverlay::update) do nothing, so the
+//! calls can be left in place for release builds.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{debug_overlay::DebugOverlay, debug_watch, fixnum::Num, input::ButtonController};
+//!
+//! let oam = gba.display.object.get_managed();
+//! let mut input = ButtonController::new();
+//! let mut overlay = DebugOverlay::new();
+//!
+//! let mut speed: Num<i32, 8> = Num::new(1) / 3;
+//! let mut on_ground = true;
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     input.update();
+//!
+//!     debug_watch!(overlay, speed);
+//!     debug_watch!(overlay, "grounded", on_ground);
+//!     overlay.update(&input, &oam);
+//!
+//!     vblank.wait_for_vblank();
+//!     oam.commit();
+//! }
+//! # }
+//! ```
+
+use core::fmt::{self, Display, Write};
+
+use crate::{
+    display::{
+        object::{DynamicSprite, OamManaged, Object, PaletteVram, Size, SpriteVram},
+        palette16::Palette16,
+        Priority,
+    },
+    fixnum::Vector2D,
+    input::{Button, ButtonController},
+};
+
+/// Whether the overlay does anything, which is only with the `debug-overlay` feature.
+const ENABLED: bool = cfg!(any(feature = "debug-overlay", test));
+
+/// The most values which can be watched. Any more are ignored.
+pub const MAX_WATCHES: usize = 10;
+/// How many characters of each watched value's line are shown, including its name.
+pub const CHARACTERS_PER_LINE: usize = 16;
+/// The buttons which show and hide the overlay when pressed together, unless another chord is
+/// given to [`DebugOverlay::with_chord`].
+pub const DEFAULT_CHORD: Button = Button::L.union(Button::R).union(Button::SELECT);
+
+const SIZE: Size = Size::S64x64;
+const CHARACTER_WIDTH: usize = 4;
+const LINE_HEIGHT: usize = 6;
+/// How often the values are logged when there's no room to show them.
+const LOG_INTERVAL_FRAMES: u32 = 60;
+
+const TEXT_COLOUR_INDEX: u8 = 1;
+const BACKGROUND_COLOUR_INDEX: u8 = 2;
+
+/// A watched value, formatted when it was given to the overlay.
+struct Watch {
+    name: &'static str,
+    value: [u8; CHARACTERS_PER_LINE],
+    value_length: usize,
+}
+
+impl Watch {
+    /// The line shown for the value, as `name=value` cut off at the end of the line.
+    fn line(&self) -> Line {
+        let mut line = Line::default();
+        let _ = write!(line, "{}=", self.name);
+        for &byte in &self.value[..self.value_length] {
+            line.push(byte);
+        }
+        line
+    }
+}
+
+#[derive(Default)]
+struct Line {
+    text: [u8; CHARACTERS_PER_LINE],
+    length: usize,
+}
+
+impl Line {
+    fn push(&mut self, byte: u8) {
+        if self.length < CHARACTERS_PER_LINE {
+            self.text[self.length] = byte;
+            self.length += 1;
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.text[..self.length]
+    }
+}
+
+impl PartialEq for Line {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+        Ok(())
+    }
+}
+
+enum State<'oam> {
+    Hidden,
+    Shown {
+        object: Object<'oam>,
+        sprite: SpriteVram,
+        /// The lines currently in the sprite's tiles, or `None` if nothing has been drawn yet.
+        drawn: Option<[Option<Line>; MAX_WATCHES]>,
+    },
+    /// Shown, but there was no room for the object so the values are logged instead.
+    Logging {
+        frames: u32,
+    },
+}
+
+/// Values shown over the game, see the [module level documentation](self).
+pub struct DebugOverlay<'oam> {
+    chord: Button,
+    position: Vector2D<i32>,
+    watches: [Option<Watch>; MAX_WATCHES],
+    state: State<'oam>,
+}
+
+impl<'oam> DebugOverlay<'oam> {
+    /// An overlay toggled by [`DEFAULT_CHORD`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_chord(DEFAULT_CHORD)
+    }
+
+    /// An overlay toggled by pressing all the buttons in `chord` together.
+    #[must_use]
+    pub fn with_chord(chord: Button) -> Self {
+        Self {
+            chord,
+            position: Vector2D::new(0, 0),
+            watches: core::array::from_fn(|_| None),
+            state: State::Hidden,
+        }
+    }
+
+    /// Moves the overlay so its top left corner is at `position`, rather than the top left of
+    /// the screen.
+    pub fn set_position(&mut self, position: Vector2D<i32>) {
+        self.position = position;
+        if let State::Shown { object, .. } = &mut self.state {
+            object.set_position(position);
+        }
+    }
+
+    /// Sets the value shown for `name`, adding it to the overlay the first time it is given.
+    /// Once [`MAX_WATCHES`] names have been given, new names are ignored. This is usually
+    /// called through [`debug_watch!`](crate::debug_watch).
+    pub fn watch(&mut self, name: &'static str, value: impl Display) {
+        if !ENABLED {
+            return;
+        }
+
+        let index = match self
+            .watches
+            .iter()
+            .position(|watch| watch.as_ref().is_some_and(|watch| watch.name == name))
+        {
+            Some(index) => index,
+            None => match self.watches.iter().position(Option::is_none) {
+                Some(index) => index,
+                None => return,
+            },
+        };
+
+        let mut formatted = Line::default();
+        let _ = write!(formatted, "{value}");
+
+        self.watches[index] = Some(Watch {
+            name,
+            value: formatted.text,
+            value_length: formatted.length,
+        });
+    }
+
+    /// Whether the overlay is currently toggled on, even if it is being logged rather than
+    /// shown.
+    #[must_use]
+    pub fn is_shown(&self) -> bool {
+        !matches!(self.state, State::Hidden)
+    }
+
+    /// Shows or hides the overlay if its chord has just been pressed, then draws the latest
+    /// values. Call this once per frame after updating `input`.
+    pub fn update(&mut self, input: &ButtonController, oam: &'oam OamManaged<'_>) {
+        if !ENABLED {
+            return;
+        }
+
+        let chord_held = self.chord.iter().all(|button| input.is_pressed(button));
+        let chord_completed = self
+            .chord
+            .iter()
+            .any(|button| input.is_just_pressed(button));
+        if chord_held && chord_completed {
+            self.toggle(oam);
+        }
+
+        self.draw();
+    }
+
+    /// Shows the overlay if it is hidden and hides it if it is shown, the same as pressing the
+    /// chord. Showing it allocates its object, tiles and palette, and hiding it frees them.
+    /// Without the `debug-overlay` feature this does nothing.
+    pub fn toggle(&mut self, oam: &'oam OamManaged<'_>) {
+        if !ENABLED {
+            return;
+        }
+
+        self.state = match self.state {
+            State::Hidden => self.show(oam),
+            State::Shown { .. } | State::Logging { .. } => State::Hidden,
+        };
+    }
+
+    fn show(&self, oam: &'oam OamManaged<'_>) -> State<'oam> {
+        let mut colours = [0; 16];
+        colours[usize::from(TEXT_COLOUR_INDEX)] = 0x7fff;
+        colours[usize::from(BACKGROUND_COLOUR_INDEX)] = 0x0000;
+
+        let sprite = PaletteVram::new(&Palette16::new(colours))
+            .ok()
+            .and_then(|palette| Some(DynamicSprite::try_new(SIZE).ok()?.to_vram(palette)));
+
+        let Some(sprite) = sprite else {
+            crate::println!("debug overlay: no room to show the overlay, logging it instead");
+            return State::Logging { frames: 0 };
+        };
+
+        let mut object = oam.object(sprite.clone());
+        object
+            .set_position(self.position)
+            .set_priority(Priority::P0)
+            .set_z(i32::MIN)
+            .show();
+
+        State::Shown {
+            object,
+            sprite,
+            drawn: None,
+        }
+    }
+
+    fn draw(&mut self) {
+        let lines: [Option<Line>; MAX_WATCHES] =
+            core::array::from_fn(|index| self.watches[index].as_ref().map(Watch::line));
+
+        match &mut self.state {
+            State::Hidden => {}
+            State::Shown { sprite, drawn, .. } => {
+                // redrawing all 64x64 pixels takes a lot of the frame, so only do it when a line
+                // has changed
+                if drawn.as_ref() == Some(&lines) {
+                    return;
+                }
+
+                let mut text = [&[][..]; MAX_WATCHES];
+                let mut line_count = 0;
+                for line in lines.iter().map_while(Option::as_ref) {
+                    text[line_count] = line.as_bytes();
+                    line_count += 1;
+                }
+
+                write_tiles(sprite, &text[..line_count]);
+                *drawn = Some(lines);
+            }
+            State::Logging { frames } => {
+                if *frames % LOG_INTERVAL_FRAMES == 0 {
+                    for line in lines.iter().map_while(Option::as_ref) {
+                        crate::println!("{}", core::str::from_utf8(line.as_bytes()).unwrap_or(""));
+                    }
+                }
+
+                *frames += 1;
+            }
+        }
+    }
+}
+
+impl Default for DebugOverlay<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws `lines` straight into the tiles of `sprite`, which is redrawn whenever a value changes
+/// so isn't worth going through a [`DynamicSprite`] for.
+fn write_tiles(sprite: &SpriteVram, lines: &[&[u8]]) {
+    let (width, height) = SIZE.to_width_height();
+    let tiles_across = width / 8;
+    let first_tile = (0x0601_0000 + usize::from(sprite.location()) * 32) as *mut u32;
+
+    for tile in 0..tiles_across * (height / 8) {
+        for row in 0..8 {
+            let y = tile / tiles_across * 8 + row;
+
+            let mut pixels = 0;
+            for column in 0..8 {
+                let x = tile % tiles_across * 8 + column;
+                pixels |= u32::from(colour_index(lines, x, y)) << (column * 4);
+            }
+
+            // Safety: the sprite is SIZE, so has this many tiles, and nothing else uses them
+            unsafe { first_tile.add(tile * 8 + row).write_volatile(pixels) };
+        }
+    }
+}
+
+/// The palette index of the pixel at (`x`, `y`) of the overlay. Each line has a dark
+/// background behind its text and is transparent beyond it.
+fn colour_index(lines: &[&[u8]], x: usize, y: usize) -> u8 {
+    let Some(line) = lines.get(y / LINE_HEIGHT) else {
+        return 0;
+    };
+    let Some(&character) = line.get(x / CHARACTER_WIDTH) else {
+        return 0;
+    };
+
+    let column = x % CHARACTER_WIDTH;
+    let row = y % LINE_HEIGHT;
+
+    // each glyph is 3x5, leaving a gap between characters and lines
+    if column < 3 && row < 5 && glyph(character) & (1 << (14 - (row * 3 + column))) != 0 {
+        TEXT_COLOUR_INDEX
+    } else {
+        BACKGROUND_COLOUR_INDEX
+    }
+}
+
+/// The 3x5 glyph for `character`, with each row as 3 bits from the top and the highest bit of
+/// each row as the leftmost pixel. Lower case letters are shown as upper case.
+const fn glyph(character: u8) -> u16 {
+    match character.to_ascii_uppercase() {
+        b'0' => 0b111_101_101_101_111,
+        b'1' => 0b010_110_010_010_111,
+        b'2' => 0b111_001_111_100_111,
+        b'3' => 0b111_001_111_001_111,
+        b'4' => 0b101_101_111_001_001,
+        b'5' => 0b111_100_111_001_111,
+        b'6' => 0b111_100_111_101_111,
+        b'7' => 0b111_001_001_010_010,
+        b'8' => 0b111_101_111_101_111,
+        b'9' => 0b111_101_111_001_111,
+        b'A' => 0b010_101_111_101_101,
+        b'B' => 0b110_101_110_101_110,
+        b'C' => 0b011_100_100_100_011,
+        b'D' => 0b110_101_101_101_110,
+        b'E' => 0b111_100_110_100_111,
+        b'F' => 0b111_100_110_100_100,
+        b'G' => 0b011_100_101_101_011,
+        b'H' => 0b101_101_111_101_101,
+        b'I' => 0b111_010_010_010_111,
+        b'J' => 0b001_001_001_101_010,
+        b'K' => 0b101_101_110_101_101,
+        b'L' => 0b100_100_100_100_111,
+        b'M' => 0b101_111_111_101_101,
+        b'N' => 0b110_101_101_101_101,
+        b'O' => 0b010_101_101_101_010,
+        b'P' => 0b110_101_110_100_100,
+        b'Q' => 0b010_101_101_110_011,
+        b'R' => 0b110_101_110_101_101,
+        b'S' => 0b011_100_010_001_110,
+        b'T' => 0b111_010_010_010_010,
+        b'U' => 0b101_101_101_101_111,
+        b'V' => 0b101_101_101_101_010,
+        b'W' => 0b101_101_111_111_101,
+        b'X' => 0b101_101_010_101_101,
+        b'Y' => 0b101_101_010_010_010,
+        b'Z' => 0b111_001_010_100_111,
+        b' ' => 0,
+        b'.' => 0b000_000_000_000_010,
+        b',' => 0b000_000_000_010_100,
+        b'-' => 0b000_000_111_000_000,
+        b'+' => 0b000_010_111_010_000,
+        b':' => 0b000_010_000_010_000,
+        b'=' => 0b000_111_000_111_000,
+        b'_' => 0b000_000_000_000_111,
+        b'(' => 0b001_010_010_010_001,
+        b')' => 0b100_010_010_010_100,
+        b'[' => 0b110_100_100_100_110,
+        b']' => 0b011_001_001_001_011,
+        b'<' => 0b001_010_100_010_001,
+        b'>' => 0b100_010_001_010_100,
+        b'/' => 0b001_001_010_100_100,
+        b'*' => 0b000_101_010_101_000,
+        b'#' => 0b101_111_101_111_101,
+        b'%' => 0b101_001_010_100_101,
+        b'!' => 0b010_010_010_000_010,
+        b'\'' => 0b010_010_000_000_000,
+        b'"' => 0b101_101_000_000_000,
+        // anything else is shown as a question mark
+        _ => 0b110_001_010_000_010,
+    }
+}
+
+/// Shows a value on a [`DebugOverlay`](crate::debug_overlay::DebugOverlay), named after the
+/// expression or with the given name. Integers, [`Num`](crate::fixnum::Num)s, booleans and
+/// anything else which implements [`Display`](core::fmt::Display) can be watched. Does nothing
+/// without the `debug-overlay` feature.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(overlay: &mut agb::debug_overlay::DebugOverlay, health: i32) {
+/// agb::debug_watch!(overlay, health);
+/// agb::debug_watch!(overlay, "hp%", health * 100 / 50);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! debug_watch {
+    ($overlay: expr, $name: literal, $value: expr) => {
+        $overlay.watch($name, &$value)
+    };
+    ($overlay: expr, $value: expr) => {
+        $overlay.watch(::core::stringify!($value), &$value)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixnum::Num;
+
+    fn read_tile_row(sprite: &SpriteVram, tile: usize, row: usize) -> u32 {
+        let first_tile = (0x0601_0000 + usize::from(sprite.location()) * 32) as *const u32;
+        unsafe { first_tile.add(tile * 8 + row).read_volatile() }
+    }
+
+    #[test_case]
+    fn shows_watched_values(gba: &mut crate::Gba) {
+        let oam = gba.display.object.get_managed();
+        let mut overlay = DebugOverlay::new();
+
+        let speed: Num<i32, 8> = Num::new(3) / 2;
+        debug_watch!(overlay, speed);
+        debug_watch!(overlay, "on", true);
+        debug_watch!(overlay, "on", false);
+
+        let lines: [Line; 2] =
+            core::array::from_fn(|i| overlay.watches[i].as_ref().unwrap().line());
+        assert_eq!(lines[0].as_bytes(), b"speed=1.5");
+        assert_eq!(lines[1].as_bytes(), b"on=false");
+        assert!(overlay.watches[2].is_none());
+
+        for i in 0..MAX_WATCHES {
+            overlay.watch(["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"][i], i);
+        }
+        assert!(overlay.watches.iter().all(Option::is_some));
+        assert_eq!(overlay.watches[2].as_ref().unwrap().name, "a");
+
+        overlay.toggle(&oam);
+        assert!(overlay.is_shown());
+        overlay.draw();
+
+        let State::Shown { sprite, .. } = &overlay.state else {
+            panic!("the overlay should have had room to be shown");
+        };
+        // the top of the S, which is 011, on the background
+        assert_eq!(read_tile_row(sprite, 0, 0), 0x2211_2112);
+        // below the last line is transparent
+        assert_eq!(read_tile_row(sprite, 7 * 8, 7), 0);
+        oam.commit();
+
+        // the tiles are left alone until a value changes
+        let first_tile = (0x0601_0000 + usize::from(sprite.location()) * 32) as *mut u32;
+        unsafe { first_tile.write_volatile(0) };
+        overlay.draw();
+        let State::Shown { sprite, .. } = &overlay.state else {
+            unreachable!();
+        };
+        assert_eq!(read_tile_row(sprite, 0, 0), 0);
+
+        overlay.watch("a", 100);
+        overlay.draw();
+        let State::Shown { sprite, .. } = &overlay.state else {
+            unreachable!();
+        };
+        assert_eq!(read_tile_row(sprite, 0, 0), 0x2211_2112);
+
+        overlay.toggle(&oam);
+        assert!(!overlay.is_shown());
+        oam.commit();
+    }
+}
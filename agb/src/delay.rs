@@ -0,0 +1,94 @@
+//! Busy waiting for short, precise amounts of time.
+//!
+//! These are for when something needs to wait a few microseconds, such as giving a device on
+//! the link cable time to respond, where setting up a timer or an interrupt would be overkill.
+//! For anything longer, [`halt_until`](crate::power::halt_until) or the
+//! [timers](crate::timer) let the CPU do something useful (or sleep) in the meantime.
+//!
+//! # Accuracy
+//!
+//! The delay loop itself lives in IWRAM as ARM code, so it takes exactly 4 cycles per iteration
+//! no matter where it is called from. What varies is the cost of getting into and out of it.
+//! Called from IWRAM, the overhead is a handful of cycles. Called from ROM, every instruction
+//! around the call pays the cartridge wait states, so expect a few tens of cycles extra, and
+//! more again if the prefetch buffer is turned off. Interrupts which happen during the delay
+//! make it longer by however long their handlers take.
+//!
+//! The delays are therefore minimums which are accurate to within a microsecond or so, which
+//! is plenty for hardware timings but not for cycle-exact effects.
+
+use crate::memory_mapped::MemoryMapped;
+
+const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+
+const CYCLES_PER_SECOND: u64 = 1 << 24;
+
+extern "C" {
+    fn agb_rs__delay_cycles(cycles: u32);
+}
+
+/// Busy waits for at least `n` CPU cycles, rounded down to a multiple of 4.
+///
+/// There are 2<sup>24</sup> cycles per second, or 280,896 in a frame.
+pub fn cycles(n: u32) {
+    // SAFETY: the routine only counts down a register
+    unsafe { agb_rs__delay_cycles(n) };
+}
+
+/// Busy waits for at least `us` microseconds.
+pub fn micros(us: u32) {
+    let mut remaining = u64::from(us) * CYCLES_PER_SECOND / 1_000_000;
+
+    while remaining > 0 {
+        let chunk = remaining.min(u64::from(u32::MAX - 3));
+        cycles(chunk as u32);
+        remaining -= chunk;
+    }
+}
+
+/// Busy waits until the display starts drawing scanline `n`.
+///
+/// Scanlines 0 to 159 are the visible part of the screen and 160 to 227 are the vertical blank.
+/// If the display is already on scanline `n` this returns straight away.
+///
+/// # Panics
+///
+/// Panics if `n` is 228 or more, since the display would never get there.
+pub fn until_scanline(n: u16) {
+    assert!(n < 228, "scanline {n} doesn't exist");
+
+    while VCOUNT.get() != n {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::{Divider, MonotonicClock};
+
+    #[test_case]
+    fn delays_wait_for_at_least_as_long_as_asked(gba: &mut crate::Gba) {
+        let timers = gba.timers.timers();
+        let clock = MonotonicClock::new(timers.timer2, timers.timer3, Divider::Divider1);
+
+        let stopwatch = clock.stopwatch();
+        cycles(10_000);
+        let elapsed = stopwatch.elapsed_ticks();
+        assert!(
+            (10_000..11_000).contains(&elapsed),
+            "waiting 10000 cycles took {elapsed}"
+        );
+
+        let stopwatch = clock.stopwatch();
+        micros(1000);
+        let elapsed = stopwatch.elapsed_micros();
+        assert!(
+            (1000..1100).contains(&elapsed),
+            "waiting 1000us took {elapsed}us"
+        );
+
+        until_scanline(100);
+        assert_eq!(VCOUNT.get(), 100);
+
+        let (_timer2, _timer3) = clock.into_timers();
+    }
+}
@@ -0,0 +1,15 @@
+@
+@ void DelayCycles(unsigned int cycles);
+@
+@ Busy waits for roughly the given number of cycles. This is arm code in iwram where every
+@ instruction fetch takes a single cycle, so each iteration of the loop takes exactly 4 cycles:
+@ 1 for the subs and 3 for the taken branch.
+@
+agb_arm_func agb_rs__delay_cycles
+    lsrs r0, r0, #2
+    bxeq lr
+1:
+    subs r0, r0, #1
+    bne 1b
+    bx lr
+agb_arm_end agb_rs__delay_cycles
@@ -83,7 +83,7 @@
 
 use core::ops::{Mul, MulAssign};
 
-use agb_fixnum::{Num, Vector2D};
+use agb_fixnum::{ConvexPolygon, Num, Rect, Vector2D};
 
 type AffineMatrixElement = Num<i32, 8>;
 
@@ -231,6 +231,57 @@ impl AffineMatrix {
             y: 0.into(),
         }
     }
+
+    #[must_use]
+    /// Transforms each corner of `rect` by this matrix, giving a polygon which
+    /// can be used for collision detection with rotated or scaled shapes.
+    ///
+    /// The matrix is treated the same way as elsewhere, so the polygon is
+    /// where `rect` ends up after the transformation the matrix represents.
+    /// For example, the matrix from [`AffineMatrix::from_translation`] moves
+    /// the rectangle by that position.
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// use agb::fixnum::{num, Num, Rect};
+    /// use agb::display::affine::AffineMatrix;
+    ///
+    /// # fn foo(_gba: &mut agb::Gba) {
+    /// let blade: Rect<Num<i32, 8>> = Rect::new((0, -2).into(), (16, 4).into());
+    ///
+    /// let hitbox = (AffineMatrix::from_rotation(num!(0.125))
+    ///     * AffineMatrix::from_translation((50, 50).into()))
+    /// .transform_rect(blade);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matrix can't be inverted, for example if it scales by
+    /// zero.
+    pub fn transform_rect(
+        &self,
+        rect: Rect<AffineMatrixElement>,
+    ) -> ConvexPolygon<AffineMatrixElement, 4> {
+        // the matrix maps from screen space to texture space, so the inverse
+        // is needed to find where the corners of the rectangle end up
+        let determinant = self.a * self.d - self.b * self.c;
+        assert_ne!(determinant, 0.into(), "affine matrix is not invertible");
+
+        let corners = ConvexPolygon::from_rect(rect);
+        let corners = corners.vertices();
+
+        let transformed: [_; 4] = core::array::from_fn(|i| {
+            let corner = corners[i] - Vector2D::new(self.x, self.y);
+            Vector2D::new(
+                (self.d * corner.x - self.b * corner.y) / determinant,
+                (self.a * corner.y - self.c * corner.x) / determinant,
+            )
+        });
+
+        ConvexPolygon::new(&transformed).expect("a rectangle has 4 corners")
+    }
 }
 
 impl Default for AffineMatrix {
@@ -427,4 +478,28 @@ mod tests {
         assert_eq!(e.position(), position);
         assert_eq!(d * d, AffineMatrix::identity());
     }
+
+    #[test_case]
+    fn test_transform_rect(_: &mut crate::Gba) {
+        let rect = Rect::new((0, 0).into(), (4, 2).into());
+
+        let moved = AffineMatrix::from_translation((10, 20).into()).transform_rect(rect);
+        assert_eq!(
+            moved,
+            ConvexPolygon::from_rect(Rect::new((10, 20).into(), (4, 2).into()))
+        );
+
+        let quarter_turn = AffineMatrix::from_rotation::<8>(num!(0.25));
+        assert_eq!(
+            quarter_turn.transform_rect(rect).vertices(),
+            &[(0, 0).into(), (0, -4).into(), (2, -4).into(), (2, 0).into()]
+        );
+
+        let hitbox = (AffineMatrix::from_rotation::<8>(num!(0.125))
+            * AffineMatrix::from_translation((10, 10).into()))
+        .transform_rect(rect);
+
+        assert!(hitbox.overlaps_rect(Rect::new((9, 9).into(), (2, 2).into())));
+        assert!(!hitbox.overlaps_rect(Rect::new((13, 10).into(), (1, 1).into())));
+    }
 }
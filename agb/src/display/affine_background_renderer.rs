@@ -0,0 +1,222 @@
+//! A camera for rotating and scaling affine backgrounds.
+//!
+//! Mode 1 has one affine background and mode 2 has two. Rather than a scroll position they have
+//! a matrix which says where on the background each pixel on the screen comes from, which is
+//! awkward to work out by hand. An [`AffineBgRenderer`] keeps the point of the background which
+//! should be in the middle of the screen, how far it is rotated and how much it is scaled, and
+//! turns those into the matrix when it is committed.
+//!
+//! Each renderer is for one background, so with two backgrounds in mode 2 use one for each.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{
+//!     display::{
+//!         affine_background_renderer::AffineBgRenderer,
+//!         tiled::{AffineBackgroundSize, TiledMap},
+//!         Priority,
+//!     },
+//!     fixnum::num,
+//! };
+//!
+//! let (gfx, mut vram) = gba.display.video.tiled2();
+//! let mut bg = gfx.background(Priority::P0, AffineBackgroundSize::Background64x64);
+//! // set the tiles
+//! bg.commit(&mut vram);
+//! bg.set_visible(true);
+//!
+//! let mut camera = AffineBgRenderer::new();
+//! camera.set_ref_point(num!(256.), num!(256.));
+//! camera.set_overflow_mode(true);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! let mut angle = 0u16;
+//! loop {
+//!     angle = angle.wrapping_add(128);
+//!     camera.set_rotation(angle);
+//!     camera.scroll_in_world_space(num!(0.5), num!(0.));
+//!
+//!     vblank.wait_for_vblank();
+//!     camera.commit(&mut bg);
+//! }
+//! # }
+//! ```
+
+use agb_fixnum::{Num, Vector2D};
+
+use super::{affine::AffineMatrixBackground, tiled::AffineMap, HEIGHT, WIDTH};
+
+/// The position, rotation and scale of an affine background. See the [module level
+/// documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AffineBgRenderer {
+    ref_point: Vector2D<Num<i32, 8>>,
+    rotation: u16,
+    scale: Vector2D<Num<i32, 8>>,
+    wrap: bool,
+}
+
+impl AffineBgRenderer {
+    /// A camera with the top left of the background in the top left of the screen, no rotation
+    /// or scaling, and without wraparound.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            ref_point: Vector2D::new(Num::new(WIDTH / 2), Num::new(HEIGHT / 2)),
+            rotation: 0,
+            scale: Vector2D::new(Num::new(1), Num::new(1)),
+            wrap: false,
+        }
+    }
+
+    /// Sets the point on the background, in pixels, which is shown in the middle of the screen.
+    /// The background rotates and scales around this point.
+    pub fn set_ref_point(&mut self, cx: Num<i32, 8>, cy: Num<i32, 8>) {
+        self.ref_point = Vector2D::new(cx, cy);
+    }
+
+    /// The point on the background which is shown in the middle of the screen.
+    #[must_use]
+    pub fn ref_point(&self) -> Vector2D<Num<i32, 8>> {
+        self.ref_point
+    }
+
+    /// Moves the point shown in the middle of the screen by (`dx`, `dy`) pixels of the
+    /// background. The movement is along the background's own axes, so it isn't affected by
+    /// the rotation or scale.
+    pub fn scroll_in_world_space(&mut self, dx: Num<i32, 8>, dy: Num<i32, 8>) {
+        self.ref_point += Vector2D::new(dx, dy);
+    }
+
+    /// Sets how far the background is rotated, where 65536 would be a full turn, so 16384 is a
+    /// quarter turn.
+    pub fn set_rotation(&mut self, angle: u16) {
+        self.rotation = angle;
+    }
+
+    /// How far the background is rotated, as given to
+    /// [`set_rotation`](AffineBgRenderer::set_rotation).
+    #[must_use]
+    pub fn rotation(&self) -> u16 {
+        self.rotation
+    }
+
+    /// Sets how many pixels of the background each pixel on the screen covers along each axis.
+    /// So 2 shows the background at half size, and 0.5 shows it at double size.
+    ///
+    /// The hardware can only use scales between -128 and 128, which
+    /// [`commit`](AffineBgRenderer::commit) checks.
+    pub fn set_scale(&mut self, sx: Num<i32, 8>, sy: Num<i32, 8>) {
+        self.scale = Vector2D::new(sx, sy);
+    }
+
+    /// The scale, as given to [`set_scale`](AffineBgRenderer::set_scale).
+    #[must_use]
+    pub fn scale(&self) -> Vector2D<Num<i32, 8>> {
+        self.scale
+    }
+
+    /// Sets whether the background repeats forever in every direction. When it doesn't, which
+    /// is the default, everything outside the background is transparent.
+    pub fn set_overflow_mode(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// The matrix for the current position, rotation and scale.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scale is outside the range the hardware can use.
+    #[must_use]
+    pub fn matrix(&self) -> AffineMatrixBackground {
+        let scale = self
+            .scale
+            .try_change_base()
+            .expect("affine background scale must be between -128 and 128");
+
+        crate::syscall::bg_affine_matrix(
+            self.ref_point,
+            Vector2D::new((WIDTH / 2) as i16, (HEIGHT / 2) as i16),
+            scale,
+            Num::from_raw(self.rotation),
+        )
+    }
+
+    /// Sets the transform and overflow mode of `map` and writes them straight to its
+    /// registers, all six affine registers together. The tiles aren't copied, so this is cheap
+    /// enough to call every frame during vblank, and [`TiledMap::commit`] is only needed when
+    /// the tiles change.
+    ///
+    /// [`TiledMap::commit`]: super::tiled::TiledMap::commit
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scale is outside the range the hardware can use.
+    pub fn commit(&self, map: &mut AffineMap) {
+        map.set_transform(self.matrix());
+        map.set_wraparound(self.wrap);
+        map.commit_registers();
+    }
+}
+
+impl Default for AffineBgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb_fixnum::num;
+
+    use super::*;
+    use crate::{
+        display::{tiled::AffineBackgroundSize, Priority},
+        memory_mapped::MemoryMapped,
+    };
+
+    #[test_case]
+    fn centres_the_ref_point(gba: &mut crate::Gba) {
+        let mut camera = AffineBgRenderer::new();
+
+        // the top left of the background is in the top left of the screen
+        let matrix = camera.matrix().to_affine_matrix();
+        assert_eq!(
+            (matrix.a, matrix.b, matrix.c, matrix.d),
+            (num!(1.), num!(0.), num!(0.), num!(1.))
+        );
+        assert_eq!((matrix.x, matrix.y), (num!(0.), num!(0.)));
+
+        camera.scroll_in_world_space(num!(10.), num!(-20.5));
+        let matrix = camera.matrix().to_affine_matrix();
+        assert_eq!((matrix.x, matrix.y), (num!(10.), num!(-20.5)));
+
+        // at double size, the screen shows 60 pixels either side of the ref point
+        camera.set_ref_point(num!(100.), num!(100.));
+        camera.set_scale(num!(0.5), num!(0.5));
+        let matrix = camera.matrix().to_affine_matrix();
+        assert_eq!((matrix.a, matrix.d), (num!(0.5), num!(0.5)));
+        assert_eq!((matrix.x, matrix.y), (num!(40.), num!(60.)));
+
+        // a half turn puts the top left of the screen the other side of the ref point
+        camera.set_scale(num!(1.), num!(1.));
+        camera.set_rotation(0x8000);
+        let matrix = camera.matrix().to_affine_matrix();
+        assert_eq!((matrix.a, matrix.d), (num!(-1.), num!(-1.)));
+        assert_eq!((matrix.x, matrix.y), (num!(220.), num!(180.)));
+
+        let (gfx, _vram) = gba.display.video.tiled2();
+        let mut bg = gfx.background(Priority::P1, AffineBackgroundSize::Background32x32);
+        camera.set_overflow_mode(true);
+        camera.commit(&mut bg);
+
+        assert!(bg.wraparound());
+        let bg2_control = unsafe { MemoryMapped::<u16>::new(0x0400_000C) };
+        assert_eq!(
+            bg2_control.get() & (1 << 13 | 0b11),
+            1 << 13 | Priority::P1 as u16
+        );
+    }
+}
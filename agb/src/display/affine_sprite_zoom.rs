@@ -0,0 +1,244 @@
+//! Zooming sprites in and out, such as a title or a boss appearing.
+//!
+//! A [`ZoomAnimation`] moves a scale towards its target over some frames, and
+//! [`update`](ZoomAnimation::update) gives the affine matrix for each frame, ready to be turned
+//! into an [`AffineMatrixInstance`](super::object::AffineMatrixInstance) for the sprite. The
+//! matrix needs a division, so it is only worked out again when the scale has changed.
+//! [`with_bounce`](ZoomAnimation::with_bounce) makes the zoom go a little past its target before
+//! settling back, which gives it some weight.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, logo: &'static agb::display::object::Sprite) {
+//! use agb::{
+//!     display::{
+//!         affine_sprite_zoom::ZoomAnimation,
+//!         object::{AffineMatrixInstance, AffineMode},
+//!     },
+//!     fixnum::num,
+//! };
+//!
+//! let oam = gba.display.object.get_managed();
+//! let mut object = oam.object_sprite(logo);
+//! object.set_position((88, 48)).show_affine(AffineMode::AffineDouble);
+//!
+//! // grow from a tenth of the size to full size over half a second, overshooting by a quarter
+//! let mut zoom = ZoomAnimation::zoom_in(num!(0.1), 30).with_bounce(num!(0.25));
+//!
+//! while let Some(matrix) = zoom.update() {
+//!     object.set_affine_matrix(AffineMatrixInstance::new(matrix.to_object_wrapping()));
+//!
+//!     agb::interrupt::VBlank::get().wait_for_vblank();
+//!     oam.commit();
+//! }
+//! # }
+//! ```
+
+use crate::fixnum::{Num, Vector2D};
+
+use super::affine::AffineMatrix;
+
+/// How much of the animation is spent getting to the overshoot when bouncing, in quarters.
+const BOUNCE_QUARTERS: u32 = 3;
+
+/// A scale animated over some frames, see the [module level documentation](self).
+#[derive(Clone, Debug)]
+pub struct ZoomAnimation {
+    from: Num<i32, 8>,
+    to: Num<i32, 8>,
+    overshoot: Num<i32, 8>,
+    duration: u8,
+    frame: u32,
+    cached: Option<(Num<i32, 8>, AffineMatrix)>,
+}
+
+impl ZoomAnimation {
+    /// Zooms from `from_scale` up or down to full size over `duration` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from_scale` isn't positive.
+    #[must_use]
+    pub fn zoom_in(from_scale: Num<i32, 8>, duration: u8) -> Self {
+        Self::new(from_scale, 1.into(), duration)
+    }
+
+    /// Zooms from full size to `to_scale` over `duration` frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_scale` isn't positive.
+    #[must_use]
+    pub fn zoom_out(to_scale: Num<i32, 8>, duration: u8) -> Self {
+        Self::new(1.into(), to_scale, duration)
+    }
+
+    fn new(from: Num<i32, 8>, to: Num<i32, 8>, duration: u8) -> Self {
+        assert!(
+            from > 0.into() && to > 0.into(),
+            "a sprite can only be zoomed by a positive scale"
+        );
+
+        Self {
+            from,
+            to,
+            overshoot: 0.into(),
+            duration,
+            frame: 0,
+            cached: None,
+        }
+    }
+
+    /// Makes the zoom go `overshoot` past its target during the first three quarters of the
+    /// animation, and settle back to the target in the last quarter. An animation only one
+    /// frame long is too short to bounce, and goes straight to the target.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `overshoot` is negative, or would take the scale to 0 or below.
+    #[must_use]
+    pub fn with_bounce(mut self, overshoot: Num<i32, 8>) -> Self {
+        assert!(overshoot >= 0.into(), "the overshoot can't be negative");
+
+        self.overshoot = overshoot;
+        assert!(
+            self.peak() > 0.into(),
+            "the overshoot would take the scale to 0 or below"
+        );
+
+        self
+    }
+
+    /// The matrix for this frame's scale, moving the animation on a frame, or `None` once the
+    /// animation has finished. The last matrix is always for the target scale.
+    pub fn update(&mut self) -> Option<AffineMatrix> {
+        if self.is_finished() {
+            return None;
+        }
+
+        let scale = self.scale();
+        self.frame += 1;
+
+        match self.cached {
+            Some((cached_scale, matrix)) if cached_scale == scale => Some(matrix),
+            _ => {
+                // the matrix maps screen space to sprite space, so holds the inverse of the scale
+                let inverse = Num::new(1) / scale;
+                let matrix = AffineMatrix::from_scale(Vector2D::new(inverse, inverse));
+                self.cached = Some((scale, matrix));
+
+                Some(matrix)
+            }
+        }
+    }
+
+    /// The scale for the frame the next [`update`](ZoomAnimation::update) returns, or the
+    /// target once the animation has finished.
+    #[must_use]
+    pub fn scale(&self) -> Num<i32, 8> {
+        let frame = self.frame.min(u32::from(self.duration));
+        let duration = u32::from(self.duration);
+
+        if duration == 0 {
+            return self.to;
+        }
+
+        if self.overshoot == 0.into() {
+            return lerp(self.from, self.to, frame, duration);
+        }
+
+        let split = duration * BOUNCE_QUARTERS / 4;
+        if split == 0 {
+            // too short for the bounce to have a frame of its own, and the last frame has to be
+            // the target
+            return lerp(self.from, self.to, frame, duration);
+        }
+
+        if frame <= split {
+            lerp(self.from, self.peak(), frame, split)
+        } else {
+            lerp(self.peak(), self.to, frame - split, duration - split)
+        }
+    }
+
+    /// Whether every frame of the animation has been returned by
+    /// [`update`](ZoomAnimation::update).
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.frame > u32::from(self.duration)
+    }
+
+    /// The scale the bounce goes out to, past the target in the direction of the zoom.
+    fn peak(&self) -> Num<i32, 8> {
+        if self.to >= self.from {
+            self.to + self.overshoot
+        } else {
+            self.to - self.overshoot
+        }
+    }
+}
+
+fn lerp(from: Num<i32, 8>, to: Num<i32, 8>, step: u32, steps: u32) -> Num<i32, 8> {
+    from + (to - from) * step as i32 / steps as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixnum::num;
+
+    use super::*;
+
+    #[test_case]
+    fn zooms_in_with_a_bounce(_gba: &mut crate::Gba) {
+        let mut zoom = ZoomAnimation::zoom_in(num!(0.5), 8).with_bounce(num!(0.5));
+
+        let mut scales = [Num::new(0); 9];
+        for scale in &mut scales {
+            *scale = zoom.scale();
+            assert!(zoom.update().is_some());
+        }
+
+        assert_eq!(scales[0], num!(0.5));
+        assert_eq!(scales[6], num!(1.5));
+        assert_eq!(scales[8], num!(1.0));
+        assert!(zoom.update().is_none());
+        assert!(zoom.is_finished());
+
+        let mut zoom_out = ZoomAnimation::zoom_out(num!(2.0), 0);
+        assert_eq!(
+            zoom_out.update(),
+            Some(AffineMatrix::from_scale(Vector2D::new(
+                num!(0.5),
+                num!(0.5)
+            )))
+        );
+        assert_eq!(zoom_out.update(), None);
+    }
+
+    #[test_case]
+    fn short_bounces_end_on_the_target(_gba: &mut crate::Gba) {
+        let mut zoom = ZoomAnimation::zoom_in(num!(0.5), 1).with_bounce(num!(0.5));
+
+        assert_eq!(zoom.scale(), num!(0.5));
+        assert!(zoom.update().is_some());
+        assert_eq!(zoom.scale(), num!(1.0));
+        assert_eq!(
+            zoom.update(),
+            Some(AffineMatrix::from_scale(Vector2D::new(
+                num!(1.0),
+                num!(1.0)
+            )))
+        );
+        assert!(zoom.update().is_none());
+
+        // with 2 frames the bounce gets the middle one
+        let mut zoom = ZoomAnimation::zoom_in(num!(0.5), 2).with_bounce(num!(0.5));
+        let scales = [(); 3].map(|()| {
+            let scale = zoom.scale();
+            zoom.update();
+            scale
+        });
+        assert_eq!(scales, [num!(0.5), num!(1.5), num!(1.0)]);
+    }
+}
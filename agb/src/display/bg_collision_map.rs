@@ -0,0 +1,263 @@
+//! Tile based collision using a bitmask which matches a background's tile map.
+//!
+//! Lots of platformers use the background as the level's collision too, where each tile is
+//! either solid or not. A [`BgCollisionMap`] stores that as one bit per tile, usually generated
+//! alongside the tile map at build time and kept in ROM, and answers the questions physics code
+//! needs: whether a tile is solid, whether a rectangle touches any solid tiles, and where a ray
+//! first hits something.
+//!
+//! Positions passed to [`collides_rect`](BgCollisionMap::collides_rect) and
+//! [`raycast`](BgCollisionMap::raycast) are in pixels, with tile (0, 0) covering pixels
+//! (0, 0) to (7, 7). Anything outside the map isn't solid.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::{display::bg_collision_map::BgCollisionMap, fixnum::{Rect, Vector2D}};
+//!
+//! // a 16x2 tile map where the bottom row is solid
+//! static COLLISION: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+//! static MAP: BgCollisionMap = BgCollisionMap::new(&COLLISION, 16, 2);
+//!
+//! let player = Rect::new(Vector2D::new(20, 2), Vector2D::new(8, 16));
+//! let on_ground = MAP.collides_rect(player);
+//! # }
+//! ```
+
+use crate::fixnum::{Rect, Vector2D};
+
+const TILE_SIZE: i32 = 8;
+
+/// Which tiles of a map are solid, one bit per tile. See the [module level
+/// documentation](self).
+#[derive(Clone, Copy, Debug)]
+pub struct BgCollisionMap {
+    data: &'static [u8],
+    width: usize,
+    height: usize,
+}
+
+impl BgCollisionMap {
+    /// Creates a collision map for a `width` by `height` tile map.
+    ///
+    /// Bit `y * width + x` of `data` is set if the tile at (`x`, `y`) is solid, where bit 0 is
+    /// the lowest bit of the first byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is too short to have a bit for every tile.
+    #[must_use]
+    pub const fn new(data: &'static [u8], width: usize, height: usize) -> Self {
+        assert!(
+            data.len() >= (width * height).div_ceil(8),
+            "the collision data needs a bit for each tile"
+        );
+
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// The width of the map in tiles.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the map in tiles.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the tile at (`tile_x`, `tile_y`) is solid. Tiles outside the map aren't.
+    #[must_use]
+    pub fn is_solid(&self, tile_x: usize, tile_y: usize) -> bool {
+        if tile_x >= self.width || tile_y >= self.height {
+            return false;
+        }
+
+        let bit = tile_y * self.width + tile_x;
+        self.data[bit / 8] & (1 << (bit % 8)) != 0
+    }
+
+    fn is_solid_signed(&self, tile_x: i32, tile_y: i32) -> bool {
+        match (usize::try_from(tile_x), usize::try_from(tile_y)) {
+            (Ok(x), Ok(y)) => self.is_solid(x, y),
+            _ => false,
+        }
+    }
+
+    fn contains_tile(&self, tile_x: i32, tile_y: i32) -> bool {
+        usize::try_from(tile_x).is_ok_and(|x| x < self.width)
+            && usize::try_from(tile_y).is_ok_and(|y| y < self.height)
+    }
+
+    /// Whether any of the tiles which `rect` overlaps are solid. `rect` is in pixels, and
+    /// rectangles with no area don't collide with anything.
+    #[must_use]
+    pub fn collides_rect(&self, rect: Rect<i16>) -> bool {
+        if rect.size.x <= 0 || rect.size.y <= 0 {
+            return false;
+        }
+
+        let left = i32::from(rect.position.x);
+        let top = i32::from(rect.position.y);
+        let right = left + i32::from(rect.size.x) - 1;
+        let bottom = top + i32::from(rect.size.y) - 1;
+
+        (top.div_euclid(TILE_SIZE)..=bottom.div_euclid(TILE_SIZE)).any(|tile_y| {
+            (left.div_euclid(TILE_SIZE)..=right.div_euclid(TILE_SIZE))
+                .any(|tile_x| self.is_solid_signed(tile_x, tile_y))
+        })
+    }
+
+    /// Follows a ray from the pixel `start` in the direction `dir`, and returns the first solid
+    /// tile it passes through, including the one it starts in.
+    ///
+    /// Returns [`None`] if the ray goes more than `max_dist` pixels or leaves the map without
+    /// hitting anything, or if `dir` is zero. Every tile the ray touches is checked, so it
+    /// can't slip between two tiles which only meet at a corner.
+    #[must_use]
+    pub fn raycast(
+        &self,
+        start: Vector2D<i16>,
+        dir: Vector2D<i16>,
+        max_dist: u16,
+    ) -> Option<Vector2D<i16>> {
+        if dir.x == 0 && dir.y == 0 {
+            return None;
+        }
+
+        // work in half pixels from the centre of the start pixel, so the ray never starts
+        // exactly on a tile boundary
+        const TILE_HALF_PIXELS: i64 = TILE_SIZE as i64 * 2;
+
+        let start_x = 2 * i64::from(start.x) + 1;
+        let start_y = 2 * i64::from(start.y) + 1;
+
+        let mut tile_x = i32::from(start.x).div_euclid(TILE_SIZE);
+        let mut tile_y = i32::from(start.y).div_euclid(TILE_SIZE);
+
+        let step_x = i32::from(dir.x.signum());
+        let step_y = i32::from(dir.y.signum());
+        let dx = i64::from(dir.x).abs();
+        let dy = i64::from(dir.y).abs();
+
+        // the distance along each axis, in half pixels, to the next tile boundary the ray
+        // crosses on that axis
+        let first_boundary = |start: i64, tile: i32, step: i32| match step {
+            1 => (i64::from(tile) + 1) * TILE_HALF_PIXELS - start,
+            _ => start - i64::from(tile) * TILE_HALF_PIXELS,
+        };
+        let mut next_x = first_boundary(start_x, tile_x, step_x);
+        let mut next_y = first_boundary(start_y, tile_y, step_y);
+
+        // the ray reaches `along` half pixels along an axis where it moves `speed` per unit of
+        // dir after along / speed units, so is along * |dir| / speed half pixels from the start
+        let length_squared = (dx * dx + dy * dy) as u64;
+        let max_half_pixels = u64::from(max_dist) * 2;
+        let too_far = |along: i64, speed: i64| {
+            let (along, speed) = (along as u64, speed as u64);
+            // the right hand side is below 2^64 since max_half_pixels < 2^17 and speed <= 2^15.
+            // If the left hand side overflows then the ray is over 2^17 half pixels away, which
+            // is further than max_dist can go
+            along
+                .checked_mul(along)
+                .and_then(|along_squared| along_squared.checked_mul(length_squared))
+                .is_none_or(|reached| reached > max_half_pixels * max_half_pixels * speed * speed)
+        };
+
+        loop {
+            if !self.contains_tile(tile_x, tile_y) {
+                return None;
+            }
+
+            if self.is_solid_signed(tile_x, tile_y) {
+                return Some(Vector2D::new(tile_x as i16, tile_y as i16));
+            }
+
+            // step along whichever axis reaches its next boundary first. An axis the ray
+            // doesn't move along never reaches one
+            let x_first = dy == 0 || (dx != 0 && next_x * dy < next_y * dx);
+
+            if x_first {
+                if too_far(next_x, dx) {
+                    return None;
+                }
+
+                tile_x += step_x;
+                next_x += TILE_HALF_PIXELS;
+            } else {
+                if too_far(next_y, dy) {
+                    return None;
+                }
+
+                tile_y += step_y;
+                next_y += TILE_HALF_PIXELS;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8x4 tiles:
+    // ........
+    // ...#....
+    // ......#.
+    // ########
+    static DATA: [u8; 4] = [0b0000_0000, 0b0000_1000, 0b0100_0000, 0b1111_1111];
+    static MAP: BgCollisionMap = BgCollisionMap::new(&DATA, 8, 4);
+
+    #[test_case]
+    fn finds_solid_tiles(_gba: &mut crate::Gba) {
+        assert!(MAP.is_solid(3, 1));
+        assert!(MAP.is_solid(6, 2));
+        assert!(!MAP.is_solid(4, 1));
+        assert!(!MAP.is_solid(8, 3));
+
+        let rect = |x, y, w, h| Rect::new(Vector2D::new(x, y), Vector2D::new(w, h));
+
+        assert!(MAP.collides_rect(rect(20, 4, 8, 8)));
+        assert!(!MAP.collides_rect(rect(0, 0, 24, 8)));
+        // touches pixel 23 but not 24, where tile 3 starts
+        assert!(!MAP.collides_rect(rect(16, 8, 8, 8)));
+        assert!(MAP.collides_rect(rect(17, 8, 8, 8)));
+        assert!(MAP.collides_rect(rect(-10, -10, 20, 40)));
+        assert!(!MAP.collides_rect(rect(-10, -10, 5, 40)));
+        assert!(!MAP.collides_rect(rect(26, 10, 0, 8)));
+    }
+
+    #[test_case]
+    fn raycasts_hit_the_first_solid_tile(_gba: &mut crate::Gba) {
+        let v = Vector2D::new;
+
+        // straight down onto the floor
+        assert_eq!(MAP.raycast(v(4, 4), v(0, 1), 100), Some(v(0, 3)));
+        // not far enough to reach it
+        assert_eq!(MAP.raycast(v(4, 4), v(0, 1), 19), None);
+        assert_eq!(MAP.raycast(v(4, 4), v(0, 1), 20), Some(v(0, 3)));
+        // right along the second row into the block
+        assert_eq!(MAP.raycast(v(0, 12), v(5, 0), 100), Some(v(3, 1)));
+        // off the edge of the map
+        assert_eq!(MAP.raycast(v(0, 4), v(1, 0), 1000), None);
+        assert_eq!(MAP.raycast(v(0, 4), v(-1, -1), 1000), None);
+        // shallowly down and right, crossing into the second row before reaching (3, 1)
+        assert_eq!(MAP.raycast(v(12, 4), v(2, 1), 100), Some(v(3, 1)));
+        // steeply down and right, missing (3, 1) and hitting the floor
+        assert_eq!(MAP.raycast(v(12, 4), v(1, 2), 100), Some(v(2, 3)));
+        // starting inside a solid tile
+        assert_eq!(MAP.raycast(v(50, 20), v(1, 0), 0), Some(v(6, 2)));
+        assert_eq!(MAP.raycast(v(4, 4), v(0, 0), 100), None);
+        // the largest directions and distance don't overflow
+        assert_eq!(MAP.raycast(v(4, 4), v(1, i16::MAX), u16::MAX), Some(v(0, 3)));
+        assert_eq!(MAP.raycast(v(0, 12), v(i16::MAX, -1), u16::MAX), Some(v(3, 1)));
+        assert_eq!(MAP.raycast(v(0, 4), v(i16::MIN, i16::MIN), u16::MAX), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Updating a tile map by only writing the tiles which changed.
+//!
+//! Rewriting a whole screen block every frame wastes VRAM bandwidth when only a few tiles
+//! change. [`BgMapDiff`] remembers what is in VRAM as well as what should be there, and
+//! [`flush`](BgMapDiff::flush) only writes the entries which differ.
+
+use crate::bitarray::Bitarray;
+
+use super::{bg_tile_replace::set_screen_entry, video_ram_map::screen_block_address};
+
+/// A `W` by `H` tile map which keeps track of what it last wrote to VRAM.
+///
+/// `W` and `H` can be at most 32, the size of a screen block.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::display::bg_map_diff::BgMapDiff;
+///
+/// let mut map = BgMapDiff::<30, 20>::new();
+/// // open a door
+/// map.set_tile(4, 7, 0x0012);
+/// map.set_tile(4, 8, 0x0013);
+///
+/// let vblank = agb::interrupt::VBlank::get();
+/// vblank.wait_for_vblank();
+/// map.flush(28);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BgMapDiff<const W: usize, const H: usize> {
+    current: [[u16; W]; H],
+    desired: [[u16; W]; H],
+    /// Entries whose value in VRAM isn't known, so are written whatever `current` says. Each
+    /// row is a word, indexed by `y * 32 + x`.
+    stale: Bitarray<H>,
+    dirty_cells: usize,
+    last_flush_writes: usize,
+}
+
+impl<const W: usize, const H: usize> BgMapDiff<W, H> {
+    /// Creates a map where every entry is 0, which assumes the screen block is already all
+    /// zeros. Call [`invalidate`](BgMapDiff::invalidate) if it might not be.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `W` or `H` is more than 32.
+    #[must_use]
+    pub const fn new() -> Self {
+        assert!(W <= 32 && H <= 32, "a screen block is only 32x32 tiles");
+
+        Self {
+            current: [[0; W]; H],
+            desired: [[0; W]; H],
+            stale: Bitarray::new(),
+            dirty_cells: 0,
+            last_flush_writes: 0,
+        }
+    }
+
+    /// Sets the screen entry at (`x`, `y`) which will be written by the next
+    /// [`flush`](BgMapDiff::flush).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside the map.
+    pub fn set_tile(&mut self, x: usize, y: usize, entry: u16) {
+        let stale = self.is_stale(x, y);
+        let was_dirty = stale || self.desired[y][x] != self.current[y][x];
+        self.desired[y][x] = entry;
+        let is_dirty = stale || entry != self.current[y][x];
+
+        match (was_dirty, is_dirty) {
+            (false, true) => self.dirty_cells += 1,
+            (true, false) => self.dirty_cells -= 1,
+            _ => {}
+        }
+    }
+
+    /// The screen entry at (`x`, `y`) which will be in VRAM after the next flush.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside the map.
+    #[must_use]
+    pub fn tile(&self, x: usize, y: usize) -> u16 {
+        self.desired[y][x]
+    }
+
+    /// The number of entries which differ from what is in VRAM, and so will be written by the
+    /// next flush.
+    #[must_use]
+    pub fn dirty_cells(&self) -> usize {
+        self.dirty_cells
+    }
+
+    /// The number of entries written by the last flush.
+    #[must_use]
+    pub fn last_flush_writes(&self) -> usize {
+        self.last_flush_writes
+    }
+
+    /// Forgets what is in VRAM, so that the next flush writes every entry. Use this if
+    /// something else has written to the screen block.
+    pub fn invalidate(&mut self) {
+        for y in 0..H {
+            for x in 0..W {
+                self.stale.set(y * 32 + x, true);
+            }
+        }
+
+        self.dirty_cells = W * H;
+    }
+
+    fn is_stale(&self, x: usize, y: usize) -> bool {
+        self.stale.get(y * 32 + x) == Some(true)
+    }
+
+    /// Writes the entries which have changed since the last flush to `screen_block`. This is
+    /// best done during vblank so the changes all appear in the same frame.
+    pub fn flush(&mut self, screen_block: usize) {
+        self.last_flush_writes = 0;
+
+        if self.dirty_cells == 0 {
+            return;
+        }
+
+        for (y, (current_row, desired_row)) in
+            self.current.iter_mut().zip(&self.desired).enumerate()
+        {
+            for (x, (current, &desired)) in current_row.iter_mut().zip(desired_row).enumerate() {
+                if *current != desired || self.stale.get(y * 32 + x) == Some(true) {
+                    set_screen_entry(screen_block, x as u8, y as u8, desired);
+                    *current = desired;
+                    self.last_flush_writes += 1;
+                }
+            }
+        }
+
+        self.stale = Bitarray::new();
+        self.dirty_cells = 0;
+    }
+}
+
+impl<const W: usize, const H: usize> Default for BgMapDiff<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vram_entry(screen_block: usize, x: usize, y: usize) -> u16 {
+        unsafe {
+            (screen_block_address(screen_block) as *const u16)
+                .add(y * 32 + x)
+                .read_volatile()
+        }
+    }
+
+    #[test_case]
+    fn only_writes_changed_entries(_gba: &mut crate::Gba) {
+        let mut map = BgMapDiff::<4, 3>::new();
+        map.invalidate();
+        map.flush(30);
+        assert_eq!(map.last_flush_writes(), 12);
+        assert_eq!(vram_entry(30, 3, 2), 0);
+
+        map.set_tile(1, 2, 5);
+        map.set_tile(3, 0, 7);
+        map.set_tile(3, 0, 8);
+        map.set_tile(2, 2, 9);
+        map.set_tile(2, 2, 0);
+        assert_eq!(map.dirty_cells(), 2);
+
+        map.flush(30);
+        assert_eq!(map.last_flush_writes(), 2);
+        assert_eq!(map.dirty_cells(), 0);
+        assert_eq!(vram_entry(30, 1, 2), 5);
+        assert_eq!(vram_entry(30, 3, 0), 8);
+        assert_eq!(map.tile(3, 0), 8);
+
+        map.flush(30);
+        assert_eq!(map.last_flush_writes(), 0);
+    }
+
+    #[test_case]
+    fn invalidate_writes_every_entry_whatever_its_value(_gba: &mut crate::Gba) {
+        let mut map = BgMapDiff::<2, 2>::new();
+        map.set_tile(0, 0, 0x1234);
+        map.flush(30);
+
+        map.invalidate();
+        // the inverse of the old entry, which a sentinel value for stale entries could hit
+        map.set_tile(0, 0, !0x1234);
+        map.set_tile(1, 1, !0);
+        assert_eq!(map.dirty_cells(), 4);
+
+        map.flush(30);
+        assert_eq!(map.last_flush_writes(), 4);
+        assert_eq!(vram_entry(30, 0, 0), !0x1234);
+        assert_eq!(vram_entry(30, 1, 1), !0);
+
+        map.flush(30);
+        assert_eq!(map.last_flush_writes(), 0);
+    }
+}
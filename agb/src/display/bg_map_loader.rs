@@ -0,0 +1,140 @@
+//! Copying large background maps into video memory a screen block at a time.
+//!
+//! A single 32x32 screen block is 2KiB and copies quickly, but loading many of them at once
+//! for a big level can take long enough to drop frames. A [`BgMapLoader`] instead copies one
+//! screen block each time [`update`](BgMapLoader::update) is called, so calling it once per
+//! vblank spreads the work out while the game shows a loading screen.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # static LEVEL: [[u16; 1024]; 4] = [[0; 1024]; 4];
+//! # fn foo() {
+//! use agb::display::bg_map_loader::BgMapLoader;
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! let mut loader = BgMapLoader::new();
+//! loader.start_load(&LEVEL, 16);
+//!
+//! while !loader.is_complete() {
+//!     vblank.wait_for_vblank();
+//!     let remaining = loader.update();
+//!     // update the loading screen using remaining
+//! }
+//! # }
+//! ```
+
+use super::video_ram_map::{screen_block_address, VramLayout};
+
+const SCREEN_BLOCK_ENTRIES: usize = 32 * 32;
+const NUM_SCREEN_BLOCKS: usize = 32;
+
+/// Uploads a list of screen blocks to video memory, one per call to
+/// [`update`](BgMapLoader::update).
+#[derive(Default)]
+pub struct BgMapLoader {
+    blocks: &'static [[u16; SCREEN_BLOCK_ENTRIES]],
+    first_sb: usize,
+    next: usize,
+}
+
+impl BgMapLoader {
+    /// Creates a loader with nothing to load.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            blocks: &[],
+            first_sb: 0,
+            next: 0,
+        }
+    }
+
+    /// Starts loading `blocks` into consecutive screen blocks starting at `first_sb`, replacing
+    /// anything which was still being loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the blocks would go past the last screen block, 31.
+    pub fn start_load(&mut self, blocks: &'static [[u16; SCREEN_BLOCK_ENTRIES]], first_sb: usize) {
+        assert!(
+            first_sb + blocks.len() <= NUM_SCREEN_BLOCKS,
+            "screen blocks must be between 0 and 31"
+        );
+
+        self.blocks = blocks;
+        self.first_sb = first_sb;
+        self.next = 0;
+    }
+
+    /// Copies the next screen block into video memory, returning the number of blocks still to
+    /// be copied. Does nothing once loading is complete.
+    pub fn update(&mut self) -> usize {
+        if let Some(block) = self.blocks.get(self.next) {
+            let sb = self.first_sb + self.next;
+
+            debug_assert!(
+                VramLayout::current().validate_screen_block(sb),
+                "screen block {sb} is not usable in the current display mode"
+            );
+
+            let destination = unsafe {
+                core::slice::from_raw_parts_mut(
+                    screen_block_address(sb) as *mut u16,
+                    SCREEN_BLOCK_ENTRIES,
+                )
+            };
+            crate::dma::copy16(block, destination);
+
+            self.next += 1;
+        }
+
+        self.remaining()
+    }
+
+    /// The number of screen blocks still to be copied.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.blocks.len() - self.next
+    }
+
+    /// Whether every screen block has been copied.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static BLOCKS: [[u16; SCREEN_BLOCK_ENTRIES]; 2] = [
+        [0x1234; SCREEN_BLOCK_ENTRIES],
+        [0x5678; SCREEN_BLOCK_ENTRIES],
+    ];
+
+    #[test_case]
+    fn loads_one_block_per_update(gba: &mut crate::Gba) {
+        let (_gfx, _vram) = gba.display.video.tiled0();
+
+        let mut loader = BgMapLoader::new();
+        assert!(loader.is_complete());
+
+        loader.start_load(&BLOCKS, 30);
+        assert_eq!(loader.remaining(), 2);
+
+        assert_eq!(loader.update(), 1);
+        assert_eq!(loader.update(), 0);
+        assert!(loader.is_complete());
+        assert_eq!(loader.update(), 0);
+
+        let last_entry = |sb: usize| unsafe {
+            (screen_block_address(sb) as *const u16)
+                .add(SCREEN_BLOCK_ENTRIES - 1)
+                .read_volatile()
+        };
+        assert_eq!(last_entry(30), 0x1234);
+        assert_eq!(last_entry(31), 0x5678);
+    }
+}
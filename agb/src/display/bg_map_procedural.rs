@@ -0,0 +1,155 @@
+//! Filling background maps with tiles worked out at runtime, for procedurally generated levels.
+//!
+//! Rather than copying a map made ahead of time, a [`ProceduralMapFiller`] asks a closure for
+//! the screen entry at each position and writes it straight to VRAM. A whole screen block can be
+//! filled with [`fill_screen_block`](ProceduralMapFiller::fill_screen_block), or just part of
+//! one with [`fill_partial`](ProceduralMapFiller::fill_partial), such as a room which has been
+//! opened up.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::{
+//!     display::bg_map_procedural::ProceduralMapFiller,
+//!     fixnum::{Rect, Vector2D},
+//! };
+//!
+//! const WALL: u16 = 1;
+//! const FLOOR: u16 = 2;
+//!
+//! let filler = ProceduralMapFiller::new();
+//!
+//! // a border of wall around the edge of the screen block
+//! filler.fill_screen_block(28, &|x, y| {
+//!     if x == 0 || y == 0 || x == 31 || y == 31 {
+//!         WALL
+//!     } else {
+//!         FLOOR
+//!     }
+//! });
+//!
+//! // knock a hole in the middle
+//! let hole = Rect::new(Vector2D::new(12, 12), Vector2D::new(8, 4));
+//! filler.fill_partial(28, hole, &|_, _| 0);
+//! # }
+//! ```
+
+use agb_fixnum::{Rect, Vector2D};
+
+use super::{
+    bg_tile_replace::set_screen_entry,
+    video_ram_map::{screen_block_address, VramLayout},
+};
+
+const SCREEN_BLOCK_SIZE: u8 = 32;
+
+/// Writes screen entries returned by a closure into screen blocks, see the
+/// [module level documentation](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProceduralMapFiller {
+    origin_x: usize,
+    origin_y: usize,
+}
+
+impl ProceduralMapFiller {
+    /// A filler which passes each tile's position within the screen block to the closure.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            origin_x: 0,
+            origin_y: 0,
+        }
+    }
+
+    /// A filler which adds (`x`, `y`) to the positions passed to the closure. A level bigger than
+    /// one screen block can be filled a block at a time with the same closure by moving the
+    /// origin by 32 tiles for each block.
+    #[must_use]
+    pub const fn with_origin(x: usize, y: usize) -> Self {
+        Self {
+            origin_x: x,
+            origin_y: y,
+        }
+    }
+
+    /// Sets every entry in screen block `sb` to what `f` returns for its position.
+    ///
+    /// The entry is the tile index in bits 0-9, horizontal and vertical flip in bits 10 and 11
+    /// and the palette in bits 12-15.
+    pub fn fill_screen_block(&self, sb: usize, f: &impl Fn(usize, usize) -> u16) {
+        let whole = Rect::new(
+            Vector2D::new(0, 0),
+            Vector2D::new(SCREEN_BLOCK_SIZE, SCREEN_BLOCK_SIZE),
+        );
+
+        self.fill_partial(sb, whole, f);
+    }
+
+    /// Sets the entries inside `rect` in screen block `sb` to what `f` returns for their
+    /// positions, leaving the rest of the screen block alone. `f` isn't called for positions
+    /// outside `rect`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rect` goes outside the 32x32 screen block.
+    pub fn fill_partial(&self, sb: usize, rect: Rect<u8>, f: &impl Fn(usize, usize) -> u16) {
+        assert!(
+            usize::from(rect.position.x) + usize::from(rect.size.x) <= 32
+                && usize::from(rect.position.y) + usize::from(rect.size.y) <= 32,
+            "the rectangle must be inside the 32x32 screen block"
+        );
+
+        debug_assert!(
+            VramLayout::current().validate_screen_block(sb),
+            "screen block {sb} is not usable in the current display mode"
+        );
+
+        if rect.size.x == 0 || rect.size.y == 0 {
+            return;
+        }
+
+        for (x, y) in rect.iter() {
+            let entry = f(
+                self.origin_x + usize::from(x),
+                self.origin_y + usize::from(y),
+            );
+            set_screen_entry(sb, x, y, entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sb: usize, x: usize, y: usize) -> u16 {
+        unsafe {
+            (screen_block_address(sb) as *const u16)
+                .add(y * 32 + x)
+                .read_volatile()
+        }
+    }
+
+    #[test_case]
+    fn fills_whole_and_partial_screen_blocks(gba: &mut crate::Gba) {
+        let (_gfx, _vram) = gba.display.video.tiled0();
+
+        let filler = ProceduralMapFiller::with_origin(64, 0);
+        filler.fill_screen_block(30, &|x, y| (x + y * 100) as u16);
+
+        assert_eq!(entry(30, 0, 0), 64);
+        assert_eq!(entry(30, 31, 31), 95 + 3100);
+
+        let rect = Rect::new(Vector2D::new(30, 2), Vector2D::new(2, 3));
+        ProceduralMapFiller::new().fill_partial(30, rect, &|x, y| {
+            assert!(x >= 30 && (2..5).contains(&y));
+            0xffff
+        });
+
+        assert_eq!(entry(30, 30, 2), 0xffff);
+        assert_eq!(entry(30, 31, 4), 0xffff);
+        assert_eq!(entry(30, 29, 2), 93 + 200);
+        assert_eq!(entry(30, 30, 5), 94 + 500);
+    }
+}
@@ -0,0 +1,154 @@
+//! Animating regions of a background by rewriting screen block entries directly.
+//!
+//! This is intended for small decorative animations like torches or fountains where
+//! re-committing an entire [`RegularMap`](super::tiled::RegularMap) every frame would be
+//! wasteful. Each [`AnimatedRegion`] describes a run of `N` consecutive screen block entries
+//! along with the entries to write for each frame of the animation.
+//!
+//! The tile entries written are raw screen block entries (tile index, flip bits and palette),
+//! so the tiles they refer to must already be resident in VRAM.
+
+use alloc::vec::Vec;
+
+use crate::interrupt::interrupt_free;
+
+use super::video_ram_map::{screen_block_address, VramLayout};
+
+const SCREEN_BLOCK_ENTRIES: usize = 32 * 32;
+
+/// A run of `N` screen block entries which cycle through `frame_data`.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimatedRegion<const N: usize> {
+    /// The screen block containing the region, between 0 and 31.
+    pub screen_block: usize,
+    /// Index of the first entry within the screen block to write to.
+    pub tile_offset: u16,
+    /// The raw screen block entries for each frame of the animation.
+    pub frame_data: &'static [[u16; N]],
+    /// How many frames each animation frame is shown for. A value of 0 is treated as 1.
+    pub frame_duration: u8,
+}
+
+impl<const N: usize> AnimatedRegion<N> {
+    fn current_frame(&self, frame: u32) -> &[u16; N] {
+        let duration = u32::from(self.frame_duration.max(1));
+        let index = (frame / duration) as usize % self.frame_data.len();
+
+        &self.frame_data[index]
+    }
+
+    fn screen_block_memory(&self) -> *mut u16 {
+        screen_block_address(self.screen_block) as *mut u16
+    }
+}
+
+/// Plays back a collection of [`AnimatedRegion`]s.
+///
+/// Call [`update`](BgTileAnimationPlayer::update) once per frame, ideally straight after
+/// waiting for vblank so that the writes land before the screen starts drawing.
+#[derive(Default)]
+pub struct BgTileAnimationPlayer<const N: usize> {
+    regions: Vec<AnimatedRegion<N>>,
+}
+
+impl<const N: usize> BgTileAnimationPlayer<N> {
+    /// Creates a player with no regions.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+        }
+    }
+
+    /// Adds a region to be animated. Regions are written in the order they are added, so
+    /// where regions overlap the most recently added one wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region doesn't fit within a single screen block or has no frames.
+    pub fn add_region(&mut self, region: AnimatedRegion<N>) {
+        assert!(
+            region.screen_block < 32,
+            "screen block must be less than 32"
+        );
+        assert!(
+            region.tile_offset as usize + N <= SCREEN_BLOCK_ENTRIES,
+            "animated region must fit within a single screen block"
+        );
+        assert!(
+            !region.frame_data.is_empty(),
+            "animated region must have at least one frame"
+        );
+
+        self.regions.push(region);
+    }
+
+    /// Removes all the regions from the player. This does not change what is currently in VRAM.
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// The regions currently being animated.
+    #[must_use]
+    pub fn regions(&self) -> &[AnimatedRegion<N>] {
+        &self.regions
+    }
+
+    /// Writes the entries for `frame` for every region to VRAM.
+    ///
+    /// Interrupts are disabled while writing so that all regions are updated together.
+    pub fn update(&self, frame: u32) {
+        debug_assert!(
+            self.regions
+                .iter()
+                .all(|region| VramLayout::current().validate_screen_block(region.screen_block)),
+            "animated region is in a screen block which is not usable in the current display mode"
+        );
+
+        interrupt_free(|_| {
+            for region in &self.regions {
+                let memory = region.screen_block_memory();
+
+                for (i, &entry) in region.current_frame(frame).iter().enumerate() {
+                    unsafe {
+                        memory
+                            .add(region.tile_offset as usize + i)
+                            .write_volatile(entry);
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FRAMES: [[u16; 2]; 3] = [[1, 2], [3, 4], [5, 6]];
+
+    #[test_case]
+    fn writes_current_frame_to_screen_block(_gba: &mut crate::Gba) {
+        let mut player = BgTileAnimationPlayer::new();
+        player.add_region(AnimatedRegion {
+            screen_block: 31,
+            tile_offset: 10,
+            frame_data: &FRAMES,
+            frame_duration: 4,
+        });
+
+        let memory = screen_block_address(31) as *const u16;
+
+        for (frame, expected) in [(0, [1, 2]), (5, [3, 4]), (11, [5, 6]), (12, [1, 2])] {
+            player.update(frame);
+
+            let written = unsafe {
+                [
+                    memory.add(10).read_volatile(),
+                    memory.add(11).read_volatile(),
+                ]
+            };
+            assert_eq!(written, expected, "frame {frame}");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Changing single tiles of a background directly in VRAM.
+//!
+//! Puzzle games often change one tile at a time, such as opening a door or picking up an item.
+//! These functions write the screen entry straight into the screen block, which is a single
+//! 16-bit write and so can be done at any time.
+//!
+//! A [`RegularMap`](super::tiled::RegularMap) keeps its own copy of its tiles and writes all of
+//! them when it is committed, which would overwrite changes made here. Use these functions for
+//! backgrounds whose screen blocks you manage yourself, or use
+//! [`RegularMap::set_tile`](super::tiled::RegularMap::set_tile) instead.
+
+use super::video_ram_map::screen_block_address;
+
+const SCREEN_BLOCK_COUNT: usize = 32;
+
+const HFLIP: u16 = 1 << 10;
+const VFLIP: u16 = 1 << 11;
+
+fn screen_entry_ptr(screen_block: usize, x: u8, y: u8) -> *mut u16 {
+    assert!(
+        screen_block < SCREEN_BLOCK_COUNT,
+        "there are only {SCREEN_BLOCK_COUNT} screen blocks"
+    );
+    assert!(
+        x < 32 && y < 32,
+        "({x}, {y}) is outside the 32x32 screen block"
+    );
+
+    (screen_block_address(screen_block) as *mut u16)
+        .wrapping_add(usize::from(y) * 32 + usize::from(x))
+}
+
+/// Sets the screen entry at (`x`, `y`) in `screen_block` to `entry`.
+///
+/// The entry is the tile index in bits 0-9, horizontal and vertical flip in bits 10 and 11 and
+/// the palette in bits 12-15.
+///
+/// # Panics
+///
+/// Panics if `screen_block` isn't one of the 32 screen blocks, or (`x`, `y`) is outside it.
+pub fn set_screen_entry(screen_block: usize, x: u8, y: u8, entry: u16) {
+    // Safety: the pointer is within the screen blocks in VRAM, and VRAM supports 16-bit writes
+    unsafe { screen_entry_ptr(screen_block, x, y).write_volatile(entry) };
+}
+
+fn toggle_bits(screen_block: usize, x: u8, y: u8, bits: u16) {
+    let ptr = screen_entry_ptr(screen_block, x, y);
+
+    // Safety: the pointer is within the screen blocks in VRAM
+    unsafe { ptr.write_volatile(ptr.read_volatile() ^ bits) };
+}
+
+/// Flips the tile at (`x`, `y`) in `screen_block` horizontally, leaving its tile index and
+/// palette alone. Toggling it twice puts the tile back as it was.
+///
+/// # Panics
+///
+/// Panics if `screen_block` isn't one of the 32 screen blocks, or (`x`, `y`) is outside it.
+pub fn toggle_tile_hflip(screen_block: usize, x: u8, y: u8) {
+    toggle_bits(screen_block, x, y, HFLIP);
+}
+
+/// Flips the tile at (`x`, `y`) in `screen_block` vertically, leaving its tile index and
+/// palette alone. Toggling it twice puts the tile back as it was.
+///
+/// # Panics
+///
+/// Panics if `screen_block` isn't one of the 32 screen blocks, or (`x`, `y`) is outside it.
+pub fn toggle_tile_vflip(screen_block: usize, x: u8, y: u8) {
+    toggle_bits(screen_block, x, y, VFLIP);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn writes_and_flips_single_entries(_gba: &mut crate::Gba) {
+        let entry_at = |x: u8, y: u8| unsafe { screen_entry_ptr(31, x, y).read_volatile() };
+
+        set_screen_entry(31, 3, 2, 0x2005);
+        set_screen_entry(31, 31, 31, 0x0123);
+
+        assert_eq!(
+            unsafe {
+                ((screen_block_address(31) + (2 * 32 + 3) * 2) as *const u16).read_volatile()
+            },
+            0x2005
+        );
+
+        toggle_tile_hflip(31, 3, 2);
+        assert_eq!(entry_at(3, 2), 0x2405);
+        toggle_tile_vflip(31, 3, 2);
+        assert_eq!(entry_at(3, 2), 0x2c05);
+        toggle_tile_hflip(31, 3, 2);
+        toggle_tile_vflip(31, 3, 2);
+        assert_eq!(entry_at(3, 2), 0x2005);
+
+        assert_eq!(entry_at(31, 31), 0x0123);
+    }
+}
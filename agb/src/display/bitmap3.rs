@@ -38,6 +38,20 @@ impl Bitmap3<'_> {
         BITMAP_MODE_3.get(x, y)
     }
 
+    /// The pixels of row `y`, for writing a whole run of them at once.
+    pub(crate) fn row_mut(&mut self, y: usize) -> &mut [u16] {
+        assert!(y < HEIGHT as usize, "row {y} is off the screen");
+
+        // Safety: the row is in bounds, and borrowing the bitmap mutably stops anything else
+        // writing to it while the row is borrowed
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                (0x600_0000 as *mut u16).add(y * WIDTH as usize),
+                WIDTH as usize,
+            )
+        }
+    }
+
     pub fn clear(&mut self, colour: u16) {
         for y in 0..(HEIGHT as usize) {
             for x in 0..(WIDTH as usize) {
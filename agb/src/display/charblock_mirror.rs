@@ -0,0 +1,78 @@
+//! Generating horizontally flipped copies of 4bpp tiles in VRAM.
+//!
+//! Background tiles can be flipped in the tile map for free, but some effects (such as copying
+//! tiles to objects, or tile sets where the flipped tile needs different neighbours) need the
+//! flipped pixels themselves. [`mirror_charblock`] produces them from tiles already in VRAM.
+
+use super::video_ram_map::charblock_address;
+
+const CHARBLOCK_COUNT: usize = 6;
+const TILES_PER_CHARBLOCK: usize = 512;
+
+/// Reverses the order of the eight 4 bit pixels in a row of a 4bpp tile.
+#[must_use]
+const fn mirror_row(row: u32) -> u32 {
+    let row = row.swap_bytes();
+    ((row >> 4) & 0x0f0f_0f0f) | ((row & 0x0f0f_0f0f) << 4)
+}
+
+/// Writes horizontally flipped copies of the first `tile_count` 4bpp tiles in charblock
+/// `src_block` to the same positions in charblock `dst_block`.
+///
+/// Charblocks 0 to 3 hold background tiles and 4 and 5 hold object tiles. `src_block` and
+/// `dst_block` can be the same, in which case the tiles are flipped in place. Each row of a
+/// tile is a single word, so flipping 256 tiles takes well under a vblank.
+///
+/// # Panics
+///
+/// Panics if either charblock doesn't exist, or if `tile_count` is more than the 512 tiles in a
+/// charblock.
+pub fn mirror_charblock(src_block: usize, dst_block: usize, tile_count: usize) {
+    assert!(
+        src_block < CHARBLOCK_COUNT && dst_block < CHARBLOCK_COUNT,
+        "there are only {CHARBLOCK_COUNT} charblocks"
+    );
+    assert!(
+        tile_count <= TILES_PER_CHARBLOCK,
+        "a charblock holds at most {TILES_PER_CHARBLOCK} tiles"
+    );
+
+    let src = charblock_address(src_block) as *const u32;
+    let dst = charblock_address(dst_block) as *mut u32;
+
+    // a 4bpp tile is 8 rows of one word each
+    for row in 0..tile_count * 8 {
+        // Safety: both charblocks are in vram and row is within them
+        unsafe {
+            let pixels = src.add(row).read_volatile();
+            dst.add(row).write_volatile(mirror_row(pixels));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn mirrors_each_row(_gba: &mut crate::Gba) {
+        assert_eq!(mirror_row(0x8765_4321), 0x1234_5678);
+        assert_eq!(mirror_row(0x0000_000f), 0xf000_0000);
+
+        let src = charblock_address(2) as *mut u32;
+        let dst = charblock_address(3) as *const u32;
+
+        for row in 0..16 {
+            unsafe { src.add(row).write_volatile(0x0000_00a1 + row as u32) };
+        }
+
+        mirror_charblock(2, 3, 2);
+
+        for row in 0..16 {
+            assert_eq!(
+                unsafe { dst.add(row).read_volatile() },
+                mirror_row(0x0000_00a1 + row as u32)
+            );
+        }
+    }
+}
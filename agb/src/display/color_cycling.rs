@@ -0,0 +1,379 @@
+//! Animating palette colours by rotating their hue, for neon signs, rainbows and psychedelic
+//! backgrounds.
+//!
+//! Each colour animated by a [`HsvPaletteAnimator`] is kept as an [`Hsv`] colour, so its hue
+//! can be turned smoothly while its saturation and brightness stay the same, which doesn't
+//! work well when adding to the red, green and blue of the colour directly. Only palette RAM is
+//! changed, so everything using the animated colours changes with them without touching any
+//! tiles.
+//!
+//! Palettes are numbered from 0 to 31, where 0 to 15 are the background palettes and 16 to 31
+//! are the object palettes.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::color_cycling::HsvPaletteAnimator;
+//!
+//! let mut animator = HsvPaletteAnimator::new();
+//! // a sign whose colour slowly changes
+//! animator.cycle_hue(0, 1, 2);
+//! // a rainbow which moves along colours 8 to 15
+//! animator.cycle_range(0, 8, 16, 45);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     animator.update();
+//!
+//!     vblank.wait_for_vblank();
+//!     animator.commit();
+//! }
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::memory_mapped::MemoryMapped1DArray;
+
+const PALETTE_RAM: MemoryMapped1DArray<u16, 512> = unsafe { MemoryMapped1DArray::new(0x0500_0000) };
+
+/// How fast colours added by [`HsvPaletteAnimator::cycle_range`] cycle, in degrees per frame.
+pub const RANGE_DEGREES_PER_FRAME: u8 = 2;
+
+/// A colour as its hue, saturation and value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hsv {
+    /// The hue in degrees, from 0 up to but not including 360, where 0 is red, 120 is green and
+    /// 240 is blue.
+    pub hue: u16,
+    /// How colourful the colour is, from 0 for grey to 255 for the most colourful.
+    pub saturation: u8,
+    /// How bright the colour is, from 0 for black to 255 for the brightest.
+    pub value: u8,
+}
+
+impl Hsv {
+    /// The colour with the given hue, saturation and value. The hue wraps round at 360 degrees.
+    #[must_use]
+    pub const fn new(hue: u16, saturation: u8, value: u8) -> Self {
+        Self {
+            hue: hue % 360,
+            saturation,
+            value,
+        }
+    }
+
+    /// Converts a colour in the GBA's 15 bit format. Greys, which have no hue, get a hue of 0.
+    #[must_use]
+    pub fn from_rgb555(colour: u16) -> Self {
+        // scaled up to 8 bits, so that 31 becomes 255
+        let channel = |shift: u16| {
+            let channel = i32::from((colour >> shift) & 0x1f);
+            (channel << 3) | (channel >> 2)
+        };
+        let (red, green, blue) = (channel(0), channel(5), channel(10));
+
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let delta = max - min;
+
+        if delta == 0 {
+            return Self::new(0, 0, max as u8);
+        }
+
+        let hue = if max == red {
+            60 * (green - blue) / delta
+        } else if max == green {
+            120 + 60 * (blue - red) / delta
+        } else {
+            240 + 60 * (red - green) / delta
+        };
+
+        Self::new(
+            hue.rem_euclid(360) as u16,
+            (delta * 255 / max) as u8,
+            max as u8,
+        )
+    }
+
+    /// The colour in the GBA's 15 bit format, as used in palettes.
+    #[must_use]
+    pub fn to_rgb555(self) -> u16 {
+        let value = u32::from(self.value);
+        let saturation = u32::from(self.saturation);
+
+        let region = self.hue / 60;
+        // how far through the region the hue is, from 0 to 255
+        let remainder = u32::from(self.hue % 60) * 255 / 60;
+
+        let p = value * (255 - saturation) / 255;
+        let q = value * (255 - saturation * remainder / 255) / 255;
+        let t = value * (255 - saturation * (255 - remainder) / 255) / 255;
+
+        let (red, green, blue) = match region {
+            0 => (value, t, p),
+            1 => (q, value, p),
+            2 => (p, value, t),
+            3 => (p, q, value),
+            4 => (t, p, value),
+            _ => (value, p, q),
+        };
+
+        ((red >> 3) | ((green >> 3) << 5) | ((blue >> 3) << 10)) as u16
+    }
+
+    /// The same colour with its hue turned by `degrees`, wrapping round at 360.
+    #[must_use]
+    pub fn rotate_hue(self, degrees: u16) -> Self {
+        Self::new(
+            (self.hue + degrees % 360) % 360,
+            self.saturation,
+            self.value,
+        )
+    }
+}
+
+struct AnimatedColour {
+    /// Where the colour is in palette RAM.
+    index: usize,
+    /// The colour before its hue was turned.
+    base: Hsv,
+    /// The exact colour `base` was made from, if it was read from palette RAM. Converting to
+    /// HSV and back can be slightly off, so this is written back whenever the hue has turned
+    /// all the way round.
+    base_rgb555: Option<u16>,
+    /// How far the hue has turned from `base`, in degrees.
+    hue_offset: u16,
+    degrees_per_frame: u8,
+}
+
+impl AnimatedColour {
+    fn colour(&self) -> Hsv {
+        self.base.rotate_hue(self.hue_offset)
+    }
+
+    fn rgb555(&self) -> u16 {
+        match self.base_rgb555 {
+            Some(rgb555) if self.hue_offset == 0 => rgb555,
+            _ => self.colour().to_rgb555(),
+        }
+    }
+}
+
+/// Turns the hue of palette colours a little every frame, see the [module level
+/// documentation](self).
+#[derive(Default)]
+pub struct HsvPaletteAnimator {
+    colours: Vec<AnimatedColour>,
+}
+
+impl HsvPaletteAnimator {
+    /// An animator with no colours to animate.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts turning the hue of colour `color_idx` of palette `palette_idx` by
+    /// `degrees_per_frame` each frame, from the colour currently in palette RAM. If the colour
+    /// is already animated, this just changes its speed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `palette_idx` is 32 or more, or `color_idx` is 16 or more.
+    pub fn cycle_hue(&mut self, palette_idx: usize, color_idx: usize, degrees_per_frame: u8) {
+        let index = palette_ram_index(palette_idx, color_idx);
+
+        match self.colours.iter_mut().find(|colour| colour.index == index) {
+            Some(colour) => colour.degrees_per_frame = degrees_per_frame,
+            None => {
+                let rgb555 = PALETTE_RAM.get(index);
+                self.colours.push(AnimatedColour {
+                    index,
+                    base: Hsv::from_rgb555(rgb555),
+                    base_rgb555: Some(rgb555),
+                    hue_offset: 0,
+                    degrees_per_frame,
+                });
+            }
+        }
+    }
+
+    /// Sets the colour at `color_idx` of palette `palette_idx`, which is shown from the next
+    /// [`commit`](HsvPaletteAnimator::commit). The colour carries on cycling if it was already
+    /// animated, and is otherwise animated without moving until given a speed by
+    /// [`cycle_hue`](HsvPaletteAnimator::cycle_hue).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `palette_idx` is 32 or more, or `color_idx` is 16 or more.
+    pub fn set_colour(&mut self, palette_idx: usize, color_idx: usize, colour: Hsv) {
+        self.set_base(palette_ram_index(palette_idx, color_idx), colour, None);
+    }
+
+    fn set_base(&mut self, index: usize, base: Hsv, base_rgb555: Option<u16>) {
+        match self
+            .colours
+            .iter_mut()
+            .find(|animated| animated.index == index)
+        {
+            Some(animated) => {
+                animated.base = base;
+                animated.base_rgb555 = base_rgb555;
+                animated.hue_offset = 0;
+            }
+            None => self.colours.push(AnimatedColour {
+                index,
+                base,
+                base_rgb555,
+                hue_offset: 0,
+                degrees_per_frame: 0,
+            }),
+        }
+    }
+
+    /// Makes a rainbow from colour `start` up to but not including colour `end` of palette
+    /// `palette_idx`. The first colour is kept as it is in palette RAM, and each colour after it
+    /// has the same saturation and value with its hue turned another `offset_per_entry`
+    /// degrees. The colours all cycle at [`RANGE_DEGREES_PER_FRAME`], so the rainbow seems to
+    /// move along them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `palette_idx` is 32 or more, or `end` is more than 16.
+    pub fn cycle_range(
+        &mut self,
+        palette_idx: usize,
+        start: usize,
+        end: usize,
+        offset_per_entry: u8,
+    ) {
+        assert!(end <= 16, "palettes only have 16 colours");
+        if start >= end {
+            return;
+        }
+
+        let first_rgb555 = PALETTE_RAM.get(palette_ram_index(palette_idx, start));
+        let first = Hsv::from_rgb555(first_rgb555);
+
+        for (offset, color_idx) in (start..end).enumerate() {
+            let degrees = (offset * usize::from(offset_per_entry) % 360) as u16;
+            self.set_base(
+                palette_ram_index(palette_idx, color_idx),
+                first.rotate_hue(degrees),
+                (degrees == 0).then_some(first_rgb555),
+            );
+            self.cycle_hue(palette_idx, color_idx, RANGE_DEGREES_PER_FRAME);
+        }
+    }
+
+    /// Stops animating colour `color_idx` of palette `palette_idx`, leaving it as it was last
+    /// committed.
+    pub fn stop(&mut self, palette_idx: usize, color_idx: usize) {
+        let index = palette_ram_index(palette_idx, color_idx);
+        self.colours.retain(|colour| colour.index != index);
+    }
+
+    /// The current colour of colour `color_idx` of palette `palette_idx`, if it is animated.
+    #[must_use]
+    pub fn colour(&self, palette_idx: usize, color_idx: usize) -> Option<Hsv> {
+        let index = palette_ram_index(palette_idx, color_idx);
+        self.colours
+            .iter()
+            .find(|colour| colour.index == index)
+            .map(AnimatedColour::colour)
+    }
+
+    /// Moves every animated colour on by a frame. Call this once per frame.
+    pub fn update(&mut self) {
+        for animated in &mut self.colours {
+            animated.hue_offset =
+                (animated.hue_offset + u16::from(animated.degrees_per_frame)) % 360;
+        }
+    }
+
+    /// Writes the animated colours to palette RAM. Call this during vblank so the colours don't
+    /// change part way down the screen.
+    pub fn commit(&self) {
+        for animated in &self.colours {
+            PALETTE_RAM.set(animated.index, animated.rgb555());
+        }
+    }
+}
+
+fn palette_ram_index(palette_idx: usize, color_idx: usize) -> usize {
+    assert!(palette_idx < 32, "there are only 32 palettes");
+    assert!(color_idx < 16, "palettes only have 16 colours");
+
+    palette_idx * 16 + color_idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn converts_between_hsv_and_rgb(_gba: &mut crate::Gba) {
+        assert_eq!(Hsv::new(0, 255, 255).to_rgb555(), 0x001f);
+        assert_eq!(Hsv::new(120, 255, 255).to_rgb555(), 0x03e0);
+        assert_eq!(Hsv::new(240, 255, 255).to_rgb555(), 0x7c00);
+        assert_eq!(Hsv::new(60, 0, 255).to_rgb555(), 0x7fff);
+
+        assert_eq!(Hsv::from_rgb555(0x03ff), Hsv::new(60, 255, 255));
+        assert_eq!(Hsv::from_rgb555(0x4210), Hsv::new(0, 0, 132));
+
+        for colour in [0x001f, 0x03e0, 0x7c00, 0x7c1f, 0x1234, 0x7fff, 0] {
+            assert_eq!(Hsv::from_rgb555(colour).to_rgb555(), colour, "{colour:#x}");
+        }
+    }
+
+    #[test_case]
+    fn cycles_palette_colours(_gba: &mut crate::Gba) {
+        PALETTE_RAM.set(palette_ram_index(3, 1), 0x001f);
+        PALETTE_RAM.set(palette_ram_index(3, 4), 0x001f);
+
+        let mut animator = HsvPaletteAnimator::new();
+        animator.cycle_hue(3, 1, 60);
+        animator.cycle_range(3, 4, 7, 120);
+        assert_eq!(animator.colour(3, 6), Some(Hsv::new(240, 255, 255)));
+
+        animator.update();
+        animator.update();
+        animator.commit();
+
+        // red turned by 120 degrees is green
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(3, 1)), 0x03e0);
+        assert_eq!(animator.colour(3, 1), Some(Hsv::new(120, 255, 255)));
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(3, 5)), 0x0be0);
+
+        animator.stop(3, 1);
+        assert_eq!(animator.colour(3, 1), None);
+    }
+
+    #[test_case]
+    fn unturned_colours_are_written_back_exactly(_gba: &mut crate::Gba) {
+        // a colour which comes back slightly different after converting to HSV and back
+        const COLOUR: u16 = 0x0025;
+        assert_ne!(Hsv::from_rgb555(COLOUR).to_rgb555(), COLOUR);
+
+        PALETTE_RAM.set(palette_ram_index(2, 1), COLOUR);
+        PALETTE_RAM.set(palette_ram_index(2, 4), COLOUR);
+
+        let mut animator = HsvPaletteAnimator::new();
+        animator.cycle_hue(2, 1, 120);
+        animator.cycle_range(2, 4, 6, 0);
+        animator.commit();
+
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(2, 1)), COLOUR);
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(2, 4)), COLOUR);
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(2, 5)), COLOUR);
+
+        // and again once the hue has turned all the way round
+        for _ in 0..3 {
+            animator.update();
+        }
+        animator.commit();
+        assert_eq!(PALETTE_RAM.get(palette_ram_index(2, 1)), COLOUR);
+    }
+}
@@ -0,0 +1,268 @@
+//! Fading some layers in and out while leaving the rest alone, such as fading the level out
+//! behind a pause menu while the HUD stays put.
+//!
+//! A [`LayeredFadeManager`] fades layers by alpha blending them with whatever is underneath,
+//! using the [`Blend`] it is given. Each fade is started on a [`LayerSet`] with its own length
+//! and direction, and [`update`](LayeredFadeManager::update) moves every fade on by a frame. Two
+//! fades can't be on the same layer at once, which panics in debug builds.
+//!
+//! The GBA only has one pair of blend weights, so every layer which isn't fully faded in is drawn
+//! at the same weight. While more than one fade is running, that is the weight of the one
+//! started most recently, so fades which run at the same time should have the same length and
+//! direction, such as fading two backgrounds out together while the HUD and objects stay. Layers
+//! which have finished fading out are also drawn at that weight while another fade runs.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::display::color_fade_manager::{LayerSet, LayeredFadeManager};
+//!
+//! let mut fades = LayeredFadeManager::new(gba.display.blend.get());
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! // the game is paused, so fade out the level but not the HUD on BG0
+//! fades.fade_layers_out(LayerSet::BG1 | LayerSet::BG2 | LayerSet::OBJ, 16);
+//!
+//! while fades.is_fading(LayerSet::all()) {
+//!     fades.update();
+//!     vblank.wait_for_vblank();
+//!     fades.commit();
+//! }
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use bitflags::bitflags;
+
+use crate::fixnum::Num;
+
+use super::{
+    blend::{Blend, BlendMode, Layer},
+    tiled::BackgroundID,
+};
+
+/// The weight of a layer which is fully faded in.
+const FULL_WEIGHT: u8 = 16;
+
+bitflags! {
+    /// Layers which can be faded.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct LayerSet: u8 {
+        /// Background 0.
+        const BG0 = 1 << 0;
+        /// Background 1.
+        const BG1 = 1 << 1;
+        /// Background 2.
+        const BG2 = 1 << 2;
+        /// Background 3.
+        const BG3 = 1 << 3;
+        /// All the objects.
+        const OBJ = 1 << 4;
+    }
+}
+
+struct Fade {
+    layers: LayerSet,
+    from: u8,
+    to: u8,
+    frames: u8,
+    elapsed: u8,
+}
+
+impl Fade {
+    fn weight(&self) -> u8 {
+        let progress = i32::from(self.elapsed) * (i32::from(self.to) - i32::from(self.from))
+            / i32::from(self.frames);
+
+        (i32::from(self.from) + progress) as u8
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.frames
+    }
+}
+
+/// Fades which run on some layers at a time, see the [module level documentation](self).
+pub struct LayeredFadeManager<'gba> {
+    blend: Blend<'gba>,
+    fades: Vec<Fade>,
+    faded_out: LayerSet,
+}
+
+impl<'gba> LayeredFadeManager<'gba> {
+    /// Creates a manager which fades layers using `blend`, with every layer fully faded in.
+    #[must_use]
+    pub fn new(blend: Blend<'gba>) -> Self {
+        Self {
+            blend,
+            fades: Vec::new(),
+            faded_out: LayerSet::empty(),
+        }
+    }
+
+    /// Starts fading `layers` out over `frames` frames, or straight away if `frames` is 0. They
+    /// stay faded out until they are [faded back in](LayeredFadeManager::fade_layers_in).
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if any of `layers` are already fading.
+    pub fn fade_layers_out(&mut self, layers: LayerSet, frames: u8) {
+        self.start(layers, FULL_WEIGHT, 0, frames);
+    }
+
+    /// Starts fading `layers` back in over `frames` frames, or straight away if `frames` is 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if any of `layers` are already fading.
+    pub fn fade_layers_in(&mut self, layers: LayerSet, frames: u8) {
+        self.start(layers, 0, FULL_WEIGHT, frames);
+    }
+
+    fn start(&mut self, layers: LayerSet, from: u8, to: u8, frames: u8) {
+        debug_assert!(
+            !self.fading_layers().intersects(layers),
+            "{:?} are already fading",
+            self.fading_layers() & layers
+        );
+
+        self.faded_out.remove(layers);
+
+        if frames == 0 {
+            if to == 0 {
+                self.faded_out.insert(layers);
+            }
+        } else {
+            self.fades.push(Fade {
+                layers,
+                from,
+                to,
+                frames,
+                elapsed: 0,
+            });
+        }
+
+        self.update_blend();
+    }
+
+    /// Moves every fade on by a frame, finishing any which have run for their whole length. Call
+    /// this once a frame, followed by [`commit`](LayeredFadeManager::commit) during vblank.
+    pub fn update(&mut self) {
+        for fade in &mut self.fades {
+            fade.elapsed += 1;
+        }
+
+        for fade in self.fades.iter().filter(|fade| fade.is_finished()) {
+            if fade.to == 0 {
+                self.faded_out.insert(fade.layers);
+            }
+        }
+
+        self.update_blend();
+        self.fades.retain(|fade| !fade.is_finished());
+    }
+
+    /// Whether any of `layers` are in the middle of fading.
+    #[must_use]
+    pub fn is_fading(&self, layers: LayerSet) -> bool {
+        self.fading_layers().intersects(layers)
+    }
+
+    /// Whether all of `layers` have finished fading out.
+    #[must_use]
+    pub fn is_faded_out(&self, layers: LayerSet) -> bool {
+        self.faded_out.contains(layers)
+    }
+
+    /// Writes the blend settings for the current frame, which should be done during vblank.
+    pub fn commit(&self) {
+        self.blend.commit();
+    }
+
+    /// Stops every fade and gives back the [`Blend`], leaving every layer fully faded in once
+    /// it is committed.
+    #[must_use]
+    pub fn into_blend(mut self) -> Blend<'gba> {
+        self.blend.reset();
+        self.blend
+    }
+
+    fn fading_layers(&self) -> LayerSet {
+        self.fades
+            .iter()
+            .fold(LayerSet::empty(), |layers, fade| layers | fade.layers)
+    }
+
+    fn update_blend(&mut self) {
+        let faded = self.fading_layers() | self.faded_out;
+        let weight = self.fades.last().map_or(0, Fade::weight);
+
+        self.blend.reset();
+
+        if faded.is_empty() {
+            self.blend.set_blend_mode(BlendMode::Off);
+            return;
+        }
+
+        // the faded layers are blended with everything else underneath them
+        for (i, layer) in [LayerSet::BG0, LayerSet::BG1, LayerSet::BG2, LayerSet::BG3]
+            .into_iter()
+            .enumerate()
+        {
+            let target = if faded.contains(layer) {
+                Layer::Top
+            } else {
+                Layer::Bottom
+            };
+            self.blend
+                .set_background_enable(target, BackgroundID(i as u8), true);
+        }
+
+        let objects = if faded.contains(LayerSet::OBJ) {
+            Layer::Top
+        } else {
+            Layer::Bottom
+        };
+
+        self.blend
+            .set_object_enable(objects, true)
+            .set_backdrop_enable(Layer::Bottom, true)
+            .set_blend_weight(Layer::Top, Num::from_raw(weight))
+            .set_blend_weight(Layer::Bottom, Num::from_raw(FULL_WEIGHT - weight))
+            .set_blend_mode(BlendMode::Normal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn fades_run_independently(gba: &mut crate::Gba) {
+        let mut fades = LayeredFadeManager::new(gba.display.blend.get());
+
+        fades.fade_layers_out(LayerSet::BG1 | LayerSet::BG2, 2);
+        fades.fade_layers_out(LayerSet::OBJ, 4);
+        assert!(fades.is_fading(LayerSet::BG1));
+        assert!(!fades.is_fading(LayerSet::BG0));
+
+        fades.update();
+        fades.update();
+        assert!(fades.is_faded_out(LayerSet::BG1 | LayerSet::BG2));
+        assert!(fades.is_fading(LayerSet::OBJ));
+
+        fades.fade_layers_in(LayerSet::BG1, 0);
+        assert!(!fades.is_faded_out(LayerSet::BG1));
+        assert!(fades.is_faded_out(LayerSet::BG2));
+
+        fades.update();
+        fades.update();
+        assert!(!fades.is_fading(LayerSet::all()));
+        assert!(fades.is_faded_out(LayerSet::OBJ));
+
+        fades.commit();
+        fades.into_blend().commit();
+    }
+}
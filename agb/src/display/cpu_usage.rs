@@ -0,0 +1,157 @@
+//! Measuring how much of each frame the CPU spends working.
+//!
+//! A frame is counted as starting when [`VBlank::wait_for_vblank`] returns and ending when it
+//! is next called, so the time between is the time spent running your game. It is measured in
+//! scanlines using the `VCOUNT` register, where a whole frame is 228 scanlines. A frame which
+//! takes longer than 228 scanlines has missed a vblank.
+//!
+//! Nothing is measured unless you wait for vblank using [`VBlank`].
+//!
+//! For a quick visual check, [`set_raster_bar`] changes the backdrop colour while the CPU is
+//! busy. The coloured band at the top of the screen then shows how much time was used, a trick
+//! as old as raster displays.
+//!
+//! [`VBlank`]: crate::interrupt::VBlank
+//! [`VBlank::wait_for_vblank`]: crate::interrupt::VBlank::wait_for_vblank
+
+use portable_atomic::{AtomicU16, AtomicU32, AtomicUsize, Ordering};
+
+use super::VCOUNT;
+use crate::memory_mapped::MemoryMapped;
+
+const SCANLINES_PER_FRAME: usize = 228;
+const VBLANK_START: usize = 160;
+const AVERAGE_SHIFT: u32 = 4;
+const NO_RASTER_BAR: u32 = u32::MAX;
+const NO_SAVED_BACKDROP: u32 = u32::MAX;
+
+const BACKDROP: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0500_0000) };
+
+static FRAME_START: AtomicUsize = AtomicUsize::new(0);
+static LAST_FRAME: AtomicU16 = AtomicU16::new(0);
+static AVERAGE_SCALED: AtomicU32 = AtomicU32::new(0);
+
+static RASTER_BAR: AtomicU32 = AtomicU32::new(NO_RASTER_BAR);
+static SAVED_BACKDROP: AtomicU32 = AtomicU32::new(NO_SAVED_BACKDROP);
+
+/// How many scanlines the CPU spent working, see the [module level documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Scanlines used by the most recent frame.
+    pub last_frame_scanlines: u16,
+    /// A rolling average of the scanlines used by recent frames.
+    pub average_scanlines: u16,
+}
+
+impl FrameStats {
+    /// The most recent frame as a percentage of the time available in a frame. Can be more
+    /// than 100 if vblanks were missed.
+    #[must_use]
+    pub fn last_frame_percentage(&self) -> u32 {
+        u32::from(self.last_frame_scanlines) * 100 / SCANLINES_PER_FRAME as u32
+    }
+
+    /// The rolling average as a percentage of the time available in a frame.
+    #[must_use]
+    pub fn average_percentage(&self) -> u32 {
+        u32::from(self.average_scanlines) * 100 / SCANLINES_PER_FRAME as u32
+    }
+}
+
+/// The CPU usage of the last frame along with the rolling average.
+#[must_use]
+pub fn frame_stats() -> FrameStats {
+    FrameStats {
+        last_frame_scanlines: LAST_FRAME.load(Ordering::Relaxed),
+        average_scanlines: (AVERAGE_SCALED.load(Ordering::Relaxed) >> AVERAGE_SHIFT) as u16,
+    }
+}
+
+/// Sets the backdrop to `colour` while the CPU is busy each frame, or stops doing so if
+/// `colour` is `None`. The backdrop is put back to its previous colour while waiting for
+/// vblank.
+pub fn set_raster_bar(colour: Option<u16>) {
+    RASTER_BAR.store(colour.map_or(NO_RASTER_BAR, u32::from), Ordering::Relaxed);
+}
+
+/// A count of scanlines which only ever goes up, where 0 is the start of the first vblank.
+fn scanline_count(vblanks: usize) -> usize {
+    let line = VCOUNT.get() as usize;
+    vblanks * SCANLINES_PER_FRAME
+        + (line + SCANLINES_PER_FRAME - VBLANK_START) % SCANLINES_PER_FRAME
+}
+
+fn average(previous_scaled: u32, scanlines: u16) -> u32 {
+    previous_scaled - (previous_scaled >> AVERAGE_SHIFT) + u32::from(scanlines)
+}
+
+/// Called by [`VBlank`](crate::interrupt::VBlank) when it stops waiting.
+pub(crate) fn frame_started(vblanks: usize) {
+    FRAME_START.store(scanline_count(vblanks), Ordering::Relaxed);
+
+    let colour = RASTER_BAR.load(Ordering::Relaxed);
+    if colour != NO_RASTER_BAR {
+        SAVED_BACKDROP.store(u32::from(BACKDROP.get()), Ordering::Relaxed);
+        BACKDROP.set(colour as u16);
+    }
+}
+
+/// Called by [`VBlank`](crate::interrupt::VBlank) when it starts waiting.
+pub(crate) fn frame_finished(vblanks: usize) {
+    let used = scanline_count(vblanks).saturating_sub(FRAME_START.load(Ordering::Relaxed));
+    let used = used.min(u16::MAX as usize) as u16;
+
+    LAST_FRAME.store(used, Ordering::Relaxed);
+    AVERAGE_SCALED.store(
+        average(AVERAGE_SCALED.load(Ordering::Relaxed), used),
+        Ordering::Relaxed,
+    );
+
+    // only put back a colour saved this frame, the raster bar may have been turned on since
+    let saved = SAVED_BACKDROP.swap(NO_SAVED_BACKDROP, Ordering::Relaxed);
+    if saved != NO_SAVED_BACKDROP {
+        BACKDROP.set(saved as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn rolling_average_settles(_gba: &mut crate::Gba) {
+        let mut scaled = 0;
+        for _ in 0..200 {
+            scaled = average(scaled, 114);
+        }
+
+        let stats = FrameStats {
+            last_frame_scanlines: 114,
+            average_scanlines: (scaled >> AVERAGE_SHIFT) as u16,
+        };
+
+        assert!((110..=114).contains(&stats.average_scanlines));
+        assert_eq!(stats.last_frame_percentage(), 50);
+    }
+
+    #[test_case]
+    fn raster_bar_restores_backdrop_it_saved(_gba: &mut crate::Gba) {
+        let original = BACKDROP.get();
+        BACKDROP.set(0x1234);
+
+        // turned on part way through a frame, so there is nothing to put back yet
+        set_raster_bar(Some(0x001f));
+        frame_finished(0);
+        assert_eq!(BACKDROP.get(), 0x1234);
+
+        frame_started(0);
+        assert_eq!(BACKDROP.get(), 0x001f);
+
+        // turned off part way through a frame, the colour saved at the start still goes back
+        set_raster_bar(None);
+        frame_finished(0);
+        assert_eq!(BACKDROP.get(), 0x1234);
+
+        BACKDROP.set(original);
+    }
+}
@@ -0,0 +1,273 @@
+//! The map size bits of the background control registers.
+//!
+//! Bits 14 and 15 of a background's control register choose how big its map is, but what the
+//! value means depends on whether the background is a text or an affine one. Text maps use two
+//! bytes per tile, and maps bigger than 32x32 tiles are made of one 2KiB screen block for each
+//! 32x32 tile quarter. Affine maps use a byte per tile and are always square.
+//!
+//! [`BgMapSize`] names each of the eight possible sizes and works out how much VRAM the map
+//! needs, and [`BgControl`] builds a whole control register value around one.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::{
+//!     hardware_bg_scale::{BgControl, BgMapSize},
+//!     Priority,
+//! };
+//!
+//! let size = BgMapSize::S64x32;
+//! assert_eq!(size.screen_blocks_needed(), 2);
+//!
+//! let control = BgControl::new(size)
+//!     .screen_block(28)
+//!     .priority(Priority::P1)
+//!     .bits();
+//! # }
+//! ```
+
+use super::{
+    tiled::{AffineBackgroundSize, RegularBackgroundSize, TileFormat},
+    Priority,
+};
+
+const SCREEN_BLOCK_BYTES: usize = 0x800;
+
+/// The size of a background map. Text background sizes are given in tiles and affine
+/// background sizes in pixels, matching how they are usually written down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BgMapSize {
+    /// A text background 32 tiles wide and 32 tiles high.
+    S32x32,
+    /// A text background 64 tiles wide and 32 tiles high.
+    S64x32,
+    /// A text background 32 tiles wide and 64 tiles high.
+    S32x64,
+    /// A text background 64 tiles wide and 64 tiles high.
+    S64x64,
+    /// An affine background 128 pixels (16 tiles) square.
+    S128x128,
+    /// An affine background 256 pixels (32 tiles) square.
+    S256x256,
+    /// An affine background 512 pixels (64 tiles) square.
+    S512x512,
+    /// An affine background 1024 pixels (128 tiles) square.
+    S1024x1024,
+}
+
+impl BgMapSize {
+    /// Whether this is the size of an affine background rather than a text one.
+    #[must_use]
+    pub const fn is_affine(self) -> bool {
+        matches!(
+            self,
+            Self::S128x128 | Self::S256x256 | Self::S512x512 | Self::S1024x1024
+        )
+    }
+
+    /// The value of the two size bits in the control register.
+    #[must_use]
+    pub const fn size_bits(self) -> u16 {
+        match self {
+            Self::S32x32 | Self::S128x128 => 0,
+            Self::S64x32 | Self::S256x256 => 1,
+            Self::S32x64 | Self::S512x512 => 2,
+            Self::S64x64 | Self::S1024x1024 => 3,
+        }
+    }
+
+    /// The width and height of the map in tiles.
+    #[must_use]
+    pub const fn tiles(self) -> (usize, usize) {
+        match self {
+            Self::S32x32 => (32, 32),
+            Self::S64x32 => (64, 32),
+            Self::S32x64 => (32, 64),
+            Self::S64x64 => (64, 64),
+            Self::S128x128 => (16, 16),
+            Self::S256x256 => (32, 32),
+            Self::S512x512 => (64, 64),
+            Self::S1024x1024 => (128, 128),
+        }
+    }
+
+    /// The number of tiles in the map.
+    #[must_use]
+    pub const fn tile_count(self) -> usize {
+        let (width, height) = self.tiles();
+        width * height
+    }
+
+    /// The number of consecutive screen blocks the map uses, starting at the one given in the
+    /// control register. The smaller affine maps don't fill their screen block, but nothing
+    /// else can use the rest of it.
+    #[must_use]
+    pub const fn screen_blocks_needed(self) -> usize {
+        let bytes_per_tile = if self.is_affine() { 1 } else { 2 };
+        (self.tile_count() * bytes_per_tile).div_ceil(SCREEN_BLOCK_BYTES)
+    }
+}
+
+impl From<RegularBackgroundSize> for BgMapSize {
+    fn from(size: RegularBackgroundSize) -> Self {
+        match size {
+            RegularBackgroundSize::Background32x32 => Self::S32x32,
+            RegularBackgroundSize::Background64x32 => Self::S64x32,
+            RegularBackgroundSize::Background32x64 => Self::S32x64,
+            RegularBackgroundSize::Background64x64 => Self::S64x64,
+        }
+    }
+}
+
+impl From<AffineBackgroundSize> for BgMapSize {
+    fn from(size: AffineBackgroundSize) -> Self {
+        match size {
+            AffineBackgroundSize::Background16x16 => Self::S128x128,
+            AffineBackgroundSize::Background32x32 => Self::S256x256,
+            AffineBackgroundSize::Background64x64 => Self::S512x512,
+            AffineBackgroundSize::Background128x128 => Self::S1024x1024,
+        }
+    }
+}
+
+/// The value of a background control register, built up one field at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BgControl {
+    size: BgMapSize,
+    priority: Priority,
+    char_block: u16,
+    screen_block: u16,
+    colours: TileFormat,
+    mosaic: bool,
+    wraparound: bool,
+}
+
+impl BgControl {
+    /// A background of the given size with its tiles and map at the start of VRAM, 4bpp
+    /// tiles, priority [`Priority::P0`] and no mosaic or wraparound.
+    #[must_use]
+    pub const fn new(size: BgMapSize) -> Self {
+        Self {
+            size,
+            priority: Priority::P0,
+            char_block: 0,
+            screen_block: 0,
+            colours: TileFormat::FourBpp,
+            mosaic: false,
+            wraparound: false,
+        }
+    }
+
+    /// Sets the priority of the background.
+    #[must_use]
+    pub const fn priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// Sets the char block (0 to 3, in 16KiB steps) the tiles start in. Only the bottom two
+    /// bits are used.
+    #[must_use]
+    pub const fn char_block(self, char_block: usize) -> Self {
+        Self {
+            char_block: char_block as u16,
+            ..self
+        }
+    }
+
+    /// Sets the screen block (0 to 31, in 2KiB steps) the map starts in. Only the bottom five
+    /// bits are used.
+    #[must_use]
+    pub const fn screen_block(self, screen_block: usize) -> Self {
+        Self {
+            screen_block: screen_block as u16,
+            ..self
+        }
+    }
+
+    /// Sets whether the tiles are 4bpp or 8bpp. Affine backgrounds are always 8bpp whatever
+    /// this is set to.
+    #[must_use]
+    pub const fn colours(self, colours: TileFormat) -> Self {
+        Self { colours, ..self }
+    }
+
+    /// Sets whether the mosaic effect applies to the background.
+    #[must_use]
+    pub const fn mosaic(self, mosaic: bool) -> Self {
+        Self { mosaic, ..self }
+    }
+
+    /// Sets whether an affine background repeats forever. Text backgrounds always repeat.
+    #[must_use]
+    pub const fn wraparound(self, wraparound: bool) -> Self {
+        Self { wraparound, ..self }
+    }
+
+    /// The size of the map.
+    #[must_use]
+    pub const fn map_size(self) -> BgMapSize {
+        self.size
+    }
+
+    /// The value to write to the control register.
+    #[must_use]
+    pub const fn bits(self) -> u16 {
+        let eight_bpp = matches!(self.colours, TileFormat::EightBpp) as u16;
+
+        (self.priority as u16)
+            | ((self.char_block & 0b11) << 2)
+            | ((self.mosaic as u16) << 6)
+            | (eight_bpp << 7)
+            | ((self.screen_block & 0b1_1111) << 8)
+            | ((self.wraparound as u16) << 13)
+            | (self.size.size_bits() << 14)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn screen_blocks_needed(_gba: &mut crate::Gba) {
+        for (size, tile_count, screen_blocks) in [
+            (BgMapSize::S32x32, 1024, 1),
+            (BgMapSize::S64x32, 2048, 2),
+            (BgMapSize::S32x64, 2048, 2),
+            (BgMapSize::S64x64, 4096, 4),
+            (BgMapSize::S128x128, 256, 1),
+            (BgMapSize::S256x256, 1024, 1),
+            (BgMapSize::S512x512, 4096, 2),
+            (BgMapSize::S1024x1024, 16384, 8),
+        ] {
+            assert_eq!(size.tile_count(), tile_count, "{size:?}");
+            assert_eq!(size.screen_blocks_needed(), screen_blocks, "{size:?}");
+        }
+
+        assert_eq!(
+            BgMapSize::from(AffineBackgroundSize::Background64x64),
+            BgMapSize::S512x512
+        );
+    }
+
+    #[test_case]
+    fn control_bits(_gba: &mut crate::Gba) {
+        let control = BgControl::new(BgMapSize::S64x32)
+            .char_block(1)
+            .screen_block(30)
+            .priority(Priority::P2)
+            .colours(TileFormat::EightBpp);
+
+        assert_eq!(
+            control.bits(),
+            2 | (1 << 2) | (1 << 7) | (30 << 8) | (1 << 14)
+        );
+        assert_eq!(
+            BgControl::new(BgMapSize::S1024x1024)
+                .wraparound(true)
+                .bits(),
+            (1 << 13) | (3 << 14)
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! A background layer which stays fixed on screen for health bars, scores and the like.
+//!
+//! The [`HudOverlay`] takes BG0 at the highest priority, so it draws above every other
+//! background. It never scrolls, so the rest of your game can move its backgrounds around
+//! with the camera while the HUD stays put. If your camera code moves every layer including
+//! BG0, tell the HUD about it with [`lock_to_scroll`](HudOverlay::lock_to_scroll) and it will
+//! scroll the other way to cancel it out.
+
+use crate::fixnum::Vector2D;
+
+use super::{
+    font::{Font, TextRenderer, TextWriter},
+    tiled::{
+        MapLoan, RegularBackgroundSize, RegularMap, TileFormat, TileSet, TileSetting, Tiled0,
+        TiledMap, VRamManager,
+    },
+    Priority,
+};
+
+/// A non-scrolling background layer drawn above everything else.
+pub struct HudOverlay<'gba, 'tiles> {
+    map: MapLoan<'gba, RegularMap>,
+    tileset: &'tiles TileSet<'tiles>,
+    camera_offset: Vector2D<i16>,
+}
+
+impl<'gba, 'tiles> HudOverlay<'gba, 'tiles> {
+    /// Creates the HUD layer using tiles from `tileset` for any bars drawn.
+    ///
+    /// # Panics
+    ///
+    /// The HUD needs to be BG0, so this must be called before any other backgrounds are
+    /// created from `tiled`.
+    #[must_use]
+    pub fn new(tiled: &'gba Tiled0<'_>, tileset: &'tiles TileSet<'tiles>) -> Self {
+        let map = tiled.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        assert_eq!(
+            map.background().0,
+            0,
+            "the HUD must be created before any other background so that it can use BG0"
+        );
+
+        Self {
+            map,
+            tileset,
+            camera_offset: Vector2D::new(0, 0),
+        }
+    }
+
+    /// Creates a text renderer for drawing text on the HUD starting at `tile_pos`.
+    ///
+    /// Use [`writer`](HudOverlay::writer) to actually write text with it.
+    #[must_use]
+    pub fn text_renderer<'font>(
+        font: &'font Font,
+        tile_pos: impl Into<Vector2D<u16>>,
+    ) -> TextRenderer<'font> {
+        font.render_text(tile_pos)
+    }
+
+    /// Gets a writer which renders text to the HUD using `renderer`.
+    pub fn writer<'a, 'b>(
+        &'a mut self,
+        renderer: &'a mut TextRenderer<'b>,
+        foreground_colour: u8,
+        background_colour: u8,
+        vram: &'a mut VRamManager,
+    ) -> TextWriter<'a, 'b> {
+        renderer.writer(foreground_colour, background_colour, &mut self.map, vram)
+    }
+
+    /// Draws a horizontal bar of `max` tiles starting at `pos`, the first `value` of which
+    /// are `fill_tile` and the rest are `empty_tile`.
+    pub fn draw_bar(
+        &mut self,
+        vram: &mut VRamManager,
+        pos: impl Into<Vector2D<u16>>,
+        value: u8,
+        max: u8,
+        fill_tile: TileSetting,
+        empty_tile: TileSetting,
+    ) {
+        let pos = pos.into();
+        let value = value.min(max);
+
+        for i in 0..max {
+            let tile = if i < value { fill_tile } else { empty_tile };
+
+            self.map
+                .set_tile(vram, (pos.x + u16::from(i), pos.y), self.tileset, tile);
+        }
+    }
+
+    /// Counter-scrolls the HUD layer by `camera_offset`, the offset which the rest of your code
+    /// adds to the scroll registers of every background including BG0. The HUD keeps
+    /// cancelling this offset in every [`commit`](HudOverlay::commit) until it is changed
+    /// again, so call this whenever the camera moves.
+    pub fn lock_to_scroll(&mut self, camera_offset: impl Into<Vector2D<i16>>) {
+        self.camera_offset = camera_offset.into();
+        self.map.set_scroll_pos(-self.camera_offset);
+    }
+
+    /// Gives access to the underlying map for anything not covered here.
+    pub fn map_mut(&mut self) -> &mut RegularMap {
+        &mut self.map
+    }
+
+    /// Commits any changes to the HUD and ensures it is visible. Any scrolling applied through
+    /// [`map_mut`](HudOverlay::map_mut) is replaced by the counter-scroll from
+    /// [`lock_to_scroll`](HudOverlay::lock_to_scroll).
+    pub fn commit(&mut self, vram: &mut VRamManager) {
+        self.map.set_scroll_pos(-self.camera_offset);
+        self.map.commit(vram);
+        self.map.set_visible(true);
+    }
+
+    /// Removes everything from the HUD.
+    pub fn clear(&mut self, vram: &mut VRamManager) {
+        self.map.clear(vram);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn hud_takes_bg0_and_stays_put(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+
+        let tile = vram.new_dynamic_tile().fill_with(1);
+        let tileset = tile.tile_set();
+
+        let mut hud = HudOverlay::new(&gfx, &tileset);
+        hud.draw_bar(
+            &mut vram,
+            (1u16, 1u16),
+            3,
+            5,
+            tile.tile_setting(),
+            TileSetting::BLANK,
+        );
+
+        hud.map_mut().set_scroll_pos(Vector2D::new(20, 30));
+        hud.commit(&mut vram);
+
+        assert_eq!(hud.map_mut().scroll_pos(), Vector2D::new(0, 0));
+
+        hud.lock_to_scroll(Vector2D::new(12, -4));
+        hud.commit(&mut vram);
+
+        assert_eq!(hud.map_mut().scroll_pos(), Vector2D::new(-12, 4));
+
+        hud.clear(&mut vram);
+        drop(hud);
+
+        vram.remove_dynamic_tile(tile);
+        vram.gc();
+    }
+}
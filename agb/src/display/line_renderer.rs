@@ -0,0 +1,176 @@
+//! Drawing lines, rectangles and circles in bitmap mode 3, for things like lasers and debug
+//! overlays.
+//!
+//! Lines are drawn with Bresenham's algorithm and circles with the midpoint circle algorithm, so
+//! neither needs any multiplication or division per pixel. Everything is clipped to the screen,
+//! so shapes can hang off the edges. Filled rectangles are drawn a row at a time with DMA, which
+//! is much quicker than drawing the pixels one by one.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{
+//!     display::line_renderer::{draw_circle, draw_line, fill_rect},
+//!     fixnum::{Rect, Vector2D},
+//! };
+//!
+//! let mut bitmap = gba.display.video.bitmap3();
+//!
+//! fill_rect(
+//!     &mut bitmap,
+//!     Rect::new(Vector2D::new(0, 0), Vector2D::new(240, 160)),
+//!     0,
+//! );
+//! draw_line(&mut bitmap, Vector2D::new(20, 140), Vector2D::new(220, 20), 0x001f);
+//! draw_circle(&mut bitmap, Vector2D::new(120, 80), 30, 0x7fff);
+//! # }
+//! ```
+
+use crate::{
+    dma,
+    fixnum::{Rect, Vector2D},
+};
+
+use super::{bitmap3::Bitmap3, HEIGHT, WIDTH};
+
+/// Draws a line from `start` to `end`, including both ends.
+pub fn draw_line(bitmap: &mut Bitmap3, start: Vector2D<i16>, end: Vector2D<i16>, colour: u16) {
+    let (mut x, mut y) = (i32::from(start.x), i32::from(start.y));
+    let (end_x, end_y) = (i32::from(end.x), i32::from(end.y));
+
+    let dx = (end_x - x).abs();
+    let dy = -(end_y - y).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_y = if y < end_y { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        plot(bitmap, x, y, colour);
+
+        if x == end_x && y == end_y {
+            break;
+        }
+
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Draws the outline of `rect`, which is one pixel wide and inside the rectangle.
+pub fn draw_rect(bitmap: &mut Bitmap3, rect: Rect<i16>, colour: u16) {
+    if rect.size.x <= 0 || rect.size.y <= 0 {
+        return;
+    }
+
+    let top_left = rect.position;
+    let bottom_right = rect.position + rect.size - Vector2D::new(1, 1);
+    let top_right = Vector2D::new(bottom_right.x, top_left.y);
+    let bottom_left = Vector2D::new(top_left.x, bottom_right.y);
+
+    draw_line(bitmap, top_left, top_right, colour);
+    draw_line(bitmap, top_right, bottom_right, colour);
+    draw_line(bitmap, bottom_right, bottom_left, colour);
+    draw_line(bitmap, bottom_left, top_left, colour);
+}
+
+/// Fills `rect` with `colour`, filling each row with DMA.
+pub fn fill_rect(bitmap: &mut Bitmap3, rect: Rect<i16>, colour: u16) {
+    let left = i32::from(rect.position.x).max(0);
+    let top = i32::from(rect.position.y).max(0);
+    let right = (i32::from(rect.position.x) + i32::from(rect.size.x)).min(WIDTH);
+    let bottom = (i32::from(rect.position.y) + i32::from(rect.size.y)).min(HEIGHT);
+
+    if left >= right || top >= bottom {
+        return;
+    }
+
+    for y in top..bottom {
+        let row = bitmap.row_mut(y as usize);
+        dma::fill16(&mut row[left as usize..right as usize], colour);
+    }
+}
+
+/// Draws the outline of a circle of `radius` pixels around `centre`.
+pub fn draw_circle(bitmap: &mut Bitmap3, centre: Vector2D<i16>, radius: u8, colour: u16) {
+    let (centre_x, centre_y) = (i32::from(centre.x), i32::from(centre.y));
+
+    let mut x = i32::from(radius);
+    let mut y = 0;
+    let mut error = 1 - x;
+
+    while x >= y {
+        // each point found in one eighth of the circle gives a point in every eighth
+        for (dx, dy) in [(x, y), (y, x)] {
+            plot(bitmap, centre_x + dx, centre_y + dy, colour);
+            plot(bitmap, centre_x - dx, centre_y + dy, colour);
+            plot(bitmap, centre_x + dx, centre_y - dy, colour);
+            plot(bitmap, centre_x - dx, centre_y - dy, colour);
+        }
+
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+fn plot(bitmap: &mut Bitmap3, x: i32, y: i32, colour: u16) {
+    if (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y) {
+        bitmap.draw_point(x, y, colour);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn draws_clipped_shapes(gba: &mut crate::Gba) {
+        let mut bitmap = gba.display.video.bitmap3();
+        bitmap.clear(0);
+
+        draw_line(
+            &mut bitmap,
+            Vector2D::new(-2, -1),
+            Vector2D::new(6, 3),
+            0x1f,
+        );
+        assert_eq!(bitmap.read_point(0, 0), 0x1f);
+        assert_eq!(bitmap.read_point(2, 1), 0x1f);
+        assert_eq!(bitmap.read_point(6, 3), 0x1f);
+        assert_eq!(bitmap.read_point(6, 2), 0);
+
+        fill_rect(
+            &mut bitmap,
+            Rect::new(Vector2D::new(230, 150), Vector2D::new(20, 20)),
+            0x3e0,
+        );
+        assert_eq!(bitmap.read_point(229, 159), 0);
+        assert_eq!(bitmap.read_point(230, 150), 0x3e0);
+        assert_eq!(bitmap.read_point(239, 159), 0x3e0);
+
+        draw_rect(
+            &mut bitmap,
+            Rect::new(Vector2D::new(100, 100), Vector2D::new(4, 3)),
+            0x7c00,
+        );
+        assert_eq!(bitmap.read_point(103, 102), 0x7c00);
+        assert_eq!(bitmap.read_point(101, 101), 0);
+
+        draw_circle(&mut bitmap, Vector2D::new(50, 50), 10, 0x7fff);
+        assert_eq!(bitmap.read_point(60, 50), 0x7fff);
+        assert_eq!(bitmap.read_point(50, 40), 0x7fff);
+        assert_eq!(bitmap.read_point(50, 50), 0);
+    }
+}
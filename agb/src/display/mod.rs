@@ -28,13 +28,62 @@ pub mod tiled;
 pub mod video;
 
 pub mod affine;
+pub mod affine_background_renderer;
+pub mod affine_sprite_zoom;
+pub mod bg_collision_map;
+pub mod bg_map_diff;
+pub mod bg_map_loader;
+pub mod bg_map_procedural;
+pub mod bg_tile_animation_player;
+pub mod bg_tile_replace;
 pub mod blend;
+pub mod charblock_mirror;
+pub mod color_cycling;
+pub mod color_fade_manager;
+pub mod cpu_usage;
+pub mod hardware_bg_scale;
+pub mod hud_overlay;
+pub mod line_renderer;
+pub mod mode0_background_manager;
+pub mod obj_1d_vs_2d_mapping;
+pub mod obj_chr_block_manager;
+pub mod obj_rotation_table;
+pub mod particle_system;
+#[cfg(feature = "profiling")]
+pub mod render_stats;
+pub mod screen_shake;
+pub mod scrolling_parallax_columns;
+pub mod sprite_animation_blending;
+pub mod sprite_damage_number;
+pub mod sprite_depth_sort;
+pub mod sprite_flash_manager;
+pub mod sprite_inventory;
+pub mod sprite_nine_slice;
+pub mod sprite_page_flip;
+pub mod sprite_render_budget;
+pub mod sprite_scale_table;
+pub mod sprite_shadow_map;
+pub mod starfield;
+pub mod text_dialog_box;
+pub mod text_renderer_cache;
+pub mod tile_map_autotile;
+pub mod tilemap_fog_of_war;
+pub mod tileset_compress_rle;
+pub mod tileset_packer;
+pub mod tileset_palette_remap;
+pub mod vblank_busy_flag;
+pub mod vcount_profiler;
+pub mod video_ram_map;
+pub mod vram_streamer;
 pub mod window;
 
 pub mod font;
 pub use font::{Font, FontLetter};
 
-const DISPLAY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0000) };
+pub use bg_tile_replace::{set_screen_entry, toggle_tile_hflip, toggle_tile_vflip};
+pub use charblock_mirror::mirror_charblock;
+
+pub(crate) const DISPLAY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0000) };
 pub(crate) const DISPLAY_STATUS: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0004) };
 const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
 
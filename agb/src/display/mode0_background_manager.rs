@@ -0,0 +1,309 @@
+//! Setting up all four backgrounds of mode 0 by hand.
+//!
+//! [`Tiled0`](super::tiled::Tiled0) picks screen blocks for each background itself and keeps its
+//! own copy of every map. If you would rather lay out VRAM yourself, for example to load maps
+//! generated at build time straight into fixed screen blocks, a [`Mode0BackgroundManager`]
+//! writes the control, scroll and enable registers for you and checks that the backgrounds
+//! don't overwrite each other's memory.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::display::{
+//!     mode0_background_manager::BgConfig,
+//!     tiled::{RegularBackgroundSize, TileFormat},
+//!     Priority,
+//! };
+//!
+//! let mut backgrounds = gba.display.video.mode0_background_manager();
+//!
+//! // tiles in the first char block, maps at the end of the last one
+//! let level = BgConfig::new(0, 28, RegularBackgroundSize::Background64x32, TileFormat::FourBpp)
+//!     .priority(Priority::P2);
+//! let hud = BgConfig::new(0, 31, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+//!
+//! backgrounds.configure(0, level).unwrap();
+//! backgrounds.configure(1, hud).unwrap();
+//! backgrounds.enable_layers(0b0011);
+//! # }
+//! ```
+
+use core::marker::PhantomData;
+
+use agb_fixnum::Vector2D;
+
+use super::{
+    hardware_bg_scale::{BgControl, BgMapSize},
+    set_graphics_mode,
+    tiled::{RegularBackgroundSize, TileFormat},
+    DisplayMode, Priority, DISPLAY_CONTROL,
+};
+use crate::memory_mapped::MemoryMapped;
+
+const LAYER_COUNT: usize = 4;
+const CHAR_BLOCK_COUNT: usize = 4;
+const SCREEN_BLOCK_COUNT: usize = 32;
+const SCREEN_BLOCKS_PER_CHAR_BLOCK: usize = 8;
+
+const BG_ENABLE_SHIFT: u16 = 8;
+const BG_ENABLE_MASK: u16 = 0b1111 << BG_ENABLE_SHIFT;
+
+fn bg_control_register(layer: usize) -> MemoryMapped<u16> {
+    unsafe { MemoryMapped::new(0x0400_0008 + 2 * layer) }
+}
+
+fn bg_scroll_registers(layer: usize) -> (MemoryMapped<i16>, MemoryMapped<i16>) {
+    unsafe {
+        (
+            MemoryMapped::new(0x0400_0010 + 4 * layer),
+            MemoryMapped::new(0x0400_0012 + 4 * layer),
+        )
+    }
+}
+
+/// Where a background's tiles and map are in VRAM and how it is drawn, for
+/// [`Mode0BackgroundManager::configure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BgConfig {
+    char_block: usize,
+    screen_block: usize,
+    size: RegularBackgroundSize,
+    colours: TileFormat,
+    priority: Priority,
+    mosaic: bool,
+}
+
+impl BgConfig {
+    /// A background whose tiles start at `char_block` (0 to 3, in 16KiB steps) and whose map
+    /// starts at `screen_block` (0 to 31, in 2KiB steps). Backgrounds bigger than 32x32 tiles
+    /// use the following screen blocks too.
+    ///
+    /// The background has priority [`Priority::P0`] and no mosaic effect.
+    #[must_use]
+    pub const fn new(
+        char_block: usize,
+        screen_block: usize,
+        size: RegularBackgroundSize,
+        colours: TileFormat,
+    ) -> Self {
+        Self {
+            char_block,
+            screen_block,
+            size,
+            colours,
+            priority: Priority::P0,
+            mosaic: false,
+        }
+    }
+
+    /// Sets the priority of the background.
+    #[must_use]
+    pub const fn priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// Sets whether the mosaic effect applies to the background.
+    #[must_use]
+    pub const fn mosaic(self, mosaic: bool) -> Self {
+        Self { mosaic, ..self }
+    }
+
+    fn screen_blocks(&self) -> core::ops::Range<usize> {
+        self.screen_block..self.screen_block + BgMapSize::from(self.size).screen_blocks_needed()
+    }
+
+    fn char_block_screen_blocks(&self) -> core::ops::Range<usize> {
+        let start = self.char_block * SCREEN_BLOCKS_PER_CHAR_BLOCK;
+        start..start + SCREEN_BLOCKS_PER_CHAR_BLOCK
+    }
+
+    /// The value [`Mode0BackgroundManager::configure`] writes to the control register.
+    #[must_use]
+    pub fn control(&self) -> BgControl {
+        BgControl::new(self.size.into())
+            .char_block(self.char_block)
+            .screen_block(self.screen_block)
+            .colours(self.colours)
+            .priority(self.priority)
+            .mosaic(self.mosaic)
+    }
+}
+
+/// Why [`Mode0BackgroundManager::configure`] refused a configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BgConfigError {
+    /// The layer isn't 0 to 3.
+    LayerOutOfRange,
+    /// The char block isn't 0 to 3.
+    CharBlockOutOfRange,
+    /// The map would go past the end of VRAM.
+    ScreenBlockOutOfRange,
+    /// The map would share screen blocks with the map of another layer.
+    ScreenBlockConflict {
+        /// The layer already using the screen blocks.
+        other_layer: usize,
+    },
+    /// The map would overwrite the tiles of a layer, or a layer's map would overwrite these
+    /// tiles. A layer's tiles are assumed to use the whole of its char block.
+    CharBlockConflict {
+        /// The layer whose tiles or map are in the way. This can be the layer being configured,
+        /// if its map is inside its own char block.
+        other_layer: usize,
+    },
+}
+
+/// Owns all four mode 0 backgrounds, see the [module level documentation](self).
+pub struct Mode0BackgroundManager<'gba> {
+    configs: [Option<BgConfig>; LAYER_COUNT],
+    phantom: PhantomData<&'gba ()>,
+}
+
+impl Mode0BackgroundManager<'_> {
+    pub(crate) unsafe fn new() -> Self {
+        set_graphics_mode(DisplayMode::Tiled0);
+
+        Self {
+            configs: [None; LAYER_COUNT],
+            phantom: PhantomData,
+        }
+    }
+
+    /// Checks `config` against the other configured layers and writes it to the control
+    /// register of `layer`. Configuring a layer again replaces its old configuration.
+    pub fn configure(&mut self, layer: usize, config: BgConfig) -> Result<(), BgConfigError> {
+        self.check(layer, &config)?;
+
+        bg_control_register(layer).set(config.control().bits());
+        self.configs[layer] = Some(config);
+
+        Ok(())
+    }
+
+    fn check(&self, layer: usize, config: &BgConfig) -> Result<(), BgConfigError> {
+        if layer >= LAYER_COUNT {
+            return Err(BgConfigError::LayerOutOfRange);
+        }
+        if config.char_block >= CHAR_BLOCK_COUNT {
+            return Err(BgConfigError::CharBlockOutOfRange);
+        }
+        if config.screen_blocks().end > SCREEN_BLOCK_COUNT {
+            return Err(BgConfigError::ScreenBlockOutOfRange);
+        }
+
+        let overlaps = |a: core::ops::Range<usize>, b: core::ops::Range<usize>| {
+            a.start < b.end && b.start < a.end
+        };
+
+        if overlaps(config.screen_blocks(), config.char_block_screen_blocks()) {
+            return Err(BgConfigError::CharBlockConflict { other_layer: layer });
+        }
+
+        for (other_layer, other) in self.configs.iter().enumerate() {
+            let Some(other) = other.filter(|_| other_layer != layer) else {
+                continue;
+            };
+
+            if overlaps(config.screen_blocks(), other.screen_blocks()) {
+                return Err(BgConfigError::ScreenBlockConflict { other_layer });
+            }
+
+            if overlaps(config.screen_blocks(), other.char_block_screen_blocks())
+                || overlaps(other.screen_blocks(), config.char_block_screen_blocks())
+            {
+                return Err(BgConfigError::CharBlockConflict { other_layer });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The configuration of `layer`, if it has been configured.
+    #[must_use]
+    pub fn config(&self, layer: usize) -> Option<BgConfig> {
+        self.configs.get(layer).copied().flatten()
+    }
+
+    /// Sets the scroll position of each layer, in pixels.
+    pub fn set_all_scrolls(&mut self, scrolls: [Vector2D<i16>; LAYER_COUNT]) {
+        for (layer, scroll) in scrolls.into_iter().enumerate() {
+            let (x, y) = bg_scroll_registers(layer);
+            x.set(scroll.x);
+            y.set(scroll.y);
+        }
+    }
+
+    /// Shows the layers whose bits are set in `mask` and hides the others, where bit 0 is layer
+    /// 0. All four layers change in a single write, so they appear and disappear together.
+    pub fn enable_layers(&mut self, mask: u8) {
+        debug_assert!(
+            mask < 1 << LAYER_COUNT,
+            "there are only {LAYER_COUNT} layers"
+        );
+
+        let enabled = (u16::from(mask) << BG_ENABLE_SHIFT) & BG_ENABLE_MASK;
+        DISPLAY_CONTROL.set((DISPLAY_CONTROL.get() & !BG_ENABLE_MASK) | enabled);
+    }
+
+    /// The layers which are currently shown, as a mask like the one given to
+    /// [`enable_layers`](Mode0BackgroundManager::enable_layers).
+    #[must_use]
+    pub fn enabled_layers(&self) -> u8 {
+        ((DISPLAY_CONTROL.get() & BG_ENABLE_MASK) >> BG_ENABLE_SHIFT) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn rejects_overlapping_layers(gba: &mut crate::Gba) {
+        let mut backgrounds = gba.display.video.mode0_background_manager();
+
+        let config = |char_block, screen_block, size| {
+            BgConfig::new(char_block, screen_block, size, TileFormat::FourBpp)
+        };
+
+        assert_eq!(
+            backgrounds.configure(0, config(0, 28, RegularBackgroundSize::Background64x32)),
+            Ok(())
+        );
+        assert_eq!(
+            backgrounds.configure(1, config(0, 29, RegularBackgroundSize::Background32x32)),
+            Err(BgConfigError::ScreenBlockConflict { other_layer: 0 })
+        );
+        assert_eq!(
+            backgrounds.configure(1, config(3, 30, RegularBackgroundSize::Background32x32)),
+            Err(BgConfigError::CharBlockConflict { other_layer: 1 })
+        );
+        assert_eq!(
+            backgrounds.configure(1, config(1, 4, RegularBackgroundSize::Background32x32)),
+            Err(BgConfigError::CharBlockConflict { other_layer: 0 })
+        );
+        assert_eq!(
+            backgrounds.configure(1, config(1, 31, RegularBackgroundSize::Background32x64)),
+            Err(BgConfigError::ScreenBlockOutOfRange)
+        );
+        assert_eq!(
+            backgrounds.configure(4, config(1, 30, RegularBackgroundSize::Background32x32)),
+            Err(BgConfigError::LayerOutOfRange)
+        );
+        assert_eq!(
+            backgrounds.configure(1, config(1, 30, RegularBackgroundSize::Background32x32)),
+            Ok(())
+        );
+
+        // reconfiguring a layer doesn't conflict with its old configuration
+        assert_eq!(
+            backgrounds.configure(0, config(0, 27, RegularBackgroundSize::Background32x32)),
+            Ok(())
+        );
+
+        assert_eq!(bg_control_register(1).get(), (1 << 2) | (30 << 8));
+
+        backgrounds.enable_layers(0b0101);
+        assert_eq!(backgrounds.enabled_layers(), 0b0101);
+        backgrounds.enable_layers(0);
+    }
+}
@@ -0,0 +1,123 @@
+//! How the tiles of a multi-tile sprite are found in object tile memory.
+//!
+//! Object tile memory holds 1024 4bpp tiles. Bit 6 of the display control register chooses
+//! between two ways of finding the tiles for sprites bigger than 8x8:
+//!
+//! * In **1D** mapping the tiles of the sprite follow each other in memory, one row of the
+//!   sprite after another. A 16x16 sprite starting at tile 10 uses tiles 10, 11, 12 and 13.
+//! * In **2D** mapping tile memory is treated as a grid 32 tiles wide. Each row of the sprite
+//!   comes from the next row of the grid, so a 16x16 sprite starting at tile 10 uses tiles
+//!   10, 11, 42 and 43.
+//!
+//! Tile indices always count 4bpp tiles, so an 8bpp sprite is twice as wide in tile memory as
+//! it is on screen.
+//!
+//! Graphics only look correct in the mode they were laid out for. agb selects 1D mapping when
+//! it sets up object memory, and the sprite loader lays tiles out to match, so you'll only need
+//! this if you're poking at the display control register yourself.
+
+use super::{object::ObjectUnmanaged, DISPLAY_CONTROL};
+
+const OBJ_TILES: usize = 1024;
+const GRID_WIDTH: usize = 32;
+
+/// Which way multi-tile sprites are laid out in object tile memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjMappingMode {
+    /// Each row of the sprite follows on from the previous one in memory.
+    OneDimensional,
+    /// Each row of the sprite is 32 tiles after the previous one.
+    TwoDimensional,
+}
+
+impl ObjMappingMode {
+    /// Reads the mapping mode from a value of the display control register.
+    #[must_use]
+    pub fn from_dispcnt(dispcnt: u16) -> Self {
+        if dispcnt & (1 << 6) != 0 {
+            Self::OneDimensional
+        } else {
+            Self::TwoDimensional
+        }
+    }
+
+    /// The mapping mode currently selected in the display control register.
+    #[must_use]
+    pub fn current() -> Self {
+        Self::from_dispcnt(DISPLAY_CONTROL.get())
+    }
+
+    pub(crate) fn select(self) {
+        DISPLAY_CONTROL.set_bits(u16::from(self == Self::OneDimensional), 1, 0x6);
+    }
+}
+
+/// Checks that every tile of `object` lies within object tile memory when read using `mode`.
+///
+/// In 1D mapping the sprite must not run off the end of tile memory. In 2D mapping each row
+/// of the sprite must also fit within a single row of the 32 tile wide grid, otherwise the
+/// hardware wraps around and draws tiles from the start of the row. 8bpp sprites take up twice
+/// as many tiles of memory as 4bpp ones of the same size.
+#[must_use]
+pub fn check_obj_tile_layout(object: &ObjectUnmanaged, mode: ObjMappingMode) -> bool {
+    tile_layout_fits(object.tile_memory_layout(), mode)
+}
+
+/// `layout` is the first tile along with the width and height of the sprite in 4bpp tiles.
+pub(crate) fn tile_layout_fits(layout: (usize, usize, usize), mode: ObjMappingMode) -> bool {
+    let (tile, width, height) = layout;
+
+    match mode {
+        ObjMappingMode::OneDimensional => tile + width * height <= OBJ_TILES,
+        ObjMappingMode::TwoDimensional => {
+            tile % GRID_WIDTH + width <= GRID_WIDTH
+                && tile / GRID_WIDTH + height <= OBJ_TILES / GRID_WIDTH
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn layouts_which_fit_each_mode(_gba: &mut crate::Gba) {
+        assert_eq!(
+            ObjMappingMode::from_dispcnt(1 << 6),
+            ObjMappingMode::OneDimensional
+        );
+        assert_eq!(
+            ObjMappingMode::from_dispcnt(0),
+            ObjMappingMode::TwoDimensional
+        );
+
+        for (layout, one_dimensional, two_dimensional) in [
+            ((0, 8, 8), true, true),
+            ((28, 4, 4), true, true),
+            ((30, 4, 4), true, false),
+            ((1020, 2, 2), true, false),
+            ((1023, 1, 1), true, true),
+            ((1022, 2, 2), false, false),
+            ((32 * 28, 8, 4), true, true),
+            ((32 * 29, 1, 4), true, false),
+            // 8bpp 64x64 and 32x32 sprites
+            ((0, 16, 8), true, true),
+            ((16, 8, 4), true, true),
+            ((28, 8, 4), true, false),
+            ((32 * 31, 16, 1), true, true),
+            ((1016, 8, 1), true, true),
+            ((1016, 8, 2), false, false),
+        ] {
+            assert_eq!(
+                tile_layout_fits(layout, ObjMappingMode::OneDimensional),
+                one_dimensional,
+                "{layout:?} in 1d"
+            );
+            assert_eq!(
+                tile_layout_fits(layout, ObjMappingMode::TwoDimensional),
+                two_dimensional,
+                "{layout:?} in 2d"
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Sharing object tile memory between everything which needs it.
+//!
+//! Object tiles are stored in the last 32KiB of VRAM, which holds 1024 4bpp tiles. In the bitmap
+//! modes only tiles 512 to 1023 can be used, since the bitmap overlaps the first half.
+//!
+//! [`ObjChrBlockManager`] keeps track of which of those tiles are in use. Everything in agb which
+//! puts tiles there, such as [`SpriteLoader`](super::object::SpriteLoader),
+//! [`DynamicSprite`](super::object::DynamicSprite) and the object text renderer, gets its tiles
+//! from it, so if you need tiles for something of your own, allocate them here too and they
+//! won't be overwritten.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{bitarray::Bitarray, interrupt::interrupt_free};
+
+/// The number of 4bpp tiles in object tile memory.
+pub const OBJ_TILE_COUNT: usize = 1024;
+
+const OBJ_TILE_START: usize = 0x0601_0000;
+const BYTES_PER_TILE_4BPP: usize = 32;
+
+type Occupancy = Bitarray<{ OBJ_TILE_COUNT / 32 }>;
+
+static OBJ_CHR_BLOCK_MANAGER: ObjChrBlockManager = ObjChrBlockManager {
+    occupied: Mutex::new(RefCell::new(Bitarray::new())),
+};
+
+/// Marks the first run of `count` free tiles as used, returning the first tile in it.
+fn claim_first_fit(occupied: &mut Occupancy, count: usize) -> Option<usize> {
+    let mut run_start = 0;
+    for tile in 0..OBJ_TILE_COUNT {
+        if occupied.get(tile) == Some(true) {
+            run_start = tile + 1;
+        } else if tile + 1 - run_start == count {
+            for used in run_start..=tile {
+                occupied.set(used, true);
+            }
+
+            return Some(run_start);
+        }
+    }
+
+    None
+}
+
+/// A run of consecutive 4bpp tiles in object tile memory, allocated by [`ObjChrBlockManager`].
+///
+/// The tiles stay allocated until the range is given back with [`ObjChrBlockManager::free`].
+/// Dropping the range without freeing it leaks the tiles.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TileRange {
+    first: u16,
+    len: u16,
+}
+
+impl TileRange {
+    /// The index of the first tile, as used for the tile index of an object.
+    #[must_use]
+    pub fn first(&self) -> u16 {
+        self.first
+    }
+
+    /// The number of tiles in the range.
+    #[must_use]
+    pub fn len(&self) -> u16 {
+        self.len
+    }
+
+    /// Whether the range contains no tiles.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A pointer to the first byte of the first tile in VRAM.
+    #[must_use]
+    pub fn as_ptr(&self) -> *mut u8 {
+        (OBJ_TILE_START + usize::from(self.first) * BYTES_PER_TILE_4BPP) as *mut u8
+    }
+}
+
+/// Tracks which tiles of object tile memory are in use, see the [module level
+/// documentation](self).
+pub struct ObjChrBlockManager {
+    occupied: Mutex<RefCell<Occupancy>>,
+}
+
+impl ObjChrBlockManager {
+    /// The manager for object tile memory. There is only one, shared by everything.
+    #[must_use]
+    pub fn get() -> &'static Self {
+        &OBJ_CHR_BLOCK_MANAGER
+    }
+
+    /// Finds `count` free consecutive tiles, marks them as used and returns them. Returns
+    /// [`None`] if there isn't a long enough run of free tiles or if `count` is 0.
+    ///
+    /// The first free run which is long enough is used, so tiles are handed out from the start
+    /// of object tile memory.
+    pub fn alloc_contiguous(&self, count: usize) -> Option<TileRange> {
+        if count == 0 || count > OBJ_TILE_COUNT {
+            return None;
+        }
+
+        let first = interrupt_free(|token| {
+            claim_first_fit(
+                &mut self.occupied.borrow_ref_mut(token.critical_section()),
+                count,
+            )
+        })?;
+
+        Some(TileRange {
+            first: first as u16,
+            len: count as u16,
+        })
+    }
+
+    /// Marks the tiles in `range` as free so they can be allocated again.
+    #[allow(clippy::needless_pass_by_value)] // taking the range stops it being freed twice
+    pub fn free(&self, range: TileRange) {
+        let TileRange { first, len } = range;
+
+        interrupt_free(|token| {
+            let mut occupied = self.occupied.borrow_ref_mut(token.critical_section());
+
+            for tile in first..first + len {
+                debug_assert!(
+                    occupied.get(tile.into()) == Some(true),
+                    "freeing object tile {tile} which wasn't allocated"
+                );
+                occupied.set(tile.into(), false);
+            }
+        });
+    }
+
+    /// The number of tiles which aren't allocated.
+    #[must_use]
+    pub fn free_tiles(&self) -> usize {
+        interrupt_free(|token| {
+            let occupied = self.occupied.borrow_ref(token.critical_section());
+            (0..OBJ_TILE_COUNT)
+                .filter(|&tile| occupied.get(tile) == Some(false))
+                .count()
+        })
+    }
+
+    /// Allocates enough tiles for `bytes` bytes of tile data, returning a pointer to them.
+    pub(crate) fn alloc_bytes(&self, bytes: usize) -> Option<core::ptr::NonNull<u8>> {
+        let range = self.alloc_contiguous(bytes.div_ceil(BYTES_PER_TILE_4BPP))?;
+        core::ptr::NonNull::new(range.as_ptr())
+    }
+
+    /// Frees tiles allocated by [`alloc_bytes`](Self::alloc_bytes).
+    pub(crate) fn free_bytes(&self, ptr: core::ptr::NonNull<u8>, bytes: usize) {
+        self.free(TileRange {
+            first: ((ptr.as_ptr() as usize - OBJ_TILE_START) / BYTES_PER_TILE_4BPP) as u16,
+            len: bytes.div_ceil(BYTES_PER_TILE_4BPP) as u16,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn claims_the_first_run_which_fits(_gba: &mut crate::Gba) {
+        let mut occupied = Occupancy::new();
+
+        assert_eq!(claim_first_fit(&mut occupied, 4), Some(0));
+        assert_eq!(claim_first_fit(&mut occupied, 2), Some(4));
+
+        // the gap left by the first run is too small for 5 tiles, but 3 fit back in it
+        for tile in 0..4 {
+            occupied.set(tile, false);
+        }
+        assert_eq!(claim_first_fit(&mut occupied, 5), Some(6));
+        assert_eq!(claim_first_fit(&mut occupied, 3), Some(0));
+        assert_eq!(claim_first_fit(&mut occupied, 1), Some(3));
+
+        assert_eq!(
+            claim_first_fit(&mut occupied, OBJ_TILE_COUNT - 11),
+            Some(11)
+        );
+        assert_eq!(claim_first_fit(&mut occupied, 1), None);
+    }
+
+    #[test_case]
+    fn allocating_and_freeing_restores_free_tiles(_gba: &mut crate::Gba) {
+        let manager = ObjChrBlockManager::get();
+        let free_at_start = manager.free_tiles();
+
+        let a = manager.alloc_contiguous(4).unwrap();
+        let b = manager.alloc_contiguous(2).unwrap();
+        assert_eq!(a.len(), 4);
+        assert_eq!(b.len(), 2);
+        assert_eq!(manager.free_tiles(), free_at_start - 6);
+
+        assert_eq!(manager.alloc_contiguous(0), None);
+        assert_eq!(manager.alloc_contiguous(OBJ_TILE_COUNT + 1), None);
+
+        manager.free(a);
+        manager.free(b);
+        assert_eq!(manager.free_tiles(), free_at_start);
+    }
+}
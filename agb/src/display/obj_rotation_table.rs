@@ -0,0 +1,138 @@
+//! Looking up rotation matrices from a precomputed table.
+//!
+//! Working out the sine and cosine for [`AffineMatrix::from_rotation`] every frame adds up when
+//! lots of sprites are rotating. A [`RotationTable`] works out `N` evenly spaced rotations at
+//! compile time and stores them in ROM, so getting a matrix is just two lookups and a linear
+//! interpolation between them.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::{affine::AffineMatrix, obj_rotation_table::RotationTable};
+//!
+//! // 64 entries is a step of about 5.6 degrees between entries
+//! static ROTATIONS: RotationTable<64> = RotationTable::new();
+//!
+//! // an eighth of a turn
+//! let matrix: AffineMatrix = ROTATIONS.get_matrix(0x2000);
+//! # }
+//! ```
+
+use super::affine::AffineMatrix;
+use crate::fixnum::Num;
+
+const FRACTIONAL_BITS: u32 = 16;
+const ONE: i32 = 1 << FRACTIONAL_BITS;
+
+/// The cosine of `turns` (where [`ONE`] is a full turn) using the same approximation as
+/// [`Num::cos`], in 16.16 fixed point.
+const fn cos(turns: i32) -> i32 {
+    let turns = turns as i64;
+    let one = ONE as i64;
+
+    let shifted = turns + one / 4;
+    let mut x = turns - one / 4 - (shifted - shifted.rem_euclid(one));
+    x = (x * ((x.abs() - one / 2) * 16)) >> FRACTIONAL_BITS;
+    x += (((x * (x.abs() - one)) >> FRACTIONAL_BITS) * 14746) >> FRACTIONAL_BITS;
+
+    x as i32
+}
+
+/// Rotation matrices for `N` equally spaced angles, see the [module level
+/// documentation](self).
+pub struct RotationTable<const N: usize> {
+    /// The cosine and sine of each angle in 16.16 fixed point.
+    entries: [(i32, i32); N],
+}
+
+impl<const N: usize> RotationTable<N> {
+    /// Works out the table. This is a `const fn` so that it can be stored in a `static` and
+    /// calculated at compile time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0 or greater than 65536.
+    #[must_use]
+    pub const fn new() -> Self {
+        assert!(N > 0 && N <= 1 << 16, "N must be between 1 and 65536");
+
+        let mut entries = [(0, 0); N];
+
+        let mut i = 0;
+        while i < N {
+            let turns = (i as i64 * ONE as i64 / N as i64) as i32;
+            entries[i] = (cos(turns), cos(turns - ONE / 4));
+            i += 1;
+        }
+
+        Self { entries }
+    }
+
+    /// The rotation matrix for `angle`, where 0 is no rotation and 65536 would be a whole
+    /// turn anticlockwise. The result is a linear interpolation between the two nearest entries
+    /// in the table.
+    #[must_use]
+    pub fn get_matrix(&self, angle: u16) -> AffineMatrix {
+        let position = u32::from(angle) * N as u32;
+        let index = (position >> 16) as usize;
+        let fraction = (position & 0xffff) as i32;
+
+        let (cos0, sin0) = self.entries[index];
+        let (cos1, sin1) = self.entries[(index + 1) % N];
+
+        let interpolate = |from: i32, to: i32| {
+            let value = from + (((to - from) as i64 * fraction as i64) >> 16) as i32;
+            Num::from_raw(value >> (FRACTIONAL_BITS - 8))
+        };
+
+        let cos = interpolate(cos0, cos1);
+        let sin = interpolate(sin0, sin1);
+
+        // the same layout as AffineMatrix::from_rotation
+        AffineMatrix {
+            a: cos,
+            b: -sin,
+            c: sin,
+            d: cos,
+            x: 0.into(),
+            y: 0.into(),
+        }
+    }
+}
+
+impl<const N: usize> Default for RotationTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TABLE: RotationTable<64> = RotationTable::new();
+
+    #[test_case]
+    fn matches_from_rotation(_gba: &mut crate::Gba) {
+        for angle in (0..=u16::MAX).step_by(0x100) {
+            let expected = AffineMatrix::from_rotation(Num::<i32, 16>::from_raw(i32::from(angle)));
+            let actual = TABLE.get_matrix(angle);
+
+            // within a couple of 256ths, interpolating between entries cuts corners slightly
+            for (expected, actual) in [
+                (expected.a, actual.a),
+                (expected.b, actual.b),
+                (expected.c, actual.c),
+                (expected.d, actual.d),
+            ] {
+                assert!(
+                    (expected - actual).abs() <= Num::from_raw(3),
+                    "angle {angle:#x}: expected {expected:?} got {actual:?}"
+                );
+            }
+        }
+
+        assert_eq!(TABLE.get_matrix(0), AffineMatrix::identity());
+    }
+}
@@ -18,6 +18,7 @@ pub use sprites::{
     include_aseprite, DynamicSprite, Graphics, PaletteVram, Size, Sprite, SpriteLoader, SpriteVram,
     Tag, TagMap,
 };
+pub(crate) use sprites::{release_palette_banks, reserve_palette_banks, OBJECT_PALETTE};
 
 pub use affine::AffineMatrixInstance;
 pub use managed::{OamManaged, Object};
@@ -27,7 +28,7 @@ pub use unmanaged::{
 
 pub use font::{ChangeColour, ObjectTextRender, TextAlignment};
 
-use super::DISPLAY_CONTROL;
+use super::{obj_1d_vs_2d_mapping::ObjMappingMode, DISPLAY_CONTROL};
 
 const OBJECT_ATTRIBUTE_MEMORY: *mut u16 = 0x0700_0000 as *mut u16;
 
@@ -42,7 +43,7 @@ pub(super) unsafe fn initilise_oam() {
         ptr.write_volatile(0b10 << 8);
     }
 
-    DISPLAY_CONTROL.set_bits(1, 1, 0x6);
+    ObjMappingMode::OneDimensional.select();
     DISPLAY_CONTROL.set_bits(1, 1, 0xC);
     DISPLAY_CONTROL.set_bits(0, 1, 0x7);
 }
@@ -4,4 +4,5 @@ mod sprite_allocator;
 const BYTES_PER_TILE_4BPP: usize = 32;
 
 pub use sprite::{include_aseprite, Graphics, Size, Sprite, Tag, TagMap};
+pub(crate) use sprite_allocator::{release_palette_banks, reserve_palette_banks, OBJECT_PALETTE};
 pub use sprite_allocator::{DynamicSprite, PaletteVram, SpriteLoader, SpriteVram};
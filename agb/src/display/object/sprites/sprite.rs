@@ -32,6 +32,12 @@ impl Sprite {
     pub fn size(&self) -> Size {
         self.size
     }
+
+    #[must_use]
+    /// Gives the palette the sprite is drawn with
+    pub fn palette(&self) -> &'static Palette16 {
+        self.palette
+    }
 }
 
 /// The sizes of sprite supported by the GBA.
@@ -1,4 +1,7 @@
-use core::{alloc::Allocator, ptr::NonNull};
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    ptr::NonNull,
+};
 
 use alloc::{
     boxed::Box,
@@ -7,8 +10,11 @@ use alloc::{
 
 use crate::{
     agb_alloc::{block_allocator::BlockAllocator, bump_allocator::StartEnd, impl_zst_allocator},
-    display::palette16::Palette16,
+    display::{
+        obj_chr_block_manager::ObjChrBlockManager, palette16::Palette16, video_ram_map::VramLayout,
+    },
     hash_map::HashMap,
+    memory_mapped::MemoryMapped1DArray,
 };
 
 use super::{
@@ -17,18 +23,28 @@ use super::{
 };
 
 pub const PALETTE_SPRITE: usize = 0x0500_0200;
+/// Every colour of the 16 object palette banks.
+pub(crate) const OBJECT_PALETTE: MemoryMapped1DArray<u16, 256> =
+    unsafe { MemoryMapped1DArray::new(PALETTE_SPRITE) };
 pub const TILE_SPRITE: usize = 0x06010000;
 
-static SPRITE_ALLOCATOR: BlockAllocator = unsafe {
-    BlockAllocator::new(StartEnd {
-        start: || TILE_SPRITE,
-        end: || TILE_SPRITE + 1024 * 8 * 4,
-    })
-};
-
+/// Allocates sprite tiles through the [`ObjChrBlockManager`] so they are shared with anything
+/// else using object tile memory.
 pub struct SpriteAllocator;
 
-impl_zst_allocator!(SpriteAllocator, SPRITE_ALLOCATOR);
+unsafe impl Allocator for SpriteAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let allocated = ObjChrBlockManager::get()
+            .alloc_bytes(layout.size())
+            .ok_or(AllocError)?;
+
+        Ok(NonNull::slice_from_raw_parts(allocated, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        ObjChrBlockManager::get().free_bytes(ptr, layout.size());
+    }
+}
 
 static PALETTE_ALLOCATOR: BlockAllocator = unsafe {
     BlockAllocator::new(StartEnd {
@@ -41,6 +57,26 @@ pub struct PaletteAllocator;
 
 impl_zst_allocator!(PaletteAllocator, PALETTE_ALLOCATOR);
 
+/// Reserves `count` consecutive object palette banks, returning the index of the first, or
+/// `None` if there isn't a run of that many free banks.
+pub(crate) fn reserve_palette_banks(count: usize) -> Option<usize> {
+    let layout = Layout::array::<Palette16>(count).ok()?;
+    let allocated = unsafe { PALETTE_ALLOCATOR.alloc(layout) }?;
+
+    Some(Location::from_palette_ptr(allocated).0)
+}
+
+/// Gives back banks reserved with [`reserve_palette_banks`].
+///
+/// # Safety
+///
+/// `first` and `count` must be from a single call to [`reserve_palette_banks`], and nothing can
+/// use the banks afterwards.
+pub(crate) unsafe fn release_palette_banks(first: usize, count: usize) {
+    let layout = Layout::array::<Palette16>(count).expect("the banks were reserved");
+    unsafe { PALETTE_ALLOCATOR.dealloc(Location(first).as_palette_ptr(), layout) };
+}
+
 /// The Sprite Id is a thin wrapper around the pointer to the sprite in
 /// rom and is therefore a unique identifier to a sprite
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -123,6 +159,12 @@ impl PaletteVram {
             }),
         })
     }
+
+    /// Which of the 16 object palette banks the palette is in.
+    #[must_use]
+    pub fn bank(&self) -> usize {
+        self.data.location.0
+    }
 }
 
 #[derive(Debug)]
@@ -134,7 +176,13 @@ struct SpriteVramData {
 
 impl Drop for SpriteVramData {
     fn drop(&mut self) {
-        unsafe { SPRITE_ALLOCATOR.dealloc(self.location.as_sprite_ptr(), self.size.layout()) }
+        // Safety: the sprite was allocated by the sprite allocator with this layout
+        unsafe {
+            SpriteAllocator.deallocate(
+                NonNull::new_unchecked(self.location.as_sprite_ptr()),
+                self.size.layout(),
+            );
+        }
     }
 }
 
@@ -161,8 +209,19 @@ pub struct SpriteVram {
 
 impl SpriteVram {
     fn new(data: &[u8], size: Size, palette: PaletteVram) -> Result<SpriteVram, LoaderError> {
-        let allocated =
-            unsafe { SPRITE_ALLOCATOR.alloc(size.layout()) }.ok_or(LoaderError::SpriteFull)?;
+        let allocated = SpriteAllocator
+            .allocate(size.layout())
+            .map_err(|_| LoaderError::SpriteFull)?
+            .cast::<u8>();
+        debug_assert!(
+            {
+                let layout = VramLayout::current();
+                let first_tile = (allocated.as_ptr() as usize - TILE_SPRITE) / BYTES_PER_TILE_4BPP;
+                let last_tile = first_tile + (data.len() - 1) / BYTES_PER_TILE_4BPP;
+                layout.validate_obj_tile(first_tile) && layout.validate_obj_tile(last_tile)
+            },
+            "sprite allocated in object tile memory which is not usable in the current display mode"
+        );
         unsafe {
             allocated
                 .as_ptr()
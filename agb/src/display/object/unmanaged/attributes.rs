@@ -56,10 +56,44 @@ impl Attributes {
         }
     }
 
+    /// The first tile of the sprite along with its width and height in tiles
+    pub fn tile_layout(self) -> (usize, usize, usize) {
+        const TILES_WIDTH_HEIGHT: [[(usize, usize); 4]; 3] = [
+            [(1, 1), (2, 2), (4, 4), (8, 8)],
+            [(2, 1), (4, 1), (4, 2), (8, 4)],
+            [(1, 2), (1, 4), (2, 4), (4, 8)],
+        ];
+
+        let shape = usize::from(self.a0.shape().value()).min(2);
+        let size = usize::from(self.a1s.size().value());
+        let (width, height) = TILES_WIDTH_HEIGHT[shape][size];
+
+        (usize::from(self.a2.tile_index().value()), width, height)
+    }
+
+    /// Like [`tile_layout`](Self::tile_layout), but with the width counted in 4bpp tiles of
+    /// object tile memory. An 8bpp tile takes up the space of two 4bpp tiles.
+    pub fn tile_memory_layout(self) -> (usize, usize, usize) {
+        let (tile_index, width, height) = self.tile_layout();
+
+        match self.a0.colour_mode() {
+            ColourMode::Four => (tile_index, width, height),
+            ColourMode::Eight => (tile_index, width * 2, height),
+        }
+    }
+
     pub fn is_visible(self) -> bool {
         self.a0.object_mode() != ObjectMode::Disabled
     }
 
+    pub fn affine_mode(self) -> Option<AffineMode> {
+        match self.a0.object_mode() {
+            ObjectMode::Affine => Some(AffineMode::Affine),
+            ObjectMode::AffineDouble => Some(AffineMode::AffineDouble),
+            ObjectMode::Normal | ObjectMode::Disabled => None,
+        }
+    }
+
     pub fn show(&mut self) -> &mut Self {
         self.a0.set_object_mode(ObjectMode::Normal);
 
@@ -153,6 +187,12 @@ impl Attributes {
         self
     }
 
+    pub fn set_tile_index(&mut self, tile_index: u16) -> &mut Self {
+        self.a2.set_tile_index(u10::new(tile_index));
+
+        self
+    }
+
     pub fn set_graphics_mode(&mut self, mode: GraphicsMode) -> &mut Self {
         self.a0.set_graphics_mode(match mode {
             GraphicsMode::Normal => GraphicsModeInternal::Normal,
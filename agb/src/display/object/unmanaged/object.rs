@@ -251,6 +251,22 @@ impl ObjectUnmanaged {
         sprite
     }
 
+    pub(crate) fn tile_layout(&self) -> (usize, usize, usize) {
+        self.attributes.tile_layout()
+    }
+
+    pub(crate) fn tile_memory_layout(&self) -> (usize, usize, usize) {
+        self.attributes.tile_memory_layout()
+    }
+
+    pub(crate) fn set_tile_index(&mut self, tile_index: u16) {
+        self.attributes.set_tile_index(tile_index);
+    }
+
+    pub(crate) fn affine_mode(&self) -> Option<AffineMode> {
+        self.attributes.affine_mode()
+    }
+
     #[must_use]
     /// Checks whether the object is not marked as hidden. Note that it could be
     /// off screen or completely transparent and still claimed to be visible.
@@ -406,6 +422,14 @@ impl ObjectUnmanaged {
         self
     }
 
+    /// Draws the object with a different palette bank to its sprite's, until the sprite is next
+    /// set.
+    pub(crate) fn set_palette_bank(&mut self, bank: u8) -> &mut Self {
+        self.attributes.set_palette(u16::from(bank));
+
+        self
+    }
+
     /// Sets the graphics mode of the object
     pub fn set_graphics_mode(&mut self, mode: GraphicsMode) -> &mut Self {
         self.attributes.set_graphics_mode(mode);
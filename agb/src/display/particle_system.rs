@@ -0,0 +1,321 @@
+//! Lots of short lived sprites moved by the CPU, for explosions, sparks and sparkles.
+//!
+//! A [`ParticleSystem`] has room for a fixed number of particles. Each one is an object showing
+//! a sprite, which moves at its own velocity, falls under its own gravity and disappears after
+//! its lifetime is up, freeing its object for another particle. Particles which end up more than
+//! a screen's width or height outside the screen are removed early, and particles never move
+//! faster than [`MAX_SPEED`] pixels a frame. Particles can be
+//! [spawned](ParticleSystem::spawn) one at a time, or many at once flying out from a point with
+//! [`emit_burst`](ParticleSystem::emit_burst).
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, spark: &'static agb::display::object::Sprite) {
+//! use agb::{display::particle_system::ParticleSystem, fixnum::{num, Vector2D}};
+//!
+//! let oam = gba.display.object.get_managed();
+//! let spark = oam.sprite(spark);
+//!
+//! let mut particles = ParticleSystem::<64>::new();
+//! particles.set_burst(30, num!(0.125));
+//!
+//! // something exploded
+//! particles.emit_burst(Vector2D::new(120, 80), 20, 32, &spark, &oam);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     particles.update();
+//!
+//!     vblank.wait_for_vblank();
+//!     oam.commit();
+//! }
+//! # }
+//! ```
+
+use crate::{
+    fixnum::{Num, Vector2D},
+    rng,
+};
+
+use super::object::{OamManaged, Object, SpriteVram};
+
+/// How many frames particles made by [`ParticleSystem::emit_burst`] last for, unless changed
+/// with [`ParticleSystem::set_burst`].
+pub const DEFAULT_BURST_LIFETIME: u8 = 30;
+
+/// The fastest a particle moves in each direction, in pixels per frame. Faster velocities and
+/// gravity beyond this are capped, which keeps positions from overflowing.
+pub const MAX_SPEED: Num<i16, 4> = Num::from_raw(256 << 4);
+
+/// How far outside the screen a particle can go before it is removed, as the range of
+/// positions which are kept.
+const KEEP_X: core::ops::Range<i16> = -240..480;
+const KEEP_Y: core::ops::Range<i16> = -160..320;
+
+/// How a particle moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Particle {
+    /// Where the centre of the particle is on the screen.
+    pub position: Vector2D<Num<i16, 4>>,
+    /// How far the particle moves each frame.
+    pub velocity: Vector2D<Num<i16, 4>>,
+    /// How much the particle's downwards velocity increases each frame.
+    pub gravity: Num<i16, 4>,
+    /// How many more frames the particle is shown for.
+    pub lifetime: u8,
+}
+
+struct LiveParticle<'oam> {
+    particle: Particle,
+    object: Object<'oam>,
+    /// Half the size of the sprite, to show it centred on the particle's position.
+    half_size: Vector2D<i32>,
+}
+
+impl LiveParticle<'_> {
+    fn place(&mut self) {
+        let position = self.particle.position.floor();
+        self.object.set_position(
+            Vector2D::new(i32::from(position.x), i32::from(position.y)) - self.half_size,
+        );
+    }
+}
+
+/// Up to `MAX` particles, see the [module level documentation](self).
+pub struct ParticleSystem<'oam, const MAX: usize> {
+    particles: [Option<LiveParticle<'oam>>; MAX],
+    burst_lifetime: u8,
+    burst_gravity: Num<i16, 4>,
+}
+
+impl<'oam, const MAX: usize> ParticleSystem<'oam, MAX> {
+    /// A particle system with no particles, whose bursts last for [`DEFAULT_BURST_LIFETIME`]
+    /// frames and have no gravity.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            particles: core::array::from_fn(|_| None),
+            burst_lifetime: DEFAULT_BURST_LIFETIME,
+            burst_gravity: Num::new(0),
+        }
+    }
+
+    /// Sets how many frames the particles made by [`emit_burst`](ParticleSystem::emit_burst)
+    /// last for, and their gravity.
+    pub fn set_burst(&mut self, lifetime: u8, gravity: Num<i16, 4>) {
+        self.burst_lifetime = lifetime;
+        self.burst_gravity = gravity;
+    }
+
+    /// Shows `sprite` as a particle moving as described by `particle`, in a free slot. Returns
+    /// `false` without spawning anything if every slot is in use or the lifetime is 0.
+    pub fn spawn(
+        &mut self,
+        particle: Particle,
+        sprite: &SpriteVram,
+        oam: &'oam OamManaged<'_>,
+    ) -> bool {
+        if particle.lifetime == 0 {
+            return false;
+        }
+
+        let Some(slot) = self.particles.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+
+        let (width, height) = sprite.size().to_width_height();
+        let mut object = oam.object(sprite.clone());
+        object.show();
+
+        let mut live = LiveParticle {
+            particle,
+            object,
+            half_size: Vector2D::new(width as i32 / 2, height as i32 / 2),
+        };
+        live.place();
+
+        *slot = Some(live);
+        true
+    }
+
+    /// Spawns up to `count` particles showing `sprite` at `center`, flying out in random
+    /// directions at random speeds of up to `spread` sixteenths of a pixel per frame. Returns
+    /// how many were spawned, which is fewer than `count` if the system runs out of room.
+    pub fn emit_burst(
+        &mut self,
+        center: Vector2D<i16>,
+        count: u8,
+        spread: u8,
+        sprite: &SpriteVram,
+        oam: &'oam OamManaged<'_>,
+    ) -> usize {
+        let position = Vector2D::new(Num::new(center.x), Num::new(center.y));
+
+        let mut spawned = 0;
+        for _ in 0..count {
+            // a fraction of a turn, and a speed in sixteenths of a pixel
+            let angle: Num<i32, 8> = Num::from_raw(rng::gen() & 0xff);
+            let speed = rng::gen().rem_euclid(i32::from(spread) + 1);
+
+            let velocity = Vector2D::new(
+                Num::from_raw((angle.cos().to_raw() * speed / 256) as i16),
+                Num::from_raw((angle.sin().to_raw() * speed / 256) as i16),
+            );
+
+            let particle = Particle {
+                position,
+                velocity,
+                gravity: self.burst_gravity,
+                lifetime: self.burst_lifetime,
+            };
+
+            if !self.spawn(particle, sprite, oam) {
+                break;
+            }
+            spawned += 1;
+        }
+
+        spawned
+    }
+
+    /// Moves every particle on by a frame, removing the ones whose lifetime has run out. Call
+    /// this once per frame.
+    pub fn update(&mut self) {
+        for slot in &mut self.particles {
+            let Some(live) = slot else {
+                continue;
+            };
+
+            let particle = &mut live.particle;
+            particle.lifetime -= 1;
+            if particle.lifetime == 0 {
+                // dropping the object removes it from the screen
+                *slot = None;
+                continue;
+            }
+
+            let position = particle.position.floor();
+            if !KEEP_X.contains(&position.x) || !KEEP_Y.contains(&position.y) {
+                *slot = None;
+                continue;
+            }
+
+            let gravity = particle.gravity.clamp(-MAX_SPEED, MAX_SPEED);
+            particle.velocity.x = particle.velocity.x.clamp(-MAX_SPEED, MAX_SPEED);
+            particle.velocity.y = (particle.velocity.y + gravity).clamp(-MAX_SPEED, MAX_SPEED);
+            particle.position += particle.velocity;
+            live.place();
+        }
+    }
+
+    /// How many particles are currently shown.
+    #[must_use]
+    pub fn live_count(&self) -> usize {
+        self.particles.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self) {
+        for slot in &mut self.particles {
+            *slot = None;
+        }
+    }
+}
+
+impl<const MAX: usize> Default for ParticleSystem<'_, MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        display::{
+            object::{DynamicSprite, PaletteVram, Size},
+            palette16::Palette16,
+        },
+        fixnum::num,
+    };
+
+    #[test_case]
+    fn particles_move_fall_and_expire(gba: &mut crate::Gba) {
+        let oam = gba.display.object.get_managed();
+        let palette = PaletteVram::new(&Palette16::new([0x7fff; 16])).unwrap();
+        let sprite = DynamicSprite::try_new(Size::S8x8).unwrap().to_vram(palette);
+
+        let mut particles = ParticleSystem::<4>::new();
+
+        let particle = Particle {
+            position: Vector2D::new(num!(10.), num!(20.)),
+            velocity: Vector2D::new(num!(1.5), num!(0.)),
+            gravity: num!(0.5),
+            lifetime: 3,
+        };
+        assert!(particles.spawn(particle, &sprite, &oam));
+
+        let object_position = |particles: &ParticleSystem<4>| {
+            particles.particles[0].as_ref().unwrap().object.position()
+        };
+        // centred on its position
+        assert_eq!(object_position(&particles), Vector2D::new(6, 16));
+
+        particles.update();
+        assert_eq!(object_position(&particles), Vector2D::new(7, 16));
+        particles.update();
+        assert_eq!(object_position(&particles), Vector2D::new(9, 17));
+        particles.update();
+        assert_eq!(particles.live_count(), 0);
+
+        assert_eq!(
+            particles.emit_burst(Vector2D::new(50, 50), 10, 16, &sprite, &oam),
+            4
+        );
+        assert!(!particles.spawn(particle, &sprite, &oam));
+
+        particles.update();
+        for live in particles.particles.iter().flatten() {
+            let distance = live.particle.position - Vector2D::new(num!(50.), num!(50.));
+            assert!(distance.x.abs() <= num!(1.) && distance.y.abs() <= num!(1.));
+        }
+
+        particles.clear();
+        oam.commit();
+    }
+
+    #[test_case]
+    fn falling_particles_are_removed_before_overflowing(gba: &mut crate::Gba) {
+        let oam = gba.display.object.get_managed();
+        let palette = PaletteVram::new(&Palette16::new([0x7fff; 16])).unwrap();
+        let sprite = DynamicSprite::try_new(Size::S8x8).unwrap().to_vram(palette);
+
+        let mut particles = ParticleSystem::<2>::new();
+
+        let falling = Particle {
+            position: Vector2D::new(num!(120.), num!(80.)),
+            velocity: Vector2D::new(num!(0.), num!(-4.)),
+            gravity: num!(0.5),
+            lifetime: 255,
+        };
+        let fast = Particle {
+            velocity: Vector2D::new(num!(2000.), num!(0.)),
+            gravity: num!(2000.),
+            ..falling
+        };
+        assert!(particles.spawn(falling, &sprite, &oam));
+        assert!(particles.spawn(fast, &sprite, &oam));
+
+        for _ in 0..255 {
+            particles.update();
+            for live in particles.particles.iter().flatten() {
+                assert!(live.particle.velocity.x <= MAX_SPEED);
+                assert!(live.particle.velocity.y <= MAX_SPEED);
+            }
+        }
+
+        assert_eq!(particles.live_count(), 0);
+        oam.commit();
+    }
+}
@@ -0,0 +1,162 @@
+//! Statistics about how many scanlines recent frames took, for debug builds.
+//!
+//! This is only available with the `profiling` feature. Measure each frame with something like
+//! [`VCountProfiler`](super::vcount_profiler::VCountProfiler) or
+//! [`frame_stats`](super::cpu_usage::frame_stats) and pass the result to
+//! [`RenderStats::record_frame`].
+
+use core::fmt::Write;
+
+use crate::fixnum::Num;
+
+use super::{
+    font::TextRenderer,
+    tiled::{RegularMap, VRamManager},
+};
+
+const WINDOW: usize = 60;
+const SCANLINES_PER_FRAME: u8 = 228;
+
+/// The average, minimum and maximum scanlines used over the last 60 frames, along with how
+/// many frames have overrun.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::display::{render_stats::RenderStats, vcount_profiler::VCountProfiler};
+///
+/// let mut stats = RenderStats::new();
+///
+/// loop {
+///     let profiler = VCountProfiler::start();
+///     // update and render the frame
+///     stats.record_frame(profiler.finish().scanlines_used);
+///
+///     agb::println!("{} scanlines on average", stats.average());
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderStats {
+    frames: [u8; WINDOW],
+    next: usize,
+    len: usize,
+    overruns: u32,
+}
+
+impl RenderStats {
+    /// Creates statistics with no frames recorded.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            frames: [0; WINDOW],
+            next: 0,
+            len: 0,
+            overruns: 0,
+        }
+    }
+
+    /// Records that a frame took `scanlines` scanlines, replacing the oldest frame once 60
+    /// have been recorded. A frame taking 228 scanlines or more counts as an overrun, since it
+    /// used the whole of its frame.
+    pub fn record_frame(&mut self, scanlines: u8) {
+        self.frames[self.next] = scanlines;
+        self.next = (self.next + 1) % WINDOW;
+        self.len = (self.len + 1).min(WINDOW);
+
+        if scanlines >= SCANLINES_PER_FRAME {
+            self.overruns += 1;
+        }
+    }
+
+    fn recorded(&self) -> &[u8] {
+        &self.frames[..self.len]
+    }
+
+    /// The average scanlines used by the recorded frames, or 0 if none have been recorded.
+    #[must_use]
+    pub fn average(&self) -> Num<u16, 4> {
+        if self.len == 0 {
+            return Num::new(0);
+        }
+
+        let total: u32 = self.recorded().iter().map(|&frame| u32::from(frame)).sum();
+        Num::from_raw(((total << 4) / self.len as u32) as u16)
+    }
+
+    /// The fewest scanlines used by a recorded frame, or 0 if none have been recorded.
+    #[must_use]
+    pub fn min(&self) -> u8 {
+        self.recorded().iter().copied().min().unwrap_or(0)
+    }
+
+    /// The most scanlines used by a recorded frame, or 0 if none have been recorded.
+    #[must_use]
+    pub fn max(&self) -> u8 {
+        self.recorded().iter().copied().max().unwrap_or(0)
+    }
+
+    /// The number of frames which have overrun since the statistics were created, including
+    /// those which are no longer in the 60 frame window.
+    #[must_use]
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Writes a one line summary using `renderer`, replacing whatever it showed before.
+    pub fn display_on_screen(
+        &self,
+        renderer: &mut TextRenderer<'_>,
+        foreground_colour: u8,
+        background_colour: u8,
+        bg: &mut RegularMap,
+        vram_manager: &mut VRamManager,
+    ) {
+        renderer.clear(vram_manager);
+
+        let mut writer = renderer.writer(foreground_colour, background_colour, bg, vram_manager);
+        let _ = write!(
+            writer,
+            "avg {} min {} max {} over {}",
+            self.average(),
+            self.min(),
+            self.max(),
+            self.overruns()
+        );
+        writer.commit();
+    }
+}
+
+impl Default for RenderStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn statistics_cover_the_last_sixty_frames(_gba: &mut crate::Gba) {
+        let mut stats = RenderStats::new();
+        assert_eq!(stats.average(), Num::new(0));
+
+        stats.record_frame(230);
+        for _ in 0..WINDOW - 2 {
+            stats.record_frame(100);
+        }
+        stats.record_frame(130);
+
+        assert_eq!(stats.min(), 100);
+        assert_eq!(stats.max(), 230);
+        assert_eq!(stats.overruns(), 1);
+        assert_eq!(stats.average(), Num::from_raw((6160 << 4) / 60));
+
+        // pushes the overrunning frame out of the window
+        stats.record_frame(120);
+        assert_eq!(stats.max(), 130);
+        assert_eq!(stats.overruns(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+//! Trauma based screen shake.
+//!
+//! Rather than shaking by a fixed amount, [`ScreenShake`] keeps a trauma value between 0 and 1
+//! which is added to when something dramatic happens and fades away over time. The shake is
+//! proportional to the square of the trauma, so small knocks barely move the screen while big
+//! impacts stack up into a violent shake that settles down smoothly.
+
+use crate::fixnum::{num, Num, Vector2D};
+
+/// Shakes the screen by an amount which dies away over time.
+///
+/// Call [`update`](ScreenShake::update) once per frame and add the offset it returns to the
+/// scroll position of every background and the position of every sprite, so the whole
+/// screen moves together.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::{display::screen_shake::ScreenShake, fixnum::num};
+///
+/// let mut shake = ScreenShake::new(8, num!(0.02));
+///
+/// // something exploded
+/// shake.add_trauma(num!(0.5));
+///
+/// for frame in 0.. {
+///     let offset = shake.update(frame);
+///     // apply offset to the backgrounds and sprites
+/// #   break;
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenShake {
+    /// How much the screen is currently shaking, from 0 for not at all to 1 for the most.
+    pub trauma: Num<i16, 8>,
+    /// How much trauma is removed each frame.
+    pub decay_per_frame: Num<i16, 8>,
+    /// The largest offset in pixels, reached in each direction at a trauma of 1.
+    pub max_offset: i16,
+}
+
+impl ScreenShake {
+    /// Creates a screen shake with no trauma which moves the screen by up to `max_offset`
+    /// pixels and loses `decay_per_frame` of trauma each frame.
+    #[must_use]
+    pub fn new(max_offset: i16, decay_per_frame: Num<i16, 8>) -> Self {
+        Self {
+            trauma: Num::new(0),
+            decay_per_frame,
+            max_offset,
+        }
+    }
+
+    /// Adds to the trauma, up to a maximum of 1.
+    pub fn add_trauma(&mut self, amount: Num<i16, 8>) {
+        self.trauma = (self.trauma + amount).clamp(num!(0.), num!(1.));
+    }
+
+    /// Returns the offset to apply to the screen this frame and decays the trauma. `frame`
+    /// should go up by one each frame, since it chooses where the screen moves to.
+    pub fn update(&mut self, frame: u32) -> Vector2D<i16> {
+        // worked out in i32, as an offset of 128 or more doesn't fit in a Num<i16, 8>
+        let trauma: Num<i32, 8> = self.trauma.change_base();
+        let strength = trauma * trauma * i32::from(self.max_offset);
+        let offset = |seed| {
+            let noise: Num<i32, 8> = noise(frame, seed).change_base();
+            (strength * noise)
+                .floor()
+                .clamp(i16::MIN.into(), i16::MAX.into()) as i16
+        };
+
+        let offset = Vector2D::new(offset(0x68e3_1da4), offset(0xb529_7a4d));
+
+        self.trauma = (self.trauma - self.decay_per_frame).max(num!(0.));
+
+        offset
+    }
+}
+
+/// A pseudo random value between -1 and 1 for each frame, different for each `seed`.
+fn noise(frame: u32, seed: u32) -> Num<i16, 8> {
+    let mut x = frame.wrapping_mul(0x9e37_79b9) ^ seed;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+
+    Num::from_raw((x & 0x1ff) as i16 - 0x100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn shake_stays_in_bounds_and_settles(_gba: &mut crate::Gba) {
+        let mut shake = ScreenShake::new(8, num!(0.05));
+
+        assert_eq!(shake.update(0), Vector2D::new(0, 0));
+
+        shake.add_trauma(num!(0.75));
+        shake.add_trauma(num!(0.75));
+        assert_eq!(shake.trauma, num!(1.));
+
+        let mut moved = false;
+        for frame in 1..30 {
+            let offset = shake.update(frame);
+            assert!((-8..=8).contains(&offset.x) && (-8..=8).contains(&offset.y));
+            moved |= offset != Vector2D::new(0, 0);
+        }
+
+        assert!(moved);
+        assert_eq!(shake.trauma, num!(0.));
+        assert_eq!(shake.update(30), Vector2D::new(0, 0));
+
+        let mut big = ScreenShake::new(200, num!(0.05));
+        big.add_trauma(num!(1.));
+        for frame in 0..30 {
+            let offset = big.update(frame);
+            assert!((-200..=200).contains(&offset.x) && (-200..=200).contains(&offset.y));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Scrolling each scanline of a background by a different amount.
+//!
+//! Fighting and racing games often scroll strips of a background at different speeds, to make
+//! the floor look like it stretches into the distance or to make heat haze ripple across the
+//! screen. A [`ColumnParallax`] keeps a horizontal scroll for each of the 160 visible scanlines
+//! and uses a DMA transfer, triggered at the end of each scanline, to write the next one to
+//! the background's scroll register while the screen is drawn.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{
+//!     display::{
+//!         scrolling_parallax_columns::ColumnParallax,
+//!         tiled::{RegularBackgroundSize, TileFormat, TiledMap},
+//!         Priority,
+//!     },
+//!     fixnum::num,
+//! };
+//!
+//! let (gfx, mut vram) = gba.display.video.tiled0();
+//! let mut map = gfx.background(
+//!     Priority::P0,
+//!     RegularBackgroundSize::Background32x32,
+//!     TileFormat::FourBpp,
+//! );
+//! // set the tiles
+//! map.commit(&mut vram);
+//! map.set_visible(true);
+//!
+//! let dmas = gba.dma.dma();
+//! let mut parallax = ColumnParallax::new();
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! let mut phase = num!(0.);
+//! loop {
+//!     phase += num!(0.01);
+//!     parallax.waving_column_effect(8, num!(0.02), phase);
+//!
+//!     vblank.wait_for_vblank();
+//!     parallax.install(&dmas.dma3, &map.x_scroll_dma());
+//! }
+//! # }
+//! ```
+
+use agb_fixnum::Num;
+
+use super::HEIGHT;
+use crate::dma::{Dma, DmaControllable, DmaTransferHandle};
+
+const SCANLINES: usize = HEIGHT as usize;
+
+/// A horizontal scroll for each scanline of a background. See the [module level
+/// documentation](self).
+pub struct ColumnParallax<'a> {
+    scrolls: [i16; SCANLINES],
+    transfer: Option<DmaTransferHandle<'a, i16>>,
+}
+
+impl<'a> ColumnParallax<'a> {
+    /// A table with every scanline scrolled to 0, which isn't installed yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            scrolls: [0; SCANLINES],
+            transfer: None,
+        }
+    }
+
+    /// Sets the horizontal scroll of `scanline`. The change is shown from the next time the
+    /// table is [installed](ColumnParallax::install).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scanline` is 160 or more.
+    pub fn set_column_scroll(&mut self, scanline: usize, offset: i16) {
+        self.scrolls[scanline] = offset;
+    }
+
+    /// The horizontal scroll of each scanline.
+    #[must_use]
+    pub fn column_scrolls(&self) -> &[i16; SCANLINES] {
+        &self.scrolls
+    }
+
+    /// Fills the table with a sine wave, so the background ripples sideways. Scanline `y` is
+    /// scrolled by `amplitude * sin(phase + y * frequency)`, where a `frequency` of 1 is a
+    /// whole wave on each line, so small values such as 0.02 give a gentle wave. Change the
+    /// `phase` each frame to make the wave move.
+    pub fn waving_column_effect(
+        &mut self,
+        amplitude: i16,
+        frequency: Num<i32, 8>,
+        phase: Num<i32, 8>,
+    ) {
+        let mut angle = phase;
+        for scroll in &mut self.scrolls {
+            *scroll = (angle.sin() * i32::from(amplitude)).round() as i16;
+            angle += frequency;
+        }
+    }
+
+    /// Starts copying the table to the scroll register of `background` at the end of each
+    /// scanline, using `dma`, with the first scanline's scroll written straight away. Get
+    /// `background` with [`RegularMap::x_scroll_dma`](super::tiled::RegularMap::x_scroll_dma).
+    ///
+    /// The table is copied when it is installed, so changes after this aren't seen until it is
+    /// installed again. Install it once each frame during vblank, after any changes, so every
+    /// frame starts from the first scanline. Installing again replaces the previous transfer.
+    ///
+    /// Use DMA 3 unless something else needs it, since DMA 0 is also used by
+    /// [`copy32`](crate::dma::copy32) for data outside ROM.
+    pub fn install(&mut self, dma: &'a Dma, background: &DmaControllable<i16>) {
+        // dropping the old transfer stops its DMA channel, which may be the one about to be used
+        self.transfer = None;
+
+        // SAFETY: the transfer copies the table into memory it owns, so it doesn't matter what
+        // happens to `self.scrolls`
+        self.transfer = Some(unsafe { dma.hblank_transfer_copied(background, &self.scrolls) });
+    }
+
+    /// Stops the DMA transfer, leaving the scroll register at whichever scanline's scroll was
+    /// copied last.
+    pub fn uninstall(&mut self) {
+        self.transfer = None;
+    }
+
+    /// Whether the table is being copied to a scroll register.
+    #[must_use]
+    pub fn is_installed(&self) -> bool {
+        self.transfer.is_some()
+    }
+}
+
+impl Default for ColumnParallax<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use agb_fixnum::num;
+
+    use super::*;
+    use crate::{
+        display::{
+            tiled::{RegularBackgroundSize, TileFormat},
+            Priority,
+        },
+        memory_mapped::MemoryMapped,
+    };
+
+    #[test_case]
+    fn waves_and_installs(gba: &mut crate::Gba) {
+        let (gfx, _vram) = gba.display.video.tiled0();
+        let map = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+        let dmas = gba.dma.dma();
+
+        let mut parallax = ColumnParallax::new();
+
+        parallax.waving_column_effect(10, num!(0.25), num!(0.));
+        assert_eq!(&parallax.column_scrolls()[..5], &[0, 10, 0, -10, 0]);
+
+        parallax.waving_column_effect(4, num!(0.), num!(0.25));
+        assert!(parallax.column_scrolls().iter().all(|&scroll| scroll == 4));
+
+        parallax.set_column_scroll(0, -7);
+        parallax.set_column_scroll(159, 3);
+        assert_eq!(parallax.column_scrolls()[159], 3);
+
+        parallax.install(&dmas.dma3, &map.x_scroll_dma());
+        assert!(parallax.is_installed());
+        // the first scanline's scroll is written straight away
+        assert_eq!(unsafe { MemoryMapped::<i16>::new(0x0400_0010) }.get(), -7);
+
+        let dma3_control = unsafe { MemoryMapped::<u32>::new(0x0400_00dc) };
+        assert_ne!(dma3_control.get() & (1 << 31), 0);
+
+        parallax.uninstall();
+        assert!(!parallax.is_installed());
+        assert_eq!(dma3_control.get() & (1 << 31), 0);
+    }
+}
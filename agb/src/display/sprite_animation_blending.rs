@@ -0,0 +1,198 @@
+//! Cross-fading between sprite animations.
+//!
+//! Switching straight from one animation to another, say from walking to running, can look
+//! jarring. A [`BlendedAnimationController`] instead plays both animations for a while and
+//! flickers between them, showing the new animation on more and more frames until it has taken
+//! over completely. At 60 frames per second this dithers the two together in time, which looks
+//! a lot like a fade.
+//!
+//! Both animations work in raw tile indices, so all their frames need to already be in object
+//! tile memory.
+
+/// A looping sequence of tile indices, each shown for the same number of frames.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteAnimation {
+    frames: &'static [u16],
+    frame_duration: u8,
+    frame: u32,
+}
+
+impl SpriteAnimation {
+    /// Creates an animation which starts at the first of `frames` and moves on to the next one
+    /// every `frame_duration` frames. A `frame_duration` of 0 is treated as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames` is empty.
+    #[must_use]
+    pub const fn new(frames: &'static [u16], frame_duration: u8) -> Self {
+        assert!(!frames.is_empty(), "animation must have at least one frame");
+
+        Self {
+            frames,
+            frame_duration,
+            frame: 0,
+        }
+    }
+
+    /// The tile index for the current frame of the animation.
+    #[must_use]
+    pub fn tile_index(&self) -> u16 {
+        let duration = u32::from(self.frame_duration.max(1));
+        self.frames[(self.frame / duration) as usize % self.frames.len()]
+    }
+
+    /// Moves the animation on by one frame.
+    pub fn advance(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Goes back to the start of the animation.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+    }
+}
+
+/// Plays a [`SpriteAnimation`], cross-fading to a new one when asked to.
+///
+/// Call [`update`](BlendedAnimationController::update) once per frame and use the tile index
+/// it returns for your sprite.
+#[derive(Clone, Copy, Debug)]
+pub struct BlendedAnimationController {
+    current: SpriteAnimation,
+    target: Option<SpriteAnimation>,
+    blend: u8,
+    transition_frames: u8,
+    transition_elapsed: u8,
+    dither: u16,
+}
+
+impl BlendedAnimationController {
+    /// Creates a controller playing `animation`.
+    #[must_use]
+    pub const fn new(animation: SpriteAnimation) -> Self {
+        Self {
+            current: animation,
+            target: None,
+            blend: 0,
+            transition_frames: 0,
+            transition_elapsed: 0,
+            dither: 0,
+        }
+    }
+
+    /// Starts cross-fading to `new_anim`, which will be fully shown after `duration_frames`
+    /// frames. `new_anim` plays from wherever it currently is, so [`reset`](SpriteAnimation::reset)
+    /// it first if it should start from the beginning.
+    ///
+    /// If a cross-fade is already happening, whichever animation is currently shown more often
+    /// is kept and the other is dropped. A `duration_frames` of 0 switches immediately.
+    pub fn transition_to(&mut self, new_anim: SpriteAnimation, duration_frames: u8) {
+        if let Some(target) = self.target.take() {
+            if self.blend >= 128 {
+                self.current = target;
+            }
+        }
+
+        self.blend = 0;
+        self.transition_elapsed = 0;
+        self.transition_frames = duration_frames;
+
+        if duration_frames == 0 {
+            self.current = new_anim;
+        } else {
+            self.target = Some(new_anim);
+        }
+    }
+
+    /// How far through the current cross-fade the controller is, where 0 shows only the old
+    /// animation and 255 shows only the new one. This is 0 when not cross-fading.
+    #[must_use]
+    pub fn blend(&self) -> u8 {
+        self.blend
+    }
+
+    /// Whether a cross-fade is in progress.
+    #[must_use]
+    pub fn is_blending(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Advances the animations by a frame, returning the tile index to show this frame.
+    pub fn update(&mut self) -> u16 {
+        self.current.advance();
+
+        let Some(target) = &mut self.target else {
+            return self.current.tile_index();
+        };
+
+        target.advance();
+
+        self.transition_elapsed += 1;
+        if self.transition_elapsed >= self.transition_frames {
+            self.current = *target;
+            self.target = None;
+            self.blend = 0;
+            return self.current.tile_index();
+        }
+
+        self.blend =
+            (u32::from(self.transition_elapsed) * 255 / u32::from(self.transition_frames)) as u8;
+
+        // Error diffusion, so that the new animation is shown on blend / 256 of the frames,
+        // with 255 counting as every frame.
+        self.dither += u16::from(self.blend) + u16::from(self.blend >> 7);
+        if self.dither >= 256 {
+            self.dither -= 256;
+            target.tile_index()
+        } else {
+            self.current.tile_index()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static WALK: [u16; 2] = [0, 4];
+    static RUN: [u16; 2] = [8, 12];
+
+    #[test_case]
+    fn animation_cycles_through_frames(_gba: &mut crate::Gba) {
+        let mut animation = SpriteAnimation::new(&WALK, 2);
+
+        let mut tiles = [0; 5];
+        for tile in &mut tiles {
+            *tile = animation.tile_index();
+            animation.advance();
+        }
+
+        assert_eq!(tiles, [0, 0, 4, 4, 0]);
+    }
+
+    #[test_case]
+    fn cross_fade_moves_from_old_to_new(_gba: &mut crate::Gba) {
+        let mut controller = BlendedAnimationController::new(SpriteAnimation::new(&WALK, 1));
+        controller.transition_to(SpriteAnimation::new(&RUN, 1), 100);
+
+        let mut shown_new = [0; 4];
+        for quarter in &mut shown_new {
+            for _ in 0..25 {
+                if controller.update() >= 8 {
+                    *quarter += 1;
+                }
+            }
+        }
+
+        assert!(!controller.is_blending());
+        assert!(shown_new[0] < shown_new[1]);
+        assert!(shown_new[1] < shown_new[2]);
+        assert!(shown_new[2] < shown_new[3]);
+
+        assert!(controller.update() >= 8);
+
+        controller.transition_to(SpriteAnimation::new(&WALK, 1), 0);
+        assert!(controller.update() < 8);
+    }
+}
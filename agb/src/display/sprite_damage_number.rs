@@ -0,0 +1,366 @@
+//! Numbers which float up from characters and fade away, as RPGs show damage and healing.
+//!
+//! [`DamageNumbers`] draws each digit from a font of 8x8 glyphs into object tiles, with a
+//! dark outline so the numbers can be read over anything. The digits are drawn once for each
+//! [`DamageColor`] the first time it is used, and shared by every number shown in it. Each
+//! number shown is a [`DamageHandle`] with one object per digit, so between 1 and 5 objects.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{
+//!     display::sprite_damage_number::{DamageColor, DamageNumbers},
+//!     fixnum::Vector2D,
+//! };
+//!
+//! let oam = gba.display.object.get_managed();
+//! let mut damage_numbers = DamageNumbers::new();
+//!
+//! // when the player is hit
+//! let mut shown =
+//!     damage_numbers.spawn(125, Vector2D::new(120, 64), DamageColor::Damage, &oam);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     if shown.as_mut().is_some_and(|number| !number.update()) {
+//!         shown = None;
+//!     }
+//!
+//!     vblank.wait_for_vblank();
+//!     oam.commit();
+//! }
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::fixnum::Vector2D;
+
+use super::{
+    object::{DynamicSprite, OamManaged, Object, PaletteVram, Size, SpriteVram},
+    palette16::Palette16,
+};
+
+/// How many frames a number is shown for.
+pub const LIFETIME_FRAMES: u32 = 60;
+/// How many frames at the end of a number's life it flickers for as it fades out.
+const FADE_FRAMES: u32 = 20;
+/// How far a number floats up over its life, in pixels.
+const RISE: i32 = 20;
+/// The biggest number which can be shown, since numbers are at most 5 digits.
+pub const MAX_VALUE: u32 = 99_999;
+
+const FILL_COLOUR_INDEX: usize = 1;
+const OUTLINE_COLOUR_INDEX: usize = 2;
+
+/// The default font, with each row of a glyph as a byte whose highest bit is the leftmost
+/// pixel. The digits are 5 by 6 pixels, leaving room for the outline.
+const DEFAULT_FONT: [[u8; 8]; 10] = {
+    const DIGITS: [[u8; 6]; 10] = [
+        [0b01110, 0b10001, 0b10011, 0b11001, 0b10001, 0b01110],
+        [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+        [0b01110, 0b10001, 0b00010, 0b00100, 0b01000, 0b11111],
+        [0b11110, 0b00001, 0b00110, 0b00001, 0b00001, 0b11110],
+        [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010],
+        [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b11110],
+        [0b01110, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000],
+        [0b01110, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    ];
+
+    let mut font = [[0; 8]; 10];
+
+    let mut digit = 0;
+    while digit < 10 {
+        let mut row = 0;
+        while row < 6 {
+            // one pixel in from the top left
+            font[digit][row + 1] = DIGITS[digit][row] << 2;
+            row += 1;
+        }
+        digit += 1;
+    }
+
+    font
+};
+
+/// The colour of a damage number. The outline is always black.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageColor {
+    /// White, for ordinary hits.
+    Damage,
+    /// Yellow, for critical hits.
+    Critical,
+    /// Green, for healing.
+    Heal,
+    /// Any other colour, in the GBA's 15 bit format.
+    Custom(u16),
+}
+
+impl DamageColor {
+    fn colour(self) -> u16 {
+        match self {
+            DamageColor::Damage => 0x7fff,
+            DamageColor::Critical => 0x03ff,
+            DamageColor::Heal => 0x03e0,
+            DamageColor::Custom(colour) => colour,
+        }
+    }
+}
+
+/// Shows floating damage numbers, see the [module level documentation](self).
+pub struct DamageNumbers {
+    font: [[u8; 8]; 10],
+    advance: i32,
+    digits: Vec<(DamageColor, [SpriteVram; 10])>,
+}
+
+impl DamageNumbers {
+    /// Damage numbers in the built in font.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_font(DEFAULT_FONT, 6)
+    }
+
+    /// Damage numbers in a font of 8x8 glyphs for the digits 0 to 9, with each row of a glyph
+    /// as a byte whose highest bit is the leftmost pixel. The outline is drawn around the set
+    /// pixels, so leave a one pixel border. Digits are drawn `advance` pixels apart.
+    #[must_use]
+    pub fn with_font(font: [[u8; 8]; 10], advance: i32) -> Self {
+        Self {
+            font,
+            advance,
+            digits: Vec::new(),
+        }
+    }
+
+    /// Shows `value` in `colour` with the top of its digits centred on `position`, using an
+    /// object in `oam` for each digit.
+    ///
+    /// Returns `None` if `value` is more than [`MAX_VALUE`], or if the digits for a new colour
+    /// can't be drawn because object tile or palette memory is full.
+    pub fn spawn<'oam>(
+        &mut self,
+        value: u32,
+        position: Vector2D<i16>,
+        colour: DamageColor,
+        oam: &'oam OamManaged<'_>,
+    ) -> Option<DamageHandle<'oam>> {
+        if value > MAX_VALUE {
+            return None;
+        }
+
+        let advance = self.advance;
+        let digits = self.digits(colour)?;
+
+        let mut values = [0; 5];
+        let mut count = 0;
+        let mut remaining = value;
+        loop {
+            values[count] = (remaining % 10) as usize;
+            count += 1;
+            remaining /= 10;
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        let width = advance * (count as i32 - 1) + 8;
+        let origin = Vector2D::new(i32::from(position.x) - width / 2, i32::from(position.y));
+
+        let objects = values[..count]
+            .iter()
+            .rev()
+            .map(|&digit| {
+                let mut object = oam.object(digits[digit].clone());
+                object.show();
+                object
+            })
+            .collect();
+
+        let mut handle = DamageHandle {
+            objects,
+            origin,
+            advance,
+            frame: 0,
+        };
+        handle.place();
+
+        Some(handle)
+    }
+
+    fn digits(&mut self, colour: DamageColor) -> Option<&[SpriteVram; 10]> {
+        let index = match self
+            .digits
+            .iter()
+            .position(|(drawn_colour, _)| *drawn_colour == colour)
+        {
+            Some(index) => index,
+            None => {
+                let digits = self.draw_digits(colour)?;
+                self.digits.push((colour, digits));
+                self.digits.len() - 1
+            }
+        };
+
+        Some(&self.digits[index].1)
+    }
+
+    fn draw_digits(&self, colour: DamageColor) -> Option<[SpriteVram; 10]> {
+        let mut colours = [0; 16];
+        colours[FILL_COLOUR_INDEX] = colour.colour();
+        let palette = PaletteVram::new(&Palette16::new(colours)).ok()?;
+
+        let mut digits = Vec::with_capacity(10);
+        for glyph in &self.font {
+            let mut sprite = DynamicSprite::try_new(Size::S8x8).ok()?;
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    if is_set(glyph, x, y) {
+                        sprite.set_pixel(x as usize, y as usize, FILL_COLOUR_INDEX);
+                    } else if is_outline(glyph, x, y) {
+                        sprite.set_pixel(x as usize, y as usize, OUTLINE_COLOUR_INDEX);
+                    }
+                }
+            }
+
+            digits.push(sprite.to_vram(palette.clone()));
+        }
+
+        digits.try_into().ok()
+    }
+}
+
+impl Default for DamageNumbers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_set(glyph: &[u8; 8], x: i32, y: i32) -> bool {
+    (0..8).contains(&x) && (0..8).contains(&y) && glyph[y as usize] & (0x80 >> x) != 0
+}
+
+fn is_outline(glyph: &[u8; 8], x: i32, y: i32) -> bool {
+    (-1..=1).any(|dy| (-1..=1).any(|dx| is_set(glyph, x + dx, y + dy)))
+}
+
+/// A damage number being shown, made by [`DamageNumbers::spawn`]. Its objects are removed
+/// when it is dropped.
+pub struct DamageHandle<'oam> {
+    objects: Vec<Object<'oam>>,
+    origin: Vector2D<i32>,
+    advance: i32,
+    frame: u32,
+}
+
+impl DamageHandle<'_> {
+    /// Moves the number on by a frame, floating it up and making it flicker as it fades out
+    /// near the end. Returns `false` once it has finished, after [`LIFETIME_FRAMES`] frames,
+    /// when the number is hidden and can be dropped.
+    pub fn update(&mut self) -> bool {
+        if self.frame >= LIFETIME_FRAMES {
+            return false;
+        }
+
+        self.frame += 1;
+
+        if self.frame >= LIFETIME_FRAMES {
+            for object in &mut self.objects {
+                object.hide();
+            }
+            return false;
+        }
+
+        self.place();
+        true
+    }
+
+    /// How many objects the number uses, which is its number of digits.
+    #[must_use]
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    fn place(&mut self) {
+        // rises quickly at first and slows down, following t * (2 - t)
+        let t = self.frame as i32;
+        let lifetime = LIFETIME_FRAMES as i32;
+        let rise = RISE * t * (2 * lifetime - t) / (lifetime * lifetime);
+
+        let fading = self.frame + FADE_FRAMES >= LIFETIME_FRAMES;
+        let visible = !fading || self.frame.is_multiple_of(2);
+
+        for (index, object) in self.objects.iter_mut().enumerate() {
+            object.set_position(self.origin + Vector2D::new(index as i32 * self.advance, -rise));
+
+            if visible {
+                object.show();
+            } else {
+                object.hide();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn spawns_and_floats_away(gba: &mut crate::Gba) {
+        let oam = gba.display.object.get_managed();
+        let mut damage_numbers = DamageNumbers::new();
+
+        assert!(damage_numbers
+            .spawn(
+                MAX_VALUE + 1,
+                Vector2D::new(0, 0),
+                DamageColor::Damage,
+                &oam
+            )
+            .is_none());
+
+        let zero = damage_numbers
+            .spawn(0, Vector2D::new(50, 50), DamageColor::Heal, &oam)
+            .unwrap();
+        assert_eq!(zero.object_count(), 1);
+        assert_eq!(zero.objects[0].position(), Vector2D::new(46, 50));
+
+        let mut number = damage_numbers
+            .spawn(1234, Vector2D::new(100, 80), DamageColor::Damage, &oam)
+            .unwrap();
+        assert_eq!(number.object_count(), 4);
+        // 4 digits 6 pixels apart are 26 pixels wide
+        assert_eq!(number.objects[0].position(), Vector2D::new(87, 80));
+        assert_eq!(number.objects[3].position(), Vector2D::new(105, 80));
+
+        // the two colours are drawn once each
+        assert_eq!(damage_numbers.digits.len(), 2);
+
+        let mut frames = 0;
+        while number.update() {
+            frames += 1;
+            assert!(number.objects[0].position().y <= 80);
+        }
+        assert_eq!(frames, LIFETIME_FRAMES - 1);
+        assert!(!number.objects[0].is_visible());
+        assert!(!number.update());
+
+        oam.commit();
+    }
+
+    #[test_case]
+    fn outlines_the_glyphs(_gba: &mut crate::Gba) {
+        let one = &DEFAULT_FONT[1];
+
+        // the top of the stem of the 1
+        assert!(is_set(one, 3, 1));
+        assert!(is_outline(one, 3, 0));
+        assert!(!is_set(one, 3, 0));
+        assert!(!is_outline(one, 0, 7));
+    }
+}
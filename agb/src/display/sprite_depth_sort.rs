@@ -0,0 +1,135 @@
+//! Depth sorting for unmanaged objects.
+//!
+//! The GBA has no depth buffer for sprites. When two objects overlap, the one in the lower
+//! numbered OAM slot is drawn on top (assuming they share the same
+//! [`Priority`](super::Priority)). [`ZSortedSpriteList`] keeps objects sorted by a `z` value as
+//! they are added so that they can be written to OAM in the correct order.
+//!
+//! If you are using [`OamManaged`](super::object::OamManaged), it already supports setting a
+//! `z` coordinate on each object and you don't need this.
+
+use crate::collections::ArrayVec;
+
+use super::object::{OamIterator, ObjectUnmanaged};
+
+/// Which way round objects should be sorted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ZOrder {
+    /// Lower `z` values are closer to the viewer and are drawn on top.
+    #[default]
+    Ascending,
+    /// Higher `z` values are closer to the viewer and are drawn on top.
+    Descending,
+}
+
+/// A list of up to `N` objects kept sorted by depth.
+///
+/// Objects with the same `z` keep the order they were pushed in.
+pub struct ZSortedSpriteList<const N: usize> {
+    entries: ArrayVec<(i16, ObjectUnmanaged), N>,
+    order: ZOrder,
+}
+
+impl<const N: usize> ZSortedSpriteList<N> {
+    /// Creates an empty list which sorts in the given order.
+    #[must_use]
+    pub fn new(order: ZOrder) -> Self {
+        Self {
+            entries: ArrayVec::new(),
+            order,
+        }
+    }
+
+    /// Inserts `object` into the list at the position given by `z`. Returns `false` without
+    /// adding the object if the list is already full.
+    pub fn push(&mut self, object: ObjectUnmanaged, z: i16) -> bool {
+        if self.entries.is_full() {
+            return false;
+        }
+
+        let position = match self.order {
+            ZOrder::Ascending => self.entries.partition_point(|&(other, _)| other <= z),
+            ZOrder::Descending => self.entries.partition_point(|&(other, _)| other >= z),
+        };
+
+        self.entries.insert(position, (z, object));
+        true
+    }
+
+    /// Writes every object to OAM, the closest first.
+    pub fn commit(&self, oam: &mut OamIterator<'_>) {
+        for (_, object) in &self.entries {
+            oam.set_next(object);
+        }
+    }
+
+    /// Removes every object from the list, ready for the next frame.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterates over the objects and their depths in the order they will be drawn.
+    pub fn iter(&self) -> impl Iterator<Item = (i16, &ObjectUnmanaged)> {
+        self.entries.iter().map(|(z, object)| (*z, object))
+    }
+
+    /// The number of objects in the list.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the list contains no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The order objects are sorted in.
+    #[must_use]
+    pub fn order(&self) -> ZOrder {
+        self.order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        display::object::{Graphics, Tag},
+        include_aseprite,
+    };
+
+    use super::*;
+
+    #[test_case]
+    fn sorts_by_depth(gba: &mut crate::Gba) {
+        static GRAPHICS: &Graphics = include_aseprite!(
+            "../examples/the-purple-night/gfx/objects.aseprite",
+            "../examples/the-purple-night/gfx/boss.aseprite"
+        );
+
+        static BOSS: &Tag = GRAPHICS.tags().get("Boss");
+
+        let (mut oam, mut loader) = gba.display.object.get_unmanaged();
+        let object = ObjectUnmanaged::new(loader.get_vram_sprite(BOSS.sprite(0)));
+
+        for (order, expected) in [
+            (ZOrder::Ascending, [-4, 1, 3, 3]),
+            (ZOrder::Descending, [3, 3, 1, -4]),
+        ] {
+            let mut list = ZSortedSpriteList::<4>::new(order);
+
+            for z in [3, -4, 3, 1] {
+                assert!(list.push(object.clone(), z));
+            }
+            assert!(!list.push(object.clone(), 0), "list should be full");
+
+            let depths: Vec<_> = list.iter().map(|(z, _)| z).collect();
+            assert_eq!(depths, expected);
+
+            list.commit(&mut oam.iter());
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Flashing sprites white for a few frames, such as when a character takes damage.
+//!
+//! A [`SpriteFlashManager`] flashes a sprite by overwriting its palette with white, and puts the
+//! original colours back once the flash is over. The sprite's tiles and object aren't touched,
+//! so nothing else about it has to change. Colour 0 is left alone, as it is transparent.
+//!
+//! Every object drawn with the flashed palette flashes, so a sprite which should flash on its
+//! own needs a palette to itself, such as one made with [`PaletteVram::new`]. Up to
+//! [`MAX_FLASHES`] palettes can flash at once.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, player_palette: &agb::display::object::PaletteVram) {
+//! use agb::display::sprite_flash_manager::SpriteFlashManager;
+//!
+//! let mut flashes = SpriteFlashManager::new();
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! // the player has been hit
+//! flashes.trigger(player_palette, 8);
+//!
+//! loop {
+//!     vblank.wait_for_vblank();
+//!     flashes.update();
+//! }
+//! # }
+//! ```
+
+use super::object::{PaletteVram, OBJECT_PALETTE};
+
+/// How many palettes can flash at once.
+pub const MAX_FLASHES: usize = 4;
+
+const WHITE: u16 = 0x7fff;
+
+struct Flash {
+    palette: PaletteVram,
+    original: [u16; 16],
+    frames_left: u8,
+}
+
+impl Flash {
+    fn restore(&self) {
+        let bank = self.palette.bank();
+        for (index, &colour) in self.original.iter().enumerate() {
+            OBJECT_PALETTE.set(bank * 16 + index, colour);
+        }
+    }
+}
+
+/// Palettes which are flashing white, see the [module level documentation](self).
+pub struct SpriteFlashManager {
+    flashes: [Option<Flash>; MAX_FLASHES],
+}
+
+impl SpriteFlashManager {
+    /// Creates a manager with nothing flashing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            flashes: [const { None }; MAX_FLASHES],
+        }
+    }
+
+    /// Turns `palette` white for `duration_frames` calls to
+    /// [`update`](SpriteFlashManager::update). If it is already flashing, the flash is made to
+    /// last `duration_frames` from now instead. Returns `false` without flashing anything if
+    /// [`MAX_FLASHES`] other palettes are already flashing or the duration is 0.
+    pub fn trigger(&mut self, palette: &PaletteVram, duration_frames: u8) -> bool {
+        if duration_frames == 0 {
+            return false;
+        }
+
+        let bank = palette.bank();
+        if let Some(flash) = self
+            .flashes
+            .iter_mut()
+            .flatten()
+            .find(|flash| flash.palette.bank() == bank)
+        {
+            flash.frames_left = duration_frames;
+            return true;
+        }
+
+        let Some(slot) = self.flashes.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+
+        let original = core::array::from_fn(|index| OBJECT_PALETTE.get(bank * 16 + index));
+        for index in 1..16 {
+            OBJECT_PALETTE.set(bank * 16 + index, WHITE);
+        }
+
+        *slot = Some(Flash {
+            // holding on to the palette stops its bank being reused before it is restored
+            palette: palette.clone(),
+            original,
+            frames_left: duration_frames,
+        });
+
+        true
+    }
+
+    /// Counts every flash down by a frame, putting back the colours of any which have finished.
+    /// Call this once a frame.
+    pub fn update(&mut self) {
+        for slot in &mut self.flashes {
+            let Some(flash) = slot else {
+                continue;
+            };
+
+            flash.frames_left -= 1;
+            if flash.frames_left == 0 {
+                flash.restore();
+                *slot = None;
+            }
+        }
+    }
+
+    /// Whether `palette` is flashing.
+    #[must_use]
+    pub fn is_flashing(&self, palette: &PaletteVram) -> bool {
+        self.flashes
+            .iter()
+            .flatten()
+            .any(|flash| flash.palette.bank() == palette.bank())
+    }
+
+    /// Stops every flash straight away, putting back the original colours.
+    pub fn clear(&mut self) {
+        for slot in &mut self.flashes {
+            if let Some(flash) = slot.take() {
+                flash.restore();
+            }
+        }
+    }
+}
+
+impl Default for SpriteFlashManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SpriteFlashManager {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::palette16::Palette16;
+
+    use super::*;
+
+    #[test_case]
+    fn flashes_white_then_restores(_gba: &mut crate::Gba) {
+        let palette = PaletteVram::new(&Palette16::new([0x1234; 16])).unwrap();
+        let colour = |index: usize| OBJECT_PALETTE.get(palette.bank() * 16 + index);
+
+        let mut flashes = SpriteFlashManager::new();
+        assert!(flashes.trigger(&palette, 2));
+        assert_eq!(colour(0), 0x1234);
+        assert_eq!(colour(5), WHITE);
+
+        flashes.update();
+        assert!(flashes.is_flashing(&palette));
+        // triggering again just makes it last longer
+        assert!(flashes.trigger(&palette, 2));
+
+        flashes.update();
+        assert_eq!(colour(5), WHITE);
+        flashes.update();
+        assert!(!flashes.is_flashing(&palette));
+        assert_eq!(colour(5), 0x1234);
+
+        assert!(!flashes.trigger(&palette, 0));
+    }
+}
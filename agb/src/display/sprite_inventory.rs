@@ -0,0 +1,236 @@
+//! A scrollable grid of item icons, as seen on RPG inventory screens.
+//!
+//! [`InventoryGrid`] keeps one object per slot and works out where each one goes on screen from
+//! the slot it is in and how far the grid has been scrolled. Items are given as a
+//! [`SpriteVram`] rather than a raw tile index and palette, since that is how objects refer to
+//! their graphics, and keeping hold of it means the tiles can't be freed while they are shown.
+
+use alloc::vec::Vec;
+
+use crate::fixnum::Vector2D;
+
+use super::object::{OamIterator, ObjectUnmanaged, SpriteVram};
+
+/// An inventory of `COLS` by `ROWS` slots, of which some number of rows are visible at once.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # use agb::display::object::{SpriteVram, OamUnmanaged};
+/// # fn foo(oam: &mut OamUnmanaged, potion: SpriteVram, cursor: SpriteVram) {
+/// use agb::display::sprite_inventory::InventoryGrid;
+/// use agb::fixnum::Vector2D;
+///
+/// // 6 columns of 20 rows of 16x16 icons, showing 4 rows at a time
+/// let mut inventory = InventoryGrid::<6, 20>::new(Vector2D::new(40, 32), Vector2D::new(20, 20), 4);
+/// inventory.set_cursor_sprite(cursor);
+///
+/// inventory.set_item(0, 0, Some(potion));
+/// inventory.set_cursor(0, 0);
+///
+/// inventory.commit(&mut oam.iter());
+/// # }
+/// ```
+pub struct InventoryGrid<const COLS: usize, const ROWS: usize> {
+    items: Vec<Option<ObjectUnmanaged>>,
+    cursor_object: Option<ObjectUnmanaged>,
+    cursor: (usize, usize),
+    first_row: usize,
+    visible_rows: usize,
+    origin: Vector2D<i32>,
+    cell_size: Vector2D<i32>,
+}
+
+impl<const COLS: usize, const ROWS: usize> InventoryGrid<COLS, ROWS> {
+    /// Creates an empty grid whose top left slot is drawn at `origin`, with slots `cell_size`
+    /// pixels apart and `visible_rows` rows shown at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `visible_rows` is zero.
+    #[must_use]
+    pub fn new(origin: Vector2D<i32>, cell_size: Vector2D<i32>, visible_rows: usize) -> Self {
+        assert!(visible_rows > 0, "at least one row must be visible");
+
+        let mut items = Vec::with_capacity(COLS * ROWS);
+        items.resize_with(COLS * ROWS, || None);
+
+        Self {
+            items,
+            cursor_object: None,
+            cursor: (0, 0),
+            first_row: 0,
+            visible_rows: visible_rows.min(ROWS),
+            origin,
+            cell_size,
+        }
+    }
+
+    fn index(col: usize, row: usize) -> usize {
+        assert!(
+            col < COLS && row < ROWS,
+            "slot ({col}, {row}) is outside the {COLS}x{ROWS} grid"
+        );
+
+        row * COLS + col
+    }
+
+    /// Puts `sprite` in the slot at (`col`, `row`), or empties the slot if it is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is outside the grid.
+    pub fn set_item(&mut self, col: usize, row: usize, sprite: Option<SpriteVram>) {
+        let index = Self::index(col, row);
+
+        self.items[index] = sprite.map(|sprite| {
+            let mut object = ObjectUnmanaged::new(sprite);
+            object.show();
+            object
+        });
+    }
+
+    /// Whether the slot at (`col`, `row`) has an item in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is outside the grid.
+    #[must_use]
+    pub fn has_item(&self, col: usize, row: usize) -> bool {
+        self.items[Self::index(col, row)].is_some()
+    }
+
+    /// Sets the sprite drawn over the selected slot. It is drawn on top of the item icons, so
+    /// it can be a frame around the slot with a transparent middle.
+    pub fn set_cursor_sprite(&mut self, sprite: SpriteVram) {
+        let mut object = ObjectUnmanaged::new(sprite);
+        object.show();
+
+        self.cursor_object = Some(object);
+    }
+
+    /// Moves the cursor to the slot at (`col`, `row`), scrolling just far enough for it to
+    /// be visible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot is outside the grid.
+    pub fn set_cursor(&mut self, col: usize, row: usize) {
+        Self::index(col, row);
+        self.cursor = (col, row);
+
+        if row < self.first_row {
+            self.scroll_to(row);
+        } else if row >= self.first_row + self.visible_rows {
+            self.scroll_to(row + 1 - self.visible_rows);
+        }
+    }
+
+    /// The slot the cursor is on.
+    #[must_use]
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// The slot the cursor is on, or `None` if there is no item in it.
+    #[must_use]
+    pub fn selected_item(&self) -> Option<(usize, usize)> {
+        let (col, row) = self.cursor;
+        self.has_item(col, row).then_some(self.cursor)
+    }
+
+    /// Scrolls the grid so that `first_row` is the top visible row. This is clamped so that
+    /// the last page doesn't scroll past the bottom of the grid.
+    pub fn scroll_to(&mut self, first_row: usize) {
+        self.first_row = first_row.min(ROWS - self.visible_rows);
+    }
+
+    /// The top visible row.
+    #[must_use]
+    pub fn first_row(&self) -> usize {
+        self.first_row
+    }
+
+    fn slot_position(&self, col: usize, row: usize) -> Option<Vector2D<i32>> {
+        let visible_row = row.checked_sub(self.first_row)?;
+        if visible_row >= self.visible_rows {
+            return None;
+        }
+
+        Some(
+            self.origin
+                + Vector2D::new(
+                    col as i32 * self.cell_size.x,
+                    visible_row as i32 * self.cell_size.y,
+                ),
+        )
+    }
+
+    /// Writes the cursor and then every visible item to OAM. Items in rows which are scrolled
+    /// out of view aren't written at all, so they don't use up any OAM slots.
+    pub fn commit(&mut self, oam: &mut OamIterator<'_>) {
+        let (col, row) = self.cursor;
+        if let Some(position) = self.slot_position(col, row) {
+            if let Some(cursor) = &mut self.cursor_object {
+                cursor.set_position(position);
+                oam.set_next(cursor);
+            }
+        }
+
+        for row in self.first_row..self.first_row + self.visible_rows {
+            for col in 0..COLS {
+                let Some(position) = self.slot_position(col, row) else {
+                    continue;
+                };
+
+                if let Some(item) = &mut self.items[row * COLS + col] {
+                    item.set_position(position);
+                    oam.set_next(item);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        display::object::{Graphics, Tag},
+        include_aseprite,
+    };
+
+    use super::*;
+
+    #[test_case]
+    fn cursor_scrolls_and_selects(gba: &mut crate::Gba) {
+        static GRAPHICS: &Graphics = include_aseprite!(
+            "../examples/the-purple-night/gfx/objects.aseprite",
+            "../examples/the-purple-night/gfx/boss.aseprite"
+        );
+
+        static BOSS: &Tag = GRAPHICS.tags().get("Boss");
+
+        let (mut oam, mut loader) = gba.display.object.get_unmanaged();
+
+        let mut inventory =
+            InventoryGrid::<4, 10>::new(Vector2D::new(8, 8), Vector2D::new(20, 20), 3);
+        inventory.set_cursor_sprite(loader.get_vram_sprite(BOSS.sprite(0)));
+        inventory.set_item(1, 6, Some(loader.get_vram_sprite(BOSS.sprite(1))));
+
+        assert_eq!(inventory.selected_item(), None);
+
+        inventory.set_cursor(1, 6);
+        assert_eq!(inventory.first_row(), 4);
+        assert_eq!(inventory.selected_item(), Some((1, 6)));
+        assert_eq!(inventory.slot_position(1, 6), Some(Vector2D::new(28, 48)));
+        assert_eq!(inventory.slot_position(1, 3), None);
+
+        inventory.set_cursor(0, 2);
+        assert_eq!(inventory.first_row(), 2);
+
+        inventory.scroll_to(100);
+        assert_eq!(inventory.first_row(), 7);
+
+        inventory.commit(&mut oam.iter());
+    }
+}
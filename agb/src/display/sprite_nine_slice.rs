@@ -0,0 +1,186 @@
+//! Panels made of sprites which can be any size, such as dialogue boxes and menus.
+//!
+//! A [`NineSlicePanel`] is drawn from nine pieces: the four corners are drawn once, the edges
+//! are repeated along the sides and the centre is repeated to fill the middle. That way the
+//! border looks the same however big the panel is. Drawing a panel makes an [`Object`] for
+//! each piece in an [`OamManaged`], and they are all removed again when the returned
+//! [`NineSliceHandle`] is dropped or [undrawn](NineSlicePanel::undraw).
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, pieces: [&'static agb::display::object::Sprite; 9]) {
+//! use agb::display::sprite_nine_slice::NineSlicePanel;
+//!
+//! let oam = gba.display.object.get_managed();
+//!
+//! // 8x8 sprites for the top left, top, top right, left, centre, right, bottom left, bottom
+//! // and bottom right of the panel
+//! let panel = NineSlicePanel::new(pieces.map(|sprite| oam.sprite(sprite)));
+//!
+//! let dialogue_box = panel.draw(8, 112, 224, 40, &oam);
+//! oam.commit();
+//!
+//! // later, once the dialogue has finished
+//! panel.undraw(dialogue_box);
+//! oam.commit();
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::fixnum::Vector2D;
+
+use super::object::{OamManaged, Object, SpriteVram};
+
+/// The nine pieces of a panel which can be drawn at any size, see the [module level
+/// documentation](self).
+pub struct NineSlicePanel {
+    pieces: [SpriteVram; 9],
+    piece_size: Vector2D<i32>,
+}
+
+impl NineSlicePanel {
+    /// A panel made of `pieces` in reading order: the top left corner, top edge, top right
+    /// corner, left edge, centre, right edge, bottom left corner, bottom edge and bottom right
+    /// corner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pieces aren't all the same size.
+    #[must_use]
+    pub fn new(pieces: [SpriteVram; 9]) -> Self {
+        let size = pieces[0].size();
+        assert!(
+            pieces.iter().all(|piece| piece.size() == size),
+            "every piece of a nine slice panel must be the same size"
+        );
+
+        let (width, height) = size.to_width_height();
+
+        Self {
+            pieces,
+            piece_size: Vector2D::new(width as i32, height as i32),
+        }
+    }
+
+    /// Shows the panel with its top left corner at (`x`, `y`), making objects in `oam` for
+    /// each of the pieces. The size is rounded up to a whole number of pieces, and the panel is
+    /// at least two pieces wide and tall so that the corners don't overlap.
+    ///
+    /// Only the first 128 objects in `oam` can be shown, so big panels of small pieces may not
+    /// be completely visible. [`NineSliceHandle::len`] is how many objects the panel used.
+    pub fn draw<'oam>(
+        &self,
+        x: i16,
+        y: i16,
+        width: u16,
+        height: u16,
+        oam: &'oam OamManaged<'_>,
+    ) -> NineSliceHandle<'oam> {
+        let columns = Self::offsets(i32::from(width), self.piece_size.x);
+        let rows = Self::offsets(i32::from(height), self.piece_size.y);
+
+        let origin = Vector2D::new(i32::from(x), i32::from(y));
+        let mut objects = Vec::with_capacity(columns.len() * rows.len());
+
+        for (row_index, &row) in rows.iter().enumerate() {
+            let piece_row = Self::piece_index(row_index, rows.len());
+
+            for (column_index, &column) in columns.iter().enumerate() {
+                let piece = piece_row * 3 + Self::piece_index(column_index, columns.len());
+
+                let mut object = oam.object(self.pieces[piece].clone());
+                object
+                    .set_position(origin + Vector2D::new(column, row))
+                    .show();
+                objects.push(object);
+            }
+        }
+
+        NineSliceHandle { objects }
+    }
+
+    /// Removes a panel from `oam`, freeing its objects. This is the same as dropping the
+    /// handle.
+    pub fn undraw(&self, handle: NineSliceHandle<'_>) {
+        drop(handle);
+    }
+
+    /// Where each piece along a side of `length` pixels goes, relative to the start.
+    fn offsets(length: i32, piece_length: i32) -> Vec<i32> {
+        let pieces = ((length + piece_length - 1) / piece_length).max(2);
+        (0..pieces).map(|i| i * piece_length).collect()
+    }
+
+    /// Whether the piece at `index` out of `count` is the first, a middle one or the last.
+    fn piece_index(index: usize, count: usize) -> usize {
+        if index == 0 {
+            0
+        } else if index == count - 1 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// The objects making up a drawn [`NineSlicePanel`]. They are removed from the
+/// [`OamManaged`] when this is dropped.
+pub struct NineSliceHandle<'oam> {
+    objects: Vec<Object<'oam>>,
+}
+
+impl NineSliceHandle<'_> {
+    /// How many objects the panel is made of.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Always false, since a panel has at least its four corners.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        display::object::{Graphics, Tag},
+        include_aseprite,
+    };
+
+    #[test_case]
+    fn tiles_edges_and_centre(gba: &mut crate::Gba) {
+        static GRAPHICS: &Graphics = include_aseprite!(
+            "../examples/the-purple-night/gfx/objects.aseprite",
+            "../examples/the-purple-night/gfx/boss.aseprite"
+        );
+
+        static BOSS: &Tag = GRAPHICS.tags().get("Boss");
+
+        assert_eq!(NineSlicePanel::offsets(64, 16), [0, 16, 32, 48]);
+        assert_eq!(NineSlicePanel::offsets(40, 16), [0, 16, 32]);
+        assert_eq!(NineSlicePanel::offsets(4, 16), [0, 16]);
+
+        let oam = gba.display.object.get_managed();
+        let sprite = oam.sprite(BOSS.sprite(0));
+        let (width, height) = sprite.size().to_width_height();
+        let panel = NineSlicePanel::new(core::array::from_fn(|_| sprite.clone()));
+
+        let handle = panel.draw(10, 20, width as u16 * 3 + 1, height as u16 * 2, &oam);
+        assert_eq!(handle.len(), 4 * 2);
+        assert_eq!(handle.objects[0].position(), Vector2D::new(10, 20));
+        assert_eq!(
+            handle.objects[7].position(),
+            Vector2D::new(10 + width as i32 * 3, 20 + height as i32)
+        );
+        oam.commit();
+
+        panel.undraw(handle);
+        oam.commit();
+    }
+}
@@ -0,0 +1,227 @@
+//! Double buffering object tiles for glitch free animation.
+//!
+//! Changing the tiles of a sprite while the screen is being drawn can show half of the old
+//! frame and half of the new one. This is a problem for animations which write new tile data
+//! each frame, such as pre-rendered cutscenes, since a frame's worth of tiles often takes
+//! longer to copy than vblank lasts.
+//!
+//! An [`ObjTileDoubleBuffer`] has two copies of its tiles. Objects show the front copy while
+//! the next frame is written to the back copy with
+//! [`write_to_back`](ObjTileDoubleBuffer::write_to_back), and
+//! [`swap`](ObjTileDoubleBuffer::swap) then moves the objects over to it by changing their tile
+//! indices. Since objects only change when they are written to OAM, the switch happens all at
+//! once as long as that is done during vblank.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, frames: &[[[u32; 8]; 16]], sprite: agb::display::object::SpriteVram) {
+//! use agb::display::{object::ObjectUnmanaged, sprite_page_flip::ObjTileDoubleBuffer};
+//!
+//! let (mut oam, _) = gba.display.object.get_unmanaged();
+//! let mut buffer = ObjTileDoubleBuffer::<16>::new();
+//!
+//! // any 32x32 sprite with the right palette, the tiles come from the buffer
+//! let mut object = ObjectUnmanaged::new(sprite);
+//! buffer.show(&mut object, 0);
+//! object.show();
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! for frame in frames {
+//!     for (tile, data) in frame.iter().enumerate() {
+//!         buffer.write_to_back(tile, data);
+//!     }
+//!
+//!     buffer.swap(core::slice::from_mut(&mut object));
+//!     vblank.wait_for_vblank();
+//!     oam.iter().set_next(&object);
+//! }
+//! # }
+//! ```
+
+use super::{
+    obj_chr_block_manager::{ObjChrBlockManager, TileRange},
+    object::ObjectUnmanaged,
+};
+
+/// The number of `u32`s in one 4bpp tile.
+const WORDS_PER_TILE: usize = 8;
+
+/// Two copies of `TILES` 4bpp object tiles, one shown while the other is written. See the
+/// [module level documentation](self).
+///
+/// The tiles are allocated from the [`ObjChrBlockManager`] and given back when the buffer is
+/// dropped, so objects shouldn't use them after that.
+#[derive(Debug)]
+pub struct ObjTileDoubleBuffer<const TILES: usize> {
+    tiles: Option<TileRange>,
+    back_is_second: bool,
+}
+
+impl<const TILES: usize> ObjTileDoubleBuffer<TILES> {
+    /// Allocates `2 * TILES` consecutive object tiles, or returns [`None`] if there isn't room.
+    /// The tiles start off with whatever was in VRAM before.
+    #[must_use]
+    pub fn try_new() -> Option<Self> {
+        Some(Self {
+            tiles: Some(ObjChrBlockManager::get().alloc_contiguous(2 * TILES)?),
+            back_is_second: true,
+        })
+    }
+
+    /// Allocates `2 * TILES` consecutive object tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't room in object tile memory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::try_new().expect("not enough object tile memory for the double buffer")
+    }
+
+    fn range(&self) -> &TileRange {
+        self.tiles
+            .as_ref()
+            .expect("the tiles are only taken when the buffer is dropped")
+    }
+
+    fn offset(is_second: bool) -> u16 {
+        if is_second {
+            TILES as u16
+        } else {
+            0
+        }
+    }
+
+    /// The object tile index of tile `tile_idx` in the copy objects are showing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_idx` isn't less than `TILES`.
+    #[must_use]
+    pub fn front_tile_index(&self, tile_idx: usize) -> u16 {
+        assert!(tile_idx < TILES, "tile {tile_idx} is outside the buffer");
+        self.range().first() + Self::offset(!self.back_is_second) + tile_idx as u16
+    }
+
+    /// The object tile index of tile `tile_idx` in the copy being written to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_idx` isn't less than `TILES`.
+    #[must_use]
+    pub fn back_tile_index(&self, tile_idx: usize) -> u16 {
+        assert!(tile_idx < TILES, "tile {tile_idx} is outside the buffer");
+        self.range().first() + Self::offset(self.back_is_second) + tile_idx as u16
+    }
+
+    /// Makes `object` show the front copy of the buffer, starting from tile `tile_idx`. The
+    /// object keeps its size and palette, and uses as many tiles as its size needs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_idx` isn't less than `TILES`.
+    pub fn show(&self, object: &mut ObjectUnmanaged, tile_idx: usize) {
+        object.set_tile_index(self.front_tile_index(tile_idx));
+    }
+
+    /// Writes the 4bpp tile `data` to tile `tile_idx` of the copy which isn't being shown.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tile_idx` isn't less than `TILES`.
+    pub fn write_to_back(&mut self, tile_idx: usize, data: &[u32; WORDS_PER_TILE]) {
+        assert!(tile_idx < TILES, "tile {tile_idx} is outside the buffer");
+
+        let offset = usize::from(Self::offset(self.back_is_second)) + tile_idx;
+        let tile = self.range().as_ptr().cast::<u32>();
+
+        for (i, &word) in data.iter().enumerate() {
+            // SAFETY: the tile is inside the range allocated for this buffer
+            unsafe { tile.add(offset * WORDS_PER_TILE + i).write_volatile(word) };
+        }
+    }
+
+    /// Swaps the copies, so the one which has been written to is shown, and moves each of
+    /// `objects` which was showing the old front copy over to the same tile of the new one.
+    /// Objects which aren't showing this buffer are left alone.
+    ///
+    /// The objects need writing to OAM during vblank for the change to happen between frames.
+    pub fn swap(&mut self, objects: &mut [ObjectUnmanaged]) {
+        let old_front = self.front_tile_index(0);
+        let new_front = self.back_tile_index(0);
+
+        for object in objects {
+            let (tile_index, _, _) = object.tile_layout();
+            let Some(tile_idx) = (tile_index as u16).checked_sub(old_front) else {
+                continue;
+            };
+
+            if usize::from(tile_idx) < TILES {
+                object.set_tile_index(new_front + tile_idx);
+            }
+        }
+
+        self.back_is_second = !self.back_is_second;
+    }
+}
+
+impl<const TILES: usize> Default for ObjTileDoubleBuffer<TILES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const TILES: usize> Drop for ObjTileDoubleBuffer<TILES> {
+    fn drop(&mut self) {
+        if let Some(tiles) = self.tiles.take() {
+            ObjChrBlockManager::get().free(tiles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{
+        object::{DynamicSprite, PaletteVram, Size},
+        palette16::Palette16,
+    };
+
+    #[test_case]
+    fn swap_moves_objects_to_the_written_copy(_gba: &mut crate::Gba) {
+        let free_tiles = ObjChrBlockManager::get().free_tiles();
+
+        {
+            let mut buffer = ObjTileDoubleBuffer::<4>::new();
+            let first = buffer.front_tile_index(0);
+            assert_eq!(buffer.back_tile_index(1), first + 5);
+            assert_eq!(ObjChrBlockManager::get().free_tiles(), free_tiles - 8);
+
+            let palette = PaletteVram::new(&Palette16::new([0xffff; 16])).unwrap();
+            let sprite = DynamicSprite::new(Size::S8x8).to_vram(palette);
+            let mut in_buffer = ObjectUnmanaged::new(sprite.clone());
+            let elsewhere = ObjectUnmanaged::new(sprite);
+            buffer.show(&mut in_buffer, 2);
+
+            buffer.write_to_back(2, &[0x1234_5678; 8]);
+            let written = buffer.back_tile_index(2);
+
+            let mut objects = [in_buffer, elsewhere.clone()];
+            buffer.swap(&mut objects);
+
+            assert_eq!(objects[0].tile_layout().0, usize::from(written));
+            assert_eq!(objects[1].tile_layout(), elsewhere.tile_layout());
+            assert_eq!(buffer.front_tile_index(2), written);
+
+            let written_ptr = (0x0601_0000 + usize::from(written) * 32) as *const u32;
+            assert_eq!(unsafe { written_ptr.add(7).read_volatile() }, 0x1234_5678);
+
+            // swapping back leaves the object where it started
+            buffer.swap(&mut objects);
+            assert_eq!(objects[0].tile_layout().0, usize::from(first + 2));
+        }
+
+        assert_eq!(ObjChrBlockManager::get().free_tiles(), free_tiles);
+    }
+}
@@ -0,0 +1,251 @@
+//! Estimating how close each scanline is to the limit on how many sprites can be drawn.
+//!
+//! The GBA draws sprites one scanline at a time, and only has a fixed amount of time to do so.
+//! Drawing a sprite takes 1 cycle for each pixel of its width, or 10 cycles plus 2 for each
+//! pixel of its width for affine sprites, and there are 1210 cycles available for each line.
+//! This is spent even on pixels which are transparent or off the side of the screen. Once a
+//! line runs out of time, the sprites later in OAM aren't drawn on it, which usually shows as
+//! flickering or sprites with slices missing.
+//!
+//! [`RenderBudget`] adds up both the number of sprites and the number of cycles on each line,
+//! so you can spot the lines that are in trouble and, for example, move enemies apart or take
+//! turns drawing some of them.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # extern crate alloc;
+//! # fn foo(objects: &[agb::display::object::ObjectUnmanaged]) {
+//! use agb::display::sprite_render_budget::{OamEntry, RenderBudget};
+//!
+//! let entries: alloc::vec::Vec<_> = objects.iter().filter_map(OamEntry::from_object).collect();
+//! let budget = RenderBudget::compute(&entries);
+//!
+//! for line in budget.find_scanlines_over_cycle_budget() {
+//!     agb::println!("sprites will be cut off on line {line}");
+//! }
+//! # }
+//! ```
+
+use super::{
+    object::{AffineMode, ObjectUnmanaged, Size},
+    HEIGHT,
+};
+
+const SCANLINES: usize = HEIGHT as usize;
+
+/// The number of cycles available for drawing sprites on each scanline.
+pub const CYCLES_PER_SCANLINE: u16 = 1210;
+
+/// The number of sprites in OAM.
+const MAX_SPRITES: u16 = 128;
+
+/// The parts of a sprite which affect how long it takes to draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OamEntry {
+    y: u8,
+    size: Size,
+    affine: Option<AffineMode>,
+}
+
+impl OamEntry {
+    /// A normal sprite of the given `size` whose top is on line `y`. Sprites whose bottom goes
+    /// past line 255 wrap around to the top of the screen, the same as on the hardware.
+    #[must_use]
+    pub const fn new(y: u8, size: Size) -> Self {
+        Self {
+            y,
+            size,
+            affine: None,
+        }
+    }
+
+    /// Makes the sprite an affine one. [`AffineMode::AffineDouble`] sprites cover twice the
+    /// width and height of their size.
+    #[must_use]
+    pub const fn affine(self, mode: AffineMode) -> Self {
+        Self {
+            affine: Some(mode),
+            ..self
+        }
+    }
+
+    /// The entry for an object, or [`None`] if it is hidden.
+    #[must_use]
+    pub fn from_object(object: &ObjectUnmanaged) -> Option<Self> {
+        if !object.is_visible() {
+            return None;
+        }
+
+        let (_, width, height) = object.tile_layout();
+        let entry = Self::new(
+            object.y() as u8,
+            Size::from_width_height(width * 8, height * 8),
+        );
+
+        Some(match object.affine_mode() {
+            Some(mode) => entry.affine(mode),
+            None => entry,
+        })
+    }
+
+    /// The width and height the sprite covers on screen, in pixels.
+    const fn bounds(self) -> (u16, u16) {
+        let (width, height) = self.size.to_width_height();
+        let scale = match self.affine {
+            Some(AffineMode::AffineDouble) => 2,
+            _ => 1,
+        };
+
+        (width as u16 * scale, height as u16 * scale)
+    }
+
+    /// The cycles needed to draw the sprite on each line it covers.
+    const fn cycles(self) -> u16 {
+        let (width, _) = self.bounds();
+        match self.affine {
+            Some(_) => 10 + 2 * width,
+            None => width,
+        }
+    }
+}
+
+/// The number of sprites and the cycles needed to draw them on each of the 160 visible
+/// scanlines. See the [module level documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderBudget {
+    sprites: [u8; SCANLINES],
+    cycles: [u16; SCANLINES],
+}
+
+impl RenderBudget {
+    /// Adds up the sprites covering each scanline.
+    #[must_use]
+    pub fn compute(entries: &[OamEntry]) -> Self {
+        let mut budget = Self {
+            sprites: [0; SCANLINES],
+            cycles: [0; SCANLINES],
+        };
+
+        for entry in entries {
+            let (_, height) = entry.bounds();
+            let cycles = entry.cycles();
+
+            for offset in 0..height {
+                let line = usize::from((u16::from(entry.y) + offset) % 256);
+                if line < SCANLINES {
+                    budget.sprites[line] = budget.sprites[line].saturating_add(1);
+                    budget.cycles[line] = budget.cycles[line].saturating_add(cycles);
+                }
+            }
+        }
+
+        budget
+    }
+
+    /// The number of sprites covering each scanline.
+    #[must_use]
+    pub fn sprites_per_scanline(&self) -> &[u8; SCANLINES] {
+        &self.sprites
+    }
+
+    /// The number of cycles needed to draw the sprites on each scanline, to compare with
+    /// [`CYCLES_PER_SCANLINE`].
+    #[must_use]
+    pub fn cycles_per_scanline(&self) -> &[u16; SCANLINES] {
+        &self.cycles
+    }
+
+    /// The scanlines with more than `limit` sprites on them.
+    pub fn find_overloaded_scanlines(&self, limit: u8) -> impl Iterator<Item = u8> + '_ {
+        (0..SCANLINES as u8).filter(move |&line| self.sprites[usize::from(line)] > limit)
+    }
+
+    /// The scanlines where there isn't enough time to draw every sprite, so some will be cut
+    /// off.
+    pub fn find_scanlines_over_cycle_budget(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..SCANLINES as u8)
+            .filter(move |&line| self.cycles[usize::from(line)] > CYCLES_PER_SCANLINE)
+    }
+
+    /// How many sprites of the same `size` and `affine` mode can share a scanline before
+    /// some of them aren't drawn.
+    ///
+    /// That is 128 for normal sprites up to 8 pixels wide, since there are only 128 sprites
+    /// anyway, but only 18 for normal 64 pixel wide sprites and 4 for double size affine 64
+    /// pixel wide sprites.
+    #[must_use]
+    pub const fn max_safe_sprites_per_scanline(size: Size, affine: Option<AffineMode>) -> u8 {
+        let entry = OamEntry { y: 0, size, affine };
+        let sprites = CYCLES_PER_SCANLINE / entry.cycles();
+
+        if sprites > MAX_SPRITES {
+            MAX_SPRITES as u8
+        } else {
+            sprites as u8
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test_case]
+    fn counts_sprites_on_each_line(_gba: &mut crate::Gba) {
+        let entries = [
+            OamEntry::new(10, Size::S16x16),
+            OamEntry::new(20, Size::S64x32),
+            OamEntry::new(24, Size::S32x32).affine(AffineMode::AffineDouble),
+            // wraps around to cover lines 0 to 7
+            OamEntry::new(248, Size::S8x16),
+        ];
+
+        let budget = RenderBudget::compute(&entries);
+        let sprites = budget.sprites_per_scanline();
+        let cycles = budget.cycles_per_scanline();
+
+        assert_eq!(&sprites[0..=10], &[1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 1]);
+        assert_eq!(sprites[25], 3);
+        assert_eq!(sprites[87], 1);
+        assert_eq!(sprites[88], 0);
+        assert_eq!(cycles[25], 16 + 64 + (10 + 2 * 64));
+        assert_eq!(cycles[0], 8);
+
+        assert_eq!(
+            budget.find_overloaded_scanlines(2).collect::<Vec<_>>(),
+            [24, 25]
+        );
+        assert_eq!(budget.find_scanlines_over_cycle_budget().count(), 0);
+
+        let crowded = [OamEntry::new(100, Size::S64x64); 19];
+        let budget = RenderBudget::compute(&crowded);
+        assert_eq!(
+            budget
+                .find_scanlines_over_cycle_budget()
+                .collect::<Vec<_>>(),
+            (100..160).collect::<Vec<_>>()
+        );
+    }
+
+    #[test_case]
+    fn max_safe_sprites_follows_the_cycle_budget(_gba: &mut crate::Gba) {
+        assert_eq!(
+            RenderBudget::max_safe_sprites_per_scanline(Size::S8x8, None),
+            128
+        );
+        assert_eq!(
+            RenderBudget::max_safe_sprites_per_scanline(Size::S64x64, None),
+            18
+        );
+        assert_eq!(
+            RenderBudget::max_safe_sprites_per_scanline(
+                Size::S64x64,
+                Some(AffineMode::AffineDouble)
+            ),
+            4
+        );
+    }
+}
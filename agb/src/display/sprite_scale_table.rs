@@ -0,0 +1,187 @@
+//! Looking up scaling matrices from a precomputed table.
+//!
+//! Making a sprite bigger or smaller needs an affine matrix with the reciprocal of the scale
+//! in it, and dividing is slow on the GBA. A [`ScaleTable`] works out `N` evenly spaced scales
+//! at compile time and stores their matrices in ROM, so zooming a sprite, such as a boss which
+//! appears large and then shrinks down, is just a lookup each frame.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::{display::sprite_scale_table::ScaleTable, fixnum::Num};
+//!
+//! // from half size up to 4 times as big, in steps of an eighth. The scales are in 24.8 fixed
+//! // point, written as raw values since the table is built at compile time
+//! static ZOOM: ScaleTable<29> = ScaleTable::new(Num::from_raw(0x80), Num::from_raw(0x400));
+//!
+//! for step in (0..29).rev() {
+//!     // the sprite using affine matrix 3 shrinks a little each frame
+//!     ZOOM.write_to_oam_slot(3, step);
+//!     agb::interrupt::VBlank::get().wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use super::affine::AffineMatrix;
+use crate::fixnum::Num;
+
+const OBJECT_ATTRIBUTE_MEMORY: *mut u16 = 0x0700_0000 as *mut u16;
+const AFFINE_MATRIX_COUNT: usize = 32;
+
+/// Matrices which scale a sprite by `N` evenly spaced amounts, see the [module level
+/// documentation](self).
+pub struct ScaleTable<const N: usize> {
+    matrices: [AffineMatrix; N],
+}
+
+impl<const N: usize> ScaleTable<N> {
+    /// Works out the matrices for scales going evenly from `min_scale` for step 0 up to
+    /// `max_scale` for step `N - 1`. This is a `const fn` so that it can be stored in a
+    /// `static` and calculated at compile time.
+    ///
+    /// A scale of 2 draws the sprite twice as big. Remember that the sprite is cut off at the
+    /// edge of its bounding box unless it uses double size mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0, if `min_scale` isn't positive or if `max_scale` is less than
+    /// `min_scale`.
+    #[must_use]
+    pub const fn new(min_scale: Num<i32, 8>, max_scale: Num<i32, 8>) -> Self {
+        assert!(N > 0, "the table needs at least one entry");
+
+        let min = min_scale.to_raw();
+        let max = max_scale.to_raw();
+        assert!(min > 0, "the scale must be positive");
+        assert!(max >= min, "max_scale can't be less than min_scale");
+
+        let mut matrices = [AffineMatrix {
+            a: Num::from_raw(0),
+            b: Num::from_raw(0),
+            c: Num::from_raw(0),
+            d: Num::from_raw(0),
+            x: Num::from_raw(0),
+            y: Num::from_raw(0),
+        }; N];
+
+        let mut i = 0;
+        while i < N {
+            let scale = if N == 1 {
+                min
+            } else {
+                min + ((max - min) as i64 * i as i64 / (N as i64 - 1)) as i32
+            };
+
+            // the matrix maps from the screen to the sprite, so it holds 1 / scale, rounded
+            let inverse = ((1 << 16) + scale / 2) / scale;
+            let inverse = Num::from_raw(if inverse > 0 { inverse } else { 1 });
+
+            matrices[i] = AffineMatrix {
+                a: inverse,
+                b: Num::from_raw(0),
+                c: Num::from_raw(0),
+                d: inverse,
+                x: Num::from_raw(0),
+                y: Num::from_raw(0),
+            };
+            i += 1;
+        }
+
+        Self { matrices }
+    }
+
+    /// The matrix for `scale_step`, where step 0 is the smallest scale and step `N - 1` is the
+    /// largest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale_step` is `N` or more.
+    #[must_use]
+    pub fn get(&self, scale_step: usize) -> &AffineMatrix {
+        assert!(
+            scale_step < N,
+            "scale step {scale_step} is outside the table of {N} entries"
+        );
+
+        &self.matrices[scale_step]
+    }
+
+    /// Writes the matrix for `scale_step` to affine matrix `slot` in OAM, so that every
+    /// affine sprite using that matrix is drawn at that scale.
+    ///
+    /// The object managers in [`object`](super::object) assign affine matrices to slots
+    /// themselves and will overwrite this, so use it with sprites whose attributes you write
+    /// yourself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is 32 or more or if `scale_step` is `N` or more.
+    pub fn write_to_oam_slot(&self, slot: usize, scale_step: usize) {
+        assert!(
+            slot < AFFINE_MATRIX_COUNT,
+            "there are only {AFFINE_MATRIX_COUNT} affine matrices in OAM"
+        );
+
+        let components = self.get(scale_step).to_object_wrapping().components();
+
+        // the matrix components are in the last halfword of each of 4 consecutive objects
+        for (index, component) in components.into_iter().enumerate() {
+            // Safety: the slot is in range, so this is within OAM
+            unsafe {
+                OBJECT_ATTRIBUTE_MEMORY
+                    .add(slot * 16 + index * 4 + 3)
+                    .write_volatile(component);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixnum::{num, Vector2D};
+
+    use super::*;
+
+    // from 0.5 to 2.5
+    static TABLE: ScaleTable<5> = ScaleTable::new(Num::from_raw(0x80), Num::from_raw(0x280));
+
+    #[test_case]
+    fn matches_from_scale(_gba: &mut crate::Gba) {
+        for (step, scale) in [
+            (0, num!(0.5)),
+            (1, num!(1.)),
+            (2, num!(1.5)),
+            (4, num!(2.5)),
+        ] {
+            let inverse: Num<i32, 8> = Num::new(1) / scale;
+            let expected = AffineMatrix::from_scale(Vector2D::new(inverse, inverse));
+
+            for (expected, actual) in [
+                (expected.a, TABLE.get(step).a),
+                (expected.d, TABLE.get(step).d),
+            ] {
+                assert!(
+                    (expected - actual).abs() <= Num::from_raw(1),
+                    "step {step}: expected {expected:?} got {actual:?}"
+                );
+            }
+        }
+
+        assert_eq!(*TABLE.get(1), AffineMatrix::identity());
+    }
+
+    #[test_case]
+    fn writes_the_matrix_to_oam(_gba: &mut crate::Gba) {
+        TABLE.write_to_oam_slot(31, 0);
+
+        let read = |index: usize| unsafe {
+            OBJECT_ATTRIBUTE_MEMORY
+                .add(31 * 16 + index * 4 + 3)
+                .read_volatile()
+        };
+
+        // 1 / 0.5 = 2 in 8.8 fixed point
+        assert_eq!([read(0), read(1), read(2), read(3)], [0x200, 0, 0, 0x200]);
+    }
+}
@@ -0,0 +1,157 @@
+//! Letting sprites walk behind parts of a background, such as the tops of trees or walls.
+//!
+//! The GBA decides whether an object is drawn in front of or behind a background using their
+//! [`Priority`]: an object is drawn in front of any background with the same or a higher
+//! priority number. So if the foreground background has priority `P1`, an object with priority
+//! `P1` is drawn in front of it and one with priority `P2` is drawn behind it.
+//!
+//! [`SpriteOcclusionMap`] records which tiles of the map should hide sprites standing behind
+//! them, and picks the priority for each sprite based on where its feet are.
+
+use alloc::vec::Vec;
+
+use super::Priority;
+
+/// Which tiles in a `W` by `H` tile map hide sprites whose feet are on them.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(player: &mut agb::display::object::ObjectUnmanaged, x: i16, y: i16) {
+/// use agb::display::{sprite_shadow_map::SpriteOcclusionMap, Priority};
+///
+/// let mut occlusion = SpriteOcclusionMap::<32, 32>::new();
+/// // the top of a tree
+/// occlusion.set_occluding(10, 4, true);
+/// occlusion.set_occluding(10, 5, true);
+///
+/// // the trees are drawn on a background with priority P1
+/// player.set_priority(occlusion.sprite_priority(x, y, 16, Priority::P1));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpriteOcclusionMap<const W: usize, const H: usize> {
+    bits: Vec<u32>,
+}
+
+impl<const W: usize, const H: usize> SpriteOcclusionMap<W, H> {
+    /// Creates a map where no tiles are occluding.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            bits: alloc::vec![0; (W * H).div_ceil(32)],
+        }
+    }
+
+    fn index(tile_x: usize, tile_y: usize) -> usize {
+        assert!(
+            tile_x < W && tile_y < H,
+            "tile ({tile_x}, {tile_y}) is outside the {W}x{H} map"
+        );
+
+        tile_y * W + tile_x
+    }
+
+    /// Marks whether the tile at (`tile_x`, `tile_y`) hides sprites whose feet are on it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile is outside the map.
+    pub fn set_occluding(&mut self, tile_x: usize, tile_y: usize, occluding: bool) {
+        let index = Self::index(tile_x, tile_y);
+        let mask = 1 << (index % 32);
+
+        if occluding {
+            self.bits[index / 32] |= mask;
+        } else {
+            self.bits[index / 32] &= !mask;
+        }
+    }
+
+    /// Whether the tile at (`tile_x`, `tile_y`) hides sprites whose feet are on it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile is outside the map.
+    #[must_use]
+    pub fn is_occluding(&self, tile_x: usize, tile_y: usize) -> bool {
+        let index = Self::index(tile_x, tile_y);
+        self.bits[index / 32] & (1 << (index % 32)) != 0
+    }
+
+    /// Whether a sprite `sprite_h` pixels tall at (`sprite_x`, `sprite_y`) in map pixel
+    /// coordinates has its feet on an occluding tile. The feet are taken to be the bottom row
+    /// of the sprite at `sprite_x`, so pass the middle of the sprite rather than its left edge
+    /// if its feet are in the middle.
+    ///
+    /// Positions outside the map are never occluded.
+    #[must_use]
+    pub fn is_occluded_at(&self, sprite_x: i16, sprite_y: i16, sprite_h: u8) -> bool {
+        let feet_y = i32::from(sprite_y) + i32::from(sprite_h) - 1;
+        let tile_x = i32::from(sprite_x).div_euclid(8);
+        let tile_y = feet_y.div_euclid(8);
+
+        match (usize::try_from(tile_x), usize::try_from(tile_y)) {
+            (Ok(tile_x), Ok(tile_y)) if tile_x < W && tile_y < H => {
+                self.is_occluding(tile_x, tile_y)
+            }
+            _ => false,
+        }
+    }
+
+    /// The priority a sprite should have to be drawn behind the background with priority
+    /// `foreground` when its feet are on an occluding tile, and in front of it otherwise.
+    ///
+    /// A sprite can't be put behind a background with priority `P3`, so if `foreground` is
+    /// `P3` this always gives `P3`.
+    #[must_use]
+    pub fn sprite_priority(
+        &self,
+        sprite_x: i16,
+        sprite_y: i16,
+        sprite_h: u8,
+        foreground: Priority,
+    ) -> Priority {
+        if !self.is_occluded_at(sprite_x, sprite_y, sprite_h) {
+            return foreground;
+        }
+
+        match foreground {
+            Priority::P0 => Priority::P1,
+            Priority::P1 => Priority::P2,
+            Priority::P2 | Priority::P3 => Priority::P3,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for SpriteOcclusionMap<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn sprites_go_behind_occluding_tiles(_gba: &mut crate::Gba) {
+        let mut map = SpriteOcclusionMap::<40, 20>::new();
+        map.set_occluding(39, 19, true);
+        map.set_occluding(3, 2, true);
+        map.set_occluding(3, 2, false);
+        map.set_occluding(3, 3, true);
+
+        assert!(map.is_occluding(39, 19));
+        assert!(!map.is_occluding(3, 2));
+
+        // a 16 pixel tall sprite at y = 12 has its feet on row 27, which is in tile 3
+        assert!(map.is_occluded_at(24, 12, 16));
+        assert!(!map.is_occluded_at(24, 4, 16));
+        assert!(!map.is_occluded_at(-4, 12, 16));
+        assert!(!map.is_occluded_at(24, 200, 16));
+
+        assert_eq!(map.sprite_priority(24, 12, 16, Priority::P1), Priority::P2);
+        assert_eq!(map.sprite_priority(24, 4, 16, Priority::P1), Priority::P1);
+    }
+}
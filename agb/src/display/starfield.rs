@@ -0,0 +1,297 @@
+//! Backgrounds of stars which scroll at different speeds, giving a sense of depth in space
+//! games.
+//!
+//! A [`Starfield`] keeps track of where each of its stars is. Each star has a speed, so when the
+//! starfield is [scrolled](Starfield::scroll) near stars move further than distant ones, and
+//! stars which go off one edge of the screen come back on the other. The stars can then be shown
+//! in two ways:
+//!
+//! * as objects, by [showing](Starfield::show) them in an [`OamManaged`] and then moving the
+//!   objects each frame with [`StarfieldObjects::update`]. This works over any background, but
+//!   uses an object for each star.
+//! * in bitmap mode 3 with [`Starfield::draw`], which only redraws the pixels where stars were
+//!   and are rather than the whole screen.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{display::starfield::Starfield, rng::RandomNumberGenerator};
+//!
+//! let oam = gba.display.object.get_managed();
+//! let mut starfield = Starfield::<32>::new(&mut RandomNumberGenerator::new());
+//! let mut objects = starfield.show(&oam).expect("no room for the stars");
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     // the ship flies right, so the stars go left
+//!     starfield.scroll(-2, 0);
+//!     objects.update(&starfield);
+//!
+//!     vblank.wait_for_vblank();
+//!     oam.commit();
+//! }
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::{fixnum::Vector2D, rng::RandomNumberGenerator};
+
+use super::{
+    bitmap3::Bitmap3,
+    object::{DynamicSprite, OamManaged, Object, PaletteVram, Size, SpriteVram},
+    palette16::Palette16,
+    HEIGHT, WIDTH,
+};
+
+/// How many different brightnesses stars shown as objects can have. Each star is shown with
+/// the closest one to its brightness.
+pub const OBJECT_BRIGHTNESS_LEVELS: usize = 4;
+
+/// A star in a [`Starfield`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Star {
+    /// Where the star is on the screen.
+    pub position: Vector2D<i16>,
+    /// How far the star moves when the starfield is scrolled, in 256ths of the distance
+    /// scrolled. Distant stars should be slow.
+    pub speed: u8,
+    /// How bright the star is, from 0 for black to 255 for white.
+    pub brightness: u8,
+}
+
+impl Star {
+    /// The colour of the star in the GBA's 15 bit format.
+    #[must_use]
+    pub fn colour(&self) -> u16 {
+        grey(self.brightness)
+    }
+}
+
+fn grey(brightness: u8) -> u16 {
+    let level = u16::from(brightness >> 3);
+    level | (level << 5) | (level << 10)
+}
+
+/// `N` stars which scroll at their own speeds, see the [module level documentation](self).
+pub struct Starfield<const N: usize> {
+    stars: [Star; N],
+    /// How far each star has moved towards its next pixel, in 256ths of a pixel.
+    sub_pixel: [Vector2D<i16>; N],
+    /// Where the stars were last drawn with [`Starfield::draw`], so they can be cleared.
+    drawn: Option<[Vector2D<i16>; N]>,
+}
+
+impl<const N: usize> Starfield<N> {
+    /// Stars scattered randomly over the screen, with random speeds and brighter stars moving
+    /// faster as if they were nearer.
+    #[must_use]
+    pub fn new(rng: &mut RandomNumberGenerator) -> Self {
+        Self::from_stars(core::array::from_fn(|_| {
+            let x = rng.gen().rem_euclid(WIDTH) as i16;
+            let y = rng.gen().rem_euclid(HEIGHT) as i16;
+            let depth = rng.gen().rem_euclid(224) as u8;
+
+            Star {
+                position: Vector2D::new(x, y),
+                speed: depth + 32,
+                brightness: depth + 32,
+            }
+        }))
+    }
+
+    /// A starfield of the given `stars`.
+    #[must_use]
+    pub fn from_stars(stars: [Star; N]) -> Self {
+        Self {
+            stars,
+            sub_pixel: [Vector2D::new(0, 0); N],
+            drawn: None,
+        }
+    }
+
+    /// The stars and where they currently are.
+    #[must_use]
+    pub fn stars(&self) -> &[Star; N] {
+        &self.stars
+    }
+
+    /// Moves each star by its speed's fraction of (`dx`, `dy`), wrapping stars which go off the
+    /// screen round to the other side. Movements of less than a pixel build up over calls, so
+    /// slow stars still move when scrolled slowly.
+    pub fn scroll(&mut self, dx: i16, dy: i16) {
+        for (star, sub_pixel) in self.stars.iter_mut().zip(&mut self.sub_pixel) {
+            let speed = i32::from(star.speed);
+            let x = i32::from(sub_pixel.x) + i32::from(dx) * speed;
+            let y = i32::from(sub_pixel.y) + i32::from(dy) * speed;
+
+            *sub_pixel = Vector2D::new(x.rem_euclid(256) as i16, y.rem_euclid(256) as i16);
+            star.position = Vector2D::new(
+                (i32::from(star.position.x) + x.div_euclid(256)).rem_euclid(WIDTH) as i16,
+                (i32::from(star.position.y) + y.div_euclid(256)).rem_euclid(HEIGHT) as i16,
+            );
+        }
+    }
+
+    /// Draws the stars in bitmap mode 3, clearing the pixels where they were last drawn to
+    /// black. The rest of the screen isn't touched, so anything else drawn over the stars
+    /// should be drawn after this.
+    pub fn draw(&mut self, bitmap: &mut Bitmap3<'_>) {
+        if let Some(drawn) = &self.drawn {
+            for position in drawn {
+                bitmap.draw_point(position.x.into(), position.y.into(), 0);
+            }
+        }
+
+        for star in &self.stars {
+            bitmap.draw_point(
+                star.position.x.into(),
+                star.position.y.into(),
+                star.colour(),
+            );
+        }
+
+        self.drawn = Some(self.stars.map(|star| star.position));
+    }
+
+    /// Shows the stars as objects in `oam`, one for each star. Returns `None` if there isn't
+    /// room in object tile or palette memory for the star sprites.
+    pub fn show<'oam>(&self, oam: &'oam OamManaged<'_>) -> Option<StarfieldObjects<'oam>> {
+        let mut colours = [0; 16];
+        for (level, colour) in colours[1..=OBJECT_BRIGHTNESS_LEVELS].iter_mut().enumerate() {
+            *colour = grey(object_level_brightness(level));
+        }
+        let palette = PaletteVram::new(&Palette16::new(colours)).ok()?;
+
+        let mut sprites = Vec::with_capacity(OBJECT_BRIGHTNESS_LEVELS);
+        for level in 0..OBJECT_BRIGHTNESS_LEVELS {
+            let mut sprite = DynamicSprite::try_new(Size::S8x8).ok()?;
+            sprite.set_pixel(0, 0, level + 1);
+            sprites.push(sprite.to_vram(palette.clone()));
+        }
+
+        let mut objects = StarfieldObjects {
+            sprites: sprites.try_into().ok()?,
+            objects: Vec::with_capacity(N),
+            levels: Vec::with_capacity(N),
+        };
+
+        for star in &self.stars {
+            let level = object_level(star.brightness);
+            let mut object = oam.object(objects.sprites[level].clone());
+            object.show();
+
+            objects.objects.push(object);
+            objects.levels.push(level);
+        }
+
+        objects.update(self);
+        Some(objects)
+    }
+}
+
+/// Which of the object brightness levels is closest to `brightness`.
+fn object_level(brightness: u8) -> usize {
+    usize::from(brightness) * OBJECT_BRIGHTNESS_LEVELS / 256
+}
+
+/// The brightness stars shown as objects at `level` are drawn with, the middle of the range of
+/// brightnesses shown at that level.
+fn object_level_brightness(level: usize) -> u8 {
+    ((level * 2 + 1) * 128 / OBJECT_BRIGHTNESS_LEVELS) as u8
+}
+
+/// The objects showing a [`Starfield`], made by [`Starfield::show`]. They are removed from the
+/// [`OamManaged`] when this is dropped.
+pub struct StarfieldObjects<'oam> {
+    sprites: [SpriteVram; OBJECT_BRIGHTNESS_LEVELS],
+    objects: Vec<Object<'oam>>,
+    levels: Vec<usize>,
+}
+
+impl StarfieldObjects<'_> {
+    /// Moves the objects to where the stars in `starfield` now are, and changes their
+    /// brightness if it has changed. The starfield should be the one which made these objects.
+    pub fn update<const N: usize>(&mut self, starfield: &Starfield<N>) {
+        for ((object, shown_level), star) in self
+            .objects
+            .iter_mut()
+            .zip(&mut self.levels)
+            .zip(&starfield.stars)
+        {
+            let level = object_level(star.brightness);
+            if level != *shown_level {
+                object.set_sprite(self.sprites[level].clone());
+                *shown_level = level;
+            }
+
+            object.set_position(Vector2D::new(
+                star.position.x.into(),
+                star.position.y.into(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star(x: i16, y: i16, speed: u8) -> Star {
+        Star {
+            position: Vector2D::new(x, y),
+            speed,
+            brightness: 255,
+        }
+    }
+
+    #[test_case]
+    fn scrolls_at_each_stars_speed(gba: &mut crate::Gba) {
+        let mut starfield = Starfield::from_stars([star(10, 10, 128), star(0, 159, 64)]);
+
+        starfield.scroll(-4, 2);
+        assert_eq!(starfield.stars()[0].position, Vector2D::new(8, 11));
+        // wraps round both edges
+        assert_eq!(starfield.stars()[1].position, Vector2D::new(239, 159));
+
+        // half a pixel builds up to a whole one
+        starfield.scroll(0, 2);
+        assert_eq!(starfield.stars()[1].position, Vector2D::new(239, 0));
+
+        let mut bitmap = gba.display.video.bitmap3();
+        bitmap.draw_point(100, 100, 0x1234);
+        starfield.draw(&mut bitmap);
+        assert_eq!(bitmap.read_point(239, 0), 0x7fff);
+
+        // a whole pixel at this star's quarter speed
+        starfield.scroll(0, 4);
+        starfield.draw(&mut bitmap);
+        assert_eq!(bitmap.read_point(239, 0), 0);
+        assert_eq!(bitmap.read_point(239, 1), 0x7fff);
+        assert_eq!(bitmap.read_point(100, 100), 0x1234);
+
+        let random = Starfield::<16>::new(&mut RandomNumberGenerator::new());
+        assert!(random.stars().iter().all(|star| {
+            (0..WIDTH as i16).contains(&star.position.x)
+                && (0..HEIGHT as i16).contains(&star.position.y)
+        }));
+    }
+
+    #[test_case]
+    fn shows_stars_as_objects(gba: &mut crate::Gba) {
+        let oam = gba.display.object.get_managed();
+        let mut starfield = Starfield::from_stars([star(10, 10, 255), star(20, 30, 0)]);
+        starfield.stars[1].brightness = 0;
+
+        let mut objects = starfield.show(&oam).unwrap();
+        assert_eq!(objects.levels, [OBJECT_BRIGHTNESS_LEVELS - 1, 0]);
+
+        starfield.scroll(5, 0);
+        objects.update(&starfield);
+        assert_eq!(objects.objects[0].position(), Vector2D::new(14, 10));
+        assert_eq!(objects.objects[1].position(), Vector2D::new(20, 30));
+
+        oam.commit();
+    }
+}
@@ -0,0 +1,357 @@
+//! RPG style dialog boxes which reveal their text a few characters at a time.
+//!
+//! A [`DialogBox`] takes a background of its own and draws a panel on it from nine tiles: the
+//! four corners are drawn once, the edges are repeated along the sides and the centre tile
+//! fills the middle. [`show`](DialogBox::show) starts revealing some text inside the panel, and
+//! [`update`](DialogBox::update) should then be called once a frame to reveal the next few
+//! characters. Pressing A while the text is being revealed shows the rest of it straight away,
+//! and pressing A again once it is all there finishes the dialog.
+//!
+//! The text is drawn with [`Font`], so it doesn't wrap by itself. Put a `\n` wherever a line
+//! should break.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(
+//! #     gba: &mut agb::Gba,
+//! #     tileset: &agb::display::tiled::TileSet,
+//! #     border: [agb::display::tiled::TileSetting; 9],
+//! #     font: &agb::display::Font,
+//! # ) {
+//! use agb::{
+//!     display::text_dialog_box::{DialogBox, DialogState},
+//!     fixnum::{Rect, Vector2D},
+//!     input::ButtonController,
+//! };
+//!
+//! let (tiled, mut vram) = gba.display.video.tiled0();
+//! let vblank = agb::interrupt::VBlank::get();
+//! let mut input = ButtonController::new();
+//!
+//! // the bottom of the screen, 30 tiles wide and 6 tall
+//! let area = Rect::new(Vector2D::new(0, 14), Vector2D::new(30, 6));
+//! let mut dialog = DialogBox::new(&tiled, tileset, border, font, area);
+//!
+//! dialog.show_with_speaker(&mut vram, "Elder", "The cave to the north is\ndangerous. Be careful!");
+//!
+//! loop {
+//!     input.update();
+//!     let state = dialog.update(&input, &mut vram);
+//!
+//!     vblank.wait_for_vblank();
+//!     dialog.commit(&mut vram);
+//!
+//!     if state == DialogState::Done {
+//!         break;
+//!     }
+//! }
+//!
+//! dialog.hide(&mut vram);
+//! # }
+//! ```
+
+use alloc::string::String;
+
+use crate::{
+    fixnum::{Rect, Vector2D},
+    input::{Button, ButtonController},
+};
+
+use super::{
+    font::{Font, TextRenderer},
+    tiled::{
+        MapLoan, RegularBackgroundSize, RegularMap, TileFormat, TileSet, TileSetting, Tiled0,
+        TiledMap, VRamManager,
+    },
+    Priority,
+};
+
+/// How many characters are revealed each frame unless
+/// [`set_chars_per_frame`](DialogBox::set_chars_per_frame) is used.
+pub const DEFAULT_CHARS_PER_FRAME: u8 = 1;
+
+/// Where a [`DialogBox`] has got to with its text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DialogState {
+    /// Some of the text still has to be revealed.
+    Revealing,
+    /// All of the text is showing, and the dialog is waiting for A to be pressed.
+    WaitingForInput,
+    /// A was pressed once all the text was showing, or nothing has been shown yet.
+    Done,
+}
+
+/// A panel which reveals text a few characters at a time, see the
+/// [module level documentation](self).
+pub struct DialogBox<'gba, 'tiles, 'font> {
+    map: MapLoan<'gba, RegularMap>,
+    tileset: &'tiles TileSet<'tiles>,
+    border: [TileSetting; 9],
+    font: &'font Font,
+    area: Rect<u16>,
+
+    foreground_colour: u8,
+    background_colour: u8,
+    chars_per_frame: u8,
+
+    speaker: TextRenderer<'font>,
+    body: TextRenderer<'font>,
+    text: String,
+    revealed: usize,
+    state: DialogState,
+}
+
+impl<'gba, 'tiles, 'font> DialogBox<'gba, 'tiles, 'font> {
+    /// Creates a dialog box covering `area`, in tiles, on a new background from `tiled`.
+    ///
+    /// `border` gives the tiles in `tileset` for the panel in reading order: the top left
+    /// corner, top edge, top right corner, left edge, centre, right edge, bottom left corner,
+    /// bottom edge and bottom right corner. The centre tile should be filled with the text's
+    /// background colour, see [`set_text_colours`](DialogBox::set_text_colours), so that the
+    /// text blends in with it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `area` is smaller than 3x3 tiles or goes outside the 32x32 background.
+    #[must_use]
+    pub fn new(
+        tiled: &'gba Tiled0<'_>,
+        tileset: &'tiles TileSet<'tiles>,
+        border: [TileSetting; 9],
+        font: &'font Font,
+        area: Rect<u16>,
+    ) -> Self {
+        assert!(
+            area.size.x >= 3 && area.size.y >= 3,
+            "a dialog box must be at least 3x3 tiles to fit its border"
+        );
+        assert!(
+            area.position.x + area.size.x <= 32 && area.position.y + area.size.y <= 32,
+            "a dialog box must be inside the 32x32 background"
+        );
+
+        let map = tiled.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let text_pos = area.position + Vector2D::new(1, 1);
+
+        Self {
+            map,
+            tileset,
+            border,
+            font,
+            area,
+
+            foreground_colour: 1,
+            background_colour: 0,
+            chars_per_frame: DEFAULT_CHARS_PER_FRAME,
+
+            speaker: font.render_text(text_pos),
+            body: font.render_text(text_pos),
+            text: String::new(),
+            revealed: 0,
+            state: DialogState::Done,
+        }
+    }
+
+    /// Sets the palette indices of the text and the space around it, which are 1 and 0 unless
+    /// this is called. This applies from the next [`show`](DialogBox::show).
+    pub fn set_text_colours(&mut self, foreground_colour: u8, background_colour: u8) {
+        self.foreground_colour = foreground_colour;
+        self.background_colour = background_colour;
+    }
+
+    /// Sets how many characters [`update`](DialogBox::update) reveals each frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chars_per_frame` is 0, since the text would never be revealed.
+    pub fn set_chars_per_frame(&mut self, chars_per_frame: u8) {
+        assert!(chars_per_frame > 0, "a dialog box must reveal some text");
+        self.chars_per_frame = chars_per_frame;
+    }
+
+    /// Clears the panel and starts revealing `text` in it, without a speaker.
+    pub fn show(&mut self, vram: &mut VRamManager, text: &str) {
+        self.start(vram, None, text);
+    }
+
+    /// Clears the panel and starts revealing `text` in it, with `speaker` shown straight away
+    /// above it.
+    pub fn show_with_speaker(&mut self, vram: &mut VRamManager, speaker: &str, text: &str) {
+        self.start(vram, Some(speaker), text);
+    }
+
+    fn start(&mut self, vram: &mut VRamManager, speaker: Option<&str>, text: &str) {
+        self.clear_text(vram);
+        self.draw_panel(vram);
+
+        let mut body_pos = self.area.position + Vector2D::new(1, 1);
+
+        if let Some(speaker) = speaker {
+            for c in speaker.chars() {
+                self.speaker
+                    .write_char(c, vram, self.foreground_colour, self.background_colour);
+            }
+            self.speaker.commit(&mut self.map, vram);
+
+            let header_rows = (self.font.line_height() as u16).div_ceil(8);
+            body_pos.y += header_rows;
+        }
+
+        self.body = self.font.render_text(body_pos);
+        self.text.clear();
+        self.text.push_str(text);
+        self.revealed = 0;
+
+        self.state = if text.is_empty() {
+            DialogState::WaitingForInput
+        } else {
+            DialogState::Revealing
+        };
+    }
+
+    /// Reveals the next few characters, or moves on if A has just been pressed, and returns
+    /// where the dialog has got to. Call this once a frame after updating `input`.
+    pub fn update(&mut self, input: &ButtonController, vram: &mut VRamManager) -> DialogState {
+        let a_pressed = input.is_just_pressed(Button::A);
+
+        match self.state {
+            DialogState::Revealing if a_pressed => self.fast_forward(vram),
+            DialogState::Revealing => self.reveal(vram, usize::from(self.chars_per_frame)),
+            DialogState::WaitingForInput if a_pressed => self.state = DialogState::Done,
+            DialogState::WaitingForInput | DialogState::Done => {}
+        }
+
+        self.state
+    }
+
+    /// Reveals the rest of the text straight away.
+    pub fn fast_forward(&mut self, vram: &mut VRamManager) {
+        if self.state == DialogState::Revealing {
+            self.reveal(vram, usize::MAX);
+        }
+    }
+
+    fn reveal(&mut self, vram: &mut VRamManager, count: usize) {
+        for c in self.text[self.revealed..].chars().take(count) {
+            self.body
+                .write_char(c, vram, self.foreground_colour, self.background_colour);
+            self.revealed += c.len_utf8();
+        }
+
+        self.body.commit(&mut self.map, vram);
+
+        if self.revealed == self.text.len() {
+            self.state = DialogState::WaitingForInput;
+        }
+    }
+
+    /// Where the dialog has got to, as last returned by [`update`](DialogBox::update).
+    #[must_use]
+    pub fn state(&self) -> DialogState {
+        self.state
+    }
+
+    /// Commits any changes to the dialog box and ensures it is visible.
+    pub fn commit(&mut self, vram: &mut VRamManager) {
+        self.map.commit(vram);
+        self.map.set_visible(true);
+    }
+
+    /// Hides the dialog box and frees the tiles used by its text.
+    pub fn hide(&mut self, vram: &mut VRamManager) {
+        self.clear_text(vram);
+        self.text.clear();
+        self.revealed = 0;
+        self.state = DialogState::Done;
+
+        self.map.clear(vram);
+        self.map.commit(vram);
+        self.map.set_visible(false);
+    }
+
+    fn clear_text(&mut self, vram: &mut VRamManager) {
+        self.speaker.clear(vram);
+        self.body.clear(vram);
+    }
+
+    fn draw_panel(&mut self, vram: &mut VRamManager) {
+        let Rect { position, size } = self.area;
+
+        for y in 0..size.y {
+            let row = if y == 0 {
+                0
+            } else if y == size.y - 1 {
+                2
+            } else {
+                1
+            };
+
+            for x in 0..size.x {
+                let column = if x == 0 {
+                    0
+                } else if x == size.x - 1 {
+                    2
+                } else {
+                    1
+                };
+
+                self.map.set_tile(
+                    vram,
+                    (position.x + x, position.y + y),
+                    self.tileset,
+                    self.border[row * 3 + column],
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FONT: Font = crate::include_font!("examples/font/yoster.ttf", 12);
+
+    #[test_case]
+    fn reveals_then_waits_for_input(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+
+        let tile = vram.new_dynamic_tile().fill_with(0);
+        let tileset = tile.tile_set();
+
+        let area = Rect::new(Vector2D::new(0, 14), Vector2D::new(30, 6));
+        let mut dialog = DialogBox::new(&gfx, &tileset, [tile.tile_setting(); 9], &FONT, area);
+        let input = ButtonController::new();
+
+        assert_eq!(dialog.state(), DialogState::Done);
+
+        dialog.set_chars_per_frame(2);
+        dialog.show_with_speaker(&mut vram, "Elder", "Hello");
+
+        assert_eq!(dialog.update(&input, &mut vram), DialogState::Revealing);
+        assert_eq!(dialog.update(&input, &mut vram), DialogState::Revealing);
+        assert_eq!(
+            dialog.update(&input, &mut vram),
+            DialogState::WaitingForInput
+        );
+
+        dialog.show(&mut vram, "Another line");
+        assert_eq!(dialog.update(&input, &mut vram), DialogState::Revealing);
+        dialog.fast_forward(&mut vram);
+        assert_eq!(dialog.state(), DialogState::WaitingForInput);
+        dialog.commit(&mut vram);
+
+        dialog.hide(&mut vram);
+        assert_eq!(dialog.state(), DialogState::Done);
+        drop(dialog);
+
+        vram.remove_dynamic_tile(tile);
+        vram.gc();
+    }
+}
@@ -0,0 +1,174 @@
+//! Skipping text rendering when the text hasn't changed.
+//!
+//! HUD text such as a score or a timer is usually drawn every frame but only changes
+//! occasionally. Rendering text is slow, since every letter is drawn into dynamic tiles, so a
+//! [`CachedTextRenderer`] remembers a hash of what each region last showed and only renders it
+//! again when that changes.
+
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::{
+    font::TextRenderer,
+    tiled::{RegularMap, VRamManager},
+    Font,
+};
+use agb_fixnum::Vector2D;
+
+const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// 32 bit FNV-1a of `bytes`, continuing from `hash`.
+fn fnv1a(hash: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+struct CachedRegion<'a> {
+    renderer: TextRenderer<'a>,
+    hash: Option<u32>,
+}
+
+/// Draws text into several regions of a background, skipping regions whose text hasn't
+/// changed since they were last drawn.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # static FONT: agb::display::Font = agb::include_font!("examples/font/yoster.ttf", 12);
+/// # fn foo(gba: &mut agb::Gba) {
+/// use agb::display::{text_renderer_cache::CachedTextRenderer, tiled::{RegularBackgroundSize, TileFormat}, Priority};
+///
+/// let (gfx, mut vram) = gba.display.video.tiled0();
+/// let mut bg = gfx.background(Priority::P0, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+///
+/// let mut text = CachedTextRenderer::new(&FONT);
+/// let score = text.add_region((0u16, 0u16));
+///
+/// loop {
+///     // only renders the first time round
+///     text.draw_cached(score, "Score: 100", 1, 0, &mut bg, &mut vram);
+/// #   break;
+/// }
+/// # }
+/// ```
+pub struct CachedTextRenderer<'a> {
+    font: &'a Font,
+    regions: Vec<CachedRegion<'a>>,
+}
+
+impl<'a> CachedTextRenderer<'a> {
+    /// Creates a renderer with no regions which draws using `font`.
+    #[must_use]
+    pub fn new(font: &'a Font) -> Self {
+        Self {
+            font,
+            regions: Vec::new(),
+        }
+    }
+
+    /// Adds a region of text starting at the given tile co-ordinates, returning the index to
+    /// pass to [`draw_cached`](CachedTextRenderer::draw_cached).
+    pub fn add_region(&mut self, tile_pos: impl Into<Vector2D<u16>>) -> usize {
+        self.regions.push(CachedRegion {
+            renderer: self.font.render_text(tile_pos),
+            hash: None,
+        });
+
+        self.regions.len() - 1
+    }
+
+    /// Draws `text` into `region` in the given colours, unless it was already showing exactly
+    /// that. Returns whether the text was drawn.
+    ///
+    /// Redrawing frees the tiles used by the previous text before rendering the new text, so
+    /// as with [`TextRenderer::clear`](super::font::TextRenderer::clear) anything the new text doesn't cover needs clearing from
+    /// the background.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region` wasn't returned by [`add_region`](CachedTextRenderer::add_region).
+    pub fn draw_cached(
+        &mut self,
+        region: usize,
+        text: &str,
+        foreground_colour: u8,
+        background_colour: u8,
+        bg: &mut RegularMap,
+        vram_manager: &mut VRamManager,
+    ) -> bool {
+        let hash = fnv1a(
+            fnv1a(FNV_OFFSET_BASIS, &[foreground_colour, background_colour]),
+            text.as_bytes(),
+        );
+
+        let region = &mut self.regions[region];
+        if region.hash == Some(hash) {
+            return false;
+        }
+
+        region.renderer.clear(vram_manager);
+
+        let mut writer =
+            region
+                .renderer
+                .writer(foreground_colour, background_colour, bg, vram_manager);
+        let _ = writer.write_str(text);
+        writer.commit();
+
+        region.hash = Some(hash);
+        true
+    }
+
+    /// Forgets what `region` last showed, so the next call to
+    /// [`draw_cached`](CachedTextRenderer::draw_cached) will draw it whatever the text is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `region` wasn't returned by [`add_region`](CachedTextRenderer::add_region).
+    pub fn invalidate(&mut self, region: usize) {
+        self.regions[region].hash = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::{
+        tiled::{RegularBackgroundSize, TileFormat},
+        Priority,
+    };
+
+    static FONT: Font = crate::include_font!("examples/font/yoster.ttf", 12);
+
+    #[test_case]
+    fn fnv1a_matches_reference_values(_gba: &mut crate::Gba) {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0x811c_9dc5);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xe40c_292c);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0xbf9c_f968);
+    }
+
+    #[test_case]
+    fn only_redraws_changed_text(gba: &mut crate::Gba) {
+        let (gfx, mut vram) = gba.display.video.tiled0();
+        let mut bg = gfx.background(
+            Priority::P0,
+            RegularBackgroundSize::Background32x32,
+            TileFormat::FourBpp,
+        );
+
+        let mut text = CachedTextRenderer::new(&FONT);
+        let score = text.add_region((0u16, 0u16));
+        let lives = text.add_region((0u16, 4u16));
+
+        assert!(text.draw_cached(score, "Score: 1", 1, 0, &mut bg, &mut vram));
+        assert!(text.draw_cached(lives, "Lives: 3", 1, 0, &mut bg, &mut vram));
+        assert!(!text.draw_cached(score, "Score: 1", 1, 0, &mut bg, &mut vram));
+        assert!(text.draw_cached(score, "Score: 2", 1, 0, &mut bg, &mut vram));
+        assert!(text.draw_cached(score, "Score: 2", 2, 0, &mut bg, &mut vram));
+
+        text.invalidate(lives);
+        assert!(text.draw_cached(lives, "Lives: 3", 1, 0, &mut bg, &mut vram));
+    }
+}
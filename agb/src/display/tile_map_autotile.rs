@@ -0,0 +1,247 @@
+//! Choosing border tiles automatically from a map of terrain types.
+//!
+//! Where two kinds of terrain meet, say grass and water, the tiles along the border need to
+//! show the transition between them, and which transition tile to use depends on what is on
+//! every side. Rather than picking them by hand, describe the map as a grid of
+//! [`TerrainType`]s, describe which tile to use for each combination of neighbours in a
+//! [`WangTileSet`], and let an [`AutotileResolver`] work out the tile for every cell.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::tile_map_autotile::{AutotileResolver, Neighbourhood, TerrainType, WangTileSet};
+//!
+//! const GRASS: TerrainType = TerrainType(0);
+//! const WATER: TerrainType = TerrainType(1);
+//!
+//! let mut tiles = WangTileSet::new();
+//! tiles.set_default(GRASS, 1);
+//! tiles.set_default(WATER, 2);
+//! // water with grass to the north
+//! tiles.add_edge_tile(WATER, [GRASS, WATER, WATER, WATER], 3);
+//!
+//! let map = [GRASS, GRASS, WATER, WATER];
+//! let resolved = AutotileResolver::new(&tiles, Neighbourhood::Four).resolve(&map, 2, 2);
+//! assert_eq!(resolved, [1, 1, 3, 3]);
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::hash_map::HashMap;
+
+/// A kind of terrain in the logical map, such as grass or water. What each value means is up
+/// to you.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TerrainType(pub u8);
+
+/// Which neighbours of a cell are considered when choosing its tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// Only the cells to the north, east, south and west.
+    Four,
+    /// The cells to the north, east, south and west along with the diagonals.
+    Eight,
+}
+
+/// The tile to use for each combination of a cell's terrain and the terrain around it.
+///
+/// Looking up a tile tries the most specific match first. With [`Neighbourhood::Eight`] that
+/// is a tile added with [`add_corner_tile`](WangTileSet::add_corner_tile), then one added with
+/// [`add_edge_tile`](WangTileSet::add_edge_tile), then the terrain's
+/// [default](WangTileSet::set_default), and finally tile 0.
+#[derive(Default)]
+pub struct WangTileSet {
+    edges: HashMap<(TerrainType, [TerrainType; 4]), u16>,
+    corners: HashMap<(TerrainType, [TerrainType; 8]), u16>,
+    defaults: HashMap<TerrainType, u16>,
+}
+
+impl WangTileSet {
+    /// Creates a tile set with no tiles.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tile used for `terrain` when no more specific tile matches its neighbours.
+    pub fn set_default(&mut self, terrain: TerrainType, tile: u16) {
+        self.defaults.insert(terrain, tile);
+    }
+
+    /// Sets the tile used for a cell of type `centre` whose neighbours are given in the order
+    /// north, east, south, west.
+    pub fn add_edge_tile(&mut self, centre: TerrainType, neighbours: [TerrainType; 4], tile: u16) {
+        self.edges.insert((centre, neighbours), tile);
+    }
+
+    /// Sets the tile used for a cell of type `centre` whose neighbours are given clockwise
+    /// starting from north: north, north east, east, south east, south, south west, west and
+    /// north west.
+    ///
+    /// A diagonal neighbour only affects the tile when both of the edge neighbours beside it
+    /// are the same terrain as the centre, since otherwise the edge tiles already cover that
+    /// corner. Any other diagonal is looked up as if it were the same as the centre, so
+    /// there's no need to add tiles for those combinations.
+    pub fn add_corner_tile(
+        &mut self,
+        centre: TerrainType,
+        neighbours: [TerrainType; 8],
+        tile: u16,
+    ) {
+        self.corners
+            .insert((centre, normalise_corners(centre, neighbours)), tile);
+    }
+
+    fn tile(
+        &self,
+        centre: TerrainType,
+        neighbours: [TerrainType; 8],
+        neighbourhood: Neighbourhood,
+    ) -> u16 {
+        let [n, _, e, _, s, _, w, _] = neighbours;
+
+        let corner = match neighbourhood {
+            Neighbourhood::Four => None,
+            Neighbourhood::Eight => self
+                .corners
+                .get(&(centre, normalise_corners(centre, neighbours)))
+                .copied(),
+        };
+
+        corner
+            .or_else(|| self.edges.get(&(centre, [n, e, s, w])).copied())
+            .or_else(|| self.defaults.get(&centre).copied())
+            .unwrap_or(0)
+    }
+}
+
+fn normalise_corners(centre: TerrainType, mut neighbours: [TerrainType; 8]) -> [TerrainType; 8] {
+    for corner in [1, 3, 5, 7] {
+        let before = neighbours[corner - 1];
+        let after = neighbours[(corner + 1) % 8];
+
+        if before != centre || after != centre {
+            neighbours[corner] = centre;
+        }
+    }
+
+    neighbours
+}
+
+/// Resolves a map of [`TerrainType`]s into tile indices using a [`WangTileSet`].
+pub struct AutotileResolver<'a> {
+    tiles: &'a WangTileSet,
+    neighbourhood: Neighbourhood,
+}
+
+impl<'a> AutotileResolver<'a> {
+    /// Creates a resolver which looks at the given neighbours of each cell.
+    #[must_use]
+    pub fn new(tiles: &'a WangTileSet, neighbourhood: Neighbourhood) -> Self {
+        Self {
+            tiles,
+            neighbourhood,
+        }
+    }
+
+    /// Works out the tile for every cell of `map`, which is `width` cells wide and `height`
+    /// cells tall stored a row at a time. Cells off the edge of the map are treated as the
+    /// same terrain as the cell being resolved, so the map doesn't get a border around it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` doesn't contain exactly `width * height` cells.
+    #[must_use]
+    pub fn resolve(&self, map: &[TerrainType], width: usize, height: usize) -> Vec<u16> {
+        assert_eq!(
+            map.len(),
+            width * height,
+            "map should contain width * height cells"
+        );
+
+        const OFFSETS: [(isize, isize); 8] = [
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+        ];
+
+        let mut resolved = Vec::with_capacity(map.len());
+
+        for y in 0..height {
+            for x in 0..width {
+                let centre = map[y * width + x];
+
+                let neighbours = OFFSETS.map(|(dx, dy)| {
+                    match (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+                        (Some(nx), Some(ny)) if nx < width && ny < height => map[ny * width + nx],
+                        _ => centre,
+                    }
+                });
+
+                resolved.push(self.tiles.tile(centre, neighbours, self.neighbourhood));
+            }
+        }
+
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRASS: TerrainType = TerrainType(0);
+    const WATER: TerrainType = TerrainType(1);
+
+    fn tile_set() -> WangTileSet {
+        let mut tiles = WangTileSet::new();
+        tiles.set_default(GRASS, 1);
+        tiles.set_default(WATER, 2);
+
+        tiles.add_edge_tile(GRASS, [GRASS, WATER, GRASS, GRASS], 10);
+        tiles.add_corner_tile(
+            GRASS,
+            [GRASS, WATER, GRASS, GRASS, GRASS, GRASS, GRASS, GRASS],
+            20,
+        );
+
+        tiles
+    }
+
+    #[test_case]
+    fn four_neighbours_uses_edges(_gba: &mut crate::Gba) {
+        let tiles = tile_set();
+        #[rustfmt::skip]
+        let map = [
+            GRASS, GRASS, GRASS,
+            GRASS, GRASS, WATER,
+        ];
+
+        let resolved = AutotileResolver::new(&tiles, Neighbourhood::Four).resolve(&map, 3, 2);
+        assert_eq!(resolved, [1, 1, 1, 1, 10, 2]);
+    }
+
+    #[test_case]
+    fn eight_neighbours_uses_corners(_gba: &mut crate::Gba) {
+        let tiles = tile_set();
+        #[rustfmt::skip]
+        let map = [
+            GRASS, WATER,
+            GRASS, GRASS,
+            GRASS, GRASS,
+        ];
+
+        let resolved = AutotileResolver::new(&tiles, Neighbourhood::Eight).resolve(&map, 2, 3);
+
+        // the middle left cell has water to the north east with grass either side of it, the
+        // top left cell has water directly to the east
+        assert_eq!(resolved, [10, 2, 20, 1, 1, 1]);
+    }
+}
@@ -4,6 +4,7 @@ use core::ops::{Deref, DerefMut};
 use crate::bitarray::Bitarray;
 use crate::display::affine::AffineMatrixBackground;
 use crate::display::tile_data::TileData;
+use crate::display::video_ram_map::{screen_block_address, VramLayout};
 use crate::display::{Priority, DISPLAY_CONTROL};
 use crate::dma;
 use crate::fixnum::Vector2D;
@@ -38,7 +39,7 @@ trait TiledMapPrivate: TiledMapTypes {
         unsafe { MemoryMapped::new(0x0400_0008 + 2 * self.background_id()) }
     }
     fn screenblock_memory(&self) -> *mut u16 {
-        (0x0600_0000 + 0x1000 * self.screenblock() / 2) as *mut u16
+        screen_block_address(self.screenblock()) as *mut u16
     }
 }
 
@@ -84,6 +85,12 @@ impl TiledMap for AffineMap {
     }
 
     fn commit(&mut self, vram: &mut VRamManager) {
+        debug_assert!(
+            VramLayout::current().validate_screen_block(self.screenblock()),
+            "screen block {} is not usable in the current display mode",
+            self.screenblock()
+        );
+
         let screenblock_memory = self.screenblock_memory() as *mut u8;
 
         if *self.tiles_dirty() {
@@ -97,15 +104,7 @@ impl TiledMap for AffineMap {
             }
         }
 
-        let tile_colour_flag: u16 = (self.colours() == TileFormat::EightBpp).into();
-
-        let new_bg_control_value = (self.priority() as u16)
-            | ((self.screenblock() as u16) << 8)
-            | (tile_colour_flag << 7)
-            | (self.map_size().size_flag() << 14);
-
-        self.bg_control_register().set(new_bg_control_value);
-        self.update_bg_registers();
+        self.commit_registers();
 
         vram.gc();
 
@@ -148,6 +147,12 @@ impl TiledMap for RegularMap {
     }
 
     fn commit(&mut self, vram: &mut VRamManager) {
+        debug_assert!(
+            VramLayout::current().validate_screen_block(self.screenblock()),
+            "screen block {} is not usable in the current display mode",
+            self.screenblock()
+        );
+
         let screenblock_memory = self.screenblock_memory();
 
         if *self.tiles_dirty() {
@@ -370,6 +375,7 @@ pub struct AffineMap {
     screenblock: u8,
     priority: Priority,
     size: AffineBackgroundSize,
+    wraparound: bool,
 
     transform: AffineMatrixBackground,
 
@@ -419,6 +425,7 @@ impl AffineMap {
             screenblock,
             priority,
             size,
+            wraparound: false,
 
             transform: Default::default(),
 
@@ -473,6 +480,33 @@ impl AffineMap {
         self.priority = priority;
     }
 
+    /// Whether the map repeats forever in every direction
+    #[must_use]
+    pub fn wraparound(&self) -> bool {
+        self.wraparound
+    }
+
+    /// Sets whether the map repeats forever in every direction. When it doesn't, which is the
+    /// default, everything outside the map is transparent.
+    pub fn set_wraparound(&mut self, wraparound: bool) {
+        self.wraparound = wraparound;
+    }
+
+    /// Writes the control and affine registers without copying the tiles to VRAM
+    pub(crate) fn commit_registers(&self) {
+        let tile_colour_flag: u16 = (self.colours() == TileFormat::EightBpp).into();
+        let wraparound_flag: u16 = self.wraparound.into();
+
+        let new_bg_control_value = (self.priority() as u16)
+            | ((self.screenblock() as u16) << 8)
+            | (tile_colour_flag << 7)
+            | (wraparound_flag << 13)
+            | (self.map_size().size_flag() << 14);
+
+        self.bg_control_register().set(new_bg_control_value);
+        self.update_bg_registers();
+    }
+
     fn bg_affine_matrix(&self) -> MemoryMapped<AffineMatrixBackground> {
         unsafe { MemoryMapped::new(0x0400_0000 + 0x10 * self.background_id()) }
     }
@@ -21,6 +21,9 @@ use map::TRANSPARENT_TILE_INDEX;
 // affine layers start at BG2
 pub(crate) const AFFINE_BG_ID_OFFSET: usize = 2;
 
+/// The size of a regular background in tiles, as stored in the size bits of its control
+/// register. Maps bigger than 32x32 tiles are made from several screen blocks, see
+/// [`BgMapSize::screen_blocks_needed`](crate::display::hardware_bg_scale::BgMapSize::screen_blocks_needed).
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u16)]
 pub enum RegularBackgroundSize {
@@ -33,6 +36,8 @@ pub enum RegularBackgroundSize {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BackgroundID(pub(crate) u8);
 
+/// The size of an affine background in tiles, as stored in the size bits of its control
+/// register.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u16)]
 pub enum AffineBackgroundSize {
@@ -4,7 +4,10 @@ use alloc::{slice, vec::Vec};
 
 use crate::{
     agb_alloc::{block_allocator::BlockAllocator, bump_allocator::StartEnd},
-    display::palette16,
+    display::{
+        palette16,
+        video_ram_map::{self, VramLayout},
+    },
     dma,
     hash_map::{Entry, HashMap},
     memory_mapped::MemoryMapped1DArray,
@@ -380,6 +383,13 @@ impl VRamManager {
 
         let target_location = tile_reference.0.as_ptr() as *mut _;
 
+        debug_assert!(
+            VramLayout::current().validate_charblock(
+                (target_location as usize - TILE_RAM_START) / video_ram_map::BLOCK_SIZE
+            ),
+            "tile data written outside of background tile memory for the current display mode"
+        );
+
         unsafe {
             match tile_format {
                 TileFormat::FourBpp => core::arch::asm!(
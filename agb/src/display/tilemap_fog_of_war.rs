@@ -0,0 +1,240 @@
+//! Hiding the parts of a tile map the player can't currently see.
+//!
+//! Roguelikes and strategy games often only show the tiles around the player, and cover
+//! everything else with a fog tile. [`FogOfWar`] keeps track of which tiles are visible now and
+//! which have ever been seen, and works out which screen entries need changing so only those
+//! are written to VRAM each turn.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(level: &[u16], screen_block: &mut [u16], player_x: usize, player_y: usize) {
+//! use agb::display::tilemap_fog_of_war::FogOfWar;
+//!
+//! const FOG_TILE: u16 = 0;
+//! let mut fog = FogOfWar::<32, 32>::new();
+//!
+//! // each turn
+//! fog.clear_visibility();
+//! fog.reveal_circle(player_x, player_y, 4);
+//! for (index, entry) in fog.darken_invisible(level, FOG_TILE) {
+//!     screen_block[index] = entry;
+//! }
+//! # }
+//! ```
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// What is known about a single tile.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct TileState: u8 {
+        const VISITED = 1 << 0;
+        const VISIBLE = 1 << 1;
+        /// Covered with fog the last time the changes were given out
+        const DRAWN_FOGGED = 1 << 2;
+        /// Never given out, so needs drawing whatever it shows
+        const UNDRAWN = 1 << 3;
+    }
+}
+
+/// Which tiles of a `W` by `H` tile map are visible, and which have been seen before. See the
+/// [module level documentation](self).
+///
+/// Everything is stored inline in a byte per tile, so it doesn't allocate.
+#[derive(Clone, Debug)]
+pub struct FogOfWar<const W: usize, const H: usize> {
+    tiles: [[TileState; W]; H],
+}
+
+impl<const W: usize, const H: usize> FogOfWar<W, H> {
+    /// Creates fog which covers the whole map, with nothing visited.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            tiles: [[TileState::UNDRAWN; W]; H],
+        }
+    }
+
+    fn tile(&self, x: usize, y: usize) -> TileState {
+        assert!(x < W && y < H, "tile ({x}, {y}) is outside the {W}x{H} map");
+
+        self.tiles[y][x]
+    }
+
+    /// Makes every tile within `radius` tiles of (`cx`, `cy`) visible, and marks them as
+    /// visited. Parts of the circle outside the map are ignored.
+    ///
+    /// Walls don't block the view, so for line of sight use [`reveal`](FogOfWar::reveal) on
+    /// the tiles you have worked out can be seen.
+    pub fn reveal_circle(&mut self, cx: usize, cy: usize, radius: usize) {
+        // comparing against r * (r + 1) rather than r * r gives rounder circles with no
+        // single tile bumps at the top, bottom and sides
+        let limit = radius * (radius + 1);
+
+        for y in cy.saturating_sub(radius)..(cy + radius + 1).min(H) {
+            for x in cx.saturating_sub(radius)..(cx + radius + 1).min(W) {
+                let dx = x.abs_diff(cx);
+                let dy = y.abs_diff(cy);
+
+                if dx * dx + dy * dy <= limit {
+                    self.reveal(x, y);
+                }
+            }
+        }
+    }
+
+    /// Makes the tile at (`x`, `y`) visible, and marks it as visited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile is outside the map.
+    pub fn reveal(&mut self, x: usize, y: usize) {
+        assert!(x < W && y < H, "tile ({x}, {y}) is outside the {W}x{H} map");
+
+        self.tiles[y][x].insert(TileState::VISIBLE | TileState::VISITED);
+    }
+
+    /// Whether the tile at (`x`, `y`) is visible now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile is outside the map.
+    #[must_use]
+    pub fn is_visible(&self, x: usize, y: usize) -> bool {
+        self.tile(x, y).contains(TileState::VISIBLE)
+    }
+
+    /// Whether the tile at (`x`, `y`) has ever been visible, for example to draw it on a
+    /// minimap or differently to tiles which have never been seen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile is outside the map.
+    #[must_use]
+    pub fn is_visited(&self, x: usize, y: usize) -> bool {
+        self.tile(x, y).contains(TileState::VISITED)
+    }
+
+    /// Makes every tile invisible again, while remembering which ones have been visited.
+    /// Usually called at the start of each turn before revealing what the player can see.
+    pub fn clear_visibility(&mut self) {
+        self.remove_from_every_tile(TileState::VISIBLE);
+    }
+
+    /// Forgets which tiles have been visited as well as which are visible, for example when
+    /// moving to a new level.
+    pub fn reset(&mut self) {
+        self.remove_from_every_tile(TileState::VISIBLE | TileState::VISITED);
+    }
+
+    fn remove_from_every_tile(&mut self, flags: TileState) {
+        for tile in self.tiles.as_flattened_mut() {
+            tile.remove(flags);
+        }
+    }
+
+    /// The screen entries which need to change for the invisible tiles to show `fog_tile`
+    /// and the visible tiles to show the entry from `map`, which is the whole map in row
+    /// order.
+    ///
+    /// Gives the index into the map and the new entry for each tile which has changed since
+    /// the last time this was called, or for every tile the first time. The fog is updated as
+    /// the iterator goes along, so any entries it doesn't reach will be given next time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `map` doesn't have an entry for each tile.
+    pub fn darken_invisible<'a>(
+        &'a mut self,
+        map: &'a [u16],
+        fog_tile: u16,
+    ) -> impl Iterator<Item = (usize, u16)> + 'a {
+        assert_eq!(map.len(), W * H, "the map needs an entry for each tile");
+
+        self.tiles
+            .as_flattened_mut()
+            .iter_mut()
+            .zip(map)
+            .enumerate()
+            .filter_map(move |(index, (tile, &entry))| {
+                let fogged = !tile.contains(TileState::VISIBLE);
+                if !tile.contains(TileState::UNDRAWN)
+                    && fogged == tile.contains(TileState::DRAWN_FOGGED)
+                {
+                    return None;
+                }
+
+                tile.set(TileState::DRAWN_FOGGED, fogged);
+                tile.remove(TileState::UNDRAWN);
+                Some((index, if fogged { fog_tile } else { entry }))
+            })
+    }
+}
+
+impl<const W: usize, const H: usize> Default for FogOfWar<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    const FOG: u16 = 0xfff;
+
+    #[test_case]
+    fn reveals_circles(_gba: &mut crate::Gba) {
+        let mut fog = FogOfWar::<8, 6>::new();
+        fog.reveal_circle(1, 1, 2);
+
+        let visible: Vec<_> = (0..6)
+            .map(|y| (0..8).filter(|&x| fog.is_visible(x, y)).count())
+            .collect();
+        assert_eq!(visible, [4, 4, 4, 3, 0, 0]);
+        assert!(fog.is_visible(3, 2));
+        assert!(!fog.is_visible(3, 3));
+
+        fog.clear_visibility();
+        assert!(!fog.is_visible(1, 1));
+        assert!(fog.is_visited(1, 1));
+        assert!(!fog.is_visited(5, 5));
+
+        // circles at the far edge don't go out of bounds
+        fog.reveal_circle(7, 5, 3);
+        assert!(fog.is_visible(7, 5));
+    }
+
+    #[test_case]
+    fn only_gives_changed_tiles(_gba: &mut crate::Gba) {
+        let map: Vec<u16> = (0..16).collect();
+        let mut fog = FogOfWar::<4, 4>::new();
+
+        fog.reveal(0, 0);
+        let first: Vec<_> = fog.darken_invisible(&map, FOG).collect();
+        assert_eq!(first.len(), 16);
+        assert_eq!(first[0], (0, 0));
+        assert_eq!(first[5], (5, FOG));
+
+        assert_eq!(fog.darken_invisible(&map, FOG).count(), 0);
+
+        fog.clear_visibility();
+        fog.reveal(1, 1);
+        assert_eq!(
+            fog.darken_invisible(&map, FOG).collect::<Vec<_>>(),
+            [(0, FOG), (5, 5)]
+        );
+
+        // stopping early leaves the rest for next time
+        fog.clear_visibility();
+        fog.reveal(3, 3);
+        assert_eq!(fog.darken_invisible(&map, FOG).next(), Some((5, FOG)));
+        assert_eq!(
+            fog.darken_invisible(&map, FOG).collect::<Vec<_>>(),
+            [(15, 15)]
+        );
+    }
+}
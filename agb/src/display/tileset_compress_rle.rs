@@ -0,0 +1,165 @@
+//! Background tiles stored run length encoded, and decompressed straight into VRAM by the BIOS.
+//!
+//! Tiles with lots of runs of the same colour, such as large flat areas, take up much less space
+//! in the ROM when run length encoded. The data is made at build time with
+//! `agb_tiles::rle::rle_compress_tiles`, and an [`RleTileset`] wrapping it is then
+//! [decompressed](RleTileset::decompress_to_char_base) into one of the background charblocks.
+//!
+//! In `build.rs`, with `agb_tiles` as a build dependency:
+//!
+//! ```rust,ignore
+//! let tiles: Vec<[u32; 8]> = load_tiles();
+//! let compressed = agb_tiles::rle::rle_compress_tiles(&tiles);
+//!
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{out_dir}/level_tiles.rle"), compressed).unwrap();
+//! ```
+//!
+//! And in the game:
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::tileset_compress_rle::RleTileset;
+//!
+//! #[repr(align(4))]
+//! struct Aligned<T: ?Sized>(T);
+//!
+//! # static LEVEL_TILES: &Aligned<[u8]> = &Aligned([0x30, 0x20, 0, 0, 0x80 | 29, 0, 0, 0]);
+//! # /*
+//! static LEVEL_TILES: &Aligned<[u8]> =
+//!     &Aligned(*include_bytes!(concat!(env!("OUT_DIR"), "/level_tiles.rle")));
+//! # */
+//!
+//! let tileset = RleTileset::new(&LEVEL_TILES.0);
+//! tileset.decompress_to_char_base(2);
+//! # }
+//! ```
+
+use crate::syscall;
+
+use super::video_ram_map::{charblock_address, VramLayout};
+
+const CHARBLOCK_SIZE: usize = 0x4000;
+/// The background tiles can use the first 64KiB of VRAM, so 4 charblocks.
+const BACKGROUND_VRAM_SIZE: usize = 0x1_0000;
+
+const RLE_HEADER_TYPE: u8 = 0x30;
+const TILE_SIZE: usize = 32;
+
+/// Run length encoded 4bpp tiles, see the [module level documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RleTileset {
+    data: &'static [u8],
+}
+
+impl RleTileset {
+    /// Wraps `data`, which must be in the format made by `agb_tiles::rle::rle_compress_tiles`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` isn't aligned to 4 bytes, which the BIOS needs, or doesn't start with a
+    /// run length encoding header.
+    #[must_use]
+    pub fn new(data: &'static [u8]) -> Self {
+        assert!(
+            (data.as_ptr() as usize).is_multiple_of(4),
+            "run length encoded data must be aligned to 4 bytes"
+        );
+        assert!(
+            data.len() >= 4 && data[0] == RLE_HEADER_TYPE,
+            "data doesn't start with a run length encoding header"
+        );
+
+        Self { data }
+    }
+
+    /// How many bytes the tiles take up in the ROM.
+    #[must_use]
+    pub fn compressed_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// How many bytes the tiles take up once decompressed.
+    #[must_use]
+    pub fn decompressed_size(&self) -> usize {
+        usize::from(self.data[1])
+            | (usize::from(self.data[2]) << 8)
+            | (usize::from(self.data[3]) << 16)
+    }
+
+    /// How many 4bpp tiles there are.
+    #[must_use]
+    pub fn tile_count(&self) -> usize {
+        self.decompressed_size() / TILE_SIZE
+    }
+
+    /// Decompresses the tiles into VRAM starting at the beginning of charblock `char_base`,
+    /// which is 0 to 3. Anything already there is overwritten, including tiles which the
+    /// [`VRamManager`](super::tiled::VRamManager) has put there, so use a charblock it isn't
+    /// using.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tiles would go past the end of the background tile area of VRAM.
+    pub fn decompress_to_char_base(&self, char_base: usize) {
+        let start = char_base * CHARBLOCK_SIZE;
+        assert!(
+            start + self.decompressed_size().next_multiple_of(2) <= BACKGROUND_VRAM_SIZE,
+            "the tiles don't fit in background VRAM from charblock {char_base}"
+        );
+
+        debug_assert!(
+            VramLayout::current().validate_charblock(char_base),
+            "charblock {char_base} is not usable in the current display mode"
+        );
+
+        // Safety: the data was checked to be aligned in `new`, and the destination is in VRAM
+        // with room for the decompressed tiles
+        unsafe {
+            syscall::rl_uncomp_vram(self.data.as_ptr(), charblock_address(char_base) as *mut u16);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(align(4))]
+    struct Aligned<T: ?Sized>(T);
+
+    // two tiles: one filled with colour 1, then one with a single row of colours 0 to 7
+    #[rustfmt::skip]
+    static TILES: &Aligned<[u8]> = &Aligned([
+        // header
+        0x30, 0x40, 0x00, 0x00,
+        // 32 bytes of 0x11
+        0x80 | 29, 0x11,
+        // the first row
+        0x03, 0x10, 0x32, 0x54, 0x76,
+        // the other 28 bytes, then padding
+        0x80 | 25, 0x00, 0x00, 0x00, 0x00,
+    ]);
+
+    #[test_case]
+    fn decompresses_into_vram(gba: &mut crate::Gba) {
+        let (_gfx, _vram) = gba.display.video.tiled0();
+
+        let tileset = RleTileset::new(&TILES.0);
+        assert_eq!(tileset.compressed_size(), 16);
+        assert_eq!(tileset.tile_count(), 2);
+
+        tileset.decompress_to_char_base(3);
+
+        let tiles = charblock_address(3) as *const u32;
+        let word = |i: usize| unsafe { tiles.add(i).read_volatile() };
+
+        assert_eq!(word(0), 0x1111_1111);
+        assert_eq!(word(7), 0x1111_1111);
+        assert_eq!(word(8), 0x7654_3210);
+        assert_eq!(word(9), 0);
+        assert_eq!(word(15), 0);
+    }
+}
@@ -0,0 +1,46 @@
+//! Using tile sets which had their duplicate tiles removed at build time.
+//!
+//! Tile sets often contain the same tile more than once, or tiles which are mirror images of
+//! each other. Since screen entries can flip tiles horizontally and vertically, only one copy of
+//! each needs to be in VRAM. A build script can find the unique tiles with
+//! `agb_tiles::tileset_packer::pack_tileset`, which also gives a table for changing a tile map
+//! to use them. Write both out for the game to `include!`, and then [`remap_tilemap`] applies
+//! the table to maps which are only built at runtime, such as ones generated by the game or
+//! unpacked from a custom format.
+//!
+//! Backgrounds loaded with [`include_background_gfx!`](crate::include_background_gfx) can
+//! remove duplicates themselves with the `deduplicate` option.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(tile_map: &mut [u16]) {
+//! use agb::display::tileset_packer::remap_tilemap;
+//!
+//! // written by the build script from pack_tileset
+//! static REMAP: [u16; 4] = [0, 0x400, 1, 0];
+//!
+//! remap_tilemap(tile_map, &REMAP);
+//! // copy the packed tiles and `tile_map` to VRAM
+//! # }
+//! ```
+
+pub use agb_tiles::tileset_packer::remap_tilemap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agb_tiles::tileset_packer::{HFLIP, VFLIP};
+
+    #[test_case]
+    fn remaps_keeping_palette_and_flips(_gba: &mut crate::Gba) {
+        // tile 1 is tile 0 flipped horizontally, and tile 2 is a tile of its own
+        let remap = [0, HFLIP, 1];
+
+        // palette 3, hflipped tile 1, so it ends up unflipped
+        let mut map = [0x3000 | HFLIP | 1, VFLIP | 2, 0];
+        remap_tilemap(&mut map, &remap);
+
+        assert_eq!(map, [0x3000, VFLIP | 1, 0]);
+    }
+}
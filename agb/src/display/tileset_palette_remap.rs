@@ -0,0 +1,272 @@
+//! Recolouring sprites at run time by switching palette banks, such as giving each team in a
+//! strategy game its own colour from the same sprites.
+//!
+//! A 16 colour sprite's tiles only hold indices into a palette bank, and which of the 16 object
+//! palette banks they index is chosen per object. So a sprite can be drawn in different colours
+//! by making copies of its palette with some colours changed, and pointing each object at the
+//! copy it needs, without touching the tiles at all.
+//!
+//! A [`PaletteBank`] reserves a run of consecutive banks from the sprite loader, one per team,
+//! and fills each with a copy of a palette with its team colour swapped in. A
+//! [`PaletteRemappedSprite`] is an object which is drawn with one of those banks, chosen by
+//! [`set_team_colour`](PaletteRemappedSprite::set_team_colour).
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba, soldier: &'static agb::display::object::Sprite) {
+//! use agb::display::{
+//!     object::ObjectUnmanaged,
+//!     tileset_palette_remap::{PaletteBank, PaletteRemappedSprite},
+//! };
+//!
+//! let (mut oam, mut loader) = gba.display.object.get_unmanaged();
+//!
+//! // colour 1 of the soldier's palette is its uniform
+//! let team_colours = [0x001f, 0x7c00, 0x03e0];
+//! let mut teams = PaletteBank::reserve(team_colours.len(), 1).expect("no room for the teams");
+//! for (team, &colour) in team_colours.iter().enumerate() {
+//!     teams.copy_with_team_colour(soldier.palette(), team, colour);
+//! }
+//!
+//! let mut enemy =
+//!     PaletteRemappedSprite::new(ObjectUnmanaged::new(loader.get_vram_sprite(soldier)), &teams);
+//! enemy.set_team_colour(2);
+//! enemy.object_mut().set_position((100, 60).into()).show();
+//!
+//! oam.iter().set_next(enemy.object());
+//! # }
+//! ```
+
+use super::{
+    object::{
+        release_palette_banks, reserve_palette_banks, ObjectUnmanaged, SpriteVram, OBJECT_PALETTE,
+    },
+    palette16::Palette16,
+};
+
+/// How many object palette banks there are.
+const BANKS: usize = 16;
+
+/// A run of consecutive object palette banks which the sprite loader won't use until this is
+/// dropped, holding recoloured copies of a palette. See the
+/// [module level documentation](self).
+#[derive(Debug)]
+pub struct PaletteBank {
+    first: usize,
+    count: usize,
+    team_colour_index: usize,
+}
+
+impl PaletteBank {
+    /// Reserves `count` banks for copies of palettes where colour `team_colour_index` is the
+    /// team colour. Returns `None` if there isn't a run of `count` free banks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is 0 or `team_colour_index` isn't a colour in a bank.
+    #[must_use]
+    pub fn reserve(count: usize, team_colour_index: usize) -> Option<Self> {
+        assert!(count > 0, "at least one bank must be reserved");
+        assert!(
+            team_colour_index < 16,
+            "a palette bank only has 16 colours, so {team_colour_index} isn't one"
+        );
+
+        let first = reserve_palette_banks(count)?;
+
+        Some(Self {
+            first,
+            count,
+            team_colour_index,
+        })
+    }
+
+    /// The index of the first bank, which is the palette offset for a
+    /// [`PaletteRemappedSprite`] using these banks.
+    #[must_use]
+    pub fn first(&self) -> u8 {
+        self.first as u8
+    }
+
+    /// How many banks there are, and so how many teams.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Always `false`, as at least one bank is reserved.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Copies `src` into the bank for `team`, with the team colour replaced by `team_colour`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `team` isn't one of the reserved banks.
+    pub fn copy_with_team_colour(&mut self, src: &Palette16, team: usize, team_colour: u16) {
+        assert!(
+            team < self.count,
+            "team {team} is out of range, as only {} banks are reserved",
+            self.count
+        );
+
+        let bank = self.first + team;
+        for index in 0..16 {
+            let colour = if index == self.team_colour_index {
+                team_colour
+            } else {
+                src.colour(index)
+            };
+
+            OBJECT_PALETTE.set(bank * 16 + index, colour);
+        }
+    }
+
+    /// Copies object palette bank `src_pal` into the bank for `dst_pal`, with the team colour
+    /// replaced by `team_colour`. This is for palettes which are already in VRAM, such as one
+    /// from [`PaletteVram::bank`](super::object::PaletteVram::bank). `dst_pal` is a team rather
+    /// than a bank, so the copy can only land in the banks reserved here.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_pal` isn't a palette bank or `dst_pal` isn't one of the reserved banks.
+    pub fn copy_bank_with_team_colour(&mut self, src_pal: usize, dst_pal: usize, team_colour: u16) {
+        assert!(src_pal < BANKS, "palette bank {src_pal} doesn't exist");
+
+        let src = Palette16::new(core::array::from_fn(|index| {
+            OBJECT_PALETTE.get(src_pal * 16 + index)
+        }));
+        self.copy_with_team_colour(&src, dst_pal, team_colour);
+    }
+}
+
+impl Drop for PaletteBank {
+    fn drop(&mut self) {
+        // Safety: these came from reserve_palette_banks, and can't be used once this is dropped
+        unsafe { release_palette_banks(self.first, self.count) };
+    }
+}
+
+/// An object drawn with a team's palette bank rather than its sprite's own palette. See the
+/// [module level documentation](self).
+pub struct PaletteRemappedSprite {
+    object: ObjectUnmanaged,
+    palette_offset: u8,
+    teams: u8,
+    team: u8,
+}
+
+impl PaletteRemappedSprite {
+    /// Draws `object` with the banks reserved by `banks`, starting with team 0.
+    #[must_use]
+    pub fn new(object: ObjectUnmanaged, banks: &PaletteBank) -> Self {
+        let mut remapped = Self {
+            object,
+            palette_offset: banks.first(),
+            // there are only 16 banks to reserve
+            teams: banks.len() as u8,
+            team: 0,
+        };
+        remapped.set_team_colour(0);
+
+        remapped
+    }
+
+    /// Draws the object with the bank for `team`. This only changes the object's attributes,
+    /// not its tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `team` isn't one of the banks the sprite was made with.
+    pub fn set_team_colour(&mut self, team: u8) {
+        assert!(
+            team < self.teams,
+            "team {team} is out of range, as only {} banks are reserved",
+            self.teams
+        );
+
+        self.team = team;
+        self.object.set_palette_bank(self.palette_offset + team);
+    }
+
+    /// The team whose bank the object is drawn with.
+    #[must_use]
+    pub fn team(&self) -> u8 {
+        self.team
+    }
+
+    /// The palette bank the object is drawn with.
+    #[must_use]
+    pub fn palette_bank(&self) -> u8 {
+        self.palette_offset + self.team
+    }
+
+    /// Changes the object's sprite, keeping its team colour. The sprite should use the palette
+    /// the team banks were copied from.
+    pub fn set_sprite(&mut self, sprite: SpriteVram) {
+        self.object.set_sprite(sprite);
+        self.set_team_colour(self.team);
+    }
+
+    /// The object, for putting in OAM.
+    #[must_use]
+    pub fn object(&self) -> &ObjectUnmanaged {
+        &self.object
+    }
+
+    /// The object, for moving it around and so on. Setting its sprite through this puts the
+    /// sprite's own palette back, so use [`set_sprite`](PaletteRemappedSprite::set_sprite)
+    /// instead.
+    pub fn object_mut(&mut self) -> &mut ObjectUnmanaged {
+        &mut self.object
+    }
+
+    /// Gives back the object, which keeps drawing with the team's bank until its sprite is set.
+    #[must_use]
+    pub fn into_object(self) -> ObjectUnmanaged {
+        self.object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::object::{DynamicSprite, PaletteVram, Size};
+
+    use super::*;
+
+    #[test_case]
+    fn copies_palettes_with_team_colours(_gba: &mut crate::Gba) {
+        let palette = Palette16::new([0x1234; 16]);
+
+        let mut teams = PaletteBank::reserve(2, 3).unwrap();
+        teams.copy_with_team_colour(&palette, 1, 0x7c00);
+
+        let bank = usize::from(teams.first()) + 1;
+        assert_eq!(OBJECT_PALETTE.get(bank * 16 + 2), 0x1234);
+        assert_eq!(OBJECT_PALETTE.get(bank * 16 + 3), 0x7c00);
+
+        let in_vram = PaletteVram::new(&Palette16::new([0x0421; 16])).unwrap();
+        teams.copy_bank_with_team_colour(in_vram.bank(), 0, 0x03e0);
+
+        let bank = usize::from(teams.first());
+        assert_eq!(OBJECT_PALETTE.get(bank * 16 + 2), 0x0421);
+        assert_eq!(OBJECT_PALETTE.get(bank * 16 + 3), 0x03e0);
+
+        let sprite = DynamicSprite::try_new(Size::S8x8)
+            .unwrap()
+            .to_vram(PaletteVram::new(&palette).unwrap());
+        let mut soldier = PaletteRemappedSprite::new(ObjectUnmanaged::new(sprite), &teams);
+
+        soldier.set_team_colour(1);
+        assert_eq!(soldier.palette_bank(), teams.first() + 1);
+
+        let sprite = DynamicSprite::try_new(Size::S8x8)
+            .unwrap()
+            .to_vram(PaletteVram::new(&palette).unwrap());
+        soldier.set_sprite(sprite);
+        assert_eq!(soldier.team(), 1);
+    }
+}
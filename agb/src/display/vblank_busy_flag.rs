@@ -0,0 +1,156 @@
+//! Making sure work which has to happen in vblank, such as writing OAM or palettes, only starts
+//! once vblank has.
+//!
+//! A [`VBlankFlag`] is set by the vblank interrupt and cleared when the game starts its vblank
+//! work with [`begin_vblank_work`](VBlankFlag::begin_vblank_work). That returns a
+//! [`VBlankToken`] which has to be given back with
+//! [`end_vblank_work`](VBlankFlag::end_vblank_work), and while it is held the flag can't be
+//! waited on or the work started again, so the borrow checker catches a frame's vblank work
+//! being started twice or the game waiting for the next vblank in the middle of it.
+//!
+//! Each flag keeps track of the vblanks it has seen separately, so several can be used at once
+//! without starting the work on one clearing the others.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::display::vblank_busy_flag::VBlankFlag;
+//!
+//! let oam = gba.display.object.get_managed();
+//! let mut vblank = VBlankFlag::new();
+//!
+//! loop {
+//!     // game logic
+//!
+//!     vblank.wait_for_vblank();
+//!
+//!     let token = vblank.begin_vblank_work();
+//!     oam.commit();
+//!     VBlankFlag::end_vblank_work(token);
+//! }
+//! # }
+//! ```
+
+use core::marker::PhantomData;
+
+use portable_atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::interrupt::{
+    add_interrupt_handler, debug_assert_interrupts_enabled, halt_until, Interrupt,
+};
+
+/// The number of vblanks since the first flag was created.
+static VBLANK_COUNT: AtomicUsize = AtomicUsize::new(0);
+static HAS_CREATED_HANDLER: AtomicBool = AtomicBool::new(false);
+
+/// A flag set at the start of every vblank, see the [module level documentation](self).
+pub struct VBlankFlag {
+    /// The vblank count when the vblank work was last started.
+    last_vblank: usize,
+}
+
+impl VBlankFlag {
+    /// Sets up the vblank interrupt to set the flag, and clears it so that
+    /// [`wait_for_vblank`](VBlankFlag::wait_for_vblank) waits for the next vblank.
+    #[must_use]
+    pub fn new() -> Self {
+        if !HAS_CREATED_HANDLER.swap(true, Ordering::SeqCst) {
+            // Safety: the handler doesn't allocate
+            let handler = unsafe {
+                add_interrupt_handler(Interrupt::VBlank, |_| {
+                    VBLANK_COUNT.store(
+                        VBLANK_COUNT.load(Ordering::SeqCst).wrapping_add(1),
+                        Ordering::SeqCst,
+                    );
+                })
+            };
+            core::mem::forget(handler);
+        }
+
+        Self {
+            last_vblank: VBLANK_COUNT.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Whether a vblank has started since the vblank work was last started.
+    #[must_use]
+    pub fn is_set(&self) -> bool {
+        VBLANK_COUNT.load(Ordering::SeqCst) != self.last_vblank
+    }
+
+    /// Halts the CPU until the flag is set, to save power. This returns straight away if a
+    /// vblank has already started since the vblank work was last started, for example because
+    /// the game logic overran.
+    pub fn wait_for_vblank(&mut self) {
+        debug_assert_interrupts_enabled();
+
+        halt_until(|| self.is_set());
+    }
+
+    /// Clears the flag and starts this frame's vblank work, which lasts until the returned token
+    /// is given to [`end_vblank_work`](VBlankFlag::end_vblank_work).
+    pub fn begin_vblank_work(&mut self) -> VBlankToken<'_> {
+        self.last_vblank = VBLANK_COUNT.load(Ordering::SeqCst);
+
+        VBlankToken { _flag: PhantomData }
+    }
+
+    /// Ends the vblank work started by [`begin_vblank_work`](VBlankFlag::begin_vblank_work).
+    /// This doesn't do anything itself, but taking the token lets the flag be used again.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn end_vblank_work(token: VBlankToken<'_>) {
+        let _ = token;
+    }
+}
+
+impl Default for VBlankFlag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Proof that vblank work is in progress, made by [`VBlankFlag::begin_vblank_work`] and ended by
+/// [`VBlankFlag::end_vblank_work`].
+#[must_use = "vblank work should be ended with VBlankFlag::end_vblank_work"]
+pub struct VBlankToken<'flag> {
+    _flag: PhantomData<&'flag mut VBlankFlag>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn flag_is_set_by_vblank_and_cleared_by_work(_gba: &mut crate::Gba) {
+        let mut flag = VBlankFlag::new();
+        flag.wait_for_vblank();
+        assert!(flag.is_set());
+
+        let token = flag.begin_vblank_work();
+        VBlankFlag::end_vblank_work(token);
+        assert!(!flag.is_set());
+
+        flag.wait_for_vblank();
+        assert!(flag.is_set());
+    }
+
+    #[test_case]
+    fn flags_dont_clear_each_other(_gba: &mut crate::Gba) {
+        let mut first = VBlankFlag::new();
+        let mut second = VBlankFlag::new();
+
+        first.wait_for_vblank();
+        assert!(second.is_set());
+
+        let token = first.begin_vblank_work();
+        VBlankFlag::end_vblank_work(token);
+        assert!(!first.is_set());
+        assert!(second.is_set());
+
+        second.wait_for_vblank();
+        let token = second.begin_vblank_work();
+        VBlankFlag::end_vblank_work(token);
+        assert!(!second.is_set());
+    }
+}
@@ -0,0 +1,123 @@
+//! Measuring how many scanlines a block of code takes using the `VCOUNT` register.
+//!
+//! The display draws 228 scanlines per frame: 160 visible ones followed by 68 of vblank. A game
+//! which does its logic for the next frame while the current one is drawn needs that logic to
+//! finish before vblank starts at line 160, so counting scanlines is a quick way of seeing how
+//! close you are to the limit.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::display::vcount_profiler::VCountProfiler;
+//!
+//! let guard = VCountProfiler::start();
+//! // update the game here
+//! let result = guard.finish();
+//!
+//! result.log("update");
+//! if result.is_overrun() {
+//!     // too slow, the work was still running once vblank started
+//! }
+//! # }
+//! ```
+
+use super::VCOUNT;
+
+const SCANLINES_PER_FRAME: u16 = 228;
+const VISIBLE_SCANLINES: u8 = 160;
+
+/// A measurement in progress, created by [`VCountProfiler::start`].
+#[must_use = "call finish to get the number of scanlines used"]
+pub struct VCountProfiler {
+    start_scanline: u8,
+}
+
+/// The scanlines at the start and end of a measured block of code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileResult {
+    /// The scanline being drawn when the measurement started.
+    pub start_scanline: u8,
+    /// The scanline being drawn when the measurement finished.
+    pub end_scanline: u8,
+    /// How many scanlines passed between the start and the end, allowing for the count
+    /// wrapping around at the end of the frame. Work taking longer than a whole frame can't be
+    /// told apart from work taking that much less time.
+    pub scanlines_used: u8,
+}
+
+fn current_scanline() -> u8 {
+    VCOUNT.get() as u8
+}
+
+impl VCountProfiler {
+    /// Starts measuring from the current scanline.
+    pub fn start() -> Self {
+        Self {
+            start_scanline: current_scanline(),
+        }
+    }
+
+    /// Stops measuring and returns the result.
+    #[must_use]
+    pub fn finish(self) -> ProfileResult {
+        ProfileResult::new(self.start_scanline, current_scanline())
+    }
+}
+
+impl ProfileResult {
+    fn new(start_scanline: u8, end_scanline: u8) -> Self {
+        let scanlines_used = (u16::from(end_scanline) + SCANLINES_PER_FRAME
+            - u16::from(start_scanline))
+            % SCANLINES_PER_FRAME;
+
+        Self {
+            start_scanline,
+            end_scanline,
+            scanlines_used: scanlines_used as u8,
+        }
+    }
+
+    /// Whether the measured code finished after vblank had started, meaning that it ran into
+    /// vblank rather than finishing while the visible part of the frame was drawn. This only
+    /// looks at where it finished, so work which ran past the end of the frame and finished
+    /// during the next frame's visible lines isn't counted.
+    #[must_use]
+    pub fn is_overrun(&self) -> bool {
+        self.end_scanline >= VISIBLE_SCANLINES
+    }
+
+    /// Prints the result to the mGBA debug output, labelled with `name`.
+    pub fn log(&self, name: &str) {
+        crate::println!(
+            "{}: {} scanlines ({} to {}){}",
+            name,
+            self.scanlines_used,
+            self.start_scanline,
+            self.end_scanline,
+            if self.is_overrun() {
+                ", overran into vblank"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn scanlines_used_wraps_at_end_of_frame(_gba: &mut crate::Gba) {
+        let result = ProfileResult::new(150, 165);
+        assert_eq!(result.scanlines_used, 15);
+        assert!(result.is_overrun());
+
+        let result = ProfileResult::new(220, 3);
+        assert_eq!(result.scanlines_used, 11);
+        assert!(!result.is_overrun());
+
+        assert_eq!(ProfileResult::new(100, 100).scanlines_used, 0);
+    }
+}
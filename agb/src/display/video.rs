@@ -1,6 +1,7 @@
 use super::{
     bitmap3::Bitmap3,
     bitmap4::Bitmap4,
+    mode0_background_manager::Mode0BackgroundManager,
     tiled::{Tiled0, Tiled1, Tiled2, VRamManager},
 };
 
@@ -27,6 +28,12 @@ impl Video {
         (unsafe { Tiled0::new() }, VRamManager::new())
     }
 
+    /// Tiled 0 mode where you decide where each background's tiles and map go in VRAM, see
+    /// [`Mode0BackgroundManager`]
+    pub fn mode0_background_manager(&mut self) -> Mode0BackgroundManager<'_> {
+        unsafe { Mode0BackgroundManager::new() }
+    }
+
     /// Tiled 1 mode provides 2 regular tiled backgrounds and 1 affine tiled background
     pub fn tiled1(&mut self) -> (Tiled1<'_>, VRamManager) {
         (unsafe { Tiled1::new() }, VRamManager::new())
@@ -0,0 +1,159 @@
+//! Describes how video RAM is laid out in each display mode.
+//!
+//! The GBA has 96KiB of VRAM at `0x0600_0000` which can be thought of as six 16KiB blocks.
+//! What each block is used for depends on the display mode selected in the display control
+//! register:
+//!
+//! | Mode  | Blocks 0-3                           | Block 4              | Block 5     |
+//! |-------|--------------------------------------|----------------------|-------------|
+//! | 0 - 2 | Background tiles and screen blocks   | Object tiles         | Object tiles|
+//! | 3 - 5 | Bitmap frame buffers                 | Bitmap frame buffers | Object tiles|
+//!
+//! Background tile data is addressed in 16KiB charblocks (so charblocks 0 to 3) and maps are
+//! addressed in 2KiB screen blocks (0 to 31), both of which share the first 64KiB. In the
+//! bitmap modes there are no backgrounds made of tiles, and the frame buffers also eat into
+//! the first half of object tile memory.
+//!
+//! agb uses [`VramLayout`] to check, in debug builds, that it never writes tiles or maps
+//! somewhere the current mode won't read them from.
+
+use core::ops::Range;
+
+use super::DISPLAY_CONTROL;
+
+/// Address of the start of VRAM.
+pub const VRAM_START: usize = 0x0600_0000;
+/// Size of one VRAM block in bytes.
+pub const BLOCK_SIZE: usize = 16 * 1024;
+/// Size of one screen block in bytes.
+pub const SCREEN_BLOCK_SIZE: usize = 2 * 1024;
+/// Number of 16KiB blocks in VRAM.
+pub const NUM_BLOCKS: usize = 6;
+
+const OBJ_TILE_SIZE: usize = 32;
+
+/// The address of the start of screen block `sb`.
+#[must_use]
+pub const fn screen_block_address(sb: usize) -> usize {
+    VRAM_START + sb * SCREEN_BLOCK_SIZE
+}
+
+/// The address of the start of charblock `cb`. Charblocks are the same size as the 16KiB VRAM
+/// blocks, so charblocks 4 and 5 are object tile memory.
+#[must_use]
+pub const fn charblock_address(cb: usize) -> usize {
+    VRAM_START + cb * BLOCK_SIZE
+}
+
+/// Which parts of VRAM are in use for what in a particular display mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VramLayout {
+    /// The display mode, between 0 and 5.
+    pub mode: u8,
+    /// The charblocks which backgrounds can read tile data from.
+    pub charblocks: Range<usize>,
+    /// The screen blocks which backgrounds can read maps from.
+    pub screen_blocks: Range<usize>,
+    /// The 16KiB blocks holding object tiles.
+    pub obj_tile_blocks: Range<usize>,
+    /// The byte offsets into VRAM of each bitmap frame buffer, if the mode has any.
+    pub bitmap_frames: [Option<Range<usize>>; 2],
+}
+
+impl VramLayout {
+    /// Works out the layout from a value of the display control register.
+    #[must_use]
+    pub fn from_dispcnt(dispcnt: u16) -> Self {
+        let mode = (dispcnt & 0b111) as u8;
+
+        match mode {
+            0..=2 => Self {
+                mode,
+                charblocks: 0..4,
+                screen_blocks: 0..32,
+                obj_tile_blocks: 4..6,
+                bitmap_frames: [None, None],
+            },
+            3 => Self::bitmap(mode, [Some(0..240 * 160 * 2), None]),
+            4 => Self::bitmap(mode, [Some(0..240 * 160), Some(0xA000..0xA000 + 240 * 160)]),
+            _ => Self::bitmap(
+                mode,
+                [Some(0..160 * 128 * 2), Some(0xA000..0xA000 + 160 * 128 * 2)],
+            ),
+        }
+    }
+
+    /// The layout for the display mode which is currently active.
+    #[must_use]
+    pub fn current() -> Self {
+        Self::from_dispcnt(DISPLAY_CONTROL.get())
+    }
+
+    fn bitmap(mode: u8, bitmap_frames: [Option<Range<usize>>; 2]) -> Self {
+        Self {
+            mode,
+            charblocks: 0..0,
+            screen_blocks: 0..0,
+            obj_tile_blocks: 5..6,
+            bitmap_frames,
+        }
+    }
+
+    /// Whether backgrounds can read tile data from charblock `cb` in this layout.
+    #[must_use]
+    pub fn validate_charblock(&self, cb: usize) -> bool {
+        self.charblocks.contains(&cb)
+    }
+
+    /// Whether backgrounds can read a map from screen block `sb` in this layout.
+    #[must_use]
+    pub fn validate_screen_block(&self, sb: usize) -> bool {
+        self.screen_blocks.contains(&sb)
+    }
+
+    /// Whether object tile `tile` (counted in 4bpp tiles) is usable in this layout.
+    #[must_use]
+    pub fn validate_obj_tile(&self, tile: usize) -> bool {
+        let block = NUM_BLOCKS - 2 + tile * OBJ_TILE_SIZE / BLOCK_SIZE;
+        self.obj_tile_blocks.contains(&block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn tiled_and_bitmap_layouts(_gba: &mut crate::Gba) {
+        for mode in 0..=2 {
+            let layout = VramLayout::from_dispcnt(mode | (1 << 8));
+
+            assert!(layout.validate_charblock(3));
+            assert!(!layout.validate_charblock(4));
+            assert!(layout.validate_screen_block(31));
+            assert!(layout.validate_obj_tile(0));
+            assert!(layout.validate_obj_tile(1023));
+        }
+
+        for mode in 3..=5 {
+            let layout = VramLayout::from_dispcnt(mode);
+
+            assert!(!layout.validate_charblock(0));
+            assert!(!layout.validate_screen_block(0));
+            assert!(!layout.validate_obj_tile(511));
+            assert!(layout.validate_obj_tile(512));
+            assert!(layout.bitmap_frames[0].is_some());
+        }
+
+        assert!(VramLayout::from_dispcnt(3).bitmap_frames[1].is_none());
+    }
+
+    #[test_case]
+    fn block_addresses(_gba: &mut crate::Gba) {
+        assert_eq!(screen_block_address(0), 0x0600_0000);
+        assert_eq!(screen_block_address(31), 0x0600_F800);
+        assert_eq!(charblock_address(1), 0x0600_4000);
+        assert_eq!(charblock_address(4), 0x0601_0000);
+        assert_eq!(charblock_address(1), screen_block_address(8));
+    }
+}
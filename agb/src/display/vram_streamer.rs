@@ -0,0 +1,259 @@
+//! Uploading data to VRAM a little at a time during horizontal blank.
+//!
+//! Writing large amounts of data to VRAM while the screen is being drawn competes with the
+//! display for access to it, and changing tiles which are on screen part way through a frame
+//! tears. Horizontal blank, the short gap at the end of each scanline, is a safe time to write
+//! but only lasts around 270 cycles. [`VramStreamer`] copies a small chunk of its queued
+//! uploads in every horizontal blank, so something the size of a whole tileset can be streamed
+//! in over several frames while the game carries on as normal.
+//!
+//! Only the destination is checked to be in VRAM. It is up to you to make sure that nothing on
+//! screen uses the area being written to until the upload is complete.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::interrupt::{add_interrupt_handler, interrupt_free, Interrupt, InterruptHandler};
+
+use super::video_ram_map::{BLOCK_SIZE, NUM_BLOCKS, VRAM_START};
+
+const VRAM_SIZE: usize = NUM_BLOCKS * BLOCK_SIZE;
+
+/// Identifies an upload queued with [`VramStreamer::queue`], for checking whether it is done.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadId(u32);
+
+#[derive(Clone, Copy)]
+struct Upload {
+    source: &'static [u32],
+    destination: usize,
+    copied: usize,
+}
+
+struct StreamerState<const N: usize> {
+    uploads: [Option<Upload>; N],
+    first: usize,
+    len: usize,
+    queued: u32,
+    words_per_line: usize,
+}
+
+impl<const N: usize> StreamerState<N> {
+    /// Copies up to `words_per_line` words from the queued uploads.
+    fn copy_chunk(&mut self) {
+        let mut budget = self.words_per_line;
+
+        while budget > 0 {
+            let Some(upload) = &mut self.uploads[self.first] else {
+                return;
+            };
+
+            let chunk = (upload.source.len() - upload.copied).min(budget);
+            let source = &upload.source[upload.copied..upload.copied + chunk];
+
+            // Safety: the destination was checked to be within vram when the upload was queued
+            let destination = unsafe {
+                core::slice::from_raw_parts_mut(
+                    (upload.destination as *mut u32).add(upload.copied),
+                    chunk,
+                )
+            };
+            crate::dma::copy32(source, destination);
+
+            upload.copied += chunk;
+            budget -= chunk;
+
+            if upload.copied == upload.source.len() {
+                self.uploads[self.first] = None;
+                self.first = (self.first + 1) % N;
+                self.len -= 1;
+            }
+        }
+    }
+}
+
+/// A queue of up to `N` uploads to VRAM which are copied in during horizontal blank.
+///
+/// The streamer needs to live for as long as the interrupt handler copying the data, so is
+/// usually kept in a `static`.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # static NEXT_ROOM_TILES: [u32; 2048] = [0; 2048];
+/// # fn foo() {
+/// use agb::display::vram_streamer::VramStreamer;
+///
+/// static STREAMER: VramStreamer<4> = VramStreamer::new(64);
+///
+/// let _handler = STREAMER.start();
+/// let upload = STREAMER
+///     .queue(&NEXT_ROOM_TILES, 0x0600_8000)
+///     .expect("queue shouldn't be full");
+///
+/// let vblank = agb::interrupt::VBlank::get();
+/// while !STREAMER.is_complete(upload) {
+///     // keep drawing the current room
+///     vblank.wait_for_vblank();
+/// }
+/// # }
+/// ```
+pub struct VramStreamer<const N: usize> {
+    state: Mutex<RefCell<StreamerState<N>>>,
+}
+
+impl<const N: usize> VramStreamer<N> {
+    /// Creates an empty streamer which copies up to `bytes_per_line` bytes (rounded down to a
+    /// whole number of words) each horizontal blank.
+    ///
+    /// Copying from ROM to VRAM takes a few cycles per word and the interrupt handler itself
+    /// takes some of the horizontal blank, so 64 bytes is a safe budget. Going much above 128
+    /// will start to overrun into the next scanline.
+    #[must_use]
+    pub const fn new(bytes_per_line: usize) -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(StreamerState {
+                uploads: [None; N],
+                first: 0,
+                len: 0,
+                queued: 0,
+                words_per_line: bytes_per_line / 4,
+            })),
+        }
+    }
+
+    /// Starts copying queued uploads every horizontal blank for as long as the returned
+    /// handler is alive.
+    pub fn start(&'static self) -> InterruptHandler {
+        // Safety: copying doesn't allocate
+        unsafe {
+            add_interrupt_handler(Interrupt::HBlank, move |cs| {
+                self.state.borrow_ref_mut(cs).copy_chunk();
+            })
+        }
+    }
+
+    /// Queues copying `source` to `destination`, which must be the address of somewhere in
+    /// VRAM. Returns `None` without queueing anything if `N` uploads are already waiting.
+    ///
+    /// Uploads are done in the order they are queued.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any part of the destination isn't in VRAM, or if it isn't word aligned.
+    pub fn queue(&self, source: &'static [u32], destination: usize) -> Option<UploadId> {
+        assert_eq!(destination % 4, 0, "destination must be word aligned");
+        assert!(
+            destination >= VRAM_START && destination + source.len() * 4 <= VRAM_START + VRAM_SIZE,
+            "destination must be in vram"
+        );
+
+        interrupt_free(|token| {
+            let mut state = self.state.borrow_ref_mut(token.critical_section());
+
+            if state.len == N {
+                return None;
+            }
+
+            let index = (state.first + state.len) % N;
+            state.uploads[index] = Some(Upload {
+                source,
+                destination,
+                copied: 0,
+            });
+            state.len += 1;
+
+            let id = UploadId(state.queued);
+            state.queued = state.queued.wrapping_add(1);
+
+            Some(id)
+        })
+    }
+
+    /// Whether the upload has been completely copied to VRAM.
+    #[must_use]
+    pub fn is_complete(&self, upload: UploadId) -> bool {
+        interrupt_free(|token| {
+            let state = self.state.borrow_ref(token.critical_section());
+
+            // the uploads which aren't complete are the last `len` to be queued
+            state.queued.wrapping_sub(upload.0) > state.len as u32
+        })
+    }
+
+    /// Whether every queued upload has been completed.
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        interrupt_free(|token| self.state.borrow_ref(token.critical_section()).len == 0)
+    }
+
+    /// The number of bytes still waiting to be copied.
+    #[must_use]
+    pub fn remaining_bytes(&self) -> usize {
+        interrupt_free(|token| {
+            let state = self.state.borrow_ref(token.critical_section());
+
+            (0..state.len)
+                .filter_map(|i| state.uploads[(state.first + i) % N])
+                .map(|upload| (upload.source.len() - upload.copied) * 4)
+                .sum()
+        })
+    }
+
+    /// Copies everything still queued straight away, for when the uploads are needed now such
+    /// as during a screen transition.
+    pub fn flush(&self) {
+        interrupt_free(|token| {
+            let mut state = self.state.borrow_ref_mut(token.critical_section());
+            let words_per_line = state.words_per_line;
+
+            state.words_per_line = usize::MAX;
+            state.copy_chunk();
+            state.words_per_line = words_per_line;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::video_ram_map::charblock_address;
+
+    static SOURCE: [u32; 100] = {
+        let mut source = [0; 100];
+        let mut i = 0;
+        while i < source.len() {
+            source[i] = i as u32 * 0x0101_0101;
+            i += 1;
+        }
+        source
+    };
+
+    #[test_case]
+    fn uploads_complete_in_order(_gba: &mut crate::Gba) {
+        static STREAMER: VramStreamer<2> = VramStreamer::new(64);
+
+        const DESTINATION: usize = charblock_address(4);
+
+        let first = STREAMER.queue(&SOURCE, DESTINATION).unwrap();
+        let second = STREAMER.queue(&SOURCE[..10], DESTINATION + 400).unwrap();
+        assert_eq!(STREAMER.queue(&SOURCE, DESTINATION), None);
+        assert_eq!(STREAMER.remaining_bytes(), 440);
+        assert!(!STREAMER.is_complete(first));
+
+        {
+            let _handler = STREAMER.start();
+            while !STREAMER.is_complete(first) {}
+        }
+
+        STREAMER.flush();
+        assert!(STREAMER.is_complete(second));
+        assert!(STREAMER.is_idle());
+
+        let vram =
+            unsafe { core::slice::from_raw_parts(DESTINATION as *const u32, SOURCE.len() + 10) };
+        assert_eq!(&vram[..100], &SOURCE);
+        assert_eq!(&vram[100..], &SOURCE[..10]);
+    }
+}
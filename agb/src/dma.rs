@@ -82,6 +82,23 @@ impl Dma {
         location: &DmaControllable<T>,
         values: &'a [T],
     ) -> DmaTransferHandle<'a, T>
+    where
+        T: Copy,
+    {
+        unsafe { self.hblank_transfer_copied(location, values) }
+    }
+
+    /// Like [`hblank_transfer`](Dma::hblank_transfer), but `values` only needs to live for
+    /// the call since the handle transfers from its own copy of them.
+    ///
+    /// # Safety
+    ///
+    /// While DmaTransferHandle is not dropped, `location` must stay valid to write to.
+    pub(crate) unsafe fn hblank_transfer_copied<'a, T>(
+        &'a self,
+        location: &DmaControllable<T>,
+        values: &[T],
+    ) -> DmaTransferHandle<'a, T>
     where
         T: Copy,
     {
@@ -173,6 +190,184 @@ const fn dma_control_addr(dma: usize) -> usize {
     0x0400_00b8 + 0x0c * dma
 }
 
+/// Copies `src` into `dst` a word at a time, using DMA if it can.
+///
+/// DMA is used when one of the general purpose channels (3, or 0 if the source isn't in ROM)
+/// isn't already in use, and neither slice is somewhere DMA can't access such as the IO
+/// registers or SRAM. Otherwise this falls back to copying with the CPU. The channels used by
+/// the sound mixer are never touched.
+///
+/// Interrupts are disabled while each DMA transfer is set up and run, so this is safe to use
+/// from both interrupt handlers and the main loop.
+///
+/// # Panics
+///
+/// Panics if `src` and `dst` are different lengths.
+pub fn copy32(src: &[u32], dst: &mut [u32]) {
+    assert_eq!(
+        src.len(),
+        dst.len(),
+        "source and destination lengths differ"
+    );
+
+    let done = unsafe {
+        immediate_transfer(
+            src.as_ptr().cast(),
+            dst.as_mut_ptr().cast(),
+            dst.len(),
+            TransferSize::Word,
+            SourceMode::Increment,
+        )
+    };
+    dst[done..].copy_from_slice(&src[done..]);
+}
+
+/// Copies `src` into `dst` a half word at a time, using DMA if it can. See [`copy32`] for when
+/// DMA is used.
+///
+/// # Panics
+///
+/// Panics if `src` and `dst` are different lengths.
+pub fn copy16(src: &[u16], dst: &mut [u16]) {
+    assert_eq!(
+        src.len(),
+        dst.len(),
+        "source and destination lengths differ"
+    );
+
+    let done = unsafe {
+        immediate_transfer(
+            src.as_ptr().cast(),
+            dst.as_mut_ptr().cast(),
+            dst.len(),
+            TransferSize::HalfWord,
+            SourceMode::Increment,
+        )
+    };
+    dst[done..].copy_from_slice(&src[done..]);
+}
+
+/// Sets every word of `dst` to `value`, using DMA if it can. See [`copy32`] for when DMA is
+/// used.
+pub fn fill32(dst: &mut [u32], value: u32) {
+    let done = unsafe {
+        immediate_transfer(
+            (&value as *const u32).cast(),
+            dst.as_mut_ptr().cast(),
+            dst.len(),
+            TransferSize::Word,
+            SourceMode::Fixed,
+        )
+    };
+    dst[done..].fill(value);
+}
+
+/// Sets every half word of `dst` to `value`, using DMA if it can. See [`copy32`] for when DMA
+/// is used.
+pub fn fill16(dst: &mut [u16], value: u16) {
+    let done = unsafe {
+        immediate_transfer(
+            (&value as *const u16).cast(),
+            dst.as_mut_ptr().cast(),
+            dst.len(),
+            TransferSize::HalfWord,
+            SourceMode::Fixed,
+        )
+    };
+    dst[done..].fill(value);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TransferSize {
+    HalfWord = 2,
+    Word = 4,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SourceMode {
+    Increment,
+    Fixed,
+}
+
+fn dma_can_access(address: usize) -> bool {
+    // not the bios, io registers or sram
+    matches!(address >> 24, 0x02 | 0x03 | 0x05..=0x0d)
+}
+
+fn is_in_rom(address: usize) -> bool {
+    matches!(address >> 24, 0x08..=0x0d)
+}
+
+/// Transfers `count` items from `src` to `dst` using whichever general purpose DMA channel is
+/// free, returning how many items were transferred. This can be fewer than `count` if the
+/// channels are busy, in which case the caller should finish the job with the CPU.
+///
+/// # Safety
+///
+/// `src` and `dst` must be valid for `count` items of `size` (or just one for a fixed source)
+/// and aligned to `size`.
+unsafe fn immediate_transfer(
+    src: *const u8,
+    dst: *mut u8,
+    count: usize,
+    size: TransferSize,
+    source_mode: SourceMode,
+) -> usize {
+    if !dma_can_access(src as usize) || !dma_can_access(dst as usize) {
+        return 0;
+    }
+
+    let mut done = 0;
+
+    while done < count {
+        let source_offset = match source_mode {
+            SourceMode::Increment => done * size as usize,
+            SourceMode::Fixed => 0,
+        };
+
+        let transferred = crate::interrupt::interrupt_free(|_| {
+            let channel = [3, 0].into_iter().find(|&channel| {
+                let enabled = unsafe { MemoryMapped::<u32>::new(dma_control_addr(channel)) }.get()
+                    & (1 << 31)
+                    != 0;
+
+                // dma 0 can't read from the cartridge
+                !enabled && (channel != 0 || !is_in_rom(src as usize))
+            })?;
+
+            let max_count = if channel == 3 { 0xffff } else { 0x3fff };
+            let chunk = (count - done).min(max_count);
+
+            let control = unsafe { MemoryMapped::<u32>::new(dma_control_addr(channel)) };
+            unsafe {
+                MemoryMapped::<u32>::new(dma_source_addr(channel))
+                    .set(src.add(source_offset) as u32);
+                MemoryMapped::<u32>::new(dma_dest_addr(channel))
+                    .set(dst.add(done * size as usize) as u32);
+            }
+
+            control.set(
+                chunk as u32
+                    | (u32::from(source_mode == SourceMode::Fixed) << 0x18) // fix the source address
+                    | (u32::from(size == TransferSize::Word) << 0x1a) // copy in words
+                    | (1 << 0x1f), // enable the dma, starting immediately
+            );
+
+            // the cpu is halted while the transfer happens, but it takes a couple of cycles to start
+            while control.get() & (1 << 31) != 0 {}
+
+            Some(chunk)
+        });
+
+        match transferred {
+            Some(chunk) => done += chunk,
+            None => break,
+        }
+    }
+
+    done
+}
+
 const DMA3_SOURCE_ADDR: MemoryMapped<u32> = unsafe { MemoryMapped::new(dma_source_addr(3)) };
 const DMA3_DEST_ADDR: MemoryMapped<u32> = unsafe { MemoryMapped::new(dma_dest_addr(3)) };
 const DMA3_CONTROL: MemoryMapped<u32> = unsafe { MemoryMapped::new(dma_control_addr(3)) };
@@ -191,7 +386,7 @@ pub(crate) fn dma3_exclusive<R>(f: impl FnOnce() -> R) -> R {
     const DMA1_CTRL_HI: MemoryMapped<u16> = unsafe { MemoryMapped::new(dma_control_addr(1) + 2) };
     const DMA2_CTRL_HI: MemoryMapped<u16> = unsafe { MemoryMapped::new(dma_control_addr(2) + 2) };
 
-    critical_section::with(|_| {
+    crate::interrupt::interrupt_free(|_| {
         let dma0_ctl = DMA0_CTRL_HI.get();
         let dma1_ctl = DMA1_CTRL_HI.get();
         let dma2_ctl = DMA2_CTRL_HI.get();
@@ -211,3 +406,37 @@ pub(crate) fn dma3_exclusive<R>(f: impl FnOnce() -> R) -> R {
         ret
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn copy_and_fill(_gba: &mut crate::Gba) {
+        let src: alloc::vec::Vec<u32> = (0..1000).collect();
+        let mut dst = alloc::vec![0; src.len()];
+
+        copy32(&src, &mut dst);
+        assert_eq!(src, dst);
+
+        fill32(&mut dst, 0x1234_5678);
+        assert!(dst.iter().all(|&x| x == 0x1234_5678));
+
+        let src16 = [1, 2, 3, 4, 5];
+        let mut dst16 = [0u16; 5];
+        copy16(&src16, &mut dst16);
+        assert_eq!(src16, dst16);
+
+        fill16(&mut dst16[1..4], 9);
+        assert_eq!(dst16, [1, 9, 9, 9, 5]);
+    }
+
+    #[test_case]
+    fn copy_from_rom(_gba: &mut crate::Gba) {
+        static ROM_DATA: [u16; 4] = [10, 20, 30, 40];
+        let mut dst = [0; 4];
+
+        copy16(&ROM_DATA, &mut dst);
+        assert_eq!(dst, ROM_DATA);
+    }
+}
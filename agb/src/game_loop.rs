@@ -0,0 +1,225 @@
+//! A main loop which runs game logic at a fixed rate.
+//!
+//! The usual shape of a game's main loop is to update the game, wait for vblank and then copy
+//! the new state to the screen. If an update occasionally takes longer than a frame, that loop
+//! slows the whole game down. [`GameLoop`] instead keeps track of how many vblanks have
+//! actually happened and runs the update as many times as needed to catch up, so the game runs
+//! at the same speed even if some frames are never drawn.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::game_loop::{GameLoop, UpdateRate};
+//!
+//! struct Game {
+//!     x: i32,
+//! }
+//!
+//! let mut game = Game { x: 0 };
+//! let mut game_loop = GameLoop::new(UpdateRate::Hz60);
+//!
+//! loop {
+//!     game_loop.frame(
+//!         &mut game,
+//!         |game| game.x += 1,
+//!         |game, _fraction| {
+//!             // copy the game state to the screen
+//!         },
+//!     );
+//! }
+//! # }
+//! ```
+
+use crate::{fixnum::Num, interrupt::VBlank};
+
+/// How often the update function is run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateRate {
+    /// Once per frame.
+    Hz60,
+    /// Once every other frame.
+    Hz30,
+}
+
+impl UpdateRate {
+    fn vblanks_per_update(self) -> usize {
+        match self {
+            UpdateRate::Hz60 => 1,
+            UpdateRate::Hz30 => 2,
+        }
+    }
+}
+
+/// How well a [`GameLoop`] is keeping up, for showing in a debug overlay.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoopStats {
+    /// The number of updates run over roughly the last second. This should match the
+    /// [`UpdateRate`] unless updates are being skipped.
+    pub updates_per_second: u32,
+    /// The total number of frames which were never drawn because the previous frame took too
+    /// long.
+    pub dropped_frames: u32,
+    /// The total number of updates which were skipped because the loop had fallen too far
+    /// behind to catch up.
+    pub skipped_updates: u32,
+}
+
+/// Runs an update function at a fixed rate and a render function once per frame.
+///
+/// See the [module level documentation](crate::game_loop) for an example.
+pub struct GameLoop {
+    vblank: VBlank,
+    last_vblank: usize,
+    timing: Timing,
+}
+
+struct Timing {
+    vblanks_per_update: usize,
+    max_updates_per_frame: usize,
+    accumulated_vblanks: usize,
+
+    window_vblanks: usize,
+    window_updates: usize,
+    stats: LoopStats,
+}
+
+impl Timing {
+    fn new(rate: UpdateRate) -> Self {
+        let vblanks_per_update = rate.vblanks_per_update();
+
+        Self {
+            vblanks_per_update,
+            max_updates_per_frame: 4,
+            accumulated_vblanks: 0,
+
+            window_vblanks: 0,
+            window_updates: 0,
+            stats: LoopStats::default(),
+        }
+    }
+
+    /// Returns how many updates to run given the number of vblanks since the last frame.
+    fn advance(&mut self, elapsed_vblanks: usize) -> usize {
+        self.stats.dropped_frames += elapsed_vblanks.saturating_sub(1) as u32;
+
+        self.accumulated_vblanks += elapsed_vblanks;
+        let mut updates = self.accumulated_vblanks / self.vblanks_per_update;
+        self.accumulated_vblanks %= self.vblanks_per_update;
+
+        if updates > self.max_updates_per_frame {
+            self.stats.skipped_updates += (updates - self.max_updates_per_frame) as u32;
+            updates = self.max_updates_per_frame;
+        }
+
+        self.window_vblanks += elapsed_vblanks;
+        self.window_updates += updates;
+        if self.window_vblanks >= 60 {
+            self.stats.updates_per_second = (self.window_updates * 60 / self.window_vblanks) as u32;
+            self.window_vblanks = 0;
+            self.window_updates = 0;
+        }
+
+        updates
+    }
+
+    /// How far between the last update and the next one this frame is.
+    fn fraction(&self) -> Num<i32, 8> {
+        Num::new(self.accumulated_vblanks as i32) / self.vblanks_per_update as i32
+    }
+}
+
+impl GameLoop {
+    /// Creates a loop which runs updates at the given rate.
+    #[must_use]
+    pub fn new(rate: UpdateRate) -> Self {
+        let vblank = VBlank::get();
+        let last_vblank = vblank.count();
+
+        Self {
+            vblank,
+            last_vblank,
+            timing: Timing::new(rate),
+        }
+    }
+
+    /// Sets the most times the update function will be run in a single frame when catching up.
+    /// Any further updates are skipped, so the game slows down rather than spending ever longer
+    /// catching up. Defaults to 4.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_updates` is 0.
+    pub fn set_max_updates_per_frame(&mut self, max_updates: usize) {
+        assert!(max_updates > 0, "must allow at least one update per frame");
+        self.timing.max_updates_per_frame = max_updates;
+    }
+
+    /// Statistics about how well the loop is keeping up.
+    #[must_use]
+    pub fn stats(&self) -> LoopStats {
+        self.timing.stats
+    }
+
+    /// Runs a single frame of the game.
+    ///
+    /// This runs `update` as many times as needed to catch up with the number of vblanks since
+    /// the last frame (which may be none when running at 30Hz), waits for the next vblank and
+    /// then calls `render`. `render` is given how far through the time between updates this
+    /// frame is, between 0 and 1, which can be used to smooth movement between updates.
+    pub fn frame<S>(
+        &mut self,
+        state: &mut S,
+        mut update: impl FnMut(&mut S),
+        render: impl FnOnce(&mut S, Num<i32, 8>),
+    ) {
+        let now = self.vblank.count();
+        let elapsed = now.wrapping_sub(self.last_vblank);
+        self.last_vblank = now;
+
+        for _ in 0..self.timing.advance(elapsed) {
+            update(state);
+        }
+
+        self.vblank.wait_for_vblank();
+
+        render(state, self.timing.fraction());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn catches_up_on_missed_frames(_gba: &mut crate::Gba) {
+        let mut timing = Timing::new(UpdateRate::Hz60);
+
+        assert_eq!(timing.advance(0), 0);
+        assert_eq!(timing.advance(1), 1);
+        assert_eq!(timing.advance(3), 3);
+        assert_eq!(timing.stats.dropped_frames, 2);
+
+        assert_eq!(timing.advance(10), 4);
+        assert_eq!(timing.stats.skipped_updates, 6);
+    }
+
+    #[test_case]
+    fn thirty_hz_updates_every_other_frame(_gba: &mut crate::Gba) {
+        let mut timing = Timing::new(UpdateRate::Hz30);
+
+        assert_eq!(timing.advance(0), 0);
+        assert_eq!(timing.fraction(), 0.into());
+
+        assert_eq!(timing.advance(1), 0);
+        assert_eq!(timing.fraction(), Num::new(1) / 2);
+
+        assert_eq!(timing.advance(1), 1);
+        assert_eq!(timing.fraction(), 0.into());
+
+        for _ in 0..58 {
+            timing.advance(1);
+        }
+        assert_eq!(timing.stats.updates_per_second, 30);
+    }
+}
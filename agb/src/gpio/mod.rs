@@ -0,0 +1,232 @@
+//! The general purpose I/O port on some cartridges, used for real-time clocks, rumble, solar
+//! sensors and the like.
+//!
+//! The port has four pins, which the cartridge wires up to whatever extra hardware it has. The
+//! pins are shared: a cartridge with a clock and a rumble motor uses pins 0 to 2 for the clock
+//! and pin 3 for the motor. To stop two drivers fighting over a pin, each one first
+//! [claims](CartGpio::claim) the pins it needs, and only ever changes those, leaving the others
+//! as they were. Claiming pins which are already claimed fails with
+//! [`GpioError::PinsInUse`], and they are released again when the [`CartGpio`] is dropped.
+//!
+//! The port is only readable while some of its pins are claimed. Otherwise its registers read
+//! back as the cartridge ROM they sit on top of, so nothing needs to turn it on or off by hand.
+//!
+//! Some hardware shares pins, such as the clock and solar sensor in Boktai, which both use pins 0
+//! to 2. Drivers for those only claim their pins while they are talking to the hardware, so they
+//! take turns, while drivers which have pins to themselves, such as
+//! [rumble](crate::rumble::Rumble::gpio), hold their claim for as long as they are in use.
+//!
+//! The [`rtc`] module is a driver for the real-time clock found on many cartridges, and
+//! [`solar`] is one for the solar sensor on Boktai cartridges. Other hardware, such as something
+//! homemade on a flash cart, can be driven through a [`CartGpio`] directly:
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::gpio::GpioError> {
+//! use agb::gpio::{CartGpio, Direction};
+//!
+//! // an LED on pin 3, with a button on pin 2
+//! let mut gpio = CartGpio::claim(0b1100)?;
+//! gpio.set_direction(3, Direction::Output);
+//! gpio.set_direction(2, Direction::Input);
+//!
+//! let pressed = gpio.pin(2);
+//! gpio.set_pin(3, pressed);
+//! # Ok(())
+//! # }
+//! ```
+
+use portable_atomic::{AtomicU8, Ordering};
+
+use crate::{interrupt::interrupt_free, memory_mapped::MemoryMapped};
+
+pub mod rtc;
+pub mod solar;
+
+const GPIO_DATA: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0800_00C4) };
+const GPIO_DIRECTION: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0800_00C6) };
+const GPIO_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0800_00C8) };
+
+/// Lets the port be read back rather than just written, which is needed to see any inputs.
+const READ_ENABLE: u16 = 1;
+
+/// All four pins of the port.
+pub const ALL_PINS: u8 = 0b1111;
+
+static CLAIMED_PINS: AtomicU8 = AtomicU8::new(0);
+
+/// The ways claiming GPIO pins can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GpioError {
+    /// Some of the pins are already claimed by something else. These are the ones which were.
+    PinsInUse(u8),
+}
+
+/// Which way a pin goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The cartridge drives the pin, and the GBA reads it.
+    Input,
+    /// The GBA drives the pin.
+    Output,
+}
+
+/// Some of the pins of the GPIO port, which nothing else can use until this is dropped. See the
+/// [module level documentation](self).
+#[derive(Debug)]
+pub struct CartGpio {
+    mask: u8,
+}
+
+impl CartGpio {
+    /// Claims the pins set in `mask`, with bit 0 being pin 0 and so on, as inputs. Fails with
+    /// the pins which were already claimed if there are any, in which case none of them are
+    /// claimed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` has bits set other than the bottom four.
+    pub fn claim(mask: u8) -> Result<Self, GpioError> {
+        assert_eq!(mask & !ALL_PINS, 0, "the GPIO port only has four pins");
+
+        interrupt_free(|_| {
+            let claimed = CLAIMED_PINS.load(Ordering::SeqCst);
+            if claimed & mask != 0 {
+                return Err(GpioError::PinsInUse(claimed & mask));
+            }
+
+            CLAIMED_PINS.store(claimed | mask, Ordering::SeqCst);
+            GPIO_CONTROL.set(READ_ENABLE);
+
+            Ok(Self { mask })
+        })
+    }
+
+    /// The pins which have been claimed.
+    #[must_use]
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Makes the claimed pins set in `outputs` outputs and the rest of the claimed pins inputs.
+    pub fn set_outputs(&mut self, outputs: u8) {
+        let mask = u16::from(self.mask);
+        interrupt_free(|_| {
+            let others = GPIO_DIRECTION.get() & !mask;
+            GPIO_DIRECTION.set(others | (u16::from(outputs) & mask));
+        });
+    }
+
+    /// Sets which way a single claimed pin goes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` hasn't been claimed.
+    pub fn set_direction(&mut self, pin: u8, direction: Direction) {
+        let bit = self.pin_bit(pin);
+        interrupt_free(|_| {
+            let others = GPIO_DIRECTION.get() & !u16::from(bit);
+            let pin = match direction {
+                Direction::Input => 0,
+                Direction::Output => u16::from(bit),
+            };
+            GPIO_DIRECTION.set(others | pin);
+        });
+    }
+
+    /// Sets the claimed output pins to the matching bits of `value`.
+    pub fn write(&mut self, value: u8) {
+        write_pins(self.mask, value);
+    }
+
+    /// Sets a single claimed pin high or low, which only does anything if it is an output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` hasn't been claimed.
+    pub fn set_pin(&mut self, pin: u8, high: bool) {
+        let bit = self.pin_bit(pin);
+        write_pins(bit, if high { bit } else { 0 });
+    }
+
+    /// Reads the claimed pins, with the other bits being 0.
+    #[must_use]
+    pub fn read(&self) -> u8 {
+        (GPIO_DATA.get() as u8) & self.mask
+    }
+
+    /// Whether a single claimed pin is high.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pin` hasn't been claimed.
+    #[must_use]
+    pub fn pin(&self, pin: u8) -> bool {
+        self.read() & self.pin_bit(pin) != 0
+    }
+
+    fn pin_bit(&self, pin: u8) -> u8 {
+        assert!(
+            pin < 4 && self.mask & (1 << pin) != 0,
+            "pin {pin} hasn't been claimed"
+        );
+
+        1 << pin
+    }
+}
+
+/// Sets the pins in `mask` to the matching bits of `value`, leaving the others alone. This is for
+/// interrupt handlers which drive pins claimed by a [`CartGpio`] they can't hold themselves.
+pub(crate) fn write_pins(mask: u8, value: u8) {
+    let mask = u16::from(mask);
+    interrupt_free(|_| {
+        let others = GPIO_DATA.get() & !mask;
+        GPIO_DATA.set(others | (u16::from(value) & mask));
+    });
+}
+
+impl Drop for CartGpio {
+    fn drop(&mut self) {
+        self.set_outputs(0);
+
+        interrupt_free(|_| {
+            let claimed = CLAIMED_PINS.fetch_and(!self.mask, Ordering::SeqCst) & !self.mask;
+            if claimed == 0 {
+                GPIO_CONTROL.set(0);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn pins_can_only_be_claimed_once(_gba: &mut crate::Gba) {
+        let clock = CartGpio::claim(0b0111).unwrap();
+        assert_eq!(
+            CartGpio::claim(0b1001).unwrap_err(),
+            GpioError::PinsInUse(0b0001)
+        );
+
+        let rumble = CartGpio::claim(0b1000).unwrap();
+        assert_eq!(rumble.mask(), 0b1000);
+
+        drop(clock);
+        assert!(CartGpio::claim(0b0001).is_ok());
+    }
+
+    #[test_case]
+    fn dropping_releases_the_pins(_gba: &mut crate::Gba) {
+        let mut gpio = CartGpio::claim(0b0100).unwrap();
+        gpio.set_direction(2, Direction::Output);
+        gpio.set_pin(2, false);
+        assert_eq!(CLAIMED_PINS.load(Ordering::SeqCst), 0b0100);
+
+        drop(gpio);
+        assert_eq!(CLAIMED_PINS.load(Ordering::SeqCst), 0);
+    }
+}
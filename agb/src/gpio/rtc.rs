@@ -0,0 +1,322 @@
+//! The S-3511 real-time clock found on many cartridges, for games which follow the real time
+//! of day.
+//!
+//! The clock is wired to pins 0 to 2 of the [GPIO port](super), and is talked to by toggling
+//! those pins by hand. [`Rtc::new`] checks that a clock is actually there and switches it to 24
+//! hour mode, after which the date and time can be [read](Rtc::read) and
+//! [set](Rtc::set). Carts without a clock don't have anything to wait for, so checking for one
+//! never hangs, it just fails with [`RtcError::NotPresent`].
+//!
+//! If the clock's battery has run out, the clock is reset to midnight on the 1st of January
+//! 2000, and [`Rtc::power_failed`] says so, so that the game can ask the player to set the time.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::gpio::rtc::Rtc;
+//!
+//! let Ok(mut rtc) = Rtc::new() else {
+//!     // no clock on this cart, so always daytime
+//!     return;
+//! };
+//!
+//! if let Ok(now) = rtc.read() {
+//!     let is_night = now.hour < 6 || now.hour >= 20;
+//! #   let _ = is_night;
+//! }
+//! # }
+//! ```
+
+use crate::interrupt::interrupt_free;
+
+use super::CartGpio;
+
+const SCK: u8 = 1 << 0;
+const SIO: u8 = 1 << 1;
+const CS: u8 = 1 << 2;
+const PINS: u8 = SCK | SIO | CS;
+
+const COMMAND_RESET: u8 = 0x60;
+const COMMAND_STATUS: u8 = 0x62;
+const COMMAND_DATE_TIME: u8 = 0x64;
+const READ: u8 = 1;
+
+const STATUS_24_HOUR: u8 = 1 << 6;
+const STATUS_POWER_FAILED: u8 = 1 << 7;
+
+const HOUR_PM: u8 = 1 << 7;
+
+/// The ways using the clock can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RtcError {
+    /// Pins 0 to 2 of the GPIO port were being used by something else when the clock was
+    /// needed.
+    PinsInUse,
+    /// There isn't a real-time clock on this cartridge.
+    NotPresent,
+    /// The clock gave back a date or time which doesn't make sense, or the one given to
+    /// [`Rtc::set`] doesn't.
+    InvalidDateTime,
+}
+
+/// A date and time read from or given to the clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    /// 2000 to 2099.
+    pub year: u16,
+    /// 1 to 12.
+    pub month: u8,
+    /// 1 to 31.
+    pub day: u8,
+    /// 0 to 6. The clock just counts these up each day, so which one is Sunday is up to the game.
+    pub weekday: u8,
+    /// 0 to 23.
+    pub hour: u8,
+    /// 0 to 59.
+    pub minute: u8,
+    /// 0 to 59.
+    pub second: u8,
+}
+
+impl DateTime {
+    fn is_valid(&self) -> bool {
+        (2000..2100).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.weekday < 7
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    fn from_registers(registers: [u8; 7], is_24_hour: bool) -> Option<Self> {
+        let [year, month, day, weekday, hour, minute, second] = registers;
+
+        let hour = if is_24_hour {
+            from_bcd(hour & !HOUR_PM)?
+        } else {
+            let pm = if hour & HOUR_PM != 0 { 12 } else { 0 };
+            from_bcd(hour & !HOUR_PM)? % 12 + pm
+        };
+
+        let date_time = Self {
+            year: 2000 + u16::from(from_bcd(year)?),
+            month: from_bcd(month)?,
+            day: from_bcd(day)?,
+            weekday: from_bcd(weekday)?,
+            hour,
+            minute: from_bcd(minute)?,
+            second: from_bcd(second)?,
+        };
+
+        date_time.is_valid().then_some(date_time)
+    }
+
+    /// The registers for 24 hour mode, where the clock works out the PM flag itself.
+    fn to_registers(self) -> [u8; 7] {
+        [
+            to_bcd((self.year - 2000) as u8),
+            to_bcd(self.month),
+            to_bcd(self.day),
+            to_bcd(self.weekday),
+            to_bcd(self.hour),
+            to_bcd(self.minute),
+            to_bcd(self.second),
+        ]
+    }
+}
+
+fn from_bcd(value: u8) -> Option<u8> {
+    let (tens, units) = (value >> 4, value & 0xf);
+    (tens < 10 && units < 10).then_some(tens * 10 + units)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// The cartridge's real-time clock, see the [module level documentation](self).
+pub struct Rtc {
+    power_failed: bool,
+}
+
+impl Rtc {
+    /// Checks that there is a clock on pins 0 to 2 of the GPIO port and switches it to 24 hour
+    /// mode. If the clock had lost power it is reset first.
+    ///
+    /// The pins are only claimed while talking to the clock, so other drivers which share them,
+    /// such as the [solar sensor](super::solar), can use them in between.
+    pub fn new() -> Result<Self, RtcError> {
+        let mut rtc = Self {
+            power_failed: false,
+        };
+
+        let status = rtc.read_status()?;
+        // without a clock the data line floats high, so everything reads back as ones. Other
+        // junk, such as from a cart without a GPIO port at all, fails the checks below.
+        if status == 0xff {
+            return Err(RtcError::NotPresent);
+        }
+
+        if status & STATUS_POWER_FAILED != 0 {
+            rtc.power_failed = true;
+            rtc.transfer(COMMAND_RESET, &mut [])?;
+        }
+
+        rtc.write_status(STATUS_24_HOUR)?;
+        if rtc.read_status()? & STATUS_24_HOUR == 0 {
+            return Err(RtcError::NotPresent);
+        }
+
+        match rtc.read() {
+            Ok(_) => Ok(rtc),
+            Err(RtcError::InvalidDateTime) => Err(RtcError::NotPresent),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Whether the clock had lost power, and so been reset, when it was found by
+    /// [`new`](Rtc::new).
+    #[must_use]
+    pub fn power_failed(&self) -> bool {
+        self.power_failed
+    }
+
+    /// Reads the current date and time.
+    pub fn read(&mut self) -> Result<DateTime, RtcError> {
+        let mut registers = [0; 7];
+        self.transfer(COMMAND_DATE_TIME | READ, &mut registers)?;
+
+        DateTime::from_registers(registers, true).ok_or(RtcError::InvalidDateTime)
+    }
+
+    /// Sets the clock to `date_time`, which it then counts on from.
+    pub fn set(&mut self, date_time: &DateTime) -> Result<(), RtcError> {
+        if !date_time.is_valid() {
+            return Err(RtcError::InvalidDateTime);
+        }
+
+        let mut registers = date_time.to_registers();
+        self.transfer(COMMAND_DATE_TIME, &mut registers)
+    }
+
+    fn read_status(&mut self) -> Result<u8, RtcError> {
+        let mut status = [0];
+        self.transfer(COMMAND_STATUS | READ, &mut status)?;
+        Ok(status[0])
+    }
+
+    fn write_status(&mut self, status: u8) -> Result<(), RtcError> {
+        self.transfer(COMMAND_STATUS, &mut [status])
+    }
+
+    /// Sends `command`, then either reads into `data` or writes it depending on the command's
+    /// read bit. Interrupts are disabled so the clock pulses stay even.
+    fn transfer(&mut self, command: u8, data: &mut [u8]) -> Result<(), RtcError> {
+        interrupt_free(|_| {
+            let mut pins = CartGpio::claim(PINS).map_err(|_| RtcError::PinsInUse)?;
+
+            pins.write(SCK);
+            pins.write(SCK | CS);
+            pins.set_outputs(PINS);
+
+            // commands go most significant bit first, unlike the data
+            for bit in (0..8).rev() {
+                send_bit(&mut pins, (command >> bit) & 1);
+            }
+
+            if command & READ != 0 {
+                pins.set_outputs(SCK | CS);
+                for byte in data.iter_mut() {
+                    *byte = receive_byte(&mut pins);
+                }
+            } else {
+                for &byte in data.iter() {
+                    for bit in 0..8 {
+                        send_bit(&mut pins, (byte >> bit) & 1);
+                    }
+                }
+            }
+
+            pins.write(SCK);
+            pins.write(SCK);
+
+            Ok(())
+        })
+    }
+}
+
+fn send_bit(pins: &mut CartGpio, bit: u8) {
+    let sio = if bit != 0 { SIO } else { 0 };
+
+    // writing the low clock a few times holds it for long enough for the clock to see
+    for _ in 0..3 {
+        pins.write(sio | CS);
+    }
+    pins.write(sio | CS | SCK);
+}
+
+fn receive_byte(pins: &mut CartGpio) -> u8 {
+    let mut byte = 0;
+
+    for _ in 0..8 {
+        for _ in 0..4 {
+            pins.write(CS);
+        }
+        pins.write(CS | SCK);
+
+        let bit = (pins.read() & SIO) >> 1;
+        byte = (byte >> 1) | (bit << 7);
+    }
+
+    byte
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn converts_registers_to_and_from_bcd(_gba: &mut crate::Gba) {
+        let date_time = DateTime {
+            year: 2024,
+            month: 12,
+            day: 31,
+            weekday: 2,
+            hour: 23,
+            minute: 59,
+            second: 58,
+        };
+
+        let registers = date_time.to_registers();
+        assert_eq!(registers, [0x24, 0x12, 0x31, 0x02, 0x23, 0x59, 0x58]);
+        assert_eq!(DateTime::from_registers(registers, true), Some(date_time));
+
+        // the clock sets the PM flag even in 24 hour mode
+        let mut pm = registers;
+        pm[4] |= HOUR_PM;
+        assert_eq!(DateTime::from_registers(pm, true), Some(date_time));
+
+        // 11pm in 12 hour mode
+        let mut twelve_hour = registers;
+        twelve_hour[4] = 0x11 | HOUR_PM;
+        assert_eq!(
+            DateTime::from_registers(twelve_hour, false),
+            Some(date_time)
+        );
+
+        let mut not_bcd = registers;
+        not_bcd[5] = 0x5a;
+        assert_eq!(DateTime::from_registers(not_bcd, true), None);
+    }
+
+    #[test_case]
+    fn checking_for_a_clock_returns(_gba: &mut crate::Gba) {
+        // the test rom may or may not be run with a clock, but either way this mustn't hang
+        // and must give the pins back
+        drop(Rtc::new());
+        assert!(CartGpio::claim(PINS).is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+//! The solar sensor on Boktai cartridges, for games which care how sunny it is.
+//!
+//! The sensor is wired to all four pins of the [GPIO port](super). Reading it resets a counter
+//! and then clocks it up until the sensor's flag on pin 3 flips, which happens sooner the more
+//! light there is, so the count is a raw darkness value. [`Solar::read_level`] turns that into a
+//! level from 0 in the dark to 10 in bright sunlight using a [`Calibration`], which the game can
+//! let the player adjust, as the sensors vary.
+//!
+//! The pins are only claimed while reading, so the sensor shares them with the
+//! [real-time clock](super::rtc) on the same cartridge.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::gpio::solar::Solar;
+//!
+//! let Ok(mut solar) = Solar::new() else {
+//!     // no sensor, so the sun never shines
+//!     return;
+//! };
+//!
+//! if let Ok(level) = solar.read_level() {
+//!     agb::println!("the sun is at level {level} of 10");
+//! }
+//! # }
+//! ```
+
+use crate::interrupt::interrupt_free;
+
+use super::{CartGpio, ALL_PINS};
+
+const CLOCK: u8 = 1 << 0;
+const RESET: u8 = 1 << 1;
+const CHIP_SELECT: u8 = 1 << 2;
+const FLAG: u8 = 1 << 3;
+
+/// The brightest level [`Solar::read_level`] returns.
+pub const MAX_LEVEL: u8 = 10;
+
+/// The ways reading the sensor can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SolarError {
+    /// The GPIO pins are being used by something else.
+    PinsInUse,
+    /// There isn't a solar sensor on this cartridge.
+    NotPresent,
+}
+
+/// The raw darkness values which count as the darkest and brightest levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// The raw value at or above which the level is 0.
+    pub darkest: u8,
+    /// The raw value at or below which the level is [`MAX_LEVEL`].
+    pub brightest: u8,
+}
+
+impl Calibration {
+    /// Turns a raw darkness value into a level from 0 to [`MAX_LEVEL`].
+    #[must_use]
+    pub fn level(&self, raw: u8) -> u8 {
+        if raw >= self.darkest {
+            return 0;
+        }
+        if raw <= self.brightest || self.darkest <= self.brightest {
+            return MAX_LEVEL;
+        }
+
+        let range = u16::from(self.darkest - self.brightest);
+        let brightness = u16::from(self.darkest - raw);
+
+        ((brightness * u16::from(MAX_LEVEL)) / range) as u8
+    }
+}
+
+impl Default for Calibration {
+    /// Roughly what the sensors read indoors with the lights off and in direct sunlight.
+    fn default() -> Self {
+        Self {
+            darkest: 0xe8,
+            brightest: 0x50,
+        }
+    }
+}
+
+/// The cartridge's solar sensor, see the [module level documentation](self).
+pub struct Solar {
+    calibration: Calibration,
+}
+
+impl Solar {
+    /// Checks that there is a solar sensor on the GPIO port, using the default
+    /// [`Calibration`]. This just takes a reading, so it doesn't change anything else on the
+    /// cartridge.
+    pub fn new() -> Result<Self, SolarError> {
+        let mut solar = Self {
+            calibration: Calibration::default(),
+        };
+
+        solar.read_raw()?;
+
+        Ok(solar)
+    }
+
+    /// The calibration used by [`read_level`](Solar::read_level).
+    #[must_use]
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Sets the calibration used by [`read_level`](Solar::read_level).
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Reads how bright it is from 0 in the dark to [`MAX_LEVEL`] in bright sunlight.
+    pub fn read_level(&mut self) -> Result<u8, SolarError> {
+        let raw = self.read_raw()?;
+        Ok(self.calibration.level(raw))
+    }
+
+    /// Reads the raw darkness value, where bigger numbers are darker.
+    ///
+    /// Without a sensor, the flag either never flips, or has already flipped before the counter
+    /// is clocked at all, both of which fail with [`SolarError::NotPresent`].
+    pub fn read_raw(&mut self) -> Result<u8, SolarError> {
+        interrupt_free(|_| {
+            let mut pins = CartGpio::claim(ALL_PINS).map_err(|_| SolarError::PinsInUse)?;
+
+            pins.set_outputs(CLOCK | RESET | CHIP_SELECT);
+            pins.write(RESET);
+            pins.write(0);
+
+            if pins.read() & FLAG != 0 {
+                return Err(SolarError::NotPresent);
+            }
+
+            for count in 0..=u8::MAX {
+                pins.write(CLOCK);
+                pins.write(0);
+
+                if pins.read() & FLAG != 0 {
+                    return Ok(count);
+                }
+            }
+
+            Err(SolarError::NotPresent)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn calibration_maps_raw_values_to_levels(_gba: &mut crate::Gba) {
+        let calibration = Calibration {
+            darkest: 200,
+            brightest: 100,
+        };
+
+        assert_eq!(calibration.level(250), 0);
+        assert_eq!(calibration.level(200), 0);
+        assert_eq!(calibration.level(150), 5);
+        assert_eq!(calibration.level(101), 9);
+        assert_eq!(calibration.level(100), MAX_LEVEL);
+        assert_eq!(calibration.level(0), MAX_LEVEL);
+    }
+}
@@ -66,6 +66,11 @@ bitflags! {
 
 const BUTTON_INPUT: *mut u16 = (0x04000130) as *mut u16;
 
+/// The buttons which are pressed, with a bit set for each pressed button.
+fn read_buttons() -> u16 {
+    !unsafe { BUTTON_INPUT.read_volatile() }
+}
+
 // const BUTTON_INTERRUPT: *mut u16 = (0x04000132) as *mut u16;
 
 /// Helper to make it easy to get the current state of the GBA's buttons.
@@ -106,7 +111,11 @@ impl ButtonController {
     /// This is the preferred way to create it.
     #[must_use]
     pub fn new() -> Self {
-        let pressed = !unsafe { BUTTON_INPUT.read_volatile() };
+        #[cfg(any(test, feature = "scripted-input"))]
+        let pressed = crate::test_input::current().unwrap_or_else(read_buttons);
+        #[cfg(not(any(test, feature = "scripted-input")))]
+        let pressed = read_buttons();
+
         ButtonController {
             previous: pressed,
             current: pressed,
@@ -118,7 +127,15 @@ impl ButtonController {
     /// Calls to any method won't change until you call this.
     pub fn update(&mut self) {
         self.previous = self.current;
-        self.current = !unsafe { BUTTON_INPUT.read_volatile() };
+
+        #[cfg(any(test, feature = "scripted-input"))]
+        {
+            self.current = crate::test_input::current().unwrap_or_else(read_buttons);
+        }
+        #[cfg(not(any(test, feature = "scripted-input")))]
+        {
+            self.current = read_buttons();
+        }
     }
 
     /// Returns [Tri::Positive] if right is pressed, [Tri::Negative] if left is pressed and [Tri::Zero] if neither or both are pressed.
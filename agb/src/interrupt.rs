@@ -1,26 +1,53 @@
-use core::{cell::Cell, marker::PhantomPinned, pin::Pin};
+use core::{
+    cell::Cell,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+};
 
 use alloc::boxed::Box;
-use critical_section::{CriticalSection, RawRestoreState};
-use portable_atomic::{AtomicBool, AtomicUsize, Ordering};
+use critical_section::CriticalSection;
+use portable_atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering};
 
 use crate::{display::DISPLAY_STATUS, memory_mapped::MemoryMapped, util::SyncUnsafeCell};
 
+/// The sources of interrupts, for use with [`add_interrupt_handler`].
 #[derive(Clone, Copy)]
 pub enum Interrupt {
+    /// The display has finished drawing the visible part of the screen.
     VBlank = 0,
+    /// The display has finished drawing a line of the screen.
     HBlank = 1,
+    /// The display has reached the line set using [`VCount`].
     VCounter = 2,
+    /// Timer 0 overflowed. The sound mixer uses timer 0.
     Timer0 = 3,
+    /// Timer 1 overflowed. The sound mixer uses timer 1.
     Timer1 = 4,
+    /// Timer 2 overflowed, see [`Timer::interrupt`](crate::timer::Timer::interrupt).
     Timer2 = 5,
+    /// Timer 3 overflowed, see [`Timer::interrupt`](crate::timer::Timer::interrupt).
     Timer3 = 6,
+    /// The link cable port finished a transfer, or received data in UART mode. The serial
+    /// port only raises this interrupt if it has been asked to in its own control register,
+    /// as [`Uart::new`](crate::net::uart::Uart::new) does.
     Serial = 7,
+    /// DMA channel 0 finished a transfer.
     Dma0 = 8,
+    /// DMA channel 1 finished a transfer.
     Dma1 = 9,
+    /// DMA channel 2 finished a transfer.
     Dma2 = 10,
+    /// DMA channel 3 finished a transfer.
     Dma3 = 11,
+    /// A combination of buttons set in the key control register was pressed.
     Keypad = 12,
+    /// The cartridge was removed.
+    ///
+    /// Once the cartridge is gone nothing in ROM can run, and that includes agb's interrupt
+    /// dispatcher and any handler added with [`add_interrupt_handler`]. Use
+    /// [`set_cartridge_removed_handler`] instead, which runs a handler without going through
+    /// ROM. The interrupt only fires once, as the cartridge is pulled out, and contacts
+    /// bouncing while that happens can also make it fire while the cartridge is inserted.
     Gamepak = 13,
 }
 
@@ -28,9 +55,17 @@ impl Interrupt {
     fn enable(self) {
         let _interrupt_token = temporary_interrupt_disable();
         self.other_things_to_enable_interrupt();
-        let interrupt = self as usize;
-        let enabled = ENABLED_INTERRUPTS.get() | (1 << (interrupt as u16));
-        ENABLED_INTERRUPTS.set(enabled);
+        let bit = 1 << (self as u16);
+
+        if MASKING_NORMAL_PRIORITY.load(Ordering::Relaxed)
+            && HIGH_PRIORITY_INTERRUPTS.load(Ordering::Relaxed) & bit == 0
+        {
+            // enabling it now would let it nest inside the normal handlers which are running,
+            // so it is enabled along with the rest once they finish
+            MASKED_INTERRUPTS.fetch_or(bit, Ordering::Relaxed);
+        } else {
+            ENABLED_INTERRUPTS.set(ENABLED_INTERRUPTS.get() | bit);
+        }
     }
 
     fn disable(self) {
@@ -39,6 +74,8 @@ impl Interrupt {
         let interrupt = self as usize;
         let enabled = ENABLED_INTERRUPTS.get() & !(1 << (interrupt as u16));
         ENABLED_INTERRUPTS.set(enabled);
+        // otherwise it would be enabled again once the high priority interrupts stop preempting
+        MASKED_INTERRUPTS.fetch_and(!(1 << (interrupt as u16)), Ordering::Relaxed);
     }
 
     fn other_things_to_enable_interrupt(self) {
@@ -49,6 +86,9 @@ impl Interrupt {
             Interrupt::HBlank => {
                 DISPLAY_STATUS.set_bits(1, 1, 4);
             }
+            Interrupt::VCounter => {
+                DISPLAY_STATUS.set_bits(1, 1, 5);
+            }
             _ => {}
         }
     }
@@ -61,6 +101,9 @@ impl Interrupt {
             Interrupt::HBlank => {
                 DISPLAY_STATUS.set_bits(0, 1, 4);
             }
+            Interrupt::VCounter => {
+                DISPLAY_STATUS.set_bits(0, 1, 5);
+            }
             _ => {}
         }
     }
@@ -91,6 +134,23 @@ fn disable_interrupts() {
     INTERRUPTS_ENABLED.set(0);
 }
 
+/// Halts the CPU until `done` returns true, checking it after each interrupt.
+///
+/// Interrupts are disabled between checking `done` and halting, so an interrupt which makes it
+/// true can't arrive in between and leave the CPU halted until the next one. Halting still ends
+/// when an enabled interrupt is requested, and its handler runs once they are enabled again.
+pub(crate) fn halt_until(mut done: impl FnMut() -> bool) {
+    loop {
+        let disable = temporary_interrupt_disable();
+        if done() {
+            return;
+        }
+
+        crate::syscall::halt();
+        drop(disable);
+    }
+}
+
 struct InterruptRoot {
     next: Cell<*const InterruptInner>,
     count: Cell<i32>,
@@ -140,15 +200,108 @@ static INTERRUPT_TABLE: SyncUnsafeCell<[InterruptRoot; 14]> = SyncUnsafeCell::ne
     InterruptRoot::new(Interrupt::Gamepak),
 ]);
 
+static HIGH_PRIORITY_INTERRUPTS: AtomicU16 = AtomicU16::new(0);
+/// The enabled normal priority interrupts which are masked off in `IE` while their handlers
+/// can be preempted by high priority ones.
+static MASKED_INTERRUPTS: AtomicU16 = AtomicU16::new(0);
+/// Whether normal priority handlers are running with only the high priority interrupts enabled.
+static MASKING_NORMAL_PRIORITY: AtomicBool = AtomicBool::new(false);
+
 #[export_name = "__RUST_INTERRUPT_HANDLER"]
 extern "C" fn interrupt_handler(interrupt: u16) -> u16 {
-    for (i, root) in unsafe { &mut *INTERRUPT_TABLE.get() }.iter().enumerate() {
-        if (1 << i) & interrupt != 0 {
+    let high_priority = HIGH_PRIORITY_INTERRUPTS.load(Ordering::Relaxed);
+
+    trigger_interrupts(interrupt & high_priority);
+
+    let normal_priority = interrupt & !high_priority;
+    if normal_priority != 0 && high_priority != 0 {
+        // Let the high priority interrupts preempt the normal handlers. The assembly half of
+        // the dispatcher has already saved the registers which a nested interrupt clobbers.
+        let enabled = ENABLED_INTERRUPTS.get();
+        MASKED_INTERRUPTS.store(enabled & !high_priority, Ordering::Relaxed);
+        ENABLED_INTERRUPTS.set(enabled & high_priority);
+        MASKING_NORMAL_PRIORITY.store(true, Ordering::Relaxed);
+        INTERRUPTS_ENABLED.set(1);
+
+        trigger_interrupts(normal_priority);
+
+        INTERRUPTS_ENABLED.set(0);
+        MASKING_NORMAL_PRIORITY.store(false, Ordering::Relaxed);
+        // The handlers could have enabled or disabled interrupts, so keep whatever they did
+        // rather than going back to the mask from before.
+        let masked = MASKED_INTERRUPTS.swap(0, Ordering::Relaxed);
+        ENABLED_INTERRUPTS.set(ENABLED_INTERRUPTS.get() | masked);
+    } else {
+        trigger_interrupts(normal_priority);
+    }
+
+    interrupt
+}
+
+fn trigger_interrupts(interrupts: u16) {
+    if interrupts == 0 {
+        return;
+    }
+
+    for (i, root) in unsafe { &*INTERRUPT_TABLE.get() }.iter().enumerate() {
+        if (1 << i) & interrupts != 0 {
             root.trigger_interrupts();
         }
     }
+}
 
-    interrupt
+/// How urgently an interrupt needs to be handled, see [`set_interrupt_priority`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum InterruptPriority {
+    /// Handlers run with all interrupts disabled. This is the default.
+    #[default]
+    Normal,
+    /// Handlers can interrupt the handlers of [`Normal`](InterruptPriority::Normal) priority
+    /// interrupts.
+    High,
+}
+
+/// Sets whether the handlers for `interrupt` can preempt the handlers of other interrupts.
+///
+/// Normally every interrupt handler runs with interrupts disabled, so a slow handler (say a
+/// vblank handler doing a lot of work) delays every other interrupt until it finishes. For
+/// raster effects which change registers in the hblank interrupt this delay causes visible
+/// tearing. Marking [`Interrupt::HBlank`] as [`InterruptPriority::High`] lets its handlers run
+/// in the middle of the handlers for any normal priority interrupt.
+///
+/// High priority handlers still run with interrupts disabled, so they can't be interrupted by
+/// each other and interrupts only ever nest one level deep. Keep them short, since each
+/// nested interrupt uses some of the small interrupt stack.
+///
+/// # Safety
+///
+/// * The [`CriticalSection`] given to normal priority handlers no longer guarantees exclusive
+///   access against high priority handlers while any interrupt is high priority. If a normal
+///   priority handler shares data with a high priority one, it must access that data inside
+///   its own call to [`critical_section::with`], which does disable every interrupt.
+/// * High priority handlers must not access save media. Writes to flash and EEPROM are
+///   sequences of commands which must not be interleaved with another access.
+/// * The sound mixer's interrupt handler isn't reentrant, so don't make the mixer's timer
+///   interrupt high priority or use the mixer from within a high priority handler.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::interrupt::{set_interrupt_priority, Interrupt, InterruptPriority};
+/// // Safety: the hblank handlers only write to display registers
+/// unsafe { set_interrupt_priority(Interrupt::HBlank, InterruptPriority::High) };
+/// # }
+/// ```
+pub unsafe fn set_interrupt_priority(interrupt: Interrupt, priority: InterruptPriority) {
+    let mask = 1 << (interrupt as u16);
+
+    match priority {
+        InterruptPriority::Normal => HIGH_PRIORITY_INTERRUPTS.fetch_and(!mask, Ordering::Relaxed),
+        InterruptPriority::High => HIGH_PRIORITY_INTERRUPTS.fetch_or(mask, Ordering::Relaxed),
+    };
 }
 
 struct InterruptInner {
@@ -258,7 +411,7 @@ pub unsafe fn add_interrupt_handler(
     handler: impl Fn(CriticalSection) + Send + Sync + 'static,
 ) -> InterruptHandler {
     fn do_with_inner(interrupt: Interrupt, inner: Pin<Box<InterruptInner>>) -> InterruptHandler {
-        critical_section::with(|_| {
+        interrupt_free(|_| {
             let root = unsafe { interrupt_to_root(interrupt) };
             root.add();
             let mut c = root.next.get();
@@ -284,21 +437,178 @@ pub unsafe fn add_interrupt_handler(
     do_with_inner(interrupt, inner)
 }
 
+#[cfg(feature = "critical-section-impl")]
 struct MyCriticalSection;
+#[cfg(feature = "critical-section-impl")]
 critical_section::set_impl!(MyCriticalSection);
 
+// Disables interrupts using the master enable, restoring whatever it was set to before so that
+// nested critical sections only re-enable interrupts when the outermost one ends.
+#[cfg(feature = "critical-section-impl")]
 unsafe impl critical_section::Impl for MyCriticalSection {
-    unsafe fn acquire() -> RawRestoreState {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
         let irq = INTERRUPTS_ENABLED.get();
         INTERRUPTS_ENABLED.set(0);
         irq
     }
 
-    unsafe fn release(token: RawRestoreState) {
+    unsafe fn release(token: critical_section::RawRestoreState) {
         INTERRUPTS_ENABLED.set(token);
     }
 }
 
+extern "C" {
+    static mut agb_rs__cartridge_removed_handler: Option<extern "C" fn() -> !>;
+}
+
+/// Sets a function to call as soon as the cartridge is removed, or stops calling one if
+/// `handler` is `None`.
+///
+/// This is meant for showing a "please reinsert the cartridge" screen rather than crashing in
+/// an unpredictable way. The handler is called from the first part of the interrupt handler,
+/// which is in IWRAM, without running anything from ROM. It runs with interrupts disabled on
+/// the small interrupt stack, and must never return since whatever was running when the
+/// cartridge was removed can't carry on.
+///
+/// # Safety
+///
+/// Everything the handler runs and reads must be in RAM, since reading from ROM will return
+/// garbage once the cartridge is gone. That means:
+/// * the handler needs `#[link_section = ".iwram"]` and must not call any function which
+///   isn't also in IWRAM and isn't inlined. Check the disassembly.
+/// * it can't use data in ROM, which includes constants such as graphics and strings.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::interrupt::set_cartridge_removed_handler;
+///
+/// #[link_section = ".iwram"]
+/// extern "C" fn cartridge_removed() -> ! {
+///     unsafe {
+///         // turn off every background and make the backdrop red
+///         (0x0400_0000 as *mut u16).write_volatile(0);
+///         (0x0500_0000 as *mut u16).write_volatile(0x001f);
+///     }
+///
+///     loop {}
+/// }
+///
+/// // Safety: the handler is in iwram and doesn't touch rom
+/// unsafe { set_cartridge_removed_handler(Some(cartridge_removed)) };
+/// # }
+/// ```
+pub unsafe fn set_cartridge_removed_handler(handler: Option<extern "C" fn() -> !>) {
+    interrupt_free(|_| {
+        core::ptr::addr_of_mut!(agb_rs__cartridge_removed_handler).write_volatile(handler);
+
+        let root = interrupt_to_root(Interrupt::Gamepak);
+        if handler.is_some() {
+            Interrupt::Gamepak.enable();
+        } else if root.count.get() == 0 {
+            Interrupt::Gamepak.disable();
+        }
+    });
+}
+
+/// Given to the closure passed to [`interrupt_free`], which runs with interrupts disabled.
+///
+/// It can't be sent anywhere else or outlive the closure, so it can only be used while
+/// [`interrupt_free`] has interrupts disabled. This is a convention rather than something the
+/// type system enforces though: nothing stops code inside the closure from turning interrupts
+/// back on, or from calling [`critical_section::with`] directly without a token. agb itself
+/// takes its critical sections through [`interrupt_free`] and never re-enables interrupts
+/// inside one.
+///
+/// Don't call functions which wait for an interrupt inside the closure, such as
+/// [`VBlank::wait_for_vblank`] or [`halt_until`](crate::power::halt_until), since nothing
+/// would wake the CPU up again. In debug builds those functions panic if called with
+/// interrupts disabled.
+#[derive(Clone, Copy)]
+pub struct InterruptFree<'cs> {
+    cs: CriticalSection<'cs>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<'cs> InterruptFree<'cs> {
+    /// The critical section token for use with [`critical_section::Mutex`].
+    #[must_use]
+    pub fn critical_section(self) -> CriticalSection<'cs> {
+        self.cs
+    }
+}
+
+/// Runs `f` with every interrupt disabled, restoring the previous state afterwards.
+///
+/// Use this for sequences of register writes which mustn't be torn apart by an interrupt
+/// handler. Calls can be nested, and interrupts are only turned back on when the outermost one
+/// finishes. This is the same as [`critical_section::with`], which agb implements for the GBA
+/// unless the `critical-section-impl` feature is disabled.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::interrupt::interrupt_free;
+///
+/// let value = interrupt_free(|_token| {
+///     // no interrupt handlers can run in here
+///     42
+/// });
+/// # }
+/// ```
+pub fn interrupt_free<R>(f: impl FnOnce(InterruptFree<'_>) -> R) -> R {
+    critical_section::with(|cs| {
+        f(InterruptFree {
+            cs,
+            _not_send: PhantomData,
+        })
+    })
+}
+
+/// Panics in debug builds if interrupts are disabled, since whatever the caller is about to
+/// wait for would never happen.
+pub(crate) fn debug_assert_interrupts_enabled() {
+    debug_assert!(
+        INTERRUPTS_ENABLED.get() != 0,
+        "waiting for an interrupt while interrupts are disabled would never return"
+    );
+}
+
+/// Runs `f` with every interrupt in `mask` enabled, including any which have no handlers.
+pub(crate) fn with_interrupts_enabled<R>(mask: u16, f: impl FnOnce() -> R) -> R {
+    debug_assert_interrupts_enabled();
+
+    let newly_enabled = interrupt_free(|_| {
+        let enabled = ENABLED_INTERRUPTS.get() | MASKED_INTERRUPTS.load(Ordering::Relaxed);
+        let newly_enabled = mask & !enabled;
+
+        for (i, root) in unsafe { &*INTERRUPT_TABLE.get() }.iter().enumerate() {
+            if newly_enabled & (1 << i) != 0 {
+                root.interrupt.enable();
+            }
+        }
+
+        newly_enabled
+    });
+
+    let result = f();
+
+    interrupt_free(|_| {
+        for (i, root) in unsafe { &*INTERRUPT_TABLE.get() }.iter().enumerate() {
+            // a handler could have been added for it by `f`, in which case it needs to stay
+            if newly_enabled & (1 << i) != 0 && root.count.get() == 0 {
+                root.interrupt.disable();
+            }
+        }
+    });
+
+    result
+}
+
 static NUM_VBLANKS: AtomicUsize = AtomicUsize::new(0); // overflows after 2.27 years
 static HAS_CREATED_INTERRUPT: AtomicBool = AtomicBool::new(false);
 
@@ -328,16 +638,91 @@ impl VBlank {
     }
     /// Pauses CPU until vblank interrupt is triggered where code execution is
     /// resumed.
+    ///
+    /// The CPU is halted while waiting, which saves battery on real hardware. Other interrupts,
+    /// such as the sound mixer's timer, are still handled while waiting but don't end the wait
+    /// early.
     pub fn wait_for_vblank(&self) {
+        crate::display::cpu_usage::frame_finished(NUM_VBLANKS.load(Ordering::SeqCst));
+
+        debug_assert_interrupts_enabled();
+
         let last_waited_number = self.last_waited_number.get();
         self.last_waited_number
             .set(NUM_VBLANKS.load(Ordering::SeqCst) + 1);
 
-        if last_waited_number < NUM_VBLANKS.load(Ordering::SeqCst) {
-            return;
+        if last_waited_number >= NUM_VBLANKS.load(Ordering::SeqCst) {
+            crate::syscall::wait_for_vblank();
         }
 
-        crate::syscall::wait_for_vblank();
+        crate::display::cpu_usage::frame_started(NUM_VBLANKS.load(Ordering::SeqCst));
+    }
+
+    /// The number of vblanks since the first [`VBlank`] was created.
+    pub(crate) fn count(&self) -> usize {
+        vblank_count()
+    }
+}
+
+/// The number of vblanks since the first [`VBlank`] was created, or 0 if one hasn't been.
+pub(crate) fn vblank_count() -> usize {
+    NUM_VBLANKS.load(Ordering::SeqCst)
+}
+
+/// Fires an interrupt when the display starts drawing a particular scanline.
+///
+/// This is much cheaper than counting lines in an [`Interrupt::HBlank`] handler if you only
+/// need to change something a few times each frame, such as splitting the screen into areas
+/// which scroll separately. Lines 0 to 159 are visible and 160 to 227 are in vblank.
+///
+/// The match line can be changed at any time, including from within the handler itself, so a
+/// single handler can step through several splits each frame.
+#[non_exhaustive]
+pub struct VCount;
+
+impl VCount {
+    /// Sets the scanline at which the [`Interrupt::VCounter`] interrupt fires.
+    pub fn set_line(line: u8) {
+        DISPLAY_STATUS.set_bits(line.into(), 8, 8);
+    }
+
+    /// The scanline at which the [`Interrupt::VCounter`] interrupt fires.
+    #[must_use]
+    pub fn line() -> u8 {
+        (DISPLAY_STATUS.get() >> 8) as u8
+    }
+
+    #[must_use]
+    /// Sets the match line to `line` and adds a handler for the [`Interrupt::VCounter`]
+    /// interrupt. The interrupt stays enabled as long as the returned value is alive.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`add_interrupt_handler`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// # fn foo() {
+    /// use agb::interrupt::VCount;
+    ///
+    /// // Safety: doesn't allocate
+    /// let _split = unsafe {
+    ///     VCount::add_handler(80, |_| {
+    ///         // alternate between firing half way down the screen and at the top
+    ///         VCount::set_line(if VCount::line() == 80 { 0 } else { 80 });
+    ///     })
+    /// };
+    /// # }
+    /// ```
+    pub unsafe fn add_handler(
+        line: u8,
+        handler: impl Fn(CriticalSection) + Send + Sync + 'static,
+    ) -> InterruptHandler {
+        Self::set_line(line);
+        unsafe { add_interrupt_handler(Interrupt::VCounter, handler) }
     }
 }
 
@@ -370,6 +755,25 @@ mod tests {
 
     use super::*;
 
+    #[test_case]
+    fn interrupt_free_nests(_gba: &mut crate::Gba) {
+        let before = INTERRUPTS_ENABLED.get();
+
+        interrupt_free(|_| {
+            assert_eq!(INTERRUPTS_ENABLED.get(), 0);
+
+            interrupt_free(|_| assert_eq!(INTERRUPTS_ENABLED.get(), 0));
+
+            assert_eq!(
+                INTERRUPTS_ENABLED.get(),
+                0,
+                "inner section turned interrupts on"
+            );
+        });
+
+        assert_eq!(INTERRUPTS_ENABLED.get(), before);
+    }
+
     #[test_case]
     fn test_interrupt_table_length(_gba: &mut crate::Gba) {
         assert_eq!(
@@ -386,6 +790,143 @@ mod tests {
         });
     }
 
+    #[test_case]
+    fn high_priority_interrupts_preempt_normal_ones(_gba: &mut crate::Gba) {
+        const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+        const NO_LINE: u16 = u16::MAX;
+
+        static LAST_LINE: AtomicU16 = AtomicU16::new(NO_LINE);
+        static WORST_GAP: AtomicU16 = AtomicU16::new(0);
+
+        // The worst case latency for the hblank handler, measured as the most scanlines
+        // between two calls to it while a slow vblank handler is running.
+        fn worst_hblank_latency() -> u16 {
+            LAST_LINE.store(NO_LINE, Ordering::SeqCst);
+            WORST_GAP.store(0, Ordering::SeqCst);
+
+            let _hblank = unsafe {
+                add_interrupt_handler(Interrupt::HBlank, |_| {
+                    let line = VCOUNT.get();
+                    let last_line = LAST_LINE.swap(line, Ordering::SeqCst);
+
+                    if last_line != NO_LINE {
+                        let gap = (line + 228 - last_line) % 228;
+                        WORST_GAP.fetch_max(gap, Ordering::SeqCst);
+                    }
+                })
+            };
+
+            let _slow_vblank = unsafe {
+                add_interrupt_handler(Interrupt::VBlank, |_| {
+                    let start = VCOUNT.get();
+                    while VCOUNT.get() < start + 10 {}
+                })
+            };
+
+            let vblank = VBlank::get();
+            for _ in 0..4 {
+                vblank.wait_for_vblank();
+            }
+
+            WORST_GAP.load(Ordering::SeqCst)
+        }
+
+        let without_nesting = worst_hblank_latency();
+
+        unsafe { set_interrupt_priority(Interrupt::HBlank, InterruptPriority::High) };
+        let with_nesting = worst_hblank_latency();
+        unsafe { set_interrupt_priority(Interrupt::HBlank, InterruptPriority::Normal) };
+
+        assert!(
+            without_nesting >= 10,
+            "expected the vblank handler to delay hblank, worst latency {without_nesting} lines"
+        );
+        assert!(
+            with_nesting <= 1,
+            "expected hblank to preempt the vblank handler, worst latency {with_nesting} lines"
+        );
+    }
+
+    #[test_case]
+    fn enabling_in_a_high_priority_handler_keeps_normal_interrupts_masked(_gba: &mut crate::Gba) {
+        const DMA3: u16 = 1 << Interrupt::Dma3 as u16;
+
+        static IN_VBLANK_HANDLER: AtomicBool = AtomicBool::new(false);
+        static ENABLED_WHILE_NESTED: AtomicU16 = AtomicU16::new(0);
+        static NESTED: AtomicBool = AtomicBool::new(false);
+
+        unsafe { set_interrupt_priority(Interrupt::HBlank, InterruptPriority::High) };
+
+        let _hblank = unsafe {
+            add_interrupt_handler(Interrupt::HBlank, |_| {
+                if IN_VBLANK_HANDLER.load(Ordering::SeqCst) {
+                    // DMA 3 has normal priority, so mustn't be able to interrupt the vblank
+                    // handler this is nested inside
+                    Interrupt::Dma3.enable();
+                    ENABLED_WHILE_NESTED.fetch_or(ENABLED_INTERRUPTS.get(), Ordering::SeqCst);
+                    NESTED.store(true, Ordering::SeqCst);
+                }
+            })
+        };
+
+        let _slow_vblank = unsafe {
+            add_interrupt_handler(Interrupt::VBlank, |_| {
+                const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+
+                IN_VBLANK_HANDLER.store(true, Ordering::SeqCst);
+                let start = VCOUNT.get();
+                while VCOUNT.get() < start + 4 {}
+                IN_VBLANK_HANDLER.store(false, Ordering::SeqCst);
+            })
+        };
+
+        let vblank = VBlank::get();
+        vblank.wait_for_vblank();
+        vblank.wait_for_vblank();
+
+        unsafe { set_interrupt_priority(Interrupt::HBlank, InterruptPriority::Normal) };
+
+        assert!(
+            NESTED.load(Ordering::SeqCst),
+            "hblank should have preempted vblank"
+        );
+        assert_eq!(ENABLED_WHILE_NESTED.load(Ordering::SeqCst) & DMA3, 0);
+        assert_ne!(
+            ENABLED_INTERRUPTS.get() & DMA3,
+            0,
+            "DMA 3 should be enabled once the vblank handler finished"
+        );
+
+        Interrupt::Dma3.disable();
+    }
+
+    #[test_case]
+    fn vcount_fires_on_the_match_line(_gba: &mut crate::Gba) {
+        const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+
+        static LINES: [AtomicU16; 2] = [AtomicU16::new(0), AtomicU16::new(0)];
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let _vcount = unsafe {
+            VCount::add_handler(40, |_| {
+                let call = CALLS.fetch_add(1, Ordering::SeqCst);
+                if let Some(line) = LINES.get(call) {
+                    line.store(VCOUNT.get(), Ordering::SeqCst);
+                }
+
+                VCount::set_line(120);
+            })
+        };
+
+        let vblank = VBlank::get();
+        while CALLS.load(Ordering::SeqCst) < 2 {
+            vblank.wait_for_vblank();
+        }
+
+        assert_eq!(LINES[0].load(Ordering::SeqCst), 40);
+        assert_eq!(LINES[1].load(Ordering::SeqCst), 120);
+    }
+
     #[test_case]
     fn atomic_check(_gba: &mut crate::Gba) {
         static ATOMIC: AtomicU8 = AtomicU8::new(8);
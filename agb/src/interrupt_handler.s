@@ -1,5 +1,5 @@
 
-@ An interrupt handler that simply acknowledges all interrupts
+@ Acknowledges the interrupts and calls into rust to handle them
     .arm
     .global InterruptHandler
     .section .iwram.interrupt_handler, "ax", %progbits
@@ -15,13 +15,32 @@ InterruptHandler:
     ldrh r3, [r2, #2] @ load 16 bit interrupt request to r3
     and r0, r1, r3 @ interrupts both enabled and requested
 
+    @ acknowledge the interrupts now, so any which happen again while the handlers are running aren't lost
+    strh r0, [r2, #2] @ store to interrupt request
+
+    @ once the cartridge is removed nothing in rom can run, including the rust dispatcher, so jump
+    @ straight to the cartridge removed handler if one has been set. It never returns.
+    tst r0, #0x2000
+    beq 1f
+    ldr r1, =agb_rs__cartridge_removed_handler
+    ldr r1, [r1]
+    cmp r1, #0
+    bxne r1
+1:
+
     ldr r1, [sp, #20]
     ldr r3, =agb_rs__program_counter
     str r1, [r3]
 
-    @ change to system mode
+    @ save the saved program status and return address, as a nested interrupt would overwrite them
+    mrs r3, spsr
+    push {{r3, lr}}
+
+    @ change to system mode, unmasking interrupts in the cpsr. They are still disabled by the master
+    @ enable which the rust handler only turns back on if there are high priority interrupts
     mrs r1, cpsr
     orr r1, r1, #0xD
+    bic r1, r1, #0x80
     msr cpsr_c, r1
 
     @ call the rust interrupt handler with r0 set to the triggered interrupts
@@ -31,16 +50,18 @@ InterruptHandler:
     bx r1
     pop {{r2, lr}}
 
-    @ change back to interrupt mode
+    @ change back to interrupt mode, masking interrupts again
     mrs r1, cpsr
     bic r1, r1, #0xD
+    orr r1, r1, #0x80
     msr cpsr_c, r1
 
+    pop {{r3, lr}}
+    msr spsr_fc, r3
+
     mov r1, #1
     strh r1, [r2, #8]
 
-    strh r0, [r2, #2] @ store to interrupt request
-
     ldr r2, =0x03007FF8 @ load bios interrupt request location
     ldrh r1, [r2] @ load bios interrupt requests
     orr r1, r1, r0 @ or with enabled and requested interrupts
@@ -55,3 +76,9 @@ InterruptHandler:
     .balign 4
 agb_rs__program_counter:
     .word 0
+
+.section .iwram.cartridge_removed_handler
+    .global agb_rs__cartridge_removed_handler
+    .balign 4
+agb_rs__cartridge_removed_handler:
+    .word 0
@@ -125,6 +125,9 @@ pub use agb_image_converter::include_font as include_font_inner;
 #[doc(hidden)]
 pub use agb_image_converter::include_colours_inner;
 
+#[doc(hidden)]
+pub use assert::assert_failed as __assert_failed;
+
 #[macro_export]
 macro_rules! include_font {
     ($font_path: literal, $font_size: literal) => {{
@@ -152,26 +155,101 @@ macro_rules! include_font {
 /// ```
 pub use agb_macros::entry;
 
+/// Declares a benchmark, which the [test runner](crate::test_runner) times like a test.
+///
+/// The function takes a [`Bencher`](crate::test_runner::Bencher), optionally after the
+/// [`Gba`] if it needs hardware for setting up, and calls
+/// [`iter`](crate::test_runner::Bencher::iter) with the code to time. The runner prints the
+/// median number of cycles it took on a line starting `bench:`, and `mgba-test-runner` can
+/// compare these against a baseline file to catch code getting slower. Benchmarks are only
+/// meaningful in release builds.
+///
+/// ```rust,ignore
+/// #[agb::bench]
+/// fn sum_of_squares(b: &mut agb::test_runner::Bencher) {
+///     b.iter(|| (0..1000u32).map(|x| x * x).sum::<u32>());
+/// }
+/// ```
+///
+/// Run `mgba-test-runner --help` to see how to use a baseline.
+#[cfg(any(test, feature = "testing"))]
+pub use agb_macros::bench;
+
+/// Declares a test like `#[test_case]`, which can have a different timeout, be ignored or be
+/// forbidden from allocating.
+///
+/// The [test runner](crate::test_runner) stops a test which takes more than
+/// [`DEFAULT_TIMEOUT_FRAMES`](crate::test_runner::DEFAULT_TIMEOUT_FRAMES) frames, reports it as
+/// failed and carries on with the next test. Tests which legitimately take longer, such as
+/// ones which erase save media, can allow themselves more frames.
+///
+/// ```rust,ignore
+/// #[agb::test(timeout_frames = 3600)]
+/// fn erases_everything(gba: &mut agb::Gba) {
+///     // ...
+/// }
+/// ```
+///
+/// Ignored tests are skipped unless `mgba-test-runner` is given `--include-ignored`, or
+/// `--ignored` to run only them. Either pass `ignore` as an argument or add `#[ignore]`.
+///
+/// ```rust,ignore
+/// #[agb::test]
+/// #[ignore]
+/// fn only_works_on_hardware(gba: &mut agb::Gba) {
+///     // ...
+/// }
+/// ```
+///
+/// A test marked `no_alloc` fails if it allocates anything, which catches allocations
+/// sneaking into code which should be allocation free. Use
+/// [`assert_no_allocations`](crate::test_runner::assert_no_allocations) to check only part of
+/// a test.
+///
+/// ```rust,ignore
+/// #[agb::test(no_alloc)]
+/// fn collision_checks_dont_allocate(_gba: &mut agb::Gba) {
+///     // ...
+/// }
+/// ```
+#[cfg(any(test, feature = "testing"))]
+pub use agb_macros::test;
+
 pub use agb_sound_converter::include_wav;
 
 extern crate alloc;
 mod agb_alloc;
 
 mod agbabi;
+mod assert;
 #[cfg(feature = "backtrace")]
-mod backtrace;
+pub mod backtrace;
+#[cfg(any(test, feature = "testing"))]
+mod bench;
 mod bitarray;
+pub mod collections;
+pub mod debug_overlay;
+pub mod delay;
 /// Implements everything relating to things that are displayed on screen.
 pub mod display;
 /// Provides access to the GBA's direct memory access (DMA) which is used for advanced effects
 pub mod dma;
+pub mod game_loop;
+/// Extra hardware on cartridges, such as real-time clocks.
+pub mod gpio;
 /// Button inputs to the system.
 pub mod input;
 /// Interacting with the GBA interrupts
 pub mod interrupt;
+pub mod math;
+pub mod mem;
 mod memory_mapped;
 /// Implements logging to the mgba emulator.
 pub mod mgba;
+/// Communicating with other devices over the link cable.
+pub mod net;
+pub mod power;
+pub mod profiler;
 #[doc(inline)]
 pub use agb_fixnum as fixnum;
 /// Contains an implementation of a hashmap which suits the gameboy advance's hardware.
@@ -180,7 +258,10 @@ pub use agb_hashmap as hash_map;
 mod panics_render;
 /// Simple random number generator
 pub mod rng;
+pub mod rumble;
 pub mod save;
+pub mod scheduler;
+pub mod scratch_arena;
 mod single;
 /// Implements sound output.
 pub mod sound;
@@ -188,9 +269,20 @@ pub mod sound;
 mod sync;
 /// System BIOS calls / syscalls.
 pub mod syscall;
+#[cfg(any(test, feature = "scripted-input"))]
+mod test_input;
+#[cfg(any(test, feature = "testing"))]
+mod test_memory;
+#[cfg(any(test, feature = "testing"))]
+mod test_options;
+#[cfg(any(test, feature = "testing"))]
+mod test_watchdog;
+/// The tilt sensor found on some cartridges.
+pub mod tilt;
 /// Interactions with the internal timers
 pub mod timer;
 pub(crate) mod util;
+pub mod watchdog;
 
 mod no_game;
 
@@ -206,17 +298,21 @@ pub mod external {
     pub use portable_atomic;
 }
 
-pub use {agb_alloc::ExternalAllocator, agb_alloc::InternalAllocator};
+pub use {
+    agb_alloc::ExternalAllocator, agb_alloc::InternalAllocator, agb_alloc::PreferInternalAllocator,
+};
 
 #[cfg(not(any(test, feature = "testing")))]
 #[panic_handler]
 #[allow(unused_must_use)]
 fn panic_implementation(info: &core::panic::PanicInfo) -> ! {
     avoid_double_panic(info);
+    rumble::stop_motor();
 
     if let Some(mut mgba) = mgba::Mgba::new() {
         let _ = mgba.print(format_args!("{info}"), mgba::DebugLevel::Fatal);
     }
+    mem::print_out_of_memory_diagnostics();
 
     #[allow(clippy::empty_loop)]
     loop {}
@@ -326,14 +422,52 @@ impl Gba {
 ///
 /// You can run the tests using `cargo test`, but it will work better through `mgba-test-runner` by
 /// running something along the lines of `CARGO_TARGET_THUMBV4T_NONE_EABI_RUNNER=mgba-test-runner cargo test`.
+///
+/// A test which takes more than [`DEFAULT_TIMEOUT_FRAMES`](test_runner::DEFAULT_TIMEOUT_FRAMES)
+/// frames is reported as timed out, and the game is reset to carry on with the next test. Use
+/// [`#[agb::test(timeout_frames = ...)]`](crate::test) instead of `#[test_case]` for tests which
+/// need longer.
+///
+/// Like the built in test harness, `mgba-test-runner` only runs the tests with the filter it is
+/// given in their names, so `cargo test save::flash` runs the tests in modules called
+/// `save::flash`. Tests declared with [`#[agb::test]`](crate::test) can be ignored.
+///
+/// With the `scripted-input` feature, tests can press buttons with `with_scripted_input`, which
+/// plays a list of the buttons held on each frame to the game's
+/// [`ButtonController`](input::ButtonController)s.
+///
+/// After each test, `mgba-test-runner` shows the most heap it had allocated at once, how many
+/// allocations it made and the most stack it used. The stack is measured by filling the unused
+/// stack with a pattern before the test and checking how much of it was overwritten.
 pub mod test_runner {
     use util::SyncUnsafeCell;
 
     use super::*;
 
+    pub use crate::bench::{Bench, Bencher};
+    use crate::{test_memory::MemoryMeasurement, test_options::TestOptions};
+
+    /// How many frames a test can take before it is stopped, unless it sets its own timeout
+    /// with [`#[agb::test]`](crate::test).
+    pub const DEFAULT_TIMEOUT_FRAMES: u32 = 600;
+
     #[doc(hidden)]
     pub trait Testable {
         fn run(&self, gba: &mut Gba);
+
+        fn name(&self) -> &str;
+
+        fn timeout_frames(&self) -> u32 {
+            DEFAULT_TIMEOUT_FRAMES
+        }
+
+        fn is_ignored(&self) -> bool {
+            false
+        }
+
+        fn forbids_allocation(&self) -> bool {
+            false
+        }
     }
 
     impl<T> Testable for T
@@ -341,24 +475,180 @@ pub mod test_runner {
         T: Fn(&mut Gba),
     {
         fn run(&self, gba: &mut Gba) {
-            let mut mgba = mgba::Mgba::new().unwrap();
-            mgba.print(
-                format_args!("{}...", core::any::type_name::<T>()),
-                mgba::DebugLevel::Info,
-            )
-            .unwrap();
-            mgba::test_runner_measure_cycles();
             self(gba);
-            mgba::test_runner_measure_cycles();
+        }
 
-            mgba.print(format_args!("[ok]"), mgba::DebugLevel::Info)
-                .unwrap();
+        fn name(&self) -> &str {
+            core::any::type_name::<T>()
+        }
+    }
+
+    /// A test with its own timeout, which is ignored or which mustn't allocate, made by
+    /// [`#[agb::test]`](crate::test).
+    pub struct Test {
+        name: &'static str,
+        f: fn(&mut Gba),
+        timeout_frames: u32,
+        ignored: bool,
+        no_alloc: bool,
+    }
+
+    impl Test {
+        /// A test called `name` which runs `f`, and is stopped if it takes more than
+        /// `timeout_frames` frames. Usually made by [`#[agb::test]`](crate::test) rather than by
+        /// hand.
+        #[must_use]
+        pub const fn new(name: &'static str, f: fn(&mut Gba), timeout_frames: u32) -> Self {
+            Self {
+                name,
+                f,
+                timeout_frames,
+                ignored: false,
+                no_alloc: false,
+            }
+        }
+
+        /// Skips the test unless `mgba-test-runner` is given `--include-ignored` or `--ignored`.
+        #[must_use]
+        pub const fn ignore(mut self) -> Self {
+            self.ignored = true;
+            self
+        }
+
+        /// Fails the test if it allocates, or reallocates, anything on either heap.
+        #[must_use]
+        pub const fn no_alloc(mut self) -> Self {
+            self.no_alloc = true;
+            self
+        }
+    }
+
+    impl Testable for Test {
+        fn run(&self, gba: &mut Gba) {
+            (self.f)(gba);
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn timeout_frames(&self) -> u32 {
+            self.timeout_frames
+        }
+
+        fn is_ignored(&self) -> bool {
+            self.ignored
+        }
+
+        fn forbids_allocation(&self) -> bool {
+            self.no_alloc
         }
     }
 
+    fn run_test(test: &dyn Testable, gba: &mut Gba) {
+        let mut mgba = mgba::Mgba::new().unwrap();
+        mgba.print(format_args!("{}...", test.name()), mgba::DebugLevel::Info)
+            .unwrap();
+
+        let measurement = MemoryMeasurement::start();
+        mgba::test_runner_measure_cycles();
+        test.run(gba);
+        mgba::test_runner_measure_cycles();
+        let usage = measurement.finish();
+
+        assert!(
+            !test.forbids_allocation() || usage.allocations == 0,
+            "{} mustn't allocate, but made {} allocations",
+            test.name(),
+            usage.allocations
+        );
+
+        // read by the test runner, which shows it after the result
+        mgba.print(
+            format_args!(
+                "mem: heap={} allocations={} stack={}",
+                usage.peak_heap_bytes, usage.allocations, usage.peak_stack_bytes
+            ),
+            mgba::DebugLevel::Info,
+        )
+        .unwrap();
+        mgba.print(format_args!("[ok]"), mgba::DebugLevel::Info)
+            .unwrap();
+    }
+
+    /// Runs `f`, panicking if it allocates or reallocates anything on either heap. Use this
+    /// to check that code which runs every frame doesn't allocate, or mark a whole test with
+    /// [`#[agb::test(no_alloc)]`](crate::test).
+    ///
+    /// ```rust,ignore
+    /// #[test_case]
+    /// fn rendering_doesnt_allocate(gba: &mut agb::Gba) {
+    ///     let mut renderer = Renderer::new(gba);
+    ///     agb::test_runner::assert_no_allocations(|| renderer.render_frame());
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn assert_no_allocations<T>(f: impl FnOnce() -> T) -> T {
+        let before = agb_alloc::total_allocations();
+        let result = f();
+        let allocations = agb_alloc::total_allocations() - before;
+
+        assert!(
+            allocations == 0,
+            "expected no allocations, but made {allocations}"
+        );
+
+        result
+    }
+
+    /// Runs `f` with [`ButtonController`](crate::input::ButtonController)s reading the buttons
+    /// from `frames` rather than the hardware, returning what `f` returns. The script starts on
+    /// its first frame and moves on to the next one at each vblank, so the game sees the same
+    /// buttons on the same frames every time it runs, however many controllers it updates.
+    /// After the last frame no buttons are pressed.
+    ///
+    /// The game code itself doesn't need changing, so whole scenarios can be played out and
+    /// checked in tests. This needs agb's `scripted-input` feature, which is best enabled only
+    /// for tests through a feature of the game:
+    ///
+    /// ```toml
+    /// [features]
+    /// scripted-input = ["agb/scripted-input"]
+    /// ```
+    ///
+    /// ```rust,ignore
+    /// #[test_case]
+    /// fn jumping_onto_the_ledge(gba: &mut agb::Gba) {
+    ///     use agb::input::Button;
+    ///
+    ///     static INPUT: [Button; 300] = {
+    ///         let mut input = [Button::RIGHT; 300];
+    ///         input[120] = Button::RIGHT.union(Button::A);
+    ///         input
+    ///     };
+    ///
+    ///     let game = agb::test_runner::with_scripted_input(&INPUT, || {
+    ///         let mut game = Game::new(gba);
+    ///         let vblank = agb::interrupt::VBlank::get();
+    ///         for _ in 0..INPUT.len() {
+    ///             game.frame();
+    ///             vblank.wait_for_vblank();
+    ///         }
+    ///         game
+    ///     });
+    ///
+    ///     assert!(game.player_is_on_ledge());
+    /// }
+    /// ```
+    #[cfg(any(test, feature = "scripted-input"))]
+    pub fn with_scripted_input<T>(frames: &'static [input::Button], f: impl FnOnce() -> T) -> T {
+        crate::test_input::play(frames, f)
+    }
+
     #[panic_handler]
     fn panic_implementation(info: &core::panic::PanicInfo) -> ! {
         avoid_double_panic(info);
+        rumble::stop_motor();
 
         #[cfg(feature = "backtrace")]
         let frames = backtrace::unwind_exception();
@@ -366,6 +656,7 @@ pub mod test_runner {
         if let Some(mut mgba) = mgba::Mgba::new() {
             let _ = mgba.print(format_args!("[failed]"), mgba::DebugLevel::Error);
         }
+        mem::print_out_of_memory_diagnostics();
 
         #[cfg(feature = "backtrace")]
         crate::panics_render::render_backtrace(&frames, info);
@@ -381,18 +672,77 @@ pub mod test_runner {
     #[doc(hidden)]
     pub fn test_runner(tests: &[&dyn Testable]) {
         let mut mgba = mgba::Mgba::new().unwrap();
-        mgba.print(
-            format_args!("Running {} tests", tests.len()),
-            mgba::DebugLevel::Info,
-        )
-        .unwrap();
+
+        let options = TestOptions::read();
+        let matching = tests
+            .iter()
+            .filter(|test| options.matches(test.name()))
+            .count();
+        let filtered_out = tests.len() - matching;
+
+        let resume_index = test_watchdog::take_resume_index();
+        if resume_index == 0 {
+            mgba.print(
+                format_args!("Running {matching} tests"),
+                mgba::DebugLevel::Info,
+            )
+            .unwrap();
+        } else {
+            mgba.print(
+                format_args!(
+                    "Carrying on from test {} of {} after a timeout",
+                    resume_index + 1,
+                    tests.len()
+                ),
+                mgba::DebugLevel::Info,
+            )
+            .unwrap();
+        }
 
         let gba = unsafe { &mut *TEST_GBA.get() }.as_mut().unwrap();
+        let _watchdog = test_watchdog::install();
+
+        for (index, test) in tests.iter().enumerate().skip(resume_index) {
+            if !options.matches(test.name()) {
+                continue;
+            }
+
+            if !options.runs(test.is_ignored()) {
+                mgba.print(format_args!("{}...", test.name()), mgba::DebugLevel::Info)
+                    .unwrap();
+                mgba.print(format_args!("[ignored]"), mgba::DebugLevel::Info)
+                    .unwrap();
+                continue;
+            }
 
-        for test in tests {
-            test.run(gba);
+            let timeout_frames = test.timeout_frames();
+            if timeout_frames != DEFAULT_TIMEOUT_FRAMES {
+                // lets mgba-test-runner allow this test longer before giving up on the run
+                mgba.print(
+                    format_args!("timeout_frames:{timeout_frames}"),
+                    mgba::DebugLevel::Info,
+                )
+                .unwrap();
+            }
+
+            test_watchdog::start_test(index, timeout_frames);
+            run_test(*test, gba);
+            test_watchdog::finish_test();
         }
 
+        let skipped = tests
+            .iter()
+            .filter(|test| options.matches(test.name()) && !options.runs(test.is_ignored()))
+            .count();
+        mgba.print(
+            format_args!(
+                "{} run; {skipped} ignored; {filtered_out} filtered out",
+                matching - skipped
+            ),
+            mgba::DebugLevel::Info,
+        )
+        .unwrap();
+
         mgba.print(
             format_args!("Tests finished successfully"),
             mgba::DebugLevel::Info,
@@ -0,0 +1,140 @@
+//! Square roots and vector lengths using integers.
+//!
+//! The GBA has no floating point hardware, so lengths and directions are worked out with
+//! integer square roots. [`sqrt_u32`] uses the BIOS, which is the quickest way for numbers
+//! which fit in 32 bits, and [`isqrt_u64`] handles bigger numbers in software.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::{fixnum::Vector2D, math};
+//!
+//! let to_player = Vector2D::new(30, -40);
+//! assert_eq!(math::magnitude_vec2(to_player), 50);
+//!
+//! // a step of one pixel towards the player
+//! let step = math::normalize_vec2_fixed(to_player);
+//! # }
+//! ```
+
+use agb_fixnum::{Num, Vector2D};
+
+/// The square root of `n`, rounded down, calculated by the BIOS.
+#[must_use]
+pub fn sqrt_u32(n: u32) -> u32 {
+    // the BIOS treats the argument as unsigned and returns a 16 bit result
+    crate::syscall::sqrt(n as i32) as u32
+}
+
+/// The square root of `n`, rounded down, using Newton's method. This works for numbers too big
+/// for [`sqrt_u32`], but is slower since the GBA has to divide 64 bit numbers in software.
+#[must_use]
+pub fn isqrt_u64(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+
+    // start above the root, so each step moves down towards it
+    let bits = u64::BITS - n.leading_zeros();
+    let mut x = 1 << bits.div_ceil(2);
+
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+/// The length of `v`, rounded down.
+#[must_use]
+pub fn magnitude_vec2(v: Vector2D<i32>) -> u32 {
+    if v.x == 0 {
+        return v.y.unsigned_abs();
+    }
+    if v.y == 0 {
+        return v.x.unsigned_abs();
+    }
+
+    let x = u64::from(v.x.unsigned_abs());
+    let y = u64::from(v.y.unsigned_abs());
+    let squared = x * x + y * y;
+
+    match u32::try_from(squared) {
+        Ok(squared) => sqrt_u32(squared),
+        Err(_) => isqrt_u64(squared) as u32,
+    }
+}
+
+/// A vector of length 1 pointing the same way as `v`, in 20.12 fixed point, or zero if `v` is
+/// zero.
+///
+/// The length is worked out to a fraction of a pixel, so this is accurate for short vectors as
+/// well as long ones.
+#[must_use]
+pub fn normalize_vec2_fixed(v: Vector2D<i32>) -> Vector2D<Num<i32, 12>> {
+    if v.x == 0 && v.y == 0 {
+        return Vector2D::new(Num::new(0), Num::new(0));
+    }
+
+    // scaling the vector down doesn't change its direction, and keeps the squares small
+    // enough to calculate the length with 12 fractional bits
+    let largest = v.x.unsigned_abs().max(v.y.unsigned_abs());
+    let shift = (u32::BITS - largest.leading_zeros()).saturating_sub(15);
+    let x = i64::from(v.x >> shift);
+    let y = i64::from(v.y >> shift);
+
+    let length = isqrt_u64(((x * x + y * y) as u64) << 24) as i64;
+
+    Vector2D::new(
+        Num::from_raw(((x << 24) / length) as i32),
+        Num::from_raw(((y << 24) / length) as i32),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn square_roots_round_down(_gba: &mut crate::Gba) {
+        for n in [0, 1, 2, 3, 4, 15, 16, 17, 1 << 20, u32::MAX] {
+            let root = sqrt_u32(n);
+            assert!(u64::from(root).pow(2) <= u64::from(n), "sqrt_u32({n})");
+            assert!(u64::from(root + 1).pow(2) > u64::from(n), "sqrt_u32({n})");
+
+            assert_eq!(isqrt_u64(u64::from(n)), u64::from(root), "isqrt_u64({n})");
+        }
+
+        assert_eq!(isqrt_u64(u64::MAX), u64::from(u32::MAX));
+        assert_eq!(isqrt_u64((1 << 40) + 1), 1 << 20);
+    }
+
+    #[test_case]
+    fn vector_lengths_and_directions(_gba: &mut crate::Gba) {
+        assert_eq!(magnitude_vec2(Vector2D::new(0, -7)), 7);
+        assert_eq!(magnitude_vec2(Vector2D::new(i32::MIN, 0)), 1 << 31);
+        assert_eq!(magnitude_vec2(Vector2D::new(-3, 4)), 5);
+        assert_eq!(
+            magnitude_vec2(Vector2D::new(i32::MAX, i32::MAX)),
+            3_037_000_498
+        );
+
+        let unit = normalize_vec2_fixed(Vector2D::new(-3, 4));
+        assert_eq!(unit, Vector2D::new(Num::new(-3) / 5, Num::new(4) / 5));
+
+        // would be (1, 1) if the length was rounded to a whole number first
+        let diagonal = normalize_vec2_fixed(Vector2D::new(1, 1));
+        assert_eq!(diagonal.x, Num::from_raw(2896));
+
+        let huge = normalize_vec2_fixed(Vector2D::new(i32::MIN, 0));
+        assert_eq!(huge, Vector2D::new(Num::new(-1), Num::new(0)));
+
+        assert_eq!(
+            normalize_vec2_fixed(Vector2D::new(0, 0)),
+            Vector2D::new(Num::new(0), Num::new(0))
+        );
+    }
+}
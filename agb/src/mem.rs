@@ -0,0 +1,129 @@
+//! Information about how much of the heap is being used.
+//!
+//! Running out of memory panics, and by the time that happens it is too late to find out how
+//! close to the limit the game has been running. [`heap_stats`] can be checked at any time,
+//! for example once a level has loaded, to see how much is allocated and how fragmented the
+//! heap has become.
+//!
+//! If an allocation does fail, the panic handler prints these statistics to the mgba log along
+//! with the size and alignment of the allocation which failed.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! let stats = agb::mem::heap_stats();
+//! agb::println!("{stats}");
+//! # }
+//! ```
+
+use core::fmt::{self, Display};
+
+use crate::agb_alloc;
+
+/// A snapshot of how much of a heap is in use, returned by [`heap_stats`] and
+/// [`iwram_heap_stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapStats {
+    /// The number of bytes currently allocated, as requested by the allocations. The space
+    /// used by the allocator to keep track of them isn't included.
+    pub allocated_bytes: usize,
+    /// The most bytes which have been allocated at once since the game started, or since the
+    /// current test started when running tests with the agb test runner.
+    pub peak_allocated_bytes: usize,
+    /// The number of allocations which haven't been freed.
+    pub allocations: usize,
+    /// The number of bytes which aren't allocated, including the space at the end of the heap
+    /// which hasn't been used yet.
+    pub free_bytes: usize,
+    /// The largest single free area of the heap. If this is much less than the total free
+    /// space then the heap is fragmented, and large allocations may fail even though there is
+    /// enough memory in total.
+    pub largest_free_block: usize,
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes in {} allocations, peak {} bytes, {} bytes free, largest free block {} bytes",
+            self.allocated_bytes,
+            self.allocations,
+            self.peak_allocated_bytes,
+            self.free_bytes,
+            self.largest_free_block
+        )
+    }
+}
+
+/// Statistics for the main heap in external work RAM, which is where [`Box`](alloc::boxed::Box),
+/// [`Vec`](alloc::vec::Vec) and everything else using the global allocator put their data.
+#[must_use]
+pub fn heap_stats() -> HeapStats {
+    agb_alloc::ewram_heap_stats()
+}
+
+/// Statistics for the heap in internal work RAM used by
+/// [`InternalAllocator`](crate::InternalAllocator) and
+/// [`PreferInternalAllocator`](crate::PreferInternalAllocator).
+///
+/// The heap starts after the code and data agb puts in internal work RAM, such as the interrupt
+/// handler, and agb's own allocations there, such as the sound mixer's buffers, are counted as
+/// allocated. So [`free_bytes`](HeapStats::free_bytes) is what is left for your game. The stack
+/// grows down from the end of internal work RAM into the same space, so leave some room for it.
+#[must_use]
+pub fn iwram_heap_stats() -> HeapStats {
+    agb_alloc::iwram_heap_stats()
+}
+
+/// Prints the heap statistics to the mgba log if the panic was caused by running out of memory.
+pub(crate) fn print_out_of_memory_diagnostics() {
+    let Some(layout) = agb_alloc::failed_ewram_allocation() else {
+        return;
+    };
+
+    if let Some(mut mgba) = crate::mgba::Mgba::new() {
+        let _ = mgba.print(
+            format_args!(
+                "Failed to allocate {} bytes aligned to {}. Heap: {}",
+                layout.size(),
+                layout.align(),
+                heap_stats()
+            ),
+            crate::mgba::DebugLevel::Fatal,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+
+    use super::*;
+
+    #[test_case]
+    fn tracks_allocations(_gba: &mut crate::Gba) {
+        let before = heap_stats();
+
+        let a = Box::new([0u8; 100]);
+        let mut b = Vec::<u32>::with_capacity(10);
+
+        let during = heap_stats();
+        assert_eq!(during.allocated_bytes, before.allocated_bytes + 140);
+        assert_eq!(during.allocations, before.allocations + 2);
+        assert!(during.peak_allocated_bytes >= during.allocated_bytes);
+
+        b.reserve_exact(20);
+        assert_eq!(heap_stats().allocated_bytes, before.allocated_bytes + 180);
+
+        drop(a);
+        drop(b);
+
+        let after = heap_stats();
+        assert_eq!(after.allocated_bytes, before.allocated_bytes);
+        assert_eq!(after.allocations, before.allocations);
+        assert!(after.peak_allocated_bytes >= before.allocated_bytes + 180);
+        assert!(after.largest_free_block > 0);
+        assert!(after.free_bytes >= after.largest_free_block);
+    }
+}
@@ -1,7 +1,13 @@
 use crate::memory_mapped::{MemoryMapped, MemoryMapped1DArray};
 use core::fmt::Write;
+use portable_atomic::{AtomicU8, Ordering};
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+/// How important a message in the mgba log is. Levels are ordered from [`Fatal`] as the most
+/// important to [`Debug`] as the least.
+///
+/// [`Fatal`]: DebugLevel::Fatal
+/// [`Debug`]: DebugLevel::Debug
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
 #[allow(dead_code)]
 pub enum DebugLevel {
     Fatal = 0,
@@ -11,6 +17,20 @@ pub enum DebugLevel {
     Debug = 4,
 }
 
+/// The least important level which the logging macros can output at all. In debug builds this
+/// is [`DebugLevel::Debug`], but release builds leave out [`log_debug!`](crate::log_debug)
+/// entirely, including formatting its arguments.
+///
+/// This is the level for agb's own build. The macros work it out again in the crate they are
+/// used in, so it is that crate's build which decides what is left out.
+pub const STATIC_MAX_LEVEL: DebugLevel = if cfg!(debug_assertions) {
+    DebugLevel::Debug
+} else {
+    DebugLevel::Info
+};
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(STATIC_MAX_LEVEL as u8);
+
 const OUTPUT_STRING: MemoryMapped1DArray<u8, 256> =
     unsafe { MemoryMapped1DArray::new(0x04FF_F600) };
 const DEBUG_ENABLE: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04FF_F780) };
@@ -21,11 +41,48 @@ const ENABLE_HANDSHAKE_OUT: u16 = 0x1DEA;
 const DEBUG_LEVEL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04FF_F700) };
 const DEBUG_FLAG_CODE: u16 = 0x0100;
 
-fn is_running_in_mgba() -> bool {
+/// Whether the game is running in mgba, or another emulator which supports its debug output.
+/// On real hardware this is `false`, so it can be used to only show debug tools in an
+/// emulator.
+#[must_use]
+pub fn is_present() -> bool {
     DEBUG_ENABLE.set(ENABLE_HANDSHAKE_IN);
     DEBUG_ENABLE.get() == ENABLE_HANDSHAKE_OUT
 }
 
+/// Sets the least important level which the logging macros output. This can't enable levels
+/// above [`STATIC_MAX_LEVEL`].
+pub fn set_max_level(level: DebugLevel) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The least important level which the logging macros output, see [`set_max_level`].
+#[must_use]
+pub fn max_level() -> DebugLevel {
+    match MAX_LEVEL.load(Ordering::Relaxed) {
+        0 => DebugLevel::Fatal,
+        1 => DebugLevel::Error,
+        2 => DebugLevel::Warning,
+        3 => DebugLevel::Info,
+        _ => DebugLevel::Debug,
+    }
+}
+
+/// Whether messages at `level` are output by the logging macros.
+#[must_use]
+pub fn log_enabled(level: DebugLevel) -> bool {
+    level <= STATIC_MAX_LEVEL && level <= max_level()
+}
+
+#[doc(hidden)]
+pub fn log(level: DebugLevel, module_path: &str, output: core::fmt::Arguments) {
+    if let Some(mut mgba) = Mgba::new() {
+        let _ = mgba.print(format_args!("[{module_path}] {output}"), level);
+    } else {
+        crate::net::uart::mirror_println(format_args!("[{module_path}] {output}"));
+    }
+}
+
 const NUMBER_OF_CYCLES: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x04FF_F800) };
 
 pub(crate) fn test_runner_measure_cycles() {
@@ -37,7 +94,7 @@ pub struct Mgba {}
 impl Mgba {
     #[must_use]
     pub fn new() -> Option<Self> {
-        if is_running_in_mgba() {
+        if is_present() {
             Some(Mgba {})
         } else {
             None
@@ -49,7 +106,10 @@ impl Mgba {
         output: core::fmt::Arguments,
         level: DebugLevel,
     ) -> Result<(), core::fmt::Error> {
-        let mut writer = MgbaWriter { bytes_written: 0 };
+        let mut writer = MgbaWriter {
+            bytes_written: 0,
+            level,
+        };
         write!(&mut writer, "{output}")?;
         self.set_level(level);
         Ok(())
@@ -58,6 +118,7 @@ impl Mgba {
 
 struct MgbaWriter {
     bytes_written: usize,
+    level: DebugLevel,
 }
 
 impl Mgba {
@@ -70,7 +131,7 @@ impl core::fmt::Write for MgbaWriter {
     fn write_str(&mut self, s: &str) -> Result<(), core::fmt::Error> {
         for b in s.bytes() {
             if self.bytes_written > 255 {
-                DEBUG_LEVEL.set(DEBUG_FLAG_CODE | DebugLevel::Info as u16);
+                DEBUG_LEVEL.set(DEBUG_FLAG_CODE | self.level as u16);
                 self.bytes_written = 0;
             }
             OUTPUT_STRING.set(self.bytes_written, b);
@@ -86,7 +147,99 @@ macro_rules! println {
         {
             if let Some(mut mgba) = $crate::mgba::Mgba::new() {
                 let _ = mgba.print(format_args!($($x,)*), $crate::mgba::DebugLevel::Info);
+            } else {
+                $crate::net::uart::mirror_println(format_args!($($x,)*));
             }
         }
     };
 }
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level:expr, $($arg:tt)*) => {
+        {
+            // checked here rather than in agb, so that this uses the calling crate's build and
+            // disabled levels are compiled out there
+            let static_max_level = if ::core::cfg!(debug_assertions) {
+                $crate::mgba::DebugLevel::Debug
+            } else {
+                $crate::mgba::DebugLevel::Info
+            };
+
+            if ($level as u8) <= (static_max_level as u8) && $level <= $crate::mgba::max_level() {
+                $crate::mgba::log($level, ::core::module_path!(), format_args!($($arg)*));
+            }
+        }
+    };
+}
+
+/// Prints a message to the mgba log at [`DebugLevel::Debug`](crate::mgba::DebugLevel::Debug), prefixed
+/// with the module it was logged from. These messages are removed from release builds, see
+/// [`STATIC_MAX_LEVEL`](crate::mgba::STATIC_MAX_LEVEL).
+///
+/// Nothing is printed when not running in mgba, so it is fine to leave logging in a game
+/// which runs on real hardware.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(position: agb::fixnum::Vector2D<i32>) {
+/// agb::log_debug!("player moved to {position:?}");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::mgba::DebugLevel::Debug, $($arg)*)
+    };
+}
+
+/// Prints a message to the mgba log at [`DebugLevel::Info`](crate::mgba::DebugLevel::Info), prefixed
+/// with the module it was logged from. See [`log_debug!`].
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::mgba::DebugLevel::Info, $($arg)*)
+    };
+}
+
+/// Prints a message to the mgba log at [`DebugLevel::Warning`](crate::mgba::DebugLevel::Warning),
+/// prefixed with the module it was logged from. See [`log_debug!`].
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::mgba::DebugLevel::Warning, $($arg)*)
+    };
+}
+
+/// Prints a message to the mgba log at [`DebugLevel::Error`](crate::mgba::DebugLevel::Error),
+/// prefixed with the module it was logged from. See [`log_debug!`].
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::__log!($crate::mgba::DebugLevel::Error, $($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn filters_by_level(_gba: &mut crate::Gba) {
+        assert!(is_present());
+
+        set_max_level(DebugLevel::Warning);
+        assert_eq!(max_level(), DebugLevel::Warning);
+        assert!(log_enabled(DebugLevel::Error));
+        assert!(log_enabled(DebugLevel::Warning));
+        assert!(!log_enabled(DebugLevel::Info));
+        crate::log_warn!("logging at {:?}", max_level());
+
+        set_max_level(DebugLevel::Debug);
+        assert_eq!(log_enabled(DebugLevel::Debug), cfg!(debug_assertions));
+
+        set_max_level(STATIC_MAX_LEVEL);
+    }
+}
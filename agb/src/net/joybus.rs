@@ -0,0 +1,252 @@
+//! Talking to a GameCube over the GameCube to Game Boy Advance cable.
+//!
+//! With that cable, the GameCube drives the link port in Joybus mode, the protocol it uses for
+//! its own controllers. The GameCube is always in charge: it sends commands, and the GBA's
+//! hardware answers them by itself. [`Joybus`] puts the port into Joybus mode and calls back
+//! from the serial interrupt with a [`JoybusEvent`] for each command which concerns the game.
+//!
+//! What this handles:
+//!
+//! * the reset and status commands, which the hardware answers with the GBA's device id and
+//!   [`JoybusStatus`] without the game doing anything. A reset is passed on as
+//!   [`JoybusEvent::Reset`], and any command at all means there is a GameCube, which
+//!   [`Joybus::is_connected`] reports.
+//! * the GameCube writing a 32 bit word, which is read out of the receive register and passed on
+//!   as [`JoybusEvent::Received`].
+//! * the GameCube reading a 32 bit word, which the game puts ready with [`Joybus::send`], with
+//!   [`JoybusEvent::Sent`] once it has been read.
+//!
+//! What the GameCube side has to do is everything else: deciding when to send commands, and
+//! what the words mean. Nothing here works out a protocol, or sends a game to the GBA.
+//!
+//! The Game Boy Player doesn't use Joybus to talk to the game, it uses the serial port's normal
+//! mode, so it isn't detected by this. Use
+//! [`gba_player_detected`](crate::rumble::gba_player_detected) and
+//! [`Rumble::gba_player`](crate::rumble::Rumble::gba_player) for that instead.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::SerialPortInUse> {
+//! use agb::net::joybus::{Joybus, JoybusEvent};
+//!
+//! // Safety: the callback doesn't allocate
+//! let mut joybus = unsafe {
+//!     Joybus::new(|event| {
+//!         if let JoybusEvent::Received(word) = event {
+//!             agb::println!("the GameCube sent {word:#010x}");
+//!         }
+//!     })?
+//! };
+//!
+//! if !joybus.wait_for_connection(60) {
+//!     agb::println!("no GameCube after a second");
+//! }
+//!
+//! // ready for the next time the GameCube reads
+//! let _ = joybus.send(0x1234_5678);
+//! # Ok(())
+//! # }
+//! ```
+
+use portable_atomic::{AtomicBool, Ordering};
+
+use crate::{
+    interrupt::{add_interrupt_handler, interrupt_free, Interrupt, InterruptHandler},
+    memory_mapped::MemoryMapped,
+};
+
+use super::{SerialPort, SerialPortInUse, Timeout, RCNT};
+
+const JOYCNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0140) };
+const JOY_RECV: MemoryMapped<u32> = unsafe { MemoryMapped::new(0x0400_0150) };
+const JOY_TRANS: MemoryMapped<u32> = unsafe { MemoryMapped::new(0x0400_0154) };
+const JOYSTAT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0158) };
+
+const JOYBUS_MODE: u16 = 0b11 << 14;
+
+const RESET_RECEIVED: u16 = 1 << 0;
+const RECEIVE_COMPLETE: u16 = 1 << 1;
+const SEND_COMPLETE: u16 = 1 << 2;
+const RESET_IRQ_ENABLE: u16 = 1 << 6;
+
+const RECEIVE_PENDING: u16 = 1 << 1;
+const SEND_PENDING: u16 = 1 << 3;
+const GENERAL_FLAGS_SHIFT: u16 = 4;
+
+/// Whether a Joybus command has arrived since Joybus mode was last entered.
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+
+/// Something the GameCube did, passed to the callback given to [`Joybus::new`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoybusEvent {
+    /// The GameCube sent a reset command.
+    Reset,
+    /// The GameCube wrote a word to the GBA.
+    Received(u32),
+    /// The GameCube read the word given to [`Joybus::send`].
+    Sent,
+}
+
+/// The ways sending a word can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JoybusError {
+    /// The GameCube hasn't read the last word sent yet.
+    SendPending,
+}
+
+/// What the GBA tells the GameCube in answer to a status command, as well as its device id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JoybusStatus {
+    /// A word the GameCube has written hasn't been read by the GBA yet.
+    pub receive_pending: bool,
+    /// A word the GBA has sent hasn't been read by the GameCube yet.
+    pub send_pending: bool,
+    /// Two bits which mean whatever the game and the GameCube agree on.
+    pub general_flags: u8,
+}
+
+/// The link port in Joybus mode, see the [module level documentation](self).
+pub struct Joybus {
+    _handler: InterruptHandler,
+    _port: SerialPort,
+}
+
+impl Joybus {
+    /// Puts the link port into Joybus mode, calling `on_event` from the serial interrupt for
+    /// everything the GameCube does. The port goes back to normal when this is dropped. Fails
+    /// if something else is using the [serial port](SerialPort).
+    ///
+    /// # Safety
+    ///
+    /// `on_event` runs in an interrupt handler, so it mustn't allocate, the same as
+    /// [`add_interrupt_handler`].
+    pub unsafe fn new(
+        on_event: impl Fn(JoybusEvent) + Send + Sync + 'static,
+    ) -> Result<Self, SerialPortInUse> {
+        let port = SerialPort::claim()?;
+
+        CONNECTED.store(false, Ordering::SeqCst);
+
+        RCNT.set(JOYBUS_MODE);
+        JOYSTAT.set(0);
+        // clear anything left over, as the flags are cleared by writing 1s to them
+        JOYCNT.set(RESET_RECEIVED | RECEIVE_COMPLETE | SEND_COMPLETE);
+
+        // Safety: on_event doesn't allocate, as promised by the caller, and nothing else does
+        let handler = unsafe {
+            add_interrupt_handler(Interrupt::Serial, move |_| {
+                let control = JOYCNT.get();
+                if control & (RESET_RECEIVED | RECEIVE_COMPLETE | SEND_COMPLETE) != 0 {
+                    CONNECTED.store(true, Ordering::SeqCst);
+                }
+
+                if control & RESET_RECEIVED != 0 {
+                    on_event(JoybusEvent::Reset);
+                }
+                if control & RECEIVE_COMPLETE != 0 {
+                    on_event(JoybusEvent::Received(JOY_RECV.get()));
+                }
+                if control & SEND_COMPLETE != 0 {
+                    on_event(JoybusEvent::Sent);
+                }
+
+                JOYCNT.set(control | RESET_IRQ_ENABLE);
+            })
+        };
+
+        JOYCNT.set(RESET_IRQ_ENABLE);
+
+        Ok(Self {
+            _handler: handler,
+            _port: port,
+        })
+    }
+
+    /// Whether the GameCube has sent any command since Joybus mode was entered, which means
+    /// there is a GameCube on the other end of the cable.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        CONNECTED.load(Ordering::SeqCst)
+    }
+
+    /// Waits for up to `frames` frames for the GameCube to send a command, returning whether
+    /// it did. GameCube games which talk to a GBA usually send one every frame or so.
+    pub fn wait_for_connection(&mut self, frames: u32) -> bool {
+        let mut timeout = Timeout::frames(frames);
+        while !self.is_connected() {
+            if timeout.expired() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Puts `word` ready for the GameCube to read. Fails if it hasn't read the last one yet.
+    pub fn send(&mut self, word: u32) -> Result<(), JoybusError> {
+        interrupt_free(|_| {
+            if JOYSTAT.get() & SEND_PENDING != 0 {
+                return Err(JoybusError::SendPending);
+            }
+
+            JOY_TRANS.set(word);
+            Ok(())
+        })
+    }
+
+    /// What the GBA answers the GameCube's status command with.
+    #[must_use]
+    pub fn status(&self) -> JoybusStatus {
+        let status = JOYSTAT.get();
+
+        JoybusStatus {
+            receive_pending: status & RECEIVE_PENDING != 0,
+            send_pending: status & SEND_PENDING != 0,
+            general_flags: ((status >> GENERAL_FLAGS_SHIFT) & 0b11) as u8,
+        }
+    }
+
+    /// Sets the two general purpose flags the GameCube sees in the GBA's status.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flags` has bits set other than the bottom two.
+    pub fn set_general_flags(&mut self, flags: u8) {
+        assert_eq!(flags & !0b11, 0, "there are only two general purpose flags");
+
+        interrupt_free(|_| {
+            let others = JOYSTAT.get() & !(0b11 << GENERAL_FLAGS_SHIFT);
+            JOYSTAT.set(others | (u16::from(flags) << GENERAL_FLAGS_SHIFT));
+        });
+    }
+}
+
+impl Drop for Joybus {
+    fn drop(&mut self) {
+        JOYCNT.set(RESET_RECEIVED | RECEIVE_COMPLETE | SEND_COMPLETE);
+        RCNT.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn enters_and_leaves_joybus_mode(_gba: &mut crate::Gba) {
+        // Safety: the callback doesn't allocate
+        let mut joybus = unsafe { Joybus::new(|_| {}) }.unwrap();
+        assert_eq!(RCNT.get() & JOYBUS_MODE, JOYBUS_MODE);
+
+        // there's no GameCube when testing
+        assert!(!joybus.wait_for_connection(2));
+
+        joybus.set_general_flags(0b10);
+        assert_eq!(joybus.status().general_flags, 0b10);
+
+        drop(joybus);
+        assert_eq!(RCNT.get() & JOYBUS_MODE, 0);
+    }
+}
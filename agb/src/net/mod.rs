@@ -0,0 +1,151 @@
+use portable_atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+use crate::memory_mapped::MemoryMapped;
+
+pub mod joybus;
+pub mod multiboot;
+pub mod multiplayer;
+pub mod normal;
+pub mod serial_keyboard;
+pub mod uart;
+
+const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+
+// The serial port's registers, whose meanings depend on the mode it is in.
+pub(crate) const SIODATA32: MemoryMapped<u32> = unsafe { MemoryMapped::new(0x0400_0120) };
+pub(crate) const SIOCNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0128) };
+pub(crate) const SIODATA8: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0400_012A) };
+pub(crate) const SIOMLT_SEND: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_012A) };
+pub(crate) const RCNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0134) };
+
+static SERIAL_PORT_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// Returned when the serial port is already in use by something else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerialPortInUse;
+
+/// The serial port, which nothing else can use until this is dropped.
+///
+/// Each of the drivers in this module, and [`Rumble::gba_player`](crate::rumble::Rumble::gba_player),
+/// puts the port into its own mode, so two of them running at once would break each other. To
+/// stop that, each one [claims](SerialPort::claim) the port when it is created and fails with
+/// [`SerialPortInUse`] if something else already has it, in the same way as
+/// [`CartGpio::claim`](crate::gpio::CartGpio::claim) does for the cartridge's GPIO pins. Code
+/// which drives the serial registers itself should claim the port too.
+#[derive(Debug)]
+pub struct SerialPort {
+    _private: (),
+}
+
+impl SerialPort {
+    /// Claims the serial port, failing if it has already been claimed.
+    pub fn claim() -> Result<Self, SerialPortInUse> {
+        SERIAL_PORT_CLAIMED
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .map_err(|_| SerialPortInUse)?;
+
+        Ok(Self { _private: () })
+    }
+}
+
+impl Drop for SerialPort {
+    fn drop(&mut self) {
+        SERIAL_PORT_CLAIMED.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A queue of bytes which one place pushes to and another pops from, with one of them in an
+/// interrupt handler. It holds up to `N - 1` bytes.
+pub(crate) struct ByteQueue<const N: usize> {
+    bytes: [AtomicU8; N],
+    read: AtomicUsize,
+    write: AtomicUsize,
+}
+
+impl<const N: usize> ByteQueue<N> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            bytes: [const { AtomicU8::new(0) }; N],
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn push(&self, byte: u8) -> bool {
+        let write = self.write.load(Ordering::Relaxed);
+        let next = (write + 1) % N;
+        if next == self.read.load(Ordering::Acquire) {
+            return false;
+        }
+
+        self.bytes[write].store(byte, Ordering::Relaxed);
+        self.write.store(next, Ordering::Release);
+
+        true
+    }
+
+    pub(crate) fn pop(&self) -> Option<u8> {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.write.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let byte = self.bytes[read].load(Ordering::Relaxed);
+        self.read.store((read + 1) % N, Ordering::Release);
+
+        Some(byte)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.read.load(Ordering::Acquire) == self.write.load(Ordering::Acquire)
+    }
+
+    /// Empties the queue. Neither end can be in use while this happens.
+    pub(crate) fn clear(&self) {
+        self.read.store(0, Ordering::SeqCst);
+        self.write.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Counts down scanlines, for timeouts which work even with interrupts disabled.
+pub(crate) struct Timeout {
+    scanlines_left: u32,
+    last_vcount: u16,
+}
+
+impl Timeout {
+    pub(crate) fn frames(frames: u32) -> Self {
+        Self {
+            scanlines_left: frames * 228,
+            last_vcount: VCOUNT.get(),
+        }
+    }
+
+    pub(crate) fn expired(&mut self) -> bool {
+        let vcount = VCOUNT.get();
+        if vcount != self.last_vcount {
+            self.last_vcount = vcount;
+            self.scanlines_left = self.scanlines_left.saturating_sub(1);
+        }
+
+        self.scanlines_left == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn serial_port_can_only_be_claimed_once(_gba: &mut crate::Gba) {
+        let port = SerialPort::claim().unwrap();
+        assert_eq!(SerialPort::claim().unwrap_err(), SerialPortInUse);
+        assert_eq!(
+            crate::rumble::Rumble::gba_player().err(),
+            Some(crate::rumble::RumbleError::SerialPortInUse)
+        );
+
+        drop(port);
+        assert!(SerialPort::claim().is_ok());
+    }
+}
@@ -0,0 +1,423 @@
+//! Sending a game to GBAs with no cartridge over the link cable, for single cartridge
+//! multi-player.
+//!
+//! A GBA turned on with a link cable but no cartridge waits for a game to be sent to it. [`send`]
+//! does the handshake with up to three of them at once and then sends the ROM with the BIOS
+//! `MultiBoot` call. The clients copy it into EWRAM and run it. This GBA has to be the parent,
+//! so have the smaller plug of the cable in it.
+//!
+//! # Building a client ROM
+//!
+//! As the whole ROM is copied into EWRAM, it can be at most [`MAX_ROM_LENGTH`] bytes (256KiB)
+//! including its header, and the client can't use a cartridge or save media. The ROM also needs
+//! to start with a valid header, since the BIOS checks it before running anything.
+//!
+//! agb can build these with its `multiboot` feature. In the client's `Cargo.toml`:
+//!
+//! ```toml
+//! [dependencies]
+//! agb = { version = "...", features = ["multiboot"] }
+//! ```
+//!
+//! This links everything to run from EWRAM at `0x0200_0000`, with the entry point the BIOS jumps
+//! to straight after the header. Build the client as usual and convert it with
+//! `agb-gbafix --padding`, which fills in the header and pads the ROM to a multiple of 16 bytes
+//! as [`send`] needs. The sending game can then include the result, aligned to 4 bytes:
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::multiboot::MultibootError> {
+//! use agb::net::multiboot::{self, Progress};
+//!
+//! #[repr(align(4))]
+//! struct Aligned<T: ?Sized>(T);
+//!
+//! # static CLIENT: &Aligned<[u8]> = &Aligned([0; 0x200]);
+//! # /*
+//! static CLIENT: &Aligned<[u8]> = &Aligned(*include_bytes!("../client.gba"));
+//! # */
+//!
+//! multiboot::send(&CLIENT.0, |progress| match progress {
+//!     Progress::Found { clients } => agb::println!("found clients {:#b}", clients),
+//!     Progress::SendingRom => agb::println!("sending the game"),
+//!     _ => {}
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    delay,
+    net::{
+        multiplayer::{BaudRate, LinkError, MultiPlayer, Role},
+        SerialPortInUse,
+    },
+    syscall,
+};
+
+/// The length of the header at the start of a ROM, which is sent before the rest.
+const HEADER_LENGTH: usize = 0xc0;
+/// The shortest ROM the BIOS will accept.
+const MIN_ROM_LENGTH: usize = HEADER_LENGTH + 0x100;
+/// The longest ROM which can be sent, which is the size of EWRAM.
+pub const MAX_ROM_LENGTH: usize = 256 * 1024;
+
+/// How many times to look for clients, with a 16th of a second between each.
+const SEARCH_ROUNDS: usize = 80;
+/// How many times in a row the clients have to answer before they count as found.
+const PROBES: usize = 15;
+/// How many times to send the palette before giving up on the clients answering with their
+/// random data.
+const PALETTE_ATTEMPTS: usize = 64;
+const SIXTEENTH_OF_A_SECOND_MICROS: u32 = 1_000_000 / 16;
+/// The time the clients' BIOS gets to prepare its next reply between transfers.
+const TRANSFER_GAP_MICROS: u32 = 100;
+
+/// The colour, direction and speed of the logo animation shown on the clients while the ROM
+/// is sent.
+const PALETTE: u8 = 0xc1;
+
+/// How far [`send`] has got, passed to its progress callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Progress {
+    /// Looking for clients waiting for a game. This is reported each time it looks.
+    Searching,
+    /// Found the clients to send to. Bit `n` is set for the client which is player `n`.
+    Found {
+        /// The players found, as bits 1 to 3.
+        clients: u8,
+    },
+    /// Sending the header of the ROM, `sent` of `total` halfwords so far.
+    SendingHeader {
+        /// How many halfwords have been sent.
+        sent: usize,
+        /// How many halfwords the header is.
+        total: usize,
+    },
+    /// Exchanging the data the clients need to check the rest of the ROM.
+    Handshaking,
+    /// The BIOS is sending the rest of the ROM, which takes a few seconds for a big one. Nothing
+    /// else is reported until it finishes.
+    SendingRom,
+}
+
+/// The ways sending a ROM can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MultibootError {
+    /// The ROM is shorter than 448 bytes, longer than [`MAX_ROM_LENGTH`] or not a multiple of 16
+    /// bytes long.
+    InvalidRomLength,
+    /// The ROM doesn't start at a multiple of 4 bytes.
+    RomNotAligned,
+    /// This GBA is a child, so can't start transfers. Swap the plugs of the link cable over.
+    NotParent,
+    /// No clients answered in time.
+    NoClients,
+    /// A client didn't answer in the way the protocol says it should. `reply` is `0xffff` if it
+    /// didn't answer at all.
+    UnexpectedReply {
+        /// The player number of the client.
+        client: u8,
+        /// What the client sent.
+        reply: u16,
+    },
+    /// Something else is using the [serial port](crate::net::SerialPort).
+    SerialPortInUse,
+    /// A transfer over the link cable failed.
+    Link(LinkError),
+    /// The BIOS reported that sending the rest of the ROM failed.
+    TransferFailed,
+}
+
+impl From<LinkError> for MultibootError {
+    fn from(error: LinkError) -> Self {
+        MultibootError::Link(error)
+    }
+}
+
+impl From<SerialPortInUse> for MultibootError {
+    fn from(_: SerialPortInUse) -> Self {
+        MultibootError::SerialPortInUse
+    }
+}
+
+/// The parameters for the BIOS `MultiBoot` call. Most of this is the BIOS's own working space,
+/// and the rest is only read by the BIOS.
+#[repr(C)]
+#[allow(dead_code)]
+pub(crate) struct MultiBootParam {
+    reserved_1: [u32; 5],
+    handshake_data: u8,
+    padding: u8,
+    handshake_timeout: u16,
+    probe_count: u8,
+    client_data: [u8; 3],
+    palette_data: u8,
+    response_bit: u8,
+    client_bit: u8,
+    reserved_2: u8,
+    boot_start: *const u8,
+    boot_end: *const u8,
+    master: *const u8,
+    reserved_3: [*const u8; 3],
+    system_work: [u32; 4],
+    send_flag: u8,
+    probe_target_bit: u8,
+    check_wait: u8,
+    server_type: u8,
+}
+
+/// Sends `rom` to every GBA waiting for a game on the link cable, calling `progress` as it goes.
+/// See the [module level documentation](self) for how to build `rom`.
+///
+/// This takes over the serial port and busy waits until the game has been sent, which is
+/// usually a few seconds, or until no clients have been found for 5 seconds.
+pub fn send(rom: &[u8], mut progress: impl FnMut(Progress)) -> Result<(), MultibootError> {
+    if rom.len() < MIN_ROM_LENGTH || rom.len() > MAX_ROM_LENGTH || !rom.len().is_multiple_of(16) {
+        return Err(MultibootError::InvalidRomLength);
+    }
+    if !(rom.as_ptr() as usize).is_multiple_of(4) {
+        return Err(MultibootError::RomNotAligned);
+    }
+
+    let mut link = Link(MultiPlayer::new(BaudRate::B115200)?);
+    if link.0.role() != Role::Parent {
+        return Err(MultibootError::NotParent);
+    }
+
+    let clients = find_clients(&mut link, &mut progress)?;
+    progress(Progress::Found { clients });
+
+    let replies = link.transfer(0x6100 | u16::from(clients))?;
+    expect(replies, clients, |bit, reply| reply == 0x7200 | bit)?;
+
+    let total = HEADER_LENGTH / 2;
+    for (sent, halfword) in rom[..HEADER_LENGTH].chunks_exact(2).enumerate() {
+        let replies = link.transfer(u16::from_le_bytes([halfword[0], halfword[1]]))?;
+        // the top byte counts down how many halfwords are left
+        expect(replies, clients, |bit, reply| reply & 0xff == bit)?;
+
+        progress(Progress::SendingHeader {
+            sent: sent + 1,
+            total,
+        });
+    }
+
+    progress(Progress::Handshaking);
+
+    let replies = link.transfer(0x6200)?;
+    expect(replies, clients, |bit, reply| reply == bit)?;
+    let replies = link.transfer(0x6200 | u16::from(clients))?;
+    expect(replies, clients, |bit, reply| reply == 0x7200 | bit)?;
+
+    let client_data = exchange_palette(&mut link, clients)?;
+
+    let handshake_data = handshake_data(client_data);
+    let replies = link.transfer(0x6400 | u16::from(handshake_data))?;
+    expect(replies, clients, |_, reply| reply >> 8 == 0x73)?;
+
+    delay::micros(SIXTEENTH_OF_A_SECOND_MICROS);
+
+    progress(Progress::SendingRom);
+
+    let mut param = MultiBootParam {
+        reserved_1: [0; 5],
+        // the BIOS checks this against the handshake already sent
+        handshake_data,
+        padding: 0,
+        handshake_timeout: 0,
+        probe_count: 0,
+        client_data,
+        palette_data: PALETTE,
+        response_bit: 0,
+        client_bit: clients,
+        reserved_2: 0,
+        boot_start: rom[HEADER_LENGTH..].as_ptr(),
+        boot_end: rom.as_ptr_range().end,
+        master: core::ptr::null(),
+        reserved_3: [core::ptr::null(); 3],
+        system_work: [0; 4],
+        send_flag: 0,
+        probe_target_bit: 0,
+        check_wait: 0,
+        server_type: 0,
+    };
+
+    // Safety: the boot pointers are into `rom`, which outlives the call. Mode 1 is multi-player.
+    if unsafe { syscall::multi_boot(&mut param, 1) } {
+        Ok(())
+    } else {
+        Err(MultibootError::TransferFailed)
+    }
+}
+
+/// The serial port in multi-player mode, leaving time between transfers for the clients.
+struct Link(MultiPlayer);
+
+impl Link {
+    fn transfer(&mut self, word: u16) -> Result<[Option<u16>; 4], LinkError> {
+        let replies = self.0.transfer(word);
+        delay::micros(TRANSFER_GAP_MICROS);
+        replies
+    }
+}
+
+/// Looks for clients until the same ones answer [`PROBES`] times in a row, returning them as
+/// bits 1 to 3.
+fn find_clients(
+    link: &mut Link,
+    progress: &mut impl FnMut(Progress),
+) -> Result<u8, MultibootError> {
+    'search: for _ in 0..SEARCH_ROUNDS {
+        progress(Progress::Searching);
+
+        let mut clients = 0;
+        for _ in 0..PROBES {
+            let found = match link.transfer(0x6200) {
+                Ok(replies) => clients_replying(replies, 0x7200),
+                // the clients may not be in multi-player mode yet
+                Err(_) => 0,
+            };
+
+            if found == 0 || (clients != 0 && found != clients) {
+                delay::micros(SIXTEENTH_OF_A_SECOND_MICROS);
+                continue 'search;
+            }
+            clients = found;
+        }
+
+        return Ok(clients);
+    }
+
+    Err(MultibootError::NoClients)
+}
+
+/// Sends the palette until every client answers with its random data, returning the data for
+/// each of players 1 to 3, with `0xff` for players who aren't clients.
+fn exchange_palette(link: &mut Link, clients: u8) -> Result<[u8; 3], MultibootError> {
+    for _ in 0..PALETTE_ATTEMPTS {
+        let replies = link.transfer(0x6300 | u16::from(PALETTE))?;
+
+        if clients_replying(replies, 0x7300) == clients {
+            return Ok(core::array::from_fn(|i| {
+                let client = i + 1;
+                match replies[client] {
+                    Some(reply) if clients & (1 << client) != 0 => reply as u8,
+                    _ => 0xff,
+                }
+            }));
+        }
+
+        // clients which aren't ready yet keep answering as they did before
+        expect(replies, clients, |bit, reply| {
+            reply == 0x7200 | bit || reply >> 8 == 0x73
+        })?;
+    }
+
+    Err(MultibootError::NoClients)
+}
+
+/// Which of players 1 to 3 answered with `high_byte` in the top byte, as bits 1 to 3. When
+/// looking for clients the bottom byte is also the client's own bit, otherwise it is data.
+fn clients_replying(replies: [Option<u16>; 4], high_byte: u16) -> u8 {
+    let mut clients = 0;
+
+    for (client, reply) in replies.iter().enumerate().skip(1) {
+        let bit = 1 << client;
+        let matches = match reply {
+            Some(reply) if high_byte == 0x7200 => *reply == high_byte | bit,
+            Some(reply) => reply & 0xff00 == high_byte,
+            None => false,
+        };
+
+        if matches {
+            clients |= bit as u8;
+        }
+    }
+
+    clients
+}
+
+/// Checks that each client's reply matches what the protocol expects, given the client's bit.
+fn expect(
+    replies: [Option<u16>; 4],
+    clients: u8,
+    matches: impl Fn(u16, u16) -> bool,
+) -> Result<(), MultibootError> {
+    for (client, reply) in replies.iter().enumerate().skip(1) {
+        let bit = 1 << client;
+        if clients & bit == 0 {
+            continue;
+        }
+
+        let reply = reply.unwrap_or(0xffff);
+        if !matches(u16::from(bit), reply) {
+            return Err(MultibootError::UnexpectedReply {
+                client: client as u8,
+                reply,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The value sent to the clients to check the handshake, worked out from their random data.
+fn handshake_data(client_data: [u8; 3]) -> u8 {
+    client_data
+        .iter()
+        .fold(0x11, |sum: u8, &data| sum.wrapping_add(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn client_replies_are_checked(_gba: &mut crate::Gba) {
+        assert_eq!(core::mem::size_of::<MultiBootParam>(), 0x4c);
+
+        let searching = [Some(0x6200), Some(0x7202), None, Some(0x7208)];
+        assert_eq!(clients_replying(searching, 0x7200), 0b1010);
+
+        let random_data = [Some(0x63c1), Some(0x7342), None, Some(0x7200 | 8)];
+        assert_eq!(clients_replying(random_data, 0x7300), 0b0010);
+
+        assert_eq!(
+            expect(searching, 0b1010, |bit, reply| reply == 0x7200 | bit),
+            Ok(())
+        );
+        assert_eq!(
+            expect(searching, 0b0110, |bit, reply| reply == 0x7200 | bit),
+            Err(MultibootError::UnexpectedReply {
+                client: 2,
+                reply: 0xffff
+            })
+        );
+
+        assert_eq!(handshake_data([0x42, 0xff, 0xff]), 0x51);
+    }
+
+    #[test_case]
+    fn invalid_roms_are_rejected(_gba: &mut crate::Gba) {
+        #[repr(align(4))]
+        struct Aligned([u8; 0x201]);
+        let rom = Aligned([0; 0x201]);
+
+        assert_eq!(
+            send(&rom.0[..0x100], |_| {}),
+            Err(MultibootError::InvalidRomLength)
+        );
+        assert_eq!(
+            send(&rom.0[..0x1f8], |_| {}),
+            Err(MultibootError::InvalidRomLength)
+        );
+        assert_eq!(
+            send(&rom.0[1..], |_| {}),
+            Err(MultibootError::RomNotAligned)
+        );
+    }
+}
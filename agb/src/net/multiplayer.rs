@@ -0,0 +1,450 @@
+//! Exchanging data between up to four GBAs over the link cable.
+//!
+//! In multi-player mode, each connected GBA sends one 16 bit word per transfer and receives the
+//! words sent by every GBA, including its own. The GBA with the smaller plug of the cable in it is
+//! the parent, which starts each transfer and is always player 0. The others are
+//! children, numbered 1 to 3 along the cable, which wait for the parent to start a transfer.
+//! The same ROM can run on every GBA and check [`MultiPlayer::role`] to see which it is.
+//!
+//! Words can be exchanged in two ways:
+//!
+//! * [`MultiPlayer::transfer`] does a single transfer, waiting for it to finish. On the parent
+//!   this starts the transfer, and on a child it waits for the parent to start one. Calling it
+//!   once per frame on every GBA keeps their games in lockstep.
+//! * [`MultiPlayer::exchange_every_frame`] transfers in the background, with the parent
+//!   starting a transfer every vblank. [`FrameExchange::latest`] gives the last words received.
+//!
+//! A player who isn't there is received as `None`. If a player who was there disappears, for
+//! example because their cable was pulled out, this is reported once as
+//! [`LinkError::Disconnected`] rather than carrying on with the last word they sent. As a
+//! missing player is received as `0xffff`, that word can't be sent.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::SerialPortInUse> {
+//! use agb::net::multiplayer::{BaudRate, MultiPlayer};
+//!
+//! let mut link = MultiPlayer::new(BaudRate::B115200)?;
+//! let vblank = agb::interrupt::VBlank::get();
+//!
+//! let mut my_position = 0;
+//! loop {
+//!     vblank.wait_for_vblank();
+//!
+//!     match link.transfer(my_position) {
+//!         Ok(words) => {
+//!             for (player, position) in words.iter().enumerate() {
+//!                 if let Some(position) = position {
+//!                     agb::println!("player {} is at {}", player, position);
+//!                 }
+//!             }
+//!         }
+//!         Err(error) => agb::println!("link error: {:?}", error),
+//!     }
+//!
+//!     my_position += 1;
+//! }
+//! # }
+//! ```
+
+use portable_atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+
+use crate::{
+    interrupt::{add_interrupt_handler, interrupt_free, Interrupt, InterruptHandler},
+    memory_mapped::{MemoryMapped, MemoryMapped1DArray},
+};
+
+use super::{SerialPort, SerialPortInUse, Timeout, RCNT, SIOCNT, SIOMLT_SEND};
+
+const SIOMULTI: MemoryMapped1DArray<u16, 4> = unsafe { MemoryMapped1DArray::new(0x0400_0120) };
+const INTERRUPT_REQUEST: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0202) };
+
+const CHILD: u16 = 1 << 2;
+const ALL_READY: u16 = 1 << 3;
+const ID_SHIFT: u16 = 4;
+const ERROR: u16 = 1 << 6;
+const START: u16 = 1 << 7;
+const MULTIPLAYER_MODE: u16 = 0b10 << 12;
+const IRQ_ENABLE: u16 = 1 << 14;
+
+const SERIAL_INTERRUPT: u16 = 1 << 7;
+
+/// What a GBA which isn't connected is received as.
+const NO_PLAYER: u16 = 0xffff;
+
+/// How long [`MultiPlayer::transfer`] waits for a transfer, in frames. This is long enough for
+/// a child to wait for a parent which only transfers once a frame.
+const TIMEOUT_FRAMES: u32 = 4;
+/// How many frames without a transfer [`FrameExchange::latest`] allows before reporting that
+/// the link has timed out.
+const STALE_FRAMES: u32 = 4;
+
+/// The speed of the link. Every GBA must use the same speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaudRate {
+    /// 9600 bits per second.
+    B9600 = 0,
+    /// 38400 bits per second.
+    B38400 = 1,
+    /// 57600 bits per second.
+    B57600 = 2,
+    /// 115200 bits per second.
+    B115200 = 3,
+}
+
+/// Whether this GBA starts transfers or waits for them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The GBA which starts each transfer, which is player 0.
+    Parent,
+    /// One of the GBAs which waits for the parent to start a transfer.
+    Child,
+}
+
+/// The state of one of the four players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerStatus {
+    /// Nothing has been received from this player.
+    NotConnected,
+    /// The last transfer received a word from this player.
+    Connected,
+    /// This player was connected, but the last transfer received nothing from them.
+    Disconnected,
+}
+
+/// The ways exchanging words can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LinkError {
+    /// Not every GBA on the cable is in multi-player mode yet, or there is no cable.
+    NotReady,
+    /// No transfer happened in time. On a child this usually means the parent has stopped.
+    TimedOut,
+    /// The hardware reported an error in the transfer, so the words received can't be trusted.
+    TransferFailed,
+    /// The player with this id was connected, but wasn't in the latest transfer.
+    Disconnected(u8),
+}
+
+/// Keeps track of which players have been seen, to notice when one disappears.
+struct Connections {
+    players: [PlayerStatus; 4],
+}
+
+impl Connections {
+    const fn new() -> Self {
+        Self {
+            players: [PlayerStatus::NotConnected; 4],
+        }
+    }
+
+    /// Updates the players from the words received in a transfer.
+    fn received(&mut self, words: [u16; 4]) -> Result<[Option<u16>; 4], LinkError> {
+        let mut disconnected = None;
+
+        for (id, (status, &word)) in self.players.iter_mut().zip(&words).enumerate() {
+            if word != NO_PLAYER {
+                *status = PlayerStatus::Connected;
+            } else if *status == PlayerStatus::Connected {
+                *status = PlayerStatus::Disconnected;
+                disconnected.get_or_insert(id as u8);
+            }
+        }
+
+        match disconnected {
+            Some(id) => Err(LinkError::Disconnected(id)),
+            None => Ok(words.map(|word| (word != NO_PLAYER).then_some(word))),
+        }
+    }
+}
+
+/// The serial port in multi-player mode, see the [module level documentation](self).
+pub struct MultiPlayer {
+    connections: Connections,
+    transferred: bool,
+    _port: SerialPort,
+}
+
+impl MultiPlayer {
+    /// Puts the serial port into multi-player mode at the given speed. Fails if something else
+    /// is using the [serial port](SerialPort).
+    pub fn new(baud_rate: BaudRate) -> Result<Self, SerialPortInUse> {
+        let port = SerialPort::claim()?;
+
+        RCNT.set(0);
+        // the interrupt is requested after each transfer even while it is disabled in the
+        // interrupt enable register, which is how `transfer` knows when one has finished
+        SIOCNT.set(MULTIPLAYER_MODE | IRQ_ENABLE | baud_rate as u16);
+
+        Ok(Self {
+            connections: Connections::new(),
+            transferred: false,
+            _port: port,
+        })
+    }
+
+    /// Whether this GBA is the parent or a child. This is only meaningful once a cable is
+    /// connected, and a GBA with no cable is a parent.
+    #[must_use]
+    pub fn role(&self) -> Role {
+        if SIOCNT.get() & CHILD == 0 {
+            Role::Parent
+        } else {
+            Role::Child
+        }
+    }
+
+    /// Whether every GBA on the cable is in multi-player mode, so transfers can happen.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        SIOCNT.get() & ALL_READY != 0
+    }
+
+    /// Which player this GBA is, from 0 for the parent to 3. This is only known once a transfer
+    /// has happened.
+    #[must_use]
+    pub fn player_id(&self) -> Option<u8> {
+        self.transferred
+            .then(|| ((SIOCNT.get() >> ID_SHIFT) & 0b11) as u8)
+    }
+
+    /// The state of the player with the given id, as of the last transfer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is 4 or more.
+    #[must_use]
+    pub fn status(&self, id: usize) -> PlayerStatus {
+        self.connections.players[id]
+    }
+
+    /// Sends `word` to every other GBA and receives theirs, waiting for the transfer to finish.
+    /// Returns the word from each player in order of player id, including this one, or `None`
+    /// for players who aren't there.
+    ///
+    /// On the parent this starts a transfer, and on a child this waits up to 4 frames for the
+    /// parent to start one.
+    pub fn transfer(&mut self, word: u16) -> Result<[Option<u16>; 4], LinkError> {
+        if !self.is_ready() {
+            return Err(LinkError::NotReady);
+        }
+
+        SIOMLT_SEND.set(word);
+        INTERRUPT_REQUEST.set(SERIAL_INTERRUPT);
+
+        if self.role() == Role::Parent {
+            SIOCNT.set(SIOCNT.get() | START);
+        }
+
+        if !wait_for_transfer() {
+            return Err(LinkError::TimedOut);
+        }
+
+        self.finish_transfer(read_words(), SIOCNT.get() & ERROR != 0)
+    }
+
+    /// Exchanges words in the background once a frame for as long as the returned
+    /// [`FrameExchange`] is alive, sending `word` until [`FrameExchange::set_word`] changes it.
+    /// The parent starts a transfer in each vblank, and the words are received in the serial
+    /// interrupt.
+    pub fn exchange_every_frame(&mut self, word: u16) -> FrameExchange<'_> {
+        EXCHANGE.reset(word);
+        SIOMLT_SEND.set(word);
+
+        let parent = self.role() == Role::Parent;
+
+        // Safety: the handlers only touch registers and atomics, so don't allocate
+        let serial = unsafe {
+            add_interrupt_handler(Interrupt::Serial, |_| {
+                EXCHANGE.received(read_words(), SIOCNT.get() & ERROR != 0);
+                SIOMLT_SEND.set(EXCHANGE.word.load(Ordering::SeqCst));
+            })
+        };
+
+        let vblank = unsafe {
+            add_interrupt_handler(Interrupt::VBlank, move |_| {
+                EXCHANGE
+                    .frames_since_transfer
+                    .fetch_add(1, Ordering::SeqCst);
+
+                let status = SIOCNT.get();
+                if parent && status & ALL_READY != 0 && status & START == 0 {
+                    SIOMLT_SEND.set(EXCHANGE.word.load(Ordering::SeqCst));
+                    SIOCNT.set(status | START);
+                }
+            })
+        };
+
+        FrameExchange {
+            multiplayer: self,
+            seen_transfers: 0,
+            _serial: serial,
+            _vblank: vblank,
+        }
+    }
+
+    fn finish_transfer(
+        &mut self,
+        words: [u16; 4],
+        failed: bool,
+    ) -> Result<[Option<u16>; 4], LinkError> {
+        self.transferred = true;
+
+        if failed {
+            return Err(LinkError::TransferFailed);
+        }
+
+        self.connections.received(words)
+    }
+}
+
+fn read_words() -> [u16; 4] {
+    core::array::from_fn(|id| SIOMULTI.get(id))
+}
+
+/// Waits for the serial interrupt to be requested, returning `false` if it doesn't happen
+/// within [`TIMEOUT_FRAMES`].
+fn wait_for_transfer() -> bool {
+    let mut timeout = Timeout::frames(TIMEOUT_FRAMES);
+
+    while INTERRUPT_REQUEST.get() & SERIAL_INTERRUPT == 0 {
+        if timeout.expired() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// The state shared with the interrupt handlers of a [`FrameExchange`].
+struct Exchange {
+    word: AtomicU16,
+    received: [AtomicU16; 4],
+    failed: AtomicBool,
+    transfers: AtomicU32,
+    frames_since_transfer: AtomicU32,
+}
+
+static EXCHANGE: Exchange = Exchange {
+    word: AtomicU16::new(0),
+    received: [const { AtomicU16::new(NO_PLAYER) }; 4],
+    failed: AtomicBool::new(false),
+    transfers: AtomicU32::new(0),
+    frames_since_transfer: AtomicU32::new(0),
+};
+
+impl Exchange {
+    fn reset(&self, word: u16) {
+        self.word.store(word, Ordering::SeqCst);
+        for received in &self.received {
+            received.store(NO_PLAYER, Ordering::SeqCst);
+        }
+        self.failed.store(false, Ordering::SeqCst);
+        self.transfers.store(0, Ordering::SeqCst);
+        self.frames_since_transfer.store(0, Ordering::SeqCst);
+    }
+
+    fn received(&self, words: [u16; 4], failed: bool) {
+        for (received, word) in self.received.iter().zip(words) {
+            received.store(word, Ordering::SeqCst);
+        }
+        self.failed.store(failed, Ordering::SeqCst);
+        self.frames_since_transfer.store(0, Ordering::SeqCst);
+        self.transfers.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Words being exchanged once a frame, made by [`MultiPlayer::exchange_every_frame`]. The
+/// exchange stops when this is dropped.
+pub struct FrameExchange<'a> {
+    multiplayer: &'a mut MultiPlayer,
+    seen_transfers: u32,
+    _serial: InterruptHandler,
+    _vblank: InterruptHandler,
+}
+
+impl FrameExchange<'_> {
+    /// Changes the word sent in the following transfers.
+    pub fn set_word(&self, word: u16) {
+        EXCHANGE.word.store(word, Ordering::SeqCst);
+    }
+
+    /// How many transfers have happened.
+    #[must_use]
+    pub fn transfers(&self) -> u32 {
+        EXCHANGE.transfers.load(Ordering::SeqCst)
+    }
+
+    /// The words received in the latest transfer, in order of player id, or `None` for players
+    /// who aren't there. Fails if there hasn't been a transfer for several frames, rather than
+    /// returning the same words again.
+    pub fn latest(&mut self) -> Result<[Option<u16>; 4], LinkError> {
+        if EXCHANGE.frames_since_transfer.load(Ordering::SeqCst) > STALE_FRAMES {
+            return Err(if self.multiplayer.is_ready() {
+                LinkError::TimedOut
+            } else {
+                LinkError::NotReady
+            });
+        }
+
+        let (words, failed, transfers) = interrupt_free(|_| {
+            (
+                core::array::from_fn(|id| EXCHANGE.received[id].load(Ordering::SeqCst)),
+                EXCHANGE.failed.load(Ordering::SeqCst),
+                EXCHANGE.transfers.load(Ordering::SeqCst),
+            )
+        });
+
+        if transfers == 0 {
+            return Ok([None; 4]);
+        }
+
+        if transfers == self.seen_transfers {
+            // already checked, so just report the same words
+            return Ok(words.map(|word| (word != NO_PLAYER).then_some(word)));
+        }
+        self.seen_transfers = transfers;
+
+        self.multiplayer.finish_transfer(words, failed)
+    }
+
+    /// The state of the player with the given id, as of the last call to
+    /// [`latest`](FrameExchange::latest).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is 4 or more.
+    #[must_use]
+    pub fn status(&self, id: usize) -> PlayerStatus {
+        self.multiplayer.status(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn disconnected_players_are_reported_once(_gba: &mut crate::Gba) {
+        let mut connections = Connections::new();
+
+        assert_eq!(
+            connections.received([5, 6, NO_PLAYER, NO_PLAYER]),
+            Ok([Some(5), Some(6), None, None])
+        );
+        assert_eq!(connections.players[1], PlayerStatus::Connected);
+        assert_eq!(connections.players[2], PlayerStatus::NotConnected);
+
+        assert_eq!(
+            connections.received([7, NO_PLAYER, NO_PLAYER, NO_PLAYER]),
+            Err(LinkError::Disconnected(1))
+        );
+        assert_eq!(connections.players[1], PlayerStatus::Disconnected);
+
+        assert_eq!(
+            connections.received([8, NO_PLAYER, NO_PLAYER, NO_PLAYER]),
+            Ok([Some(8), None, None, None])
+        );
+    }
+}
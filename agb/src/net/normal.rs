@@ -0,0 +1,654 @@
+//! Sending a stream of bytes between two GBAs, or a GBA and a PC link adapter, over the link
+//! cable.
+//!
+//! In normal mode, one end of the cable drives the clock and the other follows it. Each
+//! transfer swaps a 32 bit word in both directions at once. [`Normal`] turns these transfers
+//! into a byte stream in each direction. Bytes are queued with [`Normal::send`] and read with
+//! [`Normal::recv`], and the transfers happen in the serial interrupt, so neither end has to
+//! wait for the other.
+//!
+//! One end has to be the [`Role::Parent`], which drives the clock, and the other the
+//! [`Role::Child`]. The parent transfers back to back while there is anything to send in either
+//! direction, and once a frame otherwise so that it notices when the child has something to
+//! send. Before each transfer the parent waits for the child to say it is ready on the SI line,
+//! so no words are lost while the child is busy.
+//!
+//! Bytes can still be lost if the cable is pulled out or a receive queue fills up. [`Framed`]
+//! adds a simple framing layer which checks each frame of bytes with a CRC and resends it until
+//! the other end acknowledges it.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::normal::SerialError> {
+//! use agb::net::normal::{ClockSpeed, Normal, Role};
+//!
+//! let mut serial = Normal::new(Role::Parent(ClockSpeed::KHz256)).expect("serial port in use");
+//! serial.send(b"hello")?;
+//!
+//! let mut reply = [0; 16];
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     vblank.wait_for_vblank();
+//!
+//!     let received = serial.recv(&mut reply)?;
+//!     if received > 0 {
+//!         agb::println!("received {:?}", &reply[..received]);
+//!     }
+//! #   break;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use portable_atomic::{AtomicBool, Ordering};
+
+use crate::{
+    interrupt::{add_interrupt_handler, interrupt_free, Interrupt, InterruptHandler},
+    util::crc16,
+};
+
+use super::{ByteQueue, SerialPort, SerialPortInUse, Timeout, RCNT, SIOCNT, SIODATA32};
+
+const INTERNAL_CLOCK: u16 = 1 << 0;
+const CLOCK_2MHZ: u16 = 1 << 1;
+const SI_HIGH: u16 = 1 << 2;
+const SO_HIGH: u16 = 1 << 3;
+const START: u16 = 1 << 7;
+const NORMAL_32_BIT_MODE: u16 = 0b01 << 12;
+const IRQ_ENABLE: u16 = 1 << 14;
+
+/// How many bytes each of the send and receive queues hold.
+const QUEUE_SIZE: usize = 256;
+/// How many bytes fit in a transfer, after the byte saying how many there are.
+const BYTES_PER_TRANSFER: usize = 3;
+/// How many times the parent checks whether the child is ready before leaving the next
+/// transfer to the vblank interrupt.
+const HANDSHAKE_CHECKS: u32 = 200;
+
+/// How long [`Normal::send`] and [`Normal::flush`] wait for room in the send queue, in frames.
+const SEND_TIMEOUT_FRAMES: u32 = 10;
+
+/// The speed the parent drives the clock at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSpeed {
+    /// 256KHz, which is what the link cable is made for.
+    KHz256,
+    /// 2MHz, which is 8 times faster but may be unreliable with some cables.
+    MHz2,
+}
+
+/// Which end of the cable this is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The end which drives the clock at the given speed, starting each transfer.
+    Parent(ClockSpeed),
+    /// The end which follows the other's clock.
+    Child,
+}
+
+/// The ways sending and receiving bytes can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerialError {
+    /// The bytes couldn't be sent in time, usually because the other end isn't there.
+    TimedOut,
+    /// The receive queue filled up, so some bytes received since the last call to
+    /// [`Normal::recv`] were dropped.
+    Overflow,
+    /// The frame is longer than [`MAX_FRAME_LENGTH`], or than the buffer to receive it into.
+    FrameTooLong,
+    /// The other end didn't acknowledge a frame, even after resending it.
+    NoAcknowledgement,
+}
+
+static SENDING: ByteQueue<QUEUE_SIZE> = ByteQueue::new();
+static RECEIVED: ByteQueue<QUEUE_SIZE> = ByteQueue::new();
+static OVERFLOWED: AtomicBool = AtomicBool::new(false);
+/// Whether the parent has started a transfer which hasn't finished yet.
+static TRANSFERRING: AtomicBool = AtomicBool::new(false);
+
+/// Takes up to [`BYTES_PER_TRANSFER`] bytes from the send queue. The lowest byte of the word is
+/// how many bytes follow it.
+fn next_word() -> u32 {
+    let mut word = 0;
+    let mut count = 0;
+
+    while count < BYTES_PER_TRANSFER {
+        let Some(byte) = SENDING.pop() else {
+            break;
+        };
+
+        count += 1;
+        word |= u32::from(byte) << (8 * count);
+    }
+
+    word | count as u32
+}
+
+/// Puts the bytes from a received word in the receive queue, returning whether there were any.
+/// Nothing connected is received as `0xffff_ffff`, which isn't a valid count so is ignored.
+fn receive_word(word: u32) -> bool {
+    let count = (word & 0xff) as usize;
+    if count == 0 || count > BYTES_PER_TRANSFER {
+        return false;
+    }
+
+    for i in 1..=count {
+        if !RECEIVED.push((word >> (8 * i)) as u8) {
+            OVERFLOWED.store(true, Ordering::SeqCst);
+        }
+    }
+
+    true
+}
+
+/// Starts a transfer from the parent if there isn't one going already and the child is ready.
+fn start_transfer(control: u16) -> bool {
+    if TRANSFERRING.load(Ordering::SeqCst) || SIOCNT.get() & SI_HIGH != 0 {
+        return false;
+    }
+
+    TRANSFERRING.store(true, Ordering::SeqCst);
+    SIODATA32.set(next_word());
+    SIOCNT.set(control | START);
+
+    true
+}
+
+/// The serial port in normal mode, see the [module level documentation](self).
+pub struct Normal {
+    role: Role,
+    control: u16,
+    _serial: InterruptHandler,
+    _vblank: Option<InterruptHandler>,
+    _port: SerialPort,
+}
+
+impl Normal {
+    /// Puts the serial port into normal mode as the given end of the cable, and starts
+    /// transferring in the background. Fails if something else is using the
+    /// [serial port](SerialPort).
+    pub fn new(role: Role) -> Result<Self, SerialPortInUse> {
+        let port = SerialPort::claim()?;
+
+        SENDING.clear();
+        RECEIVED.clear();
+        OVERFLOWED.store(false, Ordering::SeqCst);
+        TRANSFERRING.store(false, Ordering::SeqCst);
+
+        RCNT.set(0);
+
+        let control = match role {
+            Role::Parent(ClockSpeed::KHz256) => NORMAL_32_BIT_MODE | IRQ_ENABLE | INTERNAL_CLOCK,
+            Role::Parent(ClockSpeed::MHz2) => {
+                NORMAL_32_BIT_MODE | IRQ_ENABLE | INTERNAL_CLOCK | CLOCK_2MHZ
+            }
+            // the child holds SO high while it is busy, and low once it is ready for a transfer
+            Role::Child => NORMAL_32_BIT_MODE | IRQ_ENABLE | SO_HIGH,
+        };
+        SIOCNT.set(control);
+
+        // Safety: the handlers only touch registers and atomics, so don't allocate
+        let (serial, vblank) = match role {
+            Role::Parent(_) => unsafe {
+                let serial = add_interrupt_handler(Interrupt::Serial, move |_| {
+                    TRANSFERRING.store(false, Ordering::SeqCst);
+
+                    // keep going while the child is sending, as it may have more to send
+                    if receive_word(SIODATA32.get()) || !SENDING.is_empty() {
+                        for _ in 0..HANDSHAKE_CHECKS {
+                            if start_transfer(control) {
+                                break;
+                            }
+                        }
+                    }
+                });
+
+                let vblank = add_interrupt_handler(Interrupt::VBlank, move |_| {
+                    start_transfer(control);
+                });
+
+                (serial, Some(vblank))
+            },
+            Role::Child => unsafe {
+                let serial = add_interrupt_handler(Interrupt::Serial, move |_| {
+                    SIOCNT.set(control);
+                    receive_word(SIODATA32.get());
+
+                    SIODATA32.set(next_word());
+                    SIOCNT.set((control | START) & !SO_HIGH);
+                });
+
+                SIODATA32.set(next_word());
+                SIOCNT.set((control | START) & !SO_HIGH);
+
+                (serial, None)
+            },
+        };
+
+        Ok(Self {
+            role,
+            control,
+            _serial: serial,
+            _vblank: vblank,
+            _port: port,
+        })
+    }
+
+    /// Which end of the cable this is.
+    #[must_use]
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Queues `bytes` to be sent, waiting for room in the queue if it is full. Fails if there
+    /// isn't room for 10 frames, in which case the bytes before the one which didn't fit are
+    /// still sent.
+    pub fn send(&mut self, bytes: &[u8]) -> Result<(), SerialError> {
+        for &byte in bytes {
+            let mut timeout = Timeout::frames(SEND_TIMEOUT_FRAMES);
+
+            while !SENDING.push(byte) {
+                self.start_transfer();
+                if timeout.expired() {
+                    return Err(SerialError::TimedOut);
+                }
+            }
+        }
+
+        self.start_transfer();
+
+        Ok(())
+    }
+
+    /// Waits for every queued byte to be sent, failing if that takes more than 10 frames.
+    pub fn flush(&mut self) -> Result<(), SerialError> {
+        let mut timeout = Timeout::frames(SEND_TIMEOUT_FRAMES);
+
+        while !SENDING.is_empty() {
+            self.start_transfer();
+            if timeout.expired() {
+                return Err(SerialError::TimedOut);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves as many received bytes as fit into `buffer`, returning how many there were. This
+    /// doesn't wait for bytes to arrive, so returns 0 if nothing has been received.
+    ///
+    /// If bytes had to be dropped because the receive queue was full, this fails with
+    /// [`SerialError::Overflow`] once, and the bytes which weren't dropped are returned by the
+    /// next call.
+    pub fn recv(&mut self, buffer: &mut [u8]) -> Result<usize, SerialError> {
+        if OVERFLOWED.swap(false, Ordering::SeqCst) {
+            return Err(SerialError::Overflow);
+        }
+
+        let mut received = 0;
+        for slot in buffer.iter_mut() {
+            let Some(byte) = RECEIVED.pop() else {
+                break;
+            };
+
+            *slot = byte;
+            received += 1;
+        }
+
+        Ok(received)
+    }
+
+    /// Starts a transfer straight away rather than at the next vblank, if this is the parent.
+    fn start_transfer(&self) {
+        if matches!(self.role, Role::Parent(_)) {
+            interrupt_free(|_| start_transfer(self.control));
+        }
+    }
+}
+
+/// The most bytes which can be sent in one frame by [`Framed`].
+pub const MAX_FRAME_LENGTH: usize = 64;
+
+/// How many times [`Framed::send_frame`] resends a frame which isn't acknowledged.
+const RESENDS: usize = 3;
+/// How long [`Framed::send_frame`] waits for an acknowledgement, in frames.
+const ACKNOWLEDGEMENT_TIMEOUT_FRAMES: u32 = 10;
+
+const SYNC: u8 = 0x7e;
+const DATA: u8 = 1;
+const ACKNOWLEDGEMENT: u8 = 2;
+
+/// The sync byte, kind, sequence number and length.
+const HEADER_LENGTH: usize = 4;
+const MAX_PACKET_LENGTH: usize = HEADER_LENGTH + MAX_FRAME_LENGTH + 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Packet {
+    /// A frame, whose bytes are in the [`PacketReader`].
+    Data {
+        sequence: u8,
+        length: usize,
+    },
+    Acknowledgement {
+        sequence: u8,
+    },
+}
+
+/// Writes a packet to `buffer`, returning how long it is. A packet is the [`SYNC`] byte, its
+/// kind, sequence number and length, the bytes of the frame and then a little endian CRC-16
+/// of everything after the sync byte.
+fn write_packet(
+    buffer: &mut [u8; MAX_PACKET_LENGTH],
+    kind: u8,
+    sequence: u8,
+    bytes: &[u8],
+) -> usize {
+    let end = HEADER_LENGTH + bytes.len();
+
+    buffer[..HEADER_LENGTH].copy_from_slice(&[SYNC, kind, sequence, bytes.len() as u8]);
+    buffer[HEADER_LENGTH..end].copy_from_slice(bytes);
+
+    let crc = crc16(&buffer[1..end]);
+    buffer[end..end + 2].copy_from_slice(&crc.to_le_bytes());
+
+    end + 2
+}
+
+/// Finds packets in the bytes received, skipping anything which isn't a valid packet.
+struct PacketReader {
+    buffer: [u8; MAX_PACKET_LENGTH],
+    filled: usize,
+}
+
+impl PacketReader {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; MAX_PACKET_LENGTH],
+            filled: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Option<Packet> {
+        if self.filled == 0 && byte != SYNC {
+            return None;
+        }
+
+        self.buffer[self.filled] = byte;
+        self.filled += 1;
+
+        if self.filled < HEADER_LENGTH {
+            return None;
+        }
+
+        let [_, kind, sequence, length] = [0, 1, 2, 3].map(|i| self.buffer[i]);
+        let length = usize::from(length);
+
+        let valid_header = match kind {
+            DATA => length <= MAX_FRAME_LENGTH,
+            ACKNOWLEDGEMENT => length == 0,
+            _ => false,
+        };
+        if !valid_header {
+            self.filled = 0;
+            return None;
+        }
+
+        let end = HEADER_LENGTH + length;
+        if self.filled < end + 2 {
+            return None;
+        }
+        self.filled = 0;
+
+        let crc = u16::from_le_bytes([self.buffer[end], self.buffer[end + 1]]);
+        if crc16(&self.buffer[1..end]) != crc {
+            return None;
+        }
+
+        Some(if kind == DATA {
+            Packet::Data { sequence, length }
+        } else {
+            Packet::Acknowledgement { sequence }
+        })
+    }
+
+    /// The bytes of the last [`Packet::Data`] returned by [`push`](PacketReader::push).
+    fn frame(&self, length: usize) -> &[u8] {
+        &self.buffer[HEADER_LENGTH..HEADER_LENGTH + length]
+    }
+
+    fn reset(&mut self) {
+        self.filled = 0;
+    }
+}
+
+/// Frames of up to [`MAX_FRAME_LENGTH`] bytes sent over [`Normal`], which are checked with a
+/// CRC and resent until they are acknowledged.
+///
+/// Both ends of the cable need to use `Framed`. Frames are acknowledged when they are received,
+/// which happens in [`recv_frame`](Framed::recv_frame) and while
+/// [`send_frame`](Framed::send_frame) waits for its own acknowledgement. One received frame is
+/// kept until `recv_frame` is called, and any others sent in the meantime aren't acknowledged,
+/// so the other end sends them again later.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() -> Result<(), agb::net::normal::SerialError> {
+/// use agb::net::normal::{Framed, Normal, Role, MAX_FRAME_LENGTH};
+///
+/// let mut link = Framed::new(Normal::new(Role::Child).expect("serial port in use"));
+/// let mut frame = [0; MAX_FRAME_LENGTH];
+///
+/// loop {
+///     if let Some(length) = link.recv_frame(&mut frame)? {
+///         // send the frame back where it came from
+///         link.send_frame(&frame[..length])?;
+///     }
+/// #   break;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Framed {
+    serial: Normal,
+    reader: PacketReader,
+    next_sequence: u8,
+    last_received: Option<u8>,
+    pending: Option<(usize, [u8; MAX_FRAME_LENGTH])>,
+}
+
+impl Framed {
+    /// Sends and receives frames over `serial`.
+    #[must_use]
+    pub fn new(serial: Normal) -> Self {
+        Self {
+            serial,
+            reader: PacketReader::new(),
+            next_sequence: 0,
+            last_received: None,
+            pending: None,
+        }
+    }
+
+    /// Stops using frames, returning the serial port underneath.
+    #[must_use]
+    pub fn into_inner(self) -> Normal {
+        self.serial
+    }
+
+    /// Sends `bytes` as a frame and waits for the other end to acknowledge it, sending it up to
+    /// 3 more times if it doesn't within 10 frames.
+    pub fn send_frame(&mut self, bytes: &[u8]) -> Result<(), SerialError> {
+        if bytes.len() > MAX_FRAME_LENGTH {
+            return Err(SerialError::FrameTooLong);
+        }
+
+        let sequence = self.next_sequence;
+        let mut packet = [0; MAX_PACKET_LENGTH];
+        let length = write_packet(&mut packet, DATA, sequence, bytes);
+
+        for _ in 0..=RESENDS {
+            self.serial.send(&packet[..length])?;
+
+            let mut timeout = Timeout::frames(ACKNOWLEDGEMENT_TIMEOUT_FRAMES);
+            while !timeout.expired() {
+                if self.poll()? == Some(sequence) {
+                    self.next_sequence = sequence.wrapping_add(1);
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(SerialError::NoAcknowledgement)
+    }
+
+    /// Copies the next frame received into `buffer`, returning its length, or `None` if no
+    /// frame has been received yet. This doesn't wait for a frame to arrive.
+    ///
+    /// Fails with [`SerialError::FrameTooLong`] and drops the frame if it doesn't fit in
+    /// `buffer`, so use a buffer of [`MAX_FRAME_LENGTH`] bytes unless the frames are known to
+    /// be shorter.
+    pub fn recv_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, SerialError> {
+        while self.pending.is_none() {
+            let Some(byte) = self.next_byte() else {
+                break;
+            };
+
+            if let Some(packet) = self.reader.push(byte) {
+                self.handle(packet)?;
+            }
+        }
+
+        let Some((length, frame)) = self.pending.take() else {
+            return Ok(None);
+        };
+
+        let Some(buffer) = buffer.get_mut(..length) else {
+            return Err(SerialError::FrameTooLong);
+        };
+        buffer.copy_from_slice(&frame[..length]);
+
+        Ok(Some(length))
+    }
+
+    /// Handles the next byte received, returning the sequence number if it finishes an
+    /// acknowledgement.
+    fn poll(&mut self) -> Result<Option<u8>, SerialError> {
+        let Some(byte) = self.next_byte() else {
+            return Ok(None);
+        };
+
+        match self.reader.push(byte) {
+            Some(packet) => self.handle(packet),
+            None => Ok(None),
+        }
+    }
+
+    /// Acknowledges and keeps a data packet if there is room for it, returning the sequence
+    /// number of an acknowledgement.
+    fn handle(&mut self, packet: Packet) -> Result<Option<u8>, SerialError> {
+        match packet {
+            Packet::Acknowledgement { sequence } => Ok(Some(sequence)),
+            Packet::Data { sequence, length } => {
+                if self.last_received == Some(sequence) {
+                    // our acknowledgement was lost, so acknowledge it again
+                    self.acknowledge(sequence)?;
+                } else if self.pending.is_none() {
+                    let mut frame = [0; MAX_FRAME_LENGTH];
+                    frame[..length].copy_from_slice(self.reader.frame(length));
+                    self.pending = Some((length, frame));
+
+                    self.last_received = Some(sequence);
+                    self.acknowledge(sequence)?;
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    fn acknowledge(&mut self, sequence: u8) -> Result<(), SerialError> {
+        let mut packet = [0; MAX_PACKET_LENGTH];
+        let length = write_packet(&mut packet, ACKNOWLEDGEMENT, sequence, &[]);
+        self.serial.send(&packet[..length])
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let mut byte = [0];
+        match self.serial.recv(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            Ok(_) => None,
+            Err(_) => {
+                // some bytes were lost, so the packet being read can't be trusted
+                self.reader.reset();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn packets_are_found_among_noise(_gba: &mut crate::Gba) {
+        let mut reader = PacketReader::new();
+        let mut packet = [0; MAX_PACKET_LENGTH];
+
+        fn read(reader: &mut PacketReader, bytes: &[u8]) -> Option<Packet> {
+            bytes.iter().filter_map(|&byte| reader.push(byte)).last()
+        }
+
+        assert_eq!(read(&mut reader, &[1, 2, 0xff, 0xff]), None);
+
+        let length = write_packet(&mut packet, DATA, 7, b"hello");
+        assert_eq!(
+            read(&mut reader, &packet[..length]),
+            Some(Packet::Data {
+                sequence: 7,
+                length: 5
+            })
+        );
+        assert_eq!(reader.frame(5), b"hello");
+
+        // a corrupted packet is skipped
+        packet[5] ^= 0x20;
+        assert_eq!(read(&mut reader, &packet[..length]), None);
+
+        let length = write_packet(&mut packet, ACKNOWLEDGEMENT, 9, &[]);
+        assert_eq!(
+            read(&mut reader, &packet[..length]),
+            Some(Packet::Acknowledgement { sequence: 9 })
+        );
+    }
+
+    #[test_case]
+    fn words_carry_up_to_three_bytes(_gba: &mut crate::Gba) {
+        SENDING.clear();
+        RECEIVED.clear();
+
+        for byte in 1..=4 {
+            assert!(SENDING.push(byte));
+        }
+
+        let word = next_word();
+        assert_eq!(word, 0x0302_0103);
+        assert_eq!(next_word(), 0x0000_0401);
+        assert_eq!(next_word(), 0);
+
+        assert!(receive_word(word));
+        assert!(!receive_word(0xffff_ffff));
+        assert_eq!(
+            [
+                RECEIVED.pop(),
+                RECEIVED.pop(),
+                RECEIVED.pop(),
+                RECEIVED.pop()
+            ],
+            [Some(1), Some(2), Some(3), None]
+        );
+    }
+}
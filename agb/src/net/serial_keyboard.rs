@@ -0,0 +1,65 @@
+//! Reading characters from a keyboard attached to the link cable.
+//!
+//! The serial port has a UART mode which talks to anything using standard RS-232 style serial,
+//! such as a USB to serial adapter plugged into a PC. [`SerialKeyboard`] sets up a [`Uart`] at
+//! 9600 baud with 8 data bits, no parity and 1 stop bit, and sends and receives ASCII
+//! characters. Characters are received in the serial interrupt, so none are lost while the game
+//! is busy.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::SerialPortInUse> {
+//! use agb::net::serial_keyboard::SerialKeyboard;
+//!
+//! let mut keyboard = SerialKeyboard::new()?;
+//!
+//! loop {
+//!     while let Some(c) = keyboard.recv_char() {
+//!         agb::println!("typed {}", c);
+//!     }
+//! #   break;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{multiplayer::BaudRate, uart::Uart, SerialPortInUse};
+
+/// A keyboard on the serial port in UART mode, see the [module level documentation](self).
+pub struct SerialKeyboard {
+    uart: Uart,
+}
+
+impl SerialKeyboard {
+    /// Puts the serial port into UART mode at 9600 baud, 8N1. Fails if something else is using
+    /// the [serial port](super::SerialPort).
+    pub fn new() -> Result<Self, SerialPortInUse> {
+        Ok(Self {
+            uart: Uart::new(BaudRate::B9600, false)?,
+        })
+    }
+
+    /// Returns the next character received, or `None` if nothing has been received. Bytes
+    /// which aren't ASCII, or which were dropped by the [`Uart`], are skipped.
+    pub fn recv_char(&mut self) -> Option<char> {
+        let mut byte = [0];
+        loop {
+            match self.uart.read(&mut byte) {
+                Ok(0) => return None,
+                Ok(_) if byte[0].is_ascii() => return Some(char::from(byte[0])),
+                // errors are only reported once, so the bytes after them are read next time
+                Ok(_) | Err(_) => {}
+            }
+        }
+    }
+
+    /// Sends a character, waiting for the previous one to finish sending first. Characters
+    /// which aren't ASCII are sent as `?`.
+    pub fn send_char(&mut self, c: char) {
+        let byte = if c.is_ascii() { c as u8 } else { b'?' };
+
+        // without flow control the hardware is always ready to send eventually
+        let _ = self.uart.write(&[byte]);
+    }
+}
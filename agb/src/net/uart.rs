@@ -0,0 +1,262 @@
+//! Talking to a PC, or anything else with a standard serial port, over the link cable.
+//!
+//! With a USB to UART adapter wired to the link cable, the serial port's UART mode gives a
+//! two way connection to a PC at up to 115200 baud, using 8 data bits, no parity and 1 stop bit.
+//! [`Uart`] receives bytes in the serial interrupt into a queue, so none are lost while the game
+//! is busy, and sends bytes by waiting for the hardware to be ready for each one.
+//!
+//! The UART can also be used as a console for [`println!`](crate::println) with
+//! [`Uart::mirror_println`]. Anything printed while mgba isn't there, such as on real hardware,
+//! is then sent over the UART instead, so it shows up in a terminal on the PC.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() -> Result<(), agb::net::uart::UartError> {
+//! use agb::net::{multiplayer::BaudRate, uart::Uart};
+//!
+//! let mut uart = Uart::new(BaudRate::B115200, false).expect("serial port in use");
+//! uart.mirror_println(true);
+//!
+//! agb::println!("hello from the GBA");
+//!
+//! let mut command = [0; 32];
+//! loop {
+//!     let received = uart.read(&mut command)?;
+//!     // echo it back
+//!     uart.write(&command[..received])?;
+//! #   break;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use core::fmt::Write;
+
+use portable_atomic::{AtomicBool, Ordering};
+
+use crate::interrupt::{add_interrupt_handler, Interrupt, InterruptHandler};
+
+use super::{
+    multiplayer::BaudRate, ByteQueue, SerialPort, SerialPortInUse, Timeout, RCNT, SIOCNT, SIODATA8,
+};
+
+const CTS_ENABLE: u16 = 1 << 2;
+const SEND_FULL: u16 = 1 << 4;
+const RECEIVE_EMPTY: u16 = 1 << 5;
+const ERROR: u16 = 1 << 6;
+const EIGHT_BITS: u16 = 1 << 7;
+const SEND_ENABLE: u16 = 1 << 10;
+const RECEIVE_ENABLE: u16 = 1 << 11;
+const UART_MODE: u16 = 0b11 << 12;
+const IRQ_ENABLE: u16 = 1 << 14;
+
+/// How many received bytes can wait to be read.
+const QUEUE_SIZE: usize = 256;
+/// How long sending a byte can wait for the hardware, in frames. This only runs out if flow
+/// control is on and the other end isn't ready.
+const SEND_TIMEOUT_FRAMES: u32 = 10;
+
+static RECEIVED: ByteQueue<QUEUE_SIZE> = ByteQueue::new();
+static OVERRUN: AtomicBool = AtomicBool::new(false);
+static FRAMING_ERROR: AtomicBool = AtomicBool::new(false);
+static MIRROR_PRINTLN: AtomicBool = AtomicBool::new(false);
+
+/// The ways reading or writing can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UartError {
+    /// Bytes arrived faster than they were read, so some were dropped.
+    Overrun,
+    /// A byte arrived with the wrong framing, usually because the other end is using a
+    /// different baud rate, so it was dropped.
+    Framing,
+    /// A byte couldn't be sent in time, because the other end didn't allow it with flow control.
+    TimedOut,
+}
+
+/// The serial port in UART mode, see the [module level documentation](self).
+pub struct Uart {
+    _handler: InterruptHandler,
+    _port: SerialPort,
+}
+
+impl Uart {
+    /// Puts the serial port into UART mode with the given baud rate, 8 data bits, no parity and
+    /// 1 stop bit, and starts receiving bytes in the serial interrupt.
+    ///
+    /// With `flow_control`, the GBA tells the other end when it is ready to receive with RTS
+    /// on the SD line, and only sends while the other end holds CTS on the SC line low.
+    ///
+    /// Fails if something else is using the [serial port](SerialPort).
+    pub fn new(baud_rate: BaudRate, flow_control: bool) -> Result<Self, SerialPortInUse> {
+        let port = SerialPort::claim()?;
+
+        RECEIVED.clear();
+        OVERRUN.store(false, Ordering::SeqCst);
+        FRAMING_ERROR.store(false, Ordering::SeqCst);
+
+        RCNT.set(0);
+
+        let mut control = UART_MODE | EIGHT_BITS | baud_rate as u16;
+        if flow_control {
+            control |= CTS_ENABLE;
+        }
+
+        // the mode has to be selected before sending and receiving are enabled
+        SIOCNT.set(control);
+
+        // Safety: the handler only touches registers and atomics, so doesn't allocate
+        let handler = unsafe { add_interrupt_handler(Interrupt::Serial, |_| receive_bytes()) };
+
+        SIOCNT.set(control | SEND_ENABLE | RECEIVE_ENABLE | IRQ_ENABLE);
+
+        Ok(Self {
+            _handler: handler,
+            _port: port,
+        })
+    }
+
+    /// Moves as many received bytes as fit into `buffer`, returning how many there were. This
+    /// doesn't wait for bytes to arrive, so returns 0 if nothing has been received.
+    ///
+    /// If bytes were dropped since the last call, this fails once with
+    /// [`UartError::Overrun`] or [`UartError::Framing`], and the bytes which weren't dropped are
+    /// returned by the next call.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, UartError> {
+        if OVERRUN.swap(false, Ordering::SeqCst) {
+            return Err(UartError::Overrun);
+        }
+        if FRAMING_ERROR.swap(false, Ordering::SeqCst) {
+            return Err(UartError::Framing);
+        }
+
+        let mut received = 0;
+        for slot in buffer.iter_mut() {
+            let Some(byte) = RECEIVED.pop() else {
+                break;
+            };
+
+            *slot = byte;
+            received += 1;
+        }
+
+        Ok(received)
+    }
+
+    /// Sends `bytes`, waiting for the hardware to be ready for each one. Fails if a byte can't
+    /// be sent within 10 frames, in which case the bytes before it have been sent.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), UartError> {
+        bytes.iter().try_for_each(|&byte| send_byte(byte))
+    }
+
+    /// Waits for the last byte written to be taken by the hardware for sending.
+    pub fn flush(&mut self) -> Result<(), UartError> {
+        wait_to_send()
+    }
+
+    /// Sets whether [`println!`](crate::println) also prints over the UART when mgba isn't
+    /// there. Lines end with `\r\n` for terminals. This stops when the `Uart` is dropped.
+    pub fn mirror_println(&mut self, enabled: bool) {
+        MIRROR_PRINTLN.store(enabled, Ordering::SeqCst);
+    }
+}
+
+impl Write for Uart {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl Drop for Uart {
+    fn drop(&mut self) {
+        MIRROR_PRINTLN.store(false, Ordering::SeqCst);
+    }
+}
+
+fn receive_bytes() {
+    loop {
+        let status = SIOCNT.get();
+        if status & RECEIVE_EMPTY != 0 {
+            return;
+        }
+
+        // the error flag is cleared by reading the control register, which we just did
+        let byte = SIODATA8.get();
+        if status & ERROR != 0 {
+            FRAMING_ERROR.store(true, Ordering::SeqCst);
+        } else if !RECEIVED.push(byte) {
+            OVERRUN.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+fn wait_to_send() -> Result<(), UartError> {
+    let mut timeout = Timeout::frames(SEND_TIMEOUT_FRAMES);
+
+    while SIOCNT.get() & SEND_FULL != 0 {
+        if timeout.expired() {
+            return Err(UartError::TimedOut);
+        }
+    }
+
+    Ok(())
+}
+
+fn send_byte(byte: u8) -> Result<(), UartError> {
+    wait_to_send()?;
+    SIODATA8.set(byte);
+    Ok(())
+}
+
+/// Writes to the UART for [`println!`](crate::println), turning `\n` into `\r\n`.
+struct Console;
+
+impl Write for Console {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                send_byte(b'\r').map_err(|_| core::fmt::Error)?;
+            }
+            send_byte(byte).map_err(|_| core::fmt::Error)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn mirror_println(output: core::fmt::Arguments) {
+    if MIRROR_PRINTLN.load(Ordering::SeqCst) {
+        let _ = writeln!(Console, "{output}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn errors_are_reported_once(_gba: &mut crate::Gba) {
+        let mut uart = Uart::new(BaudRate::B115200, false).unwrap();
+        let mut buffer = [0; 4];
+
+        assert_eq!(uart.read(&mut buffer), Ok(0));
+
+        OVERRUN.store(true, Ordering::SeqCst);
+        FRAMING_ERROR.store(true, Ordering::SeqCst);
+        for byte in b"hello" {
+            RECEIVED.push(*byte);
+        }
+
+        assert_eq!(uart.read(&mut buffer), Err(UartError::Overrun));
+        assert_eq!(uart.read(&mut buffer), Err(UartError::Framing));
+        assert_eq!(uart.read(&mut buffer), Ok(4));
+        assert_eq!(&buffer, b"hell");
+        assert_eq!(uart.read(&mut buffer), Ok(1));
+
+        uart.mirror_println(true);
+        drop(uart);
+        assert!(!MIRROR_PRINTLN.load(Ordering::SeqCst));
+    }
+}
@@ -1,13 +1,16 @@
 use core::{fmt::Write, panic::PanicInfo};
 
-use alloc::{collections::TryReserveError, format, vec::Vec};
 use qrcodegen_no_heap::DataTooLong;
 
 use crate::{
     backtrace,
-    display::{bitmap3::Bitmap3, busy_wait_for_vblank, HEIGHT, WIDTH},
+    collections::ArrayString,
+    display::{
+        affine::AffineMatrixBackground, bitmap3::Bitmap3, busy_wait_for_vblank, HEIGHT, WIDTH,
+    },
     dma::dma3_exclusive,
-    mgba, syscall, ExternalAllocator,
+    memory_mapped::MemoryMapped,
+    mgba, syscall,
 };
 
 mod text;
@@ -19,21 +22,49 @@ static WEBSITE: &str = {
     }
 };
 
+/// The longest URL put in the QR code.
+const QR_CODE_DATA_LENGTH: usize = 256;
+
+/// The number of return addresses written out in full, for looking up with `addr2line` when
+/// there is no way to scan the QR code.
+const ADDRESSES_SHOWN: usize = 4;
+
+/// Puts the hardware into a state where the crash screen can be seen, whatever the game was
+/// doing when it panicked.
+fn reset_hardware() {
+    unsafe {
+        // stop every DMA, including the ones feeding the sound FIFOs which would otherwise
+        // carry on playing whatever memory follows the sound buffers
+        for channel in 0..4 {
+            MemoryMapped::<u32>::new(0x0400_00b8 + 0x0c * channel).set(0);
+        }
+        // sound off
+        MemoryMapped::<u16>::new(0x0400_0084).set(0);
+
+        // no blending or mosaic, which could hide the screen
+        MemoryMapped::<u16>::new(0x0400_0050).set(0);
+        MemoryMapped::<u16>::new(0x0400_004c).set(0);
+
+        // bitmap modes draw background 2, which could have been rotated or scaled
+        MemoryMapped::<u16>::new(0x0400_000c).set(0);
+        MemoryMapped::<AffineMatrixBackground>::new(0x0400_0020)
+            .set(AffineMatrixBackground::default());
+    }
+}
+
+/// Shows the panic message and stack trace on screen. This doesn't allocate, since the panic
+/// could have been running out of memory.
 pub fn render_backtrace(trace: &backtrace::Frames, info: &PanicInfo) -> ! {
-    critical_section::with(|_cs| {
+    crate::interrupt::interrupt_free(|_| {
         dma3_exclusive(|| {
             // SAFETY: This is not fine, but we're crashing anyway. The loop at the end should stop anything bad happening
             let mut gba = unsafe { crate::Gba::new_in_entry() };
 
-            gba.dma.dma().dma3.disable();
+            reset_hardware();
             let mut gfx = gba.display.video.bitmap3();
             gfx.clear(0xFFFF);
 
-            let qrcode_string_data = if WEBSITE.is_empty() {
-                format!("{trace}")
-            } else {
-                format!("{WEBSITE}{trace}")
-            };
+            let qrcode_string_data = qr_code_data(WEBSITE, trace);
             crate::println!("Stack trace: {qrcode_string_data}");
 
             let location = draw_qr_code(&mut gfx, &qrcode_string_data);
@@ -46,6 +77,12 @@ pub fn render_backtrace(trace: &backtrace::Frames, info: &PanicInfo) -> ! {
                 if WEBSITE.is_empty() { "" } else { "\n" }
             );
 
+            for (i, address) in trace.addresses().iter().take(ADDRESSES_SHOWN).enumerate() {
+                let separator = if i % 2 == 0 { "\n" } else { " " };
+                let _ = write!(&mut trace_text_render, "{separator}{address:08x}");
+            }
+            let _ = writeln!(&mut trace_text_render);
+
             let trace_location = trace_text_render.head_y_position();
 
             let mut panic_text_render = text::BitmapTextRender::new(
@@ -71,25 +108,33 @@ pub fn render_backtrace(trace: &backtrace::Frames, info: &PanicInfo) -> ! {
 }
 const PADDING: i32 = 8;
 
+/// The URL for the QR code. If the trace is too long to fit, it is cut off at the last frame
+/// which does, keeping room for the format marker since the decoder needs it even then.
+fn qr_code_data(website: &str, trace: &backtrace::Frames) -> ArrayString<QR_CODE_DATA_LENGTH> {
+    let mut frames = ArrayString::<{ QR_CODE_DATA_LENGTH - backtrace::FORMAT_MARKER.len() }>::new();
+    let _ = write!(&mut frames, "{website}{}", trace.encoded_frames());
+
+    let mut data = ArrayString::new();
+    data.push_str(&frames);
+    data.push_str(backtrace::FORMAT_MARKER);
+    data
+}
+
+const MAX_VERSION: qrcodegen_no_heap::Version = qrcodegen_no_heap::Version::new(6);
+
+/// Kept on the stack rather than the heap, so that a QR code can still be made when the panic
+/// was caused by running out of memory.
 struct QrCodeBuffers {
-    temp_buffer: Vec<u8, ExternalAllocator>,
-    out_buffer: Vec<u8, ExternalAllocator>,
-    version: qrcodegen_no_heap::Version,
+    temp_buffer: [u8; MAX_VERSION.buffer_len()],
+    out_buffer: [u8; MAX_VERSION.buffer_len()],
 }
 
 impl QrCodeBuffers {
-    fn new(version: qrcodegen_no_heap::Version) -> Result<Self, TryReserveError> {
-        let buffer_length = version.buffer_len();
-        let mut temp = Vec::try_with_capacity_in(buffer_length, ExternalAllocator)?;
-        let mut out = Vec::try_with_capacity_in(buffer_length, ExternalAllocator)?;
-        temp.resize(buffer_length, 0);
-        out.resize(buffer_length, 0);
-
-        Ok(Self {
-            temp_buffer: temp,
-            out_buffer: out,
-            version,
-        })
+    fn new() -> Self {
+        Self {
+            temp_buffer: [0; MAX_VERSION.buffer_len()],
+            out_buffer: [0; MAX_VERSION.buffer_len()],
+        }
     }
 
     fn generate_qr_code(&mut self, data: &str) -> Result<qrcodegen_no_heap::QrCode, DataTooLong> {
@@ -99,7 +144,7 @@ impl QrCodeBuffers {
             &mut self.out_buffer,
             qrcodegen_no_heap::QrCodeEcc::Medium,
             qrcodegen_no_heap::Version::MIN,
-            self.version,
+            MAX_VERSION,
             None,
             true,
         )
@@ -108,12 +153,7 @@ impl QrCodeBuffers {
 
 /// Returns the width / height of the QR code + padding in pixels
 fn draw_qr_code(gfx: &mut Bitmap3<'_>, qrcode_string_data: &str) -> i32 {
-    const MAX_VERSION: qrcodegen_no_heap::Version = qrcodegen_no_heap::Version::new(6);
-
-    let Ok(mut buffers) = QrCodeBuffers::new(MAX_VERSION) else {
-        crate::println!("Failed to allocate memory to generate QR code");
-        return PADDING;
-    };
+    let mut buffers = QrCodeBuffers::new();
 
     let qr_code = buffers.generate_qr_code(qrcode_string_data);
 
@@ -141,16 +181,27 @@ fn draw_qr_code(gfx: &mut Bitmap3<'_>, qrcode_string_data: &str) -> i32 {
 
 #[cfg(test)]
 mod tests {
-    use super::QrCodeBuffers;
-
-    const MAX_VERSION: qrcodegen_no_heap::Version = qrcodegen_no_heap::Version::new(6);
+    use super::{qr_code_data, QrCodeBuffers, QR_CODE_DATA_LENGTH};
+    use crate::backtrace::Frames;
 
     #[test_case]
     fn check_qr_code_generation(_: &mut crate::Gba) {
-        let mut buffers =
-            QrCodeBuffers::new(MAX_VERSION).expect("should be able to allocate buffers");
+        let mut buffers = QrCodeBuffers::new();
         buffers
             .generate_qr_code("https://agbrs.dev/crash#09rESxF0r0Cz06hv1")
             .expect("should be able to generate qr code");
     }
+
+    #[test_case]
+    fn long_traces_keep_the_format_marker(_: &mut crate::Gba) {
+        let website = "a".repeat(240);
+        let trace = Frames::from_addresses(&[0x0300_0000; 32]);
+
+        let data = qr_code_data(&website, &trace);
+
+        // two 6 character frames fit alongside the website and the marker
+        assert_eq!(data.len(), 240 + 2 * 6 + 2);
+        assert!(data.len() <= QR_CODE_DATA_LENGTH);
+        assert!(data.ends_with("v1"));
+    }
 }
@@ -0,0 +1,101 @@
+//! Saving battery by putting the CPU to sleep.
+//!
+//! [`halt_until`] stops the CPU until an interrupt happens, which is what
+//! [`VBlank::wait_for_vblank`](crate::interrupt::VBlank::wait_for_vblank) does while waiting
+//! for the next frame. [`stop`] goes much further, turning off the display, sound and CPU until
+//! a button combination is pressed, and is what games use for a sleep mode.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::input::{Button, ButtonController};
+//!
+//! let mut input = ButtonController::new();
+//! let sleep = Button::SELECT | Button::L | Button::R;
+//!
+//! loop {
+//!     input.update();
+//!     if input.is_pressed(Button::SELECT) && input.is_pressed(Button::L) && input.is_pressed(Button::R) {
+//!         // wait for the buttons to be released, otherwise they'd wake it straight back up
+//!         while input.is_pressed(sleep) {
+//!             input.update();
+//!         }
+//!
+//!         agb::power::stop(sleep);
+//!     }
+//! #   break;
+//! }
+//! # }
+//! ```
+
+use crate::{
+    display::DISPLAY_CONTROL,
+    input::Button,
+    interrupt::{with_interrupts_enabled, Interrupt},
+    memory_mapped::MemoryMapped,
+    syscall,
+};
+
+const KEY_CONTROL: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0132) };
+const SOUND_CONTROL_X: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0084) };
+
+const FORCED_BLANK: u16 = 1 << 7;
+const KEY_IRQ_ENABLE: u16 = 1 << 14;
+const KEY_IRQ_ALL_PRESSED: u16 = 1 << 15;
+
+/// Halts the CPU until one of `interrupts` happens. Any other interrupts are handled as usual
+/// while halted, but don't end the wait.
+///
+/// The interrupts don't need to have handlers, they are enabled for as long as the CPU is
+/// halted. This must not be called with interrupts disabled, such as inside
+/// [`critical_section::with`], since then nothing could wake the CPU.
+pub fn halt_until(interrupts: &[Interrupt]) {
+    let mask = interrupts
+        .iter()
+        .fold(0, |mask, &interrupt| mask | (1 << interrupt as u16));
+
+    with_interrupts_enabled(mask, || syscall::interrupt_wait(true, mask));
+}
+
+/// Puts the GBA into its lowest power state until every button in `wake_buttons` is held down
+/// at once.
+///
+/// While stopped nothing runs at all, including timers, interrupts and the sound mixer. To
+/// follow the hardware's requirements the display is blanked and sound is turned off before
+/// stopping, and both are put back afterwards. Turning sound off resets the registers of the
+/// [DMG sound](crate::sound::dmg) channels, so set those up again after waking if you use
+/// them.
+///
+/// Make sure `wake_buttons` aren't being held when calling this, since then it will wake up
+/// straight away.
+pub fn stop(wake_buttons: Button) {
+    let display_control = DISPLAY_CONTROL.get();
+    let sound_control = SOUND_CONTROL_X.get();
+    let key_control = KEY_CONTROL.get();
+
+    DISPLAY_CONTROL.set(display_control | FORCED_BLANK);
+    SOUND_CONTROL_X.set(0);
+    KEY_CONTROL.set(wake_buttons.bits() as u16 | KEY_IRQ_ENABLE | KEY_IRQ_ALL_PRESSED);
+
+    with_interrupts_enabled(1 << Interrupt::Keypad as u16, syscall::stop);
+
+    KEY_CONTROL.set(key_control);
+    SOUND_CONTROL_X.set(sound_control);
+    DISPLAY_CONTROL.set(display_control);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn halt_until_vblank_waits_for_vblank(_gba: &mut crate::Gba) {
+        const VCOUNT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0006) };
+
+        halt_until(&[Interrupt::VBlank]);
+        assert!(VCOUNT.get() >= 160, "should wake at the start of vblank");
+
+        halt_until(&[Interrupt::VBlank, Interrupt::HBlank]);
+    }
+}
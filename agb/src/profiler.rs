@@ -0,0 +1,476 @@
+//! Measuring how long named sections of code take each frame.
+//!
+//! Wrap the code you want to measure in [`profile!`](crate::profile), then call [`end_frame`]
+//! once per frame to get a [`FrameReport`] of how long each section took. Sections can be
+//! nested, and each one records both its inclusive time (everything inside it) and its
+//! exclusive time (everything inside it apart from nested sections).
+//!
+//! Profiling is only done when the `profiling` feature of agb is enabled. Without it,
+//! [`profile!`](crate::profile) compiles down to just the code inside it and every report is
+//! empty, so the calls can be left in place.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::{profile, profiler, timer::{Divider, MonotonicClock}};
+//!
+//! let timers = gba.timers.timers();
+//! profiler::install(MonotonicClock::new(timers.timer2, timers.timer3, Divider::Divider1));
+//!
+//! loop {
+//!     profile!("physics", {
+//!         // ...
+//!     });
+//!
+//!     profiler::end_frame().log();
+//! #   break;
+//! }
+//! # }
+//! ```
+//!
+//! At most [`MAX_SECTIONS`] different names are recorded each frame and at most
+//! [`MAX_DEPTH`] sections can be nested. Anything beyond that is ignored. A section which
+//! contains itself, for example in a recursive function, counts its nested time more than once
+//! in its inclusive time.
+
+use crate::{
+    display::bitmap3::Bitmap3,
+    timer::{MonotonicClock, TimerController},
+};
+
+/// The most different section names which will be recorded in a single frame.
+pub const MAX_SECTIONS: usize = 16;
+/// The deepest that sections can be nested and still be recorded.
+pub const MAX_DEPTH: usize = 8;
+
+const CYCLES_PER_SCANLINE: u32 = 1232;
+
+/// How long a single named section took over a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionReport {
+    /// The name given to [`profile!`](crate::profile).
+    pub name: &'static str,
+    /// CPU cycles spent in the section, including nested sections.
+    pub inclusive_cycles: u32,
+    /// CPU cycles spent in the section, not including nested sections.
+    pub exclusive_cycles: u32,
+    /// How many times the section was entered.
+    pub calls: u16,
+}
+
+impl SectionReport {
+    const EMPTY: Self = Self {
+        name: "",
+        inclusive_cycles: 0,
+        exclusive_cycles: 0,
+        calls: 0,
+    };
+
+    /// The inclusive time as a number of scanlines, the time it takes the display to draw one
+    /// line of the screen. There are 228 scanlines in a frame.
+    #[must_use]
+    pub fn inclusive_scanlines(&self) -> u32 {
+        self.inclusive_cycles.div_ceil(CYCLES_PER_SCANLINE)
+    }
+
+    /// The exclusive time as a number of scanlines.
+    #[must_use]
+    pub fn exclusive_scanlines(&self) -> u32 {
+        self.exclusive_cycles.div_ceil(CYCLES_PER_SCANLINE)
+    }
+}
+
+/// The time taken by each section in a frame, returned by [`end_frame`].
+#[derive(Clone, Copy, Debug)]
+pub struct FrameReport {
+    sections: [SectionReport; MAX_SECTIONS],
+    len: usize,
+}
+
+impl FrameReport {
+    const EMPTY: Self = Self {
+        sections: [SectionReport::EMPTY; MAX_SECTIONS],
+        len: 0,
+    };
+
+    /// Each section entered during the frame, in the order they were first entered.
+    #[must_use]
+    pub fn sections(&self) -> &[SectionReport] {
+        &self.sections[..self.len]
+    }
+
+    /// Prints a line for each section to the mGBA debug output.
+    pub fn log(&self) {
+        for section in self.sections() {
+            crate::println!(
+                "{}: {} cycles ({} scanlines), {} exclusive, {} calls",
+                section.name,
+                section.inclusive_cycles,
+                section.inclusive_scanlines(),
+                section.exclusive_cycles,
+                section.calls
+            );
+        }
+    }
+
+    /// Draws a bar for each section across the top of the screen, one scanline of time per
+    /// pixel. Each bar is 4 pixels tall with a gap of 1 pixel between bars, in the order
+    /// returned by [`sections`](FrameReport::sections).
+    pub fn draw_bars(&self, bitmap: &mut Bitmap3, colour: u16) {
+        for (i, section) in self.sections().iter().enumerate() {
+            let length = section
+                .inclusive_scanlines()
+                .min(crate::display::WIDTH as u32);
+            let top = i as i32 * 5;
+
+            for y in top..top + 4 {
+                for x in 0..length as i32 {
+                    bitmap.draw_point(x, y, colour);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "profiling", test))]
+mod state {
+    use super::{FrameReport, SectionReport, MAX_DEPTH, MAX_SECTIONS};
+
+    #[derive(Clone, Copy)]
+    struct OpenSection {
+        index: Option<usize>,
+        start: u64,
+        nested: u64,
+    }
+
+    pub(super) struct ProfilerState {
+        report: FrameReport,
+        open: [OpenSection; MAX_DEPTH],
+        depth: usize,
+    }
+
+    impl ProfilerState {
+        pub(super) const fn new() -> Self {
+            Self {
+                report: FrameReport::EMPTY,
+                open: [OpenSection {
+                    index: None,
+                    start: 0,
+                    nested: 0,
+                }; MAX_DEPTH],
+                depth: 0,
+            }
+        }
+
+        fn section_index(&mut self, name: &'static str) -> Option<usize> {
+            let report = &mut self.report;
+
+            if let Some(index) = report.sections().iter().position(|s| s.name == name) {
+                return Some(index);
+            }
+
+            if report.len == MAX_SECTIONS {
+                return None;
+            }
+
+            report.sections[report.len] = SectionReport {
+                name,
+                ..SectionReport::EMPTY
+            };
+            report.len += 1;
+
+            Some(report.len - 1)
+        }
+
+        pub(super) fn enter(&mut self, name: &'static str, now: u64) {
+            if self.depth < MAX_DEPTH {
+                let index = self.section_index(name);
+                self.open[self.depth] = OpenSection {
+                    index,
+                    start: now,
+                    nested: 0,
+                };
+            }
+
+            self.depth += 1;
+        }
+
+        pub(super) fn exit(&mut self, now: u64, ticks_to_cycles: impl Fn(u64) -> u64) {
+            let Some(depth) = self.depth.checked_sub(1) else {
+                return;
+            };
+            self.depth = depth;
+
+            if depth >= MAX_DEPTH {
+                return;
+            }
+
+            let open = self.open[depth];
+            let elapsed = now - open.start;
+
+            if let Some(parent) = depth.checked_sub(1) {
+                self.open[parent].nested += elapsed;
+            }
+
+            if let Some(index) = open.index {
+                let section = &mut self.report.sections[index];
+
+                let inclusive = ticks_to_cycles(elapsed) as u32;
+                let exclusive = ticks_to_cycles(elapsed - open.nested) as u32;
+
+                section.inclusive_cycles = section.inclusive_cycles.saturating_add(inclusive);
+                section.exclusive_cycles = section.exclusive_cycles.saturating_add(exclusive);
+                section.calls = section.calls.saturating_add(1);
+            }
+        }
+
+        pub(super) fn take_report(&mut self) -> FrameReport {
+            // sections which are still open carry on into the next frame
+            for open in &mut self.open[..self.depth.min(MAX_DEPTH)] {
+                open.index = None;
+            }
+
+            core::mem::replace(&mut self.report, FrameReport::EMPTY)
+        }
+    }
+}
+
+#[cfg(feature = "profiling")]
+mod global {
+    use core::cell::RefCell;
+
+    use critical_section::Mutex;
+
+    use super::{state::ProfilerState, FrameReport};
+    use crate::{interrupt::interrupt_free, timer::MonotonicClock};
+
+    struct Profiler {
+        clock: Option<MonotonicClock>,
+        state: ProfilerState,
+    }
+
+    static PROFILER: Mutex<RefCell<Profiler>> = Mutex::new(RefCell::new(Profiler {
+        clock: None,
+        state: ProfilerState::new(),
+    }));
+
+    pub(super) fn install(clock: MonotonicClock) -> Option<MonotonicClock> {
+        interrupt_free(|token| {
+            let mut profiler = PROFILER.borrow_ref_mut(token.critical_section());
+            profiler.state = ProfilerState::new();
+            profiler.clock.replace(clock)
+        })
+    }
+
+    pub(super) fn uninstall() -> Option<MonotonicClock> {
+        interrupt_free(|token| {
+            PROFILER
+                .borrow_ref_mut(token.critical_section())
+                .clock
+                .take()
+        })
+    }
+
+    pub(super) fn enter(name: &'static str) {
+        interrupt_free(|token| {
+            let profiler = &mut *PROFILER.borrow_ref_mut(token.critical_section());
+            if let Some(clock) = &profiler.clock {
+                profiler.state.enter(name, clock.now());
+            }
+        });
+    }
+
+    pub(super) fn exit() {
+        interrupt_free(|token| {
+            let profiler = &mut *PROFILER.borrow_ref_mut(token.critical_section());
+            if let Some(clock) = &profiler.clock {
+                profiler
+                    .state
+                    .exit(clock.now(), |ticks| clock.ticks_to_cycles(ticks));
+            }
+        });
+    }
+
+    pub(super) fn end_frame() -> FrameReport {
+        interrupt_free(|token| {
+            PROFILER
+                .borrow_ref_mut(token.critical_section())
+                .state
+                .take_report()
+        })
+    }
+}
+
+/// Starts profiling using `clock` to measure time, returning the clock previously in use.
+///
+/// Without the `profiling` feature this gives `clock` straight back.
+pub fn install(clock: MonotonicClock) -> Option<MonotonicClock> {
+    #[cfg(feature = "profiling")]
+    return global::install(clock);
+
+    #[cfg(not(feature = "profiling"))]
+    Some(clock)
+}
+
+/// Starts profiling using the first pair of adjacent timers which are free, returning whether
+/// it found any. If it didn't, nothing is profiled and every report is empty, so a game can
+/// call this unconditionally and just go without profiling while it needs the timers itself.
+///
+/// Any clock previously in use is dropped first, releasing its timers. Without the `profiling`
+/// feature this doesn't claim any timers and returns `false`.
+pub fn install_with_free_timers(timers: &mut TimerController) -> bool {
+    #[cfg(feature = "profiling")]
+    {
+        drop(uninstall());
+
+        for low in [2, 1, 0] {
+            let Ok(low_timer) = timers.claim(low, "profiler") else {
+                continue;
+            };
+            let Ok(high_timer) = timers.claim(low + 1, "profiler") else {
+                continue;
+            };
+
+            drop(install(MonotonicClock::new(
+                low_timer,
+                high_timer,
+                crate::timer::Divider::Divider1,
+            )));
+            return true;
+        }
+
+        false
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    {
+        let _ = timers;
+        false
+    }
+}
+
+/// Stops profiling, returning the clock which was in use.
+#[must_use]
+pub fn uninstall() -> Option<MonotonicClock> {
+    #[cfg(feature = "profiling")]
+    return global::uninstall();
+
+    #[cfg(not(feature = "profiling"))]
+    None
+}
+
+/// Returns the time spent in each section since the last call to `end_frame`, and starts
+/// counting again from zero. Call this once per frame, outside of any sections.
+#[must_use]
+pub fn end_frame() -> FrameReport {
+    #[cfg(feature = "profiling")]
+    return global::end_frame();
+
+    #[cfg(not(feature = "profiling"))]
+    FrameReport::EMPTY
+}
+
+/// Marks a section as open until it is dropped. Created by [`profile!`](crate::profile), which
+/// you should generally use instead.
+#[must_use = "the section ends as soon as this is dropped"]
+pub struct ProfileScope {
+    _private: (),
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        global::exit();
+    }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn enter(name: &'static str) -> ProfileScope {
+    #[cfg(feature = "profiling")]
+    global::enter(name);
+
+    #[cfg(not(feature = "profiling"))]
+    let _ = name;
+
+    ProfileScope { _private: () }
+}
+
+/// Measures the time taken to run a block of code, recording it under the given name. Returns
+/// the value of the block. See the [`profiler`](crate::profiler) module for more details.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// let total = agb::profile!("sum", { (0..100).sum::<i32>() });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! profile {
+    ($name: expr, $body: block) => {{
+        let _scope = $crate::profiler::enter($name);
+        $body
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::state::ProfilerState;
+
+    #[test_case]
+    fn nested_sections_record_inclusive_and_exclusive_time(_gba: &mut crate::Gba) {
+        let mut state = ProfilerState::new();
+        let cycles = |ticks| ticks;
+
+        state.enter("update", 0);
+        state.enter("physics", 10);
+        state.exit(40, cycles);
+        state.enter("physics", 50);
+        state.exit(60, cycles);
+        state.exit(100, cycles);
+
+        let report = state.take_report();
+        let sections = report.sections();
+
+        assert_eq!(sections.len(), 2);
+
+        assert_eq!(sections[0].name, "update");
+        assert_eq!(sections[0].inclusive_cycles, 100);
+        assert_eq!(sections[0].exclusive_cycles, 60);
+        assert_eq!(sections[0].calls, 1);
+
+        assert_eq!(sections[1].name, "physics");
+        assert_eq!(sections[1].inclusive_cycles, 40);
+        assert_eq!(sections[1].exclusive_cycles, 40);
+        assert_eq!(sections[1].calls, 2);
+
+        assert!(state.take_report().sections().is_empty());
+    }
+
+    #[test_case]
+    fn too_many_sections_are_ignored(_gba: &mut crate::Gba) {
+        const NAMES: [&str; 20] = [
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
+            "16", "17", "18", "19",
+        ];
+
+        let mut state = ProfilerState::new();
+        for name in NAMES {
+            state.enter(name, 0);
+            state.exit(1, |ticks| ticks);
+        }
+
+        for _ in 0..20 {
+            state.enter("deep", 0);
+        }
+        for _ in 0..20 {
+            state.exit(1, |ticks| ticks);
+        }
+
+        let report = state.take_report();
+        assert_eq!(report.sections().len(), super::MAX_SECTIONS);
+        assert_eq!(report.sections()[15].name, "15");
+    }
+}
@@ -1,4 +1,9 @@
-use portable_atomic::{AtomicU128, Ordering};
+use portable_atomic::{AtomicBool, AtomicU128, AtomicU32, Ordering};
+
+use crate::{
+    input::{Button, ButtonController},
+    memory_mapped::MemoryMapped,
+};
 
 /// A fast pseudo-random number generator. Note that the output of the
 /// random number generator for a given seed is guaranteed stable
@@ -72,6 +77,155 @@ pub fn gen() -> i32 {
     value
 }
 
+/// Seeds the global random number generator used by [`gen`].
+///
+/// Once this has been called, [`seed_from_entropy`] does nothing. That way a game which
+/// replays recorded input can set the seed the recording was made with, and the title screen
+/// calling [`seed_from_entropy`] as usual won't replace it.
+///
+/// # Panics
+///
+/// Panics if any part of the seed is 0.
+pub fn set_seed(seed: [u32; 4]) {
+    let rng = RandomNumberGenerator::new_with_seed(seed);
+
+    EXPLICITLY_SEEDED.store(true, Ordering::SeqCst);
+    GLOBAL_RNG.store(
+        unsafe { core::mem::transmute::<[u32; 4], u128>(rng.state) },
+        Ordering::SeqCst,
+    );
+}
+
+static EXPLICITLY_SEEDED: AtomicBool = AtomicBool::new(false);
+static ENTROPY_POOL: AtomicU128 = AtomicU128::new(0);
+static ENTROPY_SAMPLES: AtomicU32 = AtomicU32::new(0);
+
+/// Mixes `sample` into every word of `pool`, so that a change to any bit of any sample
+/// changes the whole pool.
+fn mix(pool: [u32; 4], sample: u32) -> [u32; 4] {
+    let mut carry = sample;
+
+    pool.map(|word| {
+        // the finaliser from murmur3
+        let mut x = word.rotate_left(7) ^ carry;
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x85eb_ca6b);
+        x ^= x >> 13;
+        x = x.wrapping_mul(0xc2b2_ae35);
+        x ^= x >> 16;
+
+        carry = x;
+        x
+    })
+}
+
+/// Mixes whatever is changing quickly in the hardware right now into `pool`: the current
+/// scanline, the number of frames so far and the counts of all four timers.
+fn mix_hardware_state(mut pool: [u32; 4]) -> [u32; 4] {
+    let vcount = unsafe { MemoryMapped::<u16>::new(0x0400_0006) }.get();
+    pool = mix(pool, u32::from(vcount));
+    pool = mix(pool, crate::interrupt::vblank_count() as u32);
+
+    for timer in 0..4 {
+        let count = unsafe { MemoryMapped::<u16>::new(0x0400_0100 + 4 * timer) }.get();
+        pool = mix(pool, u32::from(count));
+    }
+
+    pool
+}
+
+/// Collects entropy from the timing of button presses, to be turned into a seed by
+/// [`seed_from_entropy`].
+///
+/// Call this every frame while waiting for the player on the title screen. Each time a button
+/// is newly pressed, the exact frame and scanline it was noticed on (along with the state of
+/// any running timers) is mixed into an entropy pool. Nobody can press a button with
+/// scanline accuracy, so a few presses give a seed which differs between players and between
+/// runs.
+pub fn capture_entropy(input: &ButtonController) {
+    if !Button::all()
+        .iter()
+        .any(|button| input.is_just_pressed(button))
+    {
+        return;
+    }
+
+    let pool: [u32; 4] = unsafe { core::mem::transmute(ENTROPY_POOL.load(Ordering::SeqCst)) };
+    let pool = mix(mix_hardware_state(pool), input_bits(input));
+
+    ENTROPY_POOL.store(
+        unsafe { core::mem::transmute::<[u32; 4], u128>(pool) },
+        Ordering::SeqCst,
+    );
+    ENTROPY_SAMPLES.add(1, Ordering::SeqCst);
+}
+
+fn input_bits(input: &ButtonController) -> u32 {
+    Button::all()
+        .iter()
+        .filter(|&button| input.is_pressed(button))
+        .fold(0, |bits, button| bits | button.bits())
+}
+
+/// The number of button presses captured by [`capture_entropy`] so far.
+#[must_use]
+pub fn entropy_samples() -> u32 {
+    ENTROPY_SAMPLES.load(Ordering::SeqCst)
+}
+
+/// Seeds the global random number generator used by [`gen`] from the entropy collected by
+/// [`capture_entropy`], along with the state of the hardware right now. Returns whether the
+/// seed was changed.
+///
+/// This does nothing and returns `false` if [`set_seed`] has been called, so it is always safe
+/// to call, such as when the player presses start on the title screen:
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo() {
+/// use agb::input::{Button, ButtonController};
+///
+/// let mut input = ButtonController::new();
+/// let vblank = agb::interrupt::VBlank::get();
+///
+/// loop {
+///     input.update();
+///     agb::rng::capture_entropy(&input);
+///
+///     if input.is_just_pressed(Button::START) {
+///         agb::rng::seed_from_entropy();
+///         break;
+///     }
+///
+///     vblank.wait_for_vblank();
+/// }
+/// # }
+/// ```
+///
+/// # Quality
+///
+/// The randomness comes from when the player pressed buttons, to within a scanline, so each
+/// press contributes maybe 10 to 15 bits of real entropy. That is plenty to make levels differ
+/// between players, but the seed could be guessed by someone who tried hard enough, so don't
+/// use it for anything which needs to be unpredictable. If no buttons have been captured, the
+/// seed only depends on how long the game has been running and is much less random.
+pub fn seed_from_entropy() -> bool {
+    if EXPLICITLY_SEEDED.load(Ordering::SeqCst) {
+        return false;
+    }
+
+    let pool: [u32; 4] = unsafe { core::mem::transmute(ENTROPY_POOL.load(Ordering::SeqCst)) };
+    let seed = mix_hardware_state(pool).map(|word| if word == 0 { 1 } else { word });
+
+    GLOBAL_RNG.store(
+        unsafe { core::mem::transmute::<[u32; 4], u128>(seed) },
+        Ordering::SeqCst,
+    );
+
+    true
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -94,6 +248,18 @@ mod test {
         }
     }
 
+    #[test_case]
+    fn mixing_spreads_every_sample_across_the_pool(_gba: &mut Gba) {
+        let a = mix([0; 4], 1);
+        let b = mix([0; 4], 2);
+
+        for (a, b) in a.into_iter().zip(b) {
+            assert_ne!(a, b);
+        }
+
+        assert_ne!(mix(a, 3), mix(b, 3));
+    }
+
     #[test_case]
     fn global_rng_should_be_reasonably_distributed(_gba: &mut Gba) {
         let mut values: [u32; 16] = Default::default();
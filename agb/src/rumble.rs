@@ -0,0 +1,319 @@
+//! Shaking the player's hands, with a rumble motor in the cartridge or the GameCube controller.
+//!
+//! There are two kinds of hardware which [`Rumble`] can drive:
+//!
+//! * [`Rumble::gpio`] is for cartridges with a motor wired to pin 3 of the
+//!   [GPIO port](crate::gpio), such as Drill Dozer's. The motor can only be on or off, so the
+//!   weaker strengths turn it on for only some frames.
+//! * [`Rumble::gba_player`] is for the Game Boy Player on the GameCube, which rumbles the
+//!   controller plugged into it. It talks to the game over the link port, so nothing can be
+//!   plugged into that and the serial port can't be used for anything else. The Game Boy Player
+//!   only does this for games which show its logo while starting up, see
+//!   [`gba_player_detected`].
+//!
+//! Once a strength has been [set](Rumble::set), the motor is driven from the vblank interrupt
+//! so nothing needs to be done each frame. If the game calls [`watchdog::frame_alive`] each
+//! frame, the motor is also stopped if that stops happening for a quarter of a second, so a game
+//! which has hung doesn't leave it shaking forever. This works whether or not a
+//! [`Watchdog`](crate::watchdog::Watchdog) is running. The motor is stopped if the game panics
+//! too.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::rumble::{Rumble, RumbleStrength};
+//!
+//! let mut rumble = if agb::rumble::gba_player_detected() {
+//!     Rumble::gba_player().ok()
+//! } else {
+//!     Rumble::gpio().ok()
+//! };
+//!
+//! // when the player gets hit
+//! if let Some(rumble) = &mut rumble {
+//!     rumble.set(RumbleStrength::Strong);
+//! }
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     agb::watchdog::frame_alive();
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use portable_atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+
+use crate::{
+    gpio::{self, CartGpio},
+    interrupt::{add_interrupt_handler, Interrupt, InterruptHandler},
+    memory_mapped::MemoryMapped,
+    net::{SerialPort, RCNT, SIOCNT, SIODATA32},
+    watchdog,
+};
+
+const KEYINPUT: MemoryMapped<u16> = unsafe { MemoryMapped::new(0x0400_0130) };
+
+/// Every direction held at once and nothing else, which can't happen with a real d-pad.
+const GBA_PLAYER_KEYS: u16 = 0x030f;
+
+const RUMBLE_PIN: u8 = 1 << 3;
+
+/// Normal mode with an external clock, since the Game Boy Player drives the transfers.
+const SIO_START: u16 = 1 << 7;
+const SIO_32_BIT: u16 = 1 << 12;
+const SIO_IRQ_ENABLE: u16 = 1 << 14;
+
+/// What the Game Boy Player sends while waiting for a rumble command.
+const GBA_PLAYER_POLL: u32 = 0x3000_0003;
+const GBA_PLAYER_RUMBLE_START: u32 = 0x4000_0026;
+const GBA_PLAYER_RUMBLE_STOP: u32 = 0x4000_0004;
+
+/// The replies to the Game Boy Player's handshake before it starts polling.
+const GBA_PLAYER_HANDSHAKE: [(u32, u32); 12] = [
+    (0x0000_494e, 0x494e_b6b1),
+    (0xb6b1_494e, 0x494e_b6b1),
+    (0xb6b1_544e, 0x544e_b6b1),
+    (0xabb1_544e, 0x544e_abb1),
+    (0xabb1_4e45, 0x4e45_abb1),
+    (0xb1ba_4e45, 0x4e45_b1ba),
+    (0xb1ba_4f44, 0x4f44_b1ba),
+    (0xb0bb_4f44, 0x4f44_b0bb),
+    (0xb0bb_8002, 0x8000_b0bb),
+    (0x1000_0010, 0x1000_0010),
+    (0x2000_0013, 0x2000_0013),
+    (0x4000_0004, 0x4000_0004),
+];
+
+/// How many frames without [`watchdog::frame_alive`] before the motor is stopped.
+const HANG_FRAMES: u32 = 15;
+
+const BACKEND_NONE: u8 = 0;
+const BACKEND_GPIO: u8 = 1;
+const BACKEND_GBA_PLAYER: u8 = 2;
+
+static BACKEND: AtomicU8 = AtomicU8::new(BACKEND_NONE);
+static STRENGTH: AtomicU8 = AtomicU8::new(RumbleStrength::Off as u8);
+static MOTOR_ON: AtomicBool = AtomicBool::new(false);
+static FRAME: AtomicU8 = AtomicU8::new(0);
+static LAST_COMPLETED_FRAMES: AtomicU32 = AtomicU32::new(0);
+static STALLED_FRAMES: AtomicU32 = AtomicU32::new(0);
+
+/// How strongly to rumble. The motor is either on or off, so the weaker strengths turn it on
+/// for some of the frames in every 4.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum RumbleStrength {
+    /// The motor is off.
+    #[default]
+    Off = 0b0000,
+    /// The motor is on 1 frame in 4.
+    Weak = 0b0001,
+    /// The motor is on every other frame.
+    Medium = 0b0101,
+    /// The motor is on all the time.
+    Strong = 0b1111,
+}
+
+/// The ways setting up rumble can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RumbleError {
+    /// There is already a [`Rumble`].
+    AlreadyInUse,
+    /// Pin 3 of the GPIO port is being used by something else.
+    PinsInUse,
+    /// The [serial port](crate::net::SerialPort) is being used by something else.
+    SerialPortInUse,
+}
+
+/// Whether the Game Boy Player is running the game. It says so by making it look like every
+/// direction on the d-pad is held, which it only does while the game is showing the Game Boy
+/// Player logo during startup, so check this on each frame the logo is shown.
+#[must_use]
+pub fn gba_player_detected() -> bool {
+    KEYINPUT.get() & 0x03ff == GBA_PLAYER_KEYS
+}
+
+/// A rumble motor, see the [module level documentation](self).
+pub struct Rumble {
+    _pins: Option<CartGpio>,
+    _vblank: InterruptHandler,
+    _serial: Option<InterruptHandler>,
+    _port: Option<SerialPort>,
+}
+
+impl Rumble {
+    /// Drives a motor wired to pin 3 of the cartridge's GPIO port. Carts without one just
+    /// ignore it, so this can't tell whether there is a motor.
+    pub fn gpio() -> Result<Self, RumbleError> {
+        let mut pins = CartGpio::claim(RUMBLE_PIN).map_err(|_| RumbleError::PinsInUse)?;
+        claim_backend(BACKEND_GPIO)?;
+
+        pins.write(0);
+        pins.set_outputs(RUMBLE_PIN);
+
+        Ok(Self {
+            _pins: Some(pins),
+            _vblank: start_servicing(),
+            _serial: None,
+            _port: None,
+        })
+    }
+
+    /// Rumbles the controller of the Game Boy Player the game is running on, which should have
+    /// been checked with [`gba_player_detected`]. This takes over the serial port to answer the
+    /// Game Boy Player.
+    pub fn gba_player() -> Result<Self, RumbleError> {
+        let port = SerialPort::claim().map_err(|_| RumbleError::SerialPortInUse)?;
+        claim_backend(BACKEND_GBA_PLAYER)?;
+
+        RCNT.set(0);
+        SIOCNT.set(SIO_32_BIT);
+        SIODATA32.set(0);
+
+        // Safety: the handler only touches registers and atomics, so doesn't allocate
+        let serial = unsafe { add_interrupt_handler(Interrupt::Serial, |_| answer_gba_player()) };
+
+        SIOCNT.set(SIO_32_BIT | SIO_IRQ_ENABLE | SIO_START);
+
+        Ok(Self {
+            _pins: None,
+            _vblank: start_servicing(),
+            _serial: Some(serial),
+            _port: Some(port),
+        })
+    }
+
+    /// Sets how strongly to rumble from the next frame on, until it is set again.
+    pub fn set(&mut self, strength: RumbleStrength) {
+        STRENGTH.store(strength as u8, Ordering::SeqCst);
+    }
+
+    /// The strength last [set](Rumble::set).
+    #[must_use]
+    pub fn strength(&self) -> RumbleStrength {
+        match STRENGTH.load(Ordering::SeqCst) {
+            0b0001 => RumbleStrength::Weak,
+            0b0101 => RumbleStrength::Medium,
+            0b1111 => RumbleStrength::Strong,
+            _ => RumbleStrength::Off,
+        }
+    }
+}
+
+impl Drop for Rumble {
+    fn drop(&mut self) {
+        stop_motor();
+        BACKEND.store(BACKEND_NONE, Ordering::SeqCst);
+    }
+}
+
+fn claim_backend(backend: u8) -> Result<(), RumbleError> {
+    BACKEND
+        .compare_exchange(BACKEND_NONE, backend, Ordering::SeqCst, Ordering::SeqCst)
+        .map_err(|_| RumbleError::AlreadyInUse)?;
+
+    STRENGTH.store(RumbleStrength::Off as u8, Ordering::SeqCst);
+    MOTOR_ON.store(false, Ordering::SeqCst);
+    LAST_COMPLETED_FRAMES.store(watchdog::completed_frames(), Ordering::SeqCst);
+    STALLED_FRAMES.store(0, Ordering::SeqCst);
+
+    Ok(())
+}
+
+fn start_servicing() -> InterruptHandler {
+    // Safety: the handler only touches registers and atomics, so doesn't allocate
+    unsafe { add_interrupt_handler(Interrupt::VBlank, |_| service()) }
+}
+
+/// Works out whether the motor should be on this frame, and drives it.
+fn service() {
+    let completed = watchdog::completed_frames();
+    let stalled = if LAST_COMPLETED_FRAMES.swap(completed, Ordering::SeqCst) == completed {
+        STALLED_FRAMES.add(1, Ordering::SeqCst);
+        STALLED_FRAMES.load(Ordering::SeqCst)
+    } else {
+        STALLED_FRAMES.store(0, Ordering::SeqCst);
+        0
+    };
+
+    // only games which call frame_alive can be seen to have hung
+    let hung = completed > 0 && stalled >= HANG_FRAMES;
+
+    let frame = FRAME.fetch_add(1, Ordering::SeqCst) % 4;
+    let on = !hung && STRENGTH.load(Ordering::SeqCst) & (1 << frame) != 0;
+
+    drive_motor(on);
+}
+
+fn drive_motor(on: bool) {
+    // the Game Boy Player's serial handler picks this up the next time it polls
+    MOTOR_ON.store(on, Ordering::SeqCst);
+
+    if BACKEND.load(Ordering::SeqCst) == BACKEND_GPIO {
+        gpio::write_pins(RUMBLE_PIN, if on { RUMBLE_PIN } else { 0 });
+    }
+}
+
+fn answer_gba_player() {
+    let received = SIODATA32.get();
+
+    let reply = if received == GBA_PLAYER_POLL {
+        if MOTOR_ON.load(Ordering::SeqCst) {
+            GBA_PLAYER_RUMBLE_START
+        } else {
+            GBA_PLAYER_RUMBLE_STOP
+        }
+    } else {
+        GBA_PLAYER_HANDSHAKE
+            .iter()
+            .find(|&&(expected, _)| expected == received)
+            .map_or(0, |&(_, reply)| reply)
+    };
+
+    SIODATA32.set(reply);
+    SIOCNT.set(SIOCNT.get() | SIO_START);
+}
+
+/// Turns the motor off straight away, for when the game is stopping, such as in a panic.
+pub(crate) fn stop_motor() {
+    STRENGTH.store(RumbleStrength::Off as u8, Ordering::SeqCst);
+    drive_motor(false);
+
+    if BACKEND.load(Ordering::SeqCst) == BACKEND_GBA_PLAYER {
+        // the serial handler might not run again, so leave the stop command ready to send
+        SIODATA32.set(GBA_PLAYER_RUMBLE_STOP);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn strength_is_duty_cycled_per_frame(_gba: &mut crate::Gba) {
+        let mut rumble = Rumble::gpio().unwrap();
+        assert_eq!(Rumble::gpio().err(), Some(RumbleError::PinsInUse));
+        assert_eq!(Rumble::gba_player().err(), Some(RumbleError::AlreadyInUse));
+
+        rumble.set(RumbleStrength::Medium);
+        assert_eq!(rumble.strength(), RumbleStrength::Medium);
+
+        let vblank = crate::interrupt::VBlank::get();
+        let mut frames_on = 0;
+        for _ in 0..8 {
+            watchdog::frame_alive();
+            vblank.wait_for_vblank();
+            if MOTOR_ON.load(Ordering::SeqCst) {
+                frames_on += 1;
+            }
+        }
+        assert_eq!(frames_on, 4);
+
+        drop(rumble);
+        assert!(!MOTOR_ON.load(Ordering::SeqCst));
+        assert!(CartGpio::claim(RUMBLE_PIN).is_ok());
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! EEPROM requires using DMA to issue commands for both reading and writing.
 
+use crate::interrupt::interrupt_free;
 use crate::memory_mapped::MemoryMapped;
 use crate::save::utils::Timeout;
 use crate::save::{Error, MediaInfo, MediaType, RawSaveAccess};
@@ -108,11 +109,13 @@ impl EepromProperties {
         buf.write_bit(1);
         buf.write_num(self.addr_bits, word as u32);
         buf.write_bit(0);
-        buf.submit();
 
         // Receive the buffer data. The EEPROM sends 3 irrelevant bits followed
-        // by 64 data bits.
-        buf.receive(68);
+        // by 64 data bits. Nothing else may talk to the EEPROM in between.
+        interrupt_free(|_| {
+            buf.submit();
+            buf.receive(68);
+        });
         let mut out = [0; 8];
         for i in 0..8 {
             out[i] = buf.read_num(4 + i * 8, 8) as u8;
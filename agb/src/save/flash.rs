@@ -8,6 +8,7 @@
 use once_cell::sync::OnceCell;
 use portable_atomic::{AtomicU8, Ordering};
 
+use crate::interrupt::interrupt_free;
 use crate::memory_mapped::{MemoryMapped, MemoryMapped1DArray};
 use crate::save::asm_utils::*;
 use crate::save::utils::Timeout;
@@ -52,8 +53,10 @@ fn set_bank(bank: u8) -> Result<(), Error> {
     if bank == 0xFF {
         Err(Error::OutOfBounds)
     } else if bank != CURRENT_BANK.load(Ordering::SeqCst) {
-        issue_flash_command(CMD_SET_BANK);
-        FLASH_PORT_BANK.set(bank);
+        interrupt_free(|_| {
+            issue_flash_command(CMD_SET_BANK);
+            FLASH_PORT_BANK.set(bank);
+        });
         CURRENT_BANK.store(bank, Ordering::SeqCst);
         Ok(())
     } else {
@@ -103,11 +106,13 @@ impl FlashChipType {
 
 /// Determines the raw ID of the flash chip currently in use.
 pub fn detect_chip_id() -> Result<u16, Error> {
-    issue_flash_command(CMD_READ_CHIP_ID);
-    let high = unsafe { read_raw_byte(0x0E000001) };
-    let low = unsafe { read_raw_byte(0x0E000000) };
-    let id = (high as u16) << 8 | low as u16;
-    issue_flash_command(CMD_READ_CONTENTS);
+    let id = interrupt_free(|_| {
+        issue_flash_command(CMD_READ_CHIP_ID);
+        let high = unsafe { read_raw_byte(0x0E000001) };
+        let low = unsafe { read_raw_byte(0x0E000000) };
+        issue_flash_command(CMD_READ_CONTENTS);
+        (high as u16) << 8 | low as u16
+    });
     Ok(id)
 }
 
@@ -346,23 +351,29 @@ impl ChipInfo {
     fn erase_sector(&self, sector: usize, timeout: &mut Timeout) -> Result<(), Error> {
         let offset = sector << self.info.sector_shift;
         self.set_bank(offset >> BANK_SHIFT)?;
-        issue_flash_command(CMD_ERASE_SECTOR_BEGIN);
-        start_flash_command();
-        FLASH_DATA.set(offset & BANK_MASK, CMD_ERASE_SECTOR_CONFIRM);
+        interrupt_free(|_| {
+            issue_flash_command(CMD_ERASE_SECTOR_BEGIN);
+            start_flash_command();
+            FLASH_DATA.set(offset & BANK_MASK, CMD_ERASE_SECTOR_CONFIRM);
+        });
         self.wait_for_timeout(offset & BANK_MASK, 0xFF, self.erase_sector_timeout, timeout)
     }
 
     /// Erases the entire chip.
     fn erase_chip(&self, timeout: &mut Timeout) -> Result<(), Error> {
-        issue_flash_command(CMD_ERASE_SECTOR_BEGIN);
-        issue_flash_command(CMD_ERASE_SECTOR_ALL);
+        interrupt_free(|_| {
+            issue_flash_command(CMD_ERASE_SECTOR_BEGIN);
+            issue_flash_command(CMD_ERASE_SECTOR_ALL);
+        });
         self.wait_for_timeout(0, 0xFF, 3000, timeout)
     }
 
     /// Writes a byte to the save media.
     fn write_byte(&self, offset: usize, byte: u8, timeout: &mut Timeout) -> Result<(), Error> {
-        issue_flash_command(CMD_WRITE);
-        FLASH_DATA.set(offset, byte);
+        interrupt_free(|_| {
+            issue_flash_command(CMD_WRITE);
+            FLASH_DATA.set(offset, byte);
+        });
         self.wait_for_timeout(offset, byte, self.write_timeout, timeout)
     }
 
@@ -388,7 +399,7 @@ impl ChipInfo {
         buf: &[u8],
         timeout: &mut Timeout,
     ) -> Result<(), Error> {
-        critical_section::with(|_| {
+        interrupt_free(|_| {
             issue_flash_command(CMD_WRITE);
             for i in 0..128 {
                 FLASH_DATA.set(offset + i, buf[i]);
@@ -0,0 +1,151 @@
+//! A small header for detecting whether save media contains valid save data.
+
+use super::{Error, SaveData};
+
+/// A header identifying save data as belonging to this game.
+///
+/// On the save media it takes up [`SaveHeader::SIZE`] bytes: the magic number, then the
+/// version, then a little endian checksum of the two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveHeader {
+    /// A value unique to your game, so that save data left behind by something else on the
+    /// cartridge (or uninitialised save media) isn't mistaken for yours.
+    pub magic: [u8; 4],
+    /// The version of your save data format, useful for migrating old saves.
+    pub version: u8,
+    /// A checksum of the magic number and version.
+    pub checksum: u16,
+}
+
+/// The ways in which reading a [`SaveHeader`] can fail.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum HeaderError {
+    /// The magic number doesn't match, so the save media doesn't contain save data for this
+    /// game. This is what you'll see the first time the game is run.
+    MagicMismatch,
+    /// The magic number matches but the header is corrupted.
+    ChecksumMismatch,
+    /// Reading from the save media failed.
+    ReadError(Error),
+}
+
+impl From<Error> for HeaderError {
+    fn from(error: Error) -> Self {
+        HeaderError::ReadError(error)
+    }
+}
+
+impl SaveHeader {
+    /// The number of bytes the header takes up on the save media.
+    pub const SIZE: usize = 7;
+
+    /// Creates a header with the checksum filled in.
+    #[must_use]
+    pub fn new(magic: [u8; 4], version: u8) -> Self {
+        SaveHeader {
+            magic,
+            version,
+            checksum: Self::compute_checksum(magic, version),
+        }
+    }
+
+    /// Whether the checksum matches the magic number and version.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.checksum == Self::compute_checksum(self.magic, self.version)
+    }
+
+    /// Fletcher-16 of the magic number and version.
+    fn compute_checksum(magic: [u8; 4], version: u8) -> u16 {
+        let mut sum1: u16 = 0;
+        let mut sum2: u16 = 0;
+
+        for byte in magic.into_iter().chain([version]) {
+            sum1 = (sum1 + u16::from(byte)) % 255;
+            sum2 = (sum2 + sum1) % 255;
+        }
+
+        (sum2 << 8) | sum1
+    }
+
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        let [magic0, magic1, magic2, magic3] = self.magic;
+        let [checksum0, checksum1] = self.checksum.to_le_bytes();
+
+        [
+            magic0,
+            magic1,
+            magic2,
+            magic3,
+            self.version,
+            checksum0,
+            checksum1,
+        ]
+    }
+
+    fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        SaveHeader {
+            magic: [bytes[0], bytes[1], bytes[2], bytes[3]],
+            version: bytes[4],
+            checksum: u16::from_le_bytes([bytes[5], bytes[6]]),
+        }
+    }
+}
+
+/// Writes a [`SaveHeader`] with the given magic number and version at `offset`.
+///
+/// This prepares the range it writes to, so on flash media it will erase the whole sector
+/// containing the header. Either write the header before the rest of the data in that sector,
+/// or give the header a sector of its own.
+pub fn write_header(
+    data: &mut SaveData,
+    offset: usize,
+    magic: [u8; 4],
+    version: u8,
+) -> Result<(), Error> {
+    let bytes = SaveHeader::new(magic, version).to_bytes();
+
+    data.prepare_write(offset..offset + SaveHeader::SIZE)?
+        .write_and_verify(offset, &bytes)
+}
+
+/// Reads the [`SaveHeader`] at `offset` and checks that it is valid and has the expected
+/// magic number, returning the version stored in it.
+pub fn read_and_validate(
+    data: &mut SaveData,
+    offset: usize,
+    magic: [u8; 4],
+) -> Result<u8, HeaderError> {
+    let mut bytes = [0; SaveHeader::SIZE];
+    data.read(offset, &mut bytes)?;
+
+    let header = SaveHeader::from_bytes(bytes);
+
+    if header.magic != magic {
+        Err(HeaderError::MagicMismatch)
+    } else if !header.is_valid() {
+        Err(HeaderError::ChecksumMismatch)
+    } else {
+        Ok(header.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn header_round_trips_through_bytes(_gba: &mut crate::Gba) {
+        let header = SaveHeader::new(*b"AGB!", 3);
+        assert!(header.is_valid());
+        assert_eq!(SaveHeader::from_bytes(header.to_bytes()), header);
+
+        let mut corrupted = header.to_bytes();
+        corrupted[4] = 4;
+        assert!(!SaveHeader::from_bytes(corrupted).is_valid());
+
+        // erased flash reads back as all 0xff, which must not look valid
+        assert!(!SaveHeader::from_bytes([0xff; SaveHeader::SIZE]).is_valid());
+    }
+}
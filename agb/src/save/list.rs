@@ -0,0 +1,287 @@
+//! Fixed size lists of values in save media, which can be updated one element at a time.
+
+use alloc::{vec, vec::Vec};
+use core::{marker::PhantomData, ops::Range};
+
+use crate::util::crc16;
+
+use super::{Error, SaveData};
+
+/// A value which can be stored in a [`SavedList`], as a fixed number of bytes.
+///
+/// This is implemented for the integer types, `bool` and arrays of these. Implement it for your
+/// own types by writing out each field in turn.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// use agb::save::SaveBytes;
+///
+/// #[derive(Clone, Copy)]
+/// struct HighScore {
+///     initials: [u8; 3],
+///     score: u32,
+/// }
+///
+/// impl SaveBytes for HighScore {
+///     const SIZE: usize = 3 + 4;
+///
+///     fn write_bytes(&self, bytes: &mut [u8]) {
+///         self.initials.write_bytes(&mut bytes[..3]);
+///         self.score.write_bytes(&mut bytes[3..]);
+///     }
+///
+///     fn read_bytes(bytes: &[u8]) -> Self {
+///         HighScore {
+///             initials: SaveBytes::read_bytes(&bytes[..3]),
+///             score: SaveBytes::read_bytes(&bytes[3..]),
+///         }
+///     }
+/// }
+/// ```
+pub trait SaveBytes: Sized {
+    /// How many bytes the value takes up.
+    const SIZE: usize;
+
+    /// Writes the value to `bytes`, which is [`SIZE`](SaveBytes::SIZE) bytes long.
+    fn write_bytes(&self, bytes: &mut [u8]);
+
+    /// Reads a value written by [`write_bytes`](SaveBytes::write_bytes) from `bytes`, which is
+    /// [`SIZE`](SaveBytes::SIZE) bytes long.
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_save_bytes {
+    ($($ty: ty),*) => {
+        $(
+            impl SaveBytes for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                fn write_bytes(&self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_bytes(bytes: &[u8]) -> Self {
+                    let mut le_bytes = [0; core::mem::size_of::<$ty>()];
+                    le_bytes.copy_from_slice(bytes);
+                    <$ty>::from_le_bytes(le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_save_bytes!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl SaveBytes for bool {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, bytes: &mut [u8]) {
+        bytes[0] = u8::from(*self);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+impl<T: SaveBytes, const M: usize> SaveBytes for [T; M] {
+    const SIZE: usize = T::SIZE * M;
+
+    fn write_bytes(&self, bytes: &mut [u8]) {
+        for (value, bytes) in self.iter().zip(bytes.chunks_exact_mut(T::SIZE)) {
+            value.write_bytes(bytes);
+        }
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        core::array::from_fn(|i| T::read_bytes(&bytes[i * T::SIZE..(i + 1) * T::SIZE]))
+    }
+}
+
+/// The ways in which reading or updating a [`SavedList`] can fail.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ListError {
+    /// The list in save media doesn't match its checksum, because it was never saved or has
+    /// been corrupted. Save the whole list with [`SavedList::save_all`] to fix it.
+    ChecksumMismatch,
+    /// The index is past the end of the list.
+    IndexOutOfBounds,
+    /// Reading from or writing to the save media failed.
+    SaveError(Error),
+}
+
+impl From<Error> for ListError {
+    fn from(error: Error) -> Self {
+        ListError::SaveError(error)
+    }
+}
+
+/// `N` values of type `T` stored at a fixed offset in save media, such as an inventory or a
+/// table of high scores.
+///
+/// On the save media the list takes up [`SavedList::SIZE`] bytes: each element in turn, then a
+/// little endian CRC-16 of all of them. Changing one element with
+/// [`save_element`](SavedList::save_element) only writes that element and the CRC, rather than
+/// the whole list, which is quicker and wears flash less. On flash, the rest of each sector
+/// written to has to be rewritten as well, so keep the list away from data which is written
+/// often.
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(gba: &mut agb::Gba) -> Result<(), agb::save::ListError> {
+/// use agb::save::{ListError, SavedList};
+///
+/// let mut data = gba.save.access()?;
+/// let inventory = SavedList::<u16, 20>::new(64);
+///
+/// let items = match inventory.load_all(&mut data) {
+///     Ok(items) => items,
+///     Err(ListError::ChecksumMismatch) => {
+///         // nothing has been saved yet
+///         inventory.save_all(&mut data, &[0; 20])?;
+///         [0; 20]
+///     }
+///     Err(error) => return Err(error),
+/// };
+///
+/// // picked up item 7 in the fourth slot
+/// inventory.save_element(&mut data, 3, 7)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SavedList<T, const N: usize> {
+    offset: usize,
+    _element: PhantomData<T>,
+}
+
+impl<T: Copy + SaveBytes, const N: usize> SavedList<T, N> {
+    /// The number of bytes the list takes up on the save media, including its CRC.
+    pub const SIZE: usize = T::SIZE * N + 2;
+
+    /// A list stored at `offset` in save media.
+    #[must_use]
+    pub const fn new(offset: usize) -> Self {
+        Self {
+            offset,
+            _element: PhantomData,
+        }
+    }
+
+    /// Reads the whole list, checking it against its CRC.
+    pub fn load_all(&self, data: &mut SaveData) -> Result<[T; N], ListError> {
+        let elements = self.read_elements(data)?;
+        Ok(core::array::from_fn(|i| {
+            T::read_bytes(&elements[i * T::SIZE..(i + 1) * T::SIZE])
+        }))
+    }
+
+    /// Writes the whole list and its CRC. Use this to save the list for the first time.
+    pub fn save_all(&self, data: &mut SaveData, values: &[T; N]) -> Result<(), ListError> {
+        let mut bytes = vec![0; Self::SIZE];
+        values.write_bytes(&mut bytes[..T::SIZE * N]);
+        let crc = crc16(&bytes[..T::SIZE * N]);
+        bytes[T::SIZE * N..].copy_from_slice(&crc.to_le_bytes());
+
+        let sectors = data.align_range(self.offset..self.offset + Self::SIZE);
+        write_preserving(data, sectors, &[(self.offset, &bytes)])?;
+
+        Ok(())
+    }
+
+    /// Changes the element at `index` to `value`, writing just that element and the CRC.
+    ///
+    /// The rest of the list is read to work out the new CRC, and this fails with
+    /// [`ListError::ChecksumMismatch`] rather than making a corrupted list look valid.
+    pub fn save_element(
+        &self,
+        data: &mut SaveData,
+        index: usize,
+        value: T,
+    ) -> Result<(), ListError> {
+        if index >= N {
+            return Err(ListError::IndexOutOfBounds);
+        }
+
+        let mut elements = self.read_elements(data)?;
+        let element = index * T::SIZE..(index + 1) * T::SIZE;
+        value.write_bytes(&mut elements[element.clone()]);
+        let crc = crc16(&elements).to_le_bytes();
+
+        let element_offset = self.offset + element.start;
+        let crc_offset = self.offset + T::SIZE * N;
+
+        let element_sectors = data.align_range(element_offset..element_offset + T::SIZE);
+        let crc_sectors = data.align_range(crc_offset..crc_offset + 2);
+
+        let writes = [(element_offset, &elements[element]), (crc_offset, &crc[..])];
+
+        if element_sectors.end >= crc_sectors.start {
+            // the element and the CRC share sectors, so are written together
+            write_preserving(data, element_sectors.start..crc_sectors.end, &writes)?;
+        } else {
+            for write in writes.chunks(1) {
+                let sectors = data.align_range(write[0].0..write[0].0 + write[0].1.len());
+                write_preserving(data, sectors, write)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the bytes of the elements, checking them against the CRC.
+    fn read_elements(&self, data: &mut SaveData) -> Result<Vec<u8>, ListError> {
+        let mut bytes = vec![0; Self::SIZE];
+        data.read(self.offset, &mut bytes)?;
+
+        let crc = u16::from_le_bytes([bytes[T::SIZE * N], bytes[T::SIZE * N + 1]]);
+        bytes.truncate(T::SIZE * N);
+
+        if crc16(&bytes) == crc {
+            Ok(bytes)
+        } else {
+            Err(ListError::ChecksumMismatch)
+        }
+    }
+}
+
+/// Writes each of `writes` into `range`, which is a whole number of sectors. Everything else in
+/// `range` is read first and written back, since preparing the sectors may erase them.
+fn write_preserving(
+    data: &mut SaveData,
+    range: Range<usize>,
+    writes: &[(usize, &[u8])],
+) -> Result<(), Error> {
+    let mut buffer = vec![0; range.len()];
+    data.read(range.start, &mut buffer)?;
+
+    for &(offset, bytes) in writes {
+        let start = offset - range.start;
+        buffer[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+
+    data.prepare_write(range.clone())?
+        .write_and_verify(range.start, &buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn crc_and_bytes_round_trip(_gba: &mut crate::Gba) {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+
+        let mut bytes = [0; 9];
+        let value: [i16; 3] = [-2, 300, 7];
+        value.write_bytes(&mut bytes[..6]);
+        true.write_bytes(&mut bytes[6..7]);
+        assert_eq!(bytes[..2], [0xfe, 0xff]);
+        assert_eq!(<[i16; 3]>::read_bytes(&bytes[..6]), value);
+        assert!(bool::read_bytes(&bytes[6..7]));
+
+        assert_eq!(SavedList::<[i16; 3], 4>::SIZE, 26);
+    }
+}
@@ -93,9 +93,15 @@ use core::ops::Range;
 mod asm_utils;
 mod eeprom;
 mod flash;
+mod header;
+mod list;
 mod sram;
+pub mod transaction;
 mod utils;
 
+pub use header::{read_and_validate, write_header, HeaderError, SaveHeader};
+pub use list::{ListError, SaveBytes, SavedList};
+
 /// A list of save media types.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 #[non_exhaustive]
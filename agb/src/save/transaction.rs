@@ -0,0 +1,176 @@
+//! Grouping several writes to save media so that they can be undone together.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::collections::ArrayVec;
+
+use super::{Error, SaveData};
+
+/// A group of up to `N` writes to save media which are committed together.
+///
+/// Committing snapshots the sectors which will be written to, so if something goes wrong part
+/// way through (or the game decides it didn't want those changes after all) the transaction
+/// can be [rolled back](SaveTransaction::rollback).
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(gba: &mut agb::Gba) -> Result<(), agb::save::Error> {
+/// use agb::save::transaction::SaveTransaction;
+///
+/// let mut data = gba.save.access()?;
+///
+/// let mut transaction = SaveTransaction::<2>::new();
+/// transaction.push(0, &[1, 2, 3, 4]);
+/// transaction.push(64, &[5, 6]);
+///
+/// if transaction.commit(&mut data).is_err() {
+///     transaction.rollback(&mut data)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SaveTransaction<'a, const N: usize> {
+    writes: ArrayVec<(usize, &'a [u8]), N>,
+    completed: usize,
+    snapshot: Option<(usize, Vec<u8>)>,
+}
+
+impl<'a, const N: usize> SaveTransaction<'a, N> {
+    /// Creates a transaction with no writes queued.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            writes: ArrayVec::new(),
+            completed: 0,
+            snapshot: None,
+        }
+    }
+
+    /// Queues writing `data` at `offset`. Returns `false` without queueing the write if the
+    /// transaction already has `N` writes, or if the write overlaps one already queued.
+    pub fn push(&mut self, offset: usize, data: &'a [u8]) -> bool {
+        let range = offset..offset + data.len();
+
+        if self.writes.is_full()
+            || self.writes.iter().any(|&(other, other_data)| {
+                range.start < other + other_data.len() && other < range.end
+            })
+        {
+            return false;
+        }
+
+        self.writes.push((offset, data));
+        true
+    }
+
+    /// The writes which have been queued, in the order they will be written.
+    #[must_use]
+    pub fn writes(&self) -> &[(usize, &'a [u8])] {
+        &self.writes
+    }
+
+    /// The number of writes which succeeded in the last call to
+    /// [`commit`](SaveTransaction::commit).
+    #[must_use]
+    pub fn completed_writes(&self) -> usize {
+        self.completed
+    }
+
+    /// The smallest range of offsets covering every queued write.
+    fn dirty_range(&self) -> Option<Range<usize>> {
+        let start = self.writes.iter().map(|&(offset, _)| offset).min()?;
+        let end = self
+            .writes
+            .iter()
+            .map(|&(offset, data)| offset + data.len())
+            .max()?;
+
+        Some(start..end)
+    }
+
+    /// Executes every queued write in order after a single
+    /// [`prepare_write`](SaveData::prepare_write) covering all of them.
+    ///
+    /// Before anything is written, the current contents of every sector which will be
+    /// touched are saved so that [`rollback`](SaveTransaction::rollback) can restore them.
+    /// Data sharing a sector with the writes but not covered by them is preserved, even on
+    /// flash media where preparing a write erases whole sectors.
+    ///
+    /// If any write fails, the error is returned immediately and
+    /// [`completed_writes`](SaveTransaction::completed_writes) says how many succeeded.
+    pub fn commit(&mut self, data: &mut SaveData) -> Result<(), Error> {
+        self.completed = 0;
+
+        let Some(dirty) = self.dirty_range() else {
+            return Ok(());
+        };
+
+        let sectors = data.align_range(dirty.clone());
+
+        let mut snapshot = alloc::vec![0; sectors.len()];
+        data.read(sectors.start, &mut snapshot)?;
+        self.snapshot = Some((sectors.start, snapshot));
+
+        let uses_prepare_write = data.media_info().uses_prepare_write;
+        let mut prepared = data.prepare_write(sectors.clone())?;
+
+        if uses_prepare_write {
+            // put back anything in the erased sectors which the transaction doesn't overwrite
+            let (start, snapshot) = self.snapshot.as_ref().expect("snapshot was just taken");
+
+            let mut covered: ArrayVec<Range<usize>, N> = self
+                .writes
+                .iter()
+                .map(|&(offset, data)| offset..offset + data.len())
+                .collect();
+            // writes can't overlap, so no two ranges start at the same offset
+            covered.sort_unstable_by_key(|range| range.start);
+
+            let mut position = sectors.start;
+            for range in covered
+                .into_iter()
+                .chain(core::iter::once(sectors.end..sectors.end))
+            {
+                if position < range.start {
+                    prepared.write_and_verify(
+                        position,
+                        &snapshot[position - start..range.start - start],
+                    )?;
+                }
+                position = position.max(range.end);
+            }
+        }
+
+        for &(offset, buffer) in &self.writes {
+            prepared.write_and_verify(offset, buffer)?;
+            self.completed += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Restores the save media to how it was before the last call to
+    /// [`commit`](SaveTransaction::commit), whether or not that commit succeeded.
+    ///
+    /// Does nothing if the transaction has never been committed.
+    pub fn rollback(&mut self, data: &mut SaveData) -> Result<(), Error> {
+        let Some((start, snapshot)) = &self.snapshot else {
+            return Ok(());
+        };
+
+        data.prepare_write(*start..*start + snapshot.len())?
+            .write_and_verify(*start, snapshot)?;
+
+        self.completed = 0;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for SaveTransaction<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
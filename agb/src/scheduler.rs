@@ -0,0 +1,365 @@
+//! Running things after a number of frames, once or repeatedly.
+//!
+//! A [`Scheduler`] is ticked once per frame and keeps up to `N` pending entries. When an
+//! entry's deadline is reached it either pushes an event onto a queue for your main loop to
+//! handle, or calls a closure. Events are the main way to use it, since handling them in the
+//! main loop can borrow whatever game state is needed, whereas closures have to be `'static`.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::scheduler::Scheduler;
+//!
+//! #[derive(Clone, Copy)]
+//! enum Event {
+//!     SpawnEnemy,
+//!     Blink,
+//! }
+//!
+//! let mut scheduler = Scheduler::<Event, 8>::new();
+//! scheduler.schedule_event(90, Event::SpawnEnemy);
+//! let blink = scheduler.schedule_repeating_event(30, Event::Blink);
+//!
+//! loop {
+//!     scheduler.tick();
+//!
+//!     while let Some(event) = scheduler.pop_event() {
+//!         match event {
+//!             Event::SpawnEnemy => { /* ... */ }
+//!             Event::Blink => { /* ... */ }
+//!         }
+//!     }
+//!
+//!     // when the blinking should stop
+//!     if let Some(blink) = blink {
+//!         scheduler.cancel(blink);
+//!     }
+//! #   break;
+//! }
+//! # }
+//! ```
+
+use alloc::boxed::Box;
+
+/// A closure run by a [`Scheduler`], given the scheduler so that it can schedule or cancel
+/// other entries.
+pub type Callback<E, const N: usize> = Box<dyn FnMut(&mut Scheduler<E, N>)>;
+
+/// Refers to an entry in a [`Scheduler`] so that it can be cancelled. Handles to entries which
+/// have finished or been cancelled stay invalid even if their space is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryHandle {
+    index: usize,
+    generation: u32,
+}
+
+enum Action<E, const N: usize> {
+    Event(E),
+    Callback(Callback<E, N>),
+}
+
+struct Entry<E, const N: usize> {
+    deadline: u32,
+    period: Option<u32>,
+    action: Action<E, N>,
+}
+
+enum Slot<E, const N: usize> {
+    Empty,
+    Pending(Entry<E, N>),
+    /// The entry's callback is being called, and has been taken out of the slot to do so.
+    Running {
+        cancelled: bool,
+    },
+}
+
+/// Runs up to `N` entries after a number of frames, see the [module level
+/// documentation](self).
+///
+/// The event queue holds up to `N` events. If it is full when an entry fires, the event is
+/// dropped, so drain it with [`pop_event`](Scheduler::pop_event) every frame.
+pub struct Scheduler<E, const N: usize> {
+    now: u32,
+    slots: [Slot<E, N>; N],
+    generations: [u32; N],
+
+    events: [Option<E>; N],
+    events_start: usize,
+    events_len: usize,
+}
+
+impl<E: Copy, const N: usize> Scheduler<E, N> {
+    /// Creates a scheduler with nothing scheduled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            slots: core::array::from_fn(|_| Slot::Empty),
+            generations: [0; N],
+
+            events: [None; N],
+            events_start: 0,
+            events_len: 0,
+        }
+    }
+
+    fn insert(
+        &mut self,
+        delay: u32,
+        period: Option<u32>,
+        action: Action<E, N>,
+    ) -> Option<EntryHandle> {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| matches!(slot, Slot::Empty))?;
+
+        self.slots[index] = Slot::Pending(Entry {
+            deadline: self.now + delay.max(1),
+            period,
+            action,
+        });
+
+        Some(EntryHandle {
+            index,
+            generation: self.generations[index],
+        })
+    }
+
+    fn free(&mut self, index: usize) {
+        self.slots[index] = Slot::Empty;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+    }
+
+    /// Pushes `event` onto the queue after `frames` ticks. Returns `None` if `N` entries are
+    /// already pending. A delay of 0 is treated as 1, so it fires on the next tick.
+    pub fn schedule_event(&mut self, frames: u32, event: E) -> Option<EntryHandle> {
+        self.insert(frames, None, Action::Event(event))
+    }
+
+    /// Pushes `event` onto the queue every `period` ticks until cancelled. Returns `None` if
+    /// `N` entries are already pending.
+    pub fn schedule_repeating_event(&mut self, period: u32, event: E) -> Option<EntryHandle> {
+        self.insert(period, Some(period.max(1)), Action::Event(event))
+    }
+
+    /// Calls `callback` after `frames` ticks. Returns `None` if `N` entries are already
+    /// pending.
+    pub fn schedule_callback(
+        &mut self,
+        frames: u32,
+        callback: impl FnMut(&mut Self) + 'static,
+    ) -> Option<EntryHandle> {
+        self.insert(frames, None, Action::Callback(Box::new(callback)))
+    }
+
+    /// Calls `callback` every `period` ticks until cancelled. Returns `None` if `N` entries
+    /// are already pending.
+    pub fn schedule_repeating_callback(
+        &mut self,
+        period: u32,
+        callback: impl FnMut(&mut Self) + 'static,
+    ) -> Option<EntryHandle> {
+        self.insert(
+            period,
+            Some(period.max(1)),
+            Action::Callback(Box::new(callback)),
+        )
+    }
+
+    /// Stops an entry from firing again. This can be called from within a callback, including
+    /// to cancel the callback's own entry. Returns whether the entry was still pending.
+    pub fn cancel(&mut self, handle: EntryHandle) -> bool {
+        if self.generations[handle.index] != handle.generation {
+            return false;
+        }
+
+        match &mut self.slots[handle.index] {
+            Slot::Empty => false,
+            Slot::Pending(_) => {
+                self.free(handle.index);
+                true
+            }
+            Slot::Running { cancelled } => !core::mem::replace(cancelled, true),
+        }
+    }
+
+    /// Whether the entry is still going to fire.
+    #[must_use]
+    pub fn is_pending(&self, handle: EntryHandle) -> bool {
+        self.generations[handle.index] == handle.generation
+            && matches!(
+                self.slots[handle.index],
+                Slot::Pending(_) | Slot::Running { cancelled: false }
+            )
+    }
+
+    /// Moves time on by one frame, firing any entries which are due.
+    pub fn tick(&mut self) {
+        self.now += 1;
+
+        for index in 0..N {
+            let Slot::Pending(entry) = &mut self.slots[index] else {
+                continue;
+            };
+
+            if entry.deadline > self.now {
+                continue;
+            }
+
+            match &entry.action {
+                Action::Event(event) => {
+                    let event = *event;
+
+                    if let Some(period) = entry.period {
+                        entry.deadline += period;
+                    } else {
+                        self.free(index);
+                    }
+
+                    self.push_event(event);
+                }
+                Action::Callback(_) => {
+                    let Slot::Pending(mut entry) = core::mem::replace(
+                        &mut self.slots[index],
+                        Slot::Running { cancelled: false },
+                    ) else {
+                        unreachable!();
+                    };
+
+                    if let Action::Callback(callback) = &mut entry.action {
+                        callback(self);
+                    }
+
+                    match (entry.period, &self.slots[index]) {
+                        (Some(period), Slot::Running { cancelled: false }) => {
+                            entry.deadline += period;
+                            self.slots[index] = Slot::Pending(entry);
+                        }
+                        _ => self.free(index),
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_event(&mut self, event: E) {
+        if self.events_len == N {
+            return;
+        }
+
+        self.events[(self.events_start + self.events_len) % N] = Some(event);
+        self.events_len += 1;
+    }
+
+    /// Takes the oldest event off the queue.
+    pub fn pop_event(&mut self) -> Option<E> {
+        if self.events_len == 0 {
+            return None;
+        }
+
+        let event = self.events[self.events_start].take();
+        self.events_start = (self.events_start + 1) % N;
+        self.events_len -= 1;
+
+        event
+    }
+}
+
+impl<E: Copy, const N: usize> Default for Scheduler<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    use super::*;
+
+    fn drain(scheduler: &mut Scheduler<u8, 4>) -> alloc::vec::Vec<u8> {
+        core::iter::from_fn(|| scheduler.pop_event()).collect()
+    }
+
+    #[test_case]
+    fn one_shot_and_repeating_events(_gba: &mut crate::Gba) {
+        let mut scheduler = Scheduler::<u8, 4>::new();
+
+        scheduler.schedule_event(3, 1).unwrap();
+        let repeating = scheduler.schedule_repeating_event(2, 2).unwrap();
+
+        let mut fired = alloc::vec::Vec::new();
+        for _ in 0..6 {
+            scheduler.tick();
+            fired.push(drain(&mut scheduler));
+        }
+
+        assert_eq!(
+            fired,
+            [
+                alloc::vec![],
+                alloc::vec![2],
+                alloc::vec![1],
+                alloc::vec![2],
+                alloc::vec![],
+                alloc::vec![2]
+            ]
+        );
+
+        assert!(scheduler.cancel(repeating));
+        assert!(!scheduler.cancel(repeating));
+        scheduler.tick();
+        scheduler.tick();
+        assert!(drain(&mut scheduler).is_empty());
+    }
+
+    #[test_case]
+    fn callback_can_cancel_other_entries(_gba: &mut crate::Gba) {
+        let mut scheduler = Scheduler::<u8, 4>::new();
+
+        let victim = scheduler.schedule_repeating_event(1, 7).unwrap();
+        scheduler
+            .schedule_callback(2, move |scheduler| {
+                assert!(scheduler.cancel(victim));
+            })
+            .unwrap();
+
+        scheduler.tick();
+        assert_eq!(drain(&mut scheduler), [7]);
+
+        // the victim comes first, so it still fires on the tick the callback cancels it
+        scheduler.tick();
+        assert_eq!(drain(&mut scheduler), [7]);
+        assert!(!scheduler.is_pending(victim));
+
+        scheduler.tick();
+        assert!(drain(&mut scheduler).is_empty());
+    }
+
+    #[test_case]
+    fn callback_can_cancel_itself(_gba: &mut crate::Gba) {
+        let mut scheduler = Scheduler::<u8, 4>::new();
+
+        let calls = Rc::new(Cell::new(0));
+        let handle = Rc::new(Cell::new(None));
+
+        let own_handle = handle.clone();
+        let counter = calls.clone();
+        handle.set(scheduler.schedule_repeating_callback(1, move |scheduler| {
+            counter.set(counter.get() + 1);
+            if counter.get() == 3 {
+                scheduler.cancel(own_handle.get().unwrap());
+            }
+        }));
+
+        for _ in 0..10 {
+            scheduler.tick();
+        }
+
+        assert_eq!(calls.get(), 3);
+        assert!(!scheduler.is_pending(handle.get().unwrap()));
+    }
+}
@@ -0,0 +1,288 @@
+//! A bump allocator for temporary values which only live for a frame.
+//!
+//! Lots of things a game works out each frame, such as a sorted list of things to draw or the
+//! open set of a path search, are thrown away at the end of the frame. Putting them on the heap
+//! means lots of small allocations and frees which can fragment it. An [`Arena`] instead hands
+//! out memory from a single block by moving a pointer along, and frees everything at once when
+//! it is reset.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # #![feature(allocator_api)]
+//! # extern crate alloc;
+//! # fn foo() {
+//! use alloc::vec::Vec;
+//! use agb::scratch_arena::{Arena, ArenaMemory};
+//!
+//! let mut arena = Arena::new(4096, ArenaMemory::External);
+//!
+//! loop {
+//!     arena.scope(|arena| {
+//!         let mut draw_list = Vec::new_in(arena);
+//!         draw_list.push((3, "player"));
+//!         draw_list.push((1, "tree"));
+//!         draw_list.sort_unstable();
+//!
+//!         let path = arena.alloc_slice(&[(1, 2), (1, 3), (2, 3)]);
+//!         // ...
+//!     });
+//! #   break;
+//! }
+//! # }
+//! ```
+
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::Cell,
+    ptr::NonNull,
+};
+
+use crate::{ExternalAllocator, InternalAllocator};
+
+// no type on the GBA needs more alignment than this, so the block is aligned to it
+const BLOCK_ALIGNMENT: usize = 8;
+
+/// Which memory an [`Arena`] takes its block from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArenaMemory {
+    /// The 256KiB of external work RAM, where the heap lives. There is plenty of it but it is
+    /// slower to access.
+    External,
+    /// The 32KiB of internal work RAM, which is fast but small.
+    Internal,
+}
+
+/// Hands out memory from a fixed size block, see the [module level documentation](self).
+///
+/// Values in the arena are never dropped, their memory is just reused after the arena is
+/// [reset](Arena::reset). So anything which needs its destructor to run, like a
+/// [`Vec`](alloc::vec::Vec) using the global allocator, shouldn't be put in it.
+///
+/// The arena implements [`Allocator`], so collections can be put in it with methods like
+/// [`Vec::new_in(&arena)`](alloc::vec::Vec::new_in). Freeing memory in the arena does nothing,
+/// so a vector which grows leaves its old buffer behind until the arena is reset.
+pub struct Arena {
+    block: NonNull<u8>,
+    capacity: usize,
+    used: Cell<usize>,
+    memory: ArenaMemory,
+}
+
+impl Arena {
+    /// Creates an arena which can hold `capacity` bytes, taken from `memory`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't a free block of `capacity` bytes in `memory`.
+    #[must_use]
+    pub fn new(capacity: usize, memory: ArenaMemory) -> Self {
+        let layout = Self::block_layout(capacity);
+        let block = match memory {
+            ArenaMemory::External => ExternalAllocator.allocate(layout),
+            ArenaMemory::Internal => InternalAllocator.allocate(layout),
+        }
+        .expect("not enough memory for the arena");
+
+        Self {
+            block: block.cast(),
+            capacity,
+            used: Cell::new(0),
+            memory,
+        }
+    }
+
+    fn block_layout(capacity: usize) -> Layout {
+        Layout::from_size_align(capacity.max(1), BLOCK_ALIGNMENT).expect("arena is too large")
+    }
+
+    /// The number of bytes the arena can hold.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of bytes handed out since the arena was last reset, including any padding
+    /// needed for alignment.
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Which memory the arena's block is in.
+    #[must_use]
+    pub fn memory(&self) -> ArenaMemory {
+        self.memory
+    }
+
+    /// Frees everything in the arena so that its memory can be used again.
+    ///
+    /// This takes `&mut self`, so it can only be called once nothing borrows from the arena.
+    pub fn reset(&mut self) {
+        self.used.set(0);
+    }
+
+    /// Calls `f` with the arena and then resets it. Nothing borrowed from the arena can escape
+    /// `f`, so this is a convenient way to use the arena for one frame.
+    pub fn scope<R>(&mut self, f: impl FnOnce(&Self) -> R) -> R {
+        let result = f(self);
+        self.reset();
+        result
+    }
+
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let start = self.block.as_ptr() as usize;
+        let next = start + self.used.get();
+        let aligned = next.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = aligned.checked_add(layout.size())?;
+
+        if end > start + self.capacity {
+            return None;
+        }
+
+        self.used.set(end - start);
+        // Safety: the address is inside the block, which isn't null
+        Some(unsafe { self.block.add(aligned - start) })
+    }
+
+    /// Moves `value` into the arena and returns a reference to it, or gives it back if the
+    /// arena is full.
+    #[allow(clippy::mut_from_ref)] // each allocation is handed out once, like Box::leak
+    pub fn try_alloc<T>(&self, value: T) -> Result<&mut T, T> {
+        let Some(ptr) = self.bump(Layout::new::<T>()) else {
+            return Err(value);
+        };
+
+        let ptr = ptr.cast::<T>().as_ptr();
+        // Safety: the memory is suitably aligned, big enough for a T and handed out only once
+        // until the arena is reset, which can't happen while the reference is alive
+        unsafe {
+            ptr.write(value);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// Moves `value` into the arena and returns a reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena is full.
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        match self.try_alloc(value) {
+            Ok(value) => value,
+            Err(_) => panic!("arena is full"),
+        }
+    }
+
+    /// Creates a slice of `len` elements in the arena, each made by calling `f` with its
+    /// index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena doesn't have room for the slice.
+    #[allow(clippy::mut_from_ref)] // each allocation is handed out once, like Box::leak
+    pub fn alloc_slice_fill_with<T>(&self, len: usize, mut f: impl FnMut(usize) -> T) -> &mut [T] {
+        let layout = Layout::array::<T>(len).expect("slice is too large");
+        let ptr = self
+            .bump(layout)
+            .expect("arena is full")
+            .cast::<T>()
+            .as_ptr();
+
+        for i in 0..len {
+            // Safety: the memory has room for len elements
+            unsafe { ptr.add(i).write(f(i)) };
+        }
+
+        // Safety: every element has been initialised
+        unsafe { core::slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    /// Copies `values` into the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena doesn't have room for the slice.
+    pub fn alloc_slice<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        self.alloc_slice_fill_with(values.len(), |i| values[i])
+    }
+}
+
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.bump(layout).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        // memory is only given back when the arena is reset
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        let layout = Self::block_layout(self.capacity);
+
+        // Safety: the block was allocated with this layout by the same allocator in new
+        unsafe {
+            match self.memory {
+                ArenaMemory::External => ExternalAllocator.deallocate(self.block, layout),
+                ArenaMemory::Internal => InternalAllocator.deallocate(self.block, layout),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test_case]
+    fn allocates_from_the_chosen_memory(_gba: &mut crate::Gba) {
+        for (memory, range) in [
+            (ArenaMemory::External, 0x0200_0000..0x0204_0000),
+            (ArenaMemory::Internal, 0x0300_0000..0x0300_8000),
+        ] {
+            let arena = Arena::new(64, memory);
+            let value = arena.alloc(5u32);
+
+            assert!(range.contains(&(value as *mut u32 as usize)));
+            assert_eq!(*value, 5);
+        }
+    }
+
+    #[test_case]
+    fn resets_between_scopes(_gba: &mut crate::Gba) {
+        let mut arena = Arena::new(16, ArenaMemory::Internal);
+
+        let first = arena.scope(|arena| {
+            let byte = arena.alloc(1u8);
+            let word = arena.alloc(2u32);
+            assert_eq!(word as *mut u32 as usize % 4, 0);
+            assert_eq!(arena.used(), 8);
+
+            assert_eq!(arena.alloc_slice(&[1u16, 2, 3]), &[1, 2, 3]);
+            assert_eq!(arena.try_alloc(3u32), Err(3));
+
+            core::ptr::from_mut(byte) as usize
+        });
+
+        assert_eq!(arena.used(), 0);
+        arena.scope(|arena| {
+            assert_eq!(arena.alloc(7u8) as *mut u8 as usize, first);
+        });
+    }
+
+    #[test_case]
+    fn collections_can_be_put_in_the_arena(_gba: &mut crate::Gba) {
+        let arena = Arena::new(256, ArenaMemory::External);
+
+        let mut v = Vec::new_in(&arena);
+        v.extend(0..20);
+
+        assert_eq!(v.iter().sum::<i32>(), 190);
+        assert!(arena.used() >= 20 * 4);
+    }
+}
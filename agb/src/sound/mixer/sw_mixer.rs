@@ -8,11 +8,12 @@ use critical_section::{CriticalSection, Mutex};
 use super::hw::LeftOrRight;
 use super::{hw, Frequency};
 use super::{SoundChannel, SoundPriority};
+use crate::sound::reverb::EchoFilter;
 
 use crate::InternalAllocator;
 use crate::{
     fixnum::Num,
-    interrupt::{add_interrupt_handler, InterruptHandler},
+    interrupt::{add_interrupt_handler, interrupt_free, InterruptHandler},
     timer::Divider,
     timer::Timer,
 };
@@ -112,6 +113,7 @@ pub struct Mixer<'gba> {
     frequency: Frequency,
 
     working_buffer: Box<[Num<i16, 4>], InternalAllocator>,
+    echo: Option<[EchoFilter; 2]>,
 
     fifo_timer: Timer,
 
@@ -146,11 +148,8 @@ impl Mixer<'_> {
         let buffer =
             raw_box::RawBoxDrop::new(Box::new_in(MixerBuffer::new(frequency), InternalAllocator));
 
-        // SAFETY: you can only ever have 1 Mixer at a time
-        let fifo_timer = unsafe { Timer::new(0) };
-
-        // SAFETY: you can only ever have 1 Mixer at a time
-        let mut interrupt_timer = unsafe { Timer::new(1) };
+        let fifo_timer = Timer::claim_or_panic(0, "mixer");
+        let mut interrupt_timer = Timer::claim_or_panic(1, "mixer");
         interrupt_timer
             .set_cascade(true)
             .set_divider(Divider::Divider1)
@@ -189,6 +188,7 @@ impl Mixer<'_> {
             _interrupt_handler: interrupt_handler,
 
             working_buffer: working_buffer.into_boxed_slice(),
+            echo: None,
             fifo_timer,
 
             phantom: PhantomData,
@@ -237,8 +237,44 @@ impl Mixer<'_> {
             return;
         }
 
-        self.buffer
-            .write_channels(&mut self.working_buffer, self.channels.iter_mut().flatten());
+        self.buffer.write_channels(
+            &mut self.working_buffer,
+            self.channels.iter_mut().flatten(),
+            self.echo.as_mut(),
+        );
+    }
+
+    /// Adds an echo to everything the mixer plays, repeating sounds after `delay_ms`
+    /// milliseconds at `feedback / 256` of their volume. See [`EchoFilter`] for details.
+    ///
+    /// The left and right channels each get their own delay line in IWRAM, so this uses up to
+    /// 4KiB of IWRAM depending on the delay and the mixer's frequency.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # #![no_std]
+    /// # #![no_main]
+    /// # use agb::sound::mixer::*;
+    /// # use agb::*;
+    /// # fn foo(gba: &mut Gba) {
+    /// let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+    /// // a quick echo for a cave
+    /// mixer.set_echo(120, 96);
+    /// # }
+    /// ```
+    pub fn set_echo(&mut self, delay_ms: u16, feedback: u8) {
+        let sample_rate = self.frequency.frequency() as u32;
+
+        self.echo = Some([
+            EchoFilter::new(delay_ms, feedback, sample_rate),
+            EchoFilter::new(delay_ms, feedback, sample_rate),
+        ]);
+    }
+
+    /// Removes the echo added with [`set_echo`](Mixer::set_echo), freeing its IWRAM.
+    pub fn disable_echo(&mut self) {
+        self.echo = None;
     }
 
     /// Start playing a given [`SoundChannel`].
@@ -400,7 +436,11 @@ impl MixerBuffer {
     }
 
     fn should_calculate(&self) -> bool {
-        critical_section::with(|cs| self.state.borrow_ref_mut(cs).should_calculate())
+        interrupt_free(|token| {
+            self.state
+                .borrow_ref_mut(token.critical_section())
+                .should_calculate()
+        })
     }
 
     fn swap(&self, cs: CriticalSection) {
@@ -418,6 +458,7 @@ impl MixerBuffer {
         &self,
         working_buffer: &mut [Num<i16, 4>],
         channels: impl Iterator<Item = &'a mut SoundChannel>,
+        echo: Option<&mut [EchoFilter; 2]>,
     ) {
         let mut channels = channels
             .filter(|channel| !channel.is_done && channel.volume != 0.into() && channel.is_playing);
@@ -440,8 +481,23 @@ impl MixerBuffer {
             }
         }
 
-        let write_buffer =
-            critical_section::with(|cs| self.state.borrow_ref_mut(cs).active_advanced());
+        if let Some(echo) = echo {
+            // the working buffer alternates between the two channels, and the fractional part
+            // of each sample is thrown away when collapsing anyway
+            for samples in working_buffer.chunks_exact_mut(2) {
+                for (sample, filter) in samples.iter_mut().zip(echo.iter_mut()) {
+                    let feedback = filter.feedback();
+                    let output = filter.process(sample.to_raw() >> 4, feedback);
+                    *sample = Num::from_raw(output.clamp(-2048, 2047) << 4);
+                }
+            }
+        }
+
+        let write_buffer = interrupt_free(|token| {
+            self.state
+                .borrow_ref_mut(token.critical_section())
+                .active_advanced()
+        });
 
         unsafe {
             agb_rs__mixer_collapse(
@@ -15,3 +15,5 @@
 pub mod dmg;
 
 pub mod mixer;
+pub mod reverb;
+pub mod volume_envelope;
@@ -0,0 +1,121 @@
+//! A simple echo effect for the [mixer](super::mixer).
+//!
+//! [`EchoFilter`] keeps the last few milliseconds of output in a delay line and mixes it back
+//! in, so each sound repeats more quietly after the delay. The easiest way to use it is
+//! [`Mixer::set_echo`](super::mixer::Mixer::set_echo), which runs an echo filter over
+//! everything the mixer plays.
+
+use alloc::vec::Vec;
+
+use crate::InternalAllocator;
+
+/// The most samples an [`EchoFilter`] can delay by.
+pub const MAX_DELAY_SAMPLES: usize = 2048;
+
+/// A delay line which mixes its input with what it was given a fixed number of samples ago.
+///
+/// Samples are 8-bit PCM values widened to `i16`, as the mixer works with, so they can go
+/// outside the range of an `i8` while being mixed.
+///
+/// The delay line is one byte per sample and is kept in IWRAM so that the filter is fast enough
+/// to run over every sample the mixer produces. Longer delays and higher sample rates need a
+/// longer delay line and so use more IWRAM, up to 2KiB for [`MAX_DELAY_SAMPLES`].
+pub struct EchoFilter {
+    delay_line: Vec<i8, InternalAllocator>,
+    position: usize,
+    feedback: u8,
+}
+
+impl EchoFilter {
+    /// Creates a filter which echoes after `delay_ms` milliseconds when processing
+    /// `sample_rate` samples per second. The delay is limited to between 1 and
+    /// [`MAX_DELAY_SAMPLES`] samples, which is almost 200ms at 10512Hz.
+    ///
+    /// `feedback` is how loud the echo is, where 256 would be as loud as the original sound.
+    #[must_use]
+    pub fn new(delay_ms: u16, feedback: u8, sample_rate: u32) -> Self {
+        let delay_samples = (u64::from(delay_ms) * u64::from(sample_rate) / 1000)
+            .clamp(1, MAX_DELAY_SAMPLES as u64) as usize;
+
+        let mut delay_line = Vec::with_capacity_in(delay_samples, InternalAllocator);
+        delay_line.resize(delay_samples, 0);
+
+        Self {
+            delay_line,
+            position: 0,
+            feedback,
+        }
+    }
+
+    /// The number of samples between a sound and its echo.
+    #[must_use]
+    pub fn delay_samples(&self) -> usize {
+        self.delay_line.len()
+    }
+
+    /// The feedback the filter was created with.
+    #[must_use]
+    pub fn feedback(&self) -> u8 {
+        self.feedback
+    }
+
+    /// Mixes `input` with the delayed sample scaled by `feedback / 256`, stores the result in
+    /// the delay line, and returns it.
+    ///
+    /// Because the output goes back into the delay line, echoes repeat, getting quieter each
+    /// time by a factor of `feedback / 256`.
+    pub fn process(&mut self, input: i16, feedback: u8) -> i16 {
+        let delayed = i32::from(self.delay_line[self.position]);
+        let output = (i32::from(input) + ((delayed * i32::from(feedback)) >> 8))
+            .clamp(i16::MIN.into(), i16::MAX.into());
+
+        self.delay_line[self.position] = output.clamp(i8::MIN.into(), i8::MAX.into()) as i8;
+        self.position += 1;
+        if self.position == self.delay_line.len() {
+            self.position = 0;
+        }
+
+        output as i16
+    }
+
+    /// Empties the delay line, silencing any echoes still to come.
+    pub fn clear(&mut self) {
+        self.delay_line.fill(0);
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn echoes_after_the_delay(_gba: &mut crate::Gba) {
+        let mut echo = EchoFilter::new(1, 128, 4000);
+        assert_eq!(echo.delay_samples(), 4);
+
+        assert_eq!(echo.process(100, 128), 100);
+        for _ in 0..3 {
+            assert_eq!(echo.process(0, 128), 0);
+        }
+
+        assert_eq!(echo.process(0, 128), 50);
+        for _ in 0..3 {
+            assert_eq!(echo.process(0, 128), 0);
+        }
+        assert_eq!(echo.process(0, 128), 25);
+
+        // the delay line holds 8 bit samples, so loud sounds are clipped in the echo
+        echo.clear();
+        assert_eq!(echo.process(1000, 255), 1000);
+        for _ in 0..3 {
+            echo.process(0, 255);
+        }
+        assert_eq!(echo.process(0, 255), 126);
+
+        assert_eq!(
+            EchoFilter::new(u16::MAX, 128, 32768).delay_samples(),
+            MAX_DELAY_SAMPLES
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Smooth changes in volume, including ADSR envelopes.
+//!
+//! Changing a sound's volume instantly causes an audible click. [`VolumeEnvelope`] ramps the
+//! volume linearly instead, either to a single target with [`set_target`](VolumeEnvelope::set_target)
+//! or through the attack, decay, sustain and release stages of a standard ADSR envelope with
+//! [`trigger_adsr`](VolumeEnvelope::trigger_adsr).
+//!
+//! The envelope can be applied a sample at a time with [`process`](VolumeEnvelope::process) when
+//! generating sound yourself. For sounds played by the [mixer](super::mixer), advance it by the
+//! number of samples the mixer produces each frame and set the channel's volume from it:
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # use agb::sound::mixer::{ChannelId, Mixer};
+//! # fn foo(mixer: &mut Mixer, id: ChannelId, samples_per_frame: u32) {
+//! use agb::sound::volume_envelope::VolumeEnvelope;
+//!
+//! let mut envelope = VolumeEnvelope::new(0);
+//! envelope.trigger_adsr(200, 500, 180, 2000);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     envelope.advance(samples_per_frame);
+//!     if let Some(channel) = mixer.channel(&id) {
+//!         channel.volume(envelope.channel_volume());
+//!     }
+//!
+//!     mixer.frame();
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use crate::fixnum::Num;
+
+/// The number of fractional bits used while ramping, so that slow ramps still move.
+const FRACTION_BITS: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Ramping to a target set with `set_target`, or finished ramping.
+    Ramp,
+    Attack {
+        decay: u16,
+        sustain: u8,
+        release: u16,
+    },
+    Decay {
+        release: u16,
+    },
+    Sustain {
+        release: u16,
+    },
+    Release,
+}
+
+/// A volume between 0 (silent) and 255 (full volume) which moves linearly towards a target.
+#[derive(Clone, Debug)]
+pub struct VolumeEnvelope {
+    current: i32,
+    target: i32,
+    step: i32,
+    remaining: u32,
+    stage: Stage,
+}
+
+impl VolumeEnvelope {
+    /// Creates an envelope which starts at `volume` and stays there.
+    #[must_use]
+    pub const fn new(volume: u8) -> Self {
+        Self {
+            current: (volume as i32) << FRACTION_BITS,
+            target: (volume as i32) << FRACTION_BITS,
+            step: 0,
+            remaining: 0,
+            stage: Stage::Ramp,
+        }
+    }
+
+    /// The current volume, between 0 and 255.
+    #[must_use]
+    pub fn volume(&self) -> u8 {
+        (self.current >> FRACTION_BITS) as u8
+    }
+
+    /// The current volume as a [`SoundChannel`](super::mixer::SoundChannel) volume, where 255
+    /// becomes 1.
+    #[must_use]
+    pub fn channel_volume(&self) -> Num<i16, 8> {
+        Num::from_raw((i32::from(self.volume()) * 256 / 255) as i16)
+    }
+
+    fn ramp_to(&mut self, volume: u8, samples: u32) {
+        self.target = i32::from(volume) << FRACTION_BITS;
+
+        if samples == 0 {
+            self.current = self.target;
+            self.remaining = 0;
+            self.step = 0;
+        } else {
+            // a ramp too long for an i32 moves too slowly to notice the difference anyway
+            let samples_i32 = i32::try_from(samples).unwrap_or(i32::MAX);
+            self.step = (self.target - self.current) / samples_i32;
+            self.remaining = samples;
+        }
+    }
+
+    /// Ramps the volume from wherever it is now to `volume` over `attack_samples` samples.
+    /// This replaces any ADSR envelope which is running.
+    pub fn set_target(&mut self, volume: u8, attack_samples: u32) {
+        self.stage = Stage::Ramp;
+        self.ramp_to(volume, attack_samples);
+    }
+
+    /// Starts an ADSR envelope from the current volume. The volume ramps up to full over
+    /// `attack` samples, then down to `sustain` over `decay` samples, and stays there until
+    /// [`release`](VolumeEnvelope::release) is called. Releasing ramps down to silence over
+    /// `release` samples.
+    pub fn trigger_adsr(&mut self, attack: u16, decay: u16, sustain: u8, release: u16) {
+        self.stage = Stage::Attack {
+            decay,
+            sustain,
+            release,
+        };
+        self.ramp_to(u8::MAX, attack.into());
+        self.next_stage_if_finished();
+    }
+
+    /// Starts the release stage of the ADSR envelope started by
+    /// [`trigger_adsr`](VolumeEnvelope::trigger_adsr), whichever stage it is in now. Does
+    /// nothing if no envelope is running.
+    pub fn release(&mut self) {
+        if let Stage::Attack { release, .. }
+        | Stage::Decay { release }
+        | Stage::Sustain { release } = self.stage
+        {
+            self.stage = Stage::Release;
+            self.ramp_to(0, release.into());
+        }
+    }
+
+    fn next_stage_if_finished(&mut self) {
+        while self.remaining == 0 {
+            match self.stage {
+                Stage::Attack {
+                    decay,
+                    sustain,
+                    release,
+                } => {
+                    self.stage = Stage::Decay { release };
+                    self.ramp_to(sustain, decay.into());
+                }
+                Stage::Decay { release } => self.stage = Stage::Sustain { release },
+                Stage::Ramp | Stage::Sustain { .. } | Stage::Release => return,
+            }
+        }
+    }
+
+    /// Advances the envelope by `samples` samples without processing any sound.
+    pub fn advance(&mut self, mut samples: u32) {
+        while samples > 0 && self.remaining > 0 {
+            let steps = samples.min(self.remaining);
+
+            self.current += self.step * steps as i32;
+            self.remaining -= steps;
+            samples -= steps;
+
+            if self.remaining == 0 {
+                // avoid rounding errors in the step leaving the volume just short of the target
+                self.current = self.target;
+                self.next_stage_if_finished();
+            }
+        }
+    }
+
+    /// Scales `sample` by the current volume, then advances the envelope by one sample.
+    pub fn process(&mut self, sample: i8) -> i8 {
+        let output = i32::from(sample) * i32::from(self.volume()) / 255;
+        self.advance(1);
+
+        output as i8
+    }
+
+    /// Whether the volume has stopped changing. This is the case once a ramp started by
+    /// [`set_target`](VolumeEnvelope::set_target) has finished, and for an ADSR envelope while
+    /// it is sustaining or once it has been released completely.
+    #[must_use]
+    pub fn is_settled(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn ramps_linearly_to_the_target(_gba: &mut crate::Gba) {
+        let mut envelope = VolumeEnvelope::new(0);
+        envelope.set_target(200, 4);
+
+        let volumes: [u8; 5] = core::array::from_fn(|_| {
+            let volume = envelope.volume();
+            envelope.advance(1);
+            volume
+        });
+        assert_eq!(volumes, [0, 50, 100, 150, 200]);
+        assert!(envelope.is_settled());
+
+        assert_eq!(envelope.process(-100), -78);
+        assert_eq!(VolumeEnvelope::new(255).process(127), 127);
+    }
+
+    #[test_case]
+    fn adsr_goes_through_each_stage(_gba: &mut crate::Gba) {
+        let mut envelope = VolumeEnvelope::new(0);
+        envelope.trigger_adsr(10, 20, 100, 30);
+
+        envelope.advance(10);
+        assert_eq!(envelope.volume(), 255);
+        assert!(!envelope.is_settled());
+
+        envelope.advance(20);
+        assert_eq!(envelope.volume(), 100);
+        assert!(envelope.is_settled());
+
+        envelope.advance(1000);
+        assert_eq!(envelope.volume(), 100);
+
+        envelope.release();
+        envelope.advance(15);
+        assert_eq!(envelope.volume(), 50);
+        envelope.advance(15);
+        assert_eq!(envelope.volume(), 0);
+        assert!(envelope.is_settled());
+    }
+}
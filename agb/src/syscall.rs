@@ -4,6 +4,7 @@ use core::mem::MaybeUninit;
 
 use crate::display::affine::AffineMatrixBackground;
 use crate::fixnum::Num;
+use crate::net::multiboot::MultiBootParam;
 
 #[allow(non_snake_case)]
 const fn swi_map(thumb_id: u32) -> u32 {
@@ -53,6 +54,21 @@ pub fn wait_for_interrupt() {
     }
 }
 
+/// Halts until one of the interrupts in `mask` happens. If `discard_old` is set, interrupts
+/// which already happened since the last wait are ignored.
+pub(crate) fn interrupt_wait(discard_old: bool, mask: u16) {
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x04) },
+            inlateout("r0") u32::from(discard_old) => _,
+            inlateout("r1") u32::from(mask) => _,
+            lateout("r2") _,
+            lateout("r3") _
+        );
+    }
+}
+
 /// The vblank interrupt handler [VBlank][crate::interrupt::VBlank] should be
 /// used instead of calling this function directly.
 pub(crate) fn wait_for_vblank() {
@@ -68,6 +84,93 @@ pub(crate) fn wait_for_vblank() {
     }
 }
 
+/// Clears the memory and resets the registers selected by `flags`, using the BIOS
+/// `RegisterRamReset` call. Bit 0 is EWRAM, 1 is IWRAM except the last 512 bytes, 2 is palette
+/// RAM, 3 is VRAM, 4 is OAM, 5 is the serial registers, 6 is the sound registers and 7 is every
+/// other register.
+///
+/// # Safety
+///
+/// Nothing in the cleared memory can be used afterwards, including the code and data of the
+/// caller.
+pub(crate) unsafe fn register_ram_reset(flags: u8) {
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x01) },
+            inlateout("r0") u32::from(flags) => _,
+            lateout("r1") _,
+            lateout("r2") _,
+            lateout("r3") _
+        );
+    }
+}
+
+/// Clears the memory and resets the registers selected by `flags` like [`register_ram_reset`],
+/// then restarts the game with the BIOS `SoftReset` call. This starts from the cartridge, or
+/// from the start of EWRAM if the byte at `0x0300_7ffa` isn't zero. Both calls are made back to
+/// back, so this can clear IWRAM even though the stack lives there.
+///
+/// # Safety
+///
+/// The caller must be running from memory which `flags` doesn't clear.
+pub(crate) unsafe fn register_ram_reset_and_soft_reset(flags: u8) -> ! {
+    unsafe {
+        asm!(
+            "swi {RESET}",
+            "swi {SOFT_RESET}",
+            RESET = const { swi_map(0x01) },
+            SOFT_RESET = const { swi_map(0x00) },
+            in("r0") u32::from(flags),
+            options(noreturn)
+        );
+    }
+}
+
+/// Sends a multiboot ROM to the clients described by `param` with the BIOS `MultiBoot` call,
+/// once the handshake before it has been done. `mode` is 0 for normal mode at 256KHz, 1 for
+/// multi-player mode and 2 for normal mode at 2MHz. Returns `true` if the transfer worked.
+///
+/// # Safety
+///
+/// The boot pointers in `param` must point to the ROM to send, which must stay alive until this
+/// returns.
+pub(crate) unsafe fn multi_boot(param: &mut MultiBootParam, mode: u32) -> bool {
+    let result: u32;
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x25) },
+            inlateout("r0") param as *mut MultiBootParam => result,
+            in("r1") mode,
+
+            clobber_abi("C")
+        );
+    }
+    result == 0
+}
+
+/// Decompresses BIOS run length encoded data from `src` to `dst` with the BIOS
+/// `RLUnCompReadNormalWrite16bit` call, which writes a halfword at a time so works for VRAM.
+///
+/// # Safety
+///
+/// `src` must be 4 byte aligned and point to valid run length encoded data, and `dst` must be 2
+/// byte aligned with room for the decompressed length given in the data's header, rounded up to
+/// a multiple of 2.
+pub(crate) unsafe fn rl_uncomp_vram(src: *const u8, dst: *mut u16) {
+    unsafe {
+        asm!(
+            "swi {SWI}",
+            SWI = const { swi_map(0x15) },
+            inlateout("r0") src => _,
+            inlateout("r1") dst => _,
+            lateout("r2") _,
+            lateout("r3") _
+        );
+    }
+}
+
 #[must_use]
 pub fn div(numerator: i32, denominator: i32) -> (i32, i32, i32) {
     let divide: i32;
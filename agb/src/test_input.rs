@@ -0,0 +1,130 @@
+//! Playing scripted button presses to the game in tests, so whole scenarios can be played out
+//! the same way every time.
+//!
+//! While a script is playing, [`ButtonController`](crate::input::ButtonController) reads the
+//! buttons from it rather than from the hardware. A vblank interrupt handler moves the script on
+//! to its next frame once per frame, however many controllers there are and however often they
+//! are updated. This is only compiled in for agb's own tests and with the `scripted-input`
+//! feature, so games don't pay for it.
+
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use crate::{
+    input::Button,
+    interrupt::{add_interrupt_handler, interrupt_free, Interrupt},
+};
+
+#[derive(Clone, Copy)]
+struct Script {
+    frames: &'static [Button],
+    /// The frame which [`ButtonController`](crate::input::ButtonController)s currently see.
+    current: usize,
+}
+
+static SCRIPT: Mutex<Cell<Option<Script>>> = Mutex::new(Cell::new(None));
+
+/// Plays `frames` to the [`ButtonController`](crate::input::ButtonController)s while `f` runs.
+pub(crate) fn play<T>(frames: &'static [Button], f: impl FnOnce() -> T) -> T {
+    interrupt_free(|token| {
+        SCRIPT
+            .borrow(token.critical_section())
+            .set(Some(Script { frames, current: 0 }));
+    });
+
+    // Safety: the handler doesn't allocate
+    let advance = unsafe {
+        add_interrupt_handler(Interrupt::VBlank, |cs| {
+            let cell = SCRIPT.borrow(cs);
+            if let Some(mut script) = cell.get() {
+                script.current += 1;
+                cell.set(Some(script));
+            }
+        })
+    };
+
+    let result = f();
+
+    drop(advance);
+    interrupt_free(|token| SCRIPT.borrow(token.critical_section()).set(None));
+
+    result
+}
+
+/// The buttons pressed on the current frame of the script, in the same format as the inverted
+/// `KEYINPUT` register, or `None` if no script is playing.
+pub(crate) fn current() -> Option<u16> {
+    interrupt_free(|token| SCRIPT.borrow(token.critical_section()).get()).map(pressed)
+}
+
+/// How many frames of the script have been played, or 0 if no script is playing.
+#[cfg(test)]
+fn frames_played() -> usize {
+    interrupt_free(|token| SCRIPT.borrow(token.critical_section()).get())
+        .map_or(0, |script| script.current)
+}
+
+fn pressed(script: Script) -> u16 {
+    script
+        .frames
+        .get(script.current)
+        .map_or(0, |buttons| buttons.bits() as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        input::{Button, ButtonController},
+        interrupt::VBlank,
+    };
+
+    #[test_case]
+    fn button_controller_plays_the_script(_gba: &mut crate::Gba) {
+        static SCRIPT: [Button; 4] = [
+            Button::A,
+            Button::A,
+            Button::A,
+            Button::LEFT.union(Button::B),
+        ];
+
+        let vblank = VBlank::get();
+
+        let frames = crate::test_runner::with_scripted_input(&SCRIPT, || {
+            // start at the beginning of a frame so the checks below all happen within one
+            vblank.wait_for_vblank();
+            assert_eq!(super::frames_played(), 1);
+
+            let mut input = ButtonController::new();
+            assert!(input.is_pressed(Button::A));
+
+            // updating more than once a frame doesn't move the script on
+            input.update();
+            input.update();
+            assert!(input.is_pressed(Button::A));
+            assert!(!input.is_just_pressed(Button::A));
+            assert_eq!(super::frames_played(), 1);
+
+            vblank.wait_for_vblank();
+            input.update();
+            assert!(input.is_pressed(Button::A));
+            assert!(!input.is_just_pressed(Button::A));
+
+            vblank.wait_for_vblank();
+            input.update();
+            assert!(input.is_just_released(Button::A));
+            assert!(input.is_just_pressed(Button::B));
+            assert!(input.is_pressed(Button::LEFT));
+
+            // nothing is pressed once the script has finished
+            vblank.wait_for_vblank();
+            input.update();
+            assert!(input.is_just_released(Button::LEFT));
+
+            super::frames_played()
+        });
+
+        assert_eq!(frames, 4);
+        assert_eq!(super::current(), None);
+    }
+}
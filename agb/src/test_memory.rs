@@ -0,0 +1,135 @@
+//! Measuring how much heap and stack each test uses.
+//!
+//! The heaps keep track of their own peak usage and how many allocations they have made. The
+//! stack is measured by filling the unused part of it with a pattern before the test and
+//! looking for the lowest address where the pattern was overwritten afterwards. A hole in a
+//! deep stack frame which is never written to can make this slightly underestimate, and
+//! interrupt handlers which run during the test count towards its stack since they use the
+//! same stack.
+
+use core::arch::asm;
+
+use crate::agb_alloc;
+
+/// Written over the unused stack, and unlikely to be written by anything else.
+const STACK_PAINT: u32 = 0x5ac5_5ac5;
+/// Space left below the stack pointer of the function painting the stack, so that it doesn't
+/// paint over itself.
+const PAINT_MARGIN: usize = 64;
+
+/// The heap and stack used by a test, measured by [`MemoryMeasurement`].
+pub(crate) struct MemoryUsage {
+    /// The most bytes allocated at once in both heaps, above what was allocated before the test.
+    pub(crate) peak_heap_bytes: usize,
+    /// The number of allocations and reallocations made.
+    pub(crate) allocations: usize,
+    /// The most stack used by the test and anything it called, in bytes.
+    pub(crate) peak_stack_bytes: usize,
+}
+
+pub(crate) struct MemoryMeasurement {
+    allocations_before: usize,
+    allocated_before: usize,
+    stack_top: usize,
+    painted_from: usize,
+}
+
+impl MemoryMeasurement {
+    /// Starts measuring, painting the unused stack. The stack used by the caller isn't counted.
+    #[inline(never)]
+    pub(crate) fn start() -> Self {
+        agb_alloc::reset_peak_heap_usage();
+
+        let stack_top = stack_pointer();
+        let painted_from = stack_bottom();
+        let painted_to = stack_top.saturating_sub(PAINT_MARGIN) & !3;
+
+        for address in (painted_from..painted_to).step_by(4) {
+            // Safety: this is the unused space between the iwram heap and the stack
+            unsafe { (address as *mut u32).write_volatile(STACK_PAINT) };
+        }
+
+        Self {
+            allocations_before: agb_alloc::total_allocations(),
+            allocated_before: allocated_bytes(),
+            stack_top,
+            painted_from,
+        }
+    }
+
+    /// The number of allocations made since measuring started.
+    pub(crate) fn allocations(&self) -> usize {
+        agb_alloc::total_allocations() - self.allocations_before
+    }
+
+    pub(crate) fn finish(self) -> MemoryUsage {
+        let peak_heap_bytes = (crate::mem::heap_stats().peak_allocated_bytes
+            + crate::mem::iwram_heap_stats().peak_allocated_bytes)
+            .saturating_sub(self.allocated_before);
+
+        // anything the iwram heap has grown into isn't stack
+        let scan_from = self.painted_from.max(stack_bottom());
+        let lowest_used = (scan_from..self.stack_top)
+            .step_by(4)
+            // Safety: this was painted by `start` or is still free space
+            .find(|&address| unsafe { (address as *const u32).read_volatile() } != STACK_PAINT)
+            .unwrap_or(self.stack_top);
+
+        MemoryUsage {
+            peak_heap_bytes,
+            allocations: self.allocations(),
+            peak_stack_bytes: self.stack_top - lowest_used,
+        }
+    }
+}
+
+fn allocated_bytes() -> usize {
+    crate::mem::heap_stats().allocated_bytes + crate::mem::iwram_heap_stats().allocated_bytes
+}
+
+/// The lowest address the stack could grow down to without hitting the iwram heap.
+fn stack_bottom() -> usize {
+    (agb_alloc::iwram_heap_end() + 3) & !3
+}
+
+#[inline(always)]
+fn stack_pointer() -> usize {
+    let sp: usize;
+    // Safety: only reads the stack pointer
+    unsafe { asm!("mov {}, sp", out(reg) sp, options(nomem, nostack, preserves_flags)) };
+    sp
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+    use core::hint::black_box;
+
+    use super::*;
+    use crate::test_runner::assert_no_allocations;
+
+    #[inline(never)]
+    fn use_stack() -> u32 {
+        let buffer = black_box([1u32; 256]);
+        buffer.iter().sum()
+    }
+
+    #[test_case]
+    fn measures_heap_and_stack(_gba: &mut crate::Gba) {
+        let measurement = MemoryMeasurement::start();
+
+        let vec: Vec<u8> = black_box(Vec::with_capacity(1000));
+        drop(vec);
+        let boxed = black_box(Box::new(5u32));
+        assert_eq!(use_stack(), 256);
+
+        let usage = measurement.finish();
+        drop(boxed);
+
+        assert_eq!(usage.allocations, 2);
+        assert_eq!(usage.peak_heap_bytes, 1000);
+        assert!(usage.peak_stack_bytes >= 1024, "{}", usage.peak_stack_bytes);
+
+        assert_eq!(assert_no_allocations(use_stack), 256);
+    }
+}
@@ -0,0 +1,79 @@
+//! Choosing which tests to run, set by `mgba-test-runner` from its command line.
+//!
+//! The ROM has a block of options which starts with [`MAGIC`] and is otherwise empty.
+//! `mgba-test-runner` finds that block in the ROM before loading it and writes the filter and
+//! flags given to it there, so that `cargo test save::flash` only runs the tests with
+//! `save::flash` in their names.
+
+/// The first bytes of the options block, which `mgba-test-runner` searches the ROM for.
+const MAGIC: [u8; 16] = *b"agb-test-options";
+const LENGTH: usize = 128;
+const FLAGS_OFFSET: usize = MAGIC.len();
+const FILTER_OFFSET: usize = FLAGS_OFFSET + 1;
+const FILTER_LENGTH: usize = LENGTH - FILTER_OFFSET;
+
+const INCLUDE_IGNORED: u8 = 1 << 0;
+const ONLY_IGNORED: u8 = 1 << 1;
+
+#[used]
+static OPTIONS: [u8; LENGTH] = {
+    let mut options = [0; LENGTH];
+
+    let mut i = 0;
+    while i < MAGIC.len() {
+        options[i] = MAGIC[i];
+        i += 1;
+    }
+
+    options
+};
+
+pub(crate) struct TestOptions {
+    flags: u8,
+    filter: [u8; FILTER_LENGTH],
+    filter_length: usize,
+}
+
+impl TestOptions {
+    /// The options written into the ROM by `mgba-test-runner`.
+    pub(crate) fn read() -> Self {
+        // the options are changed after compiling, so they mustn't be read at compile time
+        let read = |offset: usize| unsafe { OPTIONS.as_ptr().add(offset).read_volatile() };
+
+        let mut filter = [0; FILTER_LENGTH];
+        let mut filter_length = 0;
+        while filter_length < FILTER_LENGTH {
+            let byte = read(FILTER_OFFSET + filter_length);
+            if byte == 0 {
+                break;
+            }
+
+            filter[filter_length] = byte;
+            filter_length += 1;
+        }
+
+        Self {
+            flags: read(FLAGS_OFFSET),
+            filter,
+            filter_length,
+        }
+    }
+
+    /// The part of the name which tests must have to be run, which is empty to run every test.
+    pub(crate) fn filter(&self) -> &str {
+        core::str::from_utf8(&self.filter[..self.filter_length]).unwrap_or("")
+    }
+
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        name.contains(self.filter())
+    }
+
+    /// Whether a test which is or isn't ignored should run, if its name matches.
+    pub(crate) fn runs(&self, ignored: bool) -> bool {
+        if self.flags & ONLY_IGNORED != 0 {
+            ignored
+        } else {
+            !ignored || self.flags & INCLUDE_IGNORED != 0
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Stopping tests which never finish, so the rest of the tests still run.
+//!
+//! While a test runs, a vblank interrupt counts the frames it has taken. Once it has taken more
+//! than its timeout, the test is reported as timed out and the game is reset with the BIOS,
+//! with the index of the next test kept in palette RAM so the [test
+//! runner](crate::test_runner::test_runner) can carry on from there. Everything else is cleared
+//! by the reset, so the remaining tests start from the same state as they would otherwise.
+//!
+//! This can't catch a test which hangs with interrupts disabled, and multiboot builds live in
+//! the EWRAM which the reset would clear, so a timeout there panics instead. `mgba-test-runner`
+//! has a wall clock timeout as well for these cases.
+
+use portable_atomic::{AtomicU32, Ordering};
+
+use crate::{
+    interrupt::{add_interrupt_handler, Interrupt, InterruptHandler},
+    memory_mapped::MemoryMapped,
+    mgba, syscall,
+};
+
+/// Marks the next test index left in palette RAM by a timeout, rather than whatever palette
+/// happened to be there.
+const RESUME_MAGIC: u32 = 0x5449_4d45;
+
+// the last colours of the last object palette, which are only read once after a reset
+const RESUME_MAGIC_ADDRESS: usize = 0x0500_03f8;
+const RESUME_INDEX_ADDRESS: usize = 0x0500_03fc;
+
+const RESET_EWRAM: u8 = 1 << 0;
+const RESET_IWRAM: u8 = 1 << 1;
+const RESET_PALETTE: u8 = 1 << 2;
+const RESET_VRAM: u8 = 1 << 3;
+const RESET_OAM: u8 = 1 << 4;
+const RESET_SERIAL: u8 = 1 << 5;
+const RESET_SOUND: u8 = 1 << 6;
+const RESET_REGISTERS: u8 = 1 << 7;
+
+/// The timeout of the running test, or 0 if no test is running.
+static TIMEOUT_FRAMES: AtomicU32 = AtomicU32::new(0);
+static FRAMES: AtomicU32 = AtomicU32::new(0);
+static CURRENT_TEST: AtomicU32 = AtomicU32::new(0);
+
+/// Starts counting frames for the tests. The watchdog only runs while the returned handler is
+/// alive.
+pub(crate) fn install() -> InterruptHandler {
+    // Safety: the handler doesn't allocate
+    unsafe {
+        add_interrupt_handler(Interrupt::VBlank, |_| {
+            let timeout_frames = TIMEOUT_FRAMES.load(Ordering::SeqCst);
+            if timeout_frames == 0 {
+                return;
+            }
+
+            let frames = FRAMES.fetch_add(1, Ordering::SeqCst) + 1;
+            if frames > timeout_frames {
+                timed_out(frames);
+            }
+        })
+    }
+}
+
+/// Gives the test at `index` in the list of tests `timeout_frames` frames to finish.
+pub(crate) fn start_test(index: usize, timeout_frames: u32) {
+    CURRENT_TEST.store(index as u32, Ordering::SeqCst);
+    FRAMES.store(0, Ordering::SeqCst);
+    TIMEOUT_FRAMES.store(timeout_frames, Ordering::SeqCst);
+}
+
+pub(crate) fn finish_test() {
+    TIMEOUT_FRAMES.store(0, Ordering::SeqCst);
+}
+
+/// The index of the test to carry on from if the game was reset by a timeout, otherwise 0.
+pub(crate) fn take_resume_index() -> usize {
+    let magic = unsafe { MemoryMapped::<u32>::new(RESUME_MAGIC_ADDRESS) };
+    if magic.get() != RESUME_MAGIC {
+        return 0;
+    }
+
+    let index = unsafe { MemoryMapped::<u32>::new(RESUME_INDEX_ADDRESS) }.get();
+
+    // Safety: nothing has used palette RAM yet
+    unsafe { syscall::register_ram_reset(RESET_PALETTE) };
+
+    index as usize
+}
+
+fn timed_out(frames: u32) -> ! {
+    if let Some(mut mgba) = mgba::Mgba::new() {
+        let _ = mgba.print(
+            format_args!("[timeout after {frames} frames]"),
+            mgba::DebugLevel::Error,
+        );
+    }
+
+    let running_from_ewram = (timed_out as *const () as usize) >> 24 == 0x02;
+    if running_from_ewram {
+        panic!("test timed out after {frames} frames");
+    }
+
+    let next_test = CURRENT_TEST.load(Ordering::SeqCst) + 1;
+
+    unsafe {
+        MemoryMapped::<u32>::new(RESUME_MAGIC_ADDRESS).set(RESUME_MAGIC);
+        MemoryMapped::<u32>::new(RESUME_INDEX_ADDRESS).set(next_test);
+
+        // restart from the cartridge rather than EWRAM
+        MemoryMapped::<u8>::new(0x0300_7ffa).set(0);
+
+        // Safety: this is running from ROM and the reset never returns to the cleared stack,
+        // and everything else is started again
+        syscall::register_ram_reset_and_soft_reset(
+            RESET_EWRAM
+                | RESET_IWRAM
+                | RESET_VRAM
+                | RESET_OAM
+                | RESET_SERIAL
+                | RESET_SOUND
+                | RESET_REGISTERS,
+        )
+    }
+}
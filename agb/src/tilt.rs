@@ -0,0 +1,191 @@
+//! The two axis tilt sensor found on some cartridges, for motion controlled games.
+//!
+//! The sensor is an accelerometer mapped into the save memory area, as used by Yoshi Topsy-Turvy
+//! and Koro Koro Puzzle, and by flash carts which copy their mapper. A sample is started by
+//! writing a handshake, and the result can be read a little later once the sensor says it is
+//! ready. [`TiltSensor::sample`] does both, so calling it once a frame gives a new reading each
+//! frame.
+//!
+//! The readings are 12 bit values which sit around `0x3a0` when the GBA is held flat. They are
+//! given relative to a neutral position, which can be [captured](TiltSensor::capture_neutral)
+//! while the player holds the GBA how they like, and can be
+//! [scaled](TiltSensor::normalise) to fixed point values from -1 to 1.
+//!
+//! Since the sensor uses the save memory area, it can't be used with SRAM or flash saves. Looking
+//! for a sensor writes to that area, which on an SRAM cartridge overwrites part of the save, so
+//! [`TiltSensor::new`] is unsafe and must only be called by games which don't use SRAM or flash.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo() {
+//! use agb::tilt::TiltSensor;
+//!
+//! // Safety: this game saves to EEPROM, so there is no SRAM to overwrite
+//! let Some(mut tilt) = (unsafe { TiltSensor::new() }) else {
+//!     // no sensor, so use the d-pad instead
+//!     return;
+//! };
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     if let Some(sample) = tilt.sample() {
+//!         let tilt = tilt.normalise(sample);
+//!         // roll the ball by tilt.x and tilt.y
+//! #       let _ = tilt;
+//!     }
+//!
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use crate::{
+    fixnum::{Num, Vector2D},
+    memory_mapped::MemoryMapped,
+    net::Timeout,
+};
+
+const START_1: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8000) };
+const START_2: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8100) };
+const X_LOW: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8200) };
+const X_HIGH: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8300) };
+const Y_LOW: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8400) };
+const Y_HIGH: MemoryMapped<u8> = unsafe { MemoryMapped::new(0x0E00_8500) };
+
+/// Set in the top byte of the x axis once the sample is ready.
+const READY: u8 = 1 << 7;
+
+/// Roughly what the sensors read when held flat.
+const DEFAULT_NEUTRAL: i16 = 0x3a0;
+/// Roughly how far the readings move from neutral when the GBA is tilted a long way.
+const DEFAULT_RANGE: i16 = 0xe0;
+
+/// How long to wait for the first sample before deciding there is no sensor.
+const PROBE_TIMEOUT_FRAMES: u32 = 2;
+
+/// The cartridge's tilt sensor, see the [module level documentation](self).
+pub struct TiltSensor {
+    neutral: Vector2D<i16>,
+    range: i16,
+    last_raw: Vector2D<i16>,
+}
+
+impl TiltSensor {
+    /// Checks for a tilt sensor by taking a sample, which either arrives within a couple of
+    /// frames with sensible values, or there is no sensor. This busy waits until it knows.
+    ///
+    /// # Safety
+    ///
+    /// Taking a sample writes to `0x0E00_8000` and `0x0E00_8100` in the save memory area. On a
+    /// cartridge with SRAM these are bytes `0x0000` and `0x0100` of the save, which would be
+    /// overwritten, and whatever is saved there could be mistaken for a sensor. Flash chips
+    /// could also take the writes as part of a command. Only call this if the cartridge has no
+    /// SRAM or flash, such as in a game which saves to EEPROM or doesn't save at all.
+    #[must_use]
+    pub unsafe fn new() -> Option<Self> {
+        start_sample();
+
+        let mut timeout = Timeout::frames(PROBE_TIMEOUT_FRAMES);
+        while X_HIGH.get() & READY == 0 {
+            if timeout.expired() {
+                return None;
+            }
+        }
+
+        // only the bottom 4 bits of the top bytes are used, so anything else isn't a sensor
+        if X_HIGH.get() & 0x70 != 0 || Y_HIGH.get() & 0xf0 != 0 {
+            return None;
+        }
+
+        let last_raw = read_raw();
+        start_sample();
+
+        Some(Self {
+            neutral: Vector2D::new(DEFAULT_NEUTRAL, DEFAULT_NEUTRAL),
+            range: DEFAULT_RANGE,
+            last_raw,
+        })
+    }
+
+    /// If the last sample is ready, returns it relative to the neutral position and starts the
+    /// next one. Returns `None` if the sensor is still busy, which it won't be if this is called
+    /// once a frame.
+    pub fn sample(&mut self) -> Option<Vector2D<i16>> {
+        if X_HIGH.get() & READY == 0 {
+            return None;
+        }
+
+        self.last_raw = read_raw();
+        start_sample();
+
+        Some(self.last_raw - self.neutral)
+    }
+
+    /// Makes the position of the last sample the neutral position, so that holding the GBA like
+    /// that reads as 0 on both axes.
+    pub fn capture_neutral(&mut self) {
+        self.neutral = self.last_raw;
+    }
+
+    /// Sets how far a sample has to be from neutral to count as fully tilted in
+    /// [`normalise`](TiltSensor::normalise). This is `0xe0` unless set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` isn't positive.
+    pub fn set_range(&mut self, range: i16) {
+        assert!(range > 0, "the range must be positive");
+        self.range = range;
+    }
+
+    /// Scales a sample from [`sample`](TiltSensor::sample) to between -1 and 1 on each axis,
+    /// clamping anything tilted further than the [range](TiltSensor::set_range).
+    #[must_use]
+    pub fn normalise(&self, sample: Vector2D<i16>) -> Vector2D<Num<i32, 8>> {
+        let range = i32::from(self.range);
+        let scale = |value: i16| {
+            let value = i32::from(value).clamp(-range, range);
+            Num::new(value) / range
+        };
+
+        Vector2D::new(scale(sample.x), scale(sample.y))
+    }
+}
+
+fn start_sample() {
+    START_1.set(0x55);
+    START_2.set(0xaa);
+}
+
+fn read_raw() -> Vector2D<i16> {
+    let x = (i16::from(X_HIGH.get() & 0x0f) << 8) | i16::from(X_LOW.get());
+    let y = (i16::from(Y_HIGH.get() & 0x0f) << 8) | i16::from(Y_LOW.get());
+
+    Vector2D::new(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixnum::num;
+
+    use super::*;
+
+    #[test_case]
+    fn normalises_samples(_gba: &mut crate::Gba) {
+        let mut tilt = TiltSensor {
+            neutral: Vector2D::new(DEFAULT_NEUTRAL, DEFAULT_NEUTRAL),
+            range: DEFAULT_RANGE,
+            last_raw: Vector2D::new(0x3b0, 0x390),
+        };
+        tilt.set_range(0x40);
+
+        assert_eq!(
+            tilt.normalise(Vector2D::new(0x20, -0x100)),
+            Vector2D::new(num!(0.5), num!(-1.0))
+        );
+
+        tilt.capture_neutral();
+        assert_eq!(tilt.neutral, Vector2D::new(0x3b0, 0x390));
+    }
+}
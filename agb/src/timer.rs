@@ -1,6 +1,12 @@
-use core::marker::PhantomData;
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    marker::PhantomData,
+};
 
-use crate::memory_mapped::MemoryMapped;
+use critical_section::Mutex;
+
+use crate::{interrupt::interrupt_free, memory_mapped::MemoryMapped};
 
 const fn timer_data(timer: usize) -> MemoryMapped<u16> {
     unsafe { MemoryMapped::new(0x0400_0100 + 4 * timer) }
@@ -33,13 +39,59 @@ impl Divider {
             Divider1024 => 3,
         }
     }
+
+    fn cycles_per_tick(self) -> u64 {
+        use Divider::*;
+
+        match self {
+            Divider1 => 1,
+            Divider64 => 64,
+            Divider256 => 256,
+            Divider1024 => 1024,
+        }
+    }
+}
+
+/// Which timers are in use, and by what.
+static CLAIMS: Mutex<RefCell<[Option<&'static str>; 4]>> = Mutex::new(RefCell::new([None; 4]));
+
+/// The error given when trying to claim a timer which is already in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerInUse {
+    /// The timer which couldn't be claimed.
+    pub timer: u16,
+    /// The name given by whatever claimed the timer first.
+    pub claimed_by: &'static str,
+}
+
+impl Display for TimerInUse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "timer {} already claimed by {}",
+            self.timer, self.claimed_by
+        )
+    }
 }
 
+/// One of the GBA's 4 timers. Only one `Timer` exists for each timer at a time, and it is
+/// stopped, with its interrupt turned off, and released for something else to claim when
+/// dropped.
 #[non_exhaustive]
 pub struct Timer {
     timer_number: u16,
 }
 
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.control_register().set(0);
+
+        interrupt_free(|token| {
+            CLAIMS.borrow_ref_mut(token.critical_section())[self.timer_number()] = None;
+        });
+    }
+}
+
 #[non_exhaustive]
 pub struct Timers<'gba> {
     pub timer2: Timer,
@@ -49,23 +101,58 @@ pub struct Timers<'gba> {
 }
 
 impl Timers<'_> {
-    pub(crate) unsafe fn new() -> Self {
+    fn new() -> Self {
         Self {
-            timer2: Timer::new(2),
-            timer3: Timer::new(3),
+            timer2: Timer::claim_or_panic(2, "gba.timers.timers()"),
+            timer3: Timer::claim_or_panic(3, "gba.timers.timers()"),
 
             phantom: PhantomData,
         }
     }
+
+    /// The timers which haven't been claimed, so code which can use any timer can pick one
+    /// which is free.
+    pub fn available() -> impl Iterator<Item = u16> {
+        let claims = interrupt_free(|token| *CLAIMS.borrow_ref(token.critical_section()));
+
+        (0..4).filter(move |&timer| claims[usize::from(timer)].is_none())
+    }
 }
 
 impl Timer {
-    pub(crate) unsafe fn new(timer_number: u16) -> Self {
+    /// Claims timer `timer_number` for `name`, stopping it and resetting its count.
+    pub(crate) fn claim(timer_number: u16, name: &'static str) -> Result<Self, TimerInUse> {
+        assert!(timer_number < 4, "there are only 4 timers");
+
+        interrupt_free(|token| {
+            let mut claims = CLAIMS.borrow_ref_mut(token.critical_section());
+
+            match claims[usize::from(timer_number)] {
+                Some(claimed_by) => Err(TimerInUse {
+                    timer: timer_number,
+                    claimed_by,
+                }),
+                None => {
+                    claims[usize::from(timer_number)] = Some(name);
+                    Ok(())
+                }
+            }
+        })?;
+
         let new_timer = Self { timer_number };
         new_timer.data_register().set(0);
         new_timer.control_register().set(0);
 
-        new_timer
+        Ok(new_timer)
+    }
+
+    /// Claims timer `timer_number` for `name`, panicking with a message naming both claimants
+    /// if it is already in use.
+    pub(crate) fn claim_or_panic(timer_number: u16, name: &'static str) -> Self {
+        match Self::claim(timer_number, name) {
+            Ok(timer) => timer,
+            Err(error) => panic!("{name} couldn't claim timer {timer_number}: {error}"),
+        }
     }
 
     pub fn set_overflow_amount(&mut self, n: u16) -> &mut Self {
@@ -135,7 +222,260 @@ impl TimerController {
         Self {}
     }
 
+    /// Claims timers 2 and 3, which nothing in agb uses by itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either timer has already been claimed, for example by a previous call to this
+    /// whose timers haven't been dropped.
     pub fn timers(&mut self) -> Timers<'_> {
-        unsafe { Timers::new() }
+        Timers::new()
+    }
+
+    /// Claims any one of the 4 timers, recording `name` as its user so that anything else
+    /// trying to claim it can say what has it.
+    ///
+    /// Timers 0 and 1 are used by the [mixer](crate::sound::mixer) while it exists, so are
+    /// only free if you aren't using it. See [`Timers::available`] for which timers are free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timer` is 4 or more.
+    pub fn claim(&mut self, timer: u16, name: &'static str) -> Result<Timer, TimerInUse> {
+        Timer::claim(timer, name)
+    }
+}
+
+const CYCLES_PER_SECOND: u64 = 1 << 24;
+const CYCLES_PER_FRAME: u64 = 280_896;
+
+/// A 64 bit tick count made from two timers chained together.
+///
+/// The lower timer counts at the rate given by its [`Divider`] and the upper one counts each time
+/// the lower one overflows, giving 32 bits in hardware. The top 32 bits are kept in software by
+/// noticing when the hardware count wraps around, so [`now`](MonotonicClock::now) must be
+/// called at least once every 2<sup>32</sup> ticks. That is over four minutes with
+/// [`Divider::Divider1`] and three days with [`Divider::Divider1024`].
+///
+/// ```rust,no_run
+/// # #![no_std]
+/// # #![no_main]
+/// # fn foo(gba: &mut agb::Gba) {
+/// use agb::timer::{Divider, MonotonicClock};
+///
+/// let timers = gba.timers.timers();
+/// let clock = MonotonicClock::new(timers.timer2, timers.timer3, Divider::Divider64);
+///
+/// let stopwatch = clock.stopwatch();
+/// // do something slow
+/// agb::println!("that took {}us", stopwatch.elapsed_micros());
+/// # }
+/// ```
+pub struct MonotonicClock {
+    low: Timer,
+    high: Timer,
+    divider: Divider,
+    last_count: Cell<u32>,
+    wraps: Cell<u32>,
+}
+
+impl MonotonicClock {
+    /// Creates a clock from two adjacent timers, starting it at zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `high` isn't the timer straight after `low`, since only adjacent timers can
+    /// be cascaded.
+    #[must_use]
+    pub fn new(mut low: Timer, mut high: Timer, divider: Divider) -> Self {
+        assert_eq!(
+            high.timer_number,
+            low.timer_number + 1,
+            "the high timer must be the one after the low timer"
+        );
+
+        low.set_enabled(false);
+        high.set_enabled(false);
+
+        high.set_overflow_amount(0)
+            .set_cascade(true)
+            .set_interrupt(false);
+        low.set_overflow_amount(0)
+            .set_divider(divider)
+            .set_cascade(false)
+            .set_interrupt(false);
+
+        high.set_enabled(true);
+        low.set_enabled(true);
+
+        Self {
+            low,
+            high,
+            divider,
+            last_count: Cell::new(0),
+            wraps: Cell::new(0),
+        }
+    }
+
+    fn hardware_count(&self) -> u32 {
+        // The low timer could overflow between reading the two halves, so read the high half
+        // either side of the low half and read the low half again if a carry happened.
+        let high = self.high.value();
+        let low = self.low.value();
+        let high_again = self.high.value();
+
+        if high == high_again {
+            (u32::from(high) << 16) | u32::from(low)
+        } else {
+            (u32::from(high_again) << 16) | u32::from(self.low.value())
+        }
+    }
+
+    /// The number of ticks since the clock was created.
+    #[must_use]
+    pub fn now(&self) -> u64 {
+        let count = self.hardware_count();
+
+        if count < self.last_count.get() {
+            self.wraps.set(self.wraps.get() + 1);
+        }
+        self.last_count.set(count);
+
+        (u64::from(self.wraps.get()) << 32) | u64::from(count)
+    }
+
+    /// Converts a number of ticks of this clock into CPU cycles.
+    #[must_use]
+    pub fn ticks_to_cycles(&self, ticks: u64) -> u64 {
+        ticks * self.divider.cycles_per_tick()
+    }
+
+    /// Converts a number of ticks of this clock into microseconds.
+    #[must_use]
+    pub fn ticks_to_micros(&self, ticks: u64) -> u64 {
+        let cycles = self.ticks_to_cycles(ticks);
+
+        // split off the whole seconds so that multiplying by a million can't overflow
+        let seconds = cycles / CYCLES_PER_SECOND;
+        let remainder = cycles % CYCLES_PER_SECOND;
+        seconds * 1_000_000 + remainder * 1_000_000 / CYCLES_PER_SECOND
+    }
+
+    /// Converts a number of ticks of this clock into whole frames at roughly 59.73 frames per
+    /// second.
+    #[must_use]
+    pub fn ticks_to_frames(&self, ticks: u64) -> u64 {
+        self.ticks_to_cycles(ticks) / CYCLES_PER_FRAME
+    }
+
+    /// Starts a [`Stopwatch`] for timing a section of code.
+    #[must_use]
+    pub fn stopwatch(&self) -> Stopwatch<'_> {
+        Stopwatch {
+            clock: self,
+            start: self.now(),
+        }
+    }
+
+    /// Stops the clock and gives back the timers it was made from.
+    #[must_use]
+    pub fn into_timers(mut self) -> (Timer, Timer) {
+        self.low.set_enabled(false);
+        self.high.set_enabled(false).set_cascade(false);
+
+        (self.low, self.high)
+    }
+}
+
+/// Measures the time since it was started, created using [`MonotonicClock::stopwatch`].
+pub struct Stopwatch<'a> {
+    clock: &'a MonotonicClock,
+    start: u64,
+}
+
+impl Stopwatch<'_> {
+    /// The number of clock ticks since the stopwatch was started.
+    #[must_use]
+    pub fn elapsed_ticks(&self) -> u64 {
+        self.clock.now() - self.start
+    }
+
+    /// The number of microseconds since the stopwatch was started.
+    #[must_use]
+    pub fn elapsed_micros(&self) -> u64 {
+        self.clock.ticks_to_micros(self.elapsed_ticks())
+    }
+
+    /// Starts the stopwatch again from now, returning the ticks elapsed before restarting.
+    pub fn restart(&mut self) -> u64 {
+        let now = self.clock.now();
+        let elapsed = now - self.start;
+        self.start = now;
+
+        elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn monotonic_clock_counts_up(gba: &mut crate::Gba) {
+        let timers = gba.timers.timers();
+        let clock = MonotonicClock::new(timers.timer2, timers.timer3, Divider::Divider1);
+
+        let mut stopwatch = clock.stopwatch();
+        let mut previous = clock.now();
+
+        // long enough for the low timer to overflow several times
+        for _ in 0..10_000 {
+            let now = clock.now();
+            assert!(
+                now >= previous,
+                "clock went backwards from {previous} to {now}"
+            );
+            previous = now;
+        }
+
+        assert!(previous > 0x1_0000, "the high timer should have counted");
+        assert!(stopwatch.restart() > 0);
+        assert!(stopwatch.elapsed_ticks() < 0x1_0000);
+
+        assert_eq!(clock.ticks_to_micros(1 << 24), 1_000_000);
+        // a couple of years of cycles, which would overflow if multiplied by a million first
+        assert_eq!(clock.ticks_to_micros(1 << 50), 1_000_000 << 26);
+        assert_eq!(clock.ticks_to_frames(CYCLES_PER_FRAME * 3), 3);
+
+        let (_timer2, _timer3) = clock.into_timers();
+    }
+
+    #[test_case]
+    fn timers_can_only_be_claimed_once(gba: &mut crate::Gba) {
+        let timer = gba.timers.claim(3, "first").unwrap();
+        assert!(!Timers::available().any(|timer| timer == 3));
+
+        let error = gba.timers.claim(3, "second").err().unwrap();
+        assert_eq!(
+            alloc::format!("{error}"),
+            "timer 3 already claimed by first"
+        );
+
+        drop(timer);
+        assert!(Timers::available().any(|timer| timer == 3));
+        assert!(gba.timers.claim(3, "second").is_ok());
+    }
+
+    #[test_case]
+    fn dropping_a_timer_stops_it(gba: &mut crate::Gba) {
+        let mut timer = gba.timers.claim(2, "test").unwrap();
+        timer
+            .set_divider(Divider::Divider64)
+            .set_interrupt(true)
+            .set_enabled(true);
+        assert_ne!(timer_control(2).get(), 0);
+
+        drop(timer);
+        assert_eq!(timer_control(2).get(), 0);
     }
 }
@@ -0,0 +1,167 @@
+//! Catching frames which never finish, for use during development.
+//!
+//! A deadlock or an infinite loop normally just freezes the game with no indication of what
+//! happened. With a [`Watchdog`] running, a timer interrupt checks that [`frame_alive`] keeps
+//! being called, and panics if it hasn't been for too many frames. That goes through the usual
+//! panic handling, so mgba logs the message and real hardware shows the panic screen.
+//!
+//! The watchdog only runs in builds with debug assertions enabled. In release builds
+//! [`Watchdog::new`] leaves the timer stopped and everything else in this module does nothing.
+//!
+//! It can't catch a hang while interrupts are disabled, such as an infinite loop inside
+//! [`interrupt_free`](crate::interrupt::interrupt_free), since the timer interrupt can't run
+//! either.
+//!
+//! Some things legitimately take a long time, such as erasing save data or generating a level.
+//! Hold the guard returned by [`suspend`] while doing them.
+//!
+//! ```rust,no_run
+//! # #![no_std]
+//! # #![no_main]
+//! # fn foo(gba: &mut agb::Gba) {
+//! use agb::watchdog::{self, Watchdog};
+//!
+//! let timers = gba.timers.timers();
+//! let _watchdog = Watchdog::new(timers.timer2, 30);
+//!
+//! let vblank = agb::interrupt::VBlank::get();
+//! loop {
+//!     // update and render the frame
+//!     watchdog::frame_alive();
+//!     vblank.wait_for_vblank();
+//! }
+//! # }
+//! ```
+
+use portable_atomic::{AtomicU32, Ordering};
+
+use crate::{
+    interrupt::{add_interrupt_handler, InterruptHandler},
+    timer::{Divider, Timer},
+};
+
+/// The number of ticks of a timer using [`Divider::Divider1024`] in one frame.
+const TICKS_PER_FRAME: u16 = (280_896 / 1024) as u16;
+
+static FRAMES_SINCE_ALIVE: AtomicU32 = AtomicU32::new(0);
+static COMPLETED_FRAMES: AtomicU32 = AtomicU32::new(0);
+static SUSPENDED: AtomicU32 = AtomicU32::new(0);
+
+/// A timer which panics if [`frame_alive`] isn't called often enough, see the
+/// [module level documentation](self).
+pub struct Watchdog {
+    timer: Timer,
+    _handler: Option<InterruptHandler>,
+}
+
+impl Watchdog {
+    /// Starts the watchdog using `timer`, panicking if [`frame_alive`] isn't called for
+    /// `max_frames` frames in a row.
+    #[must_use]
+    pub fn new(mut timer: Timer, max_frames: u32) -> Self {
+        FRAMES_SINCE_ALIVE.store(0, Ordering::SeqCst);
+
+        if !cfg!(debug_assertions) {
+            return Self {
+                timer,
+                _handler: None,
+            };
+        }
+
+        // Safety: the handler doesn't allocate before panicking
+        let handler = unsafe {
+            add_interrupt_handler(timer.interrupt(), move |_| {
+                if SUSPENDED.load(Ordering::SeqCst) > 0 {
+                    return;
+                }
+
+                let frames = FRAMES_SINCE_ALIVE.fetch_add(1, Ordering::SeqCst) + 1;
+                if frames > max_frames {
+                    panic!(
+                        "watchdog: frame {}+{} never completed",
+                        COMPLETED_FRAMES.load(Ordering::SeqCst),
+                        frames
+                    );
+                }
+            })
+        };
+
+        timer
+            .set_divider(Divider::Divider1024)
+            .set_overflow_amount(TICKS_PER_FRAME)
+            .set_cascade(false)
+            .set_interrupt(true)
+            .set_enabled(true);
+
+        Self {
+            timer,
+            _handler: Some(handler),
+        }
+    }
+
+    /// Stops the watchdog and gives back its timer.
+    #[must_use]
+    pub fn into_timer(mut self) -> Timer {
+        self.timer.set_enabled(false).set_interrupt(false);
+        self.timer
+    }
+}
+
+/// Tells the watchdog that a frame has completed. Call this once per frame in your main loop.
+pub fn frame_alive() {
+    FRAMES_SINCE_ALIVE.store(0, Ordering::SeqCst);
+    COMPLETED_FRAMES.add(1, Ordering::SeqCst);
+}
+
+/// How many times [`frame_alive`] has been called, which stops going up if the game hangs.
+pub(crate) fn completed_frames() -> u32 {
+    COMPLETED_FRAMES.load(Ordering::SeqCst)
+}
+
+/// Stops the watchdog from firing until the returned guard is dropped.
+///
+/// Guards can be nested, and the watchdog resumes once they have all been dropped. When it
+/// resumes it gives a full `max_frames` before firing, however long it was suspended for.
+#[must_use]
+pub fn suspend() -> SuspendGuard {
+    SUSPENDED.add(1, Ordering::SeqCst);
+    SuspendGuard { _private: () }
+}
+
+/// Keeps the watchdog from firing while it is alive, created by [`suspend`].
+pub struct SuspendGuard {
+    _private: (),
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        FRAMES_SINCE_ALIVE.store(0, Ordering::SeqCst);
+        SUSPENDED.sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn suspended_watchdog_does_not_fire(gba: &mut crate::Gba) {
+        let timers = gba.timers.timers();
+        let watchdog = Watchdog::new(timers.timer2, 2);
+
+        let vblank = crate::interrupt::VBlank::get();
+        for _ in 0..4 {
+            frame_alive();
+            vblank.wait_for_vblank();
+        }
+
+        {
+            let _suspended = suspend();
+            for _ in 0..5 {
+                vblank.wait_for_vblank();
+            }
+        }
+
+        let _timer = watchdog.into_timer();
+    }
+}
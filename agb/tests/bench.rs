@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(agb::test_runner::test_runner)]
+
+extern crate alloc;
+
+use alloc::vec;
+use core::hint::black_box;
+
+use agb::{
+    hash_map::HashMap,
+    include_wav,
+    interrupt::VBlank,
+    sound::mixer::{Frequency, SoundChannel},
+    test_runner::Bencher,
+    Gba,
+};
+
+static DEAD_CODE: &[u8] = include_wav!("examples/JoshWoodward-DeadCode.wav");
+
+#[agb::bench]
+fn memcpy_aligned_4k(b: &mut Bencher) {
+    let source = vec![0x55u32; 1024];
+    let mut destination = vec![0u32; 1024];
+
+    b.iter(|| destination.copy_from_slice(black_box(&source)));
+}
+
+#[agb::bench]
+fn memcpy_unaligned_4k(b: &mut Bencher) {
+    let source = vec![0x55u8; 4097];
+    let mut destination = vec![0u8; 4097];
+
+    b.iter(|| destination[..4096].copy_from_slice(black_box(&source[1..])));
+}
+
+#[agb::bench]
+fn hash_map_insert_1000(b: &mut Bencher) {
+    b.iter(|| {
+        let mut map = HashMap::new();
+        for i in 0..1000u32 {
+            map.insert(i, i);
+        }
+        map
+    });
+}
+
+#[agb::bench]
+fn hash_map_get_1000(b: &mut Bencher) {
+    let map: HashMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+
+    b.iter(|| {
+        (0..1000)
+            .filter_map(|i| map.get(black_box(&i)))
+            .sum::<u32>()
+    });
+}
+
+#[agb::bench]
+fn mixer_frame_8_channels(gba: &mut Gba, b: &mut Bencher) {
+    let mut mixer = gba.mixer.mixer(Frequency::Hz10512);
+    mixer.enable();
+
+    for _ in 0..8 {
+        let mut channel = SoundChannel::new(DEAD_CODE);
+        channel.should_loop();
+        mixer.play_sound(channel).unwrap();
+    }
+
+    // the mixer only has a new buffer to fill once per frame
+    let vblank = VBlank::get();
+    b.set_iterations(15);
+    b.iter_with_setup(|| vblank.wait_for_vblank(), |()| mixer.frame());
+}
+
+#[agb::entry]
+fn entry(_gba: Gba) -> ! {
+    loop {}
+}
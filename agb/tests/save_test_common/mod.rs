@@ -1,4 +1,7 @@
-use agb::save::{Error, MediaInfo};
+use agb::save::{
+    read_and_validate, transaction::SaveTransaction, write_header, Error, HeaderError, ListError,
+    MediaInfo, SavedList,
+};
 use core::cmp;
 use once_cell::sync::OnceCell;
 
@@ -79,7 +82,8 @@ fn do_test(
     Ok(())
 }
 
-#[test_case]
+// erasing and rewriting the whole of flash takes several seconds
+#[agb::test(timeout_frames = 3600)]
 fn test_4k_blocks(gba: &mut agb::Gba) {
     let info = init_sram(gba);
 
@@ -88,13 +92,13 @@ fn test_4k_blocks(gba: &mut agb::Gba) {
     }
 }
 
-#[test_case]
+#[agb::test(timeout_frames = 3600)]
 fn test_512b_blocks(gba: &mut agb::Gba) {
     let info = init_sram(gba);
     do_test(gba, Rng(1000), 0, info.len(), 512).expect("Test encountered error");
 }
 
-#[test_case]
+#[agb::test(timeout_frames = 3600)]
 fn test_partial_writes(gba: &mut agb::Gba) {
     let info = init_sram(gba);
 
@@ -110,3 +114,112 @@ fn test_partial_writes(gba: &mut agb::Gba) {
             .expect("Test encountered error");
     }
 }
+
+#[test_case]
+fn test_save_header(gba: &mut agb::Gba) {
+    init_sram(gba);
+
+    let timers = gba.timers.timers();
+    let mut access = gba
+        .save
+        .access_with_timer(timers.timer2)
+        .expect("Test encountered error");
+
+    write_header(&mut access, 0, *b"AGBT", 7).expect("Test encountered error");
+
+    assert_eq!(read_and_validate(&mut access, 0, *b"AGBT").unwrap(), 7);
+    assert!(matches!(
+        read_and_validate(&mut access, 0, *b"NOPE"),
+        Err(HeaderError::MagicMismatch)
+    ));
+}
+
+#[test_case]
+fn test_transaction_rollback(gba: &mut agb::Gba) {
+    init_sram(gba);
+
+    let timers = gba.timers.timers();
+    let mut access = gba
+        .save
+        .access_with_timer(timers.timer2)
+        .expect("Test encountered error");
+
+    let original = [0x55; 16];
+    access
+        .prepare_write(0..32)
+        .and_then(|mut prepared| prepared.write(0, &original))
+        .expect("Test encountered error");
+
+    let mut transaction = SaveTransaction::<2>::new();
+    assert!(transaction.push(2, &[1, 2, 3]));
+    assert!(transaction.push(10, &[4, 5]));
+    assert!(
+        !transaction.push(3, &[6]),
+        "overlapping writes should be rejected"
+    );
+
+    transaction
+        .commit(&mut access)
+        .expect("Test encountered error");
+    assert_eq!(transaction.completed_writes(), 2);
+
+    let mut buffer = [0; 16];
+    access.read(0, &mut buffer).expect("Test encountered error");
+    assert_eq!(
+        buffer,
+        [0x55, 0x55, 1, 2, 3, 0x55, 0x55, 0x55, 0x55, 0x55, 4, 5, 0x55, 0x55, 0x55, 0x55]
+    );
+
+    transaction
+        .rollback(&mut access)
+        .expect("Test encountered error");
+    access.read(0, &mut buffer).expect("Test encountered error");
+    assert_eq!(buffer, original);
+}
+
+#[test_case]
+fn test_saved_list(gba: &mut agb::Gba) {
+    init_sram(gba);
+
+    let timers = gba.timers.timers();
+    let mut access = gba
+        .save
+        .access_with_timer(timers.timer2)
+        .expect("Test encountered error");
+
+    let list = SavedList::<u32, 8>::new(100);
+
+    access
+        .prepare_write(0..200)
+        .and_then(|mut prepared| prepared.write(0, &[0x55; 200]))
+        .expect("Test encountered error");
+    assert!(matches!(
+        list.load_all(&mut access),
+        Err(ListError::ChecksumMismatch)
+    ));
+    assert!(matches!(
+        list.save_element(&mut access, 0, 1),
+        Err(ListError::ChecksumMismatch)
+    ));
+
+    list.save_all(&mut access, &[10, 20, 30, 40, 50, 60, 70, 80])
+        .expect("Test encountered error");
+    list.save_element(&mut access, 2, 300)
+        .expect("Test encountered error");
+    list.save_element(&mut access, 7, 800)
+        .expect("Test encountered error");
+    assert!(matches!(
+        list.save_element(&mut access, 8, 0),
+        Err(ListError::IndexOutOfBounds)
+    ));
+
+    assert_eq!(
+        list.load_all(&mut access).unwrap(),
+        [10, 20, 300, 40, 50, 60, 70, 800]
+    );
+
+    // the data around the list is left alone
+    let mut before = [0; 100];
+    access.read(0, &mut before).expect("Test encountered error");
+    assert_eq!(before, [0x55; 100]);
+}
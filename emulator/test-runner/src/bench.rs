@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+
+/// The result of one benchmark, as printed by `agb::test_runner::Bench`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub name: String,
+    pub median_cycles: u64,
+    pub iterations: u64,
+}
+
+impl BenchResult {
+    /// Parses a line of the form `bench:<name> median=<cycles> iterations=<count>`, returning
+    /// `None` for anything which isn't a benchmark result.
+    pub fn parse(message: &str) -> Option<Self> {
+        let mut parts = message.strip_prefix("bench:")?.split_whitespace();
+
+        let name = parts.next()?.to_string();
+        let median_cycles = parts.next()?.strip_prefix("median=")?.parse().ok()?;
+        let iterations = parts.next()?.strip_prefix("iterations=")?.parse().ok()?;
+
+        Some(Self {
+            name,
+            median_cycles,
+            iterations,
+        })
+    }
+}
+
+/// How a benchmark result compares with the baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// The baseline doesn't have this benchmark.
+    New,
+    /// Within the tolerance of the baseline, or faster.
+    Ok { baseline: u64, change_percent: f64 },
+    /// Slower than the baseline by more than the tolerance.
+    Regressed { baseline: u64, change_percent: f64 },
+}
+
+/// The expected median cycles for each benchmark, stored one per line as `<name> <cycles>`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Default)]
+pub struct Baseline {
+    medians: BTreeMap<String, u64>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| anyhow!("Could not read benchmark baseline {}", path.display()))?;
+
+        Self::parse(&contents)
+            .with_context(|| anyhow!("Invalid benchmark baseline {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut medians = BTreeMap::new();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, cycles) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("line {}: expected '<name> <cycles>'", line_number + 1))?;
+            let cycles = cycles
+                .trim()
+                .parse()
+                .with_context(|| anyhow!("line {}: invalid cycle count", line_number + 1))?;
+
+            medians.insert(name.to_string(), cycles);
+        }
+
+        Ok(Self { medians })
+    }
+
+    pub fn compare(&self, result: &BenchResult, tolerance_percent: f64) -> Comparison {
+        let Some(&baseline) = self.medians.get(&result.name) else {
+            return Comparison::New;
+        };
+
+        let change_percent = if baseline == 0 {
+            if result.median_cycles == 0 {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            (result.median_cycles as f64 - baseline as f64) / baseline as f64 * 100.0
+        };
+
+        if change_percent > tolerance_percent {
+            Comparison::Regressed {
+                baseline,
+                change_percent,
+            }
+        } else {
+            Comparison::Ok {
+                baseline,
+                change_percent,
+            }
+        }
+    }
+
+    /// Replaces the baseline for each of `results`, keeping the other benchmarks.
+    pub fn update(&mut self, results: &[BenchResult]) {
+        for result in results {
+            self.medians
+                .insert(result.name.clone(), result.median_cycles);
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut contents = String::from(
+            "# Median cycles for each benchmark, checked by mgba-test-runner --bench-baseline\n",
+        );
+        for (name, cycles) in &self.medians {
+            writeln!(contents, "{name} {cycles}")?;
+        }
+
+        fs::write(path, contents)
+            .with_context(|| anyhow!("Could not write benchmark baseline {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_results_against_the_baseline() {
+        let result = BenchResult::parse("bench:bench::hash_map_get median=1150 iterations=31")
+            .expect("should parse");
+        assert_eq!(result.name, "bench::hash_map_get");
+        assert_eq!(result.iterations, 31);
+        assert_eq!(BenchResult::parse("benchmarks are great"), None);
+
+        let mut baseline =
+            Baseline::parse("# comment\n\nbench::hash_map_get 1000\nbench::memcpy 200\n").unwrap();
+
+        assert!(matches!(
+            baseline.compare(&result, 10.0),
+            Comparison::Regressed { baseline: 1000, .. }
+        ));
+        assert!(matches!(
+            baseline.compare(&result, 20.0),
+            Comparison::Ok { baseline: 1000, .. }
+        ));
+
+        baseline.update(std::slice::from_ref(&result));
+        assert!(matches!(
+            baseline.compare(&result, 0.0),
+            Comparison::Ok { .. }
+        ));
+
+        let new = BenchResult {
+            name: "bench::mixer".to_string(),
+            ..result
+        };
+        assert_eq!(baseline.compare(&new, 10.0), Comparison::New);
+
+        assert!(Baseline::parse("bench::memcpy lots").is_err());
+    }
+}
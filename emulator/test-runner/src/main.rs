@@ -5,14 +5,21 @@ use std::{
     io::Read,
     path::{Path, PathBuf},
     sync::Mutex,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context};
+use bench::{Baseline, BenchResult, Comparison};
 use clap::Parser;
 use image_compare::compare_image;
+use memory_usage::MemoryUsage;
 use mgba::{LogLevel, Logger, MCore, MemoryBacked, VFile};
+use test_options::TestOptions;
 
+mod bench;
 mod image_compare;
+mod memory_usage;
+mod test_options;
 
 static LOGGER: Logger = Logger::new(my_logger);
 
@@ -28,26 +35,152 @@ fn my_logger(category: &str, level: LogLevel, s: String) {
 #[derive(Parser)]
 struct CliArguments {
     rom: PathBuf,
+
+    /// Only run the tests with this in their names.
+    filter: Option<String>,
+
+    /// Run ignored tests as well as the others.
+    #[arg(long)]
+    include_ignored: bool,
+
+    /// Only run ignored tests.
+    #[arg(long, conflicts_with = "include_ignored")]
+    ignored: bool,
+
+    /// File of median cycle counts to compare benchmarks against. Benchmarks which are slower
+    /// than their baseline by more than the tolerance fail the run.
+    #[arg(long, env = "AGB_BENCH_BASELINE")]
+    bench_baseline: Option<PathBuf>,
+
+    /// How many percent slower than the baseline a benchmark can be before it fails.
+    #[arg(long, env = "AGB_BENCH_TOLERANCE", default_value_t = 10.0)]
+    bench_tolerance: f64,
+
+    /// Fail benchmarks which aren't in the baseline, rather than just reporting them as new.
+    #[arg(long, env = "AGB_REQUIRE_BENCH_BASELINE", requires = "bench_baseline")]
+    require_bench_baseline: bool,
+
+    /// Write the benchmark results to the baseline file instead of comparing against it.
+    /// Benchmarks which didn't run keep their old values.
+    #[arg(long, env = "AGB_UPDATE_BENCH_BASELINE", requires = "bench_baseline")]
+    update_bench_baseline: bool,
+
+    /// How many seconds of real time a test can take before the whole run is stopped. Tests
+    /// which set a longer timeout in frames with `#[agb::test]` get at least that long. This
+    /// catches tests which the watchdog in the ROM can't stop, such as ones which hang with
+    /// interrupts disabled.
+    #[arg(long, env = "AGB_TEST_TIMEOUT", default_value_t = 60)]
+    test_timeout: u64,
+}
+
+struct BenchOptions {
+    baseline_path: Option<PathBuf>,
+    baseline: Baseline,
+    tolerance_percent: f64,
+    require_baseline: bool,
+    update: bool,
+}
+
+impl BenchOptions {
+    fn new(args: &CliArguments) -> anyhow::Result<Self> {
+        let baseline = match &args.bench_baseline {
+            // a baseline being written for the first time doesn't need to exist yet
+            Some(path) if !args.update_bench_baseline || path.exists() => Baseline::load(path)?,
+            _ => Baseline::default(),
+        };
+
+        Ok(Self {
+            baseline_path: args.bench_baseline.clone(),
+            baseline,
+            tolerance_percent: args.bench_tolerance,
+            require_baseline: args.require_bench_baseline,
+            update: args.update_bench_baseline,
+        })
+    }
+
+    /// Describes how `result` compares with the baseline, and whether it fails the run.
+    fn check(&self, result: &BenchResult) -> (String, bool) {
+        let median = format!(
+            "median {} c over {} iterations",
+            result.median_cycles, result.iterations
+        );
+
+        if self.update || self.baseline_path.is_none() {
+            return (median, false);
+        }
+
+        match self.baseline.compare(result, self.tolerance_percent) {
+            Comparison::New => (format!("{median}, not in baseline"), self.require_baseline),
+            Comparison::Ok {
+                baseline,
+                change_percent,
+            } => (
+                format!("{median}, baseline {baseline} c ({change_percent:+.1}%)"),
+                false,
+            ),
+            Comparison::Regressed {
+                baseline,
+                change_percent,
+            } => (
+                format!(
+                    "{median}, baseline {baseline} c ({change_percent:+.1}%, more than {}% slower)",
+                    self.tolerance_percent
+                ),
+                true,
+            ),
+        }
+    }
+
+    fn finish(&mut self, results: &[BenchResult]) -> anyhow::Result<()> {
+        if let (true, Some(path)) = (self.update, &self.baseline_path) {
+            if !results.is_empty() {
+                self.baseline.update(results);
+                self.baseline.save(path)?;
+                eprintln!("Updated benchmark baseline {}", path.display());
+            }
+        }
+
+        Ok(())
+    }
 }
 
 struct TestRunner {
     mgba: MCore,
+    bench: BenchOptions,
+    test_timeout: Duration,
 }
 
+/// The test which is running, and when it has to finish by.
+struct RunningTest {
+    name: String,
+    deadline: Instant,
+}
+
+/// How many emulator steps to run between checking the time.
+const STEPS_BETWEEN_TIMEOUT_CHECKS: u32 = 1024;
+
 enum Timer {
     Start(u64),
     Total(u64),
 }
 
 impl TestRunner {
-    fn new<V: VFile>(rom: V) -> Result<Self, Box<dyn Error>> {
+    fn new<V: VFile>(
+        rom: V,
+        bench: BenchOptions,
+        test_timeout: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
         let mut mgba = MCore::new().ok_or(anyhow!("cannot create core"))?;
 
         mgba::set_global_default_logger(&LOGGER);
 
         mgba.load_rom(rom);
 
-        Ok(Self { mgba })
+        Ok(Self {
+            mgba,
+            bench,
+            test_timeout,
+        })
     }
 
     fn run(mut self) -> Result<(), Box<dyn Error>> {
@@ -55,8 +188,31 @@ impl TestRunner {
 
         let mut mark_tests_as_soft_failed = false;
         let mut mark_this_test_as_soft_failed = false;
+        let mut bench_results = Vec::new();
+        let mut bench_report = None;
+        let mut memory_usage = None;
+        let mut next_test_timeout = self.test_timeout;
+        let mut running_test: Option<RunningTest> = None;
+        let mut steps_until_timeout_check = STEPS_BETWEEN_TIMEOUT_CHECKS;
         loop {
             self.mgba.step();
+
+            steps_until_timeout_check -= 1;
+            if steps_until_timeout_check == 0 {
+                steps_until_timeout_check = STEPS_BETWEEN_TIMEOUT_CHECKS;
+
+                if let Some(test) = &running_test {
+                    if Instant::now() > test.deadline {
+                        eprintln!("[timeout]");
+                        return Err(anyhow!(
+                            "Test {} did not finish in time, and could not be stopped",
+                            test.name
+                        )
+                        .into());
+                    }
+                }
+            }
+
             while let Some((category, level, message)) = LOGGER_BUFFER.lock().unwrap().pop_front() {
                 match (category.as_ref(), level, message.as_ref()) {
                     (_, LogLevel::Fatal, fatal_message) => {
@@ -85,29 +241,80 @@ impl TestRunner {
                                 }
                                 Err(e) => eprintln!("{}", e),
                             }
+                        } else if let Some(usage) = MemoryUsage::parse(debug_message) {
+                            memory_usage = Some(usage);
+                        } else if let Some(result) = BenchResult::parse(debug_message) {
+                            let (report, failed) = self.bench.check(&result);
+                            if failed {
+                                mark_tests_as_soft_failed = true;
+                                mark_this_test_as_soft_failed = true;
+                            }
+
+                            // printed after the test's own result so that stays on one line
+                            bench_report = Some(report);
+                            bench_results.push(result);
+                        } else if let Some(frames) = debug_message.strip_prefix("timeout_frames:") {
+                            let frames: u64 = frames.parse().with_context(|| {
+                                anyhow!("Invalid timeout from the test: {}", debug_message)
+                            })?;
+                            // at least as long as the frames would take on hardware
+                            next_test_timeout = self
+                                .test_timeout
+                                .max(Duration::from_secs(frames.div_ceil(60)));
                         } else if debug_message.ends_with("...") {
                             eprint!("{}", debug_message);
+                            running_test = Some(RunningTest {
+                                name: debug_message.trim_end_matches("...").to_string(),
+                                deadline: Instant::now() + next_test_timeout,
+                            });
+                            next_test_timeout = self.test_timeout;
+                        } else if let Some(timeout) = debug_message
+                            .strip_prefix("[timeout ")
+                            .and_then(|message| message.strip_suffix(']'))
+                        {
+                            // the ROM resets to carry on with the next test, which starts timing
+                            // again
+                            timer = Timer::Total(0);
+                            running_test = None;
+                            bench_report = None;
+                            memory_usage = None;
+                            mark_this_test_as_soft_failed = false;
+                            mark_tests_as_soft_failed = true;
+                            eprintln!("[fail: timeout {timeout}]");
+                        } else if debug_message == "[ignored]" {
+                            running_test = None;
+                            eprintln!("{}", debug_message);
                         } else if debug_message == "[ok]" {
+                            running_test = None;
                             let cycles = match timer {
                                 Timer::Start(_) => panic!("test completed with invalid timing"),
                                 Timer::Total(c) => c,
                             };
+                            let memory = memory_usage
+                                .take()
+                                .map(|usage| format!(", {usage}"))
+                                .unwrap_or_default();
                             if mark_this_test_as_soft_failed {
                                 mark_this_test_as_soft_failed = false;
                                 eprintln!(
-                                    "[fail: {} c ≈ {} s]",
+                                    "[fail: {} c ≈ {} s{}]",
                                     cycles,
                                     ((cycles as f64 / (16.78 * 1_000_000.0)) * 100.0).round()
-                                        / 100.0
+                                        / 100.0,
+                                    memory
                                 );
                             } else {
                                 eprintln!(
-                                    "[ok: {} c ≈ {} s]",
+                                    "[ok: {} c ≈ {} s{}]",
                                     cycles,
                                     ((cycles as f64 / (16.78 * 1_000_000.0)) * 100.0).round()
-                                        / 100.0
+                                        / 100.0,
+                                    memory
                                 );
                             }
+                            if let Some(report) = bench_report.take() {
+                                eprintln!("    {report}");
+                            }
                         } else {
                             eprintln!("{}", debug_message);
                         }
@@ -116,6 +323,8 @@ impl TestRunner {
                 }
 
                 if message == "Tests finished successfully" {
+                    self.bench.finish(&bench_results)?;
+
                     if mark_tests_as_soft_failed {
                         eprintln!("Tests failed");
                         return Err(anyhow!("Tests failed").into());
@@ -131,11 +340,18 @@ impl TestRunner {
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = CliArguments::parse();
+    let bench = BenchOptions::new(&args)?;
 
-    let rom = load_rom(args.rom)?;
+    let mut rom = load_rom(&args.rom)?;
+    TestOptions {
+        filter: args.filter,
+        include_ignored: args.include_ignored,
+        only_ignored: args.ignored,
+    }
+    .write_to_rom(&mut rom)?;
     let rom = MemoryBacked::new(rom);
 
-    TestRunner::new(rom)?.run()?;
+    TestRunner::new(rom, bench, Duration::from_secs(args.test_timeout))?.run()?;
 
     Ok(())
 }
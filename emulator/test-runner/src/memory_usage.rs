@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// The heap and stack used by a test, as printed by `agb::test_runner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub peak_heap_bytes: u64,
+    pub allocations: u64,
+    pub peak_stack_bytes: u64,
+}
+
+impl MemoryUsage {
+    /// Parses a line of the form `mem: heap=<bytes> allocations=<count> stack=<bytes>`,
+    /// returning `None` for anything else.
+    pub fn parse(message: &str) -> Option<Self> {
+        let mut parts = message.strip_prefix("mem:")?.split_whitespace();
+
+        let peak_heap_bytes = parts.next()?.strip_prefix("heap=")?.parse().ok()?;
+        let allocations = parts.next()?.strip_prefix("allocations=")?.parse().ok()?;
+        let peak_stack_bytes = parts.next()?.strip_prefix("stack=")?.parse().ok()?;
+
+        Some(Self {
+            peak_heap_bytes,
+            allocations,
+            peak_stack_bytes,
+        })
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "heap {} B in {} allocs, stack {} B",
+            self.peak_heap_bytes, self.allocations, self.peak_stack_bytes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_memory_usage() {
+        let usage = MemoryUsage::parse("mem: heap=1000 allocations=2 stack=312").unwrap();
+        assert_eq!(
+            usage,
+            MemoryUsage {
+                peak_heap_bytes: 1000,
+                allocations: 2,
+                peak_stack_bytes: 312,
+            }
+        );
+        assert_eq!(usage.to_string(), "heap 1000 B in 2 allocs, stack 312 B");
+
+        assert_eq!(MemoryUsage::parse("memory is fine"), None);
+        assert_eq!(MemoryUsage::parse("mem: heap=lots"), None);
+    }
+}
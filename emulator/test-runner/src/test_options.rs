@@ -0,0 +1,91 @@
+use anyhow::{anyhow, bail};
+
+/// The start of the block in the ROM which `agb::test_runner` reads its options from.
+const MAGIC: &[u8; 16] = b"agb-test-options";
+const LENGTH: usize = 128;
+const FILTER_LENGTH: usize = LENGTH - MAGIC.len() - 1;
+
+const INCLUDE_IGNORED: u8 = 1 << 0;
+const ONLY_IGNORED: u8 = 1 << 1;
+
+/// Which tests the ROM should run.
+#[derive(Debug, Default)]
+pub struct TestOptions {
+    /// Only tests with this in their names run.
+    pub filter: Option<String>,
+    pub include_ignored: bool,
+    pub only_ignored: bool,
+}
+
+impl TestOptions {
+    fn is_default(&self) -> bool {
+        self.filter.as_deref().unwrap_or_default().is_empty()
+            && !self.include_ignored
+            && !self.only_ignored
+    }
+
+    /// Writes the options into the block reserved for them in `rom`.
+    pub fn write_to_rom(&self, rom: &mut [u8]) -> anyhow::Result<()> {
+        if self.is_default() {
+            return Ok(());
+        }
+
+        let filter = self.filter.as_deref().unwrap_or_default().as_bytes();
+        if filter.len() > FILTER_LENGTH {
+            bail!("Test filter is longer than {FILTER_LENGTH} bytes");
+        }
+
+        let start = rom
+            .windows(MAGIC.len())
+            .position(|window| window == MAGIC)
+            .ok_or_else(|| {
+                anyhow!("ROM doesn't support filtering tests, it may use an older version of agb")
+            })?;
+        let options = rom
+            .get_mut(start + MAGIC.len()..start + LENGTH)
+            .ok_or_else(|| anyhow!("Test options block in the ROM is truncated"))?;
+
+        let mut flags = 0;
+        if self.include_ignored {
+            flags |= INCLUDE_IGNORED;
+        }
+        if self.only_ignored {
+            flags |= ONLY_IGNORED;
+        }
+
+        options.fill(0);
+        options[0] = flags;
+        options[1..=filter.len()].copy_from_slice(filter);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_filter_after_the_magic() {
+        let mut rom = vec![0xff; 16];
+        rom.extend_from_slice(MAGIC);
+        rom.resize(rom.len() + LENGTH - MAGIC.len() + 8, 0xaa);
+
+        let options = TestOptions {
+            filter: Some("save::flash".to_string()),
+            include_ignored: true,
+            ..Default::default()
+        };
+        options.write_to_rom(&mut rom).unwrap();
+
+        let block = &rom[16 + MAGIC.len()..16 + LENGTH];
+        assert_eq!(block[0], INCLUDE_IGNORED);
+        assert_eq!(&block[1..12], b"save::flash");
+        assert!(block[12..].iter().all(|&byte| byte == 0));
+        assert_eq!(rom[16 + LENGTH], 0xaa);
+
+        // nothing to write, so a ROM without the block is fine
+        TestOptions::default().write_to_rom(&mut [0; 4]).unwrap();
+        assert!(options.write_to_rom(&mut [0; 4]).is_err());
+    }
+}